@@ -0,0 +1,103 @@
+use std::fmt;
+
+use vectorlab_core::Color;
+
+use crate::canvas::to_egui;
+use crate::cli::{parse_color, DEFAULT_BACKGROUND};
+
+/// Squares of the checkerboard, in screen pixels.
+const CHECKER_SIZE: f32 = 10.0;
+
+/// What the canvas shows behind the document. Many SVGs are made for white paper and
+/// disappear on the dark default, the checkerboard shows what is transparent.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Background {
+    Dark,
+    White,
+    Checkerboard,
+    Custom(Color),
+}
+
+impl Background {
+    /// dark, white, checkerboard, or any color `parse_color` knows.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "dark" => Ok(Self::Dark),
+            "white" => Ok(Self::White),
+            "checkerboard" | "checker" => Ok(Self::Checkerboard),
+            _ => parse_color(s).map(Self::Custom),
+        }
+    }
+
+    /// Solid fill for exported images, none for the checkerboard, which stands for transparent.
+    pub fn color(&self) -> Option<Color> {
+        match self {
+            Self::Dark => Some(DEFAULT_BACKGROUND),
+            Self::White => Some(Color::WHITE),
+            Self::Custom(c) => Some(*c),
+            Self::Checkerboard => None,
+        }
+    }
+
+    pub fn paint(&self, painter: &egui::Painter, rect: egui::Rect) {
+        let color = match self {
+            Self::Dark => to_egui(DEFAULT_BACKGROUND),
+            Self::White => egui::Color32::WHITE,
+            Self::Custom(c) => to_egui(*c),
+            Self::Checkerboard => {
+                painter.rect_filled(rect, 0.0, egui::Color32::from_gray(204));
+                // the dark squares as one mesh, there are thousands of them
+                let mut mesh = egui::Mesh::default();
+                let columns = (rect.width() / CHECKER_SIZE).ceil() as usize;
+                let rows = (rect.height() / CHECKER_SIZE).ceil() as usize;
+                for row in 0..rows {
+                    for column in (row % 2..columns).step_by(2) {
+                        let min = rect.min + egui::vec2(column as f32, row as f32) * CHECKER_SIZE;
+                        let square = egui::Rect::from_min_size(min, egui::Vec2::splat(CHECKER_SIZE)).intersect(rect);
+                        mesh.add_colored_rect(square, egui::Color32::from_gray(153));
+                    }
+                }
+                painter.add(mesh);
+                return;
+            }
+        };
+        painter.rect_filled(rect, 0.0, color);
+    }
+
+    /// Radio buttons for the View menu, true if the choice changed.
+    pub fn menu(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        for (choice, label) in [(Self::Dark, "Dark"), (Self::White, "White"), (Self::Checkerboard, "Checkerboard")] {
+            changed |= ui.radio_value(self, choice, label).changed();
+        }
+        ui.horizontal(|ui| {
+            let color = match self {
+                Self::Custom(c) => *c,
+                _ => Color::GRAY,
+            };
+            let custom = matches!(self, Self::Custom(_));
+            if ui.radio(custom, "Custom").clicked() && !custom {
+                *self = Self::Custom(color);
+                changed = true;
+            }
+            let mut rgb = [color.r, color.g, color.b];
+            if ui.color_edit_button_srgb(&mut rgb).changed() {
+                *self = Self::Custom(Color::rgb(rgb[0], rgb[1], rgb[2]));
+                changed = true;
+            }
+        });
+        changed
+    }
+}
+
+/// The form `parse` reads, for the settings file.
+impl fmt::Display for Background {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Dark => write!(f, "dark"),
+            Self::White => write!(f, "white"),
+            Self::Checkerboard => write!(f, "checkerboard"),
+            Self::Custom(c) => write!(f, "#{:02x}{:02x}{:02x}", c.r, c.g, c.b),
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The SVG files in the directory of `path` (including `path` itself), sorted by name.
+pub fn sibling_svgs(path: &Path) -> Vec<PathBuf> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => svgs_in(dir),
+        _ => svgs_in(Path::new(".")),
+    }
+}
+
+/// The SVG files in `dir`, sorted by name.
+pub fn svgs_in(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else { return vec![] };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && is_svg(p))
+        .collect();
+    files.sort();
+    files
+}
+
+fn is_svg(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("svg") || e.eq_ignore_ascii_case("svgz"))
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+
+use vectorlab_core::usvg::Transform;
+use vectorlab_core::{transform_point, transform_scale, Color, Document, ElementId, ElementKind, FlattenedPath, Paint, PlacedImage, RasterImage, Segment, SpatialIndex, ViewTransform};
+
+pub fn to_egui(c: Color) -> egui::Color32 {
+    egui::Color32::from_rgba_unmultiplied(c.r, c.g, c.b, c.a)
+}
+
+/// egui textures for the `<image>` elements of the open documents, keyed by their pixel buffers.
+#[derive(Default)]
+pub struct ImageCache {
+    textures: HashMap<usize, (Weak<RasterImage>, egui::TextureHandle)>,
+}
+
+impl ImageCache {
+    pub fn texture(&mut self, ctx: &egui::Context, pixels: &Arc<RasterImage>) -> egui::TextureId {
+        let key = Arc::as_ptr(pixels) as usize;
+        if let Some((weak, handle)) = self.textures.get(&key) {
+            // the address may have been reused by a newer image
+            if weak.upgrade().is_some_and(|p| Arc::ptr_eq(&p, pixels)) {
+                return handle.id();
+            }
+        }
+        let size = [pixels.width as usize, pixels.height as usize];
+        let image = egui::ColorImage::from_rgba_premultiplied(size, &pixels.rgba);
+        let handle = ctx.load_texture(format!("image-{:x}", key), image, egui::TextureOptions::LINEAR);
+        let id = handle.id();
+        self.textures.insert(key, (Arc::downgrade(pixels), handle));
+        id
+    }
+
+    /// Free the textures of images whose documents were closed or reloaded.
+    pub fn prune(&mut self) {
+        self.textures.retain(|_, (weak, _)| weak.strong_count() > 0);
+    }
+}
+
+/// Paths smaller than this on screen are drawn as a dot, those below a quarter of it not at all.
+const LOD_PIXELS: f32 = 2.0;
+
+/// Draw the paths and images of `doc` that are inside the painter's clip rect, in paint order.
+/// `index` must be up to date with `doc`.
+pub fn draw_document(painter: &egui::Painter, doc: &Document, index: &SpatialIndex, view: &ViewTransform, origin: egui::Vec2, images: &mut ImageCache) {
+    let clip = painter.clip_rect();
+    let visible = view.doc_bbox([clip.min.x - origin.x, clip.min.y - origin.y, clip.max.x - origin.x, clip.max.y - origin.y]);
+    for entry in index.query(visible) {
+        match &doc.get(entry.id).kind {
+            ElementKind::Path(path) => {
+                let b = entry.bbox;
+                let size = (b[2] - b[0]).max(b[3] - b[1]) * view.zoom;
+                if size >= LOD_PIXELS {
+                    draw_path(painter, path, &entry.transform, entry.opacity, view, origin);
+                } else if size >= LOD_PIXELS / 4.0 {
+                    let center = [(b[0] + b[2]) * 0.5, (b[1] + b[3]) * 0.5];
+                    let pos = egui::Pos2::from(view.to_screen(center)) + origin;
+                    let color = dot_color(path, &entry.transform, center).gamma_multiply(entry.opacity);
+                    painter.rect_filled(egui::Rect::from_center_size(pos, egui::vec2(1.0, 1.0)), 0.0, color);
+                }
+            }
+            ElementKind::Image { image: Some(image), .. } => {
+                let texture = images.texture(painter.ctx(), &image.pixels);
+                draw_image(painter, image, texture, &entry.transform, entry.opacity, view, origin);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Color a path that is only a dot on screen is drawn with, `center` in document coordinates.
+fn dot_color(path: &FlattenedPath, ts: &Transform, center: [f32; 2]) -> egui::Color32 {
+    match path.style.fill.as_ref().or(path.style.stroke.as_ref()) {
+        Some(Paint::Solid(color)) => to_egui(*color),
+        Some(Paint::Gradient(gradient)) => to_egui(gradient.color_at(transform_point(&ts.invert().unwrap_or_default(), center))),
+        None => egui::Color32::TRANSPARENT,
+    }
+}
+
+pub fn draw_image(painter: &egui::Painter, image: &PlacedImage, texture: egui::TextureId, ts: &Transform, opacity: f32, view: &ViewTransform, origin: egui::Vec2) {
+    let [x, y, w, h] = image.rect;
+    let [u0, v0, u1, v1] = image.uv;
+    let color = egui::Color32::WHITE.gamma_multiply(opacity);
+    let mut mesh = egui::Mesh::with_texture(texture);
+    // corners go through the full transform, so rotated and skewed images come out right
+    for (p, uv) in [([x, y], [u0, v0]), ([x + w, y], [u1, v0]), ([x + w, y + h], [u1, v1]), ([x, y + h], [u0, v1])] {
+        let pos = egui::Pos2::from(view.to_screen(transform_point(ts, p))) + origin;
+        mesh.vertices.push(egui::epaint::Vertex { pos, uv: egui::pos2(uv[0], uv[1]), color });
+    }
+    mesh.indices = vec![0, 1, 2, 0, 2, 3];
+    painter.add(egui::Shape::mesh(mesh));
+}
+
+/// `ts` maps the path's coordinates to document coordinates, `opacity` is the accumulated
+/// opacity of the path and its ancestors.
+pub fn draw_path(painter: &egui::Painter, path: &FlattenedPath, ts: &Transform, opacity: f32, view: &ViewTransform, origin: egui::Vec2) {
+    let to_screen = |p: [f32; 2]| egui::Pos2::from(view.to_screen(transform_point(ts, p))) + origin;
+    let mesh = |paint: &Paint, vertices: &[[f32; 2]], indices: &[u32], colors: &[Color]| {
+        let mut mesh = egui::Mesh::default();
+        for (i, &v) in vertices.iter().enumerate() {
+            let color = match paint {
+                Paint::Solid(color) => to_egui(*color),
+                Paint::Gradient(_) => to_egui(colors[i]),
+            };
+            mesh.colored_vertex(to_screen(v), color.gamma_multiply(opacity));
+        }
+        mesh.indices = indices.to_vec();
+        egui::Shape::mesh(mesh)
+    };
+
+    if let Some(fill) = &path.style.fill {
+        painter.add(mesh(fill, &path.fill_vertices, &path.fill_indices, &path.fill_colors));
+    }
+
+    if let Some(paint) = &path.style.stroke {
+        let width = path.style.stroke_width * transform_scale(ts) * view.zoom;
+        if width >= 1.0 || path.clip.is_some() {
+            painter.add(mesh(paint, &path.stroke_vertices, &path.stroke_indices, &path.stroke_colors));
+            return;
+        }
+        // meshes have no anti-aliasing, hairlines would break up into dots
+        let stroke = match paint {
+            Paint::Solid(color) => egui::epaint::PathStroke::new(width, to_egui(*color).gamma_multiply(opacity)),
+            Paint::Gradient(gradient) => {
+                let (gradient, view) = (gradient.clone(), *view);
+                let inverse = ts.invert().unwrap_or_default();
+                egui::epaint::PathStroke::new_uv(width, move |_, pos| {
+                    let doc = view.to_doc([pos.x - origin.x, pos.y - origin.y]);
+                    to_egui(gradient.color_at(transform_point(&inverse, doc))).gamma_multiply(opacity)
+                })
+            }
+        };
+        for contour in &path.contours {
+            let points: Vec<egui::Pos2> = contour.points.iter().map(|&p| to_screen(p)).collect();
+            if contour.closed {
+                painter.add(egui::Shape::closed_line(points, stroke.clone()));
+            } else {
+                painter.add(egui::Shape::line(points, stroke.clone()));
+            }
+        }
+    }
+}
+
+/// Outline the contours of element `id` (all paths below it for groups), e.g. to mark it as selected.
+pub fn draw_outline(painter: &egui::Painter, doc: &Document, id: ElementId, view: &ViewTransform, origin: egui::Vec2, stroke: egui::Stroke) {
+    if let ElementKind::Image { image: Some(image), .. } = &doc.get(id).kind {
+        let ts = doc.abs_transform(id);
+        let [x, y, w, h] = image.rect;
+        let points = [[x, y], [x + w, y], [x + w, y + h], [x, y + h]]
+            .map(|p| egui::Pos2::from(view.to_screen(transform_point(&ts, p))) + origin)
+            .to_vec();
+        painter.add(egui::Shape::closed_line(points, stroke));
+        return;
+    }
+    let Some(path) = doc.get(id).as_path() else {
+        for &child in &doc.get(id).children {
+            draw_outline(painter, doc, child, view, origin, stroke);
+        }
+        return;
+    };
+    let ts = doc.abs_transform(id);
+    for contour in &path.contours {
+        let points: Vec<egui::Pos2> =
+            contour.points.iter().map(|&p| egui::Pos2::from(view.to_screen(transform_point(&ts, p))) + origin).collect();
+        if contour.closed {
+            painter.add(egui::Shape::closed_line(points, stroke));
+        } else {
+            painter.add(egui::Shape::line(points, stroke));
+        }
+    }
+}
+
+/// Draw the outline of `segments` with their curves, placed on the screen by `at`. For
+/// previews of paths that are not in a document yet.
+pub fn draw_segments(painter: &egui::Painter, segments: &[Segment], at: impl Fn([f32; 2]) -> egui::Pos2, stroke: egui::Stroke) {
+    let (mut current, mut start) = (egui::Pos2::ZERO, egui::Pos2::ZERO);
+    for segment in segments {
+        match *segment {
+            Segment::MoveTo(p) => {
+                current = at(p);
+                start = current;
+            }
+            Segment::LineTo(p) | Segment::QuadTo(_, p) => {
+                painter.line_segment([current, at(p)], stroke);
+                current = at(p);
+            }
+            Segment::CubicTo(c1, c2, p) => {
+                let points = [current, at(c1), at(c2), at(p)];
+                painter.add(egui::epaint::CubicBezierShape::from_points_stroke(points, false, egui::Color32::TRANSPARENT, stroke));
+                current = at(p);
+            }
+            Segment::Close => {
+                painter.line_segment([current, start], stroke);
+                current = start;
+            }
+        }
+    }
+}
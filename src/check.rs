@@ -0,0 +1,88 @@
+use vectorlab_core::{Defect, DefectKind, Document, Unit, ViewTransform};
+
+use crate::inspector::element_label;
+use crate::tab::Tab;
+use crate::units::length_value;
+
+const KINDS: [DefectKind; 4] = [DefectKind::SelfIntersection, DefectKind::NearlyClosed, DefectKind::ZeroLength, DefectKind::DuplicatePoint];
+
+/// Defects found in a tab's document, looked for again when it changes.
+pub struct GeometryCheck {
+    tolerance_mm: f32,
+    // document revision the defects were found in, None to look again
+    revision: Option<u64>,
+    defects: Vec<Defect>,
+}
+
+impl Default for GeometryCheck {
+    fn default() -> Self {
+        Self { tolerance_mm: 0.1, revision: None, defects: vec![] }
+    }
+}
+
+impl GeometryCheck {
+    fn update(&mut self, doc: &Document) {
+        if self.revision == Some(doc.revision) {
+            return;
+        }
+        let mm = doc.mm_per_unit();
+        let tolerance = self.tolerance_mm * 2.0 / (mm[0] + mm[1]).max(f32::EPSILON);
+        self.defects = doc.find_defects(tolerance);
+        self.revision = Some(doc.revision);
+    }
+}
+
+/// The defects of the tab's document by kind. Clicking one selects the path and zooms to
+/// where it is.
+pub fn check_panel(ui: &mut egui::Ui, tab: &mut Tab, unit: Unit) {
+    let check = &mut tab.check;
+    ui.horizontal(|ui| {
+        ui.label("Tolerance");
+        let tolerance = length_value(&mut check.tolerance_mm, unit, 0.01, 0.001..=10.0);
+        if ui.add(tolerance).on_hover_text("Ends closer than this count as meant to meet, segments shorter as zero length").changed() {
+            check.revision = None;
+        }
+    });
+    check.update(&tab.doc);
+    ui.separator();
+    if check.defects.is_empty() {
+        ui.label("No problems found");
+        return;
+    }
+
+    let mut clicked = None;
+    egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+        for kind in KINDS {
+            let defects: Vec<&Defect> = check.defects.iter().filter(|d| d.kind == kind).collect();
+            if defects.is_empty() {
+                continue;
+            }
+            egui::CollapsingHeader::new(format!("{} ({})", kind.name(), defects.len())).id_source(kind).default_open(true).show(ui, |ui| {
+                for defect in defects {
+                    let mut label = element_label(tab.doc.get(defect.id));
+                    if defect.count > 1 {
+                        label += &format!("  ×{}", defect.count);
+                    }
+                    if ui.selectable_label(tab.selection == [defect.id], label).clicked() {
+                        clicked = Some(defect.clone());
+                    }
+                }
+            });
+        }
+    });
+    if let Some(defect) = clicked {
+        let mm = tab.doc.mm_per_unit();
+        let r = 10.0 / mm[0].max(mm[1]).max(f32::EPSILON);
+        tab.selection = vec![defect.id];
+        tab.zoom_to = Some([defect.at[0] - r, defect.at[1] - r, defect.at[0] + r, defect.at[1] + r]);
+    }
+}
+
+/// A ring around each defect on the canvas.
+pub fn draw_defects(painter: &egui::Painter, canvas: egui::Rect, view: &ViewTransform, check: &GeometryCheck) {
+    let stroke = egui::Stroke::new(2.0, egui::Color32::from_rgb(230, 40, 160));
+    for defect in &check.defects {
+        let at = egui::Pos2::from(view.to_screen(defect.at)) + canvas.min.to_vec2();
+        painter.circle_stroke(at, 7.0, stroke);
+    }
+}
@@ -0,0 +1,190 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use vectorlab_core::{Color, SimplifyMethod};
+
+use crate::background::Background;
+
+#[derive(Parser, Debug)]
+#[command(name = "vectorlab", version, about = "Playing around with SVG in a GUI")]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// SVG files to open on startup, directories for all SVGs in them, http(s) URLs, or '-'
+    /// to read from stdin
+    pub files: Vec<String>,
+
+    /// Initial zoom factor, 1 = 100%. Without it the drawing is fitted into the window.
+    #[arg(long)]
+    pub zoom: Option<f32>,
+
+    /// Canvas background: dark, black, white, gray, checkerboard or a #rrggbb hex color.
+    /// Without it the one from the settings is used.
+    #[arg(long, value_parser = Background::parse)]
+    pub bg: Option<Background>,
+
+    /// Start with a maximized window
+    #[arg(long)]
+    pub maximized: bool,
+
+    /// Additional directory with fonts for <text>, may be repeated
+    #[arg(long = "font-dir")]
+    pub font_dirs: Vec<PathBuf>,
+
+    /// Start a slideshow of the files, showing each for this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    pub slideshow: Option<f32>,
+
+    /// Seconds slides fade into each other, 0 to cut. Without it the one from the settings
+    /// is used.
+    #[arg(long, value_name = "SECONDS")]
+    pub crossfade: Option<f32>,
+
+    /// List every SVG spec violation with its line and column, not just what could not be shown
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Take commands from "vectorlab ctl", e.g. from an editor or a build script using this
+    /// window as a live preview
+    #[arg(long)]
+    pub listen: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Rasterize an SVG to PNG without opening a window
+    Render {
+        input: PathBuf,
+
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Output width in pixels, the height follows the aspect ratio
+        #[arg(long)]
+        width: Option<u32>,
+
+        /// Output height in pixels, used when no width is given
+        #[arg(long)]
+        height: Option<u32>,
+
+        /// Background color, transparent if omitted
+        #[arg(long, value_parser = parse_color)]
+        bg: Option<Color>,
+
+        /// Additional directory with fonts for <text>, may be repeated
+        #[arg(long = "font-dir")]
+        font_dirs: Vec<PathBuf>,
+    },
+
+    /// Combine SVGs into one PDF, a page for each at the file's own size
+    Pdf {
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Additional directory with fonts for <text>, may be repeated
+        #[arg(long = "font-dir")]
+        font_dirs: Vec<PathBuf>,
+    },
+
+    /// Reduce the points of dense polylines, writing each file as NAME-simplified.svg next
+    /// to it
+    Simplify {
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// How far the paths may move, in mm
+        #[arg(short, long, default_value_t = 0.1)]
+        tolerance: f32,
+
+        /// rdp (Ramer–Douglas–Peucker, keeps corners) or vw (Visvalingam–Whyatt, smoother)
+        #[arg(long, default_value = "rdp", value_parser = parse_simplify_method)]
+        method: SimplifyMethod,
+
+        /// Output file instead, only for a single input
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Run a Rhai script on an SVG, see the Script console for what scripts can do
+    Run {
+        script: PathBuf,
+
+        input: PathBuf,
+
+        /// Where to save the changed drawing, without it the script only prints
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Additional directory with fonts for <text>, may be repeated
+        #[arg(long = "font-dir")]
+        font_dirs: Vec<PathBuf>,
+    },
+
+    /// Control the VectorLab started with --listen
+    Ctl {
+        #[command(subcommand)]
+        command: CtlCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CtlCommand {
+    /// Open a file in a new tab, or switch to its tab if it is open already
+    Open { path: PathBuf },
+
+    /// Load the file of the current tab again, or of the one given
+    Reload { path: Option<PathBuf> },
+
+    /// Zoom the current tab, 1 = 100%
+    Zoom { zoom: f32 },
+
+    /// Fit the drawing into the window
+    Fit,
+
+    /// Save the current tab's drawing as PNG
+    ExportPng {
+        output: PathBuf,
+
+        /// Width in pixels, the height follows the aspect ratio. Without it one pixel per px.
+        #[arg(long)]
+        width: Option<u32>,
+
+        /// Background color, transparent if omitted
+        #[arg(long)]
+        bg: Option<String>,
+    },
+}
+
+/// `--method` of the simplify subcommand.
+pub fn parse_simplify_method(s: &str) -> Result<SimplifyMethod, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "rdp" | "douglas-peucker" => Ok(SimplifyMethod::DouglasPeucker),
+        "vw" | "visvalingam" => Ok(SimplifyMethod::Visvalingam),
+        _ => Err(format!("unknown method '{}', expected rdp or vw", s)),
+    }
+}
+
+pub fn parse_color(s: &str) -> Result<Color, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "dark" => Ok(DEFAULT_BACKGROUND),
+        "black" => Ok(Color::BLACK),
+        "white" => Ok(Color::WHITE),
+        "gray" | "grey" => Ok(Color::GRAY),
+        other => {
+            let hex = other.strip_prefix('#').unwrap_or(other);
+            let channel = |i: usize| hex.get(i..i + 2).and_then(|c| u8::from_str_radix(c, 16).ok());
+            match (hex.len(), channel(0), channel(2), channel(4)) {
+                (6, Some(r), Some(g), Some(b)) => Ok(Color::rgb(r, g, b)),
+                _ => Err(format!("unknown color '{}', expected a name or #rrggbb", s)),
+            }
+        }
+    }
+}
+
+/// Slightly darker than the panel, blended on top of it.
+pub const DEFAULT_BACKGROUND: Color = Color::rgba(0, 0, 0, 20);
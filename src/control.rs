@@ -0,0 +1,228 @@
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use serde_json::{json, Value};
+use winit::event_loop::EventLoopProxy;
+
+use crate::cli::CtlCommand;
+
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener as Listener, UnixStream as Stream};
+#[cfg(not(unix))]
+use std::net::{TcpListener as Listener, TcpStream as Stream};
+
+/// Where other platforms listen. Anything on the machine can connect there, so commands
+/// have to come with the token of [`token_path`].
+#[cfg(not(unix))]
+const ADDRESS: &str = "127.0.0.1:47813";
+
+/// The directory of the socket, only the user may look into it. The runtime dir is that
+/// already, the one in the temp dir is made so.
+#[cfg(unix)]
+fn socket_dir() -> PathBuf {
+    let dirs = directories::ProjectDirs::from("de", "jnweiger", "VectorLab");
+    match dirs.as_ref().and_then(|d| d.runtime_dir()) {
+        Some(dir) => dir.to_path_buf(),
+        None => std::env::temp_dir().join(format!("vectorlab-{}", unsafe { libc::getuid() })),
+    }
+}
+
+/// Whether `dir` is a directory of the user that nobody else can get into, rather than
+/// something another user put there first.
+#[cfg(unix)]
+fn check_private(dir: &std::path::Path) -> io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    let meta = fs::symlink_metadata(dir)?;
+    if !meta.is_dir() || meta.uid() != unsafe { libc::getuid() } || meta.mode() & 0o077 != 0 {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("{} is not a private directory of the user", dir.display())));
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn connect() -> io::Result<(Stream, Option<String>)> {
+    let dir = socket_dir();
+    check_private(&dir)?;
+    Ok((Stream::connect(dir.join("vectorlab.sock"))?, None))
+}
+
+/// The token along with the connection.
+#[cfg(not(unix))]
+fn connect() -> io::Result<(Stream, Option<String>)> {
+    let token = fs::read_to_string(token_path()?)?;
+    Ok((Stream::connect(ADDRESS)?, Some(token)))
+}
+
+/// The listener, the file to remove when the window closes and the token commands need.
+#[cfg(unix)]
+fn listen() -> io::Result<(Listener, PathBuf, Option<String>)> {
+    use std::os::unix::fs::DirBuilderExt;
+
+    let dir = socket_dir();
+    if let Some(parent) = dir.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match fs::DirBuilder::new().mode(0o700).create(&dir) {
+        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+        _ => check_private(&dir)?,
+    }
+    let path = dir.join("vectorlab.sock");
+    if fs::symlink_metadata(&path).is_ok() {
+        if Stream::connect(&path).is_ok() {
+            return Err(io::Error::new(io::ErrorKind::AddrInUse, format!("another VectorLab listens on {}", path.display())));
+        }
+        // left behind by one that crashed
+        fs::remove_file(&path)?;
+    }
+    Ok((Listener::bind(&path)?, path, None))
+}
+
+/// Where the token is, in the config dir in the user's profile, which only the user can read.
+#[cfg(not(unix))]
+fn token_path() -> io::Result<PathBuf> {
+    let dirs = directories::ProjectDirs::from("de", "jnweiger", "VectorLab").ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config dir"))?;
+    Ok(dirs.config_dir().join("control-token"))
+}
+
+#[cfg(not(unix))]
+fn listen() -> io::Result<(Listener, PathBuf, Option<String>)> {
+    let listener = Listener::bind(ADDRESS)?;
+    let mut random = [0u8; 32];
+    getrandom::getrandom(&mut random)?;
+    let token: String = random.iter().map(|b| format!("{:02x}", b)).collect();
+    let path = token_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // a new file, so it gets the access rights of the profile rather than whatever one that
+    // was there had
+    let _ = fs::remove_file(&path);
+    fs::OpenOptions::new().write(true).create_new(true).open(&path)?.write_all(token.as_bytes())?;
+    Ok((listener, path, Some(token)))
+}
+
+/// `a == b` in a time that doesn't tell how much of the token a client got right.
+fn same_token(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// A command from a client, answered with [`Request::reply`] once the window has carried
+/// it out.
+pub struct Request {
+    /// e.g. `{"command": "zoom", "zoom": 2}`
+    pub command: Value,
+    reply: Sender<Result<(), String>>,
+}
+
+impl Request {
+    pub fn reply(self, result: Result<(), String>) {
+        // the client may have hung up already
+        let _ = self.reply.send(result);
+    }
+}
+
+/// Listens for clients like `vectorlab ctl` and hands their commands to the window, one
+/// JSON object per line each way.
+pub struct ControlServer {
+    requests: Receiver<Request>,
+    /// the socket or the token, removed again when the window closes
+    path: PathBuf,
+}
+
+impl ControlServer {
+    /// Listen in the background, waking the event loop through `proxy` when a command comes in.
+    pub fn start(proxy: EventLoopProxy<()>) -> io::Result<Self> {
+        let (listener, path, token) = listen()?;
+        let (sender, requests) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming().filter_map(|s| s.ok()) {
+                let (sender, proxy, token) = (sender.clone(), proxy.clone(), token.clone());
+                thread::spawn(move || serve(stream, sender, proxy, token));
+            }
+        });
+        Ok(Self { requests, path })
+    }
+
+    /// Commands that came in since the last frame.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.try_iter().collect()
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Whether `command` comes with `token`, if there is one.
+fn authorized(command: &Value, token: Option<&str>) -> bool {
+    token.is_none_or(|token| command.get("token").and_then(|t| t.as_str()).is_some_and(|t| same_token(t, token)))
+}
+
+/// Answer the commands of one client until it hangs up, or sends one without `token`.
+fn serve(stream: Stream, requests: Sender<Request>, proxy: EventLoopProxy<()>, token: Option<String>) {
+    let Ok(mut writer) = stream.try_clone() else { return };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else { return };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let result = match serde_json::from_str::<Value>(&line) {
+            Ok(command) if !authorized(&command, token.as_deref()) => {
+                let _ = writeln!(writer, "{}", json!({ "ok": false, "error": "wrong token" }));
+                return;
+            }
+            Ok(command) => {
+                let (reply, answer) = mpsc::channel();
+                if requests.send(Request { command, reply }).is_err() || proxy.send_event(()).is_err() {
+                    return;
+                }
+                answer.recv().unwrap_or_else(|_| Err("VectorLab closed".to_string()))
+            }
+            Err(e) => Err(format!("not a JSON command: {}", e)),
+        };
+        let answer = match result {
+            Ok(()) => json!({ "ok": true }),
+            Err(e) => json!({ "ok": false, "error": e }),
+        };
+        if writeln!(writer, "{}", answer).is_err() {
+            return;
+        }
+    }
+}
+
+/// `vectorlab ctl`, paths are made absolute as the window may run somewhere else.
+pub fn ctl(command: &CtlCommand) -> Result<(), Box<dyn Error>> {
+    let command = match command {
+        CtlCommand::Open { path } => json!({ "command": "open", "path": std::path::absolute(path)? }),
+        CtlCommand::Reload { path: Some(path) } => json!({ "command": "reload", "path": std::path::absolute(path)? }),
+        CtlCommand::Reload { path: None } => json!({ "command": "reload" }),
+        CtlCommand::Zoom { zoom } => json!({ "command": "zoom", "zoom": zoom }),
+        CtlCommand::Fit => json!({ "command": "fit" }),
+        CtlCommand::ExportPng { output, width, bg } => json!({ "command": "export_png", "path": std::path::absolute(output)?, "width": width, "bg": bg }),
+    };
+    send(command)
+}
+
+/// Send `command` to the running VectorLab and wait until it was carried out.
+fn send(mut command: Value) -> Result<(), Box<dyn Error>> {
+    let (stream, token) = connect().map_err(|e| format!("No VectorLab listening, start one with --listen ({})", e))?;
+    if let Some(token) = token {
+        command["token"] = json!(token.trim());
+    }
+    let mut writer = stream.try_clone()?;
+    writeln!(writer, "{}", command)?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    let answer: Value = serde_json::from_str(&line).map_err(|_| "VectorLab hung up without an answer")?;
+    match answer["ok"].as_bool() {
+        Some(true) => Ok(()),
+        _ => Err(answer["error"].as_str().unwrap_or("Failed").into()),
+    }
+}
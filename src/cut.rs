@@ -0,0 +1,90 @@
+use vectorlab_core::{cut_job, CutSettings, Document, Unit};
+
+use crate::units::length_value;
+
+/// File formats of Export for cutter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CutFormat {
+    Svg,
+    Dxf,
+}
+
+impl CutFormat {
+    /// As in the settings file, and the file extension.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Svg => "svg",
+            Self::Dxf => "dxf",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        [Self::Svg, Self::Dxf].into_iter().find(|f| f.name() == name)
+    }
+}
+
+/// The File > Export for cutter window, on a copy of the settings.
+pub struct CutExport {
+    draft: CutSettings,
+    format: CutFormat,
+    /// warnings of the draft for a document revision
+    checked: Option<(u64, CutSettings, Vec<String>)>,
+}
+
+impl CutExport {
+    pub fn new(settings: &CutSettings, format: CutFormat) -> Self {
+        Self { draft: settings.clone(), format, checked: None }
+    }
+}
+
+/// Show the window while `export` is Some, with what the export would leave out of `doc`.
+/// Returns the format and settings when Export is pressed.
+pub fn cut_window(ctx: &egui::Context, export: &mut Option<CutExport>, doc: Option<&Document>, unit: Unit) -> Option<(CutFormat, CutSettings)> {
+    let state = export.as_mut()?;
+    if let Some(doc) = doc {
+        if !state.checked.as_ref().is_some_and(|(revision, settings, _)| *revision == doc.revision && *settings == state.draft) {
+            state.checked = Some((doc.revision, state.draft.clone(), cut_job(doc, &state.draft).warnings));
+        }
+    }
+    let mut open = true;
+    let mut done = None;
+    egui::Window::new("Export for cutter").open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+        let draft = &mut state.draft;
+        egui::Grid::new("cut").num_columns(2).show(ui, |ui| {
+            ui.label("Format");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut state.format, CutFormat::Svg, "SVG").on_hover_text("For Cricut Design Space and Silhouette Studio Designer Edition");
+                ui.radio_value(&mut state.format, CutFormat::Dxf, "DXF").on_hover_text("R12, for Silhouette Studio's basic edition and other cutter software");
+            });
+            ui.end_row();
+            ui.label("");
+            ui.checkbox(&mut draft.outline_strokes, "Cut around strokes").on_hover_text("Otherwise along their middle, open ones are left out");
+            ui.end_row();
+            ui.label("Close gaps up to");
+            ui.add(length_value(&mut draft.close_gaps, unit, 0.01, 0.0..=10.0));
+            ui.end_row();
+            ui.label("Curve tolerance");
+            ui.add(length_value(&mut draft.tolerance, unit, 0.001, 0.001..=1.0));
+            ui.end_row();
+        });
+        ui.weak("Closed outlines only, at the size of the page in mm.");
+        if let Some((_, _, warnings)) = &state.checked {
+            for warning in warnings {
+                ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}", warning));
+            }
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Export…").clicked() {
+                done = Some(Some((state.format, draft.clone())));
+            }
+            if ui.button("Cancel").clicked() {
+                done = Some(None);
+            }
+        });
+    });
+    if !open || done.is_some() {
+        *export = None;
+    }
+    done.flatten()
+}
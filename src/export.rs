@@ -0,0 +1,238 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use vectorlab_core::{Color, Document, RasterImage, Unit, CSS_DPI};
+
+/// Largest width or height of an export, JPEG can't go beyond 65535 and memory runs out
+/// well before that.
+pub const MAX_SIZE: u32 = 16384;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RasterFormat {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl RasterFormat {
+    pub const ALL: [Self; 3] = [Self::Png, Self::Jpeg, Self::Webp];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Png => "PNG",
+            Self::Jpeg => "JPEG",
+            Self::Webp => "WebP",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+            Self::Webp => "webp",
+        }
+    }
+
+    /// JPEG has no alpha channel.
+    pub fn has_alpha(self) -> bool {
+        self != Self::Jpeg
+    }
+}
+
+/// The File > Export Raster window. Width, height and DPI are tied together by the page's
+/// aspect ratio, changing one changes the others.
+#[derive(Clone, Debug)]
+pub struct ExportRaster {
+    /// of the page, in CSS pixels
+    page: [f32; 2],
+    pub width: u32,
+    pub height: u32,
+    pub dpi: f32,
+    pub format: RasterFormat,
+    pub transparent: bool,
+    pub background: Color,
+    /// JPEG only, 1 to 100
+    pub quality: u8,
+    /// of the selection cropped to it rather than the page
+    pub selection: bool,
+}
+
+impl ExportRaster {
+    pub fn new(doc: &Document) -> Self {
+        let page = [doc.size[0].max(1.0), doc.size[1].max(1.0)];
+        let mut export = Self { page, width: 0, height: 0, dpi: CSS_DPI, format: RasterFormat::Png, transparent: true, background: Color::WHITE, quality: 90, selection: false };
+        export.set_dpi(CSS_DPI);
+        export
+    }
+
+    fn set_dpi(&mut self, dpi: f32) {
+        let largest = self.page[0].max(self.page[1]);
+        self.dpi = dpi.clamp(1.0, MAX_SIZE as f32 * CSS_DPI / largest);
+        let scale = self.dpi / CSS_DPI;
+        self.width = (self.page[0] * scale).round().max(1.0) as u32;
+        self.height = (self.page[1] * scale).round().max(1.0) as u32;
+    }
+
+    /// The background the image is rendered on, none for transparent.
+    pub fn fill(&self) -> Option<Color> {
+        if self.transparent && self.format.has_alpha() {
+            None
+        } else {
+            Some(Color { a: 255, ..self.background })
+        }
+    }
+
+    /// Encode `image` the chosen way.
+    pub fn save(&self, image: &RasterImage, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        match self.format {
+            RasterFormat::Png => vectorlab_core::save_png(image, path, self.dpi),
+            RasterFormat::Jpeg => {
+                // rendered on an opaque background, premultiplied is the same as straight
+                let rgb: Vec<u8> = image.rgba.chunks_exact(4).flat_map(|p| [p[0], p[1], p[2]]).collect();
+                let mut encoder = jpeg_encoder::Encoder::new_file(path, self.quality)?;
+                let dpi = self.dpi.round() as u16;
+                encoder.set_density(jpeg_encoder::Density::Inch { x: dpi, y: dpi });
+                encoder.encode(&rgb, image.width as u16, image.height as u16, jpeg_encoder::ColorType::Rgb)?;
+                Ok(())
+            }
+            RasterFormat::Webp => {
+                // lossless, the encoder has no lossy mode
+                let encoder = image_webp::WebPEncoder::new(BufWriter::new(File::create(path)?));
+                encoder.encode(&image.unpremultiplied(), image.width, image.height, image_webp::ColorType::Rgba8)?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Show the window while `export` is Some, the size on paper in `unit`. Returns the choices
+/// when Export is pressed, the caller asks for the file name and renders.
+pub fn export_raster_window(ctx: &egui::Context, export: &mut Option<ExportRaster>, unit: Unit) -> Option<ExportRaster> {
+    let draft = export.as_mut()?;
+    let mut open = true;
+    let mut done = None;
+    let title = if draft.selection { "Export Selection" } else { "Export Raster" };
+    egui::Window::new(title).id(egui::Id::new("export_raster")).open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+        egui::Grid::new("export_raster").num_columns(2).show(ui, |ui| {
+            ui.label("Width");
+            let mut width = draft.width;
+            if ui.add(egui::DragValue::new(&mut width).range(1..=MAX_SIZE).suffix(" px")).changed() {
+                draft.set_dpi(width as f32 / draft.page[0] * CSS_DPI);
+            }
+            ui.end_row();
+            ui.label("Height");
+            let mut height = draft.height;
+            if ui.add(egui::DragValue::new(&mut height).range(1..=MAX_SIZE).suffix(" px")).changed() {
+                draft.set_dpi(height as f32 / draft.page[1] * CSS_DPI);
+            }
+            ui.end_row();
+            ui.label("Resolution");
+            let mut dpi = draft.dpi;
+            if ui.add(egui::DragValue::new(&mut dpi).speed(1.0).range(1.0..=2400.0).suffix(" dpi")).changed() {
+                draft.set_dpi(dpi);
+            }
+            ui.end_row();
+            ui.label("Print size");
+            let [w, h] = draft.page.map(|px| unit.format(Unit::Px.to_mm(px)));
+            ui.label(format!("{w} × {h}")).on_hover_text("Of the page, what the image comes out as when printed at its resolution");
+            ui.end_row();
+
+            ui.label("Format");
+            ui.horizontal(|ui| {
+                for format in RasterFormat::ALL {
+                    ui.radio_value(&mut draft.format, format, format.name());
+                }
+            });
+            ui.end_row();
+            if draft.format == RasterFormat::Jpeg {
+                ui.label("Quality");
+                ui.add(egui::Slider::new(&mut draft.quality, 1..=100));
+                ui.end_row();
+            }
+
+            ui.label("Background");
+            ui.horizontal(|ui| {
+                ui.add_enabled(draft.format.has_alpha(), egui::Checkbox::new(&mut draft.transparent, "Transparent")).on_disabled_hover_text("JPEG has no transparency");
+                ui.add_enabled_ui(draft.fill().is_some(), |ui| {
+                    let c = draft.background;
+                    let mut rgb = [c.r, c.g, c.b];
+                    if ui.color_edit_button_srgb(&mut rgb).changed() {
+                        draft.background = Color::rgb(rgb[0], rgb[1], rgb[2]);
+                    }
+                });
+            });
+            ui.end_row();
+        });
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Export…").clicked() {
+                done = Some(Some(draft.clone()));
+            }
+            if ui.button("Cancel").clicked() {
+                done = Some(None);
+            }
+        });
+    });
+    if !open || done.is_some() {
+        *export = None;
+    }
+    done.flatten()
+}
+
+/// The File > Export Layers window, how the files for the layers are named.
+#[derive(Clone, Debug)]
+pub struct ExportLayers {
+    pub pattern: String,
+}
+
+impl ExportLayers {
+    /// `pattern` with `{name}` replaced by the document's file name, `{layer}` by the layer's
+    /// id and `{n}` by its number, .svg added if it has no extension.
+    pub fn file_name(&self, name: &str, layer: &str, n: usize) -> String {
+        let file = self.pattern.replace("{name}", name).replace("{layer}", &layer.replace(['/', '\\'], "_")).replace("{n}", &n.to_string());
+        if Path::new(&file).extension().is_some() {
+            file
+        } else {
+            file + ".svg"
+        }
+    }
+}
+
+/// Show the window while `export` is Some. Returns the choices when Export is pressed, the
+/// caller asks for the folder and writes the files.
+pub fn export_layers_window(ctx: &egui::Context, export: &mut Option<ExportLayers>, name: &str, layers: &[String]) -> Option<ExportLayers> {
+    let draft = export.as_mut()?;
+    let mut open = true;
+    let mut done = None;
+    egui::Window::new("Export Layers").open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+        ui.label("An SVG for each element below the root, on the same page so they line up.");
+        ui.horizontal(|ui| {
+            ui.label("File names");
+            ui.text_edit_singleline(&mut draft.pattern).on_hover_text("{name} is the document's name, {layer} the layer's id, {n} its number");
+        });
+        ui.separator();
+        if layers.is_empty() {
+            ui.label("No layers");
+        }
+        for (i, layer) in layers.iter().enumerate().take(5) {
+            ui.monospace(draft.file_name(name, layer, i + 1));
+        }
+        if layers.len() > 5 {
+            ui.label(format!("and {} more", layers.len() - 5));
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.add_enabled(!layers.is_empty() && !draft.pattern.trim().is_empty(), egui::Button::new("Export…")).clicked() {
+                done = Some(Some(draft.clone()));
+            }
+            if ui.button("Cancel").clicked() {
+                done = Some(None);
+            }
+        });
+    });
+    if !open || done.is_some() {
+        *export = None;
+    }
+    done.flatten()
+}
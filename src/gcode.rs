@@ -0,0 +1,175 @@
+use std::fmt::Write;
+
+use vectorlab_core::{Document, ElementId, ElementKind, GcodeOrigin, GcodeSettings, GcodeUnits, ToolMove, ViewTransform};
+
+use crate::inspector::element_label;
+
+/// Moves of the last export or of the travel overlay, for the preview.
+pub struct Toolpaths {
+    pub moves: Vec<ToolMove>,
+    /// travel length in the drawing's own order, when the export was optimized
+    pub unoptimized_travel: Option<f32>,
+    /// of the document they were made from
+    pub revision: u64,
+}
+
+impl Toolpaths {
+    pub fn new(doc: &Document, gcode: &GcodeSettings) -> Self {
+        let home = vectorlab_core::machine_origin(doc, gcode);
+        let contours = vectorlab_core::job_contours(doc, gcode);
+        // what the optimization saved
+        let unoptimized_travel = gcode.optimize.then(|| {
+            let contours = vectorlab_core::job_contours(doc, &GcodeSettings { optimize: false, ..gcode.clone() });
+            vectorlab_core::travel_length(&contours, home)
+        });
+        Self { moves: vectorlab_core::tool_moves(&contours, home), unoptimized_travel, revision: doc.revision }
+    }
+}
+
+/// The File > Export G-code window, on a copy of the machine settings.
+pub struct GcodeExport {
+    draft: GcodeSettings,
+    /// top-level groups of the document, whose passes can be set
+    layers: Vec<(ElementId, String)>,
+}
+
+impl GcodeExport {
+    pub fn new(settings: &GcodeSettings, doc: &Document) -> Self {
+        let layers = doc
+            .get(doc.root)
+            .children
+            .iter()
+            .filter(|&&id| matches!(doc.get(id).kind, ElementKind::Group))
+            .map(|&id| (id, element_label(doc.get(id))))
+            .collect();
+        Self { draft: GcodeSettings { passes: Default::default(), ..settings.clone() }, layers }
+    }
+}
+
+/// Show the window while `export` is Some. Returns the settings when Export is pressed.
+pub fn gcode_window(ctx: &egui::Context, export: &mut Option<GcodeExport>) -> Option<GcodeSettings> {
+    let state = export.as_mut()?;
+    let mut open = true;
+    let mut done = None;
+    egui::Window::new("Export G-code").open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+        let draft = &mut state.draft;
+        let unit = if draft.units == GcodeUnits::Mm { " mm" } else { " in" };
+        egui::Grid::new("gcode").num_columns(2).show(ui, |ui| {
+            ui.label("Units");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut draft.units, GcodeUnits::Mm, "mm");
+                ui.radio_value(&mut draft.units, GcodeUnits::Inch, "inch");
+            });
+            ui.end_row();
+            ui.label("Origin");
+            egui::ComboBox::from_id_source("gcode_origin").selected_text(draft.origin.label()).show_ui(ui, |ui| {
+                for origin in GcodeOrigin::ALL {
+                    ui.selectable_value(&mut draft.origin, origin, origin.label());
+                }
+            });
+            ui.end_row();
+            ui.label("Axes");
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut draft.invert_x, "X points left");
+                ui.checkbox(&mut draft.invert_y, "Y points down");
+            });
+            ui.end_row();
+            ui.label("Feed rate");
+            ui.add(egui::DragValue::new(&mut draft.feed_rate).range(1.0..=100000.0).suffix(format!("{unit}/min")));
+            ui.end_row();
+            ui.label("Travel rate");
+            ui.add(egui::DragValue::new(&mut draft.travel_rate).range(1.0..=100000.0).suffix(format!("{unit}/min")));
+            ui.end_row();
+            ui.label("Head");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut draft.laser, false, "Pen / spindle (Z)");
+                ui.radio_value(&mut draft.laser, true, "Laser (M3 / M5)");
+            });
+            ui.end_row();
+            if draft.laser {
+                ui.label("Power");
+                ui.add(egui::DragValue::new(&mut draft.laser_power).range(0.0..=100000.0).prefix("S"));
+                ui.end_row();
+            } else {
+                ui.label("Z up / down");
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut draft.z_up).speed(0.1).suffix(unit));
+                    ui.add(egui::DragValue::new(&mut draft.z_down).speed(0.1).suffix(unit));
+                });
+                ui.end_row();
+            }
+            ui.label("Curve tolerance");
+            ui.add(egui::DragValue::new(&mut draft.tolerance).speed(0.001).range(0.001..=10.0).suffix(unit));
+            ui.end_row();
+            ui.label("");
+            ui.checkbox(&mut draft.optimize, "Optimize for plotting")
+                .on_hover_text("Reorder and reverse the paths of each layer so the pen travels less");
+            ui.end_row();
+        });
+
+        if !state.layers.is_empty() {
+            ui.separator();
+            ui.strong("Passes per layer");
+            egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+                egui::Grid::new("gcode_passes").num_columns(2).striped(true).show(ui, |ui| {
+                    for (id, label) in &state.layers {
+                        ui.label(label);
+                        let passes = draft.passes.entry(*id).or_insert(1);
+                        ui.add(egui::DragValue::new(passes).range(0..=100)).on_hover_text("0 leaves the layer out");
+                        ui.end_row();
+                    }
+                });
+            });
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Export…").clicked() {
+                done = Some(Some(draft.clone()));
+            }
+            if ui.button("Cancel").clicked() {
+                done = Some(None);
+            }
+        });
+    });
+    if !open || done.is_some() {
+        *export = None;
+    }
+    done.flatten()
+}
+
+/// The moves of the last export over the canvas, cutting solid and travel dashed, with
+/// their lengths. Returns false when the preview is closed.
+pub fn draw_toolpaths(ui: &egui::Ui, painter: &egui::Painter, canvas: egui::Rect, doc: &Document, view: &ViewTransform, toolpaths: &Toolpaths) -> bool {
+    let to_screen = |p: [f32; 2]| egui::Pos2::from(view.to_screen(p)) + canvas.min.to_vec2();
+    let cut = egui::Color32::from_rgb(0, 200, 120);
+    let travel = egui::Color32::from_rgb(255, 80, 80);
+    let (mut cut_length, mut travel_length) = (0.0, 0.0);
+    for m in &toolpaths.moves {
+        let line = [to_screen(m.from), to_screen(m.to)];
+        if m.cutting {
+            painter.line_segment(line, egui::Stroke::new(1.5, cut));
+            cut_length += m.length();
+        } else {
+            painter.extend(egui::Shape::dashed_line(&line, egui::Stroke::new(1.0, travel), 6.0, 4.0));
+            travel_length += m.length();
+        }
+    }
+
+    let mm = doc.mm_per_unit();
+    let mm = (mm[0] + mm[1]) * 0.5;
+    let mut open = true;
+    egui::Area::new(ui.id().with("toolpath_legend")).fixed_pos(canvas.min + egui::vec2(8.0, 8.0)).show(ui.ctx(), |ui| {
+        egui::Frame::popup(ui.style()).show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.colored_label(cut, format!("━ cut {:.0} mm", cut_length * mm));
+                let mut label = format!("┅ travel {:.0} mm", travel_length * mm);
+                if let Some(before) = toolpaths.unoptimized_travel {
+                    let _ = write!(label, ", {:.0} mm unoptimized", before * mm);
+                }
+                ui.colored_label(travel, label);
+                open = !ui.small_button("✕").on_hover_text("Hide the toolpaths").clicked();
+            });
+        });
+    });
+    open
+}
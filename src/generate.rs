@@ -0,0 +1,204 @@
+use vectorlab_core::{add_shape, bbox_of, Color, Generator, Paint, Segment, Unit, LSYSTEM_PRESETS};
+
+use crate::canvas::draw_segments;
+use crate::shapes::ShapeSettings;
+use crate::tab::Tab;
+use crate::units::length_value;
+
+/// Side of the preview, in points of the screen.
+const PREVIEW_SIZE: f32 = 300.0;
+
+/// The Generate window, open on one of the generators.
+pub struct GenerateWindow {
+    /// index into the generators
+    pub selected: usize,
+    /// drawing of the generator as it was last previewed
+    preview: Option<(Generator, Vec<Segment>)>,
+}
+
+impl GenerateWindow {
+    pub fn new(selected: usize) -> Self {
+        Self { selected, preview: None }
+    }
+}
+
+/// The parameters of `generator`.
+fn generator_options(ui: &mut egui::Ui, generator: &mut Generator, unit: Unit) {
+    egui::Grid::new("generate").num_columns(2).show(ui, |ui| match generator {
+        Generator::Spirograph { ring, wheel, pen, outside, radius } => {
+            ui.label("Ring teeth");
+            ui.add(egui::DragValue::new(ring).range(2..=500));
+            ui.end_row();
+            ui.label("Wheel teeth");
+            ui.add(egui::DragValue::new(wheel).range(1..=499)).on_hover_text("Fewer teeth in common with the ring make more loops");
+            ui.end_row();
+            ui.label("Pen hole");
+            ui.add(egui::Slider::new(pen, 0.0..=2.0)).on_hover_text("Distance from the wheel's center, 1 at its rim");
+            ui.end_row();
+            ui.label("");
+            ui.checkbox(outside, "Wheel rolls outside the ring");
+            ui.end_row();
+            ui.label("Radius");
+            ui.add(length_value(radius, unit, 0.5, 1.0..=1000.0));
+            ui.end_row();
+        }
+        Generator::LSystem { axiom, rules, angle, iterations, size } => {
+            ui.label("Preset");
+            egui::ComboBox::from_id_source("lsystem-preset").selected_text("Choose…").show_ui(ui, |ui| {
+                for (name, preset_axiom, preset_rules, preset_angle) in LSYSTEM_PRESETS {
+                    if ui.selectable_label(false, name).clicked() {
+                        *axiom = preset_axiom.to_string();
+                        *rules = preset_rules.to_string();
+                        *angle = preset_angle;
+                    }
+                }
+            });
+            ui.end_row();
+            ui.label("Axiom");
+            ui.text_edit_singleline(axiom);
+            ui.end_row();
+            ui.label("Rules");
+            ui.add(egui::TextEdit::multiline(rules).desired_rows(3).code_editor())
+                .on_hover_text("X=… separated by ; or lines. F, G, A and B draw forward, f moves, + and - turn, [ and ] save and restore, | turns around");
+            ui.end_row();
+            ui.label("Angle");
+            ui.add(egui::DragValue::new(angle).speed(0.5).range(-360.0..=360.0).suffix("°"));
+            ui.end_row();
+            ui.label("Iterations");
+            ui.add(egui::DragValue::new(iterations).range(0..=16));
+            ui.end_row();
+            ui.label("Size");
+            ui.add(length_value(size, unit, 0.5, 1.0..=2000.0));
+            ui.end_row();
+        }
+        Generator::Maze { columns, rows, cell, seed } => {
+            ui.label("Columns");
+            ui.add(egui::DragValue::new(columns).range(1..=300));
+            ui.end_row();
+            ui.label("Rows");
+            ui.add(egui::DragValue::new(rows).range(1..=300));
+            ui.end_row();
+            ui.label("Cell size");
+            ui.add(length_value(cell, unit, 0.1, 0.5..=100.0));
+            ui.end_row();
+            seed_row(ui, seed);
+        }
+        Generator::ThetaMaze { rings, cell, seed } => {
+            ui.label("Rings");
+            ui.add(egui::DragValue::new(rings).range(1..=100));
+            ui.end_row();
+            ui.label("Ring width");
+            ui.add(length_value(cell, unit, 0.1, 0.5..=100.0));
+            ui.end_row();
+            seed_row(ui, seed);
+        }
+        Generator::Star { points, skip, radius } => {
+            ui.label("Points");
+            ui.add(egui::DragValue::new(points).range(3..=200));
+            ui.end_row();
+            ui.label("Skip");
+            ui.add(egui::DragValue::new(skip).range(1..=(points.saturating_sub(1) / 2).max(1))).on_hover_text("Join every this many corners, 1 for a polygon");
+            ui.end_row();
+            ui.label("Radius");
+            ui.add(length_value(radius, unit, 0.5, 1.0..=1000.0));
+            ui.end_row();
+        }
+        Generator::Gear { teeth, module, pressure_angle, bore } => {
+            ui.label("Teeth");
+            ui.add(egui::DragValue::new(teeth).range(4..=300));
+            ui.end_row();
+            ui.label("Module");
+            ui.add(length_value(module, unit, 0.05, 0.1..=50.0)).on_hover_text("Pitch diameter per tooth, gears that mesh have the same");
+            ui.end_row();
+            ui.label("Pressure angle");
+            ui.add(egui::DragValue::new(pressure_angle).speed(0.5).range(10.0..=35.0).suffix("°"));
+            ui.end_row();
+            ui.label("Bore");
+            ui.add(length_value(bore, unit, 0.1, 0.0..=500.0)).on_hover_text("Diameter of the hole in the middle, 0 for none");
+            ui.end_row();
+        }
+    });
+}
+
+fn seed_row(ui: &mut egui::Ui, seed: &mut u64) {
+    ui.label("Seed");
+    ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(seed));
+        if ui.button("🎲").on_hover_text("Another maze").clicked() {
+            *seed = seed.wrapping_add(1);
+        }
+    });
+    ui.end_row();
+}
+
+/// Show the window while `state` is Some, on the parameters kept in `generators`. Returns the
+/// drawing in mm around the origin when Insert is pressed.
+pub fn generate_window(ctx: &egui::Context, state: &mut Option<GenerateWindow>, generators: &mut [Generator], unit: Unit, has_doc: bool) -> Option<Vec<Segment>> {
+    let window = state.as_mut()?;
+    let mut open = true;
+    let mut done = None;
+    egui::Window::new("Generate").open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+        egui::ComboBox::from_id_source("generator").selected_text(generators[window.selected].label()).show_ui(ui, |ui| {
+            for (i, generator) in generators.iter().enumerate() {
+                ui.selectable_value(&mut window.selected, i, generator.label());
+            }
+        });
+        let generator = &mut generators[window.selected];
+        generator_options(ui, generator, unit);
+        if !window.preview.as_ref().is_some_and(|(previewed, _)| previewed == generator) {
+            window.preview = Some((generator.clone(), generator.segments()));
+        }
+        let Some((_, segments)) = &window.preview else { return };
+
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(PREVIEW_SIZE, PREVIEW_SIZE), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, egui::Color32::WHITE);
+        let points = segments.iter().filter_map(|s| match s {
+            Segment::MoveTo(p) | Segment::LineTo(p) | Segment::QuadTo(_, p) | Segment::CubicTo(_, _, p) => Some(p),
+            Segment::Close => None,
+        });
+        let b = bbox_of(points).unwrap_or([0.0; 4]);
+        let (w, h) = (b[2] - b[0], b[3] - b[1]);
+        let scale = (PREVIEW_SIZE - 16.0) / w.max(h).max(f32::EPSILON);
+        let offset = rect.center() - egui::vec2(b[0] + b[2], b[1] + b[3]) * 0.5 * scale;
+        draw_segments(&painter, segments, |p| offset + egui::vec2(p[0], p[1]) * scale, egui::Stroke::new(1.0, egui::Color32::BLACK));
+        ui.label(format!("{} × {}, {} segments", unit.format(w), unit.format(h), segments.len()));
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.add_enabled(has_doc && !segments.is_empty(), egui::Button::new("Insert")).on_hover_text("In the middle of the page, with the stroke of the shape tool").clicked() {
+                done = Some(Some(segments.clone()));
+            }
+            if ui.button("Cancel").clicked() {
+                done = Some(None);
+            }
+        });
+    });
+    if !open || done.is_some() {
+        *state = None;
+    }
+    done.flatten()
+}
+
+/// Add `segments` in mm around the origin to the middle of the page as a new path, stroked
+/// like the shape tool strokes, and select it.
+pub fn insert_generated(tab: &mut Tab, segments: Vec<Segment>, shapes: &ShapeSettings) {
+    let doc = &tab.doc;
+    let [vx, vy, vw, vh] = doc.view_box;
+    let mm = doc.mm_per_unit();
+    let at = |p: [f32; 2]| [vx + vw * 0.5 + p[0] / mm[0], vy + vh * 0.5 + p[1] / mm[1]];
+    let segments = segments
+        .into_iter()
+        .map(|s| match s {
+            Segment::MoveTo(p) => Segment::MoveTo(at(p)),
+            Segment::LineTo(p) => Segment::LineTo(at(p)),
+            Segment::QuadTo(c, p) => Segment::QuadTo(at(c), at(p)),
+            Segment::CubicTo(c1, c2, p) => Segment::CubicTo(at(c1), at(c2), at(p)),
+            Segment::Close => Segment::Close,
+        })
+        .collect();
+    let stroke = shapes.stroke.clone().unwrap_or(Paint::Solid(Color::BLACK));
+    let width = shapes.stroke_width * 2.0 / (mm[0] + mm[1]).max(f32::EPSILON);
+    let edit = add_shape(doc, segments, None, Some(stroke), width);
+    tab.selection.clear();
+    tab.push_and_select_new(Box::new(edit));
+}
@@ -0,0 +1,246 @@
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+use egui_glow::glow::{self, HasContext};
+use vectorlab_core::usvg::Transform;
+use vectorlab_core::{transform_point, Color, Document, ElementKind, FlattenedPath, Paint, PlacedImage, ViewTransform};
+
+use crate::canvas::{draw_image, to_egui, ImageCache};
+
+/// Documents with more paths than this are drawn from GL buffers. Smaller ones look better as
+/// egui shapes, which anti-alias edges and keep hairline strokes visible.
+const GPU_PATH_THRESHOLD: usize = 2000;
+
+// position in document coordinates, premultiplied rgba
+const VERTEX_SIZE: i32 = 12;
+
+/// The paths of a document tessellated once into a GL vertex and index buffer in document
+/// coordinates. Panning, zooming and rotating only change uniforms, the buffers are
+/// rebuilt when the document's revision changes.
+#[derive(Default)]
+pub struct GpuMeshes {
+    revision: u64,
+    enabled: bool,
+    items: Vec<Item>,
+    shared: Arc<Mutex<GlState>>,
+}
+
+/// Runs of paths drawn with one call, interleaved with images in paint order.
+enum Item {
+    Paths(Range<i32>),
+    Image(PlacedImage, Transform, f32),
+}
+
+#[derive(Default)]
+struct GlState {
+    objects: Option<GlObjects>,
+    // filled on the UI side, uploaded by the next paint callback
+    pending: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+#[derive(Clone, Copy)]
+struct GlObjects {
+    program: glow::Program,
+    view_location: Option<glow::UniformLocation>,
+    offset_location: Option<glow::UniformLocation>,
+    opacity_location: Option<glow::UniformLocation>,
+    vao: glow::VertexArray,
+    vbo: glow::Buffer,
+    ibo: glow::Buffer,
+}
+
+// objects of dropped meshes, the GL context is only available inside paint callbacks
+static RETIRED: Mutex<Vec<GlObjects>> = Mutex::new(vec![]);
+
+impl Drop for GpuMeshes {
+    fn drop(&mut self) {
+        if let Some(objects) = self.shared.lock().unwrap().objects.take() {
+            RETIRED.lock().unwrap().push(objects);
+        }
+    }
+}
+
+impl GpuMeshes {
+    /// Rebuild the buffers if `doc` changed. False if the document is small enough to be
+    /// drawn as egui shapes instead.
+    pub fn update(&mut self, doc: &Document) -> bool {
+        if doc.revision == self.revision {
+            return self.enabled;
+        }
+        self.revision = doc.revision;
+        self.enabled = doc.path_count() > GPU_PATH_THRESHOLD;
+        self.items.clear();
+        if !self.enabled {
+            return false;
+        }
+
+        let mut vertices = vec![];
+        let mut indices: Vec<u32> = vec![];
+        let mut run_start = 0;
+        doc.walk(|_, element, ts, opacity| match &element.kind {
+            ElementKind::Path(path) => add_path(&mut vertices, &mut indices, path, &ts, opacity),
+            ElementKind::Image { image: Some(image), .. } => {
+                let end = indices.len() as i32;
+                if end > run_start {
+                    self.items.push(Item::Paths(run_start..end));
+                }
+                run_start = end;
+                self.items.push(Item::Image(image.clone(), ts, opacity));
+            }
+            _ => {}
+        });
+        let end = indices.len() as i32;
+        if end > run_start {
+            self.items.push(Item::Paths(run_start..end));
+        }
+
+        let indices = indices.iter().flat_map(|i| i.to_ne_bytes()).collect();
+        self.shared.lock().unwrap().pending = Some((vertices, indices));
+        true
+    }
+
+    /// Draw the document into `rect`, the canvas the view's pan is relative to, as opaque
+    /// as the painter is.
+    pub fn paint(&self, painter: &egui::Painter, rect: egui::Rect, view: &ViewTransform, images: &mut ImageCache) {
+        let opacity = painter.opacity();
+        for item in &self.items {
+            match item {
+                Item::Paths(range) => {
+                    let (shared, range, view) = (self.shared.clone(), range.clone(), *view);
+                    let callback = egui_glow::CallbackFn::new(move |info, painter| {
+                        let [w, h] = [info.viewport.width(), info.viewport.height()];
+                        // document coordinates to normalized device coordinates, y pointing up,
+                        // a 2×2 matrix by columns and the offset
+                        let [a, b, c, d] = view.matrix();
+                        let ndc = [2.0 * a / w, -2.0 * c / h, 2.0 * b / w, -2.0 * d / h, 2.0 * view.pan[0] / w - 1.0, 1.0 - 2.0 * view.pan[1] / h];
+                        unsafe { shared.lock().unwrap().draw(painter.gl(), range.clone(), ndc, opacity) };
+                    });
+                    painter.add(egui::PaintCallback { rect, callback: Arc::new(callback) });
+                }
+                Item::Image(image, ts, opacity) => {
+                    let texture = images.texture(painter.ctx(), &image.pixels);
+                    draw_image(painter, image, texture, ts, *opacity, view, rect.min.to_vec2());
+                }
+            }
+        }
+    }
+}
+
+fn add_path(vertices: &mut Vec<u8>, indices: &mut Vec<u32>, path: &FlattenedPath, ts: &Transform, opacity: f32) {
+    let meshes = [
+        (&path.style.fill, &path.fill_vertices, &path.fill_indices, &path.fill_colors),
+        (&path.style.stroke, &path.stroke_vertices, &path.stroke_indices, &path.stroke_colors),
+    ];
+    for (paint, mesh_vertices, mesh_indices, colors) in meshes {
+        let Some(paint) = paint else { continue };
+        let base = (vertices.len() / VERTEX_SIZE as usize) as u32;
+        for (i, &v) in mesh_vertices.iter().enumerate() {
+            let color = match paint {
+                Paint::Solid(color) => *color,
+                Paint::Gradient(_) => colors.get(i).copied().unwrap_or(Color::BLACK),
+            };
+            let p = transform_point(ts, v);
+            vertices.extend(p[0].to_ne_bytes());
+            vertices.extend(p[1].to_ne_bytes());
+            vertices.extend(to_egui(color).gamma_multiply(opacity).to_array());
+        }
+        indices.extend(mesh_indices.iter().map(|i| base + i));
+    }
+}
+
+impl GlState {
+    unsafe fn draw(&mut self, gl: &glow::Context, range: Range<i32>, ndc: [f32; 6], opacity: f32) {
+        for objects in RETIRED.lock().unwrap().drain(..) {
+            objects.delete(gl);
+        }
+        let objects = match self.objects {
+            Some(objects) => objects,
+            None => match GlObjects::new(gl) {
+                Ok(objects) => *self.objects.insert(objects),
+                Err(e) => {
+                    log::error!("Failed to set up GPU meshes: {}", e);
+                    return;
+                }
+            },
+        };
+        if let Some((vertices, indices)) = self.pending.take() {
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(objects.vbo));
+            gl.buffer_data_u8_slice(glow::ARRAY_BUFFER, &vertices, glow::STATIC_DRAW);
+            gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(objects.ibo));
+            gl.buffer_data_u8_slice(glow::ELEMENT_ARRAY_BUFFER, &indices, glow::STATIC_DRAW);
+        }
+
+        gl.use_program(Some(objects.program));
+        gl.uniform_matrix_2_f32_slice(objects.view_location.as_ref(), false, &ndc[..4]);
+        gl.uniform_2_f32(objects.offset_location.as_ref(), ndc[4], ndc[5]);
+        gl.uniform_1_f32(objects.opacity_location.as_ref(), opacity);
+        gl.bind_vertex_array(Some(objects.vao));
+        // egui_glow does not keep the element buffer bound to its own vertex array
+        gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(objects.ibo));
+        gl.draw_elements(glow::TRIANGLES, range.end - range.start, glow::UNSIGNED_INT, range.start * 4);
+        gl.bind_vertex_array(None);
+    }
+}
+
+impl GlObjects {
+    unsafe fn new(gl: &glow::Context) -> Result<Self, String> {
+        let version = egui_glow::ShaderVersion::get(gl);
+        let (attribute, varying_out, varying_in, frag_out, frag_color) = if version.is_new_shader_interface() {
+            ("in", "out", "in", "out vec4 f_color;", "f_color")
+        } else {
+            ("attribute", "varying", "varying", "", "gl_FragColor")
+        };
+        let header = format!("{}\n#ifdef GL_ES\nprecision mediump float;\n#endif\n", version.version_declaration());
+        let vertex_source = format!(
+            "{header}{attribute} vec2 a_pos;\n{attribute} vec4 a_color;\nuniform mat2 u_view;\nuniform vec2 u_offset;\nuniform float u_opacity;\n{varying_out} vec4 v_color;\n\
+             void main() {{\n    gl_Position = vec4(u_view * a_pos + u_offset, 0.0, 1.0);\n    v_color = a_color * u_opacity;\n}}\n"
+        );
+        let fragment_source = format!("{header}{varying_in} vec4 v_color;\n{frag_out}\nvoid main() {{\n    {frag_color} = v_color;\n}}\n");
+
+        let program = gl.create_program()?;
+        let mut shaders = vec![];
+        for (kind, source) in [(glow::VERTEX_SHADER, vertex_source), (glow::FRAGMENT_SHADER, fragment_source)] {
+            let shader = gl.create_shader(kind)?;
+            gl.shader_source(shader, &source);
+            gl.compile_shader(shader);
+            if !gl.get_shader_compile_status(shader) {
+                return Err(gl.get_shader_info_log(shader));
+            }
+            gl.attach_shader(program, shader);
+            shaders.push(shader);
+        }
+        gl.bind_attrib_location(program, 0, "a_pos");
+        gl.bind_attrib_location(program, 1, "a_color");
+        gl.link_program(program);
+        for shader in shaders {
+            gl.detach_shader(program, shader);
+            gl.delete_shader(shader);
+        }
+        if !gl.get_program_link_status(program) {
+            return Err(gl.get_program_info_log(program));
+        }
+
+        let vao = gl.create_vertex_array()?;
+        let vbo = gl.create_buffer()?;
+        let ibo = gl.create_buffer()?;
+        gl.bind_vertex_array(Some(vao));
+        gl.bind_buffer(glow::ARRAY_BUFFER, Some(vbo));
+        gl.enable_vertex_attrib_array(0);
+        gl.vertex_attrib_pointer_f32(0, 2, glow::FLOAT, false, VERTEX_SIZE, 0);
+        gl.enable_vertex_attrib_array(1);
+        gl.vertex_attrib_pointer_f32(1, 4, glow::UNSIGNED_BYTE, true, VERTEX_SIZE, 8);
+        gl.bind_vertex_array(None);
+
+        let view_location = gl.get_uniform_location(program, "u_view");
+        let offset_location = gl.get_uniform_location(program, "u_offset");
+        let opacity_location = gl.get_uniform_location(program, "u_opacity");
+        Ok(Self { program, view_location, offset_location, opacity_location, vao, vbo, ibo })
+    }
+
+    unsafe fn delete(self, gl: &glow::Context) {
+        gl.delete_program(self.program);
+        gl.delete_vertex_array(self.vao);
+        gl.delete_buffer(self.vbo);
+        gl.delete_buffer(self.ibo);
+    }
+}
@@ -0,0 +1,393 @@
+use std::error::Error;
+use std::io::Write;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use vectorlab_core::{gcode_job, Document, GcodeSettings, GrblLimits, GrblStatus, Unit};
+
+use crate::job::{job_progress, JobProgress, RunningJob};
+use crate::serial::{port_field, serial_ports, SerialPort};
+
+/// How often the controller is asked for its status.
+const STATUS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long to wait for the greeting after opening the port, Arduinos reset when it opens.
+const GREETING_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Lines kept in the console.
+const CONSOLE_LINES: usize = 1000;
+
+/// Ctrl+X, GRBL's soft reset.
+const SOFT_RESET: u8 = 0x18;
+
+/// What the window asks the connection thread to do.
+enum Request {
+    Line(String),
+    /// a real-time command like `!` for feed hold, which GRBL acts on right away
+    Realtime(u8),
+    Job(Vec<String>, Arc<JobProgress>),
+}
+
+/// What the connection thread reports.
+enum Event {
+    Console(String),
+    Status(GrblStatus),
+    JobDone(Result<(), String>),
+    Closed(String),
+}
+
+/// The connection thread's end of the serial port.
+struct Controller {
+    port: SerialPort,
+    events: Sender<Event>,
+    last_poll: Instant,
+    /// of the last status report
+    state: String,
+}
+
+impl Controller {
+    fn console(&self, line: String) {
+        let _ = self.events.send(Event::Console(line));
+    }
+
+    /// The next reply other than a status report, None if nothing came in for a while. Asks
+    /// for the status every now and then.
+    fn receive(&mut self) -> Result<Option<String>, Box<dyn Error>> {
+        if self.last_poll.elapsed() > STATUS_INTERVAL {
+            self.port.write_all(b"?")?;
+            self.last_poll = Instant::now();
+        }
+        let Some(line) = self.port.read_line()? else { return Ok(None) };
+        let line = line.trim();
+        if let Some(status) = GrblStatus::parse(line) {
+            self.state = status.state.clone();
+            let _ = self.events.send(Event::Status(status));
+            return Ok(None);
+        }
+        Ok((!line.is_empty()).then(|| line.to_string()))
+    }
+
+    /// Wait for GRBL's greeting, resetting it if it does not come by itself.
+    fn greet(&mut self) -> Result<(), Box<dyn Error>> {
+        for reset in [false, true] {
+            if reset {
+                self.port.write_all(&[SOFT_RESET])?;
+            }
+            let start = Instant::now();
+            while start.elapsed() < GREETING_TIMEOUT {
+                if let Some(line) = self.port.read_line()? {
+                    let greeting = line.starts_with("Grbl");
+                    self.console(line);
+                    if greeting {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+        Err("no GRBL controller answers".into())
+    }
+
+    /// Feed hold, then reset, which keeps the position and switches off spindle and laser.
+    fn stop(&mut self) -> Result<(), Box<dyn Error>> {
+        self.port.write_all(b"!")?;
+        thread::sleep(Duration::from_millis(300));
+        self.port.write_all(&[SOFT_RESET])?;
+        Ok(())
+    }
+
+    /// Hold the feed while paused, and stop when aborted.
+    fn follow(&mut self, progress: &JobProgress, held: &mut bool) -> Result<(), Box<dyn Error>> {
+        if progress.aborted() {
+            self.stop()?;
+            return Err("aborted".into());
+        }
+        if progress.paused() != *held {
+            *held = !*held;
+            self.port.write_all(if *held { b"!" } else { b"~" })?;
+        }
+        Ok(())
+    }
+
+    /// Send the lines one at a time, each once GRBL has taken the one before, and wait for the
+    /// machine to finish.
+    fn stream(&mut self, lines: &[String], progress: &JobProgress) -> Result<(), Box<dyn Error>> {
+        let mut held = false;
+        for (i, line) in lines.iter().enumerate() {
+            loop {
+                self.follow(progress, &mut held)?;
+                if !held {
+                    break;
+                }
+                self.receive()?;
+            }
+            self.port.write_all(format!("{}\n", line).as_bytes())?;
+            loop {
+                self.follow(progress, &mut held)?;
+                let Some(reply) = self.receive()? else { continue };
+                if reply == "ok" {
+                    break;
+                }
+                if reply.starts_with("error") || reply.starts_with("ALARM") {
+                    self.stop()?;
+                    return Err(format!("{}: {}", line, reply).into());
+                }
+                self.console(reply);
+            }
+            progress.done.store(i + 1, Ordering::Relaxed);
+        }
+        // GRBL has the last moves in its buffer still
+        self.state.clear();
+        while self.state != "Idle" {
+            self.follow(progress, &mut held)?;
+            if let Some(reply) = self.receive()? {
+                self.console(reply);
+            }
+        }
+        Ok(())
+    }
+
+    fn run(&mut self, requests: Receiver<Request>) -> Result<(), Box<dyn Error>> {
+        self.greet()?;
+        self.port.write_all(b"$$\n")?;
+        loop {
+            match requests.try_recv() {
+                Ok(Request::Line(line)) => {
+                    self.console(format!("> {}", line));
+                    self.port.write_all(format!("{}\n", line).as_bytes())?;
+                }
+                Ok(Request::Realtime(byte)) => self.port.write_all(&[byte])?,
+                Ok(Request::Job(lines, progress)) => {
+                    let result = self.stream(&lines, &progress).map_err(|e| e.to_string());
+                    let _ = self.events.send(Event::JobDone(result));
+                }
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => return Ok(()),
+            }
+            if let Some(reply) = self.receive()? {
+                self.console(reply);
+            }
+        }
+    }
+}
+
+/// A GRBL controller talked to on a thread of its own, closed when dropped.
+struct Connection {
+    requests: Sender<Request>,
+    events: Receiver<Event>,
+}
+
+impl Connection {
+    fn open(port: String) -> Self {
+        let (requests, receiver) = mpsc::channel();
+        let (sender, events) = mpsc::channel();
+        thread::spawn(move || {
+            let result = SerialPort::open(&port).and_then(|serial| {
+                let mut controller = Controller { port: serial, events: sender.clone(), last_poll: Instant::now(), state: String::new() };
+                controller.run(receiver)
+            });
+            let reason = match result {
+                Ok(()) => "Disconnected".to_string(),
+                Err(e) => e.to_string(),
+            };
+            let _ = sender.send(Event::Closed(reason));
+        });
+        Self { requests, events }
+    }
+
+    fn send(&self, request: Request) {
+        let _ = self.requests.send(request);
+    }
+}
+
+/// The File > Stream to GRBL window: a connection to a GRBL controller, its console, and
+/// the job it runs.
+pub struct GrblSender {
+    port: String,
+    connection: Option<Connection>,
+    console: Vec<String>,
+    input: String,
+    status: Option<GrblStatus>,
+    /// work coordinate offset as last reported
+    offset: Option<[f32; 3]>,
+    limits: GrblLimits,
+    pub running: Option<RunningJob>,
+    /// how the last job or the connection went
+    message: Option<String>,
+}
+
+impl GrblSender {
+    pub fn new(port: &str) -> Self {
+        let port = if port.is_empty() { serial_ports().into_iter().next().unwrap_or_default() } else { port.to_string() };
+        Self { port, connection: None, console: Vec::new(), input: String::new(), status: None, offset: None, limits: GrblLimits::default(), running: None, message: None }
+    }
+
+    pub fn connected(&self) -> bool {
+        self.connection.is_some()
+    }
+
+    /// Take in what the connection thread reported since the last frame.
+    fn poll(&mut self) {
+        while let Some(event) = self.connection.as_ref().and_then(|c| c.events.try_recv().ok()) {
+            match event {
+                Event::Console(line) => {
+                    self.limits.parse_setting(&line);
+                    self.console.push(line);
+                    if self.console.len() > CONSOLE_LINES {
+                        self.console.drain(..self.console.len() - CONSOLE_LINES);
+                    }
+                }
+                Event::Status(status) => {
+                    self.offset = status.offset.or(self.offset);
+                    self.status = Some(status);
+                }
+                Event::JobDone(result) => {
+                    self.message = Some(match result {
+                        Ok(()) => "Done".to_string(),
+                        Err(e) => format!("Stopped: {}", e),
+                    });
+                    self.running = None;
+                }
+                Event::Closed(reason) => {
+                    self.message = Some(reason);
+                    self.connection = None;
+                    self.running = None;
+                    self.status = None;
+                }
+            }
+        }
+    }
+
+    /// Check the job against the work area and hand it to the controller.
+    fn start(&mut self, doc: &Document, gcode: &GcodeSettings) {
+        let Some(connection) = &self.connection else { return };
+        let job = gcode_job(doc, gcode);
+        if job.contours.is_empty() {
+            self.message = Some("Nothing to cut".to_string());
+            return;
+        }
+        match self.limits.exceeded_axes(&job, self.offset.unwrap_or_default()) {
+            Some(axes) if !axes.is_empty() => {
+                let axes: Vec<String> = axes.iter().map(char::to_string).collect();
+                self.message = Some(format!("The job goes beyond the work area along {}", axes.join(" and ")));
+                return;
+            }
+            Some(_) => self.message = None,
+            None => self.message = Some("Work area unknown, the controller did not report $130 and $131".to_string()),
+        }
+        let running = RunningJob::new(job.contours, job.lines.iter().map(|l| (l.contour, l.duration)), doc.revision);
+        let lines = job.lines.into_iter().map(|l| l.text).collect();
+        connection.send(Request::Job(lines, running.progress.clone()));
+        self.running = Some(running);
+    }
+}
+
+/// Show the window while `sender` is Some, it stays open as long as a job runs. Returns the
+/// port when connecting, to keep it.
+pub fn grbl_window(ctx: &egui::Context, sender: &mut Option<GrblSender>, doc: Option<&Document>, gcode: &GcodeSettings, unit: Unit) -> Option<String> {
+    let state = sender.as_mut()?;
+    state.poll();
+
+    let mut open = true;
+    let mut connected = None;
+    let mut window = egui::Window::new("Stream to GRBL").collapsible(false).resizable(false);
+    if state.running.is_none() {
+        window = window.open(&mut open);
+    }
+    window.show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Port");
+            ui.add_enabled_ui(!state.connected(), |ui| port_field(ui, &mut state.port));
+            if state.connected() {
+                if ui.add_enabled(state.running.is_none(), egui::Button::new("Disconnect")).clicked() {
+                    state.connection = None;
+                    state.status = None;
+                }
+            } else if ui.add_enabled(!state.port.trim().is_empty(), egui::Button::new("Connect")).clicked() {
+                let port = state.port.trim().to_string();
+                state.console.clear();
+                state.limits = GrblLimits::default();
+                state.offset = None;
+                state.message = None;
+                state.connection = Some(Connection::open(port.clone()));
+                connected = Some(port);
+            }
+        });
+        let Some(connection) = &state.connection else {
+            if let Some(message) = &state.message {
+                ui.label(message);
+            }
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            let status = state.status.as_ref();
+            ui.strong(status.map_or("Connecting…", |s| s.state.as_str()));
+            if let Some([x, y, z]) = status.and_then(|s| s.work_position(state.offset)) {
+                ui.monospace(format!("X {}  Y {}  Z {}", unit.format(x), unit.format(y), unit.format(z)));
+            }
+        });
+        ui.add_enabled_ui(state.running.is_none(), |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Unlock").on_hover_text("$X, clear an alarm").clicked() {
+                    connection.send(Request::Line("$X".to_string()));
+                }
+                if ui.button("Home").on_hover_text("$H, run the homing cycle").clicked() {
+                    connection.send(Request::Line("$H".to_string()));
+                }
+                if ui.button("Hold").on_hover_text("Feed hold").clicked() {
+                    connection.send(Request::Realtime(b'!'));
+                }
+                if ui.button("Resume").on_hover_text("Continue after a feed hold").clicked() {
+                    connection.send(Request::Realtime(b'~'));
+                }
+                if ui.button("Reset").on_hover_text("Soft reset, stops everything").clicked() {
+                    connection.send(Request::Realtime(SOFT_RESET));
+                }
+            });
+        });
+        ui.separator();
+
+        if let Some(job) = &state.running {
+            job_progress(ui, job, "Feed hold until resumed, abort stops and resets the controller");
+        } else {
+            ui.weak("Cuts the document with the machine setup of Export G-code.");
+            let start = ui.add_enabled(doc.is_some(), egui::Button::new("Start")).on_hover_text("Checked against the work area from $130 and $131 first");
+            if let Some(message) = &state.message {
+                ui.label(message);
+            }
+            if start.clicked() {
+                if let Some(doc) = doc {
+                    state.start(doc, gcode);
+                }
+            }
+        }
+        ui.separator();
+
+        egui::CollapsingHeader::new("Console").default_open(true).show(ui, |ui| {
+            egui::ScrollArea::vertical().max_height(200.0).stick_to_bottom(true).auto_shrink([false, true]).show(ui, |ui| {
+                for line in &state.console {
+                    ui.monospace(line);
+                }
+            });
+            let Some(connection) = &state.connection else { return };
+            ui.add_enabled_ui(state.running.is_none(), |ui| {
+                ui.horizontal(|ui| {
+                    let field = ui.add(egui::TextEdit::singleline(&mut state.input).desired_width(260.0).font(egui::TextStyle::Monospace).hint_text("$$, G0 X10, …"));
+                    let entered = field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    if (ui.button("Send").clicked() || entered) && !state.input.trim().is_empty() {
+                        connection.send(Request::Line(state.input.trim().to_string()));
+                        state.input.clear();
+                        field.request_focus();
+                    }
+                });
+            });
+        });
+    });
+    if !open {
+        *sender = None;
+    }
+    connected
+}
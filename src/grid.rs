@@ -0,0 +1,102 @@
+use vectorlab_core::{Document, Unit, ViewTransform};
+
+/// Lines closer than this on screen are left out, the grid gets coarser instead.
+const MIN_SPACING: f32 = 8.0;
+
+/// Grid under the artwork, stored with the settings.
+#[derive(Clone, Debug)]
+pub struct Grid {
+    pub show: bool,
+    /// Interactive operations move to the nearest intersection of the visible lines
+    pub snapping: bool,
+    /// Distance of the major lines, in `unit` or in user units if None
+    pub spacing: f32,
+    pub unit: Option<Unit>,
+    /// Minor lines per major spacing, 1 for none
+    pub subdivisions: u32,
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Self { show: false, snapping: true, spacing: 10.0, unit: None, subdivisions: 5 }
+    }
+}
+
+impl Grid {
+    /// Distance of the lines drawn at `zoom` in user units, and every how many lines there
+    /// is a major one. Minor lines go first when they get too dense, then majors are doubled
+    /// until far enough apart, so they still fall on the configured spacing.
+    fn steps(&self, doc: &Document, zoom: f32) -> ([f32; 2], u32) {
+        let spacing = match self.unit {
+            Some(unit) => doc.mm_per_unit().map(|mm| unit.to_mm(self.spacing) / mm),
+            None => [self.spacing; 2],
+        };
+        let narrowest = spacing[0].min(spacing[1]) * zoom;
+        let subdivisions = self.subdivisions.max(1);
+        if narrowest / subdivisions as f32 >= MIN_SPACING {
+            return (spacing.map(|s| s / subdivisions as f32), subdivisions);
+        }
+        let mut factor = 1.0;
+        while narrowest * factor < MIN_SPACING {
+            factor *= 2.0;
+        }
+        (spacing.map(|s| s * factor), 1)
+    }
+
+    /// `p` moved to the closest grid intersection if snapping is on, unchanged otherwise.
+    pub fn snap(&self, p: [f32; 2], doc: &Document, zoom: f32) -> [f32; 2] {
+        if !self.show || !self.snapping || self.spacing <= 0.0 {
+            return p;
+        }
+        let (step, _) = self.steps(doc, zoom);
+        [(p[0] / step[0]).round() * step[0], (p[1] / step[1]).round() * step[1]]
+    }
+
+    pub fn draw(&self, painter: &egui::Painter, canvas: egui::Rect, doc: &Document, view: &ViewTransform) {
+        if !self.show || self.spacing <= 0.0 {
+            return;
+        }
+        let (step, every) = self.steps(doc, view.zoom);
+        let major = egui::Stroke::new(1.0, egui::Color32::from_gray(128).gamma_multiply(0.5));
+        let minor = egui::Stroke::new(1.0, egui::Color32::from_gray(128).gamma_multiply(0.2));
+        // in document coordinates, the view may be rotated
+        let visible = view.doc_bbox([0.0, 0.0, canvas.width(), canvas.height()]);
+        let to_screen = |p: [f32; 2]| egui::Pos2::from(view.to_screen(p)) + canvas.min.to_vec2();
+        for axis in [0, 1] {
+            // lines at x = k * step for axis 0, running along y
+            let other = 1 - axis;
+            let first = (visible[axis] / step[axis]).ceil() as i64;
+            let last = (visible[axis + 2] / step[axis]).floor() as i64;
+            for k in first..=last {
+                let stroke = if k.rem_euclid(every as i64) == 0 { major } else { minor };
+                let (mut a, mut b) = ([0.0; 2], [0.0; 2]);
+                a[axis] = k as f32 * step[axis];
+                b[axis] = a[axis];
+                a[other] = visible[other];
+                b[other] = visible[other + 2];
+                painter.line_segment([to_screen(a), to_screen(b)], stroke);
+            }
+        }
+    }
+
+    /// Settings for the View menu, true when they should be saved.
+    pub fn menu(&mut self, ui: &mut egui::Ui) -> bool {
+        // save once a value is dragged or typed in, not on every step
+        let done = |r: egui::Response| r.drag_stopped() || r.lost_focus();
+        let mut changed = ui.checkbox(&mut self.show, "Show grid").changed();
+        changed |= ui.checkbox(&mut self.snapping, "Snap to grid").changed();
+        ui.horizontal(|ui| {
+            ui.label("Spacing");
+            changed |= done(ui.add(egui::DragValue::new(&mut self.spacing).speed(0.1).range(0.01..=10000.0)));
+            changed |= ui.selectable_value(&mut self.unit, None, "user units").changed();
+            for unit in Unit::ALL {
+                changed |= ui.selectable_value(&mut self.unit, Some(unit), unit.name()).changed();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Subdivisions");
+            changed |= done(ui.add(egui::DragValue::new(&mut self.subdivisions).range(1..=20)));
+        });
+        changed
+    }
+}
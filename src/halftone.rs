@@ -0,0 +1,97 @@
+use vectorlab_core::{halftone, Color, Halftone, HalftonePattern, HalftoneSettings, RasterImage, Unit};
+
+use crate::units::length_value;
+
+/// Longest side of the preview, in points of the screen.
+const PREVIEW_SIZE: f32 = 360.0;
+
+/// The Halftone image window, for a PNG or JPEG picked with File > Halftone image.
+pub struct HalftoneImage {
+    /// of the file, for the ids of the layers the lines go into
+    name: String,
+    image: RasterImage,
+    draft: HalftoneSettings,
+    ink: egui::Color32,
+    /// of the drawing on the page, in mm
+    width: f32,
+    /// lines of the draft at the width
+    lines: Option<(HalftoneSettings, f32, Halftone)>,
+}
+
+impl HalftoneImage {
+    pub fn new(name: String, image: RasterImage, settings: &HalftoneSettings) -> Self {
+        Self { name, image, draft: settings.clone(), ink: egui::Color32::BLACK, width: 150.0, lines: None }
+    }
+}
+
+/// Show the window while `state` is Some, with the lines as they will be drawn. Returns the
+/// settings used and the lines as SVG layers when Insert is pressed.
+pub fn halftone_window(ctx: &egui::Context, state: &mut Option<HalftoneImage>, unit: Unit) -> Option<(HalftoneSettings, String)> {
+    let window = state.as_mut()?;
+    if !window.lines.as_ref().is_some_and(|(settings, width, _)| *settings == window.draft && *width == window.width) {
+        window.lines = Some((window.draft.clone(), window.width, halftone(&window.image, window.width, &window.draft)));
+    }
+    let mut open = true;
+    let mut done = None;
+    egui::Window::new("Halftone image").open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+        let draft = &mut window.draft;
+        egui::Grid::new("halftone").num_columns(2).show(ui, |ui| {
+            ui.label("Pattern");
+            egui::ComboBox::from_id_source("halftone-pattern").selected_text(draft.pattern.label()).show_ui(ui, |ui| {
+                for pattern in HalftonePattern::ALL {
+                    ui.selectable_value(&mut draft.pattern, pattern, pattern.label());
+                }
+            });
+            ui.end_row();
+            ui.label("Line spacing");
+            ui.add(length_value(&mut draft.spacing, unit, 0.01, 0.2..=20.0)).on_hover_text("Also the length of the waves");
+            ui.end_row();
+            ui.label("Angle");
+            ui.add(egui::DragValue::new(&mut draft.angle).speed(1.0).range(-180.0..=180.0).suffix("°"));
+            ui.end_row();
+            ui.label("Amplitude");
+            ui.horizontal(|ui| {
+                ui.add(length_value(&mut draft.min_amplitude, unit, 0.01, 0.0..=10.0)).on_hover_text("Where the image is white");
+                ui.label("to");
+                ui.add(length_value(&mut draft.max_amplitude, unit, 0.01, 0.0..=10.0)).on_hover_text("Where the image is black, at most half the spacing");
+            });
+            ui.end_row();
+            ui.label("Pen width");
+            ui.add(length_value(&mut draft.pen_width, unit, 0.01, 0.01..=5.0));
+            ui.end_row();
+            ui.label("Width");
+            ui.add(length_value(&mut window.width, unit, 1.0, 1.0..=5000.0)).on_hover_text("Of the drawing on the page");
+            ui.end_row();
+            ui.label("Color");
+            ui.color_edit_button_srgba(&mut window.ink);
+            ui.end_row();
+        });
+
+        let Some((_, _, lines)) = &window.lines else { return };
+        let [w, h] = lines.size;
+        let scale = PREVIEW_SIZE / w.max(h).max(f32::EPSILON);
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(w, h) * scale, egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, egui::Color32::WHITE);
+        let stroke = egui::Stroke::new((window.draft.pen_width * scale).max(0.5), window.ink);
+        for line in lines.layers.iter().flat_map(|(_, lines)| lines) {
+            painter.add(egui::Shape::line(line.iter().map(|p| rect.min + egui::vec2(p[0], p[1]) * scale).collect(), stroke));
+        }
+        let count: usize = lines.layers.iter().map(|(_, lines)| lines.len()).sum();
+        ui.label(format!("{} lines in {} layers, {} × {}", count, lines.layers.len(), unit.format(w), unit.format(h)));
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.add_enabled(count > 0, egui::Button::new("Insert")).on_hover_text("As new layers on top, at the top left of the page").clicked() {
+                let [r, g, b, a] = window.ink.to_srgba_unmultiplied();
+                done = Some(Some((window.draft.clone(), lines.svg(&window.name, Color::rgba(r, g, b, a), &window.draft))));
+            }
+            if ui.button("Cancel").clicked() {
+                done = Some(None);
+            }
+        });
+    });
+    if !open || done.is_some() {
+        *state = None;
+    }
+    done.flatten()
+}
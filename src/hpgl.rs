@@ -0,0 +1,223 @@
+use std::error::Error;
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+use vectorlab_core::{hpgl_job, Document, HpglJob, HpglSettings, Unit};
+
+use crate::job::{job_progress, JobProgress, RunningJob};
+use crate::serial::{port_field, FlowControl, LineSettings, SerialPort};
+use crate::units::length_value;
+
+/// The raw printing port of HP JetDirect boxes and most network printers.
+pub const JETDIRECT_PORT: u16 = 9100;
+
+/// Baud rates of classic plotters.
+const BAUD_RATES: [u32; 6] = [1200, 2400, 4800, 9600, 19200, 38400];
+
+/// Where HPGL goes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HpglOutput {
+    /// over the network instead of the serial port
+    pub network: bool,
+    pub port: String,
+    pub line: LineSettings,
+    pub host: String,
+    pub tcp_port: u16,
+}
+
+impl Default for HpglOutput {
+    fn default() -> Self {
+        Self { network: false, port: String::new(), line: LineSettings { baud: Some(9600), flow: FlowControl::Software }, host: String::new(), tcp_port: JETDIRECT_PORT }
+    }
+}
+
+/// Open the serial port or the connection to the plotter.
+fn connect(output: &HpglOutput) -> Result<Box<dyn Write>, Box<dyn Error>> {
+    if output.network {
+        let host = output.host.trim();
+        let address = (host, output.tcp_port).to_socket_addrs()?.next().ok_or_else(|| format!("{} not found", host))?;
+        let stream = TcpStream::connect_timeout(&address, Duration::from_secs(5)).map_err(|e| format!("{}: {}", address, e))?;
+        Ok(Box::new(stream))
+    } else {
+        Ok(Box::new(SerialPort::open_with(output.port.trim(), output.line)?))
+    }
+}
+
+/// Send the commands in order. The plotter or the serial line make the writes wait while its
+/// buffer is full. Pausing lifts the pen, aborting lifts it and puts it away.
+pub fn send_job(output: &HpglOutput, job: &HpglJob, progress: &JobProgress) -> Result<(), Box<dyn Error>> {
+    let mut plotter = connect(output)?;
+    for (i, command) in job.commands.iter().enumerate() {
+        if progress.paused() {
+            plotter.write_all(b"PU;\n")?;
+            plotter.flush()?;
+            // the next PD lowers it again
+            while progress.paused() && !progress.aborted() {
+                thread::sleep(Duration::from_millis(50));
+            }
+        }
+        if progress.aborted() {
+            plotter.write_all(b"PU;\nSP0;\n")?;
+            plotter.flush()?;
+            return Err("aborted".into());
+        }
+        plotter.write_all(command.text.as_bytes())?;
+        plotter.write_all(b"\n")?;
+        plotter.flush()?;
+        progress.done.store(i + 1, Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+/// A job being sent on a worker thread.
+pub struct RunningHpgl {
+    pub job: RunningJob,
+    result: Receiver<Result<(), String>>,
+}
+
+impl RunningHpgl {
+    fn start(output: HpglOutput, hpgl: HpglJob, revision: u64) -> Self {
+        let job = RunningJob::new(hpgl.contours.clone(), hpgl.commands.iter().map(|c| (c.contour, c.duration)), revision);
+        let (sender, result) = mpsc::channel();
+        let progress = job.progress.clone();
+        thread::spawn(move || {
+            let _ = sender.send(send_job(&output, &hpgl, &progress).map_err(|e| e.to_string()));
+        });
+        Self { job, result }
+    }
+
+    fn poll(&self) -> Option<Result<(), String>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err("plotter thread crashed".to_string())),
+        }
+    }
+}
+
+/// What the HPGL window asks of the app.
+pub enum HpglAction {
+    /// a job was started, keep the settings
+    Sent(HpglOutput, HpglSettings),
+    /// write a .plt file instead
+    Save(HpglSettings),
+}
+
+/// The File > Send to HPGL plotter window, on copies of the settings.
+pub struct HpglSender {
+    output: HpglOutput,
+    draft: HpglSettings,
+    pub running: Option<RunningHpgl>,
+    /// how the last job went
+    message: Option<String>,
+}
+
+impl HpglSender {
+    pub fn new(output: &HpglOutput, settings: &HpglSettings) -> Self {
+        Self { output: output.clone(), draft: settings.clone(), running: None, message: None }
+    }
+}
+
+/// Show the window while `sender` is Some, it stays open as long as a job runs.
+pub fn hpgl_window(ctx: &egui::Context, sender: &mut Option<HpglSender>, doc: Option<&Document>, unit: Unit) -> Option<HpglAction> {
+    let state = sender.as_mut()?;
+    if let Some(result) = state.running.as_ref().and_then(RunningHpgl::poll) {
+        state.message = Some(match result {
+            Ok(()) => "Sent".to_string(),
+            Err(e) => format!("Stopped: {}", e),
+        });
+        state.running = None;
+    }
+
+    let mut open = true;
+    let mut action = None;
+    let mut window = egui::Window::new("Send to HPGL plotter").collapsible(false).resizable(false);
+    if state.running.is_none() {
+        window = window.open(&mut open);
+    }
+    window.show(ctx, |ui| {
+        if let Some(hpgl) = &state.running {
+            job_progress(ui, &hpgl.job, "Lifts the pen once the plotter has drawn what it has been sent");
+            return;
+        }
+
+        let (output, draft) = (&mut state.output, &mut state.draft);
+        egui::Grid::new("hpgl").num_columns(2).show(ui, |ui| {
+            ui.label("Connection");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut output.network, false, "Serial");
+                ui.radio_value(&mut output.network, true, "Network");
+            });
+            ui.end_row();
+            if output.network {
+                ui.label("Host");
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut output.host).desired_width(160.0).hint_text("192.168.1.20"));
+                    ui.add(egui::DragValue::new(&mut output.tcp_port).prefix("port "));
+                });
+                ui.end_row();
+            } else {
+                ui.label("Port");
+                port_field(ui, &mut output.port);
+                ui.end_row();
+                ui.label("Baud rate");
+                egui::ComboBox::from_id_source("hpgl_baud").selected_text(output.line.baud.map_or("as set".to_string(), |b| b.to_string())).show_ui(ui, |ui| {
+                    ui.selectable_value(&mut output.line.baud, None, "as set");
+                    for baud in BAUD_RATES {
+                        ui.selectable_value(&mut output.line.baud, Some(baud), baud.to_string());
+                    }
+                });
+                ui.end_row();
+                ui.label("Flow control");
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut output.line.flow, FlowControl::Software, "XON/XOFF");
+                    ui.radio_value(&mut output.line.flow, FlowControl::Hardware, "RTS/CTS");
+                    ui.radio_value(&mut output.line.flow, FlowControl::None, "None");
+                });
+                ui.end_row();
+            }
+            ui.label("Pens");
+            ui.add(egui::DragValue::new(&mut draft.pens).range(1..=8)).on_hover_text("With more than one, each color goes to the pen closest to it");
+            ui.end_row();
+            ui.label("Velocity");
+            ui.add(egui::DragValue::new(&mut draft.velocity).speed(0.5).range(0.0..=100.0).custom_formatter(|v, _| if v == 0.0 { "plotter's".to_string() } else { format!("{} cm/s", v) }));
+            ui.end_row();
+            ui.label("Curve tolerance");
+            ui.add(length_value(&mut draft.tolerance, unit, 0.001, 0.001..=10.0));
+            ui.end_row();
+            ui.label("");
+            ui.checkbox(&mut draft.optimize, "Optimize").on_hover_text("Reorder and reverse the paths of each pen so it travels less");
+            ui.end_row();
+        });
+        ui.weak("The bottom left corner of the page goes to the plotter's origin.");
+        if let Some(message) = &state.message {
+            ui.label(message);
+        }
+        ui.separator();
+        let Some(doc) = doc else { return };
+        ui.horizontal(|ui| {
+            let ready = if output.network { !output.host.trim().is_empty() } else { !output.port.trim().is_empty() };
+            if ui.add_enabled(ready, egui::Button::new("Send")).clicked() {
+                let job = hpgl_job(doc, draft);
+                if job.contours.is_empty() {
+                    state.message = Some("Nothing to plot".to_string());
+                } else {
+                    state.message = None;
+                    state.running = Some(RunningHpgl::start(output.clone(), job, doc.revision));
+                    action = Some(HpglAction::Sent(output.clone(), draft.clone()));
+                }
+            }
+            if ui.button("Save as .plt…").clicked() {
+                action = Some(HpglAction::Save(draft.clone()));
+            }
+        });
+    });
+    if !open {
+        *sender = None;
+    }
+    action
+}
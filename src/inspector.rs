@@ -0,0 +1,153 @@
+use vectorlab_core::{Color, Document, EditCommand, Element, ElementId, Paint, SetOpacity, SetStyle, SetTransform, Unit};
+
+use crate::transform::selection_fields;
+
+/// Editable properties of the selected elements. Widgets work on copies, changes come back
+/// as commands for the undo history.
+pub fn inspector_panel(ui: &mut egui::Ui, doc: &Document, selection: &[ElementId], unit: Unit) -> Vec<Box<dyn EditCommand>> {
+    let mut edits: Vec<Box<dyn EditCommand>> = vec![];
+    let [mx, my] = doc.mm_per_unit();
+    let mm = (mx * my).sqrt();
+    egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+        if let Some(edit) = selection_fields(ui, doc, selection, unit) {
+            edits.push(edit);
+        }
+        ui.separator();
+        for &id in selection {
+            let element = doc.get(id);
+            ui.strong(element_label(element));
+            egui::Grid::new(id).num_columns(2).show(ui, |ui| {
+                ui.label("opacity");
+                let mut opacity = element.opacity;
+                if ui.add(egui::Slider::new(&mut opacity, 0.0..=1.0)).changed() {
+                    edits.push(Box::new(SetOpacity { id, old: element.opacity, new: opacity }));
+                }
+                ui.end_row();
+
+                ui.label("transform");
+                let mut ts = element.transform;
+                ui.vertical(|ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut ts.sx).speed(0.01));
+                        ui.add(egui::DragValue::new(&mut ts.kx).speed(0.01));
+                        ui.add(egui::DragValue::new(&mut ts.tx));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut ts.ky).speed(0.01));
+                        ui.add(egui::DragValue::new(&mut ts.sy).speed(0.01));
+                        ui.add(egui::DragValue::new(&mut ts.ty));
+                    });
+                });
+                if ts != element.transform {
+                    edits.push(Box::new(SetTransform { id, old: element.transform, new: ts }));
+                }
+                ui.end_row();
+
+                if let Some(b) = doc.element_bbox(id) {
+                    ui.label("bbox");
+                    ui.label(format!("{:.1}, {:.1} – {:.1}, {:.1}", b[0], b[1], b[2], b[3]));
+                    ui.end_row();
+                }
+
+                if let Some(path) = element.as_path() {
+                    ui.label("points");
+                    ui.label(path.point_count().to_string());
+                    ui.end_row();
+                    let length = doc.pen_down_length(id);
+                    ui.label("length");
+                    ui.label(format!("{:.2} ({})", length, unit.format(length * mm)));
+                    ui.end_row();
+                    if let Some(area) = doc.path_area(id) {
+                        ui.label("area");
+                        ui.label(format!("{:.2} ({:.*} {}²)", area, unit.decimals(), area * (mm / unit.mm()).powi(2), unit.name())).on_hover_text("Negative for counterclockwise paths");
+                        ui.end_row();
+                    }
+
+                    let mut style = path.style.clone();
+                    let mut changed = false;
+                    ui.label("fill");
+                    changed |= paint_editor(ui, &mut style.fill);
+                    ui.end_row();
+
+                    ui.label("stroke");
+                    if paint_editor(ui, &mut style.stroke) {
+                        changed = true;
+                        if style.stroke_width == 0.0 {
+                            style.stroke_width = 1.0;
+                        }
+                    }
+                    ui.end_row();
+                    if style.stroke.is_some() {
+                        ui.label("stroke width");
+                        changed |= ui.add(egui::DragValue::new(&mut style.stroke_width).speed(0.1).range(0.0..=f32::MAX)).changed();
+                        ui.end_row();
+                    }
+                    if changed {
+                        edits.push(Box::new(SetStyle { id, old: path.style.clone(), new: style }));
+                    }
+                } else {
+                    ui.label("children");
+                    ui.label(element.children.len().to_string());
+                    ui.end_row();
+                    let length = doc.pen_down_length(id);
+                    if length > 0.0 {
+                        ui.label("path length");
+                        ui.label(format!("{:.2} ({})", length, unit.format(length * mm))).on_hover_text("Total of all paths inside");
+                        ui.end_row();
+                    }
+                }
+            });
+            ui.separator();
+        }
+    });
+    edits
+}
+
+/// Checkbox to switch the paint on and off, plus a color button for solid colors.
+/// Returns true if anything changed.
+pub fn paint_editor(ui: &mut egui::Ui, paint: &mut Option<Paint>) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        let mut enabled = paint.is_some();
+        if ui.checkbox(&mut enabled, "").changed() {
+            *paint = enabled.then_some(Paint::Solid(Color::BLACK));
+            changed = true;
+        }
+        if let Some(Paint::Solid(c)) = paint {
+            let mut rgba = [c.r, c.g, c.b, c.a];
+            if ui.color_edit_button_srgba_unmultiplied(&mut rgba).changed() {
+                *c = Color::rgba(rgba[0], rgba[1], rgba[2], rgba[3]);
+                changed = true;
+            }
+        }
+        ui.label(paint_label(paint));
+    });
+    changed
+}
+
+/// `<tag> #id`, or just `<tag>` for elements without an id.
+pub fn element_label(element: &Element) -> String {
+    if element.source_id.is_empty() {
+        format!("<{}>", element.tag())
+    } else {
+        format!("<{}> #{}", element.tag(), element.source_id)
+    }
+}
+
+/// Tooltip for the path under the mouse.
+pub fn hover_tooltip(ui: &mut egui::Ui, doc: &Document, id: ElementId) {
+    ui.strong(element_label(doc.get(id)));
+    if let Some(b) = doc.element_bbox(id) {
+        ui.label(format!("{:.1}, {:.1} – {:.1}, {:.1}", b[0], b[1], b[2], b[3]));
+        ui.label(format!("{:.1} × {:.1}", b[2] - b[0], b[3] - b[1]));
+    }
+}
+
+pub fn paint_label(paint: &Option<Paint>) -> String {
+    match paint {
+        None => "none".to_string(),
+        Some(Paint::Solid(c)) if c.a == 255 => format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b),
+        Some(Paint::Solid(c)) => format!("#{:02x}{:02x}{:02x}{:02x}", c.r, c.g, c.b, c.a),
+        Some(Paint::Gradient(_)) => "gradient".to_string(),
+    }
+}
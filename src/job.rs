@@ -0,0 +1,124 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use vectorlab_core::{Contour, ViewTransform};
+
+/// Shared between the window and the thread sending a job to a machine.
+#[derive(Default)]
+pub struct JobProgress {
+    /// steps sent so far
+    pub done: AtomicUsize,
+    pub paused: AtomicBool,
+    pub aborted: AtomicBool,
+}
+
+impl JobProgress {
+    pub fn paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn aborted(&self) -> bool {
+        self.aborted.load(Ordering::Relaxed)
+    }
+}
+
+/// A job some machine is busy with, for showing how far it got.
+pub struct RunningJob {
+    /// in document coordinates, in the order they are drawn
+    pub contours: Vec<Contour>,
+    /// revision of the document it was made from, progress is only drawn over that one
+    pub revision: u64,
+    pub progress: Arc<JobProgress>,
+    /// the contour each step draws
+    step_contours: Vec<Option<usize>>,
+    /// time in ms until each step is done, from the start
+    elapsed: Vec<u64>,
+}
+
+impl RunningJob {
+    /// `steps` are the contour and duration in ms of each step.
+    pub fn new(contours: Vec<Contour>, steps: impl Iterator<Item = (Option<usize>, u32)>, revision: u64) -> Self {
+        let (step_contours, durations): (Vec<_>, Vec<_>) = steps.unzip();
+        let elapsed = durations
+            .into_iter()
+            .scan(0, |time, duration: u32| {
+                *time += duration as u64;
+                Some(*time)
+            })
+            .collect();
+        Self { contours, revision, progress: Arc::default(), step_contours, elapsed }
+    }
+
+    fn done(&self) -> usize {
+        self.progress.done.load(Ordering::Relaxed).min(self.step_contours.len())
+    }
+
+    /// The contour being drawn, None while travelling.
+    pub fn current_contour(&self) -> Option<usize> {
+        self.step_contours.get(self.done()).copied().flatten()
+    }
+
+    /// Contours before this one are done.
+    pub fn finished_contours(&self) -> usize {
+        let done = self.done();
+        self.current_contour().unwrap_or_else(|| self.step_contours[..done].iter().rev().find_map(|&c| c).map_or(0, |i| i + 1))
+    }
+
+    /// Fraction of the time behind, and the ms left.
+    fn completion(&self) -> (f32, u64) {
+        let total = self.elapsed.last().copied().unwrap_or(0);
+        let behind = self.done().checked_sub(1).map_or(0, |i| self.elapsed[i]);
+        (if total == 0 { 0.0 } else { behind as f32 / total as f32 }, total - behind)
+    }
+}
+
+/// "m:ss", or "h:mm:ss" from an hour on.
+pub fn format_duration(ms: u64) -> String {
+    let s = ms.div_ceil(1000);
+    if s >= 3600 {
+        format!("{}:{:02}:{:02}", s / 3600, s / 60 % 60, s % 60)
+    } else {
+        format!("{}:{:02}", s / 60, s % 60)
+    }
+}
+
+/// Progress bar, path count and time left, with buttons to pause and abort. `pause_hint`
+/// says what pausing does on this machine.
+pub fn job_progress(ui: &mut egui::Ui, job: &RunningJob, pause_hint: &str) {
+    let (fraction, left) = job.completion();
+    ui.add(egui::ProgressBar::new(fraction).show_percentage().desired_width(260.0));
+    let paths = job.contours.len();
+    ui.label(format!("Path {} of {}, {} left", (job.finished_contours() + 1).min(paths), paths, format_duration(left)));
+    ui.horizontal(|ui| {
+        let paused = job.progress.paused();
+        if ui.button(if paused { "Resume" } else { "Pause" }).on_hover_text(pause_hint).clicked() {
+            job.progress.paused.store(!paused, Ordering::Relaxed);
+        }
+        if ui.button("Abort").clicked() {
+            job.progress.aborted.store(true, Ordering::Relaxed);
+        }
+    });
+}
+
+/// The paths of a running job over the canvas, those done thin and the one being drawn
+/// thick.
+pub fn draw_job_progress(painter: &egui::Painter, canvas: egui::Rect, view: &ViewTransform, job: &RunningJob) {
+    let to_screen = |p: &[f32; 2]| egui::Pos2::from(view.to_screen(*p)) + canvas.min.to_vec2();
+    let finished = job.finished_contours();
+    let current = job.current_contour();
+    for (i, contour) in job.contours.iter().enumerate().take(finished + 1) {
+        let stroke = if current == Some(i) {
+            egui::Stroke::new(3.0, egui::Color32::from_rgb(255, 140, 0))
+        } else if i < finished {
+            egui::Stroke::new(1.5, egui::Color32::from_rgb(0, 200, 120))
+        } else {
+            continue;
+        };
+        let points = contour.points.iter().map(to_screen).collect();
+        if contour.closed {
+            painter.add(egui::Shape::closed_line(points, stroke));
+        } else {
+            painter.add(egui::Shape::line(points, stroke));
+        }
+    }
+}
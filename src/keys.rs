@@ -0,0 +1,244 @@
+use egui::{Key, KeyboardShortcut, Modifiers};
+
+/// Everything that has a keyboard shortcut. The names are the keys in the settings file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Open,
+    Save,
+    SaveAs,
+    CloseTab,
+    NextTab,
+    PreviousTab,
+    NextFile,
+    PreviousFile,
+    Undo,
+    Redo,
+    Find,
+    Delete,
+    Duplicate,
+    Fit,
+    ActualSize,
+    PhysicalSize,
+    RotateRight,
+    RotateLeft,
+    FlipHorizontal,
+    FlipVertical,
+    Measure,
+    EditNodes,
+    Transform,
+    DrawShapes,
+    Pencil,
+    Text,
+    Fullscreen,
+    Presentation,
+    Preferences,
+}
+
+impl Action {
+    pub const ALL: [Action; 29] = [
+        Action::Open,
+        Action::Save,
+        Action::SaveAs,
+        Action::CloseTab,
+        Action::NextTab,
+        Action::PreviousTab,
+        Action::NextFile,
+        Action::PreviousFile,
+        Action::Undo,
+        Action::Redo,
+        Action::Find,
+        Action::Delete,
+        Action::Duplicate,
+        Action::Fit,
+        Action::ActualSize,
+        Action::PhysicalSize,
+        Action::RotateRight,
+        Action::RotateLeft,
+        Action::FlipHorizontal,
+        Action::FlipVertical,
+        Action::Measure,
+        Action::EditNodes,
+        Action::Transform,
+        Action::DrawShapes,
+        Action::Pencil,
+        Action::Text,
+        Action::Fullscreen,
+        Action::Presentation,
+        Action::Preferences,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Open => "open",
+            Action::Save => "save",
+            Action::SaveAs => "save_as",
+            Action::CloseTab => "close_tab",
+            Action::NextTab => "next_tab",
+            Action::PreviousTab => "previous_tab",
+            Action::NextFile => "next_file",
+            Action::PreviousFile => "previous_file",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::Find => "find",
+            Action::Delete => "delete",
+            Action::Duplicate => "duplicate",
+            Action::Fit => "fit",
+            Action::ActualSize => "actual_size",
+            Action::PhysicalSize => "physical_size",
+            Action::RotateRight => "rotate_right",
+            Action::RotateLeft => "rotate_left",
+            Action::FlipHorizontal => "flip_horizontal",
+            Action::FlipVertical => "flip_vertical",
+            Action::Measure => "measure",
+            Action::EditNodes => "edit_nodes",
+            Action::Transform => "transform",
+            Action::DrawShapes => "draw_shapes",
+            Action::Pencil => "pencil",
+            Action::Text => "text",
+            Action::Fullscreen => "fullscreen",
+            Action::Presentation => "presentation",
+            Action::Preferences => "preferences",
+        }
+    }
+
+    /// For the Preferences dialog.
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::Open => "Open file",
+            Action::Save => "Save",
+            Action::SaveAs => "Save as",
+            Action::CloseTab => "Close tab",
+            Action::NextTab => "Next tab",
+            Action::PreviousTab => "Previous tab",
+            Action::NextFile => "Next file in directory",
+            Action::PreviousFile => "Previous file in directory",
+            Action::Undo => "Undo",
+            Action::Redo => "Redo",
+            Action::Find => "Find",
+            Action::Delete => "Delete selection",
+            Action::Duplicate => "Duplicate selection",
+            Action::Fit => "Zoom to fit",
+            Action::ActualSize => "Actual size",
+            Action::PhysicalSize => "Real size on screen",
+            Action::RotateRight => "Rotate view clockwise",
+            Action::RotateLeft => "Rotate view counterclockwise",
+            Action::FlipHorizontal => "Flip view horizontally",
+            Action::FlipVertical => "Flip view vertically",
+            Action::Measure => "Measure tool",
+            Action::EditNodes => "Node tool",
+            Action::Transform => "Transform tool",
+            Action::DrawShapes => "Shape tool",
+            Action::Pencil => "Pencil tool",
+            Action::Text => "Text tool",
+            Action::Fullscreen => "Fullscreen",
+            Action::Presentation => "Presentation mode",
+            Action::Preferences => "Preferences",
+        }
+    }
+
+    fn default_shortcut(self) -> KeyboardShortcut {
+        let (modifiers, key) = match self {
+            Action::Open => (Modifiers::NONE, Key::O),
+            Action::Save => (Modifiers::COMMAND, Key::S),
+            Action::SaveAs => (Modifiers::COMMAND | Modifiers::SHIFT, Key::S),
+            Action::CloseTab => (Modifiers::COMMAND, Key::W),
+            Action::NextTab => (Modifiers::COMMAND, Key::Tab),
+            Action::PreviousTab => (Modifiers::COMMAND | Modifiers::SHIFT, Key::Tab),
+            Action::NextFile => (Modifiers::NONE, Key::PageDown),
+            Action::PreviousFile => (Modifiers::NONE, Key::PageUp),
+            Action::Undo => (Modifiers::COMMAND, Key::Z),
+            Action::Redo => (Modifiers::COMMAND | Modifiers::SHIFT, Key::Z),
+            Action::Find => (Modifiers::COMMAND, Key::F),
+            Action::Delete => (Modifiers::NONE, Key::Delete),
+            Action::Duplicate => (Modifiers::COMMAND, Key::D),
+            Action::Fit => (Modifiers::NONE, Key::F),
+            Action::ActualSize => (Modifiers::NONE, Key::Num1),
+            Action::PhysicalSize => (Modifiers::COMMAND, Key::Num1),
+            Action::RotateRight => (Modifiers::NONE, Key::R),
+            Action::RotateLeft => (Modifiers::SHIFT, Key::R),
+            Action::FlipHorizontal => (Modifiers::NONE, Key::H),
+            Action::FlipVertical => (Modifiers::NONE, Key::V),
+            Action::Measure => (Modifiers::NONE, Key::M),
+            Action::EditNodes => (Modifiers::NONE, Key::N),
+            Action::Transform => (Modifiers::NONE, Key::T),
+            Action::DrawShapes => (Modifiers::NONE, Key::S),
+            Action::Pencil => (Modifiers::NONE, Key::P),
+            Action::Text => (Modifiers::SHIFT, Key::T),
+            Action::Fullscreen => (Modifiers::NONE, Key::F11),
+            Action::Presentation => (Modifiers::NONE, Key::F5),
+            Action::Preferences => (Modifiers::COMMAND, Key::Comma),
+        };
+        KeyboardShortcut::new(modifiers, key)
+    }
+}
+
+/// The shortcut of each action, in the order of `Action::ALL`.
+#[derive(Clone, Debug)]
+pub struct Keybindings {
+    shortcuts: Vec<KeyboardShortcut>,
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Self { shortcuts: Action::ALL.iter().map(|a| a.default_shortcut()).collect() }
+    }
+}
+
+impl Keybindings {
+    pub fn get(&self, action: Action) -> KeyboardShortcut {
+        self.shortcuts[action as usize]
+    }
+
+    pub fn set(&mut self, action: Action, shortcut: KeyboardShortcut) {
+        self.shortcuts[action as usize] = shortcut;
+    }
+
+    /// Consume the key press of `action`. Shortcuts that would type text, without Ctrl or
+    /// Alt, stay with text fields while one has the focus.
+    pub fn pressed(&self, ctx: &egui::Context, action: Action) -> bool {
+        let shortcut = self.get(action);
+        let m = shortcut.modifiers;
+        if !(m.command || m.ctrl || m.alt) && ctx.wants_keyboard_input() {
+            return false;
+        }
+        // consume_shortcut ignores an extra shift, Ctrl+Shift+S must not save as well as save as
+        let shifted = KeyboardShortcut::new(shortcut.modifiers | Modifiers::SHIFT, shortcut.logical_key);
+        if !shortcut.modifiers.shift && ctx.input(|i| i.modifiers.shift) && self.shortcuts.contains(&shifted) {
+            return false;
+        }
+        ctx.input_mut(|i| i.consume_shortcut(&shortcut))
+    }
+
+    /// As shown in menus and written to the settings file.
+    pub fn text(&self, action: Action) -> String {
+        format_shortcut(&self.get(action))
+    }
+}
+
+/// "Ctrl+Shift+S" and the like. Ctrl means Cmd on macOS.
+pub fn format_shortcut(shortcut: &KeyboardShortcut) -> String {
+    let m = shortcut.modifiers;
+    let mut text = String::new();
+    for (held, name) in [(m.command || m.ctrl, "Ctrl+"), (m.alt, "Alt+"), (m.shift, "Shift+")] {
+        if held {
+            text.push_str(name);
+        }
+    }
+    text + shortcut.logical_key.name()
+}
+
+/// Reads what `format_shortcut` writes, modifier names in any case.
+pub fn parse_shortcut(text: &str) -> Option<KeyboardShortcut> {
+    let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+    let key = Key::from_name(parts.pop()?)?;
+    let mut modifiers = Modifiers::NONE;
+    for part in parts {
+        match part.to_ascii_lowercase().as_str() {
+            "ctrl" | "cmd" | "command" => modifiers = modifiers | Modifiers::COMMAND,
+            "shift" => modifiers = modifiers | Modifiers::SHIFT,
+            "alt" | "option" => modifiers = modifiers | Modifiers::ALT,
+            _ => return None,
+        }
+    }
+    Some(KeyboardShortcut::new(modifiers, key))
+}
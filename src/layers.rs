@@ -0,0 +1,64 @@
+use vectorlab_core::{Document, EditCommand, ElementId, SetVisibility};
+
+use crate::inspector::element_label;
+
+/// Group hierarchy of the document with visibility checkboxes. Clicking a row selects the
+/// element, shift-click adds it to / removes it from the selection. Visibility changes are
+/// returned as commands for the undo history.
+pub fn layers_panel(ui: &mut egui::Ui, doc: &Document, selection: &mut Vec<ElementId>) -> Vec<Box<dyn EditCommand>> {
+    let mut edits = vec![];
+    egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+        element_tree(ui, doc, doc.root, selection, &mut edits, 0);
+    });
+    edits
+}
+
+fn element_tree(ui: &mut egui::Ui, doc: &Document, id: ElementId, selection: &mut Vec<ElementId>, edits: &mut Vec<Box<dyn EditCommand>>, depth: usize) {
+    let children = &doc.get(id).children;
+    if children.is_empty() {
+        ui.horizontal(|ui| element_row(ui, doc, id, selection, edits));
+        return;
+    }
+    let state = egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), ui.make_persistent_id(id), depth < 2);
+    state
+        .show_header(ui, |ui| element_row(ui, doc, id, selection, edits))
+        .body(|ui| {
+            for &child in children {
+                element_tree(ui, doc, child, selection, edits, depth + 1);
+            }
+        });
+}
+
+fn element_row(ui: &mut egui::Ui, doc: &Document, id: ElementId, selection: &mut Vec<ElementId>, edits: &mut Vec<Box<dyn EditCommand>>) {
+    let element = doc.get(id);
+    let mut visible = element.visible;
+    if ui.checkbox(&mut visible, "").on_hover_text("Show / hide").changed() {
+        edits.push(Box::new(SetVisibility { id, visible }));
+    }
+
+    let response = ui.selectable_label(selection.contains(&id), element_label(element));
+    if response.clicked() {
+        let extend = ui.input(|i| i.modifiers.shift);
+        update_selection(selection, Some(id), extend);
+    }
+}
+
+/// Plain clicks replace the selection, `extend` (shift) toggles `id` in it. Clicking into
+/// empty space clears the selection unless extending.
+pub fn update_selection(selection: &mut Vec<ElementId>, id: Option<ElementId>, extend: bool) {
+    match (id, extend) {
+        (Some(id), true) => {
+            if let Some(pos) = selection.iter().position(|&s| s == id) {
+                selection.remove(pos);
+            } else {
+                selection.push(id);
+            }
+        }
+        (Some(id), false) => {
+            selection.clear();
+            selection.push(id);
+        }
+        (None, true) => {}
+        (None, false) => selection.clear(),
+    }
+}
@@ -0,0 +1,62 @@
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+use vectorlab_core::{Document, Formats, LoadError, LoadOptions, LoadProgress};
+
+/// Where a loaded document goes.
+pub enum LoadTarget {
+    /// a new tab, `name` is its title for documents that did not come from a file
+    NewTab { path: Option<PathBuf>, name: Option<String> },
+    /// the tab currently showing `tab_path`, when stepping through a directory
+    Replace { tab_path: PathBuf, path: PathBuf },
+    /// the tab showing `tab_path`, which changed on disk, keeping its view
+    Reload { tab_path: PathBuf },
+}
+
+/// A document being parsed on a worker thread, so the window stays responsive.
+pub struct PendingLoad {
+    /// shown next to the progress bar
+    pub label: String,
+    pub target: LoadTarget,
+    pub progress: Arc<LoadProgress>,
+    result: Receiver<Result<Document, String>>,
+}
+
+impl PendingLoad {
+    /// `path` opened with the importer `formats` have for its extension.
+    pub fn file(path: PathBuf, formats: &Arc<Formats>, opts: &LoadOptions, target: LoadTarget) -> Self {
+        let label = path.file_name().map_or_else(|| path.display().to_string(), |n| n.to_string_lossy().into_owned());
+        let formats = formats.clone();
+        Self::spawn(label, opts, target, move |opts| formats.load_file(&path, opts))
+    }
+
+    pub fn data(data: Vec<u8>, label: String, opts: &LoadOptions, target: LoadTarget) -> Self {
+        Self::spawn(label, opts, target, move |opts| vectorlab_core::load_data(&data, opts))
+    }
+
+    fn spawn(
+        label: String,
+        opts: &LoadOptions,
+        target: LoadTarget,
+        load: impl FnOnce(&LoadOptions) -> Result<Document, LoadError> + Send + 'static,
+    ) -> Self {
+        let progress = Arc::new(LoadProgress::default());
+        let opts = LoadOptions { progress: Some(progress.clone()), ..opts.clone() };
+        let (sender, result) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(load(&opts).map_err(|e| e.to_string()));
+        });
+        Self { label, target, progress, result }
+    }
+
+    /// The document or the error once the worker is done.
+    pub fn poll(&self) -> Option<Result<Document, String>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err("loader thread crashed".to_string())),
+        }
+    }
+}
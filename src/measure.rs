@@ -0,0 +1,112 @@
+use vectorlab_core::{Document, PathPoint, Unit, ViewTransform};
+
+use crate::grid::Grid;
+use crate::tab::Tab;
+
+/// Pixels from a path outline within which measuring points stick to it.
+const PATH_REACH: f32 = 5.0;
+
+/// Two points on the canvas and what lies between them, in document units.
+pub struct Measurement {
+    start: [f32; 2],
+    end: [f32; 2],
+    // set where the points are on a path outline
+    start_on_path: Option<PathPoint>,
+    end_on_path: Option<PathPoint>,
+    // false while the end still follows the pointer
+    done: bool,
+}
+
+impl Measurement {
+    fn distance(&self) -> f32 {
+        ((self.end[0] - self.start[0]).powi(2) + (self.end[1] - self.start[1]).powi(2)).sqrt()
+    }
+
+    /// Counterclockwise from the x axis as seen on screen, in degrees.
+    fn angle(&self) -> f32 {
+        // y points down in SVG
+        (self.start[1] - self.end[1]).atan2(self.end[0] - self.start[0]).to_degrees()
+    }
+
+    fn report(&self, doc: &Document, unit: Unit) -> String {
+        let [mx, my] = doc.mm_per_unit();
+        let [dx, dy] = [self.end[0] - self.start[0], self.end[1] - self.start[1]];
+        let mm = ((dx * mx).powi(2) + (dy * my).powi(2)).sqrt();
+        let mut text = format!("distance {:.3} ({})\nangle {:.2}°\ndx {:.3}  dy {:.3}", self.distance(), unit.format(mm), self.angle(), dx, dy);
+        if let Some(length) = self.start_on_path.zip(self.end_on_path).and_then(|(a, b)| doc.arc_length(&a, &b)) {
+            // exact for a uniform scale only, good enough for a readout
+            text.push_str(&format!("\nalong path {:.3} ({})", length, unit.format(length * (mx * my).sqrt())));
+        }
+        text
+    }
+}
+
+/// Measure tool: click two points or drag from one to the other. Points close to a path
+/// outline stick to it, otherwise they snap to `grid`. A right click clears the measurement.
+pub fn handle_measure(ui: &egui::Ui, tab: &mut Tab, grid: &Grid, canvas: egui::Rect, response: &egui::Response) {
+    if response.secondary_clicked() {
+        tab.measurement = None;
+        return;
+    }
+    let space_held = ui.input(|i| i.key_down(egui::Key::Space));
+    let locate = |pos: egui::Pos2| {
+        let p = tab.view.to_doc([pos.x - canvas.min.x, pos.y - canvas.min.y]);
+        let on_path = tab.doc.point_on_path(p, PATH_REACH / tab.view.zoom);
+        (on_path.map_or_else(|| grid.snap(p, &tab.doc, tab.view.zoom), |q| q.point), on_path)
+    };
+
+    if response.drag_started_by(egui::PointerButton::Primary) && !space_held {
+        // the drag is only recognized after the pointer moved a bit
+        let Some(pos) = ui.input(|i| i.pointer.press_origin()) else { return };
+        let (p, on_path) = locate(pos);
+        tab.measurement = Some(Measurement { start: p, end: p, start_on_path: on_path, end_on_path: on_path, done: false });
+    }
+    let Some(pos) = response.interact_pointer_pos().or(response.hover_pos()) else { return };
+    let (p, on_path) = locate(pos);
+    if response.clicked() && tab.measurement.as_ref().is_none_or(|m| m.done) {
+        tab.measurement = Some(Measurement { start: p, end: p, start_on_path: on_path, end_on_path: on_path, done: false });
+        return;
+    }
+    let Some(m) = tab.measurement.as_mut().filter(|m| !m.done) else { return };
+    m.end = p;
+    m.end_on_path = on_path;
+    if response.clicked() || response.drag_stopped_by(egui::PointerButton::Primary) {
+        m.done = true;
+    }
+}
+
+/// The measured line with its readout next to the end point, where it can be copied.
+pub fn draw_measurement(ui: &egui::Ui, painter: &egui::Painter, canvas: egui::Rect, doc: &Document, view: &ViewTransform, m: &Measurement, unit: Unit) {
+    let to_screen = |p: [f32; 2]| {
+        let s = view.to_screen(p);
+        egui::pos2(canvas.min.x + s[0], canvas.min.y + s[1])
+    };
+    let (a, b) = (to_screen(m.start), to_screen(m.end));
+    let color = egui::Color32::from_rgb(230, 40, 160);
+    painter.line_segment([a, b], egui::Stroke::new(1.5, color));
+    for (p, on_path) in [(a, m.start_on_path), (b, m.end_on_path)] {
+        // filled where the point sits on a path
+        if on_path.is_some() {
+            painter.circle_filled(p, 4.0, color);
+        } else {
+            painter.circle_stroke(p, 4.0, egui::Stroke::new(1.5, color));
+        }
+    }
+    if m.distance() == 0.0 {
+        return;
+    }
+
+    let report = m.report(doc, unit);
+    egui::Area::new(ui.id().with("measurement"))
+        .fixed_pos(b + egui::vec2(10.0, 10.0))
+        .constrain_to(canvas)
+        .interactable(m.done)
+        .show(ui.ctx(), |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.monospace(&report);
+                if m.done && ui.small_button("📋 Copy").clicked() {
+                    ui.output_mut(|o| o.copied_text = report.clone());
+                }
+            });
+        });
+}
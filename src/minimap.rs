@@ -0,0 +1,66 @@
+use vectorlab_core::ViewTransform;
+
+use crate::background::Background;
+use crate::canvas::{draw_document, ImageCache};
+use crate::tab::Tab;
+
+/// Largest width and height of the overview, in pixels.
+const MINIMAP_SIZE: egui::Vec2 = egui::vec2(200.0, 150.0);
+
+/// Space between the overview and the edges of the canvas.
+const MARGIN: f32 = 10.0;
+
+/// The whole drawing small in the bottom right corner of `canvas`, with the part the main
+/// view shows outlined. Dragging in it pans the main view, a click centers it there.
+pub fn draw_minimap(ui: &egui::Ui, tab: &mut Tab, canvas: egui::Rect, background: &Background, images: &mut ImageCache) {
+    let Some(bbox) = tab.doc.bbox() else { return };
+    let [w, h] = [(bbox[2] - bbox[0]).max(f32::EPSILON), (bbox[3] - bbox[1]).max(f32::EPSILON)];
+    let scale = (MINIMAP_SIZE.x / w).min(MINIMAP_SIZE.y / h);
+    let size = egui::vec2(w * scale, h * scale).max(egui::vec2(40.0, 40.0));
+    let rect = egui::Rect::from_min_size(canvas.max - size - egui::vec2(MARGIN, MARGIN), size);
+    if !canvas.contains_rect(rect) {
+        return;
+    }
+    let mut overview = ViewTransform::default();
+    overview.fit(bbox, [size.x, size.y], 4.0);
+
+    // registered after the canvas, so it gets the pointer first
+    let response = ui.interact(rect, ui.id().with("minimap"), egui::Sense::click_and_drag());
+    let to_overview = |pos: egui::Pos2| {
+        let p = pos - rect.min;
+        overview.to_doc([p.x, p.y])
+    };
+    if response.dragged_by(egui::PointerButton::Primary) {
+        // the overview is never rotated, the main view may be
+        let delta = response.drag_delta() / overview.zoom;
+        let [a, b, c, d] = tab.view.matrix();
+        tab.view.pan_by([-(a * delta.x + b * delta.y), -(c * delta.x + d * delta.y)]);
+    } else if response.clicked() {
+        if let Some(pos) = response.interact_pointer_pos() {
+            let [x, y] = to_overview(pos);
+            let center = tab.view.to_screen([x, y]);
+            tab.view.pan_by([canvas.width() * 0.5 - center[0], canvas.height() * 0.5 - center[1]]);
+        }
+    }
+
+    let painter = ui.painter_at(rect);
+    background.paint(&painter, rect);
+    if tab.gpu.update(&tab.doc) {
+        tab.gpu.paint(&painter, rect, &overview, images);
+    } else {
+        tab.index.update(&tab.doc);
+        draw_document(&painter, &tab.doc, &tab.index, &overview, rect.min.to_vec2(), images);
+    }
+
+    let (w, h) = (canvas.width(), canvas.height());
+    let viewport = [[0.0, 0.0], [w, 0.0], [w, h], [0.0, h]]
+        .map(|p| egui::Pos2::from(overview.to_screen(tab.view.to_doc(p))) + rect.min.to_vec2())
+        .to_vec();
+    let accent = egui::Color32::from_rgb(0, 160, 255);
+    painter.add(egui::Shape::convex_polygon(viewport, accent.gamma_multiply(0.15), egui::Stroke::new(1.5, accent)));
+    let frame = ui.visuals().widgets.noninteractive.bg_stroke;
+    ui.painter().rect_stroke(rect, 0.0, frame);
+    if response.hovered() {
+        ui.ctx().set_cursor_icon(if response.dragged() { egui::CursorIcon::Grabbing } else { egui::CursorIcon::Grab });
+    }
+}
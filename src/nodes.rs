@@ -0,0 +1,147 @@
+use vectorlab_core::usvg::Transform;
+use vectorlab_core::{delete_node, insert_node, move_node, nearest_segment, path_nodes, toggle_smooth, transform_point, ElementId, Node, NodePart, Segment, SetSegments};
+
+use crate::grid::Grid;
+use crate::tab::Tab;
+
+/// Pixels from a node or handle within which the pointer grabs it.
+const GRAB: f32 = 6.0;
+
+/// Node tool state of a tab.
+#[derive(Default)]
+pub struct NodeEdit {
+    /// path and segment index of the node picked last, for Delete
+    selected: Option<(ElementId, usize)>,
+    // being dragged
+    drag: Option<(usize, NodePart)>,
+}
+
+/// The path the node tool works on, the selection if it is a single path.
+fn edited_path(tab: &Tab) -> Option<(ElementId, Transform)> {
+    let [id] = tab.selection[..] else { return None };
+    tab.doc.get(id).as_path()?;
+    Some((id, tab.doc.abs_transform(id)))
+}
+
+/// Node tool: drag anchors and handles of the selected path, double click a node to make it
+/// smooth or a corner and the outline to add one. Clicking elsewhere selects another path.
+/// Dragged points snap to `grid`.
+pub fn handle_nodes(ui: &egui::Ui, tab: &mut Tab, grid: &Grid, canvas: egui::Rect, response: &egui::Response) {
+    let space_held = ui.input(|i| i.key_down(egui::Key::Space));
+    let edited = edited_path(tab);
+    if response.drag_started_by(egui::PointerButton::Primary) && !space_held {
+        let origin = ui.input(|i| i.pointer.press_origin());
+        tab.nodes.drag = origin.and_then(|pos| grab(tab, canvas, pos));
+        if let (Some((segment, _)), Some((id, _))) = (tab.nodes.drag, edited) {
+            tab.nodes.selected = Some((id, segment));
+        }
+    }
+    if let (Some((segment, part)), Some((id, ts))) = (tab.nodes.drag, edited) {
+        if let Some(pos) = response.interact_pointer_pos().filter(|_| response.dragged_by(egui::PointerButton::Primary)) {
+            let p = grid.snap(tab.view.to_doc([pos.x - canvas.min.x, pos.y - canvas.min.y]), &tab.doc, tab.view.zoom);
+            let local = transform_point(&ts.invert().unwrap_or_default(), p);
+            let Some(path) = tab.doc.get(id).as_path() else { return };
+            let segments = move_node(&path.segments, segment, part, local);
+            if segments != path.segments {
+                let edit = SetSegments::new(&tab.doc, id, segments);
+                tab.history.push(Box::new(edit), &mut tab.doc);
+            }
+        }
+    }
+    if response.drag_stopped() {
+        tab.nodes.drag = None;
+    }
+
+    if !(response.clicked() || response.double_clicked()) || space_held {
+        return;
+    }
+    let Some(pos) = response.interact_pointer_pos() else { return };
+    if let (Some((segment, _)), Some((id, _))) = (grab(tab, canvas, pos), edited) {
+        tab.nodes.selected = Some((id, segment));
+        if response.double_clicked() {
+            update_path(tab, |segments| toggle_smooth(segments, segment));
+        }
+        return;
+    }
+    // on the outline of the edited path
+    if let Some((id, ts)) = edited.filter(|_| response.double_clicked()) {
+        let Some(path) = tab.doc.get(id).as_path() else { return };
+        let p = transform_point(&ts.invert().unwrap_or_default(), tab.view.to_doc([pos.x - canvas.min.x, pos.y - canvas.min.y]));
+        if let Some((segment, t, _)) = nearest_segment(&path.segments, p).filter(|(_, _, q)| screen_point(tab, canvas, &ts, *q).distance(pos) <= GRAB) {
+            update_path(tab, |segments| insert_node(segments, segment, t));
+            tab.nodes.selected = Some((id, segment));
+            return;
+        }
+    }
+    if response.clicked() {
+        let p = pos - canvas.min;
+        let hit = tab.doc.hit_test(tab.view.to_doc([p.x, p.y]), 3.0 / tab.view.zoom);
+        tab.selection = hit.into_iter().collect();
+        tab.nodes.selected = None;
+    }
+}
+
+/// The node or handle of the edited path under `pos`.
+fn grab(tab: &Tab, canvas: egui::Rect, pos: egui::Pos2) -> Option<(usize, NodePart)> {
+    let (id, ts) = edited_path(tab)?;
+    let nodes = path_nodes(&tab.doc.get(id).as_path()?.segments);
+    let near = |p: [f32; 2]| screen_point(tab, canvas, &ts, p).distance(pos) <= GRAB;
+    // handles first, they may sit on top of their anchor
+    let handle = nodes.iter().find_map(|n| {
+        let part = [(n.in_handle, NodePart::In), (n.out_handle, NodePart::Out)].into_iter().find(|(h, _)| h.is_some_and(near))?.1;
+        Some((n.segment, part))
+    });
+    handle.or_else(|| nodes.iter().find(|n| near(n.point)).map(|n| (n.segment, NodePart::Anchor)))
+}
+
+fn screen_point(tab: &Tab, canvas: egui::Rect, ts: &Transform, p: [f32; 2]) -> egui::Pos2 {
+    egui::Pos2::from(tab.view.to_screen(transform_point(ts, p))) + canvas.min.to_vec2()
+}
+
+/// Delete the picked node of the edited path, false if there is none.
+pub fn delete_selected_node(tab: &mut Tab) -> bool {
+    let Some((id, segment)) = tab.nodes.selected.take() else { return false };
+    if edited_path(tab).is_none_or(|(edited, _)| edited != id) {
+        return false;
+    }
+    update_path(tab, |segments| delete_node(segments, segment));
+    true
+}
+
+fn update_path(tab: &mut Tab, edit: impl FnOnce(&[Segment]) -> Vec<Segment>) {
+    let Some((id, _)) = edited_path(tab) else { return };
+    let Some(path) = tab.doc.get(id).as_path() else { return };
+    let segments = edit(&path.segments);
+    if segments != path.segments {
+        let edit = SetSegments::new(&tab.doc, id, segments);
+        tab.history.push(Box::new(edit), &mut tab.doc);
+    }
+}
+
+/// The anchors of the edited path as squares, round for smooth ones, and the handles of the
+/// curves as dots on a line to their anchor. The picked node is filled.
+pub fn draw_nodes(painter: &egui::Painter, canvas: egui::Rect, tab: &Tab) {
+    let Some((id, ts)) = edited_path(tab) else { return };
+    let Some(path) = tab.doc.get(id).as_path() else { return };
+    let to_screen = |p: [f32; 2]| screen_point(tab, canvas, &ts, p);
+    let color = egui::Color32::from_rgb(0, 160, 255);
+    let stroke = egui::Stroke::new(1.0, color);
+    let nodes: Vec<Node> = path_nodes(&path.segments);
+    for node in &nodes {
+        let anchor = to_screen(node.point);
+        for handle in [node.in_handle, node.out_handle].into_iter().flatten() {
+            let handle = to_screen(handle);
+            painter.line_segment([anchor, handle], stroke);
+            painter.circle_filled(handle, 3.0, color);
+        }
+    }
+    for node in &nodes {
+        let anchor = to_screen(node.point);
+        let fill = if tab.nodes.selected == Some((id, node.segment)) { color } else { egui::Color32::WHITE };
+        if node.smooth {
+            painter.circle(anchor, 4.0, fill, stroke);
+        } else {
+            painter.rect(egui::Rect::from_center_size(anchor, egui::vec2(7.0, 7.0)), 0.0, fill, stroke);
+        }
+    }
+}
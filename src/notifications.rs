@@ -0,0 +1,133 @@
+use std::sync::Mutex;
+
+use vectorlab_core::capture_log;
+
+/// Errors VectorLab logged outside of loading, from any thread, until the window shows them.
+static LOGGED: Mutex<Vec<String>> = Mutex::new(vec![]);
+
+/// Errors and load warnings shown in a panel at the bottom of the window, so that a file
+/// that fails to load or loads incompletely doesn't just leave a blank canvas.
+#[derive(Default)]
+pub struct Notifications {
+    entries: Vec<Notification>,
+    pub open: bool,
+}
+
+struct Notification {
+    error: bool,
+    title: String,
+    details: Vec<String>,
+}
+
+impl Notifications {
+    pub fn error(&mut self, title: impl Into<String>, error: impl ToString) {
+        self.push(true, title.into(), vec![error.to_string()]);
+    }
+
+    /// Warnings about a document that did load, nothing if there are none.
+    pub fn warnings(&mut self, title: impl Into<String>, warnings: &[impl ToString]) {
+        if !warnings.is_empty() {
+            self.push(false, title.into(), warnings.iter().map(|w| w.to_string()).collect());
+        }
+    }
+
+    /// Show what was logged with `log::error!` since the last frame. Messages are
+    /// "title: detail", like the errors of [`Notifications::error`].
+    pub fn take_logged(&mut self) {
+        let logged = std::mem::take(&mut *LOGGED.lock().unwrap());
+        for message in logged {
+            match message.split_once(": ") {
+                Some((title, detail)) => self.error(title, detail),
+                None => self.error("Error", message),
+            }
+        }
+    }
+
+    fn push(&mut self, error: bool, title: String, details: Vec<String>) {
+        self.entries.push(Notification { error, title, details });
+        self.open = true;
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Newest first, each with a button to dismiss it.
+    pub fn panel(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.strong("Messages");
+            if ui.button("Clear").clicked() {
+                self.entries.clear();
+            }
+            if ui.button("Hide").clicked() {
+                self.open = false;
+            }
+        });
+        let mut dismiss = None;
+        egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+            for (i, entry) in self.entries.iter().enumerate().rev() {
+                ui.horizontal(|ui| {
+                    if ui.small_button("×").on_hover_text("Dismiss").clicked() {
+                        dismiss = Some(i);
+                    }
+                    let (icon, color) = if entry.error {
+                        ("⛔", ui.visuals().error_fg_color)
+                    } else {
+                        ("⚠", ui.visuals().warn_fg_color)
+                    };
+                    if let [detail] = entry.details.as_slice() {
+                        ui.colored_label(color, format!("{} {}: {}", icon, entry.title, detail));
+                    } else {
+                        let title = format!("{} {} ({} warnings)", icon, entry.title, entry.details.len());
+                        ui.collapsing(egui::RichText::new(title).color(color), |ui| {
+                            for detail in &entry.details {
+                                ui.label(detail);
+                            }
+                        });
+                    }
+                });
+            }
+        });
+        if let Some(i) = dismiss {
+            self.entries.remove(i);
+        }
+        if self.entries.is_empty() {
+            self.open = false;
+        }
+    }
+}
+
+/// env_logger, except that warnings logged while a document loads are also collected into
+/// the document's warnings, and VectorLab's own errors go to the messages panel.
+struct Logger(env_logger::Logger);
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Warn || self.0.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !capture_log(record) && record.level() == log::Level::Error && record.target().starts_with("vectorlab") {
+            LOGGED.lock().unwrap().push(record.args().to_string());
+        }
+        // prints only what RUST_LOG asks for
+        self.0.log(record);
+    }
+
+    fn flush(&self) {
+        self.0.flush();
+    }
+}
+
+/// Replaces `env_logger::init()`.
+pub fn init_logging() {
+    let inner = env_logger::Builder::from_default_env().build();
+    let level = inner.filter().max(log::LevelFilter::Warn);
+    if log::set_boxed_logger(Box::new(Logger(inner))).is_ok() {
+        log::set_max_level(level);
+    }
+}
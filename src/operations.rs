@@ -0,0 +1,446 @@
+use vectorlab_core::{transform_point, ArrayLayout, Document, EditCommand, EditGroup, ElementId, HatchSettings, LineJoin, OverlapResult, Segment, SetSegments, SimplifyMethod, Unit, ViewTransform};
+
+use crate::units::{length_slider, length_value, ANY_LENGTH};
+
+/// The operations of the Path menu, with their settings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operation {
+    /// merge open paths whose ends are less than `tolerance` mm apart, see `join_paths`
+    Join { tolerance: f32 },
+    /// fewer points in the paths, staying within `tolerance` mm of them, see `simplify_paths`
+    Simplify { tolerance: f32, method: SimplifyMethod },
+    /// take out lines drawn twice and, with `hidden`, those under fills, see `remove_overlaps`
+    RemoveOverlaps { tolerance: f32, hidden: bool },
+    /// fills to lines, the lengths in mm, see `hatch_fills`
+    Hatch(HatchSettings),
+    /// outlines `distance` mm out, or in when negative, `copies` times over, see `offset_paths`
+    Offset { distance: f32, join: LineJoin, copies: u32, keep_original: bool },
+    /// trim to `size` mm at `origin` mm from the top left of the page, see `crop_paths`
+    ClipToPage { origin: [f32; 2], size: [f32; 2] },
+    /// copies in `rows` × `columns` with `gap` mm between them, see `array_elements`
+    GridArray { rows: u32, columns: u32, gap: [f32; 2] },
+    /// `count` places around `center`, mm from the top left of the page, over `sweep` degrees
+    PolarArray { count: u32, center: [f32; 2], sweep: f32, rotate: bool },
+}
+
+/// Paper sizes offered for Clip to page, portrait, in mm.
+const PAPER: [(&str, [f32; 2]); 5] = [("A5", [148.0, 210.0]), ("A4", [210.0, 297.0]), ("A3", [297.0, 420.0]), ("Letter", [215.9, 279.4]), ("Legal", [215.9, 355.6])];
+
+impl Operation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Join { .. } => "Join paths",
+            Self::Simplify { .. } => "Simplify",
+            Self::RemoveOverlaps { .. } => "Remove overlaps",
+            Self::Hatch(_) => "Fill to hatch",
+            Self::Offset { .. } => "Offset",
+            Self::ClipToPage { .. } => "Clip to page",
+            Self::GridArray { .. } => "Grid array",
+            Self::PolarArray { .. } => "Polar array",
+        }
+    }
+
+    /// Run on the paths among `ids`. The edit and what it did, None if there was nothing to do.
+    pub fn run(&self, doc: &Document, ids: &[ElementId]) -> Option<(Box<dyn EditCommand>, String)> {
+        match *self {
+            Self::Join { tolerance } => {
+                let result = vectorlab_core::join_paths(doc, ids, tolerance * units_per_mm(doc))?;
+                let report = format!("Joined {} paths into {}", result.joined + result.chains, result.chains);
+                Some((result.edit, report))
+            }
+            Self::Simplify { tolerance, method } => {
+                let simplified = vectorlab_core::simplify_paths(doc, ids, tolerance * units_per_mm(doc), method);
+                if simplified.is_empty() {
+                    return None;
+                }
+                let (before, after) = simplified.iter().fold((0, 0), |(b, a), s| (b + s.before, a + s.after));
+                let report = format!("{} paths, {} points down to {}", simplified.len(), before, after);
+                let edits = simplified.into_iter().map(|s| Box::new(SetSegments::new(doc, s.id, s.segments)) as Box<dyn EditCommand>).collect();
+                Some((Box::new(EditGroup::new("Simplify", edits)), report))
+            }
+            Self::RemoveOverlaps { tolerance, hidden } => {
+                let result = vectorlab_core::remove_overlaps(doc, ids, tolerance * units_per_mm(doc), hidden)?;
+                let report = overlap_summary(doc, &result);
+                Some((result.edit, report))
+            }
+            Self::Hatch(settings) => {
+                let result = vectorlab_core::hatch_fills(doc, ids, &in_units(doc, settings))?;
+                Some((result.edit, format!("{} fills, {} lines", result.fills, result.lines.len())))
+            }
+            Self::Offset { distance, join, copies, keep_original } => {
+                let result = vectorlab_core::offset_paths(doc, ids, distance * units_per_mm(doc), join, copies, keep_original)?;
+                Some((result.edit, format!("{} paths, {} outlines", result.paths, result.contours.len())))
+            }
+            Self::ClipToPage { origin, size } => {
+                let result = vectorlab_core::crop_paths(doc, ids, page_rect(doc, origin, size))?;
+                Some((result.edit, format!("{} paths cut, {} deleted", result.cut, result.deleted)))
+            }
+            Self::GridArray { .. } | Self::PolarArray { .. } => {
+                let layout = self.layout(doc)?;
+                let edit = vectorlab_core::array_elements(doc, ids, layout)?;
+                Some((edit, copies_summary(doc, ids, layout)))
+            }
+        }
+    }
+
+    /// What the paths among `ids` would turn into, as polylines in document coordinates,
+    /// with a summary. None for operations without a preview.
+    pub fn preview(&self, doc: &Document, ids: &[ElementId]) -> Option<(Vec<Vec<[f32; 2]>>, String)> {
+        match *self {
+            Self::Join { .. } => None,
+            Self::Simplify { tolerance, method } => {
+                let simplified = vectorlab_core::simplify_paths(doc, ids, tolerance * units_per_mm(doc), method);
+                let mut lines = vec![];
+                let (mut before, mut after) = (0, 0);
+                for s in &simplified {
+                    let ts = doc.abs_transform(s.id);
+                    let mut line: Vec<[f32; 2]> = vec![];
+                    for segment in &s.segments {
+                        match *segment {
+                            Segment::MoveTo(p) => {
+                                lines.extend((line.len() > 1).then(|| std::mem::take(&mut line)));
+                                line = vec![transform_point(&ts, p)];
+                            }
+                            Segment::LineTo(p) => line.push(transform_point(&ts, p)),
+                            Segment::Close => line.extend(line.first().copied()),
+                            _ => {}
+                        }
+                    }
+                    lines.extend((line.len() > 1).then_some(line));
+                    before += s.before;
+                    after += s.after;
+                }
+                Some((lines, format!("{} paths, {} points down to {}", simplified.len(), before, after)))
+            }
+            Self::RemoveOverlaps { tolerance, hidden } => match vectorlab_core::remove_overlaps(doc, ids, tolerance * units_per_mm(doc), hidden) {
+                Some(result) => {
+                    let summary = overlap_summary(doc, &result);
+                    Some((result.removed.iter().map(|line| line.to_vec()).collect(), summary))
+                }
+                None => Some((vec![], "No overlaps".to_string())),
+            },
+            Self::Hatch(settings) => match vectorlab_core::hatch_fills(doc, ids, &in_units(doc, settings)) {
+                Some(result) => {
+                    let summary = format!("{} fills, {} lines", result.fills, result.lines.len());
+                    Some((result.lines.iter().map(|line| line.to_vec()).collect(), summary))
+                }
+                None => Some((vec![], "No fills".to_string())),
+            },
+            Self::Offset { distance, join, copies, keep_original } => match vectorlab_core::offset_paths(doc, ids, distance * units_per_mm(doc), join, copies, keep_original) {
+                Some(result) => {
+                    let summary = format!("{} paths, {} outlines", result.paths, result.contours.len());
+                    let lines = result.contours.into_iter().map(|c| c.points.iter().chain(c.points.first()).copied().collect()).collect();
+                    Some((lines, summary))
+                }
+                None => Some((vec![], "Nothing left".to_string())),
+            },
+            Self::ClipToPage { origin, size } => {
+                let rect = page_rect(doc, origin, size);
+                let outline = vec![[rect[0], rect[1]], [rect[2], rect[1]], [rect[2], rect[3]], [rect[0], rect[3]], [rect[0], rect[1]]];
+                let summary = match vectorlab_core::crop_paths(doc, ids, rect) {
+                    Some(result) => format!("{} paths cut, {} deleted", result.cut, result.deleted),
+                    None => "Everything is inside".to_string(),
+                };
+                Some((vec![outline], summary))
+            }
+            Self::GridArray { .. } | Self::PolarArray { .. } => {
+                let layout = self.layout(doc)?;
+                Some((vectorlab_core::array_outlines(doc, ids, layout), copies_summary(doc, ids, layout)))
+            }
+        }
+    }
+
+    /// The layout of an array in document units.
+    fn layout(&self, doc: &Document) -> Option<ArrayLayout> {
+        match *self {
+            Self::GridArray { rows, columns, gap } => Some(ArrayLayout::Grid { rows, columns, gap: gap.map(|g| g * units_per_mm(doc)) }),
+            Self::PolarArray { count, center, sweep, rotate } => Some(ArrayLayout::Polar { count, center: page_point(doc, center), sweep, rotate }),
+            _ => None,
+        }
+    }
+
+    /// Whether the preview shows what goes away rather than what is left.
+    fn previews_removal(&self) -> bool {
+        matches!(self, Self::RemoveOverlaps { .. })
+    }
+}
+
+fn overlap_summary(doc: &Document, result: &OverlapResult) -> String {
+    let mm = doc.mm_per_unit();
+    let length: f32 = result.removed.iter().map(|[p, q]| ((q[0] - p[0]) * mm[0]).hypot((q[1] - p[1]) * mm[1])).sum();
+    format!("{:.0} mm of lines taken out, {} paths cut, {} deleted", length, result.changed, result.deleted)
+}
+
+fn units_per_mm(doc: &Document) -> f32 {
+    let mm = doc.mm_per_unit();
+    2.0 / (mm[0] + mm[1]).max(f32::EPSILON)
+}
+
+fn copies_summary(doc: &Document, ids: &[ElementId], layout: ArrayLayout) -> String {
+    format!("{} copies", vectorlab_core::array_outlines(doc, ids, layout).len())
+}
+
+/// The point `p` mm from the top left of the page in document coordinates.
+fn page_point(doc: &Document, p: [f32; 2]) -> [f32; 2] {
+    let to_doc = doc.viewport_transform().invert().unwrap_or_default();
+    transform_point(&to_doc, p.map(|mm| Unit::Px.from_mm(mm)))
+}
+
+/// The rectangle `size` mm large at `origin` mm from the top left of the page, in document
+/// coordinates.
+fn page_rect(doc: &Document, origin: [f32; 2], size: [f32; 2]) -> [f32; 4] {
+    let a = page_point(doc, origin);
+    let b = page_point(doc, [origin[0] + size[0], origin[1] + size[1]]);
+    [a[0].min(b[0]), a[1].min(b[1]), a[0].max(b[0]), a[1].max(b[1])]
+}
+
+/// The size of the page in mm, A4 for documents without one.
+pub fn page_size(doc: &Document) -> [f32; 2] {
+    if doc.size[0] > 0.0 && doc.size[1] > 0.0 {
+        doc.size_mm()
+    } else {
+        PAPER[1].1
+    }
+}
+
+/// `settings` with the lengths in document units instead of mm.
+fn in_units(doc: &Document, settings: HatchSettings) -> HatchSettings {
+    let k = units_per_mm(doc);
+    HatchSettings { spacing: settings.spacing * k, inset: settings.inset * k, pen_width: settings.pen_width * k, ..settings }
+}
+
+/// A preview and what it was made from, so it is only redone when something changed.
+struct Preview {
+    operation: Operation,
+    revision: u64,
+    selection: Vec<ElementId>,
+    lines: Vec<Vec<[f32; 2]>>,
+    summary: String,
+}
+
+/// The window of an operation from the Path menu, open while Some.
+pub struct PathOperation {
+    pub operation: Operation,
+    /// what the last Apply did
+    report: Option<String>,
+    show_preview: bool,
+    preview: Option<Preview>,
+}
+
+impl PathOperation {
+    pub fn new(operation: Operation) -> Self {
+        Self { operation, report: None, show_preview: true, preview: None }
+    }
+
+    /// Redo the preview if the operation, the document or the targets changed.
+    fn update_preview(&mut self, doc: &Document, selection: &[ElementId], ids: &[ElementId]) {
+        let current = self.preview.as_ref().is_some_and(|p| p.operation == self.operation && p.revision == doc.revision && p.selection == selection);
+        if !self.show_preview || current {
+            return;
+        }
+        self.preview = self.operation.preview(doc, ids).map(|(lines, summary)| Preview {
+            operation: self.operation,
+            revision: doc.revision,
+            selection: selection.to_vec(),
+            lines,
+            summary,
+        });
+    }
+}
+
+/// Show the window while `state` is Some. Apply runs the operation on the selection, or on
+/// the whole document when nothing is selected, and returns the edit. The window stays open
+/// to try again with other settings after an undo.
+pub fn operation_window(ctx: &egui::Context, state: &mut Option<PathOperation>, doc: &Document, selection: &[ElementId], unit: Unit) -> Option<Box<dyn EditCommand>> {
+    let current = state.as_mut()?;
+    let mut open = true;
+    let mut close = false;
+    let mut edit = None;
+    egui::Window::new(current.operation.name()).open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+        egui::Grid::new("path_operation").num_columns(2).show(ui, |ui| match &mut current.operation {
+            Operation::Join { tolerance } => {
+                ui.label("Tolerance");
+                ui.add(length_value(tolerance, unit, 0.01, 0.0..=100.0))
+                    .on_hover_text("How far apart the ends of two paths may be to be joined");
+                ui.end_row();
+            }
+            Operation::Simplify { tolerance, method } => {
+                ui.label("Tolerance");
+                ui.add(length_slider(tolerance, unit, 0.001..=10.0).logarithmic(true))
+                    .on_hover_text("How far the simplified paths may stray from the original ones");
+                ui.end_row();
+                ui.label("Method");
+                ui.horizontal(|ui| {
+                    ui.radio_value(method, SimplifyMethod::DouglasPeucker, SimplifyMethod::DouglasPeucker.name()).on_hover_text("Keeps sharp corners");
+                    ui.radio_value(method, SimplifyMethod::Visvalingam, SimplifyMethod::Visvalingam.name()).on_hover_text("Smoother, drops small wiggles first");
+                });
+                ui.end_row();
+            }
+            Operation::RemoveOverlaps { tolerance, hidden } => {
+                ui.label("Tolerance");
+                ui.add(length_value(tolerance, unit, 0.01, 0.0..=100.0))
+                    .on_hover_text("How close a line has to run along another one to count as drawn twice");
+                ui.end_row();
+                ui.label("");
+                ui.checkbox(hidden, "Remove hidden lines").on_hover_text("Also take out the parts of lines under opaque fills above them");
+                ui.end_row();
+            }
+            Operation::Hatch(settings) => {
+                ui.label("Angle");
+                ui.add(egui::DragValue::new(&mut settings.angle).speed(1.0).range(-180.0..=180.0).suffix("°"));
+                ui.end_row();
+                ui.label("Spacing");
+                ui.add(length_slider(&mut settings.spacing, unit, 0.1..=20.0).logarithmic(true));
+                ui.end_row();
+                ui.label("Inset");
+                ui.add(length_value(&mut settings.inset, unit, 0.05, 0.0..=100.0))
+                    .on_hover_text("How far the lines stay away from the outline");
+                ui.end_row();
+                ui.label("Pen width");
+                ui.add(length_value(&mut settings.pen_width, unit, 0.01, 0.01..=10.0));
+                ui.end_row();
+                ui.label("");
+                ui.checkbox(&mut settings.crosshatch, "Crosshatch").on_hover_text("A second set of lines at right angles");
+                ui.end_row();
+                ui.label("");
+                ui.checkbox(&mut settings.outline, "Keep the outline").on_hover_text("Draw the outline in the fill color if it has no stroke");
+                ui.end_row();
+            }
+            Operation::Offset { distance, join, copies, keep_original } => {
+                ui.label("Distance");
+                ui.add(length_value(distance, unit, 0.01, -1000.0..=1000.0))
+                    .on_hover_text("Outwards, or inwards when negative. Half the kerf to make up for a laser's cut");
+                ui.end_row();
+                ui.label("Corners");
+                ui.horizontal(|ui| {
+                    ui.radio_value(join, LineJoin::Miter, "Miter");
+                    ui.radio_value(join, LineJoin::Round, "Round");
+                    ui.radio_value(join, LineJoin::Bevel, "Bevel");
+                });
+                ui.end_row();
+                ui.label("Copies");
+                ui.add(egui::DragValue::new(copies).range(1..=1000))
+                    .on_hover_text("Repeat the offset while anything is left, for a concentric fill");
+                ui.end_row();
+                ui.label("");
+                ui.checkbox(keep_original, "Keep the original").on_hover_text("Add the outlines as new paths instead of replacing the paths");
+                ui.end_row();
+            }
+            Operation::ClipToPage { origin, size } => {
+                ui.label("Paper");
+                ui.horizontal_wrapped(|ui| {
+                    if ui.button("Page").on_hover_text("The size of the document").clicked() {
+                        *origin = [0.0, 0.0];
+                        *size = page_size(doc);
+                    }
+                    for (name, paper) in PAPER {
+                        // keep the orientation
+                        let turned = if size[0] > size[1] { [paper[1], paper[0]] } else { paper };
+                        if ui.selectable_label(*size == turned, name).clicked() {
+                            *size = turned;
+                        }
+                    }
+                    if ui.button("⟲").on_hover_text("Portrait or landscape").clicked() {
+                        size.swap(0, 1);
+                    }
+                });
+                ui.end_row();
+                ui.label("Size");
+                ui.horizontal(|ui| {
+                    ui.add(length_value(&mut size[0], unit, 0.5, 1.0..=10000.0));
+                    ui.label("×");
+                    ui.add(length_value(&mut size[1], unit, 0.5, 1.0..=10000.0));
+                });
+                ui.end_row();
+                ui.label("Position");
+                ui.horizontal(|ui| {
+                    ui.add(length_value(&mut origin[0], unit, 0.5, ANY_LENGTH).prefix("x "));
+                    ui.add(length_value(&mut origin[1], unit, 0.5, ANY_LENGTH).prefix("y "));
+                })
+                .response
+                .on_hover_text("Of the top left corner, from the top left of the page");
+                ui.end_row();
+            }
+            Operation::GridArray { rows, columns, gap } => {
+                ui.label("Rows");
+                ui.add(egui::DragValue::new(rows).range(1..=100));
+                ui.end_row();
+                ui.label("Columns");
+                ui.add(egui::DragValue::new(columns).range(1..=100));
+                ui.end_row();
+                ui.label("Gap");
+                ui.horizontal(|ui| {
+                    ui.add(length_value(&mut gap[0], unit, 0.1, -1000.0..=1000.0).prefix("x "));
+                    ui.add(length_value(&mut gap[1], unit, 0.1, -1000.0..=1000.0).prefix("y "));
+                })
+                .response
+                .on_hover_text("Between the copies, 0 to have them touch");
+                ui.end_row();
+            }
+            Operation::PolarArray { count, center, sweep, rotate } => {
+                ui.label("Count");
+                ui.add(egui::DragValue::new(count).range(2..=360)).on_hover_text("The original included");
+                ui.end_row();
+                ui.label("Center");
+                ui.horizontal(|ui| {
+                    ui.add(length_value(&mut center[0], unit, 0.5, ANY_LENGTH).prefix("x "));
+                    ui.add(length_value(&mut center[1], unit, 0.5, ANY_LENGTH).prefix("y "));
+                    if ui.button("Page").on_hover_text("The middle of the page").clicked() {
+                        *center = page_size(doc).map(|s| s * 0.5);
+                    }
+                })
+                .response
+                .on_hover_text("From the top left of the page");
+                ui.end_row();
+                ui.label("Angle");
+                ui.add(egui::DragValue::new(sweep).speed(1.0).range(-360.0..=360.0).suffix("°"))
+                    .on_hover_text("Over which the copies spread, 360° for a full circle");
+                ui.end_row();
+                ui.label("");
+                ui.checkbox(rotate, "Rotate copies").on_hover_text("Turn the copies along, otherwise they only move");
+                ui.end_row();
+            }
+        });
+        ui.label(if selection.is_empty() { "On all paths" } else { "On the selected paths" });
+        let ids = if selection.is_empty() { vec![doc.root] } else { selection.to_vec() };
+        let ids: Vec<ElementId> = ids.into_iter().flat_map(|id| doc.descendants(id)).collect();
+        if !matches!(current.operation, Operation::Join { .. }) {
+            ui.checkbox(&mut current.show_preview, "Preview");
+            current.update_preview(doc, selection, &ids);
+            if let Some(preview) = current.preview.as_ref().filter(|_| current.show_preview) {
+                ui.weak(&preview.summary);
+            }
+        }
+        if let Some(report) = &current.report {
+            ui.weak(report);
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Apply").clicked() {
+                match current.operation.run(doc, &ids) {
+                    Some((command, report)) => {
+                        edit = Some(command);
+                        current.report = Some(report);
+                    }
+                    None => current.report = Some("Nothing to do".to_string()),
+                }
+            }
+            close = ui.button("Close").clicked();
+        });
+    });
+    if !open || close {
+        *state = None;
+    }
+    edit
+}
+
+/// The preview of the open operation over the canvas.
+pub fn draw_preview(painter: &egui::Painter, canvas: egui::Rect, view: &ViewTransform, state: &PathOperation) {
+    let Some(preview) = state.preview.as_ref().filter(|_| state.show_preview) else { return };
+    let to_screen = |p: &[f32; 2]| egui::Pos2::from(view.to_screen(*p)) + canvas.min.to_vec2();
+    let color = if state.operation.previews_removal() { egui::Color32::from_rgb(255, 60, 60) } else { egui::Color32::from_rgb(255, 0, 200) };
+    let stroke = egui::Stroke::new(1.5, color);
+    for line in &preview.lines {
+        painter.add(egui::Shape::line(line.iter().map(to_screen).collect(), stroke));
+    }
+}
@@ -0,0 +1,54 @@
+use vectorlab_core::{Document, SpatialIndex, ViewTransform};
+
+/// Outlines over the artwork for debugging clipping and export margins, each switched on in
+/// the View menu.
+#[derive(Default)]
+pub struct Overlays {
+    /// extents of everything drawn, strokes included
+    pub document_bbox: bool,
+    /// one box per visible path or image
+    pub path_bboxes: bool,
+    pub view_box: bool,
+}
+
+impl Overlays {
+    pub fn menu(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.document_bbox, "Document bounding box");
+        ui.checkbox(&mut self.path_bboxes, "Path bounding boxes");
+        ui.checkbox(&mut self.view_box, "viewBox");
+    }
+
+    pub fn draw(&self, painter: &egui::Painter, canvas: egui::Rect, doc: &Document, index: &mut SpatialIndex, view: &ViewTransform) {
+        // four corners, boxes are not upright in a rotated view
+        let to_screen = |b: [f32; 4]| [[b[0], b[1]], [b[2], b[1]], [b[2], b[3]], [b[0], b[3]]].map(|p| egui::Pos2::from(view.to_screen(p)) + canvas.min.to_vec2());
+        if self.document_bbox || self.path_bboxes {
+            // the GPU renderer does not keep the index up to date
+            index.update(doc);
+        }
+        if self.path_bboxes {
+            let visible = view.doc_bbox([0.0, 0.0, canvas.width(), canvas.height()]);
+            let stroke = egui::Stroke::new(1.0, egui::Color32::from_rgb(40, 200, 80).gamma_multiply(0.7));
+            for entry in index.query(visible) {
+                painter.add(egui::Shape::closed_line(to_screen(entry.bbox).to_vec(), stroke));
+            }
+        }
+        let font = egui::FontId::proportional(10.0);
+        let labeled = |b: [f32; 4], label: &str, color: egui::Color32| {
+            let corners = to_screen(b);
+            painter.add(egui::Shape::closed_line(corners.to_vec(), egui::Stroke::new(1.5, color)));
+            let rect = egui::Rect::from_points(&corners);
+            painter.text(rect.left_top() + egui::vec2(2.0, -2.0), egui::Align2::LEFT_BOTTOM, label, font.clone(), color);
+        };
+        if self.document_bbox {
+            if let Some(b) = index.bbox() {
+                let label = format!("extents {:.1} × {:.1}", b[2] - b[0], b[3] - b[1]);
+                labeled(b, &label, egui::Color32::from_rgb(240, 120, 0));
+            }
+        }
+        let [x, y, w, h] = doc.view_box;
+        if self.view_box && w > 0.0 && h > 0.0 {
+            let label = format!("viewBox {} {} {} {}", x, y, w, h);
+            labeled([x, y, x + w, y + h], &label, egui::Color32::from_rgb(160, 80, 240));
+        }
+    }
+}
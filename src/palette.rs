@@ -0,0 +1,107 @@
+use vectorlab_core::{replace_color, Color, Document, PaletteColor};
+
+use crate::tab::Tab;
+
+/// The colors of a tab's document, collected again when it changes.
+#[derive(Default)]
+pub struct Palette {
+    // document revision the colors were collected from, None to collect again
+    revision: Option<u64>,
+    colors: Vec<PaletteColor>,
+    /// the color picked to be replaced and what with
+    replace: Option<(Color, Color)>,
+}
+
+impl Palette {
+    fn update(&mut self, doc: &Document) {
+        if self.revision == Some(doc.revision) {
+            return;
+        }
+        self.colors = doc.palette();
+        self.revision = Some(doc.revision);
+        // gone after an edit
+        if self.replace.is_some_and(|(from, _)| !self.colors.iter().any(|c| c.color == from)) {
+            self.replace = None;
+        }
+    }
+}
+
+fn hex(c: Color) -> String {
+    if c.a < 255 {
+        format!("#{:02x}{:02x}{:02x}{:02x}", c.r, c.g, c.b, c.a)
+    } else {
+        format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b)
+    }
+}
+
+/// The solid colors of the tab's document with how many paths fill and stroke with them.
+/// Clicking one selects those paths and picks it for replacing.
+pub fn palette_panel(ui: &mut egui::Ui, tab: &mut Tab) {
+    let palette = &mut tab.palette;
+    palette.update(&tab.doc);
+    if palette.colors.is_empty() {
+        ui.label("No solid colors");
+        return;
+    }
+    ui.horizontal(|ui| {
+        ui.label(format!("{} colors", palette.colors.len()));
+        if ui.small_button("Copy list").on_hover_text("The colors as hex codes, one per line").clicked() {
+            let list: Vec<String> = palette.colors.iter().map(|c| hex(c.color)).collect();
+            ui.output_mut(|o| o.copied_text = list.join("\n"));
+        }
+    });
+    ui.separator();
+
+    let mut clicked = None;
+    let height = (ui.available_height() - 80.0).max(80.0);
+    egui::ScrollArea::vertical().max_height(height).auto_shrink([false, true]).show(ui, |ui| {
+        for entry in &palette.colors {
+            let c = entry.color;
+            let picked = palette.replace.is_some_and(|(from, _)| from == c);
+            ui.horizontal(|ui| {
+                let (rect, response) = ui.allocate_exact_size(egui::vec2(28.0, 18.0), egui::Sense::click());
+                ui.painter().rect_filled(rect, 2.0, egui::Color32::from_rgba_unmultiplied(c.r, c.g, c.b, c.a));
+                let outline = if picked { ui.visuals().selection.stroke } else { ui.visuals().widgets.noninteractive.bg_stroke };
+                ui.painter().rect_stroke(rect, 2.0, outline);
+                let mut counts = vec![];
+                if !entry.fills.is_empty() {
+                    counts.push(format!("{} fill{}", entry.fills.len(), if entry.fills.len() == 1 { "" } else { "s" }));
+                }
+                if !entry.strokes.is_empty() {
+                    counts.push(format!("{} stroke{}", entry.strokes.len(), if entry.strokes.len() == 1 { "" } else { "s" }));
+                }
+                let label = ui.selectable_label(picked, format!("{}  {}", hex(c), counts.join(", ")));
+                if response.clicked() || label.clicked() {
+                    clicked = Some(entry);
+                }
+            });
+        }
+    });
+    if let Some(entry) = clicked {
+        tab.selection = entry.ids();
+        palette.replace = Some((entry.color, entry.color));
+    }
+
+    ui.separator();
+    let Some((from, to)) = &mut palette.replace else {
+        ui.label("Click a color to select its paths and replace it");
+        return;
+    };
+    let mut replace = false;
+    ui.horizontal(|ui| {
+        ui.label(format!("Replace {} with", hex(*from)));
+        let mut rgba = [to.r, to.g, to.b, to.a];
+        if ui.color_edit_button_srgba_unmultiplied(&mut rgba).changed() {
+            *to = Color::rgba(rgba[0], rgba[1], rgba[2], rgba[3]);
+        }
+        replace = ui.add_enabled(from != to, egui::Button::new("Replace")).on_hover_text("In all paths filled or stroked with it").clicked();
+    });
+    if replace {
+        let (from, to) = (*from, *to);
+        let ids = palette.colors.iter().find(|c| c.color == from).map(PaletteColor::ids).unwrap_or_default();
+        if let Some(edit) = replace_color(&tab.doc, &ids, from, to) {
+            tab.history.push(edit, &mut tab.doc);
+            palette.replace = Some((to, to));
+        }
+    }
+}
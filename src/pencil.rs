@@ -0,0 +1,102 @@
+use vectorlab_core::{add_shape, fit_curve, variable_width_outline, Unit};
+
+use crate::inspector::paint_editor;
+use crate::shapes::ShapeSettings;
+use crate::tab::Tab;
+use crate::units::length_value;
+
+/// Pixels the pointer has to move before the stroke gets another point.
+const MIN_STEP: f32 = 1.5;
+
+/// How the pencil turns strokes into paths. The color and width are those of the shape tool.
+pub struct PencilSettings {
+    /// how far in pixels the curve may stray from the stroke, more for smoother curves
+    pub smoothing: f32,
+    /// pen pressure sets the width, making a filled outline instead of a stroked path
+    pub pressure: bool,
+}
+
+impl Default for PencilSettings {
+    fn default() -> Self {
+        Self { smoothing: 2.0, pressure: true }
+    }
+}
+
+/// A stroke being drawn, in document coordinates, with the pressure at each point, 1 for
+/// pointers without any.
+pub struct PencilStroke {
+    points: Vec<[f32; 2]>,
+    pressures: Vec<f32>,
+}
+
+/// Pencil tool: drag to draw, the stroke is fitted with curves on release and added on top.
+pub fn handle_pencil(ui: &egui::Ui, tab: &mut Tab, settings: &PencilSettings, style: &ShapeSettings, pressure: Option<f32>, canvas: egui::Rect, response: &egui::Response) {
+    let space_held = ui.input(|i| i.key_down(egui::Key::Space));
+    let to_doc = |tab: &Tab, pos: egui::Pos2| tab.view.to_doc([pos.x - canvas.min.x, pos.y - canvas.min.y]);
+    if response.drag_started_by(egui::PointerButton::Primary) && !space_held {
+        if let Some(origin) = ui.input(|i| i.pointer.press_origin()) {
+            let start = to_doc(tab, origin);
+            tab.pencil = Some(PencilStroke { points: vec![start], pressures: vec![pressure.unwrap_or(1.0)] });
+        }
+    }
+    let zoom = tab.view.zoom;
+    let dragged = response.interact_pointer_pos().filter(|_| response.dragged_by(egui::PointerButton::Primary));
+    let dragged = dragged.map(|pos| to_doc(tab, pos));
+    if let (Some(stroke), Some(p)) = (&mut tab.pencil, dragged) {
+        let last = stroke.points[stroke.points.len() - 1];
+        if (p[0] - last[0]).hypot(p[1] - last[1]) * zoom >= MIN_STEP {
+            stroke.points.push(p);
+            stroke.pressures.push(pressure.unwrap_or(1.0));
+        }
+    }
+    if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+        tab.pencil = None;
+    } else if response.drag_stopped() {
+        if let Some(stroke) = tab.pencil.take() {
+            finish(tab, settings, style, stroke);
+        }
+    }
+}
+
+fn finish(tab: &mut Tab, settings: &PencilSettings, style: &ShapeSettings, stroke: PencilStroke) {
+    let tolerance = settings.smoothing.max(0.1) / tab.view.zoom;
+    let mm = tab.doc.mm_per_unit();
+    let width = style.stroke_width * 2.0 / (mm[0] + mm[1]).max(f32::EPSILON);
+    // the fill color if the stroke is switched off
+    let pen = style.stroke.clone().or_else(|| style.fill.clone());
+    let edit = if settings.pressure && stroke.pressures.iter().any(|&p| p < 0.99) {
+        let widths: Vec<f32> = stroke.pressures.iter().map(|&p| width * p.max(0.1)).collect();
+        let segments = variable_width_outline(&stroke.points, &widths, tolerance);
+        if segments.is_empty() {
+            return;
+        }
+        add_shape(&tab.doc, segments, pen, None, 0.0)
+    } else {
+        let segments = fit_curve(&stroke.points, tolerance);
+        if segments.is_empty() {
+            return;
+        }
+        add_shape(&tab.doc, segments, None, pen, width)
+    };
+    tab.selection.clear();
+    tab.push_and_select_new(Box::new(edit));
+}
+
+/// The stroke so far, as drawn.
+pub fn draw_pencil(painter: &egui::Painter, canvas: egui::Rect, tab: &Tab) {
+    let Some(stroke) = &tab.pencil else { return };
+    let points = stroke.points.iter().map(|&p| egui::Pos2::from(tab.view.to_screen(p)) + canvas.min.to_vec2()).collect();
+    painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, egui::Color32::from_rgb(0, 160, 255))));
+}
+
+/// Smoothing, pressure and the pen, for the toolbar.
+pub fn pencil_options(ui: &mut egui::Ui, settings: &mut PencilSettings, style: &mut ShapeSettings, unit: Unit) {
+    ui.label("Smoothing");
+    ui.add(egui::DragValue::new(&mut settings.smoothing).speed(0.1).range(0.1..=50.0).suffix(" px"))
+        .on_hover_text("How far the curves may stray from the stroke, more gives smoother curves with fewer points");
+    ui.checkbox(&mut settings.pressure, "Pressure").on_hover_text("A pen's pressure sets the width, the stroke becomes a filled outline");
+    ui.separator();
+    ui.label("Pen");
+    paint_editor(ui, &mut style.stroke);
+    ui.add(length_value(&mut style.stroke_width, unit, 0.01, 0.0..=100.0));
+}
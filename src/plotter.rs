@@ -0,0 +1,209 @@
+use std::error::Error;
+use std::io::Write;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use vectorlab_core::{ebb_move, pen_command, plot_job, Document, PlotJob, PlotterSettings, Unit};
+
+use crate::job::{job_progress, JobProgress, RunningJob};
+use crate::serial::{port_field, serial_ports, SerialPort};
+use crate::units::length_value;
+
+/// How long the EBB may take to answer beyond the move it is busy with, in ms.
+const REPLY_TIMEOUT: u64 = 3000;
+
+/// An EiBotBoard, the controller of AxiDraw plotters.
+struct Ebb {
+    port: SerialPort,
+}
+
+impl Ebb {
+    fn open(port: &str) -> Result<Self, Box<dyn Error>> {
+        let mut ebb = Self { port: SerialPort::open(port)? };
+        let version = ebb.command("V", 0)?;
+        if !version.contains("EBB") {
+            return Err(format!("{} is not an EiBotBoard, it answered {:?}", port, version).into());
+        }
+        Ok(ebb)
+    }
+
+    /// Send one command and return its first line of reply. The EBB only answers queued moves
+    /// once there is room for them, so it is given `busy` ms more.
+    fn command(&mut self, command: &str, busy: u32) -> Result<String, Box<dyn Error>> {
+        self.port.write_all(format!("{}\r", command).as_bytes())?;
+        let deadline = Instant::now() + Duration::from_millis(busy as u64 + REPLY_TIMEOUT);
+        loop {
+            if let Some(line) = self.port.read_line()? {
+                let reply = line.trim();
+                if reply.starts_with('!') {
+                    return Err(format!("{}: {}", command, reply).into());
+                }
+                if !reply.is_empty() {
+                    return Ok(reply.to_string());
+                }
+            }
+            if Instant::now() > deadline {
+                return Err(format!("{}: the plotter does not answer", command).into());
+            }
+        }
+    }
+}
+
+/// A job being sent to the plotter on a worker thread.
+pub struct RunningPlot {
+    pub job: RunningJob,
+    result: Receiver<Result<(), String>>,
+}
+
+impl RunningPlot {
+    fn start(port: String, plot: PlotJob, settings: PlotterSettings, revision: u64) -> Self {
+        let contours = plot.contours.iter().map(|(_, c)| c.clone()).collect();
+        let job = RunningJob::new(contours, plot.steps.iter().map(|s| (s.contour, s.duration)), revision);
+        let (sender, result) = mpsc::channel();
+        let progress = job.progress.clone();
+        thread::spawn(move || {
+            let _ = sender.send(send_job(&port, &plot, &settings, &progress).map_err(|e| e.to_string()));
+        });
+        Self { job, result }
+    }
+
+    /// None while the plotter is busy, then whether the job went through.
+    fn poll(&self) -> Option<Result<(), String>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err("plotter thread crashed".to_string())),
+        }
+    }
+}
+
+/// Send the steps in order, holding still while paused, and take the pen up and home when
+/// aborted.
+pub fn send_job(port: &str, job: &PlotJob, settings: &PlotterSettings, progress: &JobProgress) -> Result<(), Box<dyn Error>> {
+    let mut ebb = Ebb::open(port)?;
+    let mut pen_down = false;
+    for (i, step) in job.steps.iter().enumerate() {
+        if progress.paused() {
+            if pen_down {
+                ebb.command(&pen_command(true, settings.pen_delay), 0)?;
+            }
+            while progress.paused() && !progress.aborted() {
+                thread::sleep(Duration::from_millis(50));
+            }
+            if pen_down && !progress.aborted() {
+                ebb.command(&pen_command(false, settings.pen_delay), settings.pen_delay)?;
+            }
+        }
+        if progress.aborted() {
+            let at = i.checked_sub(1).map_or([0, 0], |i| job.steps[i].position);
+            ebb.command(&pen_command(true, settings.pen_delay), 0)?;
+            if let Some((home, duration)) = ebb_move(at, [0, 0], settings.speed_up) {
+                ebb.command(&home, duration)?;
+            }
+            ebb.command("EM,0,0", 0)?;
+            return Err("aborted".into());
+        }
+        let busy = i.checked_sub(1).map_or(0, |i| job.steps[i].duration);
+        ebb.command(&step.command, busy)?;
+        if step.command.starts_with("SP,") {
+            pen_down = step.command.starts_with("SP,0");
+        }
+        progress.done.store(i + 1, Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+/// The File > Plot window, on a copy of the plotter settings.
+pub struct Plotter {
+    port: String,
+    draft: PlotterSettings,
+    pub running: Option<RunningPlot>,
+    /// how the last plot went
+    message: Option<String>,
+}
+
+impl Plotter {
+    pub fn new(port: &str, settings: &PlotterSettings) -> Self {
+        let port = if port.is_empty() { serial_ports().into_iter().next().unwrap_or_default() } else { port.to_string() };
+        Self { port, draft: settings.clone(), running: None, message: None }
+    }
+}
+
+/// Show the window while `plotter` is Some, it stays open as long as a plot runs. Returns
+/// the port and settings when a plot starts, to keep them.
+pub fn plotter_window(ctx: &egui::Context, plotter: &mut Option<Plotter>, doc: Option<&Document>, unit: Unit) -> Option<(String, PlotterSettings)> {
+    let state = plotter.as_mut()?;
+    if let Some(result) = state.running.as_ref().and_then(RunningPlot::poll) {
+        state.message = Some(match result {
+            Ok(()) => "Done".to_string(),
+            Err(e) => format!("Stopped: {}", e),
+        });
+        state.running = None;
+    }
+
+    let mut open = true;
+    let mut started = None;
+    let mut window = egui::Window::new("Plot").collapsible(false).resizable(false);
+    if state.running.is_none() {
+        window = window.open(&mut open);
+    }
+    window.show(ctx, |ui| {
+        if let Some(plot) = &state.running {
+            job_progress(ui, &plot.job, "Lifts the pen until resumed, abort takes it home");
+            return;
+        }
+
+        let draft = &mut state.draft;
+        egui::Grid::new("plotter").num_columns(2).show(ui, |ui| {
+            ui.label("Port");
+            port_field(ui, &mut state.port);
+            ui.end_row();
+            ui.label("Speed down / up");
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut draft.speed_down).speed(1.0).range(1.0..=400.0).suffix(" mm/s"));
+                ui.add(egui::DragValue::new(&mut draft.speed_up).speed(1.0).range(1.0..=400.0).suffix(" mm/s"));
+            });
+            ui.end_row();
+            ui.label("Pen up / down");
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut draft.pen_up).range(0.0..=100.0).suffix(" %"));
+                ui.add(egui::DragValue::new(&mut draft.pen_down).range(0.0..=100.0).suffix(" %"));
+            });
+            ui.end_row();
+            ui.label("Pen delay");
+            ui.add(egui::DragValue::new(&mut draft.pen_delay).range(0..=5000).suffix(" ms")).on_hover_text("Time the pen gets to rise or drop");
+            ui.end_row();
+            ui.label("Curve tolerance");
+            ui.add(length_value(&mut draft.tolerance, unit, 0.001, 0.001..=10.0));
+            ui.end_row();
+            ui.label("");
+            ui.checkbox(&mut draft.optimize, "Optimize").on_hover_text("Reorder and reverse the paths so the pen travels less");
+            ui.end_row();
+        });
+        ui.weak("Home the pen at the top left corner of the page before plotting.");
+        if let Some(message) = &state.message {
+            ui.label(message);
+        }
+        ui.separator();
+        let Some(doc) = doc else { return };
+        if ui.add_enabled(!state.port.trim().is_empty(), egui::Button::new("Plot")).clicked() {
+            let job = plot_job(doc, draft);
+            if job.contours.is_empty() {
+                state.message = Some("Nothing to plot".to_string());
+            } else if job.leaves_page() {
+                state.message = Some("Some paths are left of or above the page, where the plotter can't go".to_string());
+            } else {
+                let port = state.port.trim().to_string();
+                state.message = None;
+                state.running = Some(RunningPlot::start(port.clone(), job, draft.clone(), doc.revision));
+                started = Some((port, draft.clone()));
+            }
+        }
+    });
+    if !open {
+        *plotter = None;
+    }
+    started
+}
@@ -0,0 +1,138 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+use vectorlab_core::{load_str, to_svg_string, Document, LoadOptions};
+use wasmtime::{Config, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use crate::tab::Tab;
+
+/// Instructions a plugin may run for one document, so one that is stuck ends with an error
+/// even when nobody presses Cancel.
+const FUEL: u64 = 20_000_000_000;
+
+/// Memory a plugin may grow to, in bytes.
+const MEMORY_LIMIT: usize = 1 << 30;
+
+/// Longest SVG a plugin may return, in bytes.
+const OUTPUT_LIMIT: usize = 256 << 20;
+
+/// Where the plugins of the Path menu are, `.wasm` files in the config dir.
+pub fn plugin_dir() -> Option<PathBuf> {
+    directories::ProjectDirs::from("de", "jnweiger", "VectorLab").map(|dirs| dirs.config_dir().join("plugins"))
+}
+
+/// The plugins in [`plugin_dir`], sorted by name.
+pub fn installed_plugins() -> Vec<PathBuf> {
+    let Some(entries) = plugin_dir().and_then(|dir| fs::read_dir(dir).ok()) else { return vec![] };
+    let mut plugins: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|e| e.eq_ignore_ascii_case("wasm")))
+        .collect();
+    plugins.sort();
+    plugins
+}
+
+/// For the menu and the undo step, the file name without `.wasm`.
+pub fn plugin_name(path: &Path) -> String {
+    path.file_stem().map_or_else(|| path.display().to_string(), |s| s.to_string_lossy().into_owned())
+}
+
+/// A plugin running on a worker thread on the drawing of a tab, so the window stays
+/// responsive and it can be cancelled.
+pub struct PluginRun {
+    pub name: String,
+    /// [`Tab::id`] of the tab it runs on
+    pub tab: u64,
+    /// history version of the drawing it got, the result only replaces that one
+    pub version: u64,
+    engine: Engine,
+    cancelled: Arc<AtomicBool>,
+    result: Receiver<Result<Document, String>>,
+}
+
+impl PluginRun {
+    pub fn start(path: &Path, tab: &Tab, opts: &LoadOptions) -> Result<Self, Box<dyn Error>> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        config.epoch_interruption(true);
+        let engine = Engine::new(&config)?;
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let name = plugin_name(path);
+        let (path, input, opts) = (path.to_path_buf(), to_svg_string(&tab.doc), opts.clone());
+        let (sender, result) = mpsc::channel();
+        let (worker, stop) = (engine.clone(), cancelled.clone());
+        thread::spawn(move || {
+            let _ = sender.send(run_plugin(&worker, &stop, &path, &input, &opts).map_err(|e| e.to_string()));
+        });
+        Ok(Self { name, tab: tab.id, version: tab.history.version(), engine, cancelled, result })
+    }
+
+    /// Stop the plugin where it is, [`PluginRun::poll`] then gives an error.
+    pub fn cancel(&mut self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.engine.increment_epoch();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// The drawing or the error once the plugin is done. Cancelled runs are done right away,
+    /// the worker stops on its own.
+    pub fn poll(&self) -> Option<Result<Document, String>> {
+        if self.is_cancelled() {
+            return Some(Err("cancelled".to_string()));
+        }
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err("plugin thread crashed".to_string())),
+        }
+    }
+}
+
+/// Run the WebAssembly plugin at `path` on the SVG `input` and load what it makes of it.
+/// `engine` needs fuel and epoch interruption, the next epoch or `cancelled` stops the plugin.
+///
+/// A plugin exports its `memory`, `alloc(len: u32) -> u32` that reserves `len` bytes for the
+/// input and `transform(ptr: u32, len: u32) -> u64` that gets the document as SVG there and
+/// returns where the SVG it made is, the pointer in the high and the length in the low 32
+/// bits. It is given no imports at all, so it can't touch files, the network or anything
+/// else outside of its own memory, and it only gets so much fuel and memory.
+fn run_plugin(engine: &Engine, cancelled: &AtomicBool, path: &Path, input: &str, opts: &LoadOptions) -> Result<Document, Box<dyn Error>> {
+    let module = Module::new(engine, fs::read(path)?)?;
+
+    let limits = StoreLimitsBuilder::new().memory_size(MEMORY_LIMIT).instances(1).build();
+    let mut store: Store<StoreLimits> = Store::new(engine, limits);
+    store.limiter(|limits| limits);
+    store.set_fuel(FUEL)?;
+    store.set_epoch_deadline(1);
+    // the deadline counts from now, a Cancel before it would be missed
+    if cancelled.load(Ordering::SeqCst) {
+        return Err("cancelled".into());
+    }
+    let instance = Linker::new(engine).instantiate(&mut store, &module)?;
+    let memory = instance.get_memory(&mut store, "memory").ok_or("The plugin exports no memory")?;
+    let alloc = instance.get_typed_func::<u32, u32>(&mut store, "alloc")?;
+    let transform = instance.get_typed_func::<(u32, u32), u64>(&mut store, "transform")?;
+
+    let len = u32::try_from(input.len()).map_err(|_| "The drawing is too big for a plugin")?;
+    let ptr = alloc.call(&mut store, len)?;
+    memory.write(&mut store, ptr as usize, input.as_bytes())?;
+    let result = transform.call(&mut store, (ptr, len))?;
+
+    let (ptr, len) = ((result >> 32) as usize, (result & 0xffff_ffff) as usize);
+    if len > OUTPUT_LIMIT {
+        return Err("The plugin returned too much".into());
+    }
+    // within its own memory, not copied before it is known to be there
+    let output = ptr.checked_add(len).and_then(|end| memory.data(&store).get(ptr..end)).ok_or("The plugin returned SVG outside of its memory")?;
+    let svg = std::str::from_utf8(output).map_err(|_| "The plugin returned no SVG text")?;
+    Ok(load_str(svg, opts)?)
+}
@@ -0,0 +1,105 @@
+use crate::keys::{parse_shortcut, Action};
+use crate::scale::calibration;
+use crate::settings::Settings;
+use crate::units::unit_buttons;
+
+/// The Preferences window. It works on a copy of the settings, OK hands that back.
+pub struct Preferences {
+    draft: Settings,
+    // what is typed into the shortcut fields, in the order of Action::ALL
+    shortcuts: Vec<String>,
+}
+
+impl Preferences {
+    pub fn new(settings: &Settings) -> Self {
+        let shortcuts = Action::ALL.iter().map(|&a| settings.keys.text(a)).collect();
+        Self { draft: settings.clone(), shortcuts }
+    }
+}
+
+/// Show the window while `preferences` is Some. Returns the edited settings when OK is
+/// pressed, shortcuts that don't parse keep their previous keys. Recent files and the window
+/// geometry are not edited here, the caller keeps its own.
+pub fn preferences_window(ctx: &egui::Context, preferences: &mut Option<Preferences>) -> Option<Settings> {
+    let prefs = preferences.as_mut()?;
+    let mut open = true;
+    let mut done = None;
+    egui::Window::new("Preferences").open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+        let draft = &mut prefs.draft;
+        ui.strong("Display");
+        ui.horizontal(|ui| {
+            ui.label("Background");
+            ui.vertical(|ui| draft.background.menu(ui));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Presentation");
+            ui.vertical(|ui| draft.presentation_background.menu(ui));
+        });
+        ui.checkbox(&mut draft.antialiasing, "Antialiasing").on_hover_text("Smooth edges, at the cost of some speed");
+        ui.horizontal(|ui| {
+            ui.label("Curve tolerance");
+            ui.add(egui::DragValue::new(&mut draft.curve_tolerance).speed(0.01).range(0.01..=10.0).suffix(" px"));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Units");
+            unit_buttons(ui, &mut draft.unit);
+        })
+        .response
+        .on_hover_text("Of the rulers, measurements and length fields");
+        ui.label("Screen resolution").on_hover_text("For showing drawings at their real size");
+        calibration(ui, &mut draft.screen_dpi, draft.unit);
+        ui.separator();
+
+        ui.strong("Loading");
+        ui.checkbox(&mut draft.strict_parsing, "Strict parsing").on_hover_text("List every spec violation with its line and column");
+        ui.horizontal(|ui| {
+            ui.label("Pixels per inch");
+            ui.add(egui::DragValue::new(&mut draft.dpi).speed(1.0).range(10.0..=10000.0));
+        })
+        .response
+        .on_hover_text("Of files sized in px or without a unit. 96 as in browsers, 90 for old Inkscape files, 72 for some others");
+        ui.separator();
+
+        ui.strong("Grid");
+        draft.grid.menu(ui);
+        ui.separator();
+
+        ui.strong("Keyboard shortcuts");
+        egui::ScrollArea::vertical().max_height(220.0).show(ui, |ui| {
+            egui::Grid::new("shortcuts").num_columns(2).striped(true).show(ui, |ui| {
+                for (action, text) in Action::ALL.into_iter().zip(&mut prefs.shortcuts) {
+                    ui.label(action.label());
+                    let valid = parse_shortcut(text).is_some();
+                    let mut edit = egui::TextEdit::singleline(text).desired_width(120.0);
+                    if !valid {
+                        edit = edit.text_color(ui.visuals().error_fg_color);
+                    }
+                    ui.add(edit).on_hover_text("e.g. Ctrl+Shift+S, F or PageDown");
+                    ui.end_row();
+                }
+            });
+        });
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            if ui.button("OK").clicked() {
+                for (action, text) in Action::ALL.into_iter().zip(&prefs.shortcuts) {
+                    if let Some(shortcut) = parse_shortcut(text) {
+                        prefs.draft.keys.set(action, shortcut);
+                    }
+                }
+                done = Some(Some(prefs.draft.clone()));
+            }
+            if ui.button("Cancel").clicked() {
+                done = Some(None);
+            }
+            if ui.button("Restore defaults").on_hover_text("Recent files and the window position stay").clicked() {
+                *prefs = Preferences::new(&Settings::default());
+            }
+        });
+    });
+    if !open || done.is_some() {
+        *preferences = None;
+    }
+    done.flatten()
+}
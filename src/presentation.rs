@@ -0,0 +1,44 @@
+use crate::background::Background;
+use crate::canvas::{draw_document, ImageCache};
+use crate::tab::Tab;
+
+/// Space around the artwork in presentation mode, in pixels.
+const MARGIN: f32 = 24.0;
+
+/// Only the artwork, fitted into the whole window, without selection, guides or any other
+/// decoration. Left / right arrow step to the previous / next file, the caller does that
+/// with the returned -1 or 1.
+pub fn presentation_view(ui: &mut egui::Ui, tab: &mut Tab, background: &Background, curve_tolerance: f32, images: &mut ImageCache) -> isize {
+    let (rect, _) = ui.allocate_exact_size(ui.available_size(), egui::Sense::hover());
+    draw_slide(ui, rect, tab, background, curve_tolerance, images, 1.0);
+    ui.input_mut(|i| {
+        if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowRight) {
+            1
+        } else if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowLeft) {
+            -1
+        } else {
+            0
+        }
+    })
+}
+
+/// `tab` fitted into `rect` on `background`, both at `opacity` for fading.
+pub fn draw_slide(ui: &egui::Ui, rect: egui::Rect, tab: &mut Tab, background: &Background, curve_tolerance: f32, images: &mut ImageCache, opacity: f32) {
+    let mut painter = ui.painter_at(rect);
+    painter.set_opacity(opacity);
+    background.paint(&painter, rect);
+
+    // refitted every frame, the window may just be going fullscreen
+    if let Some(bbox) = tab.doc.bbox() {
+        tab.view.fit(bbox, [rect.width(), rect.height()], MARGIN);
+    }
+    tab.canvas_size = [rect.width(), rect.height()];
+    tab.doc.reflatten(curve_tolerance / tab.view.zoom);
+
+    if tab.gpu.update(&tab.doc) {
+        tab.gpu.paint(&painter, rect, &tab.view, images);
+    } else {
+        tab.index.update(&tab.doc);
+        draw_document(&painter, &tab.doc, &tab.index, &tab.view, rect.min.to_vec2(), images);
+    }
+}
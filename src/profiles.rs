@@ -0,0 +1,196 @@
+use vectorlab_core::{Document, GcodeOrigin, MachineKind, MachineProfile, Unit, ViewTransform};
+
+use crate::units::length_value;
+
+/// The File > Machine profiles window, on copies of the profiles. OK hands them back.
+pub struct ProfileEditor {
+    profiles: Vec<MachineProfile>,
+    active: String,
+    /// the profile being edited
+    selected: usize,
+}
+
+impl ProfileEditor {
+    pub fn new(profiles: &[MachineProfile], active: &str) -> Self {
+        let selected = profiles.iter().position(|p| p.name == active).unwrap_or(0);
+        Self { profiles: profiles.to_vec(), active: active.to_string(), selected }
+    }
+
+    /// `base` with a number appended until no profile has it.
+    fn unique_name(&self, base: &str) -> String {
+        let taken = |name: &str| self.profiles.iter().any(|p| p.name == name);
+        if !taken(base) {
+            return base.to_string();
+        }
+        (2..).map(|i| format!("{} {}", base, i)).find(|name| !taken(name)).unwrap_or_default()
+    }
+}
+
+/// Show the window while `editor` is Some. Returns the profiles and the name of the active
+/// one when OK is pressed.
+pub fn profiles_window(ctx: &egui::Context, editor: &mut Option<ProfileEditor>, unit: Unit) -> Option<(Vec<MachineProfile>, String)> {
+    let state = editor.as_mut()?;
+    let mut open = true;
+    let mut done = None;
+    egui::Window::new("Machine profiles").open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Active");
+            let none = state.active.is_empty();
+            egui::ComboBox::from_id_source("active_profile").selected_text(if none { "None" } else { state.active.as_str() }).show_ui(ui, |ui| {
+                ui.selectable_value(&mut state.active, String::new(), "None");
+                for profile in &state.profiles {
+                    ui.selectable_value(&mut state.active, profile.name.clone(), &profile.name);
+                }
+            });
+        })
+        .response
+        .on_hover_text("Its work area is drawn on the canvas, its setup is taken over by the exporters and devices");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.menu_button("New", |ui| {
+                for kind in MachineKind::ALL {
+                    if ui.button(kind.label()).clicked() {
+                        let name = state.unique_name(kind.label());
+                        state.profiles.push(MachineProfile::new(&name, kind));
+                        state.selected = state.profiles.len() - 1;
+                        ui.close_menu();
+                    }
+                }
+            });
+            let selected = state.profiles.get(state.selected).cloned();
+            if ui.add_enabled(selected.is_some(), egui::Button::new("Duplicate")).clicked() {
+                if let Some(profile) = selected.clone() {
+                    let name = state.unique_name(&profile.name);
+                    state.profiles.push(MachineProfile { name, ..profile });
+                    state.selected = state.profiles.len() - 1;
+                }
+            }
+            if ui.add_enabled(selected.is_some(), egui::Button::new("Delete")).clicked() {
+                let removed = state.profiles.remove(state.selected);
+                if removed.name == state.active {
+                    state.active.clear();
+                }
+                state.selected = state.selected.min(state.profiles.len().saturating_sub(1));
+            }
+        });
+
+        if state.profiles.is_empty() {
+            ui.weak("No profiles yet, add one with New.");
+        } else {
+            ui.horizontal_top(|ui| {
+                ui.vertical(|ui| {
+                    ui.set_width(140.0);
+                    egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                        for (i, profile) in state.profiles.iter().enumerate() {
+                            let label = if profile.name == state.active { format!("{} ✔", profile.name) } else { profile.name.clone() };
+                            ui.selectable_value(&mut state.selected, i, label);
+                        }
+                    });
+                });
+                ui.separator();
+                let active = &mut state.active;
+                let profile = &mut state.profiles[state.selected];
+                let was_active = profile.name == *active;
+                profile_fields(ui, profile, unit);
+                // follow a rename
+                if was_active {
+                    active.clone_from(&profile.name);
+                }
+            });
+        }
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            let names_ok = state.profiles.iter().enumerate().all(|(i, p)| !p.name.trim().is_empty() && state.profiles[..i].iter().all(|q| q.name != p.name));
+            if ui.add_enabled(names_ok, egui::Button::new("OK")).on_disabled_hover_text("Every profile needs a name of its own").clicked() {
+                done = Some(Some((state.profiles.clone(), state.active.clone())));
+            }
+            if ui.button("Cancel").clicked() {
+                done = Some(None);
+            }
+        });
+    });
+    if !open || done.is_some() {
+        *editor = None;
+    }
+    done.flatten()
+}
+
+fn profile_fields(ui: &mut egui::Ui, profile: &mut MachineProfile, unit: Unit) {
+    egui::Grid::new("profile").num_columns(2).show(ui, |ui| {
+        ui.label("Name");
+        ui.text_edit_singleline(&mut profile.name);
+        ui.end_row();
+        ui.label("Machine");
+        ui.horizontal(|ui| {
+            for kind in MachineKind::ALL {
+                ui.radio_value(&mut profile.kind, kind, kind.label());
+            }
+        });
+        ui.end_row();
+        ui.label("Work area");
+        ui.horizontal(|ui| {
+            ui.add(length_value(&mut profile.bed[0], unit, 1.0, 1.0..=10000.0));
+            ui.label("×");
+            ui.add(length_value(&mut profile.bed[1], unit, 1.0, 1.0..=10000.0));
+        });
+        ui.end_row();
+        ui.label("Origin");
+        egui::ComboBox::from_id_source("profile_origin").selected_text(profile.origin.label()).show_ui(ui, |ui| {
+            for origin in GcodeOrigin::ALL {
+                ui.selectable_value(&mut profile.origin, origin, origin.label());
+            }
+        });
+        ui.end_row();
+        ui.label("Axes");
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut profile.invert_x, "X points left");
+            ui.checkbox(&mut profile.invert_y, "Y points down");
+        });
+        ui.end_row();
+        ui.label("Speed down / up");
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut profile.draw_speed).speed(1.0).range(1.0..=1000.0).suffix(" mm/s"));
+            ui.add(egui::DragValue::new(&mut profile.travel_speed).speed(1.0).range(1.0..=1000.0).suffix(" mm/s"));
+        });
+        ui.end_row();
+        ui.label("Acceleration");
+        ui.add(egui::DragValue::new(&mut profile.acceleration).speed(10.0).range(10.0..=100000.0).suffix(" mm/s²"));
+        ui.end_row();
+        match profile.kind {
+            MachineKind::Gcode => {
+                ui.label("Z up / down");
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut profile.pen_up).speed(0.1).suffix(" mm"));
+                    ui.add(egui::DragValue::new(&mut profile.pen_down).speed(0.1).suffix(" mm"));
+                });
+                ui.end_row();
+            }
+            MachineKind::AxiDraw => {
+                ui.label("Pen up / down");
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut profile.pen_up).range(0.0..=100.0).suffix(" %"));
+                    ui.add(egui::DragValue::new(&mut profile.pen_down).range(0.0..=100.0).suffix(" %"));
+                });
+                ui.end_row();
+            }
+            MachineKind::Hpgl => {}
+        }
+    });
+}
+
+/// The work area of `profile` under the artwork, red where the drawing reaches past it.
+/// `content` is the bounding box of the drawing.
+pub fn draw_work_area(painter: &egui::Painter, canvas: egui::Rect, doc: &Document, view: &ViewTransform, profile: &MachineProfile, content: Option<[f32; 4]>) {
+    let [x0, y0, x1, y1] = profile.work_area(doc);
+    let to_screen = |p: [f32; 2]| egui::Pos2::from(view.to_screen(p)) + canvas.min.to_vec2();
+    let corners: Vec<_> = [[x0, y0], [x1, y0], [x1, y1], [x0, y1]].into_iter().map(to_screen).collect();
+    let outside = content.is_some_and(|[a, b, c, d]| a < x0 || b < y0 || c > x1 || d > y1);
+    let color = if outside { egui::Color32::from_rgb(230, 60, 60) } else { egui::Color32::from_rgb(80, 160, 255) };
+    painter.add(egui::Shape::convex_polygon(corners.clone(), color.gamma_multiply(0.06), egui::Stroke::NONE));
+    painter.add(egui::Shape::dashed_line(&[corners.as_slice(), &corners[..1]].concat(), egui::Stroke::new(1.5, color), 8.0, 4.0));
+    let label = if outside { format!("{} — the drawing reaches past it", profile.name) } else { profile.name.clone() };
+    let top_left = corners.iter().fold(corners[0], |a, &b| if b.y < a.y || (b.y == a.y && b.x < a.x) { b } else { a });
+    painter.text(top_left + egui::vec2(4.0, -2.0), egui::Align2::LEFT_BOTTOM, label, egui::FontId::proportional(12.0), color);
+}
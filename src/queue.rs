@@ -0,0 +1,378 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+
+use vectorlab_core::{hpgl_job, job_contours, layer_documents, machine_origin, plot_job, to_gcode, tool_moves, Document, GcodeSettings, HpglSettings, LoadOptions, PlotterSettings, SaveOptions};
+
+use crate::hpgl::HpglOutput;
+use crate::job::JobProgress;
+use crate::settings::Settings;
+
+/// What a queued job does with its document, with the settings as they were when it was
+/// queued.
+#[derive(Clone)]
+pub enum QueueAction {
+    SaveSvg(SaveOptions),
+    SavePdf,
+    ExportGcode(GcodeSettings),
+    SaveHpgl(HpglSettings),
+    /// on the AxiDraw at the port
+    Plot(String, PlotterSettings),
+    SendHpgl(HpglOutput, HpglSettings),
+}
+
+/// The kinds of [`QueueAction`] to pick from.
+const TARGETS: [&str; 6] = ["SVG", "PDF", "G-code", "HPGL file", "Plot on AxiDraw", "Send to HPGL plotter"];
+
+impl QueueAction {
+    /// The `TARGETS` entry `target` with the current settings.
+    fn new(target: usize, settings: &Settings) -> Self {
+        match target {
+            0 => Self::SaveSvg(SaveOptions { text_to_paths: settings.text_to_paths }),
+            1 => Self::SavePdf,
+            2 => Self::ExportGcode(settings.gcode.clone()),
+            3 => Self::SaveHpgl(settings.hpgl.clone()),
+            4 => Self::Plot(settings.plotter_port.clone(), settings.plotter.clone()),
+            _ => Self::SendHpgl(settings.hpgl_output.clone(), settings.hpgl.clone()),
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::SaveSvg(_) => TARGETS[0],
+            Self::SavePdf => TARGETS[1],
+            Self::ExportGcode(_) => TARGETS[2],
+            Self::SaveHpgl(_) => TARGETS[3],
+            Self::Plot(..) => TARGETS[4],
+            Self::SendHpgl(..) => TARGETS[5],
+        }
+    }
+
+    /// Of the file written, None for the machines.
+    fn extension(&self) -> Option<&'static str> {
+        match self {
+            Self::SaveSvg(_) => Some("svg"),
+            Self::SavePdf => Some("pdf"),
+            Self::ExportGcode(_) => Some("gcode"),
+            Self::SaveHpgl(_) => Some("plt"),
+            Self::Plot(..) | Self::SendHpgl(..) => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JobStatus {
+    Waiting,
+    Running,
+    Done,
+    Failed(String),
+    Cancelled,
+}
+
+struct QueuedJob {
+    id: u64,
+    name: String,
+    doc: Document,
+    action: QueueAction,
+    /// for the file outputs
+    output: Option<PathBuf>,
+    status: JobStatus,
+    progress: Arc<JobProgress>,
+    /// steps sent to the machine in all, 0 until it starts
+    steps: usize,
+}
+
+#[derive(Default)]
+struct QueueState {
+    jobs: Vec<QueuedJob>,
+    /// the worker starts no further jobs
+    paused: bool,
+    /// pause after every job that went to a machine, to change the paper
+    hold_after_plot: bool,
+    next_id: u64,
+    /// the queue was dropped, the worker ends
+    closed: bool,
+}
+
+type Shared = Arc<(Mutex<QueueState>, Condvar)>;
+
+fn lock(shared: &Shared) -> MutexGuard<'_, QueueState> {
+    shared.0.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Run `doc` through `action`. Machine jobs report their steps through `steps` before
+/// sending and stop when `progress` is aborted.
+fn run_job(doc: &Document, action: &QueueAction, output: Option<&Path>, progress: &JobProgress, steps: impl FnOnce(usize)) -> Result<(), Box<dyn Error>> {
+    let output = || output.ok_or("no output file");
+    match action {
+        QueueAction::SaveSvg(opts) => vectorlab_core::save_file_with(doc, output()?, *opts)?,
+        QueueAction::SavePdf => vectorlab_core::save_pdf(std::slice::from_ref(doc), output()?)?,
+        QueueAction::ExportGcode(gcode) => {
+            let moves = tool_moves(&job_contours(doc, gcode), machine_origin(doc, gcode));
+            fs::write(output()?, to_gcode(doc, &moves, gcode))?;
+        }
+        QueueAction::SaveHpgl(hpgl) => fs::write(output()?, hpgl_job(doc, hpgl).text())?,
+        QueueAction::Plot(port, plotter) => {
+            let job = plot_job(doc, plotter);
+            steps(job.steps.len());
+            crate::plotter::send_job(port, &job, plotter, progress)?;
+        }
+        QueueAction::SendHpgl(out, hpgl) => {
+            let job = hpgl_job(doc, hpgl);
+            steps(job.commands.len());
+            crate::hpgl::send_job(out, &job, progress)?;
+        }
+    }
+    Ok(())
+}
+
+/// Take the first waiting job whenever there is one and the queue is not paused.
+fn worker(shared: Shared) {
+    loop {
+        let (id, doc, action, output, progress) = {
+            let mut state = lock(&shared);
+            let next = loop {
+                if state.closed {
+                    return;
+                }
+                match state.jobs.iter().position(|j| j.status == JobStatus::Waiting) {
+                    Some(i) if !state.paused => break i,
+                    _ => state = shared.1.wait(state).unwrap_or_else(|e| e.into_inner()),
+                }
+            };
+            let job = &mut state.jobs[next];
+            job.status = JobStatus::Running;
+            (job.id, job.doc.clone(), job.action.clone(), job.output.clone(), job.progress.clone())
+        };
+        let set_steps = |steps| {
+            if let Some(job) = lock(&shared).jobs.iter_mut().find(|j| j.id == id) {
+                job.steps = steps;
+            }
+        };
+        let result = run_job(&doc, &action, output.as_deref(), &progress, set_steps);
+        let mut state = lock(&shared);
+        if output.is_none() && state.hold_after_plot {
+            state.paused = true;
+        }
+        if let Some(job) = state.jobs.iter_mut().find(|j| j.id == id) {
+            job.status = match result {
+                Ok(()) => JobStatus::Done,
+                Err(_) if progress.aborted() => JobStatus::Cancelled,
+                Err(e) => JobStatus::Failed(e.to_string()),
+            };
+        }
+    }
+}
+
+/// The File > Job queue window and the worker thread behind it. Documents or their layers
+/// are queued with what to do with them and run one after the other.
+pub struct JobQueue {
+    shared: Shared,
+    /// index into `TARGETS` of what added jobs do
+    target: usize,
+    /// a job per layer instead of one for the document
+    layers: bool,
+    /// where the files go
+    dir: Option<PathBuf>,
+    /// about the last files added
+    message: Option<String>,
+}
+
+impl JobQueue {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        let shared = Shared::default();
+        let worker_shared = shared.clone();
+        thread::spawn(move || worker(worker_shared));
+        Self { shared, target: 0, layers: false, dir, message: None }
+    }
+
+    /// Jobs are waiting or running.
+    pub fn busy(&self) -> bool {
+        lock(&self.shared).jobs.iter().any(|j| matches!(j.status, JobStatus::Waiting | JobStatus::Running))
+    }
+
+    /// Queue `doc`, or each of its layers, under `name`.
+    fn add(&mut self, name: &str, doc: &Document, settings: &Settings) {
+        let action = QueueAction::new(self.target, settings);
+        let docs = if self.layers { layer_documents(doc).into_iter().map(|(layer, doc)| (format!("{}-{}", name, layer), doc)).collect() } else { vec![(name.to_string(), doc.clone())] };
+        let mut state = lock(&self.shared);
+        for (name, doc) in docs {
+            let output = action.extension().and_then(|ext| Some(self.dir.as_ref()?.join(&name).with_extension(ext)));
+            let id = state.next_id;
+            state.next_id += 1;
+            state.jobs.push(QueuedJob { id, name, doc, action: action.clone(), output, status: JobStatus::Waiting, progress: Arc::default(), steps: 0 });
+        }
+        self.shared.1.notify_all();
+    }
+}
+
+impl Drop for JobQueue {
+    fn drop(&mut self) {
+        let mut state = lock(&self.shared);
+        state.closed = true;
+        for job in &state.jobs {
+            job.progress.aborted.store(true, Ordering::Relaxed);
+        }
+        self.shared.1.notify_all();
+    }
+}
+
+/// Show the window while `queue` is Some. It can't be closed while jobs are left, closing it
+/// drops the queue. `current` is the name and document of the active tab.
+pub fn queue_window(ctx: &egui::Context, queue: &mut Option<JobQueue>, current: Option<(&str, &Document)>, settings: &Settings, load_options: &LoadOptions) {
+    let Some(state) = queue.as_mut() else { return };
+    let busy = state.busy();
+    let mut open = true;
+    let mut window = egui::Window::new("Job queue").collapsible(false).resizable(false);
+    if !busy {
+        window = window.open(&mut open);
+    }
+    window.show(ctx, |ui| {
+        egui::Grid::new("queue_add").num_columns(2).show(ui, |ui| {
+            ui.label("Do");
+            egui::ComboBox::from_id_source("queue_target").selected_text(TARGETS[state.target]).show_ui(ui, |ui| {
+                for (i, target) in TARGETS.iter().enumerate() {
+                    ui.selectable_value(&mut state.target, i, *target);
+                }
+            });
+            ui.end_row();
+            let files = state.target < 4;
+            if files {
+                ui.label("Into");
+                ui.horizontal(|ui| {
+                    ui.label(state.dir.as_ref().map_or("no folder yet".to_string(), |d| d.display().to_string()));
+                    if ui.button("Folder…").clicked() {
+                        let mut dialog = rfd::FileDialog::new().set_title("Folder for the queued files");
+                        if let Some(dir) = &state.dir {
+                            dialog = dialog.set_directory(dir);
+                        }
+                        if let Some(dir) = dialog.pick_folder() {
+                            state.dir = Some(dir);
+                        }
+                    }
+                });
+                ui.end_row();
+            }
+            ui.label("");
+            ui.checkbox(&mut state.layers, "A job per layer").on_hover_text("Each top-level group alone on the page");
+            ui.end_row();
+        });
+        ui.weak("Jobs keep the settings of the last export or plot of their kind, or of the active machine profile.");
+        let ready = !(state.target < 4 && state.dir.is_none());
+        ui.horizontal(|ui| {
+            if ui.add_enabled(ready && current.is_some(), egui::Button::new("Add this document")).clicked() {
+                if let Some((name, doc)) = current {
+                    state.add(name, doc, settings);
+                }
+            }
+            if ui.add_enabled(ready, egui::Button::new("Add files…")).clicked() {
+                let paths = rfd::FileDialog::new().set_title("Queue SVG files").add_filter("SVG", &["svg", "svgz"]).pick_files().unwrap_or_default();
+                let mut failed = vec![];
+                for path in paths {
+                    let name = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+                    match vectorlab_core::load_file(&path, load_options) {
+                        Ok(doc) => state.add(&name, &doc, settings),
+                        Err(e) => failed.push(format!("{}: {}", path.display(), e)),
+                    }
+                }
+                state.message = (!failed.is_empty()).then(|| failed.join("\n"));
+            }
+        });
+        if let Some(message) = &state.message {
+            ui.colored_label(ui.visuals().error_fg_color, message);
+        }
+        ui.separator();
+
+        let mut shared = lock(&state.shared);
+        if shared.jobs.is_empty() {
+            ui.weak("Nothing queued");
+        }
+        let (mut remove, mut cancel, mut swap) = (None, None, None);
+        let waiting: Vec<usize> = (0..shared.jobs.len()).filter(|&i| shared.jobs[i].status == JobStatus::Waiting).collect();
+        egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+            egui::Grid::new("queue_jobs").num_columns(4).striped(true).show(ui, |ui| {
+                for (i, job) in shared.jobs.iter().enumerate() {
+                    ui.label(&job.name).on_hover_text(job.output.as_ref().map_or(String::new(), |p| p.display().to_string()));
+                    ui.label(job.action.label());
+                    match &job.status {
+                        JobStatus::Running if job.steps > 0 => {
+                            let done = job.progress.done.load(Ordering::Relaxed).min(job.steps);
+                            ui.add(egui::ProgressBar::new(done as f32 / job.steps as f32).show_percentage().desired_width(120.0));
+                        }
+                        JobStatus::Running => {
+                            ui.spinner();
+                        }
+                        JobStatus::Waiting => {
+                            ui.weak("waiting");
+                        }
+                        JobStatus::Done => {
+                            ui.label("done");
+                        }
+                        JobStatus::Failed(e) => {
+                            ui.colored_label(ui.visuals().error_fg_color, "failed").on_hover_text(e);
+                        }
+                        JobStatus::Cancelled => {
+                            ui.weak("cancelled");
+                        }
+                    }
+                    ui.horizontal(|ui| match job.status {
+                        JobStatus::Waiting => {
+                            let at = waiting.iter().position(|&w| w == i).unwrap_or(0);
+                            if ui.add_enabled(at > 0, egui::Button::new("⏶").small()).on_hover_text("Earlier").clicked() {
+                                swap = Some((i, waiting[at - 1]));
+                            }
+                            if ui.add_enabled(at + 1 < waiting.len(), egui::Button::new("⏷").small()).on_hover_text("Later").clicked() {
+                                swap = Some((i, waiting[at + 1]));
+                            }
+                            if ui.small_button("Cancel").clicked() {
+                                cancel = Some(i);
+                            }
+                        }
+                        JobStatus::Running => {
+                            if ui.small_button("Cancel").on_hover_text("Stop it, a plotter lifts its pen").clicked() {
+                                job.progress.aborted.store(true, Ordering::Relaxed);
+                            }
+                        }
+                        _ => {
+                            if ui.small_button("Remove").clicked() {
+                                remove = Some(i);
+                            }
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+        });
+        if let Some((a, b)) = swap {
+            shared.jobs.swap(a, b);
+        }
+        if let Some(i) = cancel {
+            shared.jobs[i].status = JobStatus::Cancelled;
+        }
+        if let Some(i) = remove {
+            shared.jobs.remove(i);
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            let label = if shared.paused { "Resume" } else { "Pause" };
+            if ui.button(label).on_hover_text("Pausing lets the running job finish and starts no further ones").clicked() {
+                shared.paused = !shared.paused;
+            }
+            ui.checkbox(&mut shared.hold_after_plot, "Pause after each plot").on_hover_text("To change the paper");
+            if ui.button("Clear finished").clicked() {
+                shared.jobs.retain(|j| matches!(j.status, JobStatus::Waiting | JobStatus::Running));
+            }
+        });
+        if shared.paused {
+            ui.weak("Paused");
+        }
+        drop(shared);
+        state.shared.1.notify_all();
+    });
+    if !open {
+        *queue = None;
+    }
+}
@@ -0,0 +1,17 @@
+use std::fs;
+use std::path::PathBuf;
+
+pub const MAX_RECENT_FILES: usize = 10;
+
+/// Older versions kept recently opened files as plain text, one path per line, in the
+/// platform config dir. They are in the settings now, this is read until those have them.
+fn recent_files_path() -> Option<PathBuf> {
+    directories::ProjectDirs::from("de", "jnweiger", "VectorLab").map(|dirs| dirs.config_dir().join("recent.txt"))
+}
+
+pub fn load_recent_files() -> Vec<PathBuf> {
+    recent_files_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|text| text.lines().filter(|l| !l.trim().is_empty()).map(PathBuf::from).take(MAX_RECENT_FILES).collect())
+        .unwrap_or_default()
+}
@@ -0,0 +1,90 @@
+use vectorlab_core::{apply_color_mode, pen_colors, Color, ColorMode, Document, SpatialIndex};
+
+use crate::canvas::{draw_document, ImageCache};
+use crate::tab::Tab;
+
+/// Pens the pen color mode starts with.
+const DEFAULT_PENS: usize = 4;
+
+/// A tab's document recolored for the color preview, made again when it or the mode changes.
+#[derive(Default)]
+pub struct ColorPreview {
+    // document revision and mode the copy was made for
+    made_for: Option<(u64, ColorMode)>,
+    doc: Document,
+    index: SpatialIndex,
+}
+
+/// Draw the tab's document with its colors turned by `mode`, to see what a plotter or a
+/// single-color laser will make of it.
+pub fn draw_recolored(painter: &egui::Painter, tab: &mut Tab, mode: &ColorMode, origin: egui::Vec2, images: &mut ImageCache) {
+    let preview = &mut tab.color_preview;
+    if preview.made_for.as_ref().is_none_or(|(revision, made)| *revision != tab.doc.revision || made != mode) {
+        preview.doc = tab.doc.recolored(mode);
+        preview.made_for = Some((tab.doc.revision, mode.clone()));
+    }
+    preview.index.update(&preview.doc);
+    draw_document(painter, &preview.doc, &preview.index, &tab.view, origin, images);
+}
+
+/// The View > Color preview menu: the mode, its settings, and applying it for good.
+pub fn color_preview_menu(ui: &mut egui::Ui, mode: &mut Option<ColorMode>, tab: Option<&mut Tab>) {
+    let name = mode.as_ref().map(ColorMode::name);
+    if ui.radio(name.is_none(), "Off").clicked() {
+        *mode = None;
+    }
+    if ui.radio(name == Some("Grayscale"), "Grayscale").clicked() {
+        *mode = Some(ColorMode::Grayscale);
+    }
+    if ui.radio(name == Some("Black and white"), "Black and white").on_hover_text("For engraving with a single color").clicked() && name != Some("Black and white") {
+        *mode = Some(ColorMode::Threshold(0.5));
+    }
+    if ui.radio(name == Some("Pen colors"), "Pen colors").on_hover_text("Each color as the nearest of a few pens, for plotting").clicked() && name != Some("Pen colors") {
+        let pens = tab.as_ref().map(|tab| pen_colors(&tab.doc, DEFAULT_PENS)).unwrap_or_default();
+        *mode = Some(ColorMode::Pens(pens));
+    }
+
+    match mode {
+        Some(ColorMode::Threshold(t)) => {
+            ui.separator();
+            ui.add(egui::Slider::new(t, 0.0..=1.0).text("Threshold")).on_hover_text("Colors darker than this turn black, the others white");
+        }
+        Some(ColorMode::Pens(pens)) => {
+            ui.separator();
+            let mut count = pens.len();
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut count).range(1..=16).suffix(" pens"));
+                if ui.button("From the drawing").on_hover_text("The pens that come closest to the colors used most").clicked() {
+                    // picked again below
+                    pens.clear();
+                }
+            });
+            if count != pens.len() {
+                if let Some(tab) = tab.as_ref() {
+                    *pens = pen_colors(&tab.doc, count);
+                }
+                // a drawing with fewer colors than pens
+                pens.resize(count, Color::BLACK);
+            }
+            ui.horizontal_wrapped(|ui| {
+                for pen in pens.iter_mut() {
+                    let mut rgb = [pen.r, pen.g, pen.b];
+                    if ui.color_edit_button_srgb(&mut rgb).changed() {
+                        *pen = Color::rgb(rgb[0], rgb[1], rgb[2]);
+                    }
+                }
+            });
+        }
+        _ => {}
+    }
+
+    ui.separator();
+    let (Some(tab), Some(current)) = (tab, mode.as_ref()) else { return };
+    if ui.button("Apply to the document").on_hover_text("Recolor the paths as shown, images stay as they are").clicked() {
+        if let Some(edit) = apply_color_mode(&tab.doc, current) {
+            tab.history.push(edit, &mut tab.doc);
+        }
+        *mode = None;
+        ui.close_menu();
+    }
+}
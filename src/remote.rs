@@ -0,0 +1,31 @@
+use std::io::Read;
+
+/// Largest download accepted, to not hang on a URL that turns out to be something else.
+const MAX_DOWNLOAD: u64 = 64 * 1024 * 1024;
+
+pub fn is_url(s: &str) -> bool {
+    s.starts_with("http://") || s.starts_with("https://")
+}
+
+/// Download `url` into memory.
+pub fn fetch(url: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let response = ureq::get(url).call()?;
+    let mut data = vec![];
+    response.into_reader().take(MAX_DOWNLOAD).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+pub fn read_stdin() -> std::io::Result<Vec<u8>> {
+    let mut data = vec![];
+    std::io::stdin().read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Tab title for a downloaded file: the last path segment of the URL.
+pub fn url_title(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    match path.trim_end_matches('/').rsplit('/').next() {
+        Some(name) if !name.is_empty() && !name.contains(':') => name.to_string(),
+        _ => url.to_string(),
+    }
+}
@@ -0,0 +1,163 @@
+use vectorlab_core::{Document, Unit, ViewTransform};
+
+use crate::grid::Grid;
+use crate::tab::Tab;
+
+/// Thickness of the rulers along the top and left of the canvas, in pixels.
+pub const RULER_SIZE: f32 = 18.0;
+
+/// Pixels around a guide that pick it up for dragging.
+const GUIDE_REACH: f32 = 4.0;
+
+/// A line across the canvas at a document coordinate, for lining things up by eye.
+#[derive(Clone, Copy, Debug)]
+pub struct Guide {
+    /// at x = `position` if vertical, y = `position` otherwise
+    pub vertical: bool,
+    pub position: f32,
+}
+
+/// Ticks with labels in `unit` on the page of `doc`, at 1, 2 or 5 times a power of ten so
+/// labels stay readable at any zoom, and a mark at the cursor. `canvas` excludes the rulers.
+/// A view rotated by other than a multiple of 90° has no ticks, no document axis runs along
+/// them.
+pub fn draw_rulers(painter: &egui::Painter, canvas: egui::Rect, view: &ViewTransform, doc: &Document, unit: Unit, cursor: Option<[f32; 2]>) {
+    let visuals = painter.ctx().style().visuals.clone();
+    let top = egui::Rect::from_min_max(egui::pos2(canvas.min.x, canvas.min.y - RULER_SIZE), egui::pos2(canvas.max.x, canvas.min.y));
+    let left = egui::Rect::from_min_max(egui::pos2(canvas.min.x - RULER_SIZE, canvas.min.y), egui::pos2(canvas.min.x, canvas.max.y));
+    let corner = egui::Rect::from_min_max(canvas.min - egui::vec2(RULER_SIZE, RULER_SIZE), canvas.min);
+    for rect in [top, left, corner] {
+        painter.rect_filled(rect, 0.0, visuals.extreme_bg_color);
+    }
+
+    let color = visuals.weak_text_color();
+    let font = egui::FontId::proportional(9.0);
+    for vertical in [false, true] {
+        if !view.is_axis_aligned() {
+            break;
+        }
+        // along x for the top ruler, along y for the left one
+        let axis = usize::from(vertical);
+        let (start, length) = if vertical { (canvas.min.y, canvas.height()) } else { (canvas.min.x, canvas.width()) };
+        // pixels per `unit` along the document axis of the ruler, negative if flipped
+        let (doc_axis, scale) = view.doc_axis(axis);
+        let scale = scale * unit.mm() / doc.mm_per_unit()[doc_axis];
+        let (major, subdivisions) = tick_steps(scale.abs());
+        let minor = major / subdivisions as f32;
+        let decimals = (-major.log10().floor()).max(0.0) as usize;
+        let (v0, v1) = (-view.pan[axis] / scale, (length - view.pan[axis]) / scale);
+        let first = (v0.min(v1) / minor).ceil() as i64;
+        let last = (v0.max(v1) / minor).floor() as i64;
+        for k in first..=last {
+            let value = k as f32 * minor;
+            let pos = start + value * scale + view.pan[axis];
+            let is_major = k.rem_euclid(subdivisions) == 0;
+            let tick = if is_major { RULER_SIZE } else { RULER_SIZE * 0.3 };
+            let (a, b) = if vertical {
+                (egui::pos2(canvas.min.x - tick, pos), egui::pos2(canvas.min.x, pos))
+            } else {
+                (egui::pos2(pos, canvas.min.y - tick), egui::pos2(pos, canvas.min.y))
+            };
+            painter.line_segment([a, b], egui::Stroke::new(1.0, color));
+            if is_major {
+                let label = format!("{:.*}", decimals, value);
+                let galley = painter.layout_no_wrap(label, font.clone(), color);
+                let shape = if vertical {
+                    // reads bottom to top, next to the tick
+                    egui::epaint::TextShape::new(egui::pos2(left.min.x + 1.0, pos - 2.0), galley, color).with_angle(-std::f32::consts::FRAC_PI_2)
+                } else {
+                    egui::epaint::TextShape::new(egui::pos2(pos + 2.0, top.min.y + 1.0), galley, color)
+                };
+                painter.add(shape);
+            }
+        }
+    }
+
+    if let Some(p) = cursor {
+        let s = view.to_screen(p);
+        let mark = egui::Stroke::new(1.0, visuals.selection.stroke.color);
+        painter.line_segment([egui::pos2(canvas.min.x + s[0], top.min.y), egui::pos2(canvas.min.x + s[0], top.max.y)], mark);
+        painter.line_segment([egui::pos2(left.min.x, canvas.min.y + s[1]), egui::pos2(left.max.x, canvas.min.y + s[1])], mark);
+    }
+}
+
+/// Distance of labeled ticks in units shown `zoom` pixels large, at least ~80 px apart, and
+/// how many ticks there are per label, at least ~6 px apart.
+fn tick_steps(zoom: f32) -> (f32, i64) {
+    let wanted = 80.0 / zoom;
+    let power = 10f32.powf(wanted.log10().floor());
+    let major = [1.0, 2.0, 5.0, 10.0].into_iter().map(|m| m * power).find(|&s| s >= wanted).unwrap_or(10.0 * power);
+    let subdivisions = [10, 5, 2].into_iter().find(|&d| major / d as f32 * zoom >= 6.0).unwrap_or(1);
+    (major, subdivisions)
+}
+
+pub fn draw_guides(painter: &egui::Painter, canvas: egui::Rect, view: &ViewTransform, guides: &[Guide]) {
+    let stroke = egui::Stroke::new(1.0, egui::Color32::from_rgb(0, 200, 220));
+    let visible = view.doc_bbox([0.0, 0.0, canvas.width(), canvas.height()]);
+    for guide in guides {
+        let ends = if guide.vertical {
+            [[guide.position, visible[1]], [guide.position, visible[3]]]
+        } else {
+            [[visible[0], guide.position], [visible[2], guide.position]]
+        };
+        painter.line_segment(ends.map(|p| egui::Pos2::from(view.to_screen(p)) + canvas.min.to_vec2()), stroke);
+    }
+}
+
+/// Dragging out of the top ruler makes a guide parallel to it, out of the left one a guide
+/// parallel to that, whichever document axis that is in a rotated view. Guides on the canvas
+/// can be dragged along, dropping one outside the canvas removes it. Guides snap to `grid`.
+/// True while a guide is being dragged, the canvas should ignore that drag.
+pub fn handle_guides(ui: &egui::Ui, tab: &mut Tab, grid: &Grid, canvas: egui::Rect, canvas_response: &egui::Response) -> bool {
+    let top = egui::Rect::from_min_max(egui::pos2(canvas.min.x, canvas.min.y - RULER_SIZE), egui::pos2(canvas.max.x, canvas.min.y));
+    let left = egui::Rect::from_min_max(egui::pos2(canvas.min.x - RULER_SIZE, canvas.min.y), egui::pos2(canvas.min.x, canvas.max.y));
+    let top = ui.interact(top, ui.id().with("top_ruler"), egui::Sense::drag());
+    let left = ui.interact(left, ui.id().with("left_ruler"), egui::Sense::drag());
+    let pointer = ui.input(|i| i.pointer.interact_pos());
+    let doc_pos = |p: egui::Pos2| tab.view.to_doc([p.x - canvas.min.x, p.y - canvas.min.y]);
+
+    if top.drag_started() || left.drag_started() {
+        // a vertical guide runs along the document's y axis
+        let along = usize::from(left.drag_started());
+        tab.guides.push(Guide { vertical: tab.view.doc_axis(along).0 == 1, position: 0.0 });
+        tab.dragged_guide = Some(tab.guides.len() - 1);
+    } else if canvas_response.drag_started_by(egui::PointerButton::Primary) && !ui.input(|i| i.key_down(egui::Key::Space)) {
+        // the guide closest to where the drag started
+        tab.dragged_guide = pointer.and_then(|p| guide_near(tab, canvas, p));
+    } else if let Some(p) = canvas_response.hover_pos() {
+        if let Some(i) = guide_near(tab, canvas, p) {
+            // direction of the guide on screen
+            let m = tab.view.matrix();
+            let along = if tab.guides[i].vertical { [m[1], m[3]] } else { [m[0], m[2]] };
+            let icon = if along[0].abs() < along[1].abs() { egui::CursorIcon::ResizeHorizontal } else { egui::CursorIcon::ResizeVertical };
+            ui.ctx().set_cursor_icon(icon);
+        }
+    }
+
+    let Some(i) = tab.dragged_guide else { return false };
+    if let Some(p) = pointer {
+        let [x, y] = grid.snap(doc_pos(p), &tab.doc, tab.view.zoom);
+        tab.guides[i].position = if tab.guides[i].vertical { x } else { y };
+    }
+    if ui.input(|i| i.pointer.any_released()) {
+        tab.dragged_guide = None;
+        if !pointer.is_some_and(|p| canvas.contains(p)) {
+            tab.guides.remove(i);
+        }
+    }
+    true
+}
+
+fn guide_near(tab: &Tab, canvas: egui::Rect, p: egui::Pos2) -> Option<usize> {
+    let doc = tab.view.to_doc([p.x - canvas.min.x, p.y - canvas.min.y]);
+    tab.guides
+        .iter()
+        .enumerate()
+        .map(|(i, g)| {
+            let axis = if g.vertical { 0 } else { 1 };
+            (i, (doc[axis] - g.position).abs() * tab.view.zoom)
+        })
+        .filter(|&(_, d)| d <= GUIDE_REACH)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i)
+}
@@ -0,0 +1,96 @@
+use vectorlab_core::{Document, Unit, ViewTransform};
+
+/// Pixels per inch desktops assume before their scale factor, as CSS does.
+const LOGICAL_DPI: f32 = 96.0;
+
+/// Space between the scale bar and the edges of the canvas.
+const MARGIN: f32 = 10.0;
+
+/// Physical pixels per inch of the screen: `calibrated` if set, otherwise what the scale
+/// factor winit reports comes to at 96 per logical inch.
+pub fn screen_dpi(ctx: &egui::Context, calibrated: Option<f32>) -> f32 {
+    calibrated.unwrap_or_else(|| LOGICAL_DPI * ctx.native_pixels_per_point().unwrap_or(1.0))
+}
+
+/// egui points that make a millimeter on the screen.
+pub fn points_per_mm(ctx: &egui::Context, calibrated: Option<f32>) -> f32 {
+    screen_dpi(ctx, calibrated) / ctx.pixels_per_point() / 25.4
+}
+
+/// 1, 2 or 5 times a power of ten, the largest not over `max`.
+fn round_length(max: f32) -> f32 {
+    let power = 10f32.powf(max.log10().floor());
+    [5.0, 2.0, 1.0].into_iter().map(|m| m * power).find(|&l| l <= max).unwrap_or(power)
+}
+
+/// How much larger than in reality things are on screen, "1:1" within half a percent.
+fn ratio_label(ratio: f32) -> String {
+    let number = |v: f32| format!("{:.2}", v).trim_end_matches('0').trim_end_matches('.').to_string();
+    if (ratio - 1.0).abs() < 0.005 {
+        "1:1".to_string()
+    } else if ratio > 1.0 {
+        format!("{}:1", number(ratio))
+    } else {
+        format!("1:{}", number(1.0 / ratio))
+    }
+}
+
+/// A bar of a round length in `unit` in the bottom left corner of `canvas`, as long as that
+/// length of the page is at the current zoom, with how the view compares to the real size.
+pub fn draw_scale_bar(painter: &egui::Painter, canvas: egui::Rect, view: &ViewTransform, doc: &Document, unit: Unit, points_per_mm: f32) {
+    let [mx, my] = doc.mm_per_unit();
+    // points per mm of the page as shown
+    let shown = view.zoom / (mx * my).sqrt();
+    let length = round_length(120.0 / (shown * unit.mm()));
+    let width = length * unit.mm() * shown;
+    if !width.is_finite() || width + 2.0 * MARGIN > canvas.width() {
+        return;
+    }
+    let decimals = (-length.log10().floor()).max(0.0) as usize;
+    let label = format!("{:.*} {}   {}", decimals, length, unit.name(), ratio_label(shown / points_per_mm));
+
+    let visuals = painter.ctx().style().visuals.clone();
+    let color = visuals.text_color();
+    let galley = painter.layout_no_wrap(label, egui::FontId::proportional(11.0), color);
+    let left = egui::pos2(canvas.min.x + MARGIN + 6.0, canvas.max.y - MARGIN - 6.0);
+    let frame = egui::Rect::from_min_max(
+        egui::pos2(left.x - 6.0, left.y - galley.size().y - 12.0),
+        egui::pos2(left.x + width.max(galley.size().x) + 6.0, left.y + 4.0),
+    );
+    painter.rect_filled(frame, 3.0, visuals.extreme_bg_color.gamma_multiply(0.8));
+    let stroke = egui::Stroke::new(1.5, color);
+    let right = left + egui::vec2(width, 0.0);
+    painter.line_segment([left, right], stroke);
+    for x in [left.x, right.x] {
+        painter.line_segment([egui::pos2(x, left.y - 5.0), egui::pos2(x, left.y)], stroke);
+    }
+    painter.galley(egui::pos2(left.x, left.y - 8.0 - galley.size().y), galley, color);
+}
+
+/// The screen resolution for showing drawings at their real size, automatic or typed in
+/// after holding a ruler to the bar.
+pub fn calibration(ui: &mut egui::Ui, calibrated: &mut Option<f32>, unit: Unit) {
+    ui.horizontal(|ui| {
+        let mut auto = calibrated.is_none();
+        if ui.checkbox(&mut auto, "Auto").on_hover_text("From the scale factor, right for few screens").changed() {
+            *calibrated = if auto { None } else { Some(screen_dpi(ui.ctx(), None).round()) };
+        }
+        let mut dpi = screen_dpi(ui.ctx(), *calibrated);
+        let drag = egui::DragValue::new(&mut dpi).speed(0.5).range(20.0..=2000.0).suffix(" px/in");
+        if ui.add_enabled(!auto, drag).changed() {
+            *calibrated = Some(dpi);
+        }
+    });
+
+    // a length that fits a ruler of either kind
+    let length = if unit == Unit::In { Unit::In.to_mm(2.0) } else { 50.0 };
+    let width = length * points_per_mm(ui.ctx(), *calibrated);
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(width, 14.0), egui::Sense::hover());
+    let stroke = egui::Stroke::new(1.5, ui.visuals().text_color());
+    ui.painter().line_segment([rect.left_center(), rect.right_center()], stroke);
+    for x in [rect.min.x, rect.max.x] {
+        ui.painter().line_segment([egui::pos2(x, rect.min.y), egui::pos2(x, rect.max.y)], stroke);
+    }
+    let unit = if unit == Unit::In { Unit::In } else { Unit::Mm };
+    ui.weak(format!("Adjust until the bar measures {:.0} {}", unit.from_mm(length), unit.name()));
+}
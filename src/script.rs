@@ -0,0 +1,440 @@
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use rhai::{Array, Dynamic, Engine, EvalAltResult};
+use vectorlab_core::{
+    add_shape, boolean_paths, ellipse, polygon, polyline, rectangle, transform_point, transform_scale, BooleanOp, Color, DeleteElements, Document, EditCommand, EditGroup, ElementId, ElementKind,
+    LineJoin, Paint, Segment, SetStyle, SimplifyMethod, Style,
+};
+
+use crate::cli::parse_color;
+use crate::operations::Operation;
+use crate::tab::Tab;
+
+/// Steps a script in the console may take, so an endless loop doesn't hang the window.
+pub const MAX_OPERATIONS: u64 = 50_000_000;
+
+/// Stroke width of the paths scripts make, in mm.
+const PEN_WIDTH: f32 = 0.3;
+
+/// Most corners of `star`, its points are made outside of the operations limit.
+const MAX_CORNERS: u32 = 10_000;
+
+/// Functions scripts have besides Rhai's own, shown as help in the console.
+pub const HELP: &str = "\
+Lengths and points are in mm from the top left of the page, points as [x, y].
+
+paths(), layers(), selection(), find(query)   elements, find as in the search bar
+children(e), tag(e), name(e), class(e)        the tree and attributes of an element
+bbox(e)                                       [x0, y0, x1, y1], () for empty groups
+points(e)                                     each contour of a path as points
+page_size()                                   [width, height]
+select(elements)                              what is selected when the script ends
+
+line(points), polygon(points)                 new paths, stroked black and 0.3 mm wide
+rect(x, y, w, h), circle(x, y, r), star(x, y, r, corners, inner)
+set_fill(e, color), set_stroke(e, color)      \"#rrggbb\", a name or \"none\"
+set_stroke_width(e, width)                    on a group, for every path in it
+delete(elements)
+
+simplify(elements, tolerance)                 what they do in the Path menu, returning
+offset(elements, distance)                    what was done
+boolean(elements, op)                         union, intersection, difference or xor
+print(value)                                  into the output";
+
+/// What a script works on while it runs.
+struct Session {
+    doc: Document,
+    selection: Vec<ElementId>,
+    /// applied to `doc` so far, in order
+    edits: Vec<Box<dyn EditCommand>>,
+    output: Vec<String>,
+}
+
+impl Session {
+    fn apply(&mut self, mut edit: Box<dyn EditCommand>) {
+        edit.apply(&mut self.doc);
+        self.edits.push(edit);
+    }
+
+    /// Document coordinates of `p` mm from the top left of the page.
+    fn to_doc(&self, p: [f32; 2]) -> [f32; 2] {
+        let [vx, vy, ..] = self.doc.view_box;
+        let mm = self.doc.mm_per_unit();
+        [vx + p[0] / mm[0], vy + p[1] / mm[1]]
+    }
+
+    fn to_mm(&self, p: [f32; 2]) -> [f32; 2] {
+        let [vx, vy, ..] = self.doc.view_box;
+        let mm = self.doc.mm_per_unit();
+        [(p[0] - vx) * mm[0], (p[1] - vy) * mm[1]]
+    }
+
+    fn units_per_mm(&self) -> f32 {
+        let mm = self.doc.mm_per_unit();
+        2.0 / (mm[0] + mm[1]).max(f32::EPSILON)
+    }
+
+    /// A new path on top of everything with `segments` in mm, returning its id.
+    fn add_path(&mut self, segments: Vec<Segment>) -> ElementId {
+        let segments = segments
+            .into_iter()
+            .map(|s| match s {
+                Segment::MoveTo(p) => Segment::MoveTo(self.to_doc(p)),
+                Segment::LineTo(p) => Segment::LineTo(self.to_doc(p)),
+                Segment::QuadTo(c, p) => Segment::QuadTo(self.to_doc(c), self.to_doc(p)),
+                Segment::CubicTo(c1, c2, p) => Segment::CubicTo(self.to_doc(c1), self.to_doc(c2), self.to_doc(p)),
+                Segment::Close => Segment::Close,
+            })
+            .collect();
+        let mut edit = add_shape(&self.doc, segments, None, Some(Paint::Solid(Color::BLACK)), PEN_WIDTH * self.units_per_mm());
+        edit.apply(&mut self.doc);
+        let id = edit.id().unwrap_or(self.doc.root);
+        self.edits.push(Box::new(edit));
+        id
+    }
+
+    /// Change the style of `id`, or of every path below it, with `change`.
+    fn restyle(&mut self, id: ElementId, change: impl Fn(&mut Style)) {
+        for id in self.doc.descendants(id) {
+            let Some(path) = self.doc.get(id).as_path() else { continue };
+            let old = path.style.clone();
+            let mut new = old.clone();
+            change(&mut new);
+            self.apply(Box::new(SetStyle { id, old, new }));
+        }
+    }
+
+    /// Run one of the Path menu's operations on the paths among `ids` and what is in them.
+    fn operation(&mut self, operation: Operation, ids: &[ElementId]) -> String {
+        let tree: Vec<ElementId> = ids.iter().flat_map(|&id| self.doc.descendants(id)).collect();
+        match operation.run(&self.doc, &tree) {
+            Some((edit, report)) => {
+                self.apply(edit);
+                report
+            }
+            None => String::new(),
+        }
+    }
+}
+
+/// A script number, integer or not.
+fn number(value: &Dynamic) -> Result<f32, Box<EvalAltResult>> {
+    if let Ok(f) = value.as_float() {
+        Ok(f as f32)
+    } else if let Ok(i) = value.as_int() {
+        Ok(i as f32)
+    } else {
+        Err(format!("expected a number, not {}", value.type_name()).into())
+    }
+}
+
+fn point(value: &Dynamic) -> Result<[f32; 2], Box<EvalAltResult>> {
+    match value.clone().try_cast::<Array>().as_deref() {
+        Some([x, y]) => Ok([number(x)?, number(y)?]),
+        _ => Err(format!("expected a point [x, y], not {}", value).into()),
+    }
+}
+
+fn points(values: &Array) -> Result<Vec<[f32; 2]>, Box<EvalAltResult>> {
+    values.iter().map(point).collect()
+}
+
+/// The elements in `values`, skipping anything else.
+fn elements(values: &Array) -> Vec<ElementId> {
+    values.iter().filter_map(|v| v.clone().try_cast::<ElementId>()).collect()
+}
+
+fn array(ids: impl IntoIterator<Item = ElementId>) -> Array {
+    ids.into_iter().map(Dynamic::from).collect()
+}
+
+fn mm_array(values: impl IntoIterator<Item = f32>) -> Array {
+    values.into_iter().map(|v| Dynamic::from(v as rhai::FLOAT)).collect()
+}
+
+fn paint(color: &str) -> Result<Option<Paint>, Box<EvalAltResult>> {
+    if color.eq_ignore_ascii_case("none") {
+        return Ok(None);
+    }
+    Ok(Some(Paint::Solid(parse_color(color)?)))
+}
+
+/// An engine whose functions work on `session`, see [`HELP`].
+fn engine(session: &Rc<RefCell<Session>>, max_operations: u64) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(max_operations);
+    engine.register_type_with_name::<ElementId>("Element");
+    engine.register_fn("to_string", |id: &mut ElementId| format!("element {}", id.0));
+    engine.register_fn("to_debug", |id: &mut ElementId| format!("element {}", id.0));
+    engine.register_fn("==", |a: ElementId, b: ElementId| a == b);
+    engine.register_fn("!=", |a: ElementId, b: ElementId| a != b);
+
+    let s = session.clone();
+    engine.on_print(move |text| s.borrow_mut().output.push(text.to_string()));
+
+    // queries
+    let s = session.clone();
+    engine.register_fn("paths", move || array(s.borrow().doc.paths().map(|(id, _)| id).collect::<Vec<_>>()));
+    let s = session.clone();
+    engine.register_fn("layers", move || {
+        let s = s.borrow();
+        array(s.doc.get(s.doc.root).children.clone())
+    });
+    let s = session.clone();
+    engine.register_fn("selection", move || array(s.borrow().selection.clone()));
+    let s = session.clone();
+    engine.register_fn("find", move |query: &str| array(s.borrow().doc.find(query)));
+    let s = session.clone();
+    engine.register_fn("children", move |id: ElementId| array(s.borrow().doc.get(id).children.clone()));
+    let s = session.clone();
+    engine.register_fn("tag", move |id: ElementId| s.borrow().doc.get(id).tag().to_string());
+    let s = session.clone();
+    engine.register_fn("name", move |id: ElementId| s.borrow().doc.get(id).source_id.clone());
+    let s = session.clone();
+    engine.register_fn("class", move |id: ElementId| s.borrow().doc.get(id).source_class.clone());
+    let s = session.clone();
+    engine.register_fn("bbox", move |id: ElementId| {
+        let s = s.borrow();
+        match s.doc.subtree_bbox(id) {
+            Some([x0, y0, x1, y1]) => {
+                let ([x0, y0], [x1, y1]) = (s.to_mm([x0, y0]), s.to_mm([x1, y1]));
+                Dynamic::from(mm_array([x0, y0, x1, y1]))
+            }
+            None => Dynamic::UNIT,
+        }
+    });
+    let s = session.clone();
+    engine.register_fn("points", move |id: ElementId| {
+        let s = s.borrow();
+        let ElementKind::Path(path) = &s.doc.get(id).kind else { return Array::new() };
+        let ts = s.doc.abs_transform(id);
+        path.contours
+            .iter()
+            .map(|c| {
+                let contour: Array = c.points.iter().map(|&p| Dynamic::from(mm_array(s.to_mm(transform_point(&ts, p))))).collect();
+                Dynamic::from(contour)
+            })
+            .collect()
+    });
+    let s = session.clone();
+    engine.register_fn("page_size", move || {
+        let s = s.borrow();
+        let [.., w, h] = s.doc.view_box;
+        let mm = s.doc.mm_per_unit();
+        mm_array([w * mm[0], h * mm[1]])
+    });
+    let s = session.clone();
+    engine.register_fn("select", move |ids: Array| s.borrow_mut().selection = elements(&ids));
+
+    // new paths
+    let s = session.clone();
+    engine.register_fn("line", move |ids: Array| -> Result<ElementId, Box<EvalAltResult>> { Ok(s.borrow_mut().add_path(polyline(&points(&ids)?, false))) });
+    let s = session.clone();
+    engine.register_fn("polygon", move |ids: Array| -> Result<ElementId, Box<EvalAltResult>> { Ok(s.borrow_mut().add_path(polyline(&points(&ids)?, true))) });
+    let s = session.clone();
+    engine.register_fn("rect", move |x: Dynamic, y: Dynamic, w: Dynamic, h: Dynamic| -> Result<ElementId, Box<EvalAltResult>> {
+        let (x, y) = (number(&x)?, number(&y)?);
+        Ok(s.borrow_mut().add_path(rectangle([x, y], [x + number(&w)?, y + number(&h)?])))
+    });
+    let s = session.clone();
+    engine.register_fn("circle", move |x: Dynamic, y: Dynamic, r: Dynamic| -> Result<ElementId, Box<EvalAltResult>> {
+        let (x, y, r) = (number(&x)?, number(&y)?, number(&r)?);
+        Ok(s.borrow_mut().add_path(ellipse([x - r, y - r], [x + r, y + r])))
+    });
+    let s = session.clone();
+    engine.register_fn("star", move |x: Dynamic, y: Dynamic, r: Dynamic, corners: Dynamic, inner: Dynamic| -> Result<ElementId, Box<EvalAltResult>> {
+        let (x, y, r) = (number(&x)?, number(&y)?, number(&r)?);
+        let corners = number(&corners)?;
+        if !(3.0..=MAX_CORNERS as f32).contains(&corners) {
+            return Err(format!("a star has 3 to {} corners, not {}", MAX_CORNERS, corners).into());
+        }
+        Ok(s.borrow_mut().add_path(polygon([x, y], [x, y - r], corners as u32, Some(number(&inner)?))))
+    });
+
+    // styles
+    let s = session.clone();
+    engine.register_fn("set_fill", move |id: ElementId, color: &str| -> Result<(), Box<EvalAltResult>> {
+        let fill = paint(color)?;
+        s.borrow_mut().restyle(id, |style| style.fill = fill.clone());
+        Ok(())
+    });
+    let s = session.clone();
+    engine.register_fn("set_stroke", move |id: ElementId, color: &str| -> Result<(), Box<EvalAltResult>> {
+        let stroke = paint(color)?;
+        s.borrow_mut().restyle(id, |style| style.stroke = stroke.clone());
+        Ok(())
+    });
+    let s = session.clone();
+    engine.register_fn("set_stroke_width", move |id: ElementId, width: Dynamic| -> Result<(), Box<EvalAltResult>> {
+        let mut s = s.borrow_mut();
+        // in the path's own coordinates, like the inspector sets it
+        let width = number(&width)? * s.units_per_mm();
+        let paths: Vec<ElementId> = s.doc.descendants(id).into_iter().filter(|&id| s.doc.get(id).as_path().is_some()).collect();
+        for path in paths {
+            let scale = transform_scale(&s.doc.abs_transform(path)).max(f32::EPSILON);
+            s.restyle(path, |style| style.stroke_width = width / scale);
+        }
+        Ok(())
+    });
+    let s = session.clone();
+    engine.register_fn("delete", move |ids: Array| {
+        let mut s = s.borrow_mut();
+        let ids = elements(&ids);
+        s.selection.retain(|id| !ids.contains(id));
+        s.apply(Box::new(DeleteElements::new(ids)));
+    });
+
+    // operations
+    let s = session.clone();
+    engine.register_fn("simplify", move |ids: Array, tolerance: Dynamic| -> Result<String, Box<EvalAltResult>> {
+        let operation = Operation::Simplify { tolerance: number(&tolerance)?, method: SimplifyMethod::DouglasPeucker };
+        Ok(s.borrow_mut().operation(operation, &elements(&ids)))
+    });
+    let s = session.clone();
+    engine.register_fn("offset", move |ids: Array, distance: Dynamic| -> Result<String, Box<EvalAltResult>> {
+        let operation = Operation::Offset { distance: number(&distance)?, join: LineJoin::Round, copies: 1, keep_original: false };
+        Ok(s.borrow_mut().operation(operation, &elements(&ids)))
+    });
+    let s = session.clone();
+    engine.register_fn("boolean", move |ids: Array, op: &str| -> Result<String, Box<EvalAltResult>> {
+        let op = match op.to_ascii_lowercase().as_str() {
+            "union" => BooleanOp::Union,
+            "intersection" => BooleanOp::Intersection,
+            "difference" => BooleanOp::Difference,
+            "xor" | "exclusion" => BooleanOp::Xor,
+            _ => return Err(format!("unknown operation '{}', expected union, intersection, difference or xor", op).into()),
+        };
+        let mut s = s.borrow_mut();
+        let tree: Vec<ElementId> = elements(&ids).into_iter().flat_map(|id| s.doc.descendants(id)).collect();
+        let Some(result) = boolean_paths(&s.doc, &tree, op) else { return Ok(String::new()) };
+        s.apply(result.edit);
+        Ok(format!("{} of {} paths", op.name(), result.paths))
+    });
+    engine
+}
+
+/// What became of a document a script ran on.
+pub struct ScriptRun {
+    pub doc: Document,
+    /// everything the script changed as one edit, applied to `doc` already
+    pub edit: Option<Box<dyn EditCommand>>,
+    pub selection: Vec<ElementId>,
+    /// printed by the script, a line each
+    pub output: Vec<String>,
+    /// what stopped it, the document is left as it was then
+    pub error: Option<String>,
+}
+
+/// Run the Rhai `script` on `doc` with `selection` selected, see [`HELP`]. 0 operations
+/// means no limit.
+pub fn run_script(script: &str, doc: Document, selection: Vec<ElementId>, max_operations: u64) -> ScriptRun {
+    let session = Rc::new(RefCell::new(Session { doc, selection: selection.clone(), edits: vec![], output: vec![] }));
+    let result = engine(&session, max_operations).run(script);
+    let Ok(session) = Rc::try_unwrap(session) else { unreachable!("the engine is gone") };
+    let mut session = session.into_inner();
+    match result {
+        Ok(()) => {
+            let edit = (!session.edits.is_empty()).then(|| Box::new(EditGroup::new("Run script", session.edits)) as Box<dyn EditCommand>);
+            ScriptRun { doc: session.doc, edit, selection: session.selection, output: session.output, error: None }
+        }
+        Err(e) => {
+            for edit in session.edits.iter_mut().rev() {
+                edit.revert(&mut session.doc);
+            }
+            ScriptRun { doc: session.doc, edit: None, selection, output: session.output, error: Some(e.to_string()) }
+        }
+    }
+}
+
+/// The Script console panel, with the script kept while it is hidden.
+pub struct ScriptConsole {
+    pub code: String,
+    output: String,
+    failed: bool,
+    /// the script was opened from or saved to
+    path: Option<PathBuf>,
+}
+
+impl Default for ScriptConsole {
+    fn default() -> Self {
+        let code = "// color the selected paths red\nfor e in selection() {\n    set_stroke(e, \"#d03030\");\n}\n".to_string();
+        Self { code, output: String::new(), failed: false, path: None }
+    }
+}
+
+impl ScriptConsole {
+    /// Run the script on `tab`, as one undo step.
+    fn run(&mut self, tab: &mut Tab) {
+        let doc = std::mem::take(&mut tab.doc);
+        let run = run_script(&self.code, doc, tab.selection.clone(), MAX_OPERATIONS);
+        tab.doc = run.doc;
+        let changed = run.edit.is_some();
+        if let Some(edit) = run.edit {
+            tab.history.record(edit, &mut tab.doc);
+        }
+        let attached = tab.doc.descendants(tab.doc.root);
+        tab.selection = run.selection.into_iter().filter(|id| attached.contains(id)).collect();
+        self.output = run.output.join("\n");
+        self.failed = run.error.is_some();
+        match run.error {
+            Some(e) => self.output.push_str(&format!("\n{}", e)),
+            None if !changed => self.output.push_str("\nNo changes"),
+            None => {}
+        }
+        self.output = self.output.trim_start().to_string();
+    }
+}
+
+pub fn script_panel(ui: &mut egui::Ui, console: &mut ScriptConsole, tab: &mut Tab) {
+    let mut run = false;
+    ui.horizontal(|ui| {
+        run |= ui.button("▶ Run").on_hover_text("Ctrl+Enter, undone as one step").clicked();
+        if ui.button("Open…").clicked() {
+            if let Some(path) = rfd::FileDialog::new().set_title("Open script").add_filter("Rhai", &["rhai"]).pick_file() {
+                match std::fs::read_to_string(&path) {
+                    Ok(code) => {
+                        console.code = code;
+                        console.path = Some(path);
+                    }
+                    Err(e) => {
+                        console.output = format!("{}: {}", path.display(), e);
+                        console.failed = true;
+                    }
+                }
+            }
+        }
+        if ui.button("Save…").clicked() {
+            let mut dialog = rfd::FileDialog::new().set_title("Save script").add_filter("Rhai", &["rhai"]);
+            if let Some(name) = console.path.as_ref().and_then(|p| p.file_name()) {
+                dialog = dialog.set_file_name(name.to_string_lossy());
+            }
+            if let Some(path) = dialog.save_file() {
+                if let Err(e) = std::fs::write(&path, &console.code) {
+                    console.output = format!("{}: {}", path.display(), e);
+                    console.failed = true;
+                }
+                console.path = Some(path);
+            }
+        }
+        ui.label("ⓘ").on_hover_ui(|ui| {
+            ui.label(egui::RichText::new(HELP).monospace());
+        });
+    });
+    ui.columns(2, |columns| {
+        egui::ScrollArea::vertical().id_source("script-code").show(&mut columns[0], |ui| {
+            let editor = ui.add(egui::TextEdit::multiline(&mut console.code).code_editor().desired_width(f32::INFINITY).desired_rows(8));
+            if editor.has_focus() && ui.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::Enter)) {
+                run = true;
+            }
+        });
+        egui::ScrollArea::vertical().id_source("script-output").stick_to_bottom(true).show(&mut columns[1], |ui| {
+            let color = if console.failed { ui.visuals().error_fg_color } else { ui.visuals().text_color() };
+            ui.add(egui::Label::new(egui::RichText::new(&console.output).monospace().color(color)).selectable(true));
+        });
+    });
+    if run {
+        console.run(tab);
+    }
+}
@@ -0,0 +1,90 @@
+use vectorlab_core::ElementId;
+
+use crate::tab::Tab;
+
+/// State of the Ctrl+F bar of a tab.
+#[derive(Default)]
+pub struct Search {
+    query: String,
+    matches: Vec<ElementId>,
+    // index into matches of the one shown, None before the first Enter
+    current: Option<usize>,
+    // document revision the matches were found in
+    revision: u64,
+    focus: bool,
+}
+
+impl Search {
+    /// Open the bar with the keyboard focus in it, keeping the query if it is open already.
+    pub fn open(search: &mut Option<Search>) {
+        let query = search.take().map(|s| s.query).unwrap_or_default();
+        *search = Some(Self { query, focus: true, ..Self::default() });
+    }
+}
+
+/// Query field with previous / next buttons. Enter goes to the next match, Shift+Enter to the
+/// previous one, Escape closes the bar. Each match is selected and zoomed to.
+pub fn search_bar(ui: &mut egui::Ui, tab: &mut Tab) {
+    let Some(search) = &mut tab.search else { return };
+    let mut step = 0;
+    let mut open = true;
+    ui.horizontal(|ui| {
+        ui.label("🔍");
+        let edit = ui.add(egui::TextEdit::singleline(&mut search.query).hint_text("id, .class, #id or tag").desired_width(240.0));
+        if std::mem::take(&mut search.focus) {
+            edit.request_focus();
+        }
+        if edit.changed() || search.revision != tab.doc.revision {
+            search.matches = tab.doc.find(&search.query);
+            search.current = None;
+            search.revision = tab.doc.revision;
+        }
+        if edit.lost_focus() {
+            if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                step = if ui.input(|i| i.modifiers.shift) { -1 } else { 1 };
+                edit.request_focus();
+            } else if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                open = false;
+            }
+        }
+
+        let any = !search.matches.is_empty();
+        if ui.add_enabled(any, egui::Button::new("◀")).on_hover_text("Previous match (Shift+Enter)").clicked() {
+            step = -1;
+        }
+        if ui.add_enabled(any, egui::Button::new("▶")).on_hover_text("Next match (Enter)").clicked() {
+            step = 1;
+        }
+        match (search.current, search.matches.len()) {
+            (_, 0) if search.query.trim().is_empty() => {}
+            (_, 0) => {
+                ui.colored_label(ui.visuals().warn_fg_color, "no matches");
+            }
+            (Some(i), n) => {
+                ui.label(format!("{} of {}", i + 1, n));
+            }
+            (None, n) => {
+                ui.label(format!("{} matches", n));
+            }
+        }
+        if ui.small_button("×").on_hover_text("Close (Escape)").clicked() {
+            open = false;
+        }
+    });
+
+    let n = search.matches.len() as isize;
+    if step != 0 && n > 0 {
+        let i = match search.current {
+            Some(i) => (i as isize + step).rem_euclid(n) as usize,
+            None if step > 0 => 0,
+            None => n as usize - 1,
+        };
+        search.current = Some(i);
+        let id = search.matches[i];
+        tab.selection = vec![id];
+        tab.zoom_to = tab.doc.subtree_bbox(id);
+    }
+    if !open {
+        tab.search = None;
+    }
+}
@@ -0,0 +1,139 @@
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+
+/// A USB-serial connection to a machine controller, read line by line.
+pub struct SerialPort {
+    file: File,
+    /// received bytes of a line not complete yet
+    pending: Vec<u8>,
+}
+
+/// How the other end tells the computer to wait.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FlowControl {
+    #[default]
+    None,
+    /// XOFF and XON characters
+    Software,
+    /// RTS/CTS lines
+    Hardware,
+}
+
+impl FlowControl {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Software => "xon-xoff",
+            Self::Hardware => "rts-cts",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        [Self::None, Self::Software, Self::Hardware].into_iter().find(|f| f.name() == name)
+    }
+}
+
+/// Line settings for machines on a real RS-232 port, USB ones ignore them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LineSettings {
+    /// None to keep the port's
+    pub baud: Option<u32>,
+    pub flow: FlowControl,
+}
+
+impl SerialPort {
+    /// Reads give up after a tenth of a second, so callers can do other things in between.
+    pub fn open(port: &str) -> Result<Self, Box<dyn Error>> {
+        Self::open_with(port, LineSettings::default())
+    }
+
+    pub fn open_with(port: &str, line: LineSettings) -> Result<Self, Box<dyn Error>> {
+        #[cfg(windows)]
+        let port = if port.starts_with(r"\\.\") { port.to_string() } else { format!(r"\\.\{}", port) };
+        let file = OpenOptions::new().read(true).write(true).open(&port).map_err(|e| format!("{}: {}", port, e))?;
+        // the tty has to pass bytes through untouched
+        #[cfg(unix)]
+        {
+            let mut args: Vec<String> = ["raw", "-echo", "min", "0", "time", "1"].map(String::from).to_vec();
+            args.extend(line.baud.map(|b| b.to_string()));
+            args.extend(
+                match line.flow {
+                    FlowControl::None => ["-ixon", "-crtscts"],
+                    FlowControl::Software => ["ixon", "-crtscts"],
+                    FlowControl::Hardware => ["-ixon", "crtscts"],
+                }
+                .map(String::from),
+            );
+            let status = std::process::Command::new("stty").args(&args).stdin(file.try_clone()?).status()?;
+            if !status.success() {
+                return Err(format!("{}: could not set up the serial port", port).into());
+            }
+        }
+        #[cfg(windows)]
+        if line != LineSettings::default() {
+            let name = port.trim_start_matches(r"\\.\");
+            let mut args = vec![format!("{}:", name)];
+            args.extend(line.baud.map(|b| format!("BAUD={}", b)));
+            args.push(format!("XON={}", if line.flow == FlowControl::Software { "on" } else { "off" }));
+            args.push(format!("OCTS={}", if line.flow == FlowControl::Hardware { "on" } else { "off" }));
+            std::process::Command::new("mode").args(&args).status()?;
+        }
+        Ok(Self { file, pending: Vec::new() })
+    }
+
+    /// The next line without its line break, None if none came in for a while.
+    pub fn read_line(&mut self) -> io::Result<Option<String>> {
+        loop {
+            if let Some(end) = self.pending.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = self.pending.drain(..=end).collect();
+                return Ok(Some(String::from_utf8_lossy(&line).trim_end().to_string()));
+            }
+            let mut buffer = [0; 256];
+            match self.file.read(&mut buffer)? {
+                0 => return Ok(None),
+                n => self.pending.extend_from_slice(&buffer[..n]),
+            }
+        }
+    }
+}
+
+impl Write for SerialPort {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.file.write(bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Serial ports that look like a machine's USB connection.
+pub fn serial_ports() -> Vec<String> {
+    let Ok(entries) = fs::read_dir("/dev") else { return Vec::new() };
+    let mut ports: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .filter(|name| ["ttyACM", "ttyUSB", "cu.usbmodem", "cu.usbserial"].iter().any(|prefix| name.starts_with(prefix)))
+        .map(|name| format!("/dev/{}", name))
+        .collect();
+    ports.sort();
+    ports
+}
+
+/// A text field for the port with a list of the ports found.
+pub fn port_field(ui: &mut egui::Ui, port: &mut String) {
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(port).desired_width(160.0).hint_text("/dev/ttyACM0 or COM3"));
+        egui::ComboBox::from_id_source(ui.id().with("ports")).selected_text("Detect").show_ui(ui, |ui| {
+            let ports = serial_ports();
+            if ports.is_empty() {
+                ui.weak("No controller found");
+            }
+            for found in ports {
+                let label = found.clone();
+                ui.selectable_value(port, found, label);
+            }
+        });
+    });
+}
@@ -0,0 +1,704 @@
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+
+use vectorlab_core::{Color, CutSettings, GcodeOrigin, GcodeSettings, GcodeUnits, HalftonePattern, HalftoneSettings, HpglSettings, MachineKind, MachineProfile, PlotterSettings, SimulationSettings, StippleSettings, TikzSettings, TraceSettings, Unit, CSS_DPI};
+
+use crate::background::Background;
+use crate::cut::CutFormat;
+use crate::grid::Grid;
+use crate::hpgl::HpglOutput;
+use crate::keys::{parse_shortcut, Action, Keybindings};
+use crate::recent::{load_recent_files, MAX_RECENT_FILES};
+use crate::serial::FlowControl;
+
+/// User preferences, stored as settings.toml in the platform config dir. Only the bits of
+/// TOML that this file needs are read: `key = value` lines, strings, arrays of strings or
+/// numbers on one line, the `[[profiles]]` tables and the `[keys]` table.
+#[derive(Clone, Debug)]
+pub struct Settings {
+    /// How far flattened curves may deviate from the true curve, in screen pixels
+    pub curve_tolerance: f32,
+    /// Smooth edges of everything egui draws, the canvas included
+    pub antialiasing: bool,
+    /// List every spec violation of opened files, see `LoadOptions::strict`
+    pub strict_parsing: bool,
+    /// Save texts as their glyph outlines instead of `<text>`
+    pub text_to_paths: bool,
+    /// What lengths are shown and typed in
+    pub unit: Unit,
+    /// Pixels per inch of files sized in px, see `LoadOptions::dpi`
+    pub dpi: f32,
+    /// Physical pixels per inch of the screen as measured, None to go by the scale factor
+    pub screen_dpi: Option<f32>,
+    pub grid: Grid,
+    pub background: Background,
+    /// Behind the artwork in presentation mode
+    pub presentation_background: Background,
+    /// Seconds per slide
+    pub slideshow_interval: f32,
+    /// Seconds slides fade into each other, 0 to cut
+    pub slideshow_crossfade: f32,
+    /// File names of Export Layers, see `ExportLayers::file_name`
+    pub layer_file_pattern: String,
+    /// Machine setup of the last G-code export, without the per-layer passes
+    pub gcode: GcodeSettings,
+    /// Export for cutter
+    pub cut: CutSettings,
+    pub cut_format: CutFormat,
+    /// Export TikZ and Copy as TikZ
+    pub tikz: TikzSettings,
+    /// Trace bitmap
+    pub trace: TraceSettings,
+    /// Stipple image
+    pub stipple: StippleSettings,
+    /// Halftone image
+    pub halftone: HalftoneSettings,
+    /// Machine speeds of Simulate plot
+    pub simulation: SimulationSettings,
+    /// Serial port of the plotter, empty to pick the first one found
+    pub plotter_port: String,
+    pub plotter: PlotterSettings,
+    /// Serial port of the GRBL controller
+    pub grbl_port: String,
+    /// Where Send to HPGL plotter went last
+    pub hpgl_output: HpglOutput,
+    pub hpgl: HpglSettings,
+    /// Machines to draw for, see `MachineProfile`
+    pub profiles: Vec<MachineProfile>,
+    /// Name of the profile in use, empty for none. Its work area is drawn on the canvas
+    pub active_profile: String,
+    /// Most recent first, at most `MAX_RECENT_FILES`
+    pub recent_files: Vec<PathBuf>,
+    pub keys: Keybindings,
+    /// Inner size of the main window in logical pixels when it was last closed
+    pub window_size: Option<[f32; 2]>,
+    /// Outer position of the main window in physical pixels
+    pub window_position: Option<[i32; 2]>,
+    pub window_maximized: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            curve_tolerance: 0.25,
+            antialiasing: true,
+            strict_parsing: false,
+            text_to_paths: false,
+            unit: Unit::Mm,
+            dpi: CSS_DPI,
+            screen_dpi: None,
+            grid: Grid::default(),
+            background: Background::Dark,
+            presentation_background: Background::Custom(Color::BLACK),
+            slideshow_interval: 5.0,
+            slideshow_crossfade: 1.0,
+            layer_file_pattern: "{name}-{layer}".to_string(),
+            gcode: GcodeSettings::default(),
+            cut: CutSettings::default(),
+            cut_format: CutFormat::Svg,
+            tikz: TikzSettings::default(),
+            trace: TraceSettings::default(),
+            stipple: StippleSettings::default(),
+            halftone: HalftoneSettings::default(),
+            simulation: SimulationSettings::default(),
+            plotter_port: String::new(),
+            plotter: PlotterSettings::default(),
+            grbl_port: String::new(),
+            hpgl_output: HpglOutput::default(),
+            hpgl: HpglSettings::default(),
+            profiles: vec![],
+            active_profile: String::new(),
+            recent_files: vec![],
+            keys: Keybindings::default(),
+            window_size: None,
+            window_position: None,
+            window_maximized: false,
+        }
+    }
+}
+
+fn settings_path() -> Option<PathBuf> {
+    directories::ProjectDirs::from("de", "jnweiger", "VectorLab").map(|dirs| dirs.config_dir().join("settings.toml"))
+}
+
+impl Settings {
+    /// Unknown keys and unparsable values are ignored and keep their defaults.
+    pub fn load() -> Self {
+        let mut settings = Self::default();
+        let Some(text) = settings_path().and_then(|path| fs::read_to_string(path).ok()) else {
+            settings.recent_files = load_recent_files();
+            return settings;
+        };
+        let mut has_recent = false;
+        let mut table = String::new();
+        for line in text.lines().map(str::trim) {
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                table = name.trim().to_string();
+                // each [[profiles]] starts another one
+                if table == "[profiles]" {
+                    settings.profiles.push(MachineProfile::default());
+                }
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else { continue };
+            let (key, value) = (key.trim(), value.trim());
+            if table == "[profiles]" {
+                if let Some(profile) = settings.profiles.last_mut() {
+                    profile_key(profile, key, value);
+                }
+                continue;
+            }
+            if table == "keys" {
+                let action = Action::ALL.into_iter().find(|a| a.name() == key);
+                if let Some((action, shortcut)) = action.zip(string(value).as_deref().and_then(parse_shortcut)) {
+                    settings.keys.set(action, shortcut);
+                }
+                continue;
+            }
+            match key {
+                "curve_tolerance" => {
+                    if let Ok(v) = value.parse::<f32>() {
+                        settings.curve_tolerance = v.clamp(0.01, 10.0);
+                    }
+                }
+                "antialiasing" => {
+                    if let Ok(v) = value.parse() {
+                        settings.antialiasing = v;
+                    }
+                }
+                "strict_parsing" => {
+                    if let Ok(v) = value.parse() {
+                        settings.strict_parsing = v;
+                    }
+                }
+                "text_to_paths" => {
+                    if let Ok(v) = value.parse() {
+                        settings.text_to_paths = v;
+                    }
+                }
+                "unit" => {
+                    if let Some(v) = string(value).as_deref().and_then(Unit::parse) {
+                        settings.unit = v;
+                    }
+                }
+                "dpi" => {
+                    if let Ok(v) = value.parse::<f32>() {
+                        settings.dpi = v.clamp(10.0, 10000.0);
+                    }
+                }
+                "screen_dpi" => {
+                    if let Ok(v) = value.parse::<f32>() {
+                        settings.screen_dpi = Some(v.clamp(20.0, 2000.0));
+                    }
+                }
+                "layer_file_pattern" => {
+                    if let Some(v) = string(value).filter(|v| !v.trim().is_empty()) {
+                        settings.layer_file_pattern = v;
+                    }
+                }
+                "grid_show" => {
+                    if let Ok(v) = value.parse() {
+                        settings.grid.show = v;
+                    }
+                }
+                "grid_snapping" => {
+                    if let Ok(v) = value.parse() {
+                        settings.grid.snapping = v;
+                    }
+                }
+                "grid_spacing" => {
+                    if let Ok(v) = value.parse::<f32>() {
+                        settings.grid.spacing = v.clamp(0.01, 10000.0);
+                    }
+                }
+                "background" => {
+                    if let Some(v) = string(value).and_then(|v| Background::parse(&v).ok()) {
+                        settings.background = v;
+                    }
+                }
+                "presentation_background" => {
+                    if let Some(v) = string(value).and_then(|v| Background::parse(&v).ok()) {
+                        settings.presentation_background = v;
+                    }
+                }
+                "slideshow_interval" => {
+                    if let Ok(v) = value.parse::<f32>() {
+                        settings.slideshow_interval = v.clamp(0.5, 3600.0);
+                    }
+                }
+                "slideshow_crossfade" => {
+                    if let Ok(v) = value.parse::<f32>() {
+                        settings.slideshow_crossfade = v.clamp(0.0, 10.0);
+                    }
+                }
+                "gcode_feed_rate" | "gcode_travel_rate" | "gcode_z_up" | "gcode_z_down" | "gcode_laser_power" | "gcode_tolerance" => {
+                    let gcode = &mut settings.gcode;
+                    let field = match key {
+                        "gcode_feed_rate" => &mut gcode.feed_rate,
+                        "gcode_travel_rate" => &mut gcode.travel_rate,
+                        "gcode_z_up" => &mut gcode.z_up,
+                        "gcode_z_down" => &mut gcode.z_down,
+                        "gcode_laser_power" => &mut gcode.laser_power,
+                        _ => &mut gcode.tolerance,
+                    };
+                    if let Ok(v) = value.parse::<f32>() {
+                        *field = v;
+                    }
+                }
+                "gcode_laser" => {
+                    if let Ok(v) = value.parse() {
+                        settings.gcode.laser = v;
+                    }
+                }
+                "gcode_optimize" => {
+                    if let Ok(v) = value.parse() {
+                        settings.gcode.optimize = v;
+                    }
+                }
+                "gcode_units" => settings.gcode.units = if string(value).as_deref() == Some("inch") { GcodeUnits::Inch } else { GcodeUnits::Mm },
+                "gcode_origin" => settings.gcode.origin = string(value).as_deref().and_then(GcodeOrigin::parse).unwrap_or(GcodeOrigin::BottomLeft),
+                "gcode_invert_x" | "gcode_invert_y" => {
+                    let field = if key == "gcode_invert_x" { &mut settings.gcode.invert_x } else { &mut settings.gcode.invert_y };
+                    if let Ok(v) = value.parse() {
+                        *field = v;
+                    }
+                }
+                "cut_outline_strokes" => {
+                    if let Ok(v) = value.parse() {
+                        settings.cut.outline_strokes = v;
+                    }
+                }
+                "cut_close_gaps" | "cut_tolerance" => {
+                    let field = if key == "cut_close_gaps" { &mut settings.cut.close_gaps } else { &mut settings.cut.tolerance };
+                    if let Ok(v) = value.parse::<f32>() {
+                        *field = v;
+                    }
+                }
+                "cut_format" => {
+                    if let Some(v) = string(value).as_deref().and_then(CutFormat::parse) {
+                        settings.cut_format = v;
+                    }
+                }
+                "tikz_precision" => {
+                    if let Ok(v) = value.parse::<usize>() {
+                        settings.tikz.precision = v.min(6);
+                    }
+                }
+                "tikz_scale" => {
+                    if let Ok(v) = value.parse::<f32>() {
+                        settings.tikz.scale = v.clamp(0.01, 100.0);
+                    }
+                }
+                "tikz_standalone" => {
+                    if let Ok(v) = value.parse() {
+                        settings.tikz.standalone = v;
+                    }
+                }
+                "trace_threshold" | "trace_smoothing" | "trace_tolerance" => {
+                    let trace = &mut settings.trace;
+                    let field = match key {
+                        "trace_threshold" => &mut trace.threshold,
+                        "trace_smoothing" => &mut trace.smoothing,
+                        _ => &mut trace.tolerance,
+                    };
+                    if let Ok(v) = value.parse::<f32>() {
+                        *field = v.max(0.0);
+                    }
+                }
+                "trace_invert" => {
+                    if let Ok(v) = value.parse() {
+                        settings.trace.invert = v;
+                    }
+                }
+                "trace_despeckle" => {
+                    if let Ok(v) = value.parse() {
+                        settings.trace.despeckle = v;
+                    }
+                }
+                "stipple_points" => {
+                    if let Ok(v) = value.parse::<usize>() {
+                        settings.stipple.points = v.clamp(10, 100_000);
+                    }
+                }
+                "stipple_iterations" => {
+                    if let Ok(v) = value.parse::<u32>() {
+                        settings.stipple.iterations = v.min(200);
+                    }
+                }
+                "stipple_dot_size" => {
+                    if let Ok(v) = value.parse::<f32>() {
+                        settings.stipple.dot_size = v.max(0.05);
+                    }
+                }
+                "stipple_connect" => {
+                    if let Ok(v) = value.parse() {
+                        settings.stipple.connect = v;
+                    }
+                }
+                "halftone_pattern" => {
+                    if let Some(v) = string(value).as_deref().and_then(HalftonePattern::parse) {
+                        settings.halftone.pattern = v;
+                    }
+                }
+                "halftone_spacing" | "halftone_angle" | "halftone_min_amplitude" | "halftone_max_amplitude" | "halftone_pen_width" => {
+                    let halftone = &mut settings.halftone;
+                    let field = match key {
+                        "halftone_spacing" => &mut halftone.spacing,
+                        "halftone_angle" => &mut halftone.angle,
+                        "halftone_min_amplitude" => &mut halftone.min_amplitude,
+                        "halftone_max_amplitude" => &mut halftone.max_amplitude,
+                        _ => &mut halftone.pen_width,
+                    };
+                    if let Ok(v) = value.parse::<f32>() {
+                        *field = v;
+                    }
+                }
+                "active_profile" => settings.active_profile = string(value).unwrap_or_default(),
+                "simulation_draw_speed" | "simulation_travel_speed" | "simulation_acceleration" | "simulation_pen_delay" => {
+                    let simulation = &mut settings.simulation;
+                    let field = match key {
+                        "simulation_draw_speed" => &mut simulation.draw_speed,
+                        "simulation_travel_speed" => &mut simulation.travel_speed,
+                        "simulation_acceleration" => &mut simulation.acceleration,
+                        _ => &mut simulation.pen_delay,
+                    };
+                    if let Ok(v) = value.parse::<f32>() {
+                        *field = v;
+                    }
+                }
+                "plotter_port" => settings.plotter_port = string(value).unwrap_or_default(),
+                "grbl_port" => settings.grbl_port = string(value).unwrap_or_default(),
+                "hpgl_network" => {
+                    if let Ok(v) = value.parse() {
+                        settings.hpgl_output.network = v;
+                    }
+                }
+                "hpgl_port" => settings.hpgl_output.port = string(value).unwrap_or_default(),
+                "hpgl_baud" => settings.hpgl_output.line.baud = value.parse().ok().filter(|&b| b > 0),
+                "hpgl_flow" => {
+                    if let Some(v) = string(value).as_deref().and_then(FlowControl::parse) {
+                        settings.hpgl_output.line.flow = v;
+                    }
+                }
+                "hpgl_host" => settings.hpgl_output.host = string(value).unwrap_or_default(),
+                "hpgl_tcp_port" => {
+                    if let Ok(v) = value.parse() {
+                        settings.hpgl_output.tcp_port = v;
+                    }
+                }
+                "hpgl_pens" => {
+                    if let Ok(v) = value.parse::<u32>() {
+                        settings.hpgl.pens = v.clamp(1, 8);
+                    }
+                }
+                "hpgl_velocity" | "hpgl_tolerance" => {
+                    let field = if key == "hpgl_velocity" { &mut settings.hpgl.velocity } else { &mut settings.hpgl.tolerance };
+                    if let Ok(v) = value.parse::<f32>() {
+                        *field = v;
+                    }
+                }
+                "hpgl_optimize" => {
+                    if let Ok(v) = value.parse() {
+                        settings.hpgl.optimize = v;
+                    }
+                }
+                "plotter_speed_down" | "plotter_speed_up" | "plotter_pen_up" | "plotter_pen_down" | "plotter_tolerance" => {
+                    let plotter = &mut settings.plotter;
+                    let field = match key {
+                        "plotter_speed_down" => &mut plotter.speed_down,
+                        "plotter_speed_up" => &mut plotter.speed_up,
+                        "plotter_pen_up" => &mut plotter.pen_up,
+                        "plotter_pen_down" => &mut plotter.pen_down,
+                        _ => &mut plotter.tolerance,
+                    };
+                    if let Ok(v) = value.parse::<f32>() {
+                        *field = v;
+                    }
+                }
+                "plotter_pen_delay" => {
+                    if let Ok(v) = value.parse::<u32>() {
+                        settings.plotter.pen_delay = v.min(5000);
+                    }
+                }
+                "plotter_optimize" => {
+                    if let Ok(v) = value.parse() {
+                        settings.plotter.optimize = v;
+                    }
+                }
+                "grid_unit" => settings.grid.unit = string(value).as_deref().and_then(Unit::parse),
+                "grid_subdivisions" => {
+                    if let Ok(v) = value.parse::<u32>() {
+                        settings.grid.subdivisions = v.clamp(1, 20);
+                    }
+                }
+                "recent_files" => {
+                    has_recent = true;
+                    settings.recent_files = array(value).into_iter().filter_map(string).map(PathBuf::from).take(MAX_RECENT_FILES).collect();
+                }
+                "window_size" => {
+                    let size: Vec<_> = array(value).iter().map(|v| v.parse::<f32>()).collect();
+                    if let [Ok(w), Ok(h)] = size.as_slice() {
+                        settings.window_size = Some([w.max(200.0), h.max(150.0)]);
+                    }
+                }
+                "window_position" => {
+                    let position: Vec<_> = array(value).iter().map(|v| v.parse::<i32>()).collect();
+                    if let [Ok(x), Ok(y)] = position.as_slice() {
+                        settings.window_position = Some([*x, *y]);
+                    }
+                }
+                "window_maximized" => {
+                    if let Ok(v) = value.parse() {
+                        settings.window_maximized = v;
+                    }
+                }
+                _ => {}
+            }
+        }
+        if !has_recent {
+            // kept in recent.txt before there were settings
+            settings.recent_files = load_recent_files();
+        }
+        settings
+    }
+
+    pub fn save(&self) {
+        let Some(path) = settings_path() else { return };
+        let grid = &self.grid;
+        let mut text = String::new();
+        let _ = writeln!(text, "curve_tolerance = {}", self.curve_tolerance);
+        let _ = writeln!(text, "antialiasing = {}", self.antialiasing);
+        let _ = writeln!(text, "strict_parsing = {}", self.strict_parsing);
+        let _ = writeln!(text, "text_to_paths = {}", self.text_to_paths);
+        let _ = writeln!(text, "unit = {}", quote(self.unit.name()));
+        let _ = writeln!(text, "dpi = {}", self.dpi);
+        if let Some(dpi) = self.screen_dpi {
+            let _ = writeln!(text, "screen_dpi = {}", dpi);
+        }
+        let _ = writeln!(text, "layer_file_pattern = {}", quote(&self.layer_file_pattern));
+        let _ = writeln!(text, "background = {}", quote(&self.background.to_string()));
+        let _ = writeln!(text, "presentation_background = {}", quote(&self.presentation_background.to_string()));
+        let _ = writeln!(text, "slideshow_interval = {}", self.slideshow_interval);
+        let _ = writeln!(text, "slideshow_crossfade = {}", self.slideshow_crossfade);
+        let _ = writeln!(text, "grid_show = {}", grid.show);
+        let _ = writeln!(text, "grid_snapping = {}", grid.snapping);
+        let _ = writeln!(text, "grid_spacing = {}", grid.spacing);
+        let _ = writeln!(text, "grid_unit = {}", quote(grid.unit.map_or("user", Unit::name)));
+        let _ = writeln!(text, "grid_subdivisions = {}", grid.subdivisions);
+        let gcode = &self.gcode;
+        let _ = writeln!(text, "gcode_feed_rate = {}", gcode.feed_rate);
+        let _ = writeln!(text, "gcode_travel_rate = {}", gcode.travel_rate);
+        let _ = writeln!(text, "gcode_laser = {}", gcode.laser);
+        let _ = writeln!(text, "gcode_z_up = {}", gcode.z_up);
+        let _ = writeln!(text, "gcode_z_down = {}", gcode.z_down);
+        let _ = writeln!(text, "gcode_laser_power = {}", gcode.laser_power);
+        let _ = writeln!(text, "gcode_units = {}", quote(if gcode.units == GcodeUnits::Inch { "inch" } else { "mm" }));
+        let _ = writeln!(text, "gcode_origin = {}", quote(gcode.origin.name()));
+        let _ = writeln!(text, "gcode_invert_x = {}", gcode.invert_x);
+        let _ = writeln!(text, "gcode_invert_y = {}", gcode.invert_y);
+        let _ = writeln!(text, "gcode_tolerance = {}", gcode.tolerance);
+        let _ = writeln!(text, "gcode_optimize = {}", gcode.optimize);
+        let _ = writeln!(text, "cut_outline_strokes = {}", self.cut.outline_strokes);
+        let _ = writeln!(text, "cut_close_gaps = {}", self.cut.close_gaps);
+        let _ = writeln!(text, "cut_tolerance = {}", self.cut.tolerance);
+        let _ = writeln!(text, "cut_format = {}", quote(self.cut_format.name()));
+        let _ = writeln!(text, "tikz_precision = {}", self.tikz.precision);
+        let _ = writeln!(text, "tikz_scale = {}", self.tikz.scale);
+        let _ = writeln!(text, "tikz_standalone = {}", self.tikz.standalone);
+        let _ = writeln!(text, "trace_threshold = {}", self.trace.threshold);
+        let _ = writeln!(text, "trace_invert = {}", self.trace.invert);
+        let _ = writeln!(text, "trace_despeckle = {}", self.trace.despeckle);
+        let _ = writeln!(text, "trace_smoothing = {}", self.trace.smoothing);
+        let _ = writeln!(text, "trace_tolerance = {}", self.trace.tolerance);
+        let _ = writeln!(text, "stipple_points = {}", self.stipple.points);
+        let _ = writeln!(text, "stipple_iterations = {}", self.stipple.iterations);
+        let _ = writeln!(text, "stipple_dot_size = {}", self.stipple.dot_size);
+        let _ = writeln!(text, "stipple_connect = {}", self.stipple.connect);
+        let _ = writeln!(text, "halftone_pattern = {}", quote(self.halftone.pattern.name()));
+        let _ = writeln!(text, "halftone_spacing = {}", self.halftone.spacing);
+        let _ = writeln!(text, "halftone_angle = {}", self.halftone.angle);
+        let _ = writeln!(text, "halftone_min_amplitude = {}", self.halftone.min_amplitude);
+        let _ = writeln!(text, "halftone_max_amplitude = {}", self.halftone.max_amplitude);
+        let _ = writeln!(text, "halftone_pen_width = {}", self.halftone.pen_width);
+        let simulation = &self.simulation;
+        let _ = writeln!(text, "simulation_draw_speed = {}", simulation.draw_speed);
+        let _ = writeln!(text, "simulation_travel_speed = {}", simulation.travel_speed);
+        let _ = writeln!(text, "simulation_acceleration = {}", simulation.acceleration);
+        let _ = writeln!(text, "simulation_pen_delay = {}", simulation.pen_delay);
+        let plotter = &self.plotter;
+        let _ = writeln!(text, "plotter_port = {}", quote(&self.plotter_port));
+        let _ = writeln!(text, "plotter_speed_down = {}", plotter.speed_down);
+        let _ = writeln!(text, "plotter_speed_up = {}", plotter.speed_up);
+        let _ = writeln!(text, "plotter_pen_up = {}", plotter.pen_up);
+        let _ = writeln!(text, "plotter_pen_down = {}", plotter.pen_down);
+        let _ = writeln!(text, "plotter_pen_delay = {}", plotter.pen_delay);
+        let _ = writeln!(text, "plotter_tolerance = {}", plotter.tolerance);
+        let _ = writeln!(text, "plotter_optimize = {}", plotter.optimize);
+        let _ = writeln!(text, "grbl_port = {}", quote(&self.grbl_port));
+        let (output, hpgl) = (&self.hpgl_output, &self.hpgl);
+        let _ = writeln!(text, "hpgl_network = {}", output.network);
+        let _ = writeln!(text, "hpgl_port = {}", quote(&output.port));
+        let _ = writeln!(text, "hpgl_baud = {}", output.line.baud.unwrap_or(0));
+        let _ = writeln!(text, "hpgl_flow = {}", quote(output.line.flow.name()));
+        let _ = writeln!(text, "hpgl_host = {}", quote(&output.host));
+        let _ = writeln!(text, "hpgl_tcp_port = {}", output.tcp_port);
+        let _ = writeln!(text, "hpgl_pens = {}", hpgl.pens);
+        let _ = writeln!(text, "hpgl_velocity = {}", hpgl.velocity);
+        let _ = writeln!(text, "hpgl_tolerance = {}", hpgl.tolerance);
+        let _ = writeln!(text, "hpgl_optimize = {}", hpgl.optimize);
+        let recent: Vec<String> = self.recent_files.iter().map(|p| quote(&p.to_string_lossy())).collect();
+        let _ = writeln!(text, "recent_files = [{}]", recent.join(", "));
+        if let Some([w, h]) = self.window_size {
+            let _ = writeln!(text, "window_size = [{}, {}]", w, h);
+        }
+        if let Some([x, y]) = self.window_position {
+            let _ = writeln!(text, "window_position = [{}, {}]", x, y);
+        }
+        let _ = writeln!(text, "window_maximized = {}", self.window_maximized);
+        let _ = writeln!(text, "active_profile = {}", quote(&self.active_profile));
+        for profile in &self.profiles {
+            let _ = writeln!(text, "\n[[profiles]]");
+            let _ = writeln!(text, "name = {}", quote(&profile.name));
+            let _ = writeln!(text, "kind = {}", quote(profile.kind.name()));
+            let _ = writeln!(text, "bed = [{}, {}]", profile.bed[0], profile.bed[1]);
+            let _ = writeln!(text, "origin = {}", quote(profile.origin.name()));
+            let _ = writeln!(text, "invert_x = {}", profile.invert_x);
+            let _ = writeln!(text, "invert_y = {}", profile.invert_y);
+            let _ = writeln!(text, "draw_speed = {}", profile.draw_speed);
+            let _ = writeln!(text, "travel_speed = {}", profile.travel_speed);
+            let _ = writeln!(text, "acceleration = {}", profile.acceleration);
+            let _ = writeln!(text, "pen_up = {}", profile.pen_up);
+            let _ = writeln!(text, "pen_down = {}", profile.pen_down);
+        }
+        let _ = writeln!(text, "\n[keys]");
+        for action in Action::ALL {
+            let _ = writeln!(text, "{} = {}", action.name(), quote(&self.keys.text(action)));
+        }
+        if let Err(e) = path.parent().map_or(Ok(()), fs::create_dir_all).and_then(|_| fs::write(&path, text)) {
+            log::error!("Failed to save settings to {}: {}", path.display(), e);
+        }
+    }
+
+    /// The profile named by `active_profile`.
+    pub fn active_profile(&self) -> Option<&MachineProfile> {
+        self.profiles.iter().find(|p| p.name == self.active_profile)
+    }
+
+    /// Take over the active profile into the settings of the exporters and devices.
+    pub fn apply_profile(&mut self) {
+        let Some(profile) = self.active_profile().cloned() else { return };
+        profile.apply_gcode(&mut self.gcode);
+        profile.apply_plotter(&mut self.plotter);
+        profile.apply_hpgl(&mut self.hpgl);
+        profile.apply_simulation(&mut self.simulation);
+    }
+}
+
+/// A `key = value` line of a `[[profiles]]` table.
+fn profile_key(profile: &mut MachineProfile, key: &str, value: &str) {
+    match key {
+        "name" => profile.name = string(value).unwrap_or_default(),
+        "kind" => {
+            if let Some(v) = string(value).as_deref().and_then(MachineKind::parse) {
+                profile.kind = v;
+            }
+        }
+        "bed" => {
+            let size: Vec<_> = array(value).iter().map(|v| v.parse::<f32>()).collect();
+            if let [Ok(w), Ok(h)] = size.as_slice() {
+                profile.bed = [w.max(1.0), h.max(1.0)];
+            }
+        }
+        "origin" => {
+            if let Some(v) = string(value).as_deref().and_then(GcodeOrigin::parse) {
+                profile.origin = v;
+            }
+        }
+        "invert_x" | "invert_y" => {
+            let field = if key == "invert_x" { &mut profile.invert_x } else { &mut profile.invert_y };
+            if let Ok(v) = value.parse() {
+                *field = v;
+            }
+        }
+        "draw_speed" | "travel_speed" | "acceleration" | "pen_up" | "pen_down" => {
+            let field = match key {
+                "draw_speed" => &mut profile.draw_speed,
+                "travel_speed" => &mut profile.travel_speed,
+                "acceleration" => &mut profile.acceleration,
+                "pen_up" => &mut profile.pen_up,
+                _ => &mut profile.pen_down,
+            };
+            if let Ok(v) = value.parse::<f32>() {
+                *field = v;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// TOML basic string.
+fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04X}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Reads what `quote` writes. Older settings files had strings without quotes, those are
+/// taken as they are.
+fn string(value: &str) -> Option<String> {
+    let Some(inner) = value.strip_prefix('"') else { return Some(value.to_string()) };
+    let mut s = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Some(s),
+            '\\' => match chars.next()? {
+                'n' => s.push('\n'),
+                't' => s.push('\t'),
+                'u' => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    s.push(u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32)?);
+                }
+                c => s.push(c),
+            },
+            c => s.push(c),
+        }
+    }
+    None
+}
+
+/// Elements of a one-line array, strings still quoted.
+fn array(value: &str) -> Vec<&str> {
+    let Some(inner) = value.strip_prefix('[').and_then(|v| v.trim_end().strip_suffix(']')) else { return vec![] };
+    let mut items = vec![];
+    let (mut start, mut in_string, mut escaped) = (0, false, false);
+    for (i, c) in inner.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            ',' if !in_string => {
+                items.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(inner[start..].trim());
+    items.retain(|item| !item.is_empty());
+    items
+}
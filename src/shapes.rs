@@ -0,0 +1,245 @@
+use vectorlab_core::{add_shape, ellipse, polygon, polyline, rectangle, Color, Paint, Segment, Unit};
+
+use crate::grid::Grid;
+use crate::inspector::paint_editor;
+use crate::tab::Tab;
+use crate::units::length_value;
+
+/// Pixels from the first point of a polyline within which a click closes it.
+const CLOSE_DISTANCE: f32 = 6.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShapeKind {
+    Rectangle,
+    Ellipse,
+    Polygon,
+    Line,
+}
+
+impl ShapeKind {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Rectangle => "▭",
+            Self::Ellipse => "◯",
+            Self::Polygon => "⬠",
+            Self::Line => "╱",
+        }
+    }
+
+    fn hint(self) -> &'static str {
+        match self {
+            Self::Rectangle => "Rectangle: drag from corner to corner, Shift for a square",
+            Self::Ellipse => "Ellipse: drag across its box, Shift for a circle",
+            Self::Polygon => "Polygon or star: drag from the center to a corner, Shift snaps the angle",
+            Self::Line => "Lines: click to add points, double click or Enter to finish, click the first point to close. Drag for a single line, Shift snaps the angle",
+        }
+    }
+}
+
+/// What the shape tool draws and how, the same for all tabs.
+pub struct ShapeSettings {
+    pub kind: ShapeKind,
+    /// of polygons and stars
+    pub corners: u32,
+    pub star: bool,
+    /// inner corners of a star, as a fraction of the radius
+    pub inner: f32,
+    pub fill: Option<Paint>,
+    pub stroke: Option<Paint>,
+    /// mm
+    pub stroke_width: f32,
+}
+
+impl Default for ShapeSettings {
+    fn default() -> Self {
+        Self { kind: ShapeKind::Rectangle, corners: 5, star: false, inner: 0.5, fill: None, stroke: Some(Paint::Solid(Color::BLACK)), stroke_width: 0.3 }
+    }
+}
+
+impl ShapeSettings {
+    /// The shape through `points` in document coordinates, None while there are too few.
+    fn segments(&self, points: &[[f32; 2]], closed: bool) -> Option<Vec<Segment>> {
+        match (self.kind, points) {
+            (ShapeKind::Line, _) if points.len() >= 2 => Some(polyline(points, closed)),
+            (ShapeKind::Rectangle, &[a, b]) => Some(rectangle(a, b)),
+            (ShapeKind::Ellipse, &[a, b]) => Some(ellipse(a, b)),
+            (ShapeKind::Polygon, &[a, b]) => Some(polygon(a, b, self.corners, self.star.then_some(self.inner))),
+            _ => None,
+        }
+    }
+}
+
+/// Shape tool state of a tab.
+#[derive(Default)]
+pub struct ShapeDraw {
+    /// placed so far, in document coordinates. The corners or center and corner of a shape
+    /// being dragged, the points of a polyline.
+    points: Vec<[f32; 2]>,
+    /// where the pointer would put the next point of a polyline
+    hover: Option<[f32; 2]>,
+    dragging: bool,
+}
+
+/// Shape tool: drag out rectangles, ellipses and polygons, click polylines together. Points
+/// snap to `grid`. The finished shape is added on top and selected.
+pub fn handle_shapes(ui: &egui::Ui, tab: &mut Tab, settings: &ShapeSettings, grid: &Grid, canvas: egui::Rect, response: &egui::Response) {
+    let space_held = ui.input(|i| i.key_down(egui::Key::Space));
+    let shift = ui.input(|i| i.modifiers.shift);
+    let to_doc = |tab: &Tab, pos: egui::Pos2| grid.snap(tab.view.to_doc([pos.x - canvas.min.x, pos.y - canvas.min.y]), &tab.doc, tab.view.zoom);
+    if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+        tab.shape = ShapeDraw::default();
+        return;
+    }
+
+    if response.drag_started_by(egui::PointerButton::Primary) && !space_held && tab.shape.points.is_empty() {
+        if let Some(origin) = ui.input(|i| i.pointer.press_origin()) {
+            let p = to_doc(tab, origin);
+            tab.shape.points = vec![p, p];
+            tab.shape.dragging = true;
+        }
+    }
+    if tab.shape.dragging {
+        if let Some(pos) = response.interact_pointer_pos().filter(|_| response.dragged_by(egui::PointerButton::Primary)) {
+            let p = to_doc(tab, pos);
+            let start = tab.shape.points[0];
+            tab.shape.points[1] = if shift { constrain(settings.kind, start, p) } else { p };
+        }
+        if response.drag_stopped() {
+            let points = std::mem::take(&mut tab.shape.points);
+            tab.shape.dragging = false;
+            finish(tab, settings, &points, false);
+        }
+        return;
+    }
+
+    if settings.kind != ShapeKind::Line || space_held {
+        return;
+    }
+    let last = tab.shape.points.last().copied();
+    tab.shape.hover = response.hover_pos().map(|pos| {
+        let p = to_doc(tab, pos);
+        match last {
+            Some(last) if shift => constrain(ShapeKind::Line, last, p),
+            _ => p,
+        }
+    });
+    let enter = ui.input(|i| i.key_pressed(egui::Key::Enter));
+    if response.double_clicked() || enter {
+        // the first click of a double click already placed the point
+        let points = std::mem::take(&mut tab.shape.points);
+        tab.shape.hover = None;
+        finish(tab, settings, &points, false);
+    } else if response.clicked() {
+        let Some(p) = tab.shape.hover else { return };
+        let first = tab.shape.points.first().map(|&first| egui::Pos2::from(tab.view.to_screen(first)) + canvas.min.to_vec2());
+        let closes = tab.shape.points.len() > 2 && first.zip(response.interact_pointer_pos()).is_some_and(|(first, pos)| first.distance(pos) <= CLOSE_DISTANCE);
+        if closes {
+            let points = std::mem::take(&mut tab.shape.points);
+            tab.shape.hover = None;
+            finish(tab, settings, &points, true);
+        } else {
+            tab.shape.points.push(p);
+        }
+    }
+}
+
+/// Square boxes, and angles in steps of 15° from `start`.
+fn constrain(kind: ShapeKind, start: [f32; 2], p: [f32; 2]) -> [f32; 2] {
+    let d = [p[0] - start[0], p[1] - start[1]];
+    match kind {
+        ShapeKind::Rectangle | ShapeKind::Ellipse => {
+            let size = d[0].abs().max(d[1].abs());
+            [start[0] + size.copysign(d[0]), start[1] + size.copysign(d[1])]
+        }
+        ShapeKind::Polygon | ShapeKind::Line => {
+            let step = 15f32.to_radians();
+            let angle = (d[1].atan2(d[0]) / step).round() * step;
+            let length = d[0].hypot(d[1]);
+            [start[0] + length * angle.cos(), start[1] + length * angle.sin()]
+        }
+    }
+}
+
+/// Add the shape through `points` to the document and select it, unless it has no size.
+fn finish(tab: &mut Tab, settings: &ShapeSettings, points: &[[f32; 2]], closed: bool) {
+    let mut points = points.to_vec();
+    points.dedup();
+    let Some(b) = vectorlab_core::bbox_of(points.iter()) else { return };
+    if b[2] - b[0] <= f32::EPSILON && b[3] - b[1] <= f32::EPSILON {
+        return;
+    }
+    let Some(segments) = settings.segments(&points, closed) else { return };
+    let mm = tab.doc.mm_per_unit();
+    let width = settings.stroke_width * 2.0 / (mm[0] + mm[1]).max(f32::EPSILON);
+    let edit = add_shape(&tab.doc, segments, settings.fill.clone(), settings.stroke.clone(), width);
+    tab.selection.clear();
+    tab.push_and_select_new(Box::new(edit));
+}
+
+/// The shape being drawn, as a thin outline.
+pub fn draw_shape_preview(painter: &egui::Painter, canvas: egui::Rect, tab: &Tab, settings: &ShapeSettings) {
+    let mut points = tab.shape.points.clone();
+    points.extend(tab.shape.hover.filter(|_| settings.kind == ShapeKind::Line && !tab.shape.dragging && !points.is_empty()));
+    let Some(segments) = settings.segments(&points, false) else { return };
+    let to_screen = |p: [f32; 2]| egui::Pos2::from(tab.view.to_screen(p)) + canvas.min.to_vec2();
+    let stroke = egui::Stroke::new(1.5, egui::Color32::from_rgb(0, 160, 255));
+    let mut line: Vec<egui::Pos2> = vec![];
+    let mut start = [0.0; 2];
+    let mut at = [0.0; 2];
+    for segment in segments {
+        match segment {
+            Segment::MoveTo(p) => {
+                painter.add(egui::Shape::line(std::mem::take(&mut line), stroke));
+                line.push(to_screen(p));
+                (start, at) = (p, p);
+            }
+            Segment::LineTo(p) => {
+                line.push(to_screen(p));
+                at = p;
+            }
+            Segment::QuadTo(c, p) => {
+                line.extend((1..=16).map(|i| to_screen(cubic(at, c, c, p, i as f32 / 16.0))));
+                at = p;
+            }
+            Segment::CubicTo(c1, c2, p) => {
+                line.extend((1..=16).map(|i| to_screen(cubic(at, c1, c2, p, i as f32 / 16.0))));
+                at = p;
+            }
+            Segment::Close => {
+                line.push(to_screen(start));
+                at = start;
+            }
+        }
+    }
+    painter.add(egui::Shape::line(line, stroke));
+    // where a click closes the polyline
+    if settings.kind == ShapeKind::Line && tab.shape.points.len() > 2 {
+        painter.circle_stroke(to_screen(tab.shape.points[0]), CLOSE_DISTANCE, stroke);
+    }
+}
+
+fn cubic(p0: [f32; 2], p1: [f32; 2], p2: [f32; 2], p3: [f32; 2], t: f32) -> [f32; 2] {
+    let u = 1.0 - t;
+    let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+    [a * p0[0] + b * p1[0] + c * p2[0] + d * p3[0], a * p0[1] + b * p1[1] + c * p2[1] + d * p3[1]]
+}
+
+/// The kind of shape, corners, fill and stroke, for the toolbar.
+pub fn shape_options(ui: &mut egui::Ui, settings: &mut ShapeSettings, unit: Unit) {
+    for kind in [ShapeKind::Rectangle, ShapeKind::Ellipse, ShapeKind::Polygon, ShapeKind::Line] {
+        ui.selectable_value(&mut settings.kind, kind, kind.label()).on_hover_text(kind.hint());
+    }
+    if settings.kind == ShapeKind::Polygon {
+        ui.add(egui::DragValue::new(&mut settings.corners).range(3..=100).suffix(" corners"));
+        ui.checkbox(&mut settings.star, "Star");
+        if settings.star {
+            ui.add(egui::DragValue::new(&mut settings.inner).speed(0.01).range(0.05..=1.0)).on_hover_text("How far out the inner corners are, as a fraction of the radius");
+        }
+    }
+    ui.separator();
+    ui.label("Fill");
+    paint_editor(ui, &mut settings.fill);
+    ui.label("Stroke");
+    paint_editor(ui, &mut settings.stroke);
+    ui.add(length_value(&mut settings.stroke_width, unit, 0.01, 0.0..=100.0));
+}
@@ -0,0 +1,165 @@
+use std::time::Instant;
+
+use vectorlab_core::{job_contours, machine_origin, simulate, tool_moves, Document, GcodeSettings, Simulation, SimulationSettings, ViewTransform};
+
+use crate::job::format_duration;
+
+/// Playback speeds, simulated seconds per second.
+const RATES: [f32; 7] = [1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0];
+
+/// The File > Simulate plot window: the job played through on the canvas at the machine's
+/// speeds.
+pub struct PlotSimulation {
+    draft: SimulationSettings,
+    optimize: bool,
+    simulation: Simulation,
+    /// the same job in drawing order, to compare
+    unoptimized: Option<Simulation>,
+    // document revision and settings the simulation was made for
+    made_for: Option<(u64, SimulationSettings, bool)>,
+    /// simulated seconds since the start
+    time: f32,
+    playing: bool,
+    rate: f32,
+    last_frame: Option<Instant>,
+}
+
+impl PlotSimulation {
+    pub fn new(settings: &SimulationSettings, optimize: bool) -> Self {
+        Self {
+            draft: settings.clone(),
+            optimize,
+            simulation: Simulation::default(),
+            unoptimized: None,
+            made_for: None,
+            time: 0.0,
+            playing: true,
+            rate: 10.0,
+            last_frame: None,
+        }
+    }
+
+    pub fn playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Simulate `doc` again if it or the settings changed since.
+    fn update(&mut self, doc: &Document, gcode: &GcodeSettings) {
+        if self.made_for.as_ref().is_some_and(|(revision, settings, optimize)| *revision == doc.revision && *settings == self.draft && *optimize == self.optimize) {
+            return;
+        }
+        let run = |optimize| {
+            let gcode = GcodeSettings { optimize, ..gcode.clone() };
+            let moves = tool_moves(&job_contours(doc, &gcode), machine_origin(doc, &gcode));
+            simulate(&moves, doc.mm_per_unit(), &self.draft)
+        };
+        self.simulation = run(self.optimize);
+        self.unoptimized = self.optimize.then(|| run(false));
+        self.time = self.time.min(self.simulation.duration);
+        self.made_for = Some((doc.revision, self.draft.clone(), self.optimize));
+    }
+
+    /// Move the playback on by the time since the last frame.
+    fn advance(&mut self) {
+        let now = Instant::now();
+        if let (true, Some(last)) = (self.playing, self.last_frame) {
+            self.time += now.duration_since(last).as_secs_f32() * self.rate;
+            if self.time >= self.simulation.duration {
+                self.time = self.simulation.duration;
+                self.playing = false;
+            }
+        }
+        self.last_frame = Some(now);
+    }
+}
+
+fn seconds(s: f32) -> String {
+    format_duration((s * 1000.0) as u64)
+}
+
+/// Show the window while `simulation` is Some, with the job of the machine setup of Export
+/// G-code. Returns the settings when the window is closed, to keep them.
+pub fn simulation_window(ctx: &egui::Context, simulation: &mut Option<PlotSimulation>, doc: Option<&Document>, gcode: &GcodeSettings) -> Option<SimulationSettings> {
+    let state = simulation.as_mut()?;
+    if let Some(doc) = doc {
+        state.update(doc, gcode);
+    }
+    state.advance();
+
+    let mut open = true;
+    egui::Window::new("Simulate plot").open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+        let draft = &mut state.draft;
+        egui::Grid::new("simulation").num_columns(2).show(ui, |ui| {
+            ui.label("Speed down / up");
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut draft.draw_speed).speed(1.0).range(1.0..=1000.0).suffix(" mm/s"));
+                ui.add(egui::DragValue::new(&mut draft.travel_speed).speed(1.0).range(1.0..=1000.0).suffix(" mm/s"));
+            });
+            ui.end_row();
+            ui.label("Acceleration");
+            ui.add(egui::DragValue::new(&mut draft.acceleration).speed(10.0).range(10.0..=100000.0).suffix(" mm/s²"));
+            ui.end_row();
+            ui.label("Pen up / down");
+            ui.add(egui::DragValue::new(&mut draft.pen_delay).speed(5.0).range(0.0..=5000.0).suffix(" ms"));
+            ui.end_row();
+            ui.label("");
+            ui.checkbox(&mut state.optimize, "Optimize").on_hover_text("Reorder and reverse the paths so the pen travels less");
+            ui.end_row();
+        });
+        ui.separator();
+
+        let sim = &state.simulation;
+        ui.label(format!("{} in all, drawing {:.0} mm, travelling {:.0} mm", seconds(sim.duration), sim.draw_length, sim.travel_length));
+        if let Some(unoptimized) = &state.unoptimized {
+            ui.weak(format!("In drawing order {}, travelling {:.0} mm", seconds(unoptimized.duration), unoptimized.travel_length));
+        }
+        let text = format!("{} / -{}", seconds(state.time), seconds(sim.duration - state.time));
+        ui.add(egui::Slider::new(&mut state.time, 0.0..=sim.duration.max(0.001)).show_value(false).text(text));
+        ui.horizontal(|ui| {
+            let at_end = state.time >= sim.duration;
+            if ui.button(if state.playing { "Pause" } else { "Play" }).clicked() {
+                if at_end {
+                    state.time = 0.0;
+                }
+                state.playing = !state.playing;
+            }
+            if ui.button("Restart").clicked() {
+                state.time = 0.0;
+            }
+            egui::ComboBox::from_id_source("simulation_rate").selected_text(format!("{}×", state.rate)).show_ui(ui, |ui| {
+                for rate in RATES {
+                    ui.selectable_value(&mut state.rate, rate, format!("{}×", rate));
+                }
+            });
+        });
+    });
+    if !open {
+        return simulation.take().map(|s| s.draft);
+    }
+    None
+}
+
+/// What the pen has traced by now, drawing solid and travel dashed, and where it is.
+pub fn draw_simulation(painter: &egui::Painter, canvas: egui::Rect, view: &ViewTransform, simulation: &PlotSimulation) {
+    let sim = &simulation.simulation;
+    let Some((pen, current)) = sim.position_at(simulation.time) else { return };
+    let to_screen = |p: [f32; 2]| egui::Pos2::from(view.to_screen(p)) + canvas.min.to_vec2();
+    let draw = egui::Stroke::new(2.0, egui::Color32::from_rgb(40, 110, 230));
+    let travel = egui::Stroke::new(1.0, egui::Color32::from_rgb(255, 80, 80));
+    for (i, m) in sim.moves[..=current].iter().enumerate() {
+        let to = if i == current { pen } else { m.tool_move.to };
+        let line = [to_screen(m.tool_move.from), to_screen(to)];
+        if m.tool_move.cutting {
+            painter.line_segment(line, draw);
+        } else {
+            painter.extend(egui::Shape::dashed_line(&line, travel, 6.0, 4.0));
+        }
+    }
+    let down = sim.moves[current].tool_move.cutting && simulation.time >= sim.moves[current].start;
+    let color = if down { draw.color } else { travel.color };
+    if down {
+        painter.circle_filled(to_screen(pen), 5.0, color);
+    } else {
+        painter.circle_stroke(to_screen(pen), 6.0, egui::Stroke::new(2.0, color));
+    }
+}
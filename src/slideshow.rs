@@ -0,0 +1,96 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::background::Background;
+use crate::canvas::ImageCache;
+use crate::presentation::draw_slide;
+use crate::tab::Tab;
+
+/// Plays the open tabs one after the other in presentation mode.
+pub struct Slideshow {
+    /// seconds each slide is shown
+    interval: f32,
+    /// seconds the previous slide takes to fade into the next one, 0 for a cut
+    crossfade: f32,
+    /// files in the order they were given, tabs finish loading in any order
+    playlist: Vec<PathBuf>,
+    /// when the current slide came up
+    shown_at: Instant,
+    /// tab index of the slide fading out
+    previous: Option<usize>,
+    paused: bool,
+}
+
+impl Slideshow {
+    pub fn new(interval: f32, crossfade: f32, playlist: Vec<PathBuf>) -> Self {
+        Self { interval: interval.max(0.1), crossfade: crossfade.max(0.0), playlist, shown_at: Instant::now(), previous: None, paused: false }
+    }
+
+    /// Advance when it is time and draw the slide, fading from the previous one. Space
+    /// pauses, the arrow keys step. Returns the tab to make active.
+    pub fn show(&mut self, ui: &mut egui::Ui, tabs: &mut [Tab], active: usize, background: &Background, curve_tolerance: f32, images: &mut ImageCache) -> usize {
+        let (rect, _) = ui.allocate_exact_size(ui.available_size(), egui::Sense::hover());
+        let mut active = active.min(tabs.len().saturating_sub(1));
+        let (pause, step) = ui.input_mut(|i| {
+            let pause = i.consume_key(egui::Modifiers::NONE, egui::Key::Space);
+            let step = if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowRight) {
+                1
+            } else if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowLeft) {
+                -1
+            } else {
+                0
+            };
+            (pause, step)
+        });
+        if pause {
+            self.paused = !self.paused;
+            self.shown_at = Instant::now();
+        }
+        let due = !self.paused && self.shown_at.elapsed().as_secs_f32() >= self.interval;
+        if (step != 0 || due) && tabs.len() > 1 {
+            self.previous = Some(active);
+            active = self.next(tabs, active, if step == 0 { 1 } else { step });
+            self.shown_at = Instant::now();
+        }
+
+        let fade = if self.crossfade > 0.0 { self.shown_at.elapsed().as_secs_f32() / self.crossfade } else { 1.0 };
+        match self.previous {
+            Some(previous) if fade < 1.0 && previous < tabs.len() => {
+                draw_slide(ui, rect, &mut tabs[previous], background, curve_tolerance, images, 1.0);
+                ui.ctx().request_repaint();
+            }
+            _ => self.previous = None,
+        }
+        if let Some(tab) = tabs.get_mut(active) {
+            draw_slide(ui, rect, tab, background, curve_tolerance, images, fade.min(1.0));
+        } else {
+            background.paint(ui.painter(), rect);
+        }
+        if self.paused {
+            let font = egui::FontId::proportional(14.0);
+            ui.painter().text(rect.right_bottom() - egui::vec2(12.0, 12.0), egui::Align2::RIGHT_BOTTOM, "⏸ paused", font, egui::Color32::GRAY);
+        }
+        active
+    }
+
+    /// When the event loop has to wake up for the next frame.
+    pub fn next_frame(&self) -> Instant {
+        if self.previous.is_some() {
+            // fading
+            Instant::now() + Duration::from_millis(16)
+        } else if self.paused {
+            Instant::now() + Duration::from_secs(3600)
+        } else {
+            self.shown_at + Duration::from_secs_f32(self.interval)
+        }
+    }
+
+    /// The tab `step` slides away from `active`, tabs in the order of the playlist and then
+    /// the rest in their own order.
+    fn next(&self, tabs: &[Tab], active: usize, step: isize) -> usize {
+        let mut order: Vec<usize> = (0..tabs.len()).collect();
+        order.sort_by_key(|&i| tabs[i].path.as_ref().and_then(|p| self.playlist.iter().position(|q| q == p)).unwrap_or(usize::MAX));
+        let position = order.iter().position(|&i| i == active).unwrap_or(0) as isize;
+        order[(position + step).rem_euclid(order.len() as isize) as usize]
+    }
+}
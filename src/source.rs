@@ -0,0 +1,230 @@
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use egui::text::{LayoutJob, TextFormat};
+use vectorlab_core::{Document, ElementId};
+
+use crate::tab::Tab;
+
+/// How long shapes picked in the source blink on the canvas.
+pub const FLASH_DURATION: Duration = Duration::from_millis(1200);
+
+/// Longer lines are broken into rows of this many bytes, minified SVG is often a single line.
+const MAX_ROW: usize = 200;
+
+/// Rows of the source with where the highlighter is at the start of each, so that only the
+/// rows on screen need to be laid out.
+pub struct SourceView {
+    // byte ranges of the rows in Document::source, without line breaks
+    rows: Vec<Range<usize>>,
+    states: Vec<Lex>,
+    // the selection the view last scrolled to
+    scrolled_to: Vec<ElementId>,
+}
+
+/// Where in the XML syntax the highlighter is.
+#[derive(Clone, Copy, PartialEq)]
+enum Lex {
+    Text,
+    TagName,
+    Tag,
+    Value(char),
+    Comment,
+    CData,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Class {
+    Text,
+    Punctuation,
+    TagName,
+    AttributeName,
+    Value,
+    Comment,
+}
+
+impl SourceView {
+    pub fn new(source: &str) -> Self {
+        let mut rows = vec![];
+        let mut start = 0;
+        for line in source.split_inclusive('\n') {
+            let end = start + line.trim_end_matches(['\n', '\r']).len();
+            let mut row_start = start;
+            while end - row_start > MAX_ROW {
+                let mut split = row_start + MAX_ROW;
+                while !source.is_char_boundary(split) {
+                    split -= 1;
+                }
+                rows.push(row_start..split);
+                row_start = split;
+            }
+            rows.push(row_start..end);
+            start += line.len();
+        }
+        let mut states = Vec::with_capacity(rows.len());
+        let mut state = Lex::Text;
+        for row in &rows {
+            states.push(state);
+            state = lex(&source[row.clone()], state, |_, _| {});
+        }
+        Self { rows, states, scrolled_to: vec![] }
+    }
+
+    fn row_at(&self, offset: usize) -> usize {
+        self.rows.partition_point(|r| r.end < offset)
+    }
+}
+
+/// The source the tab's document was loaded from, highlighting the selected element.
+/// Clicking into it selects what was made from the element there and flashes it on the
+/// canvas, selecting on the canvas scrolls to the element's definition.
+pub fn source_panel(ui: &mut egui::Ui, tab: &mut Tab) {
+    if tab.doc.source.is_empty() {
+        ui.label("No source for this document");
+        return;
+    }
+    let view = tab.source_view.get_or_insert_with(|| SourceView::new(&tab.doc.source));
+    let font = egui::TextStyle::Monospace.resolve(ui.style());
+    let row_height = ui.fonts(|f| f.row_height(&font));
+    let highlight = tab.selection.first().and_then(|&id| source_range(&tab.doc, id));
+
+    let mut scroll = egui::ScrollArea::both().auto_shrink([false, false]);
+    if view.scrolled_to != tab.selection {
+        view.scrolled_to = tab.selection.clone();
+        if let Some(range) = &highlight {
+            // a few rows of context above
+            let row = view.row_at(range.start).saturating_sub(3);
+            scroll = scroll.vertical_scroll_offset(row as f32 * (row_height + ui.spacing().item_spacing.y));
+        }
+    }
+
+    let mut clicked = None;
+    scroll.show_rows(ui, row_height, view.rows.len(), |ui, rows| {
+        for i in rows {
+            let range = view.rows[i].clone();
+            let text = &tab.doc.source[range.clone()];
+            let job = highlight_row(ui, text, range.start, view.states[i], highlight.as_ref(), &font);
+            let galley = ui.fonts(|f| f.layout_job(job));
+            let response = ui.add(egui::Label::new(galley.clone()).selectable(false).sense(egui::Sense::click()));
+            if let Some(pos) = response.interact_pointer_pos().filter(|_| response.clicked()) {
+                let index = galley.cursor_from_pos(pos - response.rect.min).ccursor.index;
+                let byte = text.char_indices().nth(index).map_or(text.len(), |(b, _)| b);
+                clicked = Some(range.start + byte);
+            }
+        }
+    });
+
+    if let Some(offset) = clicked {
+        let ids = tab.doc.elements_at_source(offset);
+        tab.selection = ids.clone();
+        // it is on screen already
+        view.scrolled_to = ids.clone();
+        tab.flash = Some((ids, Instant::now()));
+    }
+}
+
+/// Source of `id` or of the closest ancestor that has one, e.g. for glyphs of a text.
+fn source_range(doc: &Document, mut id: ElementId) -> Option<Range<usize>> {
+    loop {
+        let element = doc.get(id);
+        if let Some(range) = &element.source_range {
+            return Some(range.clone());
+        }
+        id = element.parent?;
+    }
+}
+
+fn highlight_row(ui: &egui::Ui, text: &str, offset: usize, state: Lex, highlight: Option<&Range<usize>>, font: &egui::FontId) -> LayoutJob {
+    let visuals = ui.visuals();
+    let mut job = LayoutJob::default();
+    lex(text, state, |span, class| {
+        let mut format = TextFormat::simple(font.clone(), match class {
+            Class::Text => visuals.text_color(),
+            Class::Punctuation | Class::Comment => visuals.weak_text_color(),
+            Class::TagName => egui::Color32::from_rgb(80, 140, 230),
+            Class::AttributeName => egui::Color32::from_rgb(200, 130, 60),
+            Class::Value => egui::Color32::from_rgb(100, 170, 90),
+        });
+        format.italics = class == Class::Comment;
+        // split the span where the highlighted element starts or ends
+        let mut cuts = vec![span.start, span.end];
+        if let Some(h) = highlight {
+            cuts.extend([h.start, h.end].map(|b| b.saturating_sub(offset)).into_iter().filter(|b| span.contains(b)));
+        }
+        cuts.sort_unstable();
+        for piece in cuts.windows(2).filter(|w| w[0] < w[1]) {
+            let selected = highlight.is_some_and(|h| h.contains(&(offset + piece[0])));
+            format.background = if selected { visuals.selection.bg_fill.linear_multiply(0.5) } else { egui::Color32::TRANSPARENT };
+            job.append(&text[piece[0]..piece[1]], 0.0, format.clone());
+        }
+    });
+    job
+}
+
+/// Run the highlighter over `text` starting in `state`, reporting spans of the same class.
+/// Returns the state at the end. Comments and CDATA ending on the next row are missed.
+fn lex(text: &str, mut state: Lex, mut span: impl FnMut(Range<usize>, Class)) -> Lex {
+    let mut start = 0;
+    let mut class = Class::Text;
+    for (i, c) in text.char_indices() {
+        let c_class = match state {
+            Lex::Text if c == '<' => {
+                if text[i..].starts_with("<!--") {
+                    state = Lex::Comment;
+                    Class::Comment
+                } else if text[i..].starts_with("<![CDATA[") {
+                    state = Lex::CData;
+                    Class::Punctuation
+                } else {
+                    state = Lex::TagName;
+                    Class::Punctuation
+                }
+            }
+            Lex::Text | Lex::CData => {
+                if c == '>' && text[..=i].ends_with("]]>") {
+                    state = Lex::Text;
+                }
+                Class::Text
+            }
+            Lex::TagName | Lex::Tag if c == '>' => {
+                state = Lex::Text;
+                Class::Punctuation
+            }
+            Lex::TagName if c.is_whitespace() => {
+                state = Lex::Tag;
+                Class::Text
+            }
+            Lex::TagName => Class::TagName,
+            Lex::Tag if c == '"' || c == '\'' => {
+                state = Lex::Value(c);
+                Class::Value
+            }
+            Lex::Tag if c.is_whitespace() => Class::Text,
+            Lex::Tag if matches!(c, '=' | '/' | '?') => Class::Punctuation,
+            Lex::Tag => Class::AttributeName,
+            Lex::Value(quote) => {
+                if c == quote {
+                    state = Lex::Tag;
+                }
+                Class::Value
+            }
+            Lex::Comment => {
+                if c == '>' && text[..=i].ends_with("-->") {
+                    state = Lex::Text;
+                }
+                Class::Comment
+            }
+        };
+        if c_class != class {
+            if i > start {
+                span(start..i, class);
+            }
+            start = i;
+            class = c_class;
+        }
+    }
+    if text.len() > start {
+        span(start..text.len(), class);
+    }
+    state
+}
@@ -0,0 +1,126 @@
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use vectorlab_core::{stipple, Color, RasterImage, Stipple, StippleSettings, Unit};
+
+use crate::units::length_value;
+
+/// Longest side of the preview, in points of the screen.
+const PREVIEW_SIZE: f32 = 360.0;
+
+/// The Stipple image window, for a PNG or JPEG picked with File > Stipple image.
+pub struct StippleImage {
+    /// of the file, for the id of the layer the dots go into
+    name: String,
+    image: Arc<RasterImage>,
+    draft: StippleSettings,
+    ink: egui::Color32,
+    /// of the stipple on the page, in mm
+    width: f32,
+    /// the latest stipple, with the settings it was made with
+    stippled: Option<(StippleSettings, Stipple)>,
+    running: Option<(StippleSettings, JoinHandle<Stipple>)>,
+}
+
+impl StippleImage {
+    pub fn new(name: String, image: RasterImage, settings: &StippleSettings) -> Self {
+        Self { name, image: Arc::new(image), draft: settings.clone(), ink: egui::Color32::BLACK, width: 150.0, stippled: None, running: None }
+    }
+
+    /// Whether points are being placed, to check back for them.
+    pub fn busy(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// Points and iterations are what takes time, the rest is only drawn differently.
+    fn stale(&self) -> bool {
+        let same = |s: &StippleSettings| s.points == self.draft.points && s.iterations == self.draft.iterations;
+        !self.stippled.as_ref().is_some_and(|(s, _)| same(s)) && !self.running.as_ref().is_some_and(|(s, _)| same(s))
+    }
+}
+
+/// Show the window while `state` is Some, placing the points on a thread whenever their
+/// number changes. Returns the settings used and the stipple as an SVG layer when Insert is
+/// pressed.
+pub fn stipple_window(ctx: &egui::Context, state: &mut Option<StippleImage>, unit: Unit) -> Option<(StippleSettings, String)> {
+    let window = state.as_mut()?;
+    if window.running.as_ref().is_some_and(|(_, handle)| handle.is_finished()) {
+        if let Some((settings, handle)) = window.running.take() {
+            window.stippled = handle.join().ok().map(|stipple| (settings, stipple));
+        }
+    }
+    if window.running.is_none() && window.stale() {
+        let (image, settings) = (window.image.clone(), window.draft.clone());
+        let handle = std::thread::spawn(move || stipple(&image, &settings));
+        window.running = Some((window.draft.clone(), handle));
+    }
+
+    let mut open = true;
+    let mut done = None;
+    egui::Window::new("Stipple image").open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+        let draft = &mut window.draft;
+        egui::Grid::new("stipple").num_columns(2).show(ui, |ui| {
+            ui.label("Points");
+            ui.add(egui::DragValue::new(&mut draft.points).speed(50.0).range(10..=100_000));
+            ui.end_row();
+            ui.label("Iterations");
+            ui.add(egui::DragValue::new(&mut draft.iterations).range(0..=200)).on_hover_text("More spread the points more evenly");
+            ui.end_row();
+            ui.label("Dot size");
+            ui.add(length_value(&mut draft.dot_size, unit, 0.01, 0.05..=10.0)).on_hover_text("Diameter of the dots, or width of the line, about that of the pen");
+            ui.end_row();
+            ui.label("");
+            ui.checkbox(&mut draft.connect, "Connect into one line").on_hover_text("A single path through all the points, for continuous line art");
+            ui.end_row();
+            ui.label("Width");
+            ui.add(length_value(&mut window.width, unit, 1.0, 1.0..=5000.0)).on_hover_text("Of the stipple on the page");
+            ui.end_row();
+            ui.label("Color");
+            ui.color_edit_button_srgba(&mut window.ink);
+            ui.end_row();
+        });
+
+        let [w, h] = [window.image.width.max(1) as f32, window.image.height.max(1) as f32];
+        let scale = PREVIEW_SIZE / w.max(h);
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(w, h) * scale, egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, egui::Color32::WHITE);
+        if let Some((_, stippled)) = &window.stippled {
+            let at = |p: [f32; 2]| rect.min + egui::vec2(p[0], p[1]) * scale;
+            let dot = window.draft.dot_size / window.width * w * scale;
+            if window.draft.connect {
+                let points = stippled.points.iter().map(|&p| at(p)).collect();
+                painter.add(egui::Shape::line(points, egui::Stroke::new(dot.max(0.5), window.ink)));
+            } else {
+                for &p in &stippled.points {
+                    painter.circle_filled(at(p), (dot * 0.5).max(0.5), window.ink);
+                }
+            }
+        }
+        if window.running.is_some() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Placing the points…");
+            });
+        } else if let Some((_, stippled)) = &window.stippled {
+            ui.label(format!("{} points", stippled.points.len()));
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            let ready = !window.stale() && window.running.is_none();
+            if ui.add_enabled(ready, egui::Button::new("Insert")).on_hover_text("As a new layer on top, at the top left of the page").clicked() {
+                if let Some((_, stippled)) = &window.stippled {
+                    let [r, g, b, a] = window.ink.to_srgba_unmultiplied();
+                    done = Some(Some((window.draft.clone(), stippled.svg(&window.name, Color::rgba(r, g, b, a), window.width, &window.draft))));
+                }
+            }
+            if ui.button("Cancel").clicked() {
+                done = Some(None);
+            }
+        });
+    });
+    if !open || done.is_some() {
+        *state = None;
+    }
+    done.flatten()
+}
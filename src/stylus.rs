@@ -0,0 +1,28 @@
+/// Pen input, as far as winit reports it: pens and styluses arrive as touches that come with
+/// a force. There are no tilt (apart from the altitude on iOS), eraser or barrel button
+/// events; tablet drivers usually send the barrel button as a middle click, which pans.
+#[derive(Default)]
+pub struct Stylus {
+    /// 0 to 1 while the pen touches, for pressure sensitive tools
+    pub pressure: Option<f32>,
+}
+
+impl Stylus {
+    /// Follow this frame's touch events.
+    pub fn update(&mut self, ctx: &egui::Context) {
+        ctx.input(|i| {
+            for event in &i.events {
+                if let egui::Event::Touch { phase, force, .. } = event {
+                    match phase {
+                        egui::TouchPhase::Start | egui::TouchPhase::Move => {
+                            if force.is_some() {
+                                self.pressure = *force;
+                            }
+                        }
+                        egui::TouchPhase::End | egui::TouchPhase::Cancel => self.pressure = None,
+                    }
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,445 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Instant, SystemTime};
+
+use vectorlab_core::{
+    boolean_paths, duplicate_elements, even_odd_paths, load_str, outline_strokes, parse_clipboard, selection_svg, BooleanOp, DeleteElements, Document, EditCommand, EditGroup, ElementId, History,
+    LoadError, LoadOptions, Paste, SaveOptions, SpatialIndex, ViewTransform,
+};
+
+use crate::browse::sibling_svgs;
+use crate::check::GeometryCheck;
+use crate::gcode::Toolpaths;
+use crate::gpu::GpuMeshes;
+use crate::keys::{Action, Keybindings};
+use crate::layers::update_selection;
+use crate::measure::Measurement;
+use crate::nodes::NodeEdit;
+use crate::palette::Palette;
+use crate::rulers::Guide;
+use crate::pencil::PencilStroke;
+use crate::recolor::ColorPreview;
+use crate::search::Search;
+use crate::shapes::ShapeDraw;
+use crate::source::SourceView;
+use crate::text::TextDraft;
+use crate::transform::TransformDrag;
+use crate::winding::WindingView;
+
+/// Degrees the view turns per key press.
+pub const ROTATION_STEP: f32 = 15.0;
+
+/// How far right and down duplicates go, in mm.
+const DUPLICATE_OFFSET: f32 = 5.0;
+
+/// What the primary mouse button does on the canvas.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tool {
+    Select,
+    Measure,
+    Nodes,
+    Transform,
+    Shapes,
+    Pencil,
+    Text,
+}
+
+/// Source of [`Tab::id`].
+static NEXT_TAB_ID: AtomicU64 = AtomicU64::new(0);
+
+/// One open file with its own view, selection and undo history.
+pub struct Tab {
+    /// stays the same while the tab is open, unlike its index when tabs before it close
+    pub id: u64,
+    pub doc: Document,
+    pub path: Option<PathBuf>,
+    /// shown instead of the file name for documents that did not come from a file
+    pub name: Option<String>,
+    pub view: ViewTransform,
+    pub selection: Vec<ElementId>,
+    pub history: History,
+    /// SVG files next to `path`, for stepping through a directory
+    pub siblings: Vec<PathBuf>,
+    /// modification time of the file when it was last loaded or saved
+    pub disk_mtime: Option<SystemTime>,
+    /// [`History::version`] when the file was last loaded or saved
+    pub saved_version: u64,
+    // the canvas size is only known while drawing, so fitting waits for the next frame
+    pub fit_pending: bool,
+    /// document area to fit into the canvas on the next frame, e.g. a search match
+    pub zoom_to: Option<[f32; 4]>,
+    /// as of the last frame, the view turns around its middle
+    pub canvas_size: [f32; 2],
+    pub gpu: GpuMeshes,
+    /// for drawing only what is on screen
+    pub index: SpatialIndex,
+    /// built when the source panel first shows the document
+    pub source_view: Option<SourceView>,
+    /// elements picked in the source, blinking on the canvas since then
+    pub flash: Option<(Vec<ElementId>, Instant)>,
+    /// the Ctrl+F bar, while it is open
+    pub search: Option<Search>,
+    /// kept across reloads, they are about the drawing's layout rather than its elements
+    pub guides: Vec<Guide>,
+    /// index into `guides` while one is dragged
+    pub dragged_guide: Option<usize>,
+    /// pointer position over the canvas in document coordinates
+    pub cursor: Option<[f32; 2]>,
+    pub tool: Tool,
+    pub measurement: Option<Measurement>,
+    pub nodes: NodeEdit,
+    pub transform_drag: Option<TransformDrag>,
+    pub shape: ShapeDraw,
+    /// stroke of the pencil being drawn
+    pub pencil: Option<PencilStroke>,
+    /// text being typed with the text tool
+    pub text: Option<TextDraft>,
+    /// moves of the last G-code export, shown until closed or the document changes
+    pub toolpaths: Option<Toolpaths>,
+    /// for the geometry check panel
+    pub check: GeometryCheck,
+    /// for the colors panel
+    pub palette: Palette,
+    /// for View > Color preview
+    pub color_preview: ColorPreview,
+    /// for the winding number view
+    pub winding: WindingView,
+}
+
+impl Tab {
+    pub fn new(doc: Document, path: Option<PathBuf>) -> Self {
+        let siblings = path.as_deref().map(sibling_svgs).unwrap_or_default();
+        let mut tab = Self {
+            id: NEXT_TAB_ID.fetch_add(1, Ordering::Relaxed),
+            doc,
+            path,
+            name: None,
+            view: ViewTransform::default(),
+            selection: vec![],
+            history: History::default(),
+            siblings,
+            disk_mtime: None,
+            saved_version: 0,
+            fit_pending: true,
+            zoom_to: None,
+            canvas_size: [0.0, 0.0],
+            gpu: GpuMeshes::default(),
+            index: SpatialIndex::default(),
+            source_view: None,
+            flash: None,
+            search: None,
+            guides: vec![],
+            dragged_guide: None,
+            cursor: None,
+            tool: Tool::Select,
+            measurement: None,
+            nodes: NodeEdit::default(),
+            transform_drag: None,
+            shape: ShapeDraw::default(),
+            pencil: None,
+            text: None,
+            toolpaths: None,
+            check: GeometryCheck::default(),
+            palette: Palette::default(),
+            color_preview: ColorPreview::default(),
+            winding: WindingView::default(),
+        };
+        tab.update_mtime();
+        tab
+    }
+
+    /// Remember the file's current modification time, so that our own saves and
+    /// duplicate change events don't cause a reload.
+    pub fn update_mtime(&mut self) {
+        self.disk_mtime = self.path.as_ref().and_then(|p| fs::metadata(p).ok()).and_then(|m| m.modified().ok());
+    }
+
+    /// True if there are edits that were not saved.
+    pub fn is_modified(&self) -> bool {
+        self.history.version() != self.saved_version
+    }
+
+    /// Remember that what the tab shows now is what is in its file.
+    pub fn mark_saved(&mut self) {
+        self.saved_version = self.history.version();
+        self.update_mtime();
+    }
+
+    /// True if the file on disk is newer than what this tab shows.
+    pub fn changed_on_disk(&self) -> bool {
+        let mtime = self.path.as_ref().and_then(|p| fs::metadata(p).ok()).and_then(|m| m.modified().ok());
+        mtime.is_some() && mtime != self.disk_mtime
+    }
+
+    /// Swap in a freshly loaded version of the same file. The view stays where it is.
+    pub fn reload(&mut self, doc: Document) {
+        self.doc = doc;
+        // element ids of the old document mean nothing in the new one
+        self.selection.clear();
+        self.history.clear();
+        self.source_view = None;
+        self.measurement = None;
+        self.toolpaths = None;
+        self.mark_saved();
+    }
+
+    /// Show another file in this tab, keeping the directory listing.
+    pub fn replace(&mut self, doc: Document, path: &Path) {
+        self.doc = doc;
+        self.path = Some(path.to_path_buf());
+        self.selection.clear();
+        self.history.clear();
+        self.source_view = None;
+        self.measurement = None;
+        self.toolpaths = None;
+        self.fit_pending = true;
+        self.mark_saved();
+    }
+
+    /// Position of the tab's file in `siblings`.
+    pub fn sibling_index(&self) -> Option<usize> {
+        let path = self.path.as_ref()?;
+        self.siblings.iter().position(|p| p == path || p.file_name() == path.file_name())
+    }
+
+    pub fn title(&self) -> String {
+        match self.path.as_ref().and_then(|p| p.file_name()) {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => self.name.clone().unwrap_or_else(|| "Untitled".to_string()),
+        }
+    }
+
+    pub fn undo(&mut self) {
+        self.history.undo(&mut self.doc);
+        self.prune_selection();
+    }
+
+    pub fn redo(&mut self) {
+        self.history.redo(&mut self.doc);
+        self.prune_selection();
+    }
+
+    /// Forget selected elements an undo or redo took out of the drawing. Edits that swap the
+    /// whole element list, like Split by color or a plugin, leave ids past its end.
+    fn prune_selection(&mut self) {
+        let present: HashSet<ElementId> = self.doc.descendants(self.doc.root).into_iter().collect();
+        self.selection.retain(|id| present.contains(id));
+        if self.flash.as_ref().is_some_and(|(ids, _)| ids.iter().any(|id| !present.contains(id))) {
+            self.flash = None;
+        }
+    }
+
+    pub fn delete_selection(&mut self) {
+        if !self.selection.is_empty() {
+            let ids = std::mem::take(&mut self.selection);
+            self.history.push(Box::new(DeleteElements::new(ids)), &mut self.doc);
+        }
+    }
+
+    /// Copy the selection a little to the right and down, and select the copies.
+    pub fn duplicate_selection(&mut self) {
+        let mm = self.doc.mm_per_unit();
+        let offset = [DUPLICATE_OFFSET / mm[0], DUPLICATE_OFFSET / mm[1]];
+        let Some(edit) = duplicate_elements(&self.doc, &self.selection, offset) else { return };
+        self.push_and_select_top(edit);
+    }
+
+    /// The selection as SVG of its own for the clipboard, None if nothing with a size is selected.
+    pub fn copy_selection(&self, opts: SaveOptions) -> Option<String> {
+        selection_svg(&self.doc, &self.selection, opts)
+    }
+
+    /// Paste SVG markup from the clipboard, centered on `at` in document coordinates or else
+    /// where it was on its page, and select it. False if there was nothing in it.
+    pub fn paste(&mut self, text: &str, opts: &LoadOptions, at: Option<[f32; 2]>) -> Result<bool, LoadError> {
+        let pasted = parse_clipboard(text, opts)?;
+        let Some(edit) = Paste::new(&self.doc, pasted, at) else { return Ok(false) };
+        self.push_and_select_top(Box::new(edit));
+        Ok(true)
+    }
+
+    /// Put the content of `svg` on top of everything as a new layer, at its size and place on
+    /// the page, and select it. Undone as `name`.
+    pub fn add_layer(&mut self, name: &str, svg: &str, opts: &LoadOptions) -> Result<(), LoadError> {
+        let layer = load_str(svg, opts)?;
+        if let Some(edit) = Paste::new(&self.doc, layer, None) {
+            self.push_and_select_top(Box::new(EditGroup::new(name, vec![Box::new(edit)])));
+        }
+        Ok(())
+    }
+
+    /// Push `edit` and select the elements it added, not what is inside them.
+    fn push_and_select_top(&mut self, edit: Box<dyn EditCommand>) {
+        let before: HashSet<ElementId> = self.doc.descendants(self.doc.root).into_iter().collect();
+        self.history.push(edit, &mut self.doc);
+        let doc = &self.doc;
+        let new = |id: &ElementId| !before.contains(id);
+        self.selection = doc.descendants(doc.root).into_iter().filter(|id| new(id) && doc.get(*id).parent.is_none_or(|p| !new(&p))).collect();
+    }
+
+    /// The selected elements and everything in selected groups.
+    fn selected_tree(&self) -> Vec<ElementId> {
+        self.selection.iter().flat_map(|&id| self.doc.descendants(id)).collect()
+    }
+
+    /// Push `edit` and select what it added along with what is left of the selection.
+    pub fn push_and_select_new(&mut self, edit: Box<dyn EditCommand>) {
+        let before: HashSet<ElementId> = self.doc.descendants(self.doc.root).into_iter().collect();
+        self.history.push(edit, &mut self.doc);
+        let after = self.doc.descendants(self.doc.root);
+        self.selection.retain(|id| after.contains(id));
+        self.selection.extend(after.into_iter().filter(|id| !before.contains(id)));
+    }
+
+    /// Combine the selected paths, those in selected groups too, into one and select it.
+    /// False if there was nothing to combine.
+    pub fn combine_selection(&mut self, op: BooleanOp) -> bool {
+        let Some(result) = boolean_paths(&self.doc, &self.selected_tree(), op) else { return false };
+        self.push_and_select_new(result.edit);
+        true
+    }
+
+    /// Turn the strokes of the selected paths into fills. False if none has a stroke.
+    pub fn outline_selection(&mut self) -> bool {
+        let Some(result) = outline_strokes(&self.doc, &self.selected_tree()) else { return false };
+        self.push_and_select_new(result.edit);
+        true
+    }
+
+    /// Switch the nonzero fills among the selection to even-odd, see `even_odd_paths`.
+    pub fn even_odd_selection(&mut self) -> bool {
+        let Some(result) = even_odd_paths(&self.doc, &self.selected_tree()) else { return false };
+        self.history.push(result.edit, &mut self.doc);
+        true
+    }
+
+    /// The document at its own size, see `Document::initial_view`, still turned and flipped
+    /// the way the view is, around the middle of the page.
+    pub fn rotate(&mut self, degrees: f32) {
+        let [w, h] = self.canvas_size;
+        self.view.rotate_at([w * 0.5, h * 0.5], degrees);
+    }
+
+    pub fn flip(&mut self, horizontal: bool) {
+        let [w, h] = self.canvas_size;
+        self.view.flip_at([w * 0.5, h * 0.5], horizontal);
+    }
+
+    pub fn reset_orientation(&mut self) {
+        let [w, h] = self.canvas_size;
+        self.view.reset_orientation([w * 0.5, h * 0.5]);
+    }
+
+    pub fn actual_size(&mut self) {
+        let mut view = self.doc.initial_view();
+        let [x, y, w, h] = self.doc.view_box;
+        let anchor = view.to_screen([x + w * 0.5, y + h * 0.5]);
+        if self.view.flip_x {
+            view.flip_at(anchor, true);
+        }
+        if self.view.flip_y {
+            view.flip_at(anchor, false);
+        }
+        view.rotate_at(anchor, self.view.rotation);
+        self.view = view;
+    }
+
+    /// Like `actual_size`, but as large as the page really is on a screen with
+    /// `points_per_mm`, see `scale::points_per_mm`.
+    pub fn physical_size(&mut self, points_per_mm: f32) {
+        self.actual_size();
+        let [mx, my] = self.doc.mm_per_unit();
+        let [x, y, w, h] = self.doc.view_box;
+        let anchor = self.view.to_screen([x + w * 0.5, y + h * 0.5]);
+        self.view.zoom_at(anchor, points_per_mm * (mx * my).sqrt() / self.view.zoom);
+    }
+
+    /// Wheel zooms around the cursor, Alt+wheel turns the view around it, middle button or
+    /// space+drag pans. Pinching zooms, two fingers on a trackpad or touch screen pan and a
+    /// double tap fits. '1' / Ctrl+0 shows the document at its own size, Ctrl+1 at its real
+    /// size with `points_per_mm`, 'F' fits the drawing
+    /// into the canvas, R / Shift+R turn and H / V flip the view, all as bound in `keys`. Plain clicks select. `initial_zoom` replaces the
+    /// zoom of the first fit, for the --zoom option.
+    pub fn handle_view_input(&mut self, ui: &egui::Ui, rect: egui::Rect, response: &egui::Response, keys: &Keybindings, points_per_mm: f32, initial_zoom: &mut Option<f32>) {
+        self.canvas_size = [rect.width(), rect.height()];
+        if let Some(hover) = response.hover_pos() {
+            let (scroll, alt, zoom) = ui.input(|i| (i.smooth_scroll_delta, i.modifiers.alt, i.zoom_delta()));
+            // trackpads scroll by pixels, wheels by lines
+            let trackpad = ui.input(|i| i.events.iter().any(|e| matches!(e, egui::Event::MouseWheel { unit: egui::MouseWheelUnit::Point, .. })));
+            let anchor = hover - rect.min;
+            if scroll != egui::Vec2::ZERO && alt {
+                self.view.rotate_at([anchor.x, anchor.y], -scroll.y * 0.1);
+            } else if scroll != egui::Vec2::ZERO && trackpad {
+                self.view.pan_by([scroll.x, scroll.y]);
+            } else if scroll.y != 0.0 {
+                self.view.zoom_at([anchor.x, anchor.y], (scroll.y * 0.002).exp());
+            }
+            // pinching and Ctrl+wheel, around the middle of the fingers on a touch screen
+            if zoom != 1.0 {
+                let anchor = ui.input(|i| i.multi_touch()).map_or(anchor, |touch| touch.start_pos - rect.min);
+                self.view.zoom_at([anchor.x, anchor.y], zoom);
+            }
+        }
+        if let Some(touch) = ui.input(|i| i.multi_touch()) {
+            self.view.pan_by([touch.translation_delta.x, touch.translation_delta.y]);
+        }
+        if response.double_clicked() && ui.input(|i| i.any_touches() || i.has_touch_screen()) {
+            self.fit_pending = true;
+        }
+
+        let space_held = ui.input(|i| i.key_down(egui::Key::Space));
+        if response.dragged_by(egui::PointerButton::Middle)
+            || (space_held && response.dragged_by(egui::PointerButton::Primary))
+        {
+            let delta = response.drag_delta();
+            self.view.pan_by([delta.x, delta.y]);
+        } else if response.clicked() && matches!(self.tool, Tool::Select | Tool::Transform) {
+            if let Some(pos) = response.interact_pointer_pos() {
+                let p = pos - rect.min;
+                // a few pixels of slack so hairlines can be picked at any zoom
+                let hit = self.doc.hit_test(self.view.to_doc([p.x, p.y]), 3.0 / self.view.zoom);
+                let extend = ui.input(|i| i.modifiers.shift);
+                update_selection(&mut self.selection, hit, extend);
+            }
+        }
+
+        let actual_size = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Num0);
+        if keys.pressed(ui.ctx(), Action::ActualSize) || ui.input_mut(|i| i.consume_shortcut(&actual_size)) {
+            self.actual_size();
+        }
+        if keys.pressed(ui.ctx(), Action::PhysicalSize) {
+            self.physical_size(points_per_mm);
+        }
+        if keys.pressed(ui.ctx(), Action::RotateRight) {
+            self.rotate(ROTATION_STEP);
+        }
+        if keys.pressed(ui.ctx(), Action::RotateLeft) {
+            self.rotate(-ROTATION_STEP);
+        }
+        if keys.pressed(ui.ctx(), Action::FlipHorizontal) {
+            self.flip(true);
+        }
+        if keys.pressed(ui.ctx(), Action::FlipVertical) {
+            self.flip(false);
+        }
+        if keys.pressed(ui.ctx(), Action::Fit) {
+            self.fit_pending = true;
+        }
+
+        if let Some(bbox) = self.zoom_to.take() {
+            self.view.fit(bbox, [rect.width(), rect.height()], 40.0);
+        }
+        if self.fit_pending {
+            self.fit_pending = false;
+            if let Some(bbox) = self.doc.bbox() {
+                self.view.fit(bbox, [rect.width(), rect.height()], 20.0);
+                if let Some(zoom) = initial_zoom.take() {
+                    let center = [rect.width() * 0.5, rect.height() * 0.5];
+                    self.view.zoom_at(center, zoom * self.doc.initial_view().zoom / self.view.zoom);
+                }
+            }
+        }
+    }
+}
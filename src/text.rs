@@ -0,0 +1,162 @@
+use vectorlab_core::usvg::fontdb;
+use vectorlab_core::{transform_point, transform_scale, AddText, Color, EditCommand, EditText, ElementId, ElementKind, LoadOptions, TextAnchor, TextLayout, Unit};
+
+use crate::grid::Grid;
+use crate::notifications::Notifications;
+use crate::tab::Tab;
+use crate::units::length_value;
+
+/// Fonts tried in turn for the first text, the generic families are not resolved everywhere.
+const PREFERRED_FAMILIES: [&str; 5] = ["DejaVu Sans", "Arial", "Helvetica", "Liberation Sans", "Noto Sans"];
+
+/// What the text tool sets texts with, the same for all tabs.
+pub struct TextSettings {
+    pub family: String,
+    /// mm
+    pub size: f32,
+    pub anchor: TextAnchor,
+    pub color: Color,
+    /// of the fonts that were found, sorted
+    families: Vec<String>,
+}
+
+impl TextSettings {
+    pub fn new(fonts: &fontdb::Database) -> Self {
+        let mut families: Vec<String> = fonts.faces().flat_map(|face| face.families.iter().map(|(name, _)| name.clone())).collect();
+        families.sort();
+        families.dedup();
+        let family = PREFERRED_FAMILIES
+            .iter()
+            .find(|f| families.iter().any(|g| g == *f))
+            .map(|f| f.to_string())
+            .or_else(|| families.first().cloned())
+            .unwrap_or_else(|| "sans-serif".to_string());
+        Self { family, size: 5.0, anchor: TextAnchor::Start, color: Color::BLACK, families }
+    }
+}
+
+/// A text being typed, new or one picked for editing.
+pub struct TextDraft {
+    /// None for a new text
+    target: Option<ElementId>,
+    /// of the first baseline, in document coordinates
+    position: [f32; 2],
+    content: String,
+}
+
+/// Text tool: click to start a text there, or on a text made here to edit it. Its layout
+/// goes into the settings so the toolbar shows it.
+pub fn handle_text(ui: &egui::Ui, tab: &mut Tab, settings: &mut TextSettings, grid: &Grid, canvas: egui::Rect, response: &egui::Response) {
+    // typed text is only dropped with Cancel
+    let typing = tab.text.as_ref().is_some_and(|draft| !draft.content.is_empty());
+    if !response.clicked() || typing || ui.input(|i| i.key_down(egui::Key::Space)) {
+        return;
+    }
+    let Some(pos) = response.interact_pointer_pos() else { return };
+    let p = tab.view.to_doc([pos.x - canvas.min.x, pos.y - canvas.min.y]);
+    // the box counts, not just the glyphs, to hit the gaps between them
+    let picked = tab.doc.descendants(tab.doc.root).into_iter().rev().find_map(|id| {
+        let ElementKind::Text { content, layout: Some(layout) } = &tab.doc.get(id).kind else { return None };
+        let b = tab.doc.subtree_bbox(id)?;
+        (b[0] <= p[0] && p[0] <= b[2] && b[1] <= p[1] && p[1] <= b[3]).then(|| (id, content.clone(), layout.clone()))
+    });
+    tab.text = Some(match picked {
+        Some((id, content, layout)) => {
+            let ts = tab.doc.abs_transform(tab.doc.get(id).parent.unwrap_or(tab.doc.root));
+            let mm = tab.doc.mm_per_unit();
+            settings.family = layout.family;
+            settings.size = layout.size * transform_scale(&ts) * (mm[0] + mm[1]) * 0.5;
+            settings.anchor = layout.anchor;
+            settings.color = layout.color;
+            tab.selection = vec![id];
+            // where it shows, moved along with the text
+            let position = transform_point(&tab.doc.abs_transform(id), layout.position);
+            TextDraft { target: Some(id), position, content }
+        }
+        None => TextDraft { target: None, position: grid.snap(p, &tab.doc, tab.view.zoom), content: String::new() },
+    });
+}
+
+/// The box to type the text in, at its place on the canvas. Ctrl+Enter or Place sets it,
+/// Escape or Cancel drops it.
+pub fn text_editor(ui: &egui::Ui, tab: &mut Tab, settings: &TextSettings, opts: &LoadOptions, notifications: &mut Notifications, canvas: egui::Rect) {
+    let Some(draft) = &mut tab.text else { return };
+    let at = egui::Pos2::from(tab.view.to_screen(draft.position)) + canvas.min.to_vec2();
+    let mut place = false;
+    let mut cancel = ui.input(|i| i.key_pressed(egui::Key::Escape));
+    egui::Area::new(egui::Id::new("text_editor")).fixed_pos(at + egui::vec2(0.0, 4.0)).order(egui::Order::Foreground).show(ui.ctx(), |ui| {
+        egui::Frame::popup(ui.style()).show(ui, |ui| {
+            let edit = ui.add(egui::TextEdit::multiline(&mut draft.content).desired_rows(2).desired_width(240.0).hint_text("Text"));
+            if !edit.has_focus() && !edit.lost_focus() {
+                edit.request_focus();
+            }
+            place = ui.input(|i| i.modifiers.command && i.key_pressed(egui::Key::Enter));
+            ui.horizontal(|ui| {
+                place |= ui.button("Place").on_hover_text("Ctrl+Enter").clicked();
+                cancel |= ui.button("Cancel").clicked();
+            });
+        });
+    });
+    if cancel {
+        tab.text = None;
+    } else if place {
+        if let Some(draft) = tab.text.take() {
+            place_text(tab, draft, settings, opts, notifications);
+        }
+    }
+}
+
+fn place_text(tab: &mut Tab, draft: TextDraft, settings: &TextSettings, opts: &LoadOptions, notifications: &mut Notifications) {
+    let content = draft.content.trim_end();
+    let parent = draft.target.and_then(|id| tab.doc.get(id).parent).unwrap_or(tab.doc.root);
+    let to_local = tab.doc.abs_transform(parent).invert().unwrap_or_default();
+    let mm = tab.doc.mm_per_unit();
+    let layout = TextLayout {
+        family: settings.family.clone(),
+        size: settings.size * 2.0 / (mm[0] + mm[1]).max(f32::EPSILON) * transform_scale(&to_local),
+        anchor: settings.anchor,
+        position: match draft.target.map(|id| &tab.doc.get(id).kind) {
+            Some(ElementKind::Text { layout: Some(old), .. }) => old.position,
+            _ => transform_point(&to_local, draft.position),
+        },
+        color: settings.color,
+    };
+    let edit: Result<Box<dyn EditCommand>, _> = match draft.target {
+        Some(_) if content.is_empty() => {
+            tab.delete_selection();
+            return;
+        }
+        None if content.is_empty() => return,
+        Some(id) => EditText::new(&tab.doc, id, content, layout, opts).map(|e| Box::new(e) as Box<dyn EditCommand>),
+        None => AddText::new(&tab.doc, parent, content, layout, opts).map(|e| Box::new(e) as Box<dyn EditCommand>),
+    };
+    match edit {
+        Ok(edit) if draft.target.is_some() => tab.history.push(edit, &mut tab.doc),
+        Ok(edit) => {
+            tab.selection.clear();
+            tab.push_and_select_new(edit);
+            // the glyphs came along
+            let doc = &tab.doc;
+            tab.selection.retain(|&id| matches!(doc.get(id).kind, ElementKind::Text { .. }));
+        }
+        Err(e) => notifications.error("Could not set the text", e),
+    }
+}
+
+/// Font, size, alignment and color, for the toolbar.
+pub fn text_options(ui: &mut egui::Ui, settings: &mut TextSettings, unit: Unit) {
+    egui::ComboBox::from_id_source("text_family").selected_text(settings.family.as_str()).width(160.0).show_ui(ui, |ui| {
+        for family in &settings.families {
+            ui.selectable_value(&mut settings.family, family.clone(), family);
+        }
+    });
+    ui.add(length_value(&mut settings.size, unit, 0.1, 0.1..=1000.0)).on_hover_text("Font size");
+    for (anchor, label, hint) in [(TextAnchor::Start, "⬅", "Align left"), (TextAnchor::Middle, "↔", "Center"), (TextAnchor::End, "➡", "Align right")] {
+        ui.selectable_value(&mut settings.anchor, anchor, label).on_hover_text(hint);
+    }
+    let c = settings.color;
+    let mut rgba = [c.r, c.g, c.b, c.a];
+    if ui.color_edit_button_srgba_unmultiplied(&mut rgba).changed() {
+        settings.color = Color::rgba(rgba[0], rgba[1], rgba[2], rgba[3]);
+    }
+}
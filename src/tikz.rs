@@ -0,0 +1,64 @@
+use vectorlab_core::TikzSettings;
+
+/// What the TikZ window asks of the app.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TikzAction {
+    Copy,
+    /// into a .tex file
+    Save,
+}
+
+/// The File > Export TikZ window, on a copy of the settings.
+pub struct TikzExport {
+    draft: TikzSettings,
+    /// only the selected elements, cropped to them
+    selection_only: bool,
+}
+
+impl TikzExport {
+    pub fn new(settings: &TikzSettings, has_selection: bool) -> Self {
+        Self { draft: settings.clone(), selection_only: has_selection }
+    }
+}
+
+/// Show the window while `export` is Some. `has_selection` enables exporting only the
+/// selection. Returns what to do with the settings, and whether only the selection is
+/// wanted. It stays open after copying, to paste and try other settings.
+pub fn tikz_window(ctx: &egui::Context, export: &mut Option<TikzExport>, has_selection: bool) -> Option<(TikzAction, TikzSettings, bool)> {
+    let state = export.as_mut()?;
+    let mut open = true;
+    let mut action = None;
+    egui::Window::new("Export TikZ").open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+        let draft = &mut state.draft;
+        egui::Grid::new("tikz").num_columns(2).show(ui, |ui| {
+            ui.label("Decimals");
+            ui.add(egui::DragValue::new(&mut draft.precision).range(0..=6)).on_hover_text("Of the coordinates, in mm");
+            ui.end_row();
+            ui.label("Scale");
+            ui.add(egui::DragValue::new(&mut draft.scale).speed(0.01).range(0.01..=100.0).suffix("×")).on_hover_text("1× is the size of the page");
+            ui.end_row();
+            ui.label("");
+            ui.checkbox(&mut draft.standalone, "Standalone document").on_hover_text("With \\documentclass{standalone}, to compile on its own");
+            ui.end_row();
+            ui.label("");
+            ui.add_enabled(has_selection, egui::Checkbox::new(&mut state.selection_only, "Selection only"));
+            ui.end_row();
+        });
+        ui.weak("Needs \\usepackage{tikz}. Images are left out.");
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Copy").clicked() {
+                action = Some(TikzAction::Copy);
+            }
+            if ui.button("Save as .tex…").clicked() {
+                action = Some(TikzAction::Save);
+            }
+        });
+    });
+    state.selection_only &= has_selection;
+    let result = action.map(|action| (action, state.draft.clone(), state.selection_only));
+    if !open || action == Some(TikzAction::Save) {
+        *export = None;
+    }
+    result
+}
@@ -0,0 +1,107 @@
+use vectorlab_core::{trace_bitmap, Color, RasterImage, Trace, TraceSettings, Unit};
+
+use crate::canvas::draw_segments;
+
+/// Longest side of the preview image, in pixels.
+const PREVIEW_SIZE: usize = 360;
+
+/// The Trace bitmap window, for a PNG or JPEG picked with File > Trace bitmap.
+pub struct TraceBitmap {
+    /// of the file, for the id of the layer the outlines go into
+    name: String,
+    image: RasterImage,
+    draft: TraceSettings,
+    fill: egui::Color32,
+    /// pixels per inch the outlines are placed at
+    dpi: f32,
+    /// trace of the draft
+    traced: Option<(TraceSettings, Trace)>,
+    preview: Option<egui::TextureHandle>,
+}
+
+impl TraceBitmap {
+    pub fn new(name: String, image: RasterImage, settings: &TraceSettings) -> Self {
+        Self { name, image, draft: settings.clone(), fill: egui::Color32::BLACK, dpi: 96.0, traced: None, preview: None }
+    }
+}
+
+/// The image scaled down to at most `PREVIEW_SIZE`, for a texture.
+fn preview_image(image: &RasterImage) -> egui::ColorImage {
+    let (w, h) = (image.width as usize, image.height as usize);
+    let step = (w.max(h) as f32 / PREVIEW_SIZE as f32).max(1.0);
+    let size = [((w as f32 / step) as usize).max(1), ((h as f32 / step) as usize).max(1)];
+    let mut rgba = Vec::with_capacity(size[0] * size[1] * 4);
+    for y in 0..size[1] {
+        for x in 0..size[0] {
+            let i = (((y as f32 * step) as usize).min(h - 1) * w + ((x as f32 * step) as usize).min(w - 1)) * 4;
+            rgba.extend_from_slice(&image.rgba[i..i + 4]);
+        }
+    }
+    egui::ColorImage::from_rgba_premultiplied(size, &rgba)
+}
+
+/// Show the window while `trace` is Some, with the outlines over the image. Returns the
+/// settings used and the outlines as an SVG layer when Insert is pressed.
+pub fn trace_window(ctx: &egui::Context, trace: &mut Option<TraceBitmap>, unit: Unit) -> Option<(TraceSettings, String)> {
+    let state = trace.as_mut()?;
+    if !state.traced.as_ref().is_some_and(|(settings, _)| *settings == state.draft) {
+        state.traced = Some((state.draft.clone(), trace_bitmap(&state.image, &state.draft)));
+    }
+    let texture = state.preview.get_or_insert_with(|| ctx.load_texture("trace-preview", preview_image(&state.image), egui::TextureOptions::LINEAR)).clone();
+    let mut open = true;
+    let mut done = None;
+    egui::Window::new("Trace bitmap").open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+        let draft = &mut state.draft;
+        egui::Grid::new("trace").num_columns(2).show(ui, |ui| {
+            ui.label("Threshold");
+            ui.add(egui::Slider::new(&mut draft.threshold, 0.0..=1.0)).on_hover_text("Pixels darker than this are traced");
+            ui.end_row();
+            ui.label("");
+            ui.checkbox(&mut draft.invert, "Trace the light pixels");
+            ui.end_row();
+            ui.label("Despeckle");
+            ui.add(egui::DragValue::new(&mut draft.despeckle).range(0..=1000).suffix(" px")).on_hover_text("Specks and holes this small are left out");
+            ui.end_row();
+            ui.label("Corner smoothing");
+            ui.add(egui::Slider::new(&mut draft.smoothing, 0.0..=1.34)).on_hover_text("0 keeps every corner sharp, the most rounds them all");
+            ui.end_row();
+            ui.label("Tolerance");
+            ui.add(egui::DragValue::new(&mut draft.tolerance).speed(0.05).range(0.0..=10.0).suffix(" px")).on_hover_text("How far the outlines may stray from the pixels, more gives fewer nodes");
+            ui.end_row();
+            ui.label("Resolution");
+            ui.add(egui::DragValue::new(&mut state.dpi).speed(1.0).range(10.0..=2400.0).suffix(" dpi")).on_hover_text("Of the image, sets the size of the outlines");
+            ui.end_row();
+            ui.label("Fill");
+            ui.color_edit_button_srgba(&mut state.fill);
+            ui.end_row();
+        });
+
+        let size = texture.size_vec2() * (PREVIEW_SIZE as f32 / texture.size_vec2().max_elem()).min(1.0);
+        let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, egui::Color32::WHITE);
+        painter.image(texture.id(), rect, egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)), egui::Color32::from_white_alpha(96));
+        let Some((_, traced)) = &state.traced else { return };
+        let scale = rect.width() / traced.size[0].max(1) as f32;
+        for shape in &traced.shapes {
+            draw_segments(&painter, shape, |p| rect.min + egui::vec2(p[0], p[1]) * scale, egui::Stroke::new(1.0, egui::Color32::from_rgb(220, 40, 40)));
+        }
+        let [w, h] = traced.size;
+        let inches = |pixels: u32| Unit::In.to_mm(pixels as f32 / state.dpi);
+        ui.label(format!("{} shapes, {} × {}", traced.shapes.len(), unit.format(inches(w)), unit.format(inches(h))));
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.add_enabled(!traced.shapes.is_empty(), egui::Button::new("Insert")).on_hover_text("As a new layer on top, at the top left of the page").clicked() {
+                let [r, g, b, a] = state.fill.to_srgba_unmultiplied();
+                done = Some(Some((state.draft.clone(), traced.svg(&state.name, Color::rgba(r, g, b, a), state.dpi))));
+            }
+            if ui.button("Cancel").clicked() {
+                done = Some(None);
+            }
+        });
+    });
+    if !open || done.is_some() {
+        *trace = None;
+    }
+    done.flatten()
+}
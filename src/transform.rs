@@ -0,0 +1,254 @@
+use vectorlab_core::usvg::Transform;
+use vectorlab_core::{Document, EditCommand, ElementId, SetTransforms, Unit, ViewTransform};
+
+use crate::grid::Grid;
+use crate::tab::Tab;
+use crate::units::user_units_parser;
+
+/// Pixels from a handle within which the pointer grabs it.
+const GRAB: f32 = 6.0;
+/// Pixels the rotation handle sits above the top edge.
+const ROTATE_REACH: f32 = 24.0;
+/// Degrees rotations snap to with Shift.
+const ROTATE_STEP: f32 = 15.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Handle {
+    Move,
+    Rotate,
+    /// -1 for the left / top side, 1 for the right / bottom one, 0 for the middle
+    Scale([i8; 2]),
+}
+
+/// A drag of the transform tool, with the transform it applies so far.
+pub struct TransformDrag {
+    handle: Handle,
+    /// where it started and the selection's box then, in document coordinates
+    start: [f32; 2],
+    bbox: [f32; 4],
+    ids: Vec<ElementId>,
+    preview: Option<SetTransforms>,
+}
+
+/// Bounding box of everything selected, in document coordinates.
+fn selection_bbox(doc: &Document, selection: &[ElementId]) -> Option<[f32; 4]> {
+    selection.iter().filter_map(|&id| doc.subtree_bbox(id)).reduce(|a, b| [a[0].min(b[0]), a[1].min(b[1]), a[2].max(b[2]), a[3].max(b[3])])
+}
+
+fn scale_handles(bbox: [f32; 4]) -> impl Iterator<Item = (Handle, [f32; 2])> {
+    let at = |side: i8, lo: f32, hi: f32| match side {
+        -1 => lo,
+        1 => hi,
+        _ => (lo + hi) * 0.5,
+    };
+    [[-1, -1], [0, -1], [1, -1], [1, 0], [1, 1], [0, 1], [-1, 1], [-1, 0]]
+        .into_iter()
+        .map(move |[x, y]| (Handle::Scale([x, y]), [at(x, bbox[0], bbox[2]), at(y, bbox[1], bbox[3])]))
+}
+
+/// The rotation handle on screen, above the middle of the top edge as the view shows it.
+fn rotate_handle(view: &ViewTransform, canvas: egui::Rect, bbox: [f32; 4]) -> egui::Pos2 {
+    let to_screen = |p: [f32; 2]| egui::Pos2::from(view.to_screen(p)) + canvas.min.to_vec2();
+    let center = to_screen([(bbox[0] + bbox[2]) * 0.5, (bbox[1] + bbox[3]) * 0.5]);
+    let top = to_screen([(bbox[0] + bbox[2]) * 0.5, bbox[1]]);
+    let up = (top - center).normalized();
+    let up = if up.is_finite() { up } else { -egui::Vec2::Y };
+    top + up * ROTATE_REACH
+}
+
+/// Transform tool: drag inside the selection to move it, the corner and edge handles to scale
+/// it and the handle above it to turn it. Shift keeps the proportions, moves along one axis
+/// and turns in steps of 15°. Dragging an element outside the selection selects and moves it,
+/// Escape cancels a drag.
+pub fn handle_transform(ui: &egui::Ui, tab: &mut Tab, grid: &Grid, canvas: egui::Rect, response: &egui::Response) {
+    let space_held = ui.input(|i| i.key_down(egui::Key::Space));
+    let to_doc = |view: &ViewTransform, pos: egui::Pos2| view.to_doc([pos.x - canvas.min.x, pos.y - canvas.min.y]);
+
+    if response.drag_started_by(egui::PointerButton::Primary) && !space_held {
+        let Some(pos) = ui.input(|i| i.pointer.press_origin()) else { return };
+        let start = to_doc(&tab.view, pos);
+        let mut handle = None;
+        if let Some(bbox) = selection_bbox(&tab.doc, &tab.selection) {
+            let near = |p: egui::Pos2| p.distance(pos) <= GRAB;
+            if near(rotate_handle(&tab.view, canvas, bbox)) {
+                handle = Some(Handle::Rotate);
+            } else if let Some((h, _)) = scale_handles(bbox).find(|&(_, p)| near(egui::Pos2::from(tab.view.to_screen(p)) + canvas.min.to_vec2())) {
+                handle = Some(h);
+            } else if start[0] >= bbox[0] && start[0] <= bbox[2] && start[1] >= bbox[1] && start[1] <= bbox[3] {
+                handle = Some(Handle::Move);
+            }
+        }
+        if handle.is_none() {
+            if let Some(hit) = tab.doc.hit_test(start, 3.0 / tab.view.zoom) {
+                tab.selection = vec![hit];
+                handle = Some(Handle::Move);
+            }
+        }
+        tab.transform_drag = handle.zip(selection_bbox(&tab.doc, &tab.selection)).map(|(handle, bbox)| TransformDrag {
+            handle,
+            start,
+            bbox,
+            ids: tab.selection.clone(),
+            preview: None,
+        });
+    }
+
+    let Some(drag) = &mut tab.transform_drag else { return };
+    if let Some(mut preview) = drag.preview.take() {
+        preview.revert(&mut tab.doc);
+        tab.doc.touch();
+    }
+    if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+        tab.transform_drag = None;
+        return;
+    }
+    let Some(pos) = response.interact_pointer_pos() else { return };
+    let shift = ui.input(|i| i.modifiers.shift);
+    let ts = drag_transform(drag, to_doc(&tab.view, pos), shift, |p| grid.snap(p, &tab.doc, tab.view.zoom));
+    let mut edit = SetTransforms::new(&tab.doc, &drag.ids, ts);
+    if response.drag_stopped() {
+        if ts != Transform::identity() {
+            tab.history.push(Box::new(edit), &mut tab.doc);
+        }
+        tab.transform_drag = None;
+    } else {
+        edit.apply(&mut tab.doc);
+        tab.doc.touch();
+        drag.preview = Some(edit);
+    }
+}
+
+/// What the drag does in document coordinates with the pointer at `p`. `snap` puts points
+/// on the grid.
+fn drag_transform(drag: &TransformDrag, p: [f32; 2], shift: bool, snap: impl Fn([f32; 2]) -> [f32; 2]) -> Transform {
+    let b = drag.bbox;
+    let center = [(b[0] + b[2]) * 0.5, (b[1] + b[3]) * 0.5];
+    match drag.handle {
+        Handle::Move => {
+            // the top left corner goes onto the grid
+            let corner = snap([b[0] + p[0] - drag.start[0], b[1] + p[1] - drag.start[1]]);
+            let mut d = [corner[0] - b[0], corner[1] - b[1]];
+            if shift {
+                let along = usize::from(d[1].abs() > d[0].abs());
+                d[1 - along] = 0.0;
+            }
+            Transform::from_translate(d[0], d[1])
+        }
+        Handle::Rotate => {
+            let angle = |q: [f32; 2]| (q[1] - center[1]).atan2(q[0] - center[0]);
+            let mut degrees = (angle(p) - angle(drag.start)).to_degrees();
+            if shift {
+                degrees = (degrees / ROTATE_STEP).round() * ROTATE_STEP;
+            }
+            Transform::from_rotate_at(degrees, center[0], center[1])
+        }
+        Handle::Scale(side) => {
+            let p = snap(p);
+            let mut anchor = center;
+            let mut scale = [1.0f32; 2];
+            for axis in 0..2 {
+                let (lo, hi) = (b[axis], b[axis + 2]);
+                if side[axis] == 0 || hi - lo <= 0.0 {
+                    continue;
+                }
+                // the opposite side stays
+                let (fixed, moved) = if side[axis] > 0 { (lo, hi) } else { (hi, lo) };
+                anchor[axis] = fixed;
+                scale[axis] = (p[axis] - fixed) / (moved - fixed);
+            }
+            if shift {
+                let s = match side {
+                    [0, _] => scale[1],
+                    [_, 0] => scale[0],
+                    _ if scale[0].abs() > scale[1].abs() => scale[0],
+                    _ => scale[1],
+                };
+                scale = [s, s];
+            }
+            // flat boxes would not turn back
+            let scale = scale.map(|s| if s.abs() < 1e-3 { 1e-3f32.copysign(s) } else { s });
+            Transform::from_translate(anchor[0], anchor[1]).pre_concat(Transform::from_scale(scale[0], scale[1])).pre_concat(Transform::from_translate(-anchor[0], -anchor[1]))
+        }
+    }
+}
+
+/// The selection's box with its handles.
+pub fn draw_transform_handles(painter: &egui::Painter, canvas: egui::Rect, tab: &Tab) {
+    let Some(bbox) = selection_bbox(&tab.doc, &tab.selection) else { return };
+    let to_screen = |p: [f32; 2]| egui::Pos2::from(tab.view.to_screen(p)) + canvas.min.to_vec2();
+    let color = egui::Color32::from_rgb(0, 160, 255);
+    let stroke = egui::Stroke::new(1.0, color);
+    let corners = [[bbox[0], bbox[1]], [bbox[2], bbox[1]], [bbox[2], bbox[3]], [bbox[0], bbox[3]]].map(to_screen).to_vec();
+    painter.add(egui::Shape::dashed_line(&[corners.clone(), vec![corners[0]]].concat(), stroke, 4.0, 3.0));
+
+    let top = to_screen([(bbox[0] + bbox[2]) * 0.5, bbox[1]]);
+    let rotate = rotate_handle(&tab.view, canvas, bbox);
+    painter.line_segment([top, rotate], stroke);
+    painter.circle(rotate, 4.5, egui::Color32::WHITE, stroke);
+    for (_, p) in scale_handles(bbox) {
+        painter.rect(egui::Rect::from_center_size(to_screen(p), egui::vec2(7.0, 7.0)), 0.0, egui::Color32::WHITE, stroke);
+    }
+    let center = to_screen([(bbox[0] + bbox[2]) * 0.5, (bbox[1] + bbox[3]) * 0.5]);
+    painter.line_segment([center - egui::vec2(4.0, 0.0), center + egui::vec2(4.0, 0.0)], stroke);
+    painter.line_segment([center - egui::vec2(0.0, 4.0), center + egui::vec2(0.0, 4.0)], stroke);
+}
+
+/// Position and size of the whole selection in document units, and a rotation, to type in.
+/// Lengths typed with a unit like "20 mm" are converted.
+pub fn selection_fields(ui: &mut egui::Ui, doc: &Document, selection: &[ElementId], unit: Unit) -> Option<Box<dyn EditCommand>> {
+    let bbox = selection_bbox(doc, selection)?;
+    let [mx, my] = doc.mm_per_unit();
+    let (mut x, mut y, mut w, mut h) = (bbox[0], bbox[1], bbox[2] - bbox[0], bbox[3] - bbox[1]);
+    // the rotation is not stored anywhere, what was typed is kept until the selection changes
+    let rotation_id = ui.id().with("rotation").with(selection);
+    let lock_id = ui.id().with("lock_proportions");
+    let rotation: f32 = ui.data(|d| d.get_temp(rotation_id)).unwrap_or(0.0);
+    let mut lock: bool = ui.data(|d| d.get_temp(lock_id)).unwrap_or(true);
+    let mut degrees = rotation;
+    let mut ts = None;
+
+    egui::Grid::new("selection_fields").num_columns(2).show(ui, |ui| {
+        ui.label("position");
+        ui.horizontal(|ui| {
+            let moved = ui.add(egui::DragValue::new(&mut x).prefix("x ").custom_parser(user_units_parser(mx))).changed()
+                | ui.add(egui::DragValue::new(&mut y).prefix("y ").custom_parser(user_units_parser(my))).changed();
+            if moved {
+                ts = Some(Transform::from_translate(x - bbox[0], y - bbox[1]));
+            }
+        })
+        .response
+        .on_hover_text(format!("{}, {}", unit.format(x * mx), unit.format(y * my)));
+        ui.end_row();
+
+        ui.label("size");
+        ui.horizontal(|ui| {
+            let (w0, h0) = (w, h);
+            let dw = ui.add_enabled(w0 > 0.0, egui::DragValue::new(&mut w).prefix("w ").range(1e-3..=f32::MAX).custom_parser(user_units_parser(mx))).changed();
+            let dh = ui.add_enabled(h0 > 0.0, egui::DragValue::new(&mut h).prefix("h ").range(1e-3..=f32::MAX).custom_parser(user_units_parser(my))).changed();
+            ui.toggle_value(&mut lock, "🔗").on_hover_text("Keep the proportions");
+            if dw || dh {
+                let (mut sx, mut sy) = (if w0 > 0.0 { w / w0 } else { 1.0 }, if h0 > 0.0 { h / h0 } else { 1.0 });
+                if lock {
+                    let s = if dw { sx } else { sy };
+                    (sx, sy) = (s, s);
+                }
+                ts = Some(Transform::from_translate(bbox[0], bbox[1]).pre_concat(Transform::from_scale(sx, sy)).pre_concat(Transform::from_translate(-bbox[0], -bbox[1])));
+            }
+        })
+        .response
+        .on_hover_text(format!("{} × {}", unit.format(w * mx), unit.format(h * my)));
+        ui.end_row();
+
+        ui.label("rotation");
+        if ui.add(egui::DragValue::new(&mut degrees).suffix("°").speed(0.5)).changed() {
+            let center = [(bbox[0] + bbox[2]) * 0.5, (bbox[1] + bbox[3]) * 0.5];
+            ts = Some(Transform::from_rotate_at(degrees - rotation, center[0], center[1]));
+        }
+        ui.end_row();
+    });
+    ui.data_mut(|d| {
+        d.insert_temp(rotation_id, degrees);
+        d.insert_temp(lock_id, lock);
+    });
+    ts.map(|ts| Box::new(SetTransforms::new(doc, selection, ts)) as Box<dyn EditCommand>)
+}
@@ -0,0 +1,52 @@
+use std::ops::RangeInclusive;
+
+use vectorlab_core::{parse_length, Unit};
+
+/// For lengths that may take any value.
+pub const ANY_LENGTH: RangeInclusive<f32> = f32::MIN..=f32::MAX;
+
+/// A field for a length kept in mm, shown in `unit`. Typing a number with another unit like
+/// "0.5 in" converts it. `speed` and `range` are in mm.
+pub fn length_value(mm: &mut f32, unit: Unit, speed: f32, range: RangeInclusive<f32>) -> egui::DragValue<'_> {
+    let display = unit.from_mm(*range.start()) as f64..=unit.from_mm(*range.end()) as f64;
+    egui::DragValue::from_get_set(move |value| {
+        if let Some(value) = value {
+            *mm = unit.to_mm(value as f32);
+        }
+        unit.from_mm(*mm) as f64
+    })
+    .speed(unit.from_mm(speed))
+    .range(display)
+    .suffix(format!(" {}", unit.name()))
+    .custom_parser(move |text| parse_length(text, unit).map(|mm| unit.from_mm(mm) as f64))
+}
+
+/// A slider for a length kept in mm, shown in `unit`, over `range` mm.
+pub fn length_slider(mm: &mut f32, unit: Unit, range: RangeInclusive<f32>) -> egui::Slider<'_> {
+    let display = unit.from_mm(*range.start()) as f64..=unit.from_mm(*range.end()) as f64;
+    egui::Slider::from_get_set(display, move |value| {
+        if let Some(value) = value {
+            *mm = unit.to_mm(value as f32);
+        }
+        unit.from_mm(*mm) as f64
+    })
+    .suffix(format!(" {}", unit.name()))
+}
+
+/// Buttons to pick one of the units, true when it changed.
+pub fn unit_buttons(ui: &mut egui::Ui, unit: &mut Unit) -> bool {
+    let mut changed = false;
+    for u in Unit::ALL {
+        changed |= ui.selectable_value(unit, u, u.name()).changed();
+    }
+    changed
+}
+
+/// Reads a field in user units: plain numbers as they are, lengths with a unit like "5 mm"
+/// converted at `mm_per_unit`.
+pub fn user_units_parser(mm_per_unit: f32) -> impl Fn(&str) -> Option<f64> {
+    move |text| match text.trim().parse::<f64>() {
+        Ok(value) => Some(value),
+        Err(_) => parse_length(text, Unit::Px).map(|mm| (mm / mm_per_unit) as f64),
+    }
+}
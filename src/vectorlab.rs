@@ -1,11 +1,15 @@
+use std::collections::HashSet;
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use winit::{
     application::ApplicationHandler,
-    dpi::LogicalSize,
+    dpi::{LogicalSize, PhysicalPosition},
     event::{Event, WindowEvent, StartCause},
-    event_loop::{ActiveEventLoop, EventLoop},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
     keyboard::{Key, NamedKey},
-    window::{Window, WindowAttributes},
+    window::{Fullscreen, Window, WindowAttributes},
 };
 use glutin_winit::DisplayBuilder;
 use glutin::{
@@ -18,8 +22,123 @@ use glow::HasContext;
 use egui_winit::State as EguiWinitState;
 use egui::{ClippedPrimitive, Context as EguiContext, TexturesDelta};
 use egui_glow::Painter;
-use resvg::tiny_skia::PathBuilder;
-use resvg::usvg::{self, TreeParsing};
+use clap::Parser;
+use vectorlab_core::{align_elements, distribute_elements, Align, BooleanOp, ColorMode, CutSettings, ElementId, FlattenDocument, Formats, GcodeSettings, Generator, HatchSettings, HpglSettings, LineJoin, LoadOptions, RasterImage, ReplaceDrawing, SaveOptions, SimplifyMethod, SplitByColor};
+
+mod background;
+mod browse;
+mod canvas;
+mod check;
+mod cli;
+mod control;
+mod cut;
+mod export;
+mod gcode;
+mod generate;
+mod gpu;
+mod grbl;
+mod halftone;
+mod hpgl;
+mod grid;
+mod inspector;
+mod keys;
+mod layers;
+mod loading;
+mod measure;
+mod minimap;
+mod nodes;
+mod notifications;
+mod operations;
+mod overlays;
+mod palette;
+mod pencil;
+mod job;
+mod plotter;
+mod plugins;
+mod preferences;
+mod presentation;
+mod queue;
+mod profiles;
+mod recent;
+mod recolor;
+mod remote;
+mod rulers;
+mod scale;
+mod script;
+mod search;
+mod serial;
+mod settings;
+mod shapes;
+mod simulation;
+mod slideshow;
+mod source;
+mod stipple;
+mod stylus;
+mod tab;
+mod text;
+mod tikz;
+mod trace;
+mod transform;
+mod units;
+mod watch;
+mod winding;
+
+use canvas::{draw_document, draw_outline, ImageCache};
+use check::{check_panel, draw_defects};
+use background::Background;
+use browse::svgs_in;
+use cli::{Cli, Command};
+use control::ControlServer;
+use export::{export_layers_window, export_raster_window, ExportLayers, ExportRaster, MAX_SIZE};
+use cut::{cut_window, CutExport, CutFormat};
+use gcode::{draw_toolpaths, gcode_window, GcodeExport, Toolpaths};
+use generate::{generate_window, insert_generated, GenerateWindow};
+use grbl::{grbl_window, GrblSender};
+use halftone::{halftone_window, HalftoneImage};
+use hpgl::{hpgl_window, HpglAction, HpglSender};
+use inspector::{hover_tooltip, inspector_panel};
+use layers::layers_panel;
+use loading::{LoadTarget, PendingLoad};
+use notifications::{init_logging, Notifications};
+use operations::{draw_preview, operation_window, page_size, Operation, PathOperation};
+use overlays::Overlays;
+use palette::palette_panel;
+use pencil::{draw_pencil, handle_pencil, pencil_options, PencilSettings};
+use job::draw_job_progress;
+use plotter::{plotter_window, Plotter};
+use plugins::{installed_plugins, plugin_dir, plugin_name, PluginRun};
+use keys::Action;
+use preferences::{preferences_window, Preferences};
+use profiles::{draw_work_area, profiles_window, ProfileEditor};
+use queue::{queue_window, JobQueue};
+use presentation::presentation_view;
+use recent::MAX_RECENT_FILES;
+use recolor::{color_preview_menu, draw_recolored};
+use measure::{draw_measurement, handle_measure};
+use minimap::draw_minimap;
+use nodes::{delete_selected_node, draw_nodes, handle_nodes};
+use transform::{draw_transform_handles, handle_transform};
+use rulers::{draw_guides, draw_rulers, handle_guides, RULER_SIZE};
+use scale::{draw_scale_bar, points_per_mm};
+use script::{run_script, script_panel, ScriptConsole};
+use search::{search_bar, Search};
+use settings::Settings;
+use shapes::{draw_shape_preview, handle_shapes, shape_options, ShapeSettings};
+use tikz::{tikz_window, TikzAction, TikzExport};
+use trace::{trace_window, TraceBitmap};
+use simulation::{draw_simulation, simulation_window, PlotSimulation};
+use slideshow::Slideshow;
+use source::{source_panel, FLASH_DURATION};
+use stipple::{stipple_window, StippleImage};
+use stylus::Stylus;
+use tab::{Tab, Tool, ROTATION_STEP};
+use text::{handle_text, text_editor, text_options, TextSettings};
+use units::unit_buttons;
+use watch::FileWatcher;
+use winding::draw_winding;
+
+/// Shown when a file with unsaved edits changed on disk.
+const KEPT_EDITS: &str = "The file changed on disk, the unsaved edits here were kept. Saving overwrites the new version, closing and opening the file again shows it.";
 
 struct VectorLabApp {
     egui_ctx: EguiContext,
@@ -32,15 +151,89 @@ struct VectorLabApp {
     surface: Surface<WindowSurface>,
     gl_context: glutin::context::PossiblyCurrentContext<glutin_winit::Api>,
     window: Window,
-    paths: Vec<Vec<[f32; 2]>>,
-    scale: f32,
-    offset: [f32; 2],
+    tabs: Vec<Tab>,
+    active: usize,
+    load_options: LoadOptions,
+    /// what files are opened and exported with, by extension
+    formats: Arc<Formats>,
+    images: ImageCache,
+    show_layers: bool,
+    show_source: bool,
+    show_check: bool,
+    show_script: bool,
+    /// the Script console, with the script kept while it is hidden
+    script: ScriptConsole,
+    show_palette: bool,
+    show_winding: bool,
+    /// canvas colors turned to preview a plot or engraving
+    color_mode: Option<ColorMode>,
+    /// align and distribute on the page rather than within the selection
+    arrange_on_page: bool,
+    /// what the shape tool draws
+    shapes: ShapeSettings,
+    pencil: PencilSettings,
+    /// what the text tool sets texts with
+    text: TextSettings,
+    show_rulers: bool,
+    show_minimap: bool,
+    show_scale_bar: bool,
+    /// the moves of the job over the canvas, kept up to date with the document
+    show_travel: bool,
+    stylus: Stylus,
+    /// Some while in presentation mode, with whether the window was fullscreen before
+    presenting: Option<bool>,
+    /// plays the tabs while presenting
+    slideshow: Option<Slideshow>,
+    overlays: Overlays,
+    // zoom requested on the command line or with "vectorlab ctl zoom", applied instead of the next fit
+    initial_zoom: Option<f32>,
+    /// takes commands from "vectorlab ctl" when started with --listen
+    control: Option<ControlServer>,
+    /// from the settings unless given on the command line
+    background: Background,
     file_dialog_open: bool,
-    current_file: Option<String>,
+    save_dialog_open: bool,
+    // text of the Open URL window while it is shown
+    url_dialog: Option<String>,
+    last_dir: Option<PathBuf>,
+    hovered_files: Vec<PathBuf>,
+    // None if the platform has no file notifications, open files are then not reloaded
+    watcher: Option<FileWatcher>,
+    settings: Settings,
+    preferences: Option<Preferences>,
+    profile_editor: Option<ProfileEditor>,
+    /// the Job queue window, with the jobs and the thread running them
+    queue: Option<JobQueue>,
+    export_raster: Option<ExportRaster>,
+    export_layers: Option<ExportLayers>,
+    gcode_export: Option<GcodeExport>,
+    cut_export: Option<CutExport>,
+    tikz_export: Option<TikzExport>,
+    trace_bitmap: Option<TraceBitmap>,
+    /// the Stipple image window, with the points being placed
+    stipple: Option<StippleImage>,
+    /// the plugin running on the drawing of a tab
+    plugin: Option<PluginRun>,
+    halftone: Option<HalftoneImage>,
+    generate: Option<GenerateWindow>,
+    /// parameters of the Generate window, kept while it is closed
+    generators: Vec<Generator>,
+    /// the Simulate plot window, drawn over the canvas while open
+    simulation: Option<PlotSimulation>,
+    /// the Plot window, with the plot it is sending
+    plotter: Option<Plotter>,
+    /// the Stream to GRBL window, with its connection
+    grbl: Option<GrblSender>,
+    /// the Send to HPGL plotter window, with the job it is sending
+    hpgl: Option<HpglSender>,
+    path_operation: Option<PathOperation>,
+    // documents being parsed on worker threads
+    loads: Vec<PendingLoad>,
+    notifications: Notifications,
 }
 
 impl VectorLabApp {
-    fn new(window: &Window, gl_display: &glutin::display::Display<glutin_winit::Api>) -> Result<Self, Box<dyn std::error::Error>> {
+    fn new(window: &Window, gl_display: &glutin::display::Display<glutin_winit::Api>, settings: Settings) -> Result<Self, Box<dyn std::error::Error>> {
         let gl_config = gl_display
             .find_configs(ConfigSurfaceTypes::default())
             .expect("No GL config")
@@ -77,56 +270,1135 @@ impl VectorLabApp {
             surface,
             gl_context,
             window: window.clone(),
-            paths: vec![],
-            scale: 1.0,
-            offset: [0.0, 0.0],
+            tabs: vec![],
+            active: 0,
+            load_options: LoadOptions::default(),
+            formats: Arc::new(Formats::default()),
+            images: ImageCache::default(),
+            show_layers: true,
+            show_source: false,
+            show_check: false,
+            show_script: false,
+            script: ScriptConsole::default(),
+            show_palette: false,
+            show_winding: false,
+            color_mode: None,
+            arrange_on_page: false,
+            shapes: ShapeSettings::default(),
+            pencil: PencilSettings::default(),
+            text: TextSettings::new(&LoadOptions::default().fontdb),
+            show_rulers: true,
+            show_minimap: true,
+            show_scale_bar: true,
+            show_travel: false,
+            stylus: Stylus::default(),
+            presenting: None,
+            slideshow: None,
+            overlays: Overlays::default(),
+            initial_zoom: None,
+            control: None,
+            background: Background::Dark,
             file_dialog_open: false,
-            current_file: None,
+            save_dialog_open: false,
+            url_dialog: None,
+            last_dir: None,
+            hovered_files: vec![],
+            watcher: FileWatcher::new().map_err(|e| log::error!("File watching disabled: {}", e)).ok(),
+            settings,
+            preferences: None,
+            profile_editor: None,
+            queue: None,
+            export_raster: None,
+            export_layers: None,
+            gcode_export: None,
+            cut_export: None,
+            tikz_export: None,
+            trace_bitmap: None,
+            stipple: None,
+            plugin: None,
+            halftone: None,
+            generate: None,
+            generators: Generator::all(),
+            simulation: None,
+            plotter: None,
+            grbl: None,
+            hpgl: None,
+            path_operation: None,
+            loads: vec![],
+            notifications: Notifications::default(),
         })
     }
 
+    fn tab(&self) -> Option<&Tab> {
+        self.tabs.get(self.active)
+    }
+
+    fn tab_mut(&mut self) -> Option<&mut Tab> {
+        self.tabs.get_mut(self.active)
+    }
+
+    /// Start loading `path` into a new tab, or switch to its tab if it is already open.
     fn load_svg(&mut self, path: &str) {
-        match fs::read_to_string(path) {
-            Ok(svg_string) => {
-                let opts = usvg::Options::default();
-                if let Ok(tree) = usvg::Tree::from_str(&svg_string, &opts) {
-                    self.paths.clear();
-                    let svg_size = tree.size().to_int_size();
-
-                    for node in tree.root().descendants() {
-                        if let usvg::NodeKind::Path(path_node) = node.borrow() {
-                            let mut path_builder = PathBuilder::new();
-                            for segment in &path_node.data.segments {
-                                match &segment {
-                                    &usvg::path::PathSegment::MoveTo { x, y } =>
-                                        path_builder.move_to(x as f32, y as f32),
-                                    &usvg::path::PathSegment::LineTo { x, y } =>
-                                        path_builder.line_to(x as f32, y as f32),
-                                    usvg::path::PathSegment::CurveTo { x1, y1, x2, y2, x, y } =>
-                                        path_builder.cubic_to(x1 as f32, y1 as f32, x2 as f32, y2 as f32, x as f32, y as f32),
-                                    _ => {}
-                                }
-                            }
+        let path = Path::new(path);
+        if let Some(index) = self.tabs.iter().position(|t| t.path.as_deref() == Some(path)) {
+            self.active = index;
+            return;
+        }
+        let loading = self.loads.iter().any(|l| matches!(&l.target, LoadTarget::NewTab { path: Some(p), .. } if p == path));
+        if !loading {
+            let target = LoadTarget::NewTab { path: Some(path.to_path_buf()), name: None };
+            self.loads.push(PendingLoad::file(path.to_path_buf(), &self.formats, &self.load_options, target));
+        }
+    }
 
-                            if let Some(tsvg_path) = path_builder.finish() {
-                                let mut points = vec![];
-                                for point in tsvg_path.iter() {
-                                    points.push([point.x as f32, point.y as f32]);
-                                }
-                                if !points.is_empty() {
-                                    self.paths.push(points);
-                                }
-                            }
+    /// Move documents that finished loading into their tabs.
+    fn finish_loads(&mut self) {
+        let mut i = 0;
+        while i < self.loads.len() {
+            let Some(result) = self.loads[i].poll() else {
+                i += 1;
+                continue;
+            };
+            let load = self.loads.remove(i);
+            let doc = match result {
+                Ok(doc) => doc,
+                Err(_) if load.progress.is_cancelled() => continue,
+                Err(e) => {
+                    self.notifications.error(format!("Failed to load {}", load.label), e);
+                    continue;
+                }
+            };
+            self.notifications.warnings(&load.label, &doc.warnings);
+            match load.target {
+                LoadTarget::NewTab { path, name } => {
+                    if let Some(path) = &path {
+                        self.add_recent_file(path);
+                    }
+                    let mut tab = Tab::new(doc, path);
+                    tab.name = name;
+                    self.tabs.push(tab);
+                    self.active = self.tabs.len() - 1;
+                }
+                LoadTarget::Replace { tab_path, path } => {
+                    // the tab may have been closed or moved on in the meantime
+                    let Some(tab) = self.tabs.iter_mut().find(|t| t.path.as_ref() == Some(&tab_path)) else { continue };
+                    tab.replace(doc, &path);
+                    self.add_recent_file(&path);
+                }
+                LoadTarget::Reload { tab_path } => {
+                    let Some(tab) = self.tabs.iter_mut().find(|t| t.path.as_ref() == Some(&tab_path)) else { continue };
+                    // edited while it was loading
+                    if tab.is_modified() {
+                        tab.update_mtime();
+                        self.notifications.warnings(tab.title(), &[KEPT_EDITS]);
+                        continue;
+                    }
+                    tab.reload(doc);
+                }
+            }
+        }
+    }
+
+    /// Replace the current tab's document with the next (`delta` = 1) or previous (-1)
+    /// SVG file of its directory.
+    fn step_sibling(&mut self, delta: isize) {
+        let Some(tab) = self.tabs.get_mut(self.active) else { return };
+        let Some(index) = tab.sibling_index() else { return };
+        let Some(path) = index.checked_add_signed(delta).and_then(|i| tab.siblings.get(i)).cloned() else { return };
+        let Some(tab_path) = tab.path.clone() else { return };
+        let target = LoadTarget::Replace { tab_path, path: path.clone() };
+        self.loads.push(PendingLoad::file(path, &self.formats, &self.load_options, target));
+    }
+
+    /// Reload tabs whose files changed on disk, e.g. when VectorLab is used as a live preview
+    /// next to an editor.
+    fn reload_changed_files(&mut self) {
+        let Some(watcher) = &mut self.watcher else { return };
+        watcher.sync(self.tabs.iter().filter_map(|t| t.path.as_ref()));
+        let changed = watcher.changed_files();
+        for tab in &mut self.tabs {
+            let Some(path) = tab.path.clone() else { continue };
+            let canonical = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+            if !changed.contains(&canonical) || !tab.changed_on_disk() {
+                continue;
+            }
+            // reloading would throw the edits and their undo history away
+            if tab.is_modified() {
+                tab.update_mtime();
+                self.notifications.warnings(tab.title(), &[KEPT_EDITS]);
+                continue;
+            }
+            // what an earlier event started may have read the file half written
+            for load in &self.loads {
+                if matches!(&load.target, LoadTarget::Reload { tab_path } if *tab_path == path) {
+                    load.progress.cancel();
+                }
+            }
+            let target = LoadTarget::Reload { tab_path: path.clone() };
+            self.loads.push(PendingLoad::file(path, &self.formats, &self.load_options, target));
+        }
+    }
+
+    /// Load the current tab's file again, e.g. to see it with other load options.
+    fn reload_active_tab(&mut self) {
+        let Some(path) = self.tab().and_then(|t| t.path.clone()) else { return };
+        let target = LoadTarget::Replace { tab_path: path.clone(), path: path.clone() };
+        self.loads.push(PendingLoad::file(path, &self.formats, &self.load_options, target));
+    }
+
+    /// Carry out what "vectorlab ctl" asked for since the last frame.
+    fn handle_control_requests(&mut self) {
+        let Some(control) = &self.control else { return };
+        for request in control.requests() {
+            if request.command.get("command").and_then(|v| v.as_str()) == Some("export_png") {
+                // rendering takes a while, the client gets its answer when it is done
+                match self.control_export(&request.command) {
+                    Ok(export) => {
+                        std::thread::spawn(move || request.reply(export()));
+                    }
+                    Err(e) => request.reply(Err(e)),
+                }
+                continue;
+            }
+            let result = self.control_command(&request.command);
+            request.reply(result);
+        }
+    }
+
+    /// The "export_png" command of the control socket, rendering a copy of the current
+    /// drawing when called, at most [`MAX_SIZE`] pixels wide and high.
+    fn control_export(&self, command: &serde_json::Value) -> Result<impl FnOnce() -> Result<(), String> + Send, String> {
+        let text = |key: &str| command.get(key).and_then(|v| v.as_str());
+        let path = PathBuf::from(text("path").ok_or("export_png needs a path")?);
+        let bg = text("bg").map(cli::parse_color).transpose()?;
+        let doc = self.tab().ok_or("No document open")?.doc.clone();
+        let fonts = self.load_options.clone();
+        let page = [doc.size[0].max(1.0), doc.size[1].max(1.0)];
+        let width = command.get("width").and_then(|v| v.as_u64()).map_or(page[0], |w| w as f32);
+        let scale = (width / page[0]).min(MAX_SIZE as f32 / page[0].max(page[1]));
+        let size = page.map(|s| (s * scale).round().max(1.0) as u32);
+        Ok(move || {
+            let image = vectorlab_core::render_document(&doc, size[0], size[1], bg, &fonts).map_err(|e| e.to_string())?;
+            vectorlab_core::save_png(&image, &path, vectorlab_core::CSS_DPI * scale).map_err(|e| e.to_string())
+        })
+    }
+
+    /// One command of the control socket, see [`control::ctl`] for what they look like.
+    /// Files are opened and reloaded in the background, their errors end up in the
+    /// notifications rather than with the client.
+    fn control_command(&mut self, command: &serde_json::Value) -> Result<(), String> {
+        let text = |key: &str| command.get(key).and_then(|v| v.as_str());
+        match text("command") {
+            Some("open") => self.load_svg(text("path").ok_or("open needs a path")?),
+            Some("reload") => {
+                if let Some(path) = text("path") {
+                    match self.tabs.iter().position(|t| t.path.as_deref() == Some(Path::new(path))) {
+                        Some(index) => self.active = index,
+                        None => {
+                            self.load_svg(path);
+                            return Ok(());
                         }
                     }
+                }
+                if self.tab().and_then(|t| t.path.as_ref()).is_none() {
+                    return Err("The current tab has no file to reload".to_string());
+                }
+                self.reload_active_tab();
+            }
+            Some("zoom") => {
+                let zoom = command.get("zoom").and_then(|v| v.as_f64()).filter(|z| *z > 0.0).ok_or("zoom needs a factor above 0")?;
+                self.tab_mut().ok_or("No document open")?.fit_pending = true;
+                self.initial_zoom = Some(zoom as f32);
+            }
+            Some("fit") => self.tab_mut().ok_or("No document open")?.fit_pending = true,
+            Some(other) => return Err(format!("unknown command '{}'", other)),
+            None => return Err("no command given".to_string()),
+        }
+        Ok(())
+    }
+
+    /// Open SVG data that did not come from a file, e.g. a download or stdin, in a new tab.
+    fn open_data(&mut self, data: Vec<u8>, name: &str) {
+        let target = LoadTarget::NewTab { path: None, name: Some(name.to_string()) };
+        self.loads.push(PendingLoad::data(data, name.to_string(), &self.load_options, target));
+    }
+
+    fn open_url(&mut self, url: &str) {
+        match remote::fetch(url) {
+            Ok(data) => self.open_data(data, &remote::url_title(url)),
+            Err(e) => self.notifications.error(format!("Failed to download {}", url), e),
+        }
+    }
+
+    /// Command line argument: a file, an http(s) URL or '-' for stdin.
+    fn open_location(&mut self, location: &str) {
+        if location == "-" {
+            match remote::read_stdin() {
+                Ok(data) => self.open_data(data, "stdin"),
+                Err(e) => self.notifications.error("Failed to read stdin", e),
+            }
+        } else if remote::is_url(location) {
+            self.open_url(location);
+        } else {
+            self.load_svg(location);
+        }
+    }
 
-                    self.scale = 400.0 / svg_size.width().max(1.0) as f32;
-                    self.offset = [600.0, 400.0];
-                    self.current_file = Some(path.to_string());
+    fn close_tab(&mut self, index: usize) {
+        if index < self.tabs.len() {
+            self.tabs.remove(index);
+            if self.active > index || self.active >= self.tabs.len() {
+                self.active = self.active.saturating_sub(1);
+            }
+        }
+    }
+
+    fn cycle_tabs(&mut self, backwards: bool) {
+        let n = self.tabs.len();
+        if n > 0 {
+            self.active = if backwards { (self.active + n - 1) % n } else { (self.active + 1) % n };
+        }
+    }
+
+    /// Native open dialog. Blocks until the user picks a file or cancels.
+    fn open_file_dialog(&mut self) {
+        let mut dialog = rfd::FileDialog::new().set_title("Open").add_filter("All supported", &self.formats.import_extensions());
+        for importer in self.formats.importers() {
+            dialog = dialog.add_filter(importer.name(), importer.extensions());
+        }
+        dialog = dialog.add_filter("All files", &["*"]);
+        if let Some(dir) = &self.last_dir {
+            dialog = dialog.set_directory(dir);
+        }
+        if let Some(path) = dialog.pick_file() {
+            self.last_dir = path.parent().map(|p| p.to_path_buf());
+            self.load_svg(&path.to_string_lossy());
+        }
+    }
+
+    fn add_recent_file(&mut self, path: &Path) {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let recent = &mut self.settings.recent_files;
+        recent.retain(|p| *p != path);
+        recent.insert(0, path);
+        recent.truncate(MAX_RECENT_FILES);
+        self.settings.save();
+    }
+
+    fn toggle_fullscreen(&mut self) {
+        let fullscreen = self.window.fullscreen().is_none().then_some(Fullscreen::Borderless(None));
+        self.window.set_fullscreen(fullscreen);
+    }
+
+    /// Presentation mode goes fullscreen and leaves the window as it was when it ends.
+    fn set_presenting(&mut self, on: bool) {
+        match (on, self.presenting) {
+            (true, None) => {
+                self.presenting = Some(self.window.fullscreen().is_some());
+                self.window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+            }
+            (false, Some(was_fullscreen)) => {
+                self.presenting = None;
+                self.slideshow = None;
+                if !was_fullscreen {
+                    self.window.set_fullscreen(None);
+                }
+                // back to the view around the artwork the panels leave
+                if let Some(tab) = self.tab_mut() {
+                    tab.fit_pending = true;
                 }
             }
-            Err(e) => eprintln!("Failed to load SVG {}: {}", path, e),
+            _ => {}
+        }
+    }
+
+    /// Play the open tabs, or the folder of the only one, in presentation mode. `playlist`
+    /// gives the order of the files, tabs not in it come last.
+    fn start_slideshow(&mut self, mut playlist: Vec<PathBuf>, interval: f32, crossfade: f32) {
+        if let [tab] = self.tabs.as_slice() {
+            if playlist.is_empty() {
+                playlist = tab.siblings.clone();
+            }
+            let open = tab.path.as_ref().and_then(|p| p.file_name().map(|n| n.to_os_string()));
+            for path in tab.siblings.clone() {
+                if path.file_name() != open.as_deref() {
+                    self.load_svg(&path.to_string_lossy());
+                }
+            }
+        }
+        self.slideshow = Some(Slideshow::new(interval, crossfade, playlist));
+        self.set_presenting(true);
+    }
+
+    /// Remember where the window is for the next start.
+    fn save_window_geometry(&mut self) {
+        self.settings.window_maximized = self.window.is_maximized();
+        // a maximized or fullscreen window keeps the size and place it had before
+        if !self.settings.window_maximized && self.window.fullscreen().is_none() {
+            let size = self.window.inner_size().to_logical::<f32>(self.window.scale_factor());
+            self.settings.window_size = Some([size.width, size.height]);
+            self.settings.window_position = self.window.outer_position().ok().map(|p| [p.x, p.y]);
+        }
+        self.settings.save();
+    }
+
+    /// Take over what OK in the Preferences window returned.
+    fn apply_preferences(&mut self, mut settings: Settings) {
+        settings.recent_files = std::mem::take(&mut self.settings.recent_files);
+        settings.window_size = self.settings.window_size;
+        settings.window_position = self.settings.window_position;
+        settings.window_maximized = self.settings.window_maximized;
+        let reload = settings.strict_parsing != self.settings.strict_parsing || settings.dpi != self.settings.dpi;
+        self.settings = settings;
+        self.settings.save();
+        self.background = self.settings.background;
+        self.load_options.strict = self.settings.strict_parsing;
+        self.load_options.dpi = self.settings.dpi;
+        if reload {
+            self.reload_active_tab();
+        }
+    }
+
+    fn save(&mut self) {
+        match self.tab().and_then(|t| t.path.clone()) {
+            Some(path) => self.save_to(&path),
+            None => self.save_dialog_open = true,
+        }
+    }
+
+    fn save_to(&mut self, path: &Path) {
+        let Some(tab) = self.tabs.get_mut(self.active) else { return };
+        let opts = SaveOptions { text_to_paths: self.settings.text_to_paths };
+        match vectorlab_core::save_file_with(&tab.doc, path, opts) {
+            Ok(()) => {
+                tab.path = Some(path.to_path_buf());
+                tab.mark_saved();
+                self.add_recent_file(path);
+            }
+            Err(e) => self.notifications.error(format!("Failed to save {}", path.display()), e),
+        }
+    }
+
+    fn save_file_dialog(&mut self) {
+        let mut dialog = rfd::FileDialog::new()
+            .set_title("Save SVG")
+            .add_filter("SVG", &["svg"]);
+        if let Some(dir) = &self.last_dir {
+            dialog = dialog.set_directory(dir);
+        }
+        if let Some(name) = self.tab().and_then(|t| t.path.as_ref()).and_then(|p| p.file_name()) {
+            dialog = dialog.set_file_name(name.to_string_lossy());
+        }
+        if let Some(path) = dialog.save_file() {
+            self.last_dir = path.parent().map(|p| p.to_path_buf());
+            self.save_to(&path);
+        }
+    }
+
+    /// Save what the canvas shows, at `scale` times the screen's pixels.
+    fn export_view_dialog(&mut self, scale: f32) {
+        let background = self.background.color();
+        let Some(tab) = self.tabs.get(self.active) else { return };
+        let mut dialog = rfd::FileDialog::new()
+            .set_title("Export view as PNG")
+            .add_filter("PNG", &["png"]);
+        if let Some(dir) = &self.last_dir {
+            dialog = dialog.set_directory(dir);
+        }
+        dialog = dialog.set_file_name(Path::new(&tab.title()).with_extension("png").to_string_lossy());
+        let Some(path) = dialog.save_file() else { return };
+        self.last_dir = path.parent().map(|p| p.to_path_buf());
+        if let Err(e) = vectorlab_core::render_view_png(&tab.doc, &tab.view, tab.canvas_size, scale, &path, background, &self.load_options) {
+            self.notifications.error(format!("Failed to export {}", path.display()), e);
+        }
+    }
+
+    /// Render the whole document the way the Export Raster window was set up.
+    fn export_raster_dialog(&mut self, export: &ExportRaster) {
+        let Some(tab) = self.tabs.get(self.active) else { return };
+        let extension = export.format.extension();
+        let mut dialog = rfd::FileDialog::new()
+            .set_title("Export Raster")
+            .add_filter(export.format.name(), &[extension]);
+        if let Some(dir) = &self.last_dir {
+            dialog = dialog.set_directory(dir);
+        }
+        dialog = dialog.set_file_name(Path::new(&tab.title()).with_extension(extension).to_string_lossy());
+        let Some(path) = dialog.save_file() else { return };
+        self.last_dir = path.parent().map(|p| p.to_path_buf());
+        let selection = export.selection.then(|| vectorlab_core::selection_document(&tab.doc, &tab.selection)).flatten();
+        let doc = selection.as_ref().unwrap_or(&tab.doc);
+        let result = vectorlab_core::render_document(doc, export.width, export.height, export.fill(), &self.load_options)
+            .and_then(|image| export.save(&image, &path));
+        if let Err(e) = result {
+            self.notifications.error(format!("Failed to export {}", path.display()), e);
+        }
+    }
+
+    /// Export the whole document in one of the formats, picked by the extension of the file.
+    fn export_file_dialog(&mut self) {
+        let Some(tab) = self.tabs.get(self.active) else { return };
+        let mut dialog = rfd::FileDialog::new().set_title("Export");
+        for exporter in self.formats.exporters() {
+            dialog = dialog.add_filter(exporter.name(), exporter.extensions());
+        }
+        if let Some(dir) = &self.last_dir {
+            dialog = dialog.set_directory(dir);
+        }
+        let Some(path) = dialog.save_file() else { return };
+        self.last_dir = path.parent().map(|p| p.to_path_buf());
+        if let Err(e) = self.formats.save_file(&tab.doc, &path) {
+            self.notifications.error(format!("Failed to export {}", path.display()), e);
+        }
+    }
+
+    /// Write the selection cropped to it as an SVG of its own.
+    fn export_selection_dialog(&mut self) {
+        let opts = SaveOptions { text_to_paths: self.settings.text_to_paths };
+        let Some(tab) = self.tabs.get(self.active) else { return };
+        let Some(svg) = tab.copy_selection(opts) else { return };
+        let mut dialog = rfd::FileDialog::new()
+            .set_title("Export selection as SVG")
+            .add_filter("SVG", &["svg"]);
+        if let Some(dir) = &self.last_dir {
+            dialog = dialog.set_directory(dir);
+        }
+        let stem = Path::new(&tab.title()).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        dialog = dialog.set_file_name(format!("{}-selection.svg", stem));
+        let Some(path) = dialog.save_file() else { return };
+        self.last_dir = path.parent().map(|p| p.to_path_buf());
+        if let Err(e) = fs::write(&path, svg) {
+            self.notifications.error(format!("Failed to export {}", path.display()), e);
+        }
+    }
+
+    /// Write an SVG per layer into a folder, named by the pattern of the Export Layers window.
+    fn export_layers_dialog(&mut self, export: &ExportLayers) {
+        self.settings.layer_file_pattern = export.pattern.clone();
+        self.settings.save();
+        let opts = SaveOptions { text_to_paths: self.settings.text_to_paths };
+        let Some(tab) = self.tabs.get(self.active) else { return };
+        let mut dialog = rfd::FileDialog::new().set_title("Export layers to");
+        if let Some(dir) = &self.last_dir {
+            dialog = dialog.set_directory(dir);
+        }
+        let Some(dir) = dialog.pick_folder() else { return };
+        self.last_dir = Some(dir.clone());
+        let name = Path::new(&tab.title()).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        for (i, (layer, svg)) in vectorlab_core::layer_svgs(&tab.doc, opts).into_iter().enumerate() {
+            let path = dir.join(export.file_name(&name, &layer, i + 1));
+            if let Err(e) = fs::write(&path, svg) {
+                self.notifications.error(format!("Failed to export {}", path.display()), e);
+                return;
+            }
+        }
+    }
+
+    /// Write an SVG per color next to the name picked, as Split by color would sort them.
+    fn export_colors_dialog(&mut self) {
+        let opts = SaveOptions { text_to_paths: self.settings.text_to_paths };
+        let Some(tab) = self.tabs.get(self.active) else { return };
+        let mut dialog = rfd::FileDialog::new()
+            .set_title("Export per color")
+            .add_filter("SVG", &["svg"]);
+        if let Some(dir) = &self.last_dir {
+            dialog = dialog.set_directory(dir);
+        }
+        dialog = dialog.set_file_name(Path::new(&tab.title()).with_extension("svg").to_string_lossy());
+        let Some(path) = dialog.save_file() else { return };
+        self.last_dir = path.parent().map(|p| p.to_path_buf());
+        let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        for (layer, svg) in vectorlab_core::color_layer_svgs(&tab.doc, opts) {
+            let path = path.with_file_name(format!("{}-{}.svg", stem, layer));
+            if let Err(e) = fs::write(&path, svg) {
+                self.notifications.error(format!("Failed to export {}", path.display()), e);
+                return;
+            }
+        }
+    }
+
+    /// Write the active document as G-code and keep its moves for the preview.
+    fn export_gcode_dialog(&mut self, gcode: GcodeSettings) {
+        self.settings.gcode = gcode;
+        self.settings.save();
+        let Some(tab) = self.tabs.get_mut(self.active) else { return };
+        let mut dialog = rfd::FileDialog::new()
+            .set_title("Export G-code")
+            .add_filter("G-code", &["gcode", "nc", "ngc"]);
+        if let Some(dir) = &self.last_dir {
+            dialog = dialog.set_directory(dir);
+        }
+        dialog = dialog.set_file_name(Path::new(&tab.title()).with_extension("gcode").to_string_lossy());
+        let Some(path) = dialog.save_file() else { return };
+        self.last_dir = path.parent().map(|p| p.to_path_buf());
+        let toolpaths = Toolpaths::new(&tab.doc, &self.settings.gcode);
+        match fs::write(&path, vectorlab_core::to_gcode(&tab.doc, &toolpaths.moves, &self.settings.gcode)) {
+            Ok(()) => tab.toolpaths = Some(toolpaths),
+            Err(e) => self.notifications.error(format!("Failed to export {}", path.display()), e),
+        }
+    }
+
+    /// The active document or its selection as TikZ.
+    fn tikz(&self, selection_only: bool) -> Option<String> {
+        let tab = self.tabs.get(self.active)?;
+        let selection = if selection_only { vectorlab_core::selection_document(&tab.doc, &tab.selection) } else { None };
+        Some(vectorlab_core::to_tikz(selection.as_ref().unwrap_or(&tab.doc), &self.settings.tikz))
+    }
+
+    fn copy_tikz(&mut self, ctx: &egui::Context, selection_only: bool) {
+        if let Some(tex) = self.tikz(selection_only) {
+            ctx.output_mut(|o| o.copied_text = tex);
+        }
+    }
+
+    fn save_tikz_dialog(&mut self, selection_only: bool) {
+        let Some(tab) = self.tabs.get(self.active) else { return };
+        let mut dialog = rfd::FileDialog::new().set_title("Export TikZ").add_filter("TeX", &["tex", "tikz"]);
+        if let Some(dir) = &self.last_dir {
+            dialog = dialog.set_directory(dir);
+        }
+        dialog = dialog.set_file_name(Path::new(&tab.title()).with_extension("tex").to_string_lossy());
+        let Some(path) = dialog.save_file() else { return };
+        self.last_dir = path.parent().map(|p| p.to_path_buf());
+        let tex = self.tikz(selection_only).unwrap_or_default();
+        if let Err(e) = fs::write(&path, tex) {
+            self.notifications.error(format!("Failed to export {}", path.display()), e);
+        }
+    }
+
+    /// Pick and decode a PNG or JPEG to make paths from, with its file name made into an id
+    /// for the layer they go into.
+    fn pick_image(&mut self, title: &str) -> Option<(String, RasterImage)> {
+        let mut dialog = rfd::FileDialog::new().set_title(title).add_filter("Images", &["png", "jpg", "jpeg", "gif"]);
+        if let Some(dir) = &self.last_dir {
+            dialog = dialog.set_directory(dir);
+        }
+        let path = dialog.pick_file()?;
+        self.last_dir = path.parent().map(|p| p.to_path_buf());
+        let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        let id: String = stem.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect();
+        let id = if id.starts_with(|c: char| c.is_ascii_alphabetic()) { id } else { format!("image-{}", id) };
+        match fs::read(&path).map_err(|e| e.into()).and_then(RasterImage::decode_file) {
+            Ok(image) => Some((id, image)),
+            Err(e) => {
+                self.notifications.error(format!("Failed to open {}", path.display()), e);
+                None
+            }
+        }
+    }
+
+    fn export_cut_dialog(&mut self, format: CutFormat, cut: CutSettings) {
+        self.settings.cut = cut;
+        self.settings.cut_format = format;
+        self.settings.save();
+        let Some(tab) = self.tabs.get(self.active) else { return };
+        let (filter, extensions): (&str, &[&str]) = match format {
+            CutFormat::Svg => ("SVG", &["svg"]),
+            CutFormat::Dxf => ("DXF", &["dxf"]),
+        };
+        let mut dialog = rfd::FileDialog::new().set_title("Export for cutter").add_filter(filter, extensions);
+        if let Some(dir) = &self.last_dir {
+            dialog = dialog.set_directory(dir);
+        }
+        let stem = Path::new(&tab.title()).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        dialog = dialog.set_file_name(format!("{}-cut.{}", stem, format.name()));
+        let Some(path) = dialog.save_file() else { return };
+        self.last_dir = path.parent().map(|p| p.to_path_buf());
+        let job = vectorlab_core::cut_job(&tab.doc, &self.settings.cut);
+        let text = match format {
+            CutFormat::Svg => job.svg(),
+            CutFormat::Dxf => job.dxf(),
+        };
+        if let Err(e) = fs::write(&path, text) {
+            self.notifications.error(format!("Failed to export {}", path.display()), e);
+        }
+    }
+
+    fn save_hpgl_dialog(&mut self, hpgl: HpglSettings) {
+        self.settings.hpgl = hpgl;
+        self.settings.save();
+        let Some(tab) = self.tabs.get(self.active) else { return };
+        let mut dialog = rfd::FileDialog::new().set_title("Save HPGL").add_filter("HPGL", &["plt", "hpgl", "hpg"]);
+        if let Some(dir) = &self.last_dir {
+            dialog = dialog.set_directory(dir);
+        }
+        dialog = dialog.set_file_name(Path::new(&tab.title()).with_extension("plt").to_string_lossy());
+        let Some(path) = dialog.save_file() else { return };
+        self.last_dir = path.parent().map(|p| p.to_path_buf());
+        if let Err(e) = fs::write(&path, vectorlab_core::hpgl_job(&tab.doc, &self.settings.hpgl).text()) {
+            self.notifications.error(format!("Failed to save {}", path.display()), e);
+        }
+    }
+
+    /// Timing of the slideshow, true when Start is clicked.
+    fn slideshow_menu(&mut self, ui: &mut egui::Ui) -> bool {
+        let done = |r: egui::Response| r.drag_stopped() || r.lost_focus();
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label("Every");
+            changed |= done(ui.add(egui::DragValue::new(&mut self.settings.slideshow_interval).speed(0.1).range(0.5..=3600.0).suffix(" s")));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Crossfade");
+            changed |= done(ui.add(egui::DragValue::new(&mut self.settings.slideshow_crossfade).speed(0.05).range(0.0..=10.0).suffix(" s")));
+        });
+        if changed {
+            self.settings.save();
+        }
+        let hint = if self.tabs.len() == 1 { "Plays the files in the folder, space pauses" } else { "Plays the open tabs, space pauses" };
+        ui.add_enabled(!self.tabs.is_empty(), egui::Button::new("Start")).on_hover_text(hint).clicked()
+    }
+
+    fn orientation_menu(&mut self, ui: &mut egui::Ui) {
+        let keys = self.settings.keys.clone();
+        let Some(tab) = self.tab_mut() else {
+            ui.label("No document");
+            return;
+        };
+        let item = |ui: &mut egui::Ui, label: &str, action: Action| ui.add(egui::Button::new(label).shortcut_text(keys.text(action))).clicked();
+        if item(ui, "Rotate clockwise", Action::RotateRight) {
+            tab.rotate(ROTATION_STEP);
+        }
+        if item(ui, "Rotate counterclockwise", Action::RotateLeft) {
+            tab.rotate(-ROTATION_STEP);
+        }
+        if item(ui, "Flip horizontally", Action::FlipHorizontal) {
+            tab.flip(true);
+        }
+        if item(ui, "Flip vertically", Action::FlipVertical) {
+            tab.flip(false);
+        }
+        ui.separator();
+        if ui.add_enabled(tab.view.is_oriented(), egui::Button::new("Reset")).clicked() {
+            tab.reset_orientation();
+        }
+        ui.label("Alt+wheel turns freely");
+    }
+
+    fn edit_menu(&mut self, ui: &mut egui::Ui) {
+        let keys = self.settings.keys.clone();
+        if ui.add(egui::Button::new("Preferences…").shortcut_text(keys.text(Action::Preferences))).clicked() {
+            self.preferences = Some(Preferences::new(&self.settings));
+            ui.close_menu();
+        }
+        ui.separator();
+        let opts = SaveOptions { text_to_paths: self.settings.text_to_paths };
+        let tikz = self.settings.tikz.clone();
+        let Some(tab) = self.tab_mut() else {
+            ui.label("No document");
+            return;
+        };
+        let undo = tab.history.undo_name().map(|n| format!("Undo {}", n));
+        if ui.add_enabled(undo.is_some(), egui::Button::new(undo.as_deref().unwrap_or("Undo")).shortcut_text(keys.text(Action::Undo))).clicked() {
+            tab.undo();
+            ui.close_menu();
+        }
+        let redo = tab.history.redo_name().map(|n| format!("Redo {}", n));
+        if ui.add_enabled(redo.is_some(), egui::Button::new(redo.as_deref().unwrap_or("Redo")).shortcut_text(keys.text(Action::Redo))).clicked() {
+            tab.redo();
+            ui.close_menu();
+        }
+        ui.separator();
+        if ui.add_enabled(!tab.selection.is_empty(), egui::Button::new("Delete").shortcut_text(keys.text(Action::Delete))).clicked() {
+            tab.delete_selection();
+            ui.close_menu();
+        }
+        if ui.add_enabled(!tab.selection.is_empty(), egui::Button::new("Duplicate").shortcut_text(keys.text(Action::Duplicate))).clicked() {
+            tab.duplicate_selection();
+            ui.close_menu();
+        }
+        ui.separator();
+        // fixed shortcuts, egui-winit handles them
+        let shortcut = |key| ui.ctx().format_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, key));
+        let (cut, copy, paste) = (shortcut(egui::Key::X), shortcut(egui::Key::C), shortcut(egui::Key::V));
+        let selected = !tab.selection.is_empty();
+        let cut = ui.add_enabled(selected, egui::Button::new("Cut").shortcut_text(cut)).on_hover_text("As SVG, for pasting here or into other editors").clicked();
+        let copy = ui.add_enabled(selected, egui::Button::new("Copy").shortcut_text(copy)).on_hover_text("As SVG, for pasting here or into other editors").clicked();
+        if cut || copy {
+            if let Some(svg) = tab.copy_selection(opts) {
+                ui.output_mut(|o| o.copied_text = svg);
+                if cut {
+                    tab.delete_selection();
+                }
+            }
+            ui.close_menu();
+        }
+        if ui.add_enabled(selected, egui::Button::new("Copy as TikZ")).on_hover_text("For LaTeX, as set in File > Export TikZ").clicked() {
+            if let Some(doc) = vectorlab_core::selection_document(&tab.doc, &tab.selection) {
+                ui.output_mut(|o| o.copied_text = vectorlab_core::to_tikz(&doc, &tikz));
+            }
+            ui.close_menu();
+        }
+        let paste = ui.add(egui::Button::new("Paste").shortcut_text(paste)).on_hover_text("SVG markup, where it was on its page").clicked();
+        ui.separator();
+        if ui.add(egui::Button::new("Find…").shortcut_text(keys.text(Action::Find))).clicked() {
+            Search::open(&mut tab.search);
+            ui.close_menu();
+        }
+        if paste {
+            ui.close_menu();
+            let text = self.egui_winit.clipboard_text().unwrap_or_default();
+            let tab = &mut self.tabs[self.active];
+            match tab.paste(&text, &self.load_options, None) {
+                Ok(true) => {}
+                Ok(false) => self.notifications.error("Nothing to paste", "The clipboard holds no SVG elements"),
+                Err(e) => self.notifications.error("Could not paste", e),
+            }
+        }
+    }
+
+    /// Align and distribute the selection, among itself or on the page.
+    fn arrange_menu(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.arrange_on_page, "Relative to the page").on_hover_text("Otherwise to the box around the selection");
+        ui.separator();
+        let on_page = self.arrange_on_page;
+        let Some(tab) = self.tabs.get_mut(self.active) else {
+            ui.label("No document");
+            return;
+        };
+        let page = if on_page { tab.doc.page_bbox().or_else(|| tab.doc.bbox()) } else { None };
+        let count = tab.selection.len();
+        let mut edit = None;
+        for align in [Align::Left, Align::CenterX, Align::Right, Align::Top, Align::CenterY, Align::Bottom] {
+            if ui.add_enabled(count >= if on_page { 1 } else { 2 }, egui::Button::new(align.name())).clicked() {
+                edit = align_elements(&tab.doc, &tab.selection, align, page);
+                ui.close_menu();
+            }
+            if align == Align::Right {
+                ui.separator();
+            }
+        }
+        ui.separator();
+        for (axis, name) in [(0, "Distribute horizontally"), (1, "Distribute vertically")] {
+            let button = egui::Button::new(name);
+            if ui.add_enabled(count >= if on_page { 2 } else { 3 }, button).on_hover_text("Equal gaps between the selected elements").clicked() {
+                edit = distribute_elements(&tab.doc, &tab.selection, axis, page);
+                ui.close_menu();
+            }
+        }
+        if let Some(edit) = edit {
+            tab.history.push(edit, &mut tab.doc);
+        }
+    }
+
+    fn generate_menu(&mut self, ui: &mut egui::Ui) {
+        let has_doc = self.tab().is_some();
+        for i in 0..self.generators.len() {
+            if ui.add_enabled(has_doc, egui::Button::new(format!("{}…", self.generators[i].label()))).clicked() {
+                self.generate = Some(GenerateWindow::new(i));
+                ui.close_menu();
+            }
+        }
+    }
+
+    fn path_menu(&mut self, ui: &mut egui::Ui) {
+        let has_doc = self.tab().is_some();
+        if ui.add_enabled(has_doc, egui::Button::new("Join…")).on_hover_text("Merge paths whose ends nearly touch").clicked() {
+            self.path_operation = Some(PathOperation::new(Operation::Join { tolerance: 0.1 }));
+            ui.close_menu();
+        }
+        if ui.add_enabled(has_doc, egui::Button::new("Simplify…")).on_hover_text("Fewer points in dense polylines").clicked() {
+            self.path_operation = Some(PathOperation::new(Operation::Simplify { tolerance: 0.1, method: SimplifyMethod::DouglasPeucker }));
+            ui.close_menu();
+        }
+        if ui.add_enabled(has_doc, egui::Button::new("Remove overlaps…")).on_hover_text("Take out lines a plotter would draw twice").clicked() {
+            self.path_operation = Some(PathOperation::new(Operation::RemoveOverlaps { tolerance: 0.1, hidden: false }));
+            ui.close_menu();
+        }
+        if ui.add_enabled(has_doc, egui::Button::new("Fill to hatch…")).on_hover_text("Turn fills into lines a pen plotter can draw").clicked() {
+            let hatch = HatchSettings { angle: 45.0, spacing: 1.0, crosshatch: false, inset: 0.0, pen_width: 0.3, outline: true };
+            self.path_operation = Some(PathOperation::new(Operation::Hatch(hatch)));
+            ui.close_menu();
+        }
+        if ui.add_enabled(has_doc, egui::Button::new("Offset…")).on_hover_text("Grow or shrink shapes, for kerf compensation or concentric fills").clicked() {
+            let offset = Operation::Offset { distance: 1.0, join: LineJoin::Round, copies: 1, keep_original: true };
+            self.path_operation = Some(PathOperation::new(offset));
+            ui.close_menu();
+        }
+        if ui.add_enabled(has_doc, egui::Button::new("Clip to page…")).on_hover_text("Trim everything to the paper or the bed of a machine").clicked() {
+            if let Some(tab) = self.tab() {
+                let clip = Operation::ClipToPage { origin: [0.0, 0.0], size: page_size(&tab.doc) };
+                self.path_operation = Some(PathOperation::new(clip));
+            }
+            ui.close_menu();
+        }
+        ui.separator();
+        if ui.add_enabled(has_doc, egui::Button::new("Grid array…")).on_hover_text("Copies in rows and columns, to lay out parts for cutting").clicked() {
+            self.path_operation = Some(PathOperation::new(Operation::GridArray { rows: 2, columns: 2, gap: [5.0, 5.0] }));
+            ui.close_menu();
+        }
+        if ui.add_enabled(has_doc, egui::Button::new("Polar array…")).on_hover_text("Copies around a center").clicked() {
+            if let Some(tab) = self.tab() {
+                let polar = Operation::PolarArray { count: 6, center: page_size(&tab.doc).map(|s| s * 0.5), sweep: 360.0, rotate: true };
+                self.path_operation = Some(PathOperation::new(polar));
+            }
+            ui.close_menu();
+        }
+        ui.separator();
+        let selected = self.tab().is_some_and(|tab| !tab.selection.is_empty());
+        if ui.add_enabled(selected, egui::Button::new("Stroke to path")).on_hover_text("Turn the strokes of the selected paths into filled outlines").clicked() {
+            if let Some(tab) = self.tab_mut() {
+                if !tab.outline_selection() {
+                    self.notifications.warnings("Stroke to path", &["None of the selected paths has a stroke"]);
+                }
+            }
+            ui.close_menu();
+        }
+        ui.separator();
+        for (op, hover) in [
+            (BooleanOp::Union, "Merge the selected shapes into one"),
+            (BooleanOp::Difference, "Cut the shapes above out of the bottom one"),
+            (BooleanOp::Intersection, "Keep where all the selected shapes overlap"),
+            (BooleanOp::Xor, "Keep where an odd number of the selected shapes overlap"),
+        ] {
+            if ui.add_enabled(selected, egui::Button::new(op.name())).on_hover_text(hover).clicked() {
+                if let Some(tab) = self.tab_mut() {
+                    if !tab.combine_selection(op) {
+                        self.notifications.warnings(op.name(), &["Select at least two overlapping paths"]);
+                    }
+                }
+                ui.close_menu();
+            }
+        }
+        ui.separator();
+        if ui.add_enabled(has_doc, egui::Button::new("Flatten")).on_hover_text("Bake all transforms into the coordinates and dissolve all groups").clicked() {
+            if let Some(tab) = self.tab_mut() {
+                tab.history.push(Box::new(FlattenDocument::default()), &mut tab.doc);
+                // the groups are gone
+                let alive: HashSet<ElementId> = tab.doc.descendants(tab.doc.root).into_iter().collect();
+                tab.selection.retain(|id| alive.contains(id));
+            }
+            ui.close_menu();
+        }
+        let hover = "A layer per stroke color, or fill color for paths without a stroke, for one pen or laser pass each. Flattens first";
+        if ui.add_enabled(has_doc, egui::Button::new("Split by color")).on_hover_text(hover).clicked() {
+            if let Some(tab) = self.tab_mut() {
+                tab.history.push(Box::new(SplitByColor::default()), &mut tab.doc);
+                let alive: HashSet<ElementId> = tab.doc.descendants(tab.doc.root).into_iter().collect();
+                tab.selection.retain(|id| alive.contains(id));
+            }
+            ui.close_menu();
+        }
+        ui.separator();
+        ui.menu_button("Plugins", |ui| {
+            // one at a time
+            let can_run = has_doc && self.plugin.is_none();
+            let plugins = installed_plugins();
+            for path in &plugins {
+                if ui.add_enabled(can_run, egui::Button::new(plugin_name(path))).clicked() {
+                    self.run_plugin(path);
+                    ui.close_menu();
+                }
+            }
+            if plugins.is_empty() {
+                let hint = plugin_dir().map_or_else(String::new, |dir| format!("Put .wasm files into {}", dir.display()));
+                ui.add_enabled(false, egui::Label::new("No plugins installed")).on_disabled_hover_text(hint);
+            }
+            ui.separator();
+            if ui.add_enabled(can_run, egui::Button::new("Run plugin file…")).on_hover_text("A WebAssembly plugin from anywhere, without installing it").clicked() {
+                let dialog = rfd::FileDialog::new().set_title("Run plugin").add_filter("WebAssembly", &["wasm"]);
+                if let Some(path) = dialog.pick_file() {
+                    self.run_plugin(&path);
+                }
+                ui.close_menu();
+            }
+        });
+    }
+
+    /// Start the plugin at `path` on the drawing of the current tab, see [`Self::finish_plugin`].
+    fn run_plugin(&mut self, path: &Path) {
+        let Some(tab) = self.tab() else { return };
+        match PluginRun::start(path, tab, &self.load_options) {
+            Ok(run) => self.plugin = Some(run),
+            Err(e) => self.notifications.error(format!("Plugin {} failed", plugin_name(path)), e),
+        }
+    }
+
+    /// Replace the drawing of the tab the plugin ran on with what it made of it, as one undo
+    /// step. Not if the drawing was edited in the meantime, that would be lost.
+    fn finish_plugin(&mut self) {
+        let Some(result) = self.plugin.as_ref().and_then(PluginRun::poll) else { return };
+        let Some(run) = self.plugin.take() else { return };
+        let doc = match result {
+            Ok(doc) => doc,
+            Err(_) if run.is_cancelled() => return,
+            Err(e) => {
+                self.notifications.error(format!("Plugin {} failed", run.name), e);
+                return;
+            }
+        };
+        // the tab may have been closed in the meantime
+        let Some(tab) = self.tabs.iter_mut().find(|t| t.id == run.tab) else { return };
+        if tab.history.version() != run.version {
+            self.notifications.error(format!("Plugin {} not applied", run.name), "The drawing was changed while it ran, run it again");
+            return;
+        }
+        tab.selection.clear();
+        tab.history.push(Box::new(ReplaceDrawing::new(run.name, doc)), &mut tab.doc);
+    }
+
+    fn file_menu(&mut self, ui: &mut egui::Ui) {
+        if ui.add(egui::Button::new("Open…").shortcut_text(self.settings.keys.text(Action::Open))).clicked() {
+            self.file_dialog_open = true;
+            ui.close_menu();
+        }
+        if ui.button("Open URL…").clicked() {
+            self.url_dialog = Some(String::new());
+            ui.close_menu();
+        }
+        let has_doc = self.tab().is_some();
+        if ui.add_enabled(has_doc, egui::Button::new("Save").shortcut_text(self.settings.keys.text(Action::Save))).clicked() {
+            self.save();
+            ui.close_menu();
+        }
+        if ui.add_enabled(has_doc, egui::Button::new("Save As…").shortcut_text(self.settings.keys.text(Action::SaveAs))).clicked() {
+            self.save_dialog_open = true;
+            ui.close_menu();
+        }
+        if ui.checkbox(&mut self.settings.text_to_paths, "Save texts as paths").on_hover_text("Write the glyph outlines, also when copying, for machines and programs without the fonts").changed() {
+            self.settings.save();
+        }
+        if ui.add_enabled(has_doc, egui::Button::new("Trace bitmap…")).on_hover_text("Outlines of the dark parts of a PNG or JPEG, as a new layer").clicked() {
+            if let Some((name, image)) = self.pick_image("Trace bitmap") {
+                self.trace_bitmap = Some(TraceBitmap::new(name, image, &self.settings.trace));
+            }
+            ui.close_menu();
+        }
+        if ui.add_enabled(has_doc, egui::Button::new("Stipple image…")).on_hover_text("Dots spaced by the darkness of a PNG or JPEG, for pen plotters, as a new layer").clicked() {
+            if let Some((name, image)) = self.pick_image("Stipple image") {
+                self.stipple = Some(StippleImage::new(name, image, &self.settings.stipple));
+            }
+            ui.close_menu();
+        }
+        if ui.add_enabled(has_doc, egui::Button::new("Halftone image…")).on_hover_text("Lines that wave more where a PNG or JPEG is darker, as new layers").clicked() {
+            if let Some((name, image)) = self.pick_image("Halftone image") {
+                self.halftone = Some(HalftoneImage::new(name, image, &self.settings.halftone));
+            }
+            ui.close_menu();
+        }
+        ui.separator();
+        let formats: Vec<&str> = self.formats.exporters().map(|e| e.name()).collect();
+        if ui.add_enabled(has_doc, egui::Button::new("Export…")).on_hover_text(format!("As {}, by the extension of the file", formats.join(", "))).clicked() {
+            self.export_file_dialog();
+            ui.close_menu();
+        }
+        if ui.add_enabled(has_doc, egui::Button::new("Export Raster…")).clicked() {
+            self.export_raster = self.tab().map(|t| ExportRaster::new(&t.doc));
+            ui.close_menu();
+        }
+        let selected = self.tab().is_some_and(|t| !t.selection.is_empty());
+        if ui.add_enabled(selected, egui::Button::new("Export selection as SVG…")).on_hover_text("Cropped to the selection").clicked() {
+            self.export_selection_dialog();
+            ui.close_menu();
+        }
+        if ui.add_enabled(selected, egui::Button::new("Export selection as PNG…")).on_hover_text("Cropped to the selection").clicked() {
+            let selection = self.tab().and_then(|t| vectorlab_core::selection_document(&t.doc, &t.selection));
+            self.export_raster = selection.map(|doc| ExportRaster { selection: true, ..ExportRaster::new(&doc) });
+            ui.close_menu();
+        }
+        if ui.add_enabled(has_doc, egui::Button::new("Export layers…")).on_hover_text("An SVG for each layer").clicked() {
+            self.export_layers = Some(ExportLayers { pattern: self.settings.layer_file_pattern.clone() });
+            ui.close_menu();
+        }
+        if ui.add_enabled(has_doc, egui::Button::new("Export per color…")).on_hover_text("An SVG for each color, NAME-color-rrggbb.svg, on the same page").clicked() {
+            self.export_colors_dialog();
+            ui.close_menu();
+        }
+        if ui.button("Machine profiles…").on_hover_text("Work areas and setups of your machines, the active one is used when exporting and plotting").clicked() {
+            self.profile_editor = Some(ProfileEditor::new(&self.settings.profiles, &self.settings.active_profile));
+            ui.close_menu();
+        }
+        if ui.add_enabled(has_doc, egui::Button::new("Export G-code…")).clicked() {
+            self.gcode_export = self.tab().map(|t| GcodeExport::new(&self.settings.gcode, &t.doc));
+            ui.close_menu();
+        }
+        if ui.add_enabled(has_doc, egui::Button::new("Export TikZ…")).on_hover_text("The drawing as TikZ commands for LaTeX").clicked() {
+            self.tikz_export = Some(TikzExport::new(&self.settings.tikz, selected));
+            ui.close_menu();
+        }
+        if ui.add_enabled(has_doc, egui::Button::new("Export for cutter…")).on_hover_text("Closed outlines in mm for Cricut, Silhouette and other craft cutters").clicked() {
+            self.cut_export = Some(CutExport::new(&self.settings.cut, self.settings.cut_format));
+            ui.close_menu();
+        }
+        if ui.add_enabled(has_doc, egui::Button::new("Simulate plot…")).on_hover_text("Watch the job at the machine's speeds and see how long it takes").clicked() {
+            if self.simulation.is_none() {
+                self.simulation = Some(PlotSimulation::new(&self.settings.simulation, self.settings.gcode.optimize));
+            }
+            ui.close_menu();
+        }
+        if ui.add_enabled(has_doc, egui::Button::new("Plot…")).on_hover_text("Draw on an AxiDraw connected over USB").clicked() {
+            if self.plotter.is_none() {
+                self.plotter = Some(Plotter::new(&self.settings.plotter_port, &self.settings.plotter));
+            }
+            ui.close_menu();
+        }
+        if ui.add_enabled(has_doc, egui::Button::new("Stream to GRBL…")).on_hover_text("Cut on a GRBL machine connected over USB").clicked() {
+            if self.grbl.is_none() {
+                self.grbl = Some(GrblSender::new(&self.settings.grbl_port));
+            }
+            ui.close_menu();
+        }
+        if ui.add_enabled(has_doc, egui::Button::new("Send to HPGL plotter…")).on_hover_text("Classic pen plotters on a serial port or the network").clicked() {
+            if self.hpgl.is_none() {
+                self.hpgl = Some(HpglSender::new(&self.settings.hpgl_output, &self.settings.hpgl));
+            }
+            ui.close_menu();
+        }
+        if ui.button("Job queue…").on_hover_text("Export or plot documents and layers one after the other").clicked() {
+            if self.queue.is_none() {
+                self.queue = Some(JobQueue::new(self.last_dir.clone()));
+            }
+            ui.close_menu();
         }
+        ui.add_enabled_ui(has_doc, |ui| {
+            ui.menu_button("Export view as PNG", |ui| {
+                for scale in [1.0, 2.0, 4.0] {
+                    if ui.button(format!("{}×", scale)).clicked() {
+                        self.export_view_dialog(scale);
+                        ui.close_menu();
+                    }
+                }
+            })
+        });
+        if ui.add_enabled(has_doc, egui::Button::new("Close").shortcut_text(self.settings.keys.text(Action::CloseTab))).clicked() {
+            self.close_tab(self.active);
+            ui.close_menu();
+        }
+        ui.menu_button("Recent", |ui| {
+            if self.settings.recent_files.is_empty() {
+                ui.label("No recent files");
+                return;
+            }
+            let mut open = None;
+            for path in &self.settings.recent_files {
+                let button = egui::Button::new(path.display().to_string());
+                // files that vanished stay listed, greyed out, until pruned
+                if ui.add_enabled(path.exists(), button).clicked() {
+                    open = Some(path.clone());
+                }
+            }
+            ui.separator();
+            if ui.button("Remove missing").clicked() {
+                self.settings.recent_files.retain(|p| p.exists());
+                self.settings.save();
+            }
+            if ui.button("Clear list").clicked() {
+                self.settings.recent_files.clear();
+                self.settings.save();
+                ui.close_menu();
+            }
+            if let Some(path) = open {
+                self.load_svg(&path.to_string_lossy());
+                ui.close_menu();
+            }
+        });
+    }
+
+    /// winit reports every file of a drop as its own event, each one gets its own tab.
+    fn file_dropped(&mut self, path: PathBuf) {
+        self.hovered_files.clear();
+        self.load_svg(&path.to_string_lossy());
+        self.window.request_redraw();
     }
 
     fn render(&mut self) -> Result<(), winit::error::EventLoopError> {
@@ -135,57 +1407,695 @@ impl VectorLabApp {
             self.gl.clear(glow::COLOR_BUFFER_BIT);
         }
 
+        self.reload_changed_files();
+        self.finish_loads();
+        self.finish_plugin();
+        self.notifications.take_logged();
+        self.handle_control_requests();
+        self.images.prune();
+
         let raw_input = self.egui_winit.take_egui_input(self.window_size);
-        let output = self.egui_ctx.run(raw_input, |egui_ctx| {
-            egui::TopBottomPanel::top("menu_bar").show(egui_ctx, |ui| {
-                ui.horizontal(|ui| {
-                    if ui.button("📁 Open").clicked() {
-                        self.file_dialog_open = true;
+        let egui_ctx = self.egui_ctx.clone();
+        let antialiasing = self.settings.antialiasing;
+        egui_ctx.tessellation_options_mut(|o| o.feathering = antialiasing);
+        let output = egui_ctx.run(raw_input, |egui_ctx| {
+            let keys = self.settings.keys.clone();
+            if keys.pressed(egui_ctx, Action::Open) {
+                self.file_dialog_open = true;
+            }
+            if keys.pressed(egui_ctx, Action::Preferences) {
+                self.preferences = Some(Preferences::new(&self.settings));
+            }
+            if keys.pressed(egui_ctx, Action::PreviousTab) {
+                self.cycle_tabs(true);
+            } else if keys.pressed(egui_ctx, Action::NextTab) {
+                self.cycle_tabs(false);
+            }
+            if keys.pressed(egui_ctx, Action::CloseTab) {
+                self.close_tab(self.active);
+            }
+            if keys.pressed(egui_ctx, Action::Fullscreen) {
+                self.toggle_fullscreen();
+            }
+            if keys.pressed(egui_ctx, Action::Presentation) {
+                self.set_presenting(self.presenting.is_none());
+            }
+
+            if self.tab().is_some() {
+                if keys.pressed(egui_ctx, Action::SaveAs) {
+                    self.save_dialog_open = true;
+                } else if keys.pressed(egui_ctx, Action::Save) {
+                    self.save();
+                }
+                if keys.pressed(egui_ctx, Action::NextFile) {
+                    self.step_sibling(1);
+                } else if keys.pressed(egui_ctx, Action::PreviousFile) {
+                    self.step_sibling(-1);
+                }
+
+                let tab = &mut self.tabs[self.active];
+                if keys.pressed(egui_ctx, Action::Find) {
+                    Search::open(&mut tab.search);
+                }
+                // Ctrl+Y as well, as on Windows
+                let redo_y = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Y);
+                if keys.pressed(egui_ctx, Action::Redo) || egui_ctx.input_mut(|i| i.consume_shortcut(&redo_y)) {
+                    tab.redo();
+                } else if keys.pressed(egui_ctx, Action::Undo) {
+                    tab.undo();
+                }
+                if keys.pressed(egui_ctx, Action::Delete) && !(tab.tool == Tool::Nodes && delete_selected_node(tab)) {
+                    tab.delete_selection();
+                }
+                if keys.pressed(egui_ctx, Action::Duplicate) {
+                    tab.duplicate_selection();
+                }
+                // egui-winit turns the clipboard shortcuts into these, text fields take them first
+                if !egui_ctx.wants_keyboard_input() {
+                    let events = egui_ctx.input(|i| i.events.clone());
+                    for event in events {
+                        match event {
+                            egui::Event::Copy | egui::Event::Cut => {
+                                let opts = SaveOptions { text_to_paths: self.settings.text_to_paths };
+                                if let Some(svg) = tab.copy_selection(opts) {
+                                    egui_ctx.output_mut(|o| o.copied_text = svg);
+                                    if event == egui::Event::Cut {
+                                        tab.delete_selection();
+                                    }
+                                }
+                            }
+                            egui::Event::Paste(text) => match tab.paste(&text, &self.load_options, tab.cursor) {
+                                Ok(true) => {}
+                                Ok(false) => self.notifications.error("Nothing to paste", "The clipboard holds no SVG elements"),
+                                Err(e) => self.notifications.error("Could not paste", e),
+                            },
+                            _ => {}
+                        }
                     }
-                    ui.separator();
-                    ui.label(self.current_file.as_deref().unwrap_or("No file"));
+                }
+                if keys.pressed(egui_ctx, Action::Measure) {
+                    tab.tool = if tab.tool == Tool::Measure { Tool::Select } else { Tool::Measure };
+                }
+                if keys.pressed(egui_ctx, Action::EditNodes) {
+                    tab.tool = if tab.tool == Tool::Nodes { Tool::Select } else { Tool::Nodes };
+                }
+                if keys.pressed(egui_ctx, Action::Transform) {
+                    tab.tool = if tab.tool == Tool::Transform { Tool::Select } else { Tool::Transform };
+                }
+                if keys.pressed(egui_ctx, Action::DrawShapes) {
+                    tab.tool = if tab.tool == Tool::Shapes { Tool::Select } else { Tool::Shapes };
+                }
+                if keys.pressed(egui_ctx, Action::Pencil) {
+                    tab.tool = if tab.tool == Tool::Pencil { Tool::Select } else { Tool::Pencil };
+                }
+                if keys.pressed(egui_ctx, Action::Text) {
+                    tab.tool = if tab.tool == Tool::Text { Tool::Select } else { Tool::Text };
+                }
+            }
+            if let Some(settings) = preferences_window(egui_ctx, &mut self.preferences) {
+                self.apply_preferences(settings);
+            }
+            if let Some(export) = export_raster_window(egui_ctx, &mut self.export_raster, self.settings.unit) {
+                self.export_raster_dialog(&export);
+            }
+            if let Some(tab) = self.tabs.get(self.active) {
+                let name = Path::new(&tab.title()).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+                let layers = vectorlab_core::layer_names(&tab.doc);
+                if let Some(export) = export_layers_window(egui_ctx, &mut self.export_layers, &name, &layers) {
+                    self.export_layers_dialog(&export);
+                }
+            }
+            let current = self.tabs.get(self.active).map(|t| (t.title(), &t.doc));
+            let current = current.as_ref().map(|(title, doc)| (Path::new(title).file_stem().and_then(|s| s.to_str()).unwrap_or("drawing"), *doc));
+            queue_window(egui_ctx, &mut self.queue, current, &self.settings, &self.load_options);
+            if let Some((profiles, active)) = profiles_window(egui_ctx, &mut self.profile_editor, self.settings.unit) {
+                self.settings.profiles = profiles;
+                self.settings.active_profile = active;
+                self.settings.apply_profile();
+                self.settings.save();
+                // the travel overlay follows the new origin
+                for tab in &mut self.tabs {
+                    tab.toolpaths = None;
+                }
+            }
+            if let Some(gcode) = gcode_window(egui_ctx, &mut self.gcode_export) {
+                self.export_gcode_dialog(gcode);
+            }
+            let has_selection = self.tabs.get(self.active).is_some_and(|t| !t.selection.is_empty());
+            if let Some((action, tikz, selection_only)) = tikz_window(egui_ctx, &mut self.tikz_export, has_selection) {
+                self.settings.tikz = tikz;
+                self.settings.save();
+                match action {
+                    TikzAction::Copy => self.copy_tikz(egui_ctx, selection_only),
+                    TikzAction::Save => self.save_tikz_dialog(selection_only),
+                }
+            }
+            if let Some((trace, svg)) = trace_window(egui_ctx, &mut self.trace_bitmap, self.settings.unit) {
+                self.settings.trace = trace;
+                self.settings.save();
+                if let Some(tab) = self.tabs.get_mut(self.active) {
+                    if let Err(e) = tab.add_layer("Trace bitmap", &svg, &self.load_options) {
+                        self.notifications.error("Failed to insert the trace", e);
+                    }
+                }
+            }
+            if let Some((stipple, svg)) = stipple_window(egui_ctx, &mut self.stipple, self.settings.unit) {
+                self.settings.stipple = stipple;
+                self.settings.save();
+                if let Some(tab) = self.tabs.get_mut(self.active) {
+                    if let Err(e) = tab.add_layer("Stipple image", &svg, &self.load_options) {
+                        self.notifications.error("Failed to insert the stipple", e);
+                    }
+                }
+            }
+            if let Some((halftone, svg)) = halftone_window(egui_ctx, &mut self.halftone, self.settings.unit) {
+                self.settings.halftone = halftone;
+                self.settings.save();
+                if let Some(tab) = self.tabs.get_mut(self.active) {
+                    if let Err(e) = tab.add_layer("Halftone image", &svg, &self.load_options) {
+                        self.notifications.error("Failed to insert the halftone", e);
+                    }
+                }
+            }
+            let has_doc = self.tab().is_some();
+            if let Some(segments) = generate_window(egui_ctx, &mut self.generate, &mut self.generators, self.settings.unit, has_doc) {
+                if let Some(tab) = self.tabs.get_mut(self.active) {
+                    insert_generated(tab, segments, &self.shapes);
+                }
+            }
+            if let Some((format, cut)) = cut_window(egui_ctx, &mut self.cut_export, self.tabs.get(self.active).map(|t| &t.doc), self.settings.unit) {
+                self.export_cut_dialog(format, cut);
+            }
+            if let Some(simulation) = simulation_window(egui_ctx, &mut self.simulation, self.tabs.get(self.active).map(|t| &t.doc), &self.settings.gcode) {
+                self.settings.simulation = simulation;
+                self.settings.save();
+            }
+            if let Some((port, plotter)) = plotter_window(egui_ctx, &mut self.plotter, self.tabs.get(self.active).map(|t| &t.doc), self.settings.unit) {
+                self.settings.plotter_port = port;
+                self.settings.plotter = plotter;
+                self.settings.save();
+            }
+            if let Some(port) = grbl_window(egui_ctx, &mut self.grbl, self.tabs.get(self.active).map(|t| &t.doc), &self.settings.gcode, self.settings.unit) {
+                self.settings.grbl_port = port;
+                self.settings.save();
+            }
+            match hpgl_window(egui_ctx, &mut self.hpgl, self.tabs.get(self.active).map(|t| &t.doc), self.settings.unit) {
+                Some(HpglAction::Sent(output, hpgl)) => {
+                    self.settings.hpgl_output = output;
+                    self.settings.hpgl = hpgl;
+                    self.settings.save();
+                }
+                Some(HpglAction::Save(hpgl)) => self.save_hpgl_dialog(hpgl),
+                None => {}
+            }
+            if let Some(tab) = self.tabs.get_mut(self.active) {
+                if let Some(edit) = operation_window(egui_ctx, &mut self.path_operation, &tab.doc, &tab.selection, self.settings.unit) {
+                    tab.history.push(edit, &mut tab.doc);
+                    // paths merged into others are gone
+                    let alive: HashSet<ElementId> = tab.doc.descendants(tab.doc.root).into_iter().collect();
+                    tab.selection.retain(|id| alive.contains(id));
+                }
+            }
+
+            // presentation mode shows nothing but the artwork
+            if self.presenting.is_none() {
+                egui::TopBottomPanel::top("menu_bar").show(egui_ctx, |ui| {
+                    egui::menu::bar(ui, |ui| {
+                        ui.menu_button("File", |ui| self.file_menu(ui));
+                        ui.menu_button("Edit", |ui| self.edit_menu(ui));
+                        ui.menu_button("Path", |ui| self.path_menu(ui));
+                        ui.menu_button("Arrange", |ui| self.arrange_menu(ui));
+                        ui.menu_button("Generate", |ui| self.generate_menu(ui));
+                        ui.menu_button("View", |ui| {
+                            ui.checkbox(&mut self.show_layers, "Layers panel");
+                            ui.checkbox(&mut self.show_source, "Source panel");
+                            ui.checkbox(&mut self.show_check, "Geometry check panel").on_hover_text("Self-intersections, open contours and degenerate segments");
+                            ui.checkbox(&mut self.show_script, "Script console").on_hover_text("Run Rhai scripts on the drawing");
+                            ui.checkbox(&mut self.show_palette, "Colors panel").on_hover_text("The fill and stroke colors in use, to select and replace them");
+                            ui.checkbox(&mut self.show_rulers, "Rulers").on_hover_text("Drag guides out of the rulers");
+                            ui.checkbox(&mut self.show_winding, "Winding numbers").on_hover_text("Color the fills by how often their outlines go around, to see where nonzero and even-odd differ");
+                            ui.checkbox(&mut self.show_minimap, "Minimap").on_hover_text("Overview of the whole drawing, drag it to pan");
+                            ui.checkbox(&mut self.show_travel, "Travel moves").on_hover_text("Where the pen goes up and travels, in the order of Export G-code");
+                            ui.checkbox(&mut self.show_scale_bar, "Scale bar").on_hover_text("A length on the page as shown, and how that compares to its real size");
+                            ui.menu_button("Color preview", |ui| color_preview_menu(ui, &mut self.color_mode, self.tabs.get_mut(self.active)));
+                            ui.menu_button("Rotate and flip", |ui| self.orientation_menu(ui));
+                        ui.separator();
+                        let keys = self.settings.keys.clone();
+                        let fullscreen = self.window.fullscreen().is_some();
+                        if ui.add(egui::Button::new("Fullscreen").selected(fullscreen).shortcut_text(keys.text(Action::Fullscreen))).clicked() {
+                            self.toggle_fullscreen();
+                            ui.close_menu();
+                        }
+                        let presentation = egui::Button::new("Presentation").shortcut_text(keys.text(Action::Presentation));
+                        if ui.add(presentation).on_hover_text("Only the artwork, fullscreen. ← → step through the folder, Esc ends").clicked() {
+                            self.set_presenting(true);
+                            ui.close_menu();
+                        }
+                        ui.menu_button("Slideshow", |ui| {
+                            if self.slideshow_menu(ui) {
+                                self.start_slideshow(vec![], self.settings.slideshow_interval, self.settings.slideshow_crossfade);
+                                ui.close_menu();
+                            }
+                        });
+                        ui.separator();
+                            ui.menu_button("Background", |ui| {
+                                if self.background.menu(ui) {
+                                    self.settings.background = self.background;
+                                    self.settings.save();
+                                }
+                            });
+                            ui.menu_button("Overlays", |ui| self.overlays.menu(ui));
+                            ui.menu_button("Grid", |ui| {
+                                if self.settings.grid.menu(ui) {
+                                    self.settings.save();
+                                }
+                            });
+                            let strict = ui.checkbox(&mut self.load_options.strict, "Strict parsing")
+                                .on_hover_text("List every spec violation with its line and column");
+                            if strict.changed() {
+                                self.settings.strict_parsing = self.load_options.strict;
+                                self.settings.save();
+                                self.reload_active_tab();
+                            }
+                            let messages = format!("Messages ({})", self.notifications.len());
+                            ui.add_enabled(!self.notifications.is_empty(), egui::Checkbox::new(&mut self.notifications.open, messages));
+                            ui.horizontal(|ui| {
+                                ui.label("Curve tolerance");
+                                let drag = egui::DragValue::new(&mut self.settings.curve_tolerance).speed(0.01).range(0.01..=10.0).suffix(" px");
+                                let response = ui.add(drag).on_hover_text("How far flattened curves may deviate from the true shape");
+                                if response.drag_stopped() || response.lost_focus() {
+                                    self.settings.save();
+                                }
+                            });
+                        });
+                        ui.separator();
+                        let keys = &self.settings.keys;
+                        if ui.button("📁 Open").on_hover_text(format!("Open a file ({})", keys.text(Action::Open))).clicked() {
+                            self.file_dialog_open = true;
+                        }
+                        let (previous_file, next_file) = (keys.text(Action::PreviousFile), keys.text(Action::NextFile));
+                        let (actual_size, fit, measure) = (keys.text(Action::ActualSize), keys.text(Action::Fit), keys.text(Action::Measure));
+                        let physical_size = keys.text(Action::PhysicalSize);
+                        let (edit_nodes, transform, draw_shapes) = (keys.text(Action::EditNodes), keys.text(Action::Transform), keys.text(Action::DrawShapes));
+                        let (pencil, text) = (keys.text(Action::Pencil), keys.text(Action::Text));
+                        if let Some((index, count)) = self.tab().and_then(|t| Some((t.sibling_index()?, t.siblings.len()))) {
+                            if count > 1 {
+                                ui.separator();
+                                if ui.add_enabled(index > 0, egui::Button::new("◀")).on_hover_text(format!("Previous file ({})", previous_file)).clicked() {
+                                    self.step_sibling(-1);
+                                }
+                                ui.label(format!("{}/{}", index + 1, count));
+                                if ui.add_enabled(index + 1 < count, egui::Button::new("▶")).on_hover_text(format!("Next file ({})", next_file)).clicked() {
+                                    self.step_sibling(1);
+                                }
+                            }
+                        }
+                        if let Some(tab) = self.tabs.get_mut(self.active) {
+                            ui.separator();
+                            ui.label(format!("{} paths", tab.doc.path_count()));
+                            ui.separator();
+                            // relative to the document's own size, not to one pixel per user unit
+                            let actual = tab.doc.initial_view();
+                            if ui.button(format!("{:.0}%", tab.view.zoom / actual.zoom * 100.0)).on_hover_text(format!("Reset to 100% ({})", actual_size)).clicked() {
+                                tab.actual_size();
+                            }
+                            if ui.button("1:1").on_hover_text(format!("Real size on screen ({})", physical_size)).clicked() {
+                                tab.physical_size(points_per_mm(ui.ctx(), self.settings.screen_dpi));
+                            }
+                            if tab.view.is_oriented() {
+                                let mut label = format!("⟳ {:.0}°", tab.view.rotation);
+                                if tab.view.flip_x != tab.view.flip_y {
+                                    label.push_str(" ⇄");
+                                }
+                                if ui.button(label).on_hover_text("Rotated or flipped, click to reset").clicked() {
+                                    tab.reset_orientation();
+                                }
+                            }
+                            if ui.button("Fit").on_hover_text(format!("Zoom to fit ({})", fit)).clicked() {
+                                tab.fit_pending = true;
+                            }
+                            ui.separator();
+                            ui.selectable_value(&mut tab.tool, Tool::Select, "⬉ Select");
+                            ui.selectable_value(&mut tab.tool, Tool::Measure, "📏 Measure").on_hover_text(format!("Click two points or drag, right click to clear ({})", measure));
+                            let hint = "Drag points and handles of the selected path. Double click a node to make it smooth or a corner, the outline to add one, Delete removes the picked one";
+                            ui.selectable_value(&mut tab.tool, Tool::Nodes, "✏ Nodes").on_hover_text(format!("{} ({})", hint, edit_nodes));
+                            let hint = "Drag the selection to move it, its handles to scale and turn it. Shift keeps proportions and snaps angles";
+                            ui.selectable_value(&mut tab.tool, Tool::Transform, "⛶ Transform").on_hover_text(format!("{} ({})", hint, transform));
+                            ui.selectable_value(&mut tab.tool, Tool::Shapes, "⬟ Shapes").on_hover_text(format!("Draw rectangles, ellipses, polygons and lines ({})", draw_shapes));
+                            ui.selectable_value(&mut tab.tool, Tool::Text, "🗛 Text").on_hover_text(format!("Click to place a text, on one made here to edit it ({})", text));
+                            ui.selectable_value(&mut tab.tool, Tool::Pencil, "✍ Pencil").on_hover_text(format!("Draw freehand, the strokes become smooth curves ({})", pencil));
+                            if tab.tool == Tool::Shapes {
+                                ui.separator();
+                                shape_options(ui, &mut self.shapes, self.settings.unit);
+                            } else if tab.tool == Tool::Pencil {
+                                ui.separator();
+                                pencil_options(ui, &mut self.pencil, &mut self.shapes, self.settings.unit);
+                            } else if tab.tool == Tool::Text {
+                                ui.separator();
+                                text_options(ui, &mut self.text, self.settings.unit);
+                            }
+                        }
+                    });
                 });
-            });
 
-            egui::CentralPanel::default().show(egui_ctx, |ui| {
-                let rect = ui.available_rect_before_wrap();
-                ui.painter().rect_filled(rect, 0.0, egui::Color32::from_black_alpha(20));
+                if self.notifications.open {
+                    egui::TopBottomPanel::bottom("notifications").show(egui_ctx, |ui| self.notifications.panel(ui));
+                }
 
-                if self.file_dialog_open {
-                    ui.centered_and_justified(|ui| {
-                        ui.heading("Load SVG file");
-                        if ui.button("Load /home/jw/test.svg").clicked() {
-                            self.load_svg("/home/jw/test.svg");
-                            self.file_dialog_open = false;
+                if !self.loads.is_empty() || self.plugin.is_some() {
+                    egui::TopBottomPanel::bottom("loads").show(egui_ctx, |ui| {
+                        for load in &self.loads {
+                            ui.horizontal(|ui| {
+                                ui.label(format!("Loading {}", load.label));
+                                ui.add(egui::ProgressBar::new(load.progress.fraction()).desired_width(240.0).show_percentage());
+                                if ui.add_enabled(!load.progress.is_cancelled(), egui::Button::new("Cancel")).clicked() {
+                                    load.progress.cancel();
+                                }
+                            });
+                        }
+                        if let Some(plugin) = &mut self.plugin {
+                            ui.horizontal(|ui| {
+                                ui.spinner();
+                                ui.label(format!("Running plugin {}", plugin.name));
+                                if ui.button("Cancel").clicked() {
+                                    plugin.cancel();
+                                }
+                            });
                         }
                     });
-                } else if !self.paths.is_empty() {
-                    ui.heading(format!("{} paths loaded", self.paths.len()));
-                    egui::ScrollArea::both().show(ui, |ui| {
-                        let rect = ui.available_rect_before_wrap();
-                        ui.painter().rect(rect, 0.0, egui::Color32::BLACK, egui::Stroke::new(1.0, egui::Color32::WHITE));
-
-                        for path in &self.paths {
-                            let points: Vec<egui::Pos2> = path.iter()
-                                .map(|p| egui::Pos2::new(
-                                    rect.left() + (p[0] * self.scale + self.offset[0]) * rect.width() / 1200.0,
-                                    rect.top() + (p[1] * self.scale + self.offset[1]) * rect.height() / 800.0,
-                                ))
-                                .collect();
-                            if points.len() > 1 {
-                                ui.painter().add(egui::Shape::line(points.iter().cloned().collect::<Vec<_>>(), egui::Stroke::new(2.0, egui::Color32::GREEN)));
+                }
+
+                if !self.tabs.is_empty() {
+                    egui::TopBottomPanel::top("tabs").show(egui_ctx, |ui| {
+                        ui.horizontal(|ui| {
+                            let mut close = None;
+                            for (i, tab) in self.tabs.iter().enumerate() {
+                                let label = ui.selectable_label(i == self.active, tab.title());
+                                if let Some(path) = &tab.path {
+                                    label.clone().on_hover_text(path.display().to_string());
+                                }
+                                if label.clicked() {
+                                    self.active = i;
+                                }
+                                let close_clicked = ui.small_button("×").on_hover_text("Close (Ctrl+W)").clicked();
+                                if close_clicked || label.middle_clicked() {
+                                    close = Some(i);
+                                }
+                                ui.separator();
                             }
-                        }
+                            if let Some(i) = close {
+                                self.close_tab(i);
+                            }
+                        });
                     });
-                } else {
-                    ui.centered_and_justified(|ui| {
-                        ui.heading("VectorLab");
-                        ui.label("Press 📁 Open or 'O' to load SVG");
+                }
+
+                if let Some(tab) = self.tabs.get_mut(self.active) {
+                    if tab.search.is_some() {
+                        egui::TopBottomPanel::top("search").show(egui_ctx, |ui| search_bar(ui, tab));
+                    }
+
+                    if self.show_layers {
+                        egui::SidePanel::left("layers").resizable(true).default_width(220.0).show(egui_ctx, |ui| {
+                            ui.heading("Layers");
+                            ui.separator();
+                            for edit in layers_panel(ui, &tab.doc, &mut tab.selection) {
+                                tab.history.push(edit, &mut tab.doc);
+                            }
+                        });
+                    }
+
+                    if !tab.selection.is_empty() {
+                        egui::SidePanel::right("inspector").resizable(true).default_width(260.0).show(egui_ctx, |ui| {
+                            ui.heading("Inspector");
+                            ui.separator();
+                            for edit in inspector_panel(ui, &tab.doc, &tab.selection, self.settings.unit) {
+                                tab.history.push(edit, &mut tab.doc);
+                            }
+                        });
+                    }
+
+                    if self.show_source {
+                        egui::SidePanel::right("source").resizable(true).default_width(420.0).show(egui_ctx, |ui| {
+                            ui.heading("Source");
+                            ui.separator();
+                            source_panel(ui, tab);
+                        });
+                    }
+
+                    if self.show_check {
+                        egui::SidePanel::right("check").resizable(true).default_width(240.0).show(egui_ctx, |ui| {
+                            ui.heading("Geometry check");
+                            ui.separator();
+                            check_panel(ui, tab, self.settings.unit);
+                        });
+                    }
+
+                    if self.show_script {
+                        egui::TopBottomPanel::bottom("script").resizable(true).default_height(220.0).show(egui_ctx, |ui| {
+                            ui.heading("Script console");
+                            ui.separator();
+                            script_panel(ui, &mut self.script, tab);
+                        });
+                    }
+
+                    if self.show_palette {
+                        egui::SidePanel::right("palette").resizable(true).default_width(240.0).show(egui_ctx, |ui| {
+                            ui.heading("Colors");
+                            ui.separator();
+                            palette_panel(ui, tab);
+                        });
+                    }
+                }
+
+                self.stylus.update(egui_ctx);
+                if let Some(tab) = self.tabs.get(self.active) {
+                    let pressure = self.stylus.pressure;
+                    let settings = &mut self.settings;
+                    egui::TopBottomPanel::bottom("status").show(egui_ctx, |ui| {
+                        ui.horizontal(|ui| {
+                            let unit = settings.unit;
+                            match tab.cursor {
+                                Some([x, y]) => {
+                                    let [mx, my] = tab.doc.mm_per_unit();
+                                    ui.monospace(format!("x {:9.2}  y {:9.2}", x, y));
+                                    ui.separator();
+                                    let d = unit.decimals();
+                                    ui.monospace(format!("{:8.*}  {:8.*}", d, unit.from_mm(x * mx), d, unit.from_mm(y * my)));
+                                }
+                                None => {
+                                    ui.label("");
+                                }
+                            }
+                            ui.menu_button(unit.name(), |ui| {
+                                if unit_buttons(ui, &mut settings.unit) {
+                                    settings.save();
+                                    ui.close_menu();
+                                }
+                            })
+                            .response
+                            .on_hover_text("Units of the rulers, measurements and length fields");
+                            if let Some(pressure) = pressure {
+                                ui.separator();
+                                ui.monospace(format!("pen {:3.0}%", pressure * 100.0));
+                            }
+                        });
                     });
                 }
-            });
+            }
+
+            if self.presenting.is_some() {
+                let mut step = 0;
+                egui::CentralPanel::default().frame(egui::Frame::none()).show(egui_ctx, |ui| {
+                    let background = &self.settings.presentation_background;
+                    if let Some(show) = &mut self.slideshow {
+                        self.active = show.show(ui, &mut self.tabs, self.active, background, self.settings.curve_tolerance, &mut self.images);
+                    } else if let Some(tab) = self.tabs.get_mut(self.active) {
+                        step = presentation_view(ui, tab, background, self.settings.curve_tolerance, &mut self.images);
+                    } else {
+                        background.paint(ui.painter(), ui.max_rect());
+                    }
+                });
+                if step != 0 {
+                    self.step_sibling(step);
+                }
+            } else {
+                egui::CentralPanel::default().show(egui_ctx, |ui| {
+                    let rect = ui.available_rect_before_wrap();
+                    self.background.paint(ui.painter(), rect);
+
+                    if let Some(tab) = self.tabs.get_mut(self.active) {
+                        let (full, _) = ui.allocate_exact_size(ui.available_size(), egui::Sense::hover());
+                        let rect = if self.show_rulers { full.with_min_x(full.min.x + RULER_SIZE).with_min_y(full.min.y + RULER_SIZE) } else { full };
+                        let response = ui.interact(rect, ui.id().with("canvas"), egui::Sense::click_and_drag());
+                        if !(self.show_rulers && handle_guides(ui, tab, &self.settings.grid, rect, &response)) {
+                            match tab.tool {
+                                Tool::Measure => handle_measure(ui, tab, &self.settings.grid, rect, &response),
+                                Tool::Nodes => handle_nodes(ui, tab, &self.settings.grid, rect, &response),
+                                Tool::Transform => handle_transform(ui, tab, &self.settings.grid, rect, &response),
+                                Tool::Shapes => handle_shapes(ui, tab, &self.shapes, &self.settings.grid, rect, &response),
+                                Tool::Pencil => handle_pencil(ui, tab, &self.pencil, &self.shapes, self.stylus.pressure, rect, &response),
+                                Tool::Text => handle_text(ui, tab, &mut self.text, &self.settings.grid, rect, &response),
+                                Tool::Select => {}
+                            }
+                            tab.handle_view_input(ui, rect, &response, &self.settings.keys, points_per_mm(ui.ctx(), self.settings.screen_dpi), &mut self.initial_zoom);
+                        }
+                        tab.cursor = response.hover_pos().map(|p| tab.view.to_doc([p.x - rect.min.x, p.y - rect.min.y]));
+                        // smooth curves when zoomed in, fewer points when zoomed out
+                        tab.doc.reflatten(self.settings.curve_tolerance / tab.view.zoom);
+
+                        let painter = ui.painter_at(rect);
+                        let origin = rect.min.to_vec2();
+                        self.settings.grid.draw(&painter, rect, &tab.doc, &tab.view);
+                        if let Some(profile) = self.settings.active_profile() {
+                            tab.index.update(&tab.doc);
+                            draw_work_area(&painter, rect, &tab.doc, &tab.view, profile, tab.index.bbox());
+                        }
+                        if self.show_winding {
+                            self.show_winding = draw_winding(ui, &painter, rect, tab, &mut self.notifications);
+                        } else if let Some(mode) = &self.color_mode {
+                            draw_recolored(&painter, tab, mode, origin, &mut self.images);
+                        } else if tab.gpu.update(&tab.doc) {
+                            tab.gpu.paint(&painter, rect, &tab.view, &mut self.images);
+                        } else {
+                            tab.index.update(&tab.doc);
+                            draw_document(&painter, &tab.doc, &tab.index, &tab.view, origin, &mut self.images);
+                        }
+                        self.overlays.draw(&painter, rect, &tab.doc, &mut tab.index, &tab.view);
+                        draw_guides(&painter, rect, &tab.view, &tab.guides);
+                        let highlight = egui::Stroke::new(2.0, egui::Color32::from_rgb(0, 160, 255));
+                        for &id in &tab.selection {
+                            draw_outline(&painter, &tab.doc, id, &tab.view, origin, highlight);
+                        }
+                        match tab.tool {
+                            Tool::Nodes => draw_nodes(&painter, rect, tab),
+                            Tool::Transform => draw_transform_handles(&painter, rect, tab),
+                            Tool::Shapes => draw_shape_preview(&painter, rect, tab, &self.shapes),
+                            Tool::Pencil => draw_pencil(&painter, rect, tab),
+                            Tool::Text => text_editor(ui, tab, &self.text, &self.load_options, &mut self.notifications, rect),
+                            // a text left open is dropped with the tool
+                            _ => tab.text = None,
+                        }
+                        if let Some((ids, start)) = &tab.flash {
+                            let t = start.elapsed();
+                            if t < FLASH_DURATION {
+                                // three blinks
+                                let phase = t.as_secs_f32() / FLASH_DURATION.as_secs_f32() * 3.0 * std::f32::consts::TAU;
+                                let alpha = 0.5 - 0.5 * phase.cos();
+                                let flash = egui::Stroke::new(4.0, egui::Color32::from_rgb(255, 230, 0).gamma_multiply(alpha));
+                                for &id in ids {
+                                    draw_outline(&painter, &tab.doc, id, &tab.view, origin, flash);
+                                }
+                                ui.ctx().request_repaint();
+                            } else {
+                                tab.flash = None;
+                            }
+                        }
+
+                        let hovered = match response.hover_pos() {
+                            Some(pos) if !response.dragged() => {
+                                let p = pos - rect.min;
+                                tab.doc.hit_test(tab.view.to_doc([p.x, p.y]), 3.0 / tab.view.zoom)
+                            }
+                            _ => None,
+                        };
+                        if let Some(id) = hovered {
+                            let hover = egui::Stroke::new(1.5, egui::Color32::from_rgb(255, 170, 0));
+                            draw_outline(&painter, &tab.doc, id, &tab.view, origin, hover);
+                            response.on_hover_ui_at_pointer(|ui| hover_tooltip(ui, &tab.doc, id));
+                        }
+                        if let Some(m) = &tab.measurement {
+                            draw_measurement(ui, &painter, rect, &tab.doc, &tab.view, m, self.settings.unit);
+                        }
+                        if self.show_travel && tab.toolpaths.as_ref().is_none_or(|t| t.revision != tab.doc.revision) {
+                            tab.toolpaths = Some(Toolpaths::new(&tab.doc, &self.settings.gcode));
+                        }
+                        if let Some(toolpaths) = &tab.toolpaths {
+                            if !draw_toolpaths(ui, &painter, rect, &tab.doc, &tab.view, toolpaths) {
+                                tab.toolpaths = None;
+                                self.show_travel = false;
+                            }
+                        }
+                        if let Some(simulation) = &self.simulation {
+                            draw_simulation(&painter, rect, &tab.view, simulation);
+                        }
+                        let plot = self.plotter.as_ref().and_then(|p| p.running.as_ref()).map(|p| &p.job);
+                        let hpgl = self.hpgl.as_ref().and_then(|h| h.running.as_ref()).map(|h| &h.job);
+                        for job in plot.into_iter().chain(self.grbl.as_ref().and_then(|g| g.running.as_ref())).chain(hpgl) {
+                            if job.revision == tab.doc.revision {
+                                draw_job_progress(&painter, rect, &tab.view, job);
+                            }
+                        }
+                        if self.show_check {
+                            draw_defects(&painter, rect, &tab.view, &tab.check);
+                        }
+                        if let Some(operation) = &self.path_operation {
+                            draw_preview(&painter, rect, &tab.view, operation);
+                        }
+                        if self.show_rulers {
+                            draw_rulers(ui.painter(), rect, &tab.view, &tab.doc, self.settings.unit, tab.cursor);
+                        }
+                        if self.show_scale_bar {
+                            draw_scale_bar(ui.painter(), rect, &tab.view, &tab.doc, self.settings.unit, points_per_mm(ui.ctx(), self.settings.screen_dpi));
+                        }
+                        if self.show_minimap {
+                            draw_minimap(ui, tab, rect, &self.background, &mut self.images);
+                        }
+                    } else {
+                        ui.centered_and_justified(|ui| {
+                            ui.heading("VectorLab");
+                            ui.label("Press 📁 Open or 'O' to load SVG");
+                        });
+                    }
+                });
+            }
+
+            if let Some(url) = &mut self.url_dialog {
+                let mut open = false;
+                let mut cancel = false;
+                egui::Window::new("Open URL").collapsible(false).resizable(false).show(egui_ctx, |ui| {
+                    let edit = ui.add(egui::TextEdit::singleline(url).hint_text("https://…").desired_width(400.0));
+                    edit.request_focus();
+                    open = edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    ui.horizontal(|ui| {
+                        open |= ui.button("Open").clicked();
+                        cancel = ui.button("Cancel").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape));
+                    });
+                });
+                if open && remote::is_url(url.trim()) {
+                    let url = url.trim().to_string();
+                    self.url_dialog = None;
+                    self.open_url(&url);
+                } else if cancel {
+                    self.url_dialog = None;
+                }
+            }
+
+            if !self.hovered_files.is_empty() {
+                let rect = egui_ctx.screen_rect();
+                let painter = egui_ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("drop_overlay")));
+                painter.rect_filled(rect, 0.0, egui::Color32::from_black_alpha(160));
+                let n = self.hovered_files.len();
+                painter.text(
+                    rect.center(),
+                    egui::Align2::CENTER_CENTER,
+                    if n == 1 { "Drop to open".to_string() } else { format!("Drop to open {} files", n) },
+                    egui::FontId::proportional(24.0),
+                    egui::Color32::WHITE,
+                );
+            }
         });
 
+        // the native dialog is modal, so run it outside of the egui frame
+        if self.file_dialog_open {
+            self.file_dialog_open = false;
+            self.open_file_dialog();
+        }
+        if self.save_dialog_open {
+            self.save_dialog_open = false;
+            self.save_file_dialog();
+        }
+
         self.textures.append(output.textures_delta);
         self.egui_winit.handle_platform_output(&self.window, output.platform_output);
         self.paint_jobs = self.egui_ctx.tessellate(output.shapes, egui_ctx.tessellation_config());
@@ -211,6 +2121,49 @@ impl ApplicationHandler for VectorLabApp {
 
     fn window_event(&mut self, event_loop: &ActiveEventLoop, _window_id: winit::window::WindowId, event: WindowEvent) {
         self.egui_winit.on_window_event(&self.egui_ctx, &event);
+
+        // file drops must get through even while egui has the pointer
+        match &event {
+            WindowEvent::HoveredFile(path) => {
+                self.hovered_files.push(path.clone());
+                self.window.request_redraw();
+                return;
+            }
+            WindowEvent::HoveredFileCancelled => {
+                self.hovered_files.clear();
+                self.window.request_redraw();
+                return;
+            }
+            WindowEvent::DroppedFile(path) => {
+                self.file_dropped(path.clone());
+                return;
+            }
+            // egui-winit 0.28 only knows the gesture events by their winit 0.29 names
+            WindowEvent::PinchGesture { delta, .. } => {
+                if delta.is_finite() {
+                    self.egui_winit.egui_input_mut().events.push(egui::Event::Zoom((*delta as f32).exp()));
+                }
+                self.window.request_redraw();
+                return;
+            }
+            WindowEvent::PanGesture { delta, .. } => {
+                let delta = delta.to_logical::<f32>(self.window.scale_factor());
+                let input = self.egui_winit.egui_input_mut();
+                let modifiers = input.modifiers;
+                input.events.push(egui::Event::MouseWheel { unit: egui::MouseWheelUnit::Point, delta: egui::vec2(delta.x, delta.y), modifiers });
+                self.window.request_redraw();
+                return;
+            }
+            WindowEvent::DoubleTapGesture { .. } => {
+                if let Some(tab) = self.tab_mut() {
+                    tab.fit_pending = true;
+                }
+                self.window.request_redraw();
+                return;
+            }
+            _ => {}
+        }
+
         if self.egui_winit.egui_ctx().wants_pointer_input() || self.egui_winit.egui_ctx().wants_keyboard_input() {
             return;
         }
@@ -223,16 +2176,17 @@ impl ApplicationHandler for VectorLabApp {
             WindowEvent::RedrawRequested => {
                 let _ = self.render();
             }
-            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::CloseRequested => {
+                self.save_window_geometry();
+                event_loop.exit();
+            }
             WindowEvent::KeyboardInput { event: keyboard_input, .. } => {
-                if keyboard_input.state.is_pressed() && !keyboard_input.repeat {
-                    match keyboard_input.logical_key {
-                        Key::Named(NamedKey::KeyO) => {
-                            self.file_dialog_open = true;
-                            self.window.request_redraw();
-                        }
-                        Key::Named(NamedKey::Escape) => event_loop.exit(),
-                        _ => {}
+                if keyboard_input.state.is_pressed() && !keyboard_input.repeat && keyboard_input.logical_key == Key::Named(NamedKey::Escape) {
+                    if self.presenting.is_some() {
+                        self.set_presenting(false);
+                    } else {
+                        self.save_window_geometry();
+                        event_loop.exit();
                     }
                 }
             }
@@ -244,16 +2198,99 @@ impl ApplicationHandler for VectorLabApp {
         self.textures.remove();
         self.window.request_redraw();
     }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        // wake up regularly while loading, plotting or connected to a machine, for the progress
+        // bars and to pick up the results
+        if !self.loads.is_empty() || self.plotter.as_ref().is_some_and(|p| p.running.is_some()) || self.grbl.as_ref().is_some_and(GrblSender::connected) || self.hpgl.as_ref().is_some_and(|h| h.running.is_some()) || self.queue.as_ref().is_some_and(JobQueue::busy) || self.stipple.as_ref().is_some_and(StippleImage::busy) || self.plugin.is_some() {
+            event_loop.set_control_flow(ControlFlow::WaitUntil(Instant::now() + Duration::from_millis(100)));
+        } else if self.simulation.as_ref().is_some_and(PlotSimulation::playing) {
+            event_loop.set_control_flow(ControlFlow::WaitUntil(Instant::now() + Duration::from_millis(16)));
+        } else if let Some(show) = &self.slideshow {
+            event_loop.set_control_flow(ControlFlow::WaitUntil(show.next_frame()));
+        } else {
+            event_loop.set_control_flow(ControlFlow::Wait);
+        }
+    }
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    env_logger::init();
+    init_logging();
+    let cli = Cli::parse();
+
+    if let Some(Command::Render { input, output, width, height, bg, font_dirs }) = &cli.command {
+        let fonts = LoadOptions::with_font_dirs(font_dirs);
+        let (w, h) = vectorlab_core::render_png(input, output, *width, *height, *bg, &fonts)?;
+        println!("{} -> {} ({}x{})", input.display(), output.display(), w, h);
+        return Ok(());
+    }
+    if let Some(Command::Pdf { inputs, output, font_dirs }) = &cli.command {
+        let fonts = LoadOptions::with_font_dirs(font_dirs);
+        let mut docs = vec![];
+        for input in inputs {
+            docs.push(vectorlab_core::load_file(input, &fonts).map_err(|e| format!("{}: {}", input.display(), e))?);
+        }
+        vectorlab_core::save_pdf(&docs, output)?;
+        println!("{} pages -> {}", docs.len(), output.display());
+        return Ok(());
+    }
+    if let Some(Command::Simplify { inputs, tolerance, method, output }) = &cli.command {
+        if output.is_some() && inputs.len() > 1 {
+            return Err("--output only works with a single input".into());
+        }
+        for input in inputs {
+            let mut doc = vectorlab_core::load_file(input, &LoadOptions::default()).map_err(|e| format!("{}: {}", input.display(), e))?;
+            let mm = doc.mm_per_unit();
+            let ids = doc.descendants(doc.root);
+            let simplified = vectorlab_core::simplify_paths(&doc, &ids, tolerance * 2.0 / (mm[0] + mm[1]), *method);
+            let (before, after) = simplified.iter().fold((0, 0), |(b, a), s| (b + s.before, a + s.after));
+            for s in &simplified {
+                if let Some(path) = doc.get_mut(s.id).as_path_mut() {
+                    path.set_segments(s.segments.clone());
+                }
+            }
+            let output = output.clone().unwrap_or_else(|| {
+                let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+                input.with_file_name(format!("{}-simplified.svg", stem))
+            });
+            vectorlab_core::save_file(&doc, &output)?;
+            println!("{} -> {} ({} points down to {})", input.display(), output.display(), before, after);
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Run { script, input, output, font_dirs }) = &cli.command {
+        let code = std::fs::read_to_string(script).map_err(|e| format!("{}: {}", script.display(), e))?;
+        let doc = vectorlab_core::load_file(input, &LoadOptions::with_font_dirs(font_dirs)).map_err(|e| format!("{}: {}", input.display(), e))?;
+        let run = run_script(&code, doc, vec![], 0);
+        for line in &run.output {
+            println!("{}", line);
+        }
+        if let Some(e) = run.error {
+            return Err(format!("{}: {}", script.display(), e).into());
+        }
+        if let Some(output) = output {
+            vectorlab_core::save_file(&run.doc, output)?;
+            println!("{} -> {}", input.display(), output.display());
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Ctl { command }) = &cli.command {
+        return control::ctl(command);
+    }
 
     let event_loop = EventLoop::new()?;
 
-    let window_attrs = WindowAttributes::default()
+    let settings = Settings::load();
+    let [width, height] = settings.window_size.unwrap_or([1200.0, 800.0]);
+    let mut window_attrs = WindowAttributes::default()
         .with_title("VectorLab - SVG Viewer")
-        .with_inner_size(LogicalSize::new(1200.0, 800.0));
+        .with_inner_size(LogicalSize::new(width, height))
+        .with_maximized(cli.maximized || settings.window_maximized);
+    if let Some([x, y]) = settings.window_position {
+        window_attrs = window_attrs.with_position(PhysicalPosition::new(x, y));
+    }
 
     let window = event_loop.create_window(window_attrs)?;
 
@@ -261,7 +2298,36 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .with_window_attributes(window.window_attributes_dpi())
         .build(&event_loop, glutin_winit::DisplayRequestTemplate::default(), |configs| configs.next().unwrap())?;
 
-    let mut app = VectorLabApp::new(&window, &gl_display.0)?;
+    let mut app = VectorLabApp::new(&window, &gl_display.0, settings)?;
+    app.background = cli.bg.unwrap_or(app.settings.background);
+    app.initial_zoom = cli.zoom;
+    if cli.listen {
+        match ControlServer::start(event_loop.create_proxy()) {
+            Ok(server) => app.control = Some(server),
+            Err(e) => app.notifications.error("Not listening for vectorlab ctl", e),
+        }
+    }
+    app.load_options = LoadOptions::with_font_dirs(&cli.font_dirs);
+    app.text = TextSettings::new(&app.load_options.fontdb);
+    app.load_options.strict = cli.strict || app.settings.strict_parsing;
+    app.load_options.dpi = app.settings.dpi;
+    let mut playlist = vec![];
+    for location in &cli.files {
+        let path = Path::new(location);
+        if path.is_dir() {
+            for path in svgs_in(path) {
+                app.load_svg(&path.to_string_lossy());
+                playlist.push(path);
+            }
+        } else {
+            app.open_location(location);
+            playlist.push(path.to_path_buf());
+        }
+    }
+    if let Some(seconds) = cli.slideshow {
+        let crossfade = cli.crossfade.unwrap_or(app.settings.slideshow_crossfade);
+        app.start_slideshow(playlist, seconds, crossfade);
+    }
 
     event_loop.run_app(&mut app)?;
     Ok(())
@@ -0,0 +1,62 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// Reports changes to the open files. The parent directories are watched rather than the
+/// files themselves, since many editors save by writing a new file and renaming it over the old one.
+pub struct FileWatcher {
+    watcher: RecommendedWatcher,
+    events: Receiver<PathBuf>,
+    files: HashSet<PathBuf>,
+    dirs: HashSet<PathBuf>,
+}
+
+impl FileWatcher {
+    pub fn new() -> notify::Result<Self> {
+        let (tx, events) = channel();
+        let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            if let Ok(event) = res {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    for path in event.paths {
+                        let _ = tx.send(path);
+                    }
+                }
+            }
+        })?;
+        Ok(Self { watcher, events, files: HashSet::new(), dirs: HashSet::new() })
+    }
+
+    /// Watch exactly `paths`, forgetting files that are no longer open.
+    pub fn sync<'a>(&mut self, paths: impl Iterator<Item = &'a PathBuf>) {
+        let files: HashSet<PathBuf> = paths.map(|p| fs::canonicalize(p).unwrap_or_else(|_| p.clone())).collect();
+        if files == self.files {
+            return;
+        }
+        let dirs: HashSet<PathBuf> = files.iter().filter_map(|p| p.parent().map(|d| d.to_path_buf())).collect();
+        for dir in self.dirs.difference(&dirs) {
+            let _ = self.watcher.unwatch(dir);
+        }
+        for dir in dirs.difference(&self.dirs) {
+            if let Err(e) = self.watcher.watch(dir, RecursiveMode::NonRecursive) {
+                log::error!("Cannot watch {} for changes: {}", dir.display(), e);
+            }
+        }
+        self.files = files;
+        self.dirs = dirs;
+    }
+
+    /// Watched files that changed since the last call, each reported once.
+    pub fn changed_files(&self) -> Vec<PathBuf> {
+        let mut changed = vec![];
+        for path in self.events.try_iter() {
+            let path = fs::canonicalize(&path).unwrap_or(path);
+            if self.files.contains(&path) && !changed.contains(&path) {
+                changed.push(path);
+            }
+        }
+        changed
+    }
+}
@@ -0,0 +1,102 @@
+use vectorlab_core::usvg::Transform;
+use vectorlab_core::{winding_regions, Color, ElementId, FillRule, FlattenedPath, Paint, Segment, Style};
+
+use crate::canvas::{draw_outline, draw_path, to_egui};
+use crate::notifications::Notifications;
+use crate::tab::Tab;
+
+/// The filled paths of a tab's document split up by winding number, built again when it
+/// changes.
+#[derive(Default)]
+pub struct WindingView {
+    // document revision the regions were made for
+    revision: Option<u64>,
+    // each filled path with a path filled in its region's color per winding number
+    paths: Vec<(ElementId, Transform, Vec<(i32, FlattenedPath)>)>,
+}
+
+impl WindingView {
+    fn update(&mut self, tab: &Tab) {
+        if self.revision == Some(tab.doc.revision) {
+            return;
+        }
+        self.paths.clear();
+        tab.doc.walk(|id, element, ts, _| {
+            let Some(path) = element.as_path().filter(|p| p.style.fill.is_some()) else { return };
+            let mut regions = vec![];
+            for region in winding_regions(&path.contours, path.tolerance.max(1e-4)) {
+                let mut segments = vec![];
+                for contour in &region.contours {
+                    segments.push(Segment::MoveTo(contour.points[0]));
+                    segments.extend(contour.points[1..].iter().map(|&p| Segment::LineTo(p)));
+                    segments.push(Segment::Close);
+                }
+                let mut filled = path.clone();
+                filled.style = Style { fill: Some(Paint::Solid(winding_color(region.winding))), fill_rule: FillRule::NonZero, stroke: None, ..path.style.clone() };
+                filled.clip = None;
+                filled.set_segments(segments);
+                regions.push((region.winding, filled));
+            }
+            self.paths.push((id, ts, regions));
+        });
+        self.revision = Some(tab.doc.revision);
+    }
+}
+
+/// Blue for areas the outlines go around clockwise on screen, orange the other way round,
+/// darker the more often.
+fn winding_color(winding: i32) -> Color {
+    let (r, g, b) = match winding {
+        1 => (140, 180, 255),
+        2 => (60, 110, 230),
+        3 => (30, 60, 170),
+        4.. => (15, 25, 100),
+        -1 => (255, 190, 130),
+        -2 => (235, 120, 50),
+        -3 => (180, 70, 20),
+        _ => (110, 35, 10),
+    };
+    Color { r, g, b, a: 255 }
+}
+
+/// Draw the filled paths in colors by winding number instead of their own, with a legend.
+/// Nonzero fills all the colored area, even-odd leaves out the even numbers. Returns false
+/// when the legend was closed.
+pub fn draw_winding(ui: &egui::Ui, painter: &egui::Painter, canvas: egui::Rect, tab: &mut Tab, notifications: &mut Notifications) -> bool {
+    let mut view = std::mem::take(&mut tab.winding);
+    view.update(tab);
+    let origin = canvas.min.to_vec2();
+    let mut windings = vec![];
+    for (id, ts, regions) in &view.paths {
+        for (winding, path) in regions {
+            draw_path(painter, path, ts, 1.0, &tab.view, origin);
+            windings.push(*winding);
+        }
+        draw_outline(painter, &tab.doc, *id, &tab.view, origin, egui::Stroke::new(1.0, egui::Color32::from_gray(60)));
+    }
+    windings.sort_unstable();
+    windings.dedup();
+    tab.winding = view;
+
+    let mut open = true;
+    egui::Area::new(ui.id().with("winding_legend")).fixed_pos(canvas.min + egui::vec2(8.0, 8.0)).show(ui.ctx(), |ui| {
+        egui::Frame::popup(ui.style()).show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Winding");
+                for &winding in &windings {
+                    let text = egui::RichText::new(format!("{:+}", winding)).background_color(to_egui(winding_color(winding)));
+                    let text = if winding % 2 == 0 { text.strikethrough() } else { text };
+                    ui.label(text.color(egui::Color32::BLACK));
+                }
+                open = !ui.small_button("✕").on_hover_text("Back to the normal view").clicked();
+            });
+            ui.label("Even-odd leaves out the even, struck out numbers");
+            let convert = egui::Button::new("Convert selection to even-odd");
+            let hover = "Fill the selected paths even-odd without changing what they cover";
+            if ui.add_enabled(!tab.selection.is_empty(), convert).on_hover_text(hover).clicked() && !tab.even_odd_selection() {
+                notifications.warnings("Convert to even-odd", &["None of the selected paths is filled nonzero"]);
+            }
+        });
+    });
+    open
+}
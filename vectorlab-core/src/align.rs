@@ -0,0 +1,135 @@
+use resvg::usvg::Transform;
+
+use crate::document::{transform_point, Document, ElementId};
+use crate::edit::{EditCommand, EditGroup, SetTransforms};
+
+/// Which edges or middles to line up, see [`align_elements`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    CenterX,
+    Right,
+    Top,
+    CenterY,
+    Bottom,
+}
+
+impl Align {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Left => "Align left",
+            Self::CenterX => "Center horizontally",
+            Self::Right => "Align right",
+            Self::Top => "Align top",
+            Self::CenterY => "Center vertically",
+            Self::Bottom => "Align bottom",
+        }
+    }
+
+    fn axis(self) -> usize {
+        match self {
+            Self::Left | Self::CenterX | Self::Right => 0,
+            _ => 1,
+        }
+    }
+
+    /// Where on a box [x0, y0, x1, y1] along `axis` it lines up.
+    fn position(self, b: [f32; 4]) -> f32 {
+        let axis = self.axis();
+        match self {
+            Self::Left | Self::Top => b[axis],
+            Self::Right | Self::Bottom => b[axis + 2],
+            _ => (b[axis] + b[axis + 2]) * 0.5,
+        }
+    }
+}
+
+impl Document {
+    /// The page in document coordinates, None for documents without a size.
+    pub fn page_bbox(&self) -> Option<[f32; 4]> {
+        if self.size[0] <= 0.0 || self.size[1] <= 0.0 {
+            return None;
+        }
+        let to_doc = self.viewport_transform().invert()?;
+        let a = transform_point(&to_doc, [0.0, 0.0]);
+        let b = transform_point(&to_doc, self.size);
+        Some([a[0].min(b[0]), a[1].min(b[1]), a[0].max(b[0]), a[1].max(b[1])])
+    }
+
+    /// `ids` without those inside others among them, with their boxes.
+    pub(crate) fn outermost_bboxes(&self, ids: &[ElementId]) -> Vec<(ElementId, [f32; 4])> {
+        let inside_another = |id: ElementId| {
+            let mut parent = self.get(id).parent;
+            while let Some(p) = parent {
+                if ids.contains(&p) {
+                    return true;
+                }
+                parent = self.get(p).parent;
+            }
+            false
+        };
+        let mut out: Vec<(ElementId, [f32; 4])> = vec![];
+        for &id in ids {
+            if inside_another(id) || out.iter().any(|&(o, _)| o == id) {
+                continue;
+            }
+            if let Some(b) = self.subtree_bbox(id) {
+                out.push((id, b));
+            }
+        }
+        out
+    }
+}
+
+/// Move the elements among `ids` so their edges or middles line up with those of `to`, the
+/// page for example, or of the box around all of them. None if nothing moves.
+pub fn align_elements(doc: &Document, ids: &[ElementId], align: Align, to: Option<[f32; 4]>) -> Option<Box<dyn EditCommand>> {
+    let boxes = doc.outermost_bboxes(ids);
+    let to = to.or_else(|| union(boxes.iter().map(|&(_, b)| b)))?;
+    let target = align.position(to);
+    let moves = boxes.iter().map(|&(id, b)| (id, target - align.position(b))).collect();
+    shift(doc, align.axis(), moves, align.name())
+}
+
+/// Spread the elements among `ids` out along x (`axis` 0) or y (1) with equal gaps between
+/// them. Within `to`, the first and last go to its edges, otherwise they stay where they are.
+/// None with too few elements.
+pub fn distribute_elements(doc: &Document, ids: &[ElementId], axis: usize, to: Option<[f32; 4]>) -> Option<Box<dyn EditCommand>> {
+    let mut boxes = doc.outermost_bboxes(ids);
+    if boxes.len() < if to.is_some() { 2 } else { 3 } {
+        return None;
+    }
+    boxes.sort_by(|a, b| (a.1[axis] + a.1[axis + 2]).total_cmp(&(b.1[axis] + b.1[axis + 2])));
+    let span = to.or_else(|| union(boxes.iter().map(|&(_, b)| b)))?;
+    let sizes: f32 = boxes.iter().map(|(_, b)| b[axis + 2] - b[axis]).sum();
+    let gap = (span[axis + 2] - span[axis] - sizes) / (boxes.len() - 1) as f32;
+    let mut at = span[axis];
+    let mut moves = vec![];
+    for &(id, b) in &boxes {
+        moves.push((id, at - b[axis]));
+        at += b[axis + 2] - b[axis] + gap;
+    }
+    let name = if axis == 0 { "Distribute horizontally" } else { "Distribute vertically" };
+    shift(doc, axis, moves, name)
+}
+
+/// Move each element by its distance along `axis`, as one undo step.
+fn shift(doc: &Document, axis: usize, moves: Vec<(ElementId, f32)>, name: &str) -> Option<Box<dyn EditCommand>> {
+    let mut changes = vec![];
+    for (id, d) in moves {
+        if d.abs() <= f32::EPSILON * 16.0 {
+            continue;
+        }
+        let ts = if axis == 0 { Transform::from_translate(d, 0.0) } else { Transform::from_translate(0.0, d) };
+        changes.extend(SetTransforms::new(doc, &[id], ts).changes);
+    }
+    if changes.is_empty() {
+        return None;
+    }
+    Some(Box::new(EditGroup::new(name, vec![Box::new(SetTransforms { changes })])))
+}
+
+pub(crate) fn union(mut boxes: impl Iterator<Item = [f32; 4]>) -> Option<[f32; 4]> {
+    let first = boxes.next()?;
+    Some(boxes.fold(first, |a, b| [a[0].min(b[0]), a[1].min(b[1]), a[2].max(b[2]), a[3].max(b[3])]))
+}
@@ -0,0 +1,91 @@
+use resvg::usvg::Transform;
+
+use crate::align::union;
+use crate::document::{transform_point, Document, ElementId};
+use crate::edit::{AddCopies, EditCommand, EditGroup};
+
+/// Where the copies of an array go, lengths in document units.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArrayLayout {
+    /// `rows` × `columns` places, the original top left, `gap` between the boxes of the copies
+    Grid { rows: u32, columns: u32, gap: [f32; 2] },
+    /// `count` places around `center`, the original included, spread over `sweep` degrees.
+    /// With `rotate` the copies turn along, otherwise they only move.
+    Polar { count: u32, center: [f32; 2], sweep: f32, rotate: bool },
+}
+
+impl ArrayLayout {
+    /// A transform in document coordinates for each copy of something with the box `bbox`.
+    pub fn placements(&self, bbox: [f32; 4]) -> Vec<Transform> {
+        match *self {
+            Self::Grid { rows, columns, gap } => {
+                let step = [bbox[2] - bbox[0] + gap[0], bbox[3] - bbox[1] + gap[1]];
+                let places = (0..rows).flat_map(|row| (0..columns).map(move |column| (row, column)));
+                places.skip(1).map(|(row, column)| Transform::from_translate(column as f32 * step[0], row as f32 * step[1])).collect()
+            }
+            Self::Polar { count, center, sweep, rotate } => {
+                if count < 2 {
+                    return vec![];
+                }
+                // a full turn would put the last copy on the first
+                let full = (sweep.abs() - 360.0).abs() < 0.01;
+                let step = sweep / if full { count } else { count - 1 } as f32;
+                let middle = [(bbox[0] + bbox[2]) * 0.5, (bbox[1] + bbox[3]) * 0.5];
+                (1..count)
+                    .map(|i| {
+                        let turn = Transform::from_rotate_at(step * i as f32, center[0], center[1]);
+                        if rotate {
+                            turn
+                        } else {
+                            let to = transform_point(&turn, middle);
+                            Transform::from_translate(to[0] - middle[0], to[1] - middle[1])
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// The elements among `ids` to copy, the children of the root in place of it, with the box
+/// around them all.
+fn targets(doc: &Document, ids: &[ElementId]) -> (Vec<ElementId>, Option<[f32; 4]>) {
+    let ids: Vec<ElementId> = ids.iter().flat_map(|&id| if id == doc.root { doc.get(id).children.clone() } else { vec![id] }).collect();
+    let boxes = doc.outermost_bboxes(&ids);
+    let bbox = union(boxes.iter().map(|&(_, b)| b));
+    (boxes.into_iter().map(|(id, _)| id).collect(), bbox)
+}
+
+/// Copy the elements among `ids` moved by `offset`. None if there is nothing to copy.
+pub fn duplicate_elements(doc: &Document, ids: &[ElementId], offset: [f32; 2]) -> Option<Box<dyn EditCommand>> {
+    let (ids, _) = targets(doc, ids);
+    if ids.is_empty() {
+        return None;
+    }
+    let ts = Transform::from_translate(offset[0], offset[1]);
+    Some(Box::new(AddCopies::new(ids.into_iter().map(|id| (id, vec![ts])).collect())))
+}
+
+/// Copies of the elements among `ids`, as one block, laid out by `layout`. None if there is
+/// nothing to copy or no copies to make.
+pub fn array_elements(doc: &Document, ids: &[ElementId], layout: ArrayLayout) -> Option<Box<dyn EditCommand>> {
+    let (ids, bbox) = targets(doc, ids);
+    let placements = layout.placements(bbox?);
+    if placements.is_empty() {
+        return None;
+    }
+    let name = match layout {
+        ArrayLayout::Grid { .. } => "Grid array",
+        ArrayLayout::Polar { .. } => "Polar array",
+    };
+    let copies = AddCopies::new(ids.into_iter().map(|id| (id, placements.clone())).collect());
+    Some(Box::new(EditGroup::new(name, vec![Box::new(copies)])))
+}
+
+/// The box around the elements among `ids` where each copy of `layout` would put it, as
+/// closed outlines in document coordinates.
+pub fn array_outlines(doc: &Document, ids: &[ElementId], layout: ArrayLayout) -> Vec<Vec<[f32; 2]>> {
+    let Some(b) = targets(doc, ids).1 else { return vec![] };
+    let corners = [[b[0], b[1]], [b[2], b[1]], [b[2], b[3]], [b[0], b[3]], [b[0], b[1]]];
+    layout.placements(b).iter().map(|ts| corners.iter().map(|&p| transform_point(ts, p)).collect()).collect()
+}
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use resvg::usvg::Transform;
+
+use crate::document::{transform_point, transform_scale, Document, ElementId, ElementKind, FlattenedPath, Segment};
+use crate::style::{Gradient, Paint};
+
+impl FlattenedPath {
+    /// The path with `ts` applied to its coordinates, gradients, clip and stroke width, so it
+    /// looks the same without the transform. Strokes scaled differently along x and y get the
+    /// average width. The tolerance scales along, so it is flattened as finely as before.
+    pub fn transformed(&self, ts: &Transform) -> FlattenedPath {
+        let scale = transform_scale(ts);
+        let map = |p: [f32; 2]| transform_point(ts, p);
+        let segments = self
+            .segments
+            .iter()
+            .map(|segment| match *segment {
+                Segment::MoveTo(p) => Segment::MoveTo(map(p)),
+                Segment::LineTo(p) => Segment::LineTo(map(p)),
+                Segment::QuadTo(c, p) => Segment::QuadTo(map(c), map(p)),
+                Segment::CubicTo(c1, c2, p) => Segment::CubicTo(map(c1), map(c2), map(p)),
+                Segment::Close => Segment::Close,
+            })
+            .collect();
+        let mut path = self.clone();
+        path.style.stroke_width *= scale;
+        // gradients map path coordinates, which now come out of `ts`
+        let from_new = ts.invert().unwrap_or_default();
+        for paint in [&mut path.style.fill, &mut path.style.stroke].into_iter().flatten() {
+            if let Paint::Gradient(gradient) = paint {
+                *gradient = Arc::new(Gradient { to_gradient: gradient.to_gradient.pre_concat(from_new), ..(**gradient).clone() });
+            }
+        }
+        path.clip = self.clip.as_ref().map(|clip| Arc::new(clip.transformed(ts)));
+        path.tolerance = self.tolerance * scale.max(f32::EPSILON);
+        path.set_segments(segments);
+        path
+    }
+}
+
+impl Document {
+    /// Bake all transforms into the coordinates and dissolve the groups, leaving the paths,
+    /// texts and images directly below the root in paint order. Group opacities multiply into
+    /// what was in them, hidden groups hide it. Images keep a transform of their own, a
+    /// rectangle cannot take a rotation. See `FlattenDocument` for the undoable edit.
+    pub fn flatten(&mut self) {
+        let root = self.root;
+        let mut leaves = vec![];
+        let ts = self.get(root).transform;
+        for child in self.get(root).children.clone() {
+            self.collect_leaves(child, ts, 1.0, true, &mut leaves);
+        }
+        for &(id, ts, opacity, visible) in &leaves {
+            self.bake(id, ts);
+            let element = self.get_mut(id);
+            element.parent = Some(root);
+            element.opacity = opacity;
+            element.visible = visible;
+        }
+        let root = self.get_mut(root);
+        root.transform = Transform::identity();
+        root.children = leaves.into_iter().map(|(id, ..)| id).collect();
+        self.touch();
+    }
+
+    /// Everything below `id` that is not a group, with the transform, opacity and
+    /// visibility it ends up with.
+    fn collect_leaves(&self, id: ElementId, parent_ts: Transform, parent_opacity: f32, parent_visible: bool, out: &mut Vec<(ElementId, Transform, f32, bool)>) {
+        let element = self.get(id);
+        let ts = parent_ts.pre_concat(element.transform);
+        let opacity = parent_opacity * element.opacity;
+        let visible = parent_visible && element.visible;
+        match element.kind {
+            ElementKind::Group => {
+                for &child in &element.children {
+                    self.collect_leaves(child, ts, opacity, visible, out);
+                }
+            }
+            _ => out.push((id, ts, opacity, visible)),
+        }
+    }
+
+    /// Apply `ts` to `id` and what is below it, e.g. the glyphs of a text.
+    fn bake(&mut self, id: ElementId, ts: Transform) {
+        let element = self.get_mut(id);
+        match &mut element.kind {
+            ElementKind::Path(path) => *path = path.transformed(&ts),
+            ElementKind::Image { .. } => {
+                element.transform = ts;
+                return;
+            }
+            _ => {}
+        }
+        element.transform = Transform::identity();
+        for child in self.get(id).children.clone() {
+            let child_ts = ts.pre_concat(self.get(child).transform);
+            self.bake(child, child_ts);
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::document::{transform_point, transform_scale, Contour, Document, Element, ElementId, ElementKind, Segment};
+use crate::edit::{AddElement, DeleteElements, EditCommand, EditGroup};
+use crate::hit::fill_contains;
+use crate::loader::flatten_segments;
+use crate::style::FillRule;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BooleanOp {
+    /// the area either one covers
+    Union,
+    /// the area both cover
+    Intersection,
+    /// the area the first covers and the second does not
+    Difference,
+    /// the area exactly one of them covers
+    Xor,
+}
+
+impl BooleanOp {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Union => "Union",
+            Self::Intersection => "Intersection",
+            Self::Difference => "Difference",
+            Self::Xor => "Exclusion",
+        }
+    }
+
+    fn apply(self, a: bool, b: bool) -> bool {
+        match self {
+            Self::Union => a || b,
+            Self::Intersection => a && b,
+            Self::Difference => a && !b,
+            Self::Xor => a != b,
+        }
+    }
+}
+
+/// What `boolean_paths` did.
+pub struct BooleanResult {
+    pub edit: Box<dyn EditCommand>,
+    /// outlines of the new path, in document coordinates
+    pub contours: Vec<Contour>,
+    /// paths that went into it
+    pub paths: usize,
+}
+
+/// Combine the fills of the paths among `ids` into one new path, which takes the place and
+/// the style of the bottom one, and delete them. Union, intersection and exclusion go through
+/// them from the bottom up, the difference is the bottom one without all the others, like
+/// cutting a stencil. Every subpath counts as closed. None with fewer than two paths or when
+/// nothing is left.
+pub fn boolean_paths(doc: &Document, ids: &[ElementId], op: BooleanOp) -> Option<BooleanResult> {
+    let wanted: HashSet<ElementId> = ids.iter().copied().collect();
+    let mut paths = vec![];
+    doc.walk(|id, element, ts, _| {
+        if element.as_path().is_some() && wanted.contains(&id) {
+            paths.push((id, ts));
+        }
+    });
+    if paths.len() < 2 {
+        return None;
+    }
+    // the finest of the paths' tolerances, on the page
+    let tolerance = paths
+        .iter()
+        .filter_map(|(id, ts)| doc.get(*id).as_path().map(|p| p.tolerance * transform_scale(ts)))
+        .fold(f32::INFINITY, f32::min)
+        .max(1e-4);
+    let shapes: Vec<(Vec<Contour>, FillRule)> = paths
+        .iter()
+        .filter_map(|&(id, ts)| {
+            let path = doc.get(id).as_path()?;
+            let contours = flatten_segments(&path.segments, tolerance / transform_scale(&ts).max(f32::EPSILON))
+                .into_iter()
+                .map(|c| Contour { points: c.points.iter().map(|&p| transform_point(&ts, p)).collect(), closed: true })
+                .collect();
+            Some((contours, path.style.fill_rule))
+        })
+        .collect();
+    let (first, rest) = shapes.split_first()?;
+    let mut result = first.0.clone();
+    let mut rule = first.1;
+    for (contours, other) in rest {
+        result = boolean_contours(&result, rule, contours, *other, op, tolerance);
+        // the pieces run with the area on their left, so the result is nonzero
+        rule = FillRule::NonZero;
+    }
+    if result.is_empty() {
+        return None;
+    }
+
+    let (bottom, ts) = paths[0];
+    let path = doc.get(bottom).as_path()?;
+    let to_local = ts.invert().unwrap_or_default();
+    let mut segments = vec![];
+    for contour in &result {
+        segments.push(Segment::MoveTo(transform_point(&to_local, contour.points[0])));
+        segments.extend(contour.points[1..].iter().map(|&p| Segment::LineTo(transform_point(&to_local, p))));
+        segments.push(Segment::Close);
+    }
+    let mut combined = path.clone();
+    combined.style.fill_rule = FillRule::NonZero;
+    combined.set_segments(segments);
+    let mut element = Element::new(ElementKind::Path(combined));
+    element.transform = doc.get(bottom).transform;
+    element.opacity = doc.get(bottom).opacity;
+    let commands: Vec<Box<dyn EditCommand>> = vec![Box::new(AddElement::after(doc, bottom, element)), Box::new(DeleteElements::new(paths.iter().map(|&(id, _)| id).collect()))];
+    Some(BooleanResult { edit: Box::new(EditGroup::new(op.name(), commands)), contours: result, paths: paths.len() })
+}
+
+/// The outline of the area `op` makes of what `a` fills under `a_rule` and `b` under
+/// `b_rule`, every contour taken as closed. Outlines run with the area on their left, holes
+/// the other way round, so it fills the same under either rule. Slivers smaller than
+/// `tolerance` squared are left out.
+pub fn boolean_contours(a: &[Contour], a_rule: FillRule, b: &[Contour], b_rule: FillRule, op: BooleanOp, tolerance: f32) -> Vec<Contour> {
+    let loops: Vec<Vec<[f32; 2]>> = a.iter().chain(b).map(|c| dedup(&c.points, true)).filter(|l| l.len() > 2).collect();
+    let inside = |p: [f32; 2]| op.apply(fill_contains(a, a_rule, p), fill_contains(b, b_rule, p));
+    let nudge = tolerance * 0.05;
+    let key = |p: [f32; 2]| (p[0].to_bits(), p[1].to_bits());
+    let mut seen = HashSet::new();
+    let mut pieces = vec![];
+    for [p, q] in split_edges(&loops) {
+        // the boundary of the result where inside and outside differ across the piece
+        let length = distance2(p, q).sqrt().max(f32::EPSILON);
+        let n = [-(q[1] - p[1]) / length * nudge, (q[0] - p[0]) / length * nudge];
+        let middle = [(p[0] + q[0]) * 0.5, (p[1] + q[1]) * 0.5];
+        let piece = match (inside([middle[0] + n[0], middle[1] + n[1]]), inside([middle[0] - n[0], middle[1] - n[1]])) {
+            (true, false) => [p, q],
+            (false, true) => [q, p],
+            _ => continue,
+        };
+        // edges both shapes share only once
+        if seen.insert((key(piece[0]), key(piece[1]))) {
+            pieces.push(piece);
+        }
+    }
+    chain_pieces(&pieces, tolerance * tolerance)
+}
+
+/// The edges of the closed `loops` cut wherever another one crosses them or starts or ends
+/// on them. Each cut point is computed once, so the pieces meeting there share the exact same
+/// point.
+pub(crate) fn split_edges(loops: &[Vec<[f32; 2]>]) -> Vec<[[f32; 2]; 2]> {
+    let edges: Vec<[[f32; 2]; 2]> = loops.iter().flat_map(|l| (0..l.len()).map(move |i| [l[i], l[(i + 1) % l.len()]])).collect();
+    let mut cuts: Vec<Vec<(f32, [f32; 2])>> = vec![vec![]; edges.len()];
+    let mut order: Vec<usize> = (0..edges.len()).collect();
+    let min_x = |e: &[[f32; 2]; 2]| e[0][0].min(e[1][0]);
+    let max_x = |e: &[[f32; 2]; 2]| e[0][0].max(e[1][0]);
+    order.sort_by(|&i, &j| min_x(&edges[i]).total_cmp(&min_x(&edges[j])));
+    for (k, &i) in order.iter().enumerate() {
+        for &j in &order[k + 1..] {
+            if min_x(&edges[j]) > max_x(&edges[i]) {
+                break;
+            }
+            if let Some((t, u, x)) = crossing(edges[i], edges[j]) {
+                cuts[i].push((t, x));
+                cuts[j].push((u, x));
+            } else {
+                // running along each other, each cut where the other one ends
+                for (this, other) in [(i, j), (j, i)] {
+                    for end in edges[other] {
+                        if let Some(t) = along(edges[this], end) {
+                            cuts[this].push((t, end));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    let mut pieces = vec![];
+    for (edge, mut cut) in edges.iter().zip(cuts) {
+        cut.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut from = edge[0];
+        for to in cut.into_iter().map(|(_, x)| x).chain([edge[1]]) {
+            if from != to {
+                pieces.push([from, to]);
+            }
+            from = to;
+        }
+    }
+    pieces
+}
+
+/// `pieces` that go on where another one ends chained into closed loops, leaving out loose
+/// ends and loops smaller than `min_area`.
+pub(crate) fn chain_pieces(pieces: &[[[f32; 2]; 2]], min_area: f32) -> Vec<Contour> {
+    let key = |p: [f32; 2]| (p[0].to_bits(), p[1].to_bits());
+    let mut starting: HashMap<(u32, u32), Vec<usize>> = HashMap::new();
+    for (i, piece) in pieces.iter().enumerate() {
+        starting.entry(key(piece[0])).or_default().push(i);
+    }
+    let mut used = vec![false; pieces.len()];
+    let mut result = vec![];
+    for first in 0..pieces.len() {
+        if used[first] {
+            continue;
+        }
+        let mut points = vec![pieces[first][0]];
+        let mut at = first;
+        let closed = loop {
+            used[at] = true;
+            let end = pieces[at][1];
+            if end == points[0] {
+                break true;
+            }
+            points.push(end);
+            match starting.get(&key(end)).and_then(|next| next.iter().copied().find(|&n| !used[n])) {
+                Some(next) => at = next,
+                // loose ends where edges lie on top of each other
+                None => break false,
+            }
+        };
+        let contour = Contour { points, closed: true };
+        // and slivers where pieces meet at a crossing
+        if closed && contour.points.len() > 2 && area(&contour).abs() > min_area {
+            result.push(contour);
+        }
+    }
+    result
+}
+
+/// `points` without repeats, for a closed loop also without the last one repeating the first.
+pub(crate) fn dedup(points: &[[f32; 2]], closed: bool) -> Vec<[f32; 2]> {
+    let mut out: Vec<[f32; 2]> = vec![];
+    for &p in points {
+        if out.last() != Some(&p) {
+            out.push(p);
+        }
+    }
+    if closed && out.len() > 1 && out.first() == out.last() {
+        out.pop();
+    }
+    out
+}
+
+/// Where the edges p and q cross strictly inside both, as the fractions along each and the
+/// point.
+pub(crate) fn crossing(p: [[f32; 2]; 2], q: [[f32; 2]; 2]) -> Option<(f32, f32, [f32; 2])> {
+    let r = [p[1][0] - p[0][0], p[1][1] - p[0][1]];
+    let s = [q[1][0] - q[0][0], q[1][1] - q[0][1]];
+    let denominator = r[0] * s[1] - r[1] * s[0];
+    if denominator.abs() <= f32::EPSILON {
+        return None;
+    }
+    let (dx, dy) = (q[0][0] - p[0][0], q[0][1] - p[0][1]);
+    let t = (dx * s[1] - dy * s[0]) / denominator;
+    let u = (dx * r[1] - dy * r[0]) / denominator;
+    let inside = |v: f32| v > 1e-6 && v < 1.0 - 1e-6;
+    (inside(t) && inside(u)).then(|| (t, u, [p[0][0] + r[0] * t, p[0][1] + r[1] * t]))
+}
+
+/// How far along `edge` the point `p` lies, if it is on it and strictly between its ends.
+fn along(edge: [[f32; 2]; 2], p: [f32; 2]) -> Option<f32> {
+    let [a, b] = edge;
+    let d = [b[0] - a[0], b[1] - a[1]];
+    let length2 = d[0] * d[0] + d[1] * d[1];
+    if length2 <= f32::EPSILON {
+        return None;
+    }
+    let off = [p[0] - a[0], p[1] - a[1]];
+    let across = (off[0] * d[1] - off[1] * d[0]).abs() / length2.sqrt();
+    let t = (off[0] * d[0] + off[1] * d[1]) / length2;
+    (across <= length2.sqrt() * 1e-6 && t > 1e-6 && t < 1.0 - 1e-6).then_some(t)
+}
+
+fn area(contour: &Contour) -> f32 {
+    let n = contour.points.len();
+    (0..n).map(|i| {
+        let (a, b) = (contour.points[i], contour.points[(i + 1) % n]);
+        a[0] * b[1] - b[0] * a[1]
+    }).sum::<f32>() * 0.5
+}
+
+fn distance2(a: [f32; 2], b: [f32; 2]) -> f32 {
+    (b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)
+}
@@ -0,0 +1,189 @@
+use resvg::usvg::Transform;
+
+use crate::document::{bbox_of, transform_point, Contour};
+
+/// An area to clip against, as disjoint triangles. Built from the tessellated
+/// `clip-path` (or mask) content, so any shape and fill rule works.
+#[derive(Clone, Debug, Default)]
+pub struct ClipRegion {
+    pub triangles: Vec<[[f32; 2]; 3]>,
+}
+
+impl ClipRegion {
+    pub fn from_mesh(vertices: &[[f32; 2]], indices: &[u32]) -> Self {
+        let triangles = indices.chunks_exact(3)
+            .map(|t| [vertices[t[0] as usize], vertices[t[1] as usize], vertices[t[2] as usize]])
+            .collect();
+        Self { triangles }
+    }
+
+    /// Axis aligned rectangle [x, y, width, height].
+    pub fn rect([x, y, w, h]: [f32; 4]) -> Self {
+        Self { triangles: vec![[[x, y], [x + w, y], [x + w, y + h]], [[x, y], [x + w, y + h], [x, y + h]]] }
+    }
+
+    pub fn transformed(&self, ts: &Transform) -> Self {
+        Self { triangles: self.triangles.iter().map(|t| t.map(|p| transform_point(ts, p))).collect() }
+    }
+
+    /// The area covered by both regions.
+    pub fn intersect(&self, other: &ClipRegion) -> Self {
+        let mut triangles = vec![];
+        for a in &self.triangles {
+            for b in &other.triangles {
+                if !bboxes_overlap(a, b) {
+                    continue;
+                }
+                let polygon = clip_convex(a, b);
+                triangles.extend(fan(&polygon));
+            }
+        }
+        Self { triangles }
+    }
+
+    /// Restrict a triangle mesh to the region. Each triangle is cut against every clip
+    /// triangle; since the clip triangles don't overlap, neither do the pieces.
+    pub fn clip_mesh(&self, vertices: &[[f32; 2]], indices: &[u32]) -> (Vec<[f32; 2]>, Vec<u32>) {
+        let mut out_vertices = vec![];
+        let mut out_indices = vec![];
+        for t in indices.chunks_exact(3) {
+            let subject = [vertices[t[0] as usize], vertices[t[1] as usize], vertices[t[2] as usize]];
+            for clip in &self.triangles {
+                if !bboxes_overlap(&subject, clip) {
+                    continue;
+                }
+                let polygon = clip_convex(&subject, clip);
+                if polygon.len() < 3 {
+                    continue;
+                }
+                let base = out_vertices.len() as u32;
+                out_vertices.extend_from_slice(&polygon);
+                for i in 1..polygon.len() as u32 - 1 {
+                    out_indices.extend_from_slice(&[base, base + i, base + i + 1]);
+                }
+            }
+        }
+        (out_vertices, out_indices)
+    }
+
+    /// The parts of a polyline that lie inside the region, as open contours.
+    pub fn clip_contour(&self, contour: &Contour) -> Vec<Contour> {
+        let points = &contour.points;
+        let n = points.len();
+        let segments = if contour.closed { n } else { n.saturating_sub(1) };
+        let mut pieces = vec![];
+        let mut current: Vec<[f32; 2]> = vec![];
+
+        for i in 0..segments {
+            let (a, b) = (points[i], points[(i + 1) % n]);
+            let mut spans: Vec<(f32, f32)> = self.triangles.iter()
+                .filter_map(|t| segment_in_triangle(a, b, t))
+                .collect();
+            spans.sort_by(|x, y| x.0.total_cmp(&y.0));
+            // neighbouring triangles split a span at their shared edge, join those back together
+            let mut merged: Vec<(f32, f32)> = vec![];
+            for (t0, t1) in spans {
+                match merged.last_mut() {
+                    Some(last) if t0 <= last.1 + 1e-4 => last.1 = last.1.max(t1),
+                    _ => merged.push((t0, t1)),
+                }
+            }
+            let lerp = |t: f32| [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t];
+            for (t0, t1) in merged {
+                // continue the previous piece if it ended exactly where this one starts
+                if t0 > 1e-4 || current.is_empty() {
+                    finish_piece(&mut pieces, &mut current);
+                    current.push(lerp(t0));
+                }
+                current.push(lerp(t1));
+                if t1 < 1.0 - 1e-4 {
+                    finish_piece(&mut pieces, &mut current);
+                }
+            }
+        }
+        finish_piece(&mut pieces, &mut current);
+        pieces
+    }
+
+    pub fn bbox(&self) -> Option<[f32; 4]> {
+        bbox_of(self.triangles.iter().flatten())
+    }
+}
+
+fn finish_piece(pieces: &mut Vec<Contour>, current: &mut Vec<[f32; 2]>) {
+    if current.len() > 1 {
+        pieces.push(Contour { points: std::mem::take(current), closed: false });
+    }
+    current.clear();
+}
+
+fn cross(o: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0])
+}
+
+fn bboxes_overlap(a: &[[f32; 2]; 3], b: &[[f32; 2]; 3]) -> bool {
+    let (Some(x), Some(y)) = (bbox_of(a.iter()), bbox_of(b.iter())) else { return false };
+    x[0] <= y[2] && y[0] <= x[2] && x[1] <= y[3] && y[1] <= x[3]
+}
+
+/// Sutherland-Hodgman: the part of convex polygon `subject` inside triangle `clip`.
+fn clip_convex(subject: &[[f32; 2]], clip: &[[f32; 2]; 3]) -> Vec<[f32; 2]> {
+    // inside means on the same side as the triangle's interior, whatever its winding
+    let orientation = cross(clip[0], clip[1], clip[2]).signum();
+    if orientation == 0.0 {
+        return vec![];
+    }
+    let mut output = subject.to_vec();
+    for i in 0..3 {
+        let (e0, e1) = (clip[i], clip[(i + 1) % 3]);
+        let input = std::mem::take(&mut output);
+        let Some(&last) = input.last() else { break };
+        let side = |p: [f32; 2]| cross(e0, e1, p) * orientation;
+        let mut prev = last;
+        for &p in &input {
+            let (sp, sq) = (side(prev), side(p));
+            if sq >= 0.0 {
+                if sp < 0.0 {
+                    output.push(intersect(prev, p, sp, sq));
+                }
+                output.push(p);
+            } else if sp >= 0.0 {
+                output.push(intersect(prev, p, sp, sq));
+            }
+            prev = p;
+        }
+    }
+    output
+}
+
+fn intersect(p: [f32; 2], q: [f32; 2], sp: f32, sq: f32) -> [f32; 2] {
+    let t = sp / (sp - sq);
+    [p[0] + (q[0] - p[0]) * t, p[1] + (q[1] - p[1]) * t]
+}
+
+fn fan(polygon: &[[f32; 2]]) -> Vec<[[f32; 2]; 3]> {
+    (1..polygon.len().saturating_sub(1)).map(|i| [polygon[0], polygon[i], polygon[i + 1]]).collect()
+}
+
+/// Cyrus-Beck: the parameter range of segment a-b inside the triangle, if any.
+fn segment_in_triangle(a: [f32; 2], b: [f32; 2], t: &[[f32; 2]; 3]) -> Option<(f32, f32)> {
+    let orientation = cross(t[0], t[1], t[2]).signum();
+    if orientation == 0.0 {
+        return None;
+    }
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    for i in 0..3 {
+        let (e0, e1) = (t[i], t[(i + 1) % 3]);
+        let sa = cross(e0, e1, a) * orientation;
+        let sb = cross(e0, e1, b) * orientation;
+        if sa < 0.0 && sb < 0.0 {
+            return None;
+        }
+        if sa < 0.0 {
+            t0 = t0.max(sa / (sa - sb));
+        } else if sb < 0.0 {
+            t1 = t1.min(sa / (sa - sb));
+        }
+    }
+    (t0 < t1).then_some((t0, t1))
+}
@@ -0,0 +1,94 @@
+use std::any::Any;
+
+use resvg::usvg::Transform;
+
+use crate::align::union;
+use crate::diagnostics::LoadError;
+use crate::document::{transform_point, Document, ElementId};
+use crate::edit::EditCommand;
+use crate::extract::selection_document;
+use crate::loader::{load_str, LoadOptions};
+use crate::saver::{to_svg_string_with, SaveOptions};
+
+/// The elements among `ids` as an SVG of their own, see [`selection_document`], for pasting
+/// into other programs.
+pub fn selection_svg(doc: &Document, ids: &[ElementId], opts: SaveOptions) -> Option<String> {
+    selection_document(doc, ids).map(|doc| to_svg_string_with(&doc, opts))
+}
+
+/// Clipboard text as a document: SVG markup as other editors copy it, Inkscape's included, or
+/// bare elements without the `<svg>` around them.
+pub fn parse_clipboard(text: &str, opts: &LoadOptions) -> Result<Document, LoadError> {
+    let opts = LoadOptions { strict: false, progress: None, ..opts.clone() };
+    if text.contains("<svg") {
+        load_str(text, &opts)
+    } else {
+        load_str(&format!(r#"<svg xmlns="http://www.w3.org/2000/svg">{text}</svg>"#), &opts)
+    }
+}
+
+/// The content of another document on top of this one, at the same size and place on the page
+/// or centered on a point.
+pub struct Paste {
+    // until the first apply, with the transform from its coordinates into the document's
+    pasted: Option<(Document, Transform)>,
+    after: Option<ElementId>,
+    ids: Vec<ElementId>,
+}
+
+impl Paste {
+    /// Paste `pasted` into `doc`, centered on `at` in document coordinates if given. None if
+    /// there is nothing in it.
+    pub fn new(doc: &Document, pasted: Document, at: Option<[f32; 2]>) -> Option<Self> {
+        if pasted.is_empty() {
+            return None;
+        }
+        // both viewports map to CSS pixels, so this keeps the size in mm
+        let mut ts = doc.viewport_transform().invert().unwrap_or_default().pre_concat(pasted.viewport_transform());
+        let bbox = union(pasted.get(pasted.root).children.iter().filter_map(|&id| pasted.subtree_bbox(id)));
+        if let (Some(at), Some(b)) = (at, bbox) {
+            let center = transform_point(&ts, [(b[0] + b[2]) * 0.5, (b[1] + b[3]) * 0.5]);
+            ts = Transform::from_translate(at[0] - center[0], at[1] - center[1]).pre_concat(ts);
+        }
+        let after = doc.get(doc.root).children.last().copied();
+        Some(Self { pasted: Some((pasted, ts)), after, ids: vec![] })
+    }
+
+    /// Ids of the pasted elements once applied, the top ones.
+    pub fn ids(&self) -> &[ElementId] {
+        &self.ids
+    }
+}
+
+impl EditCommand for Paste {
+    fn apply(&mut self, doc: &mut Document) {
+        if let Some((pasted, ts)) = self.pasted.take() {
+            let to_root = doc.abs_transform(doc.root).invert().unwrap_or_default().pre_concat(ts);
+            for &id in &pasted.get(pasted.root).children {
+                let copy = doc.copy_from(&pasted, id, doc.root);
+                doc.get_mut(copy).transform = to_root.pre_concat(pasted.abs_transform(id));
+                doc.detach(copy);
+                self.ids.push(copy);
+            }
+        }
+        let siblings = &doc.get(doc.root).children;
+        let index = self.after.and_then(|after| siblings.iter().position(|&c| c == after)).map_or(0, |i| i + 1);
+        for (i, &id) in self.ids.iter().enumerate() {
+            doc.attach(id, doc.root, index + i);
+        }
+    }
+
+    fn revert(&mut self, doc: &mut Document) {
+        for &id in self.ids.iter().rev() {
+            doc.detach(id);
+        }
+    }
+
+    fn name(&self) -> &str {
+        "Paste"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
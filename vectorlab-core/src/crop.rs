@@ -0,0 +1,129 @@
+use std::collections::HashSet;
+
+use crate::boolean::{boolean_contours, BooleanOp};
+use crate::document::{bbox_of, transform_point, transform_scale, Contour, Document, ElementId};
+use crate::edit::{DeleteElements, EditCommand, EditGroup, SetSegments, SetStyle};
+use crate::loader::flatten_segments;
+use crate::overlap::polylines;
+use crate::style::{FillRule, Style};
+
+/// What `crop_paths` did.
+pub struct CropResult {
+    pub edit: Box<dyn EditCommand>,
+    /// paths that were cut at the edge, and those entirely outside
+    pub cut: usize,
+    pub deleted: usize,
+}
+
+/// Trim the paths among `ids` to `rect`, [x0, y0, x1, y1] in document coordinates, e.g. the
+/// paper or the bed of a machine. Paths entirely outside are deleted, those crossing the edge
+/// are cut there. Lines are split into the parts inside, fills keep the part inside, closed
+/// along the edge. Cut paths become polylines. None if everything is inside already.
+pub fn crop_paths(doc: &Document, ids: &[ElementId], rect: [f32; 4]) -> Option<CropResult> {
+    let wanted: HashSet<ElementId> = ids.iter().copied().collect();
+    let mut paths = vec![];
+    doc.walk(|id, element, ts, _| {
+        if element.as_path().is_some() && wanted.contains(&id) {
+            paths.push((id, ts));
+        }
+    });
+    let page = Contour { points: vec![[rect[0], rect[1]], [rect[2], rect[1]], [rect[2], rect[3]], [rect[0], rect[3]]], closed: true };
+    let mut commands: Vec<Box<dyn EditCommand>> = vec![];
+    let mut deleted = vec![];
+    let mut cut = 0;
+    for (id, ts) in paths {
+        let Some(path) = doc.get(id).as_path() else { continue };
+        let tolerance = path.tolerance * transform_scale(&ts);
+        let contours: Vec<Contour> = flatten_segments(&path.segments, path.tolerance)
+            .into_iter()
+            .map(|c| Contour { points: c.points.iter().map(|&p| transform_point(&ts, p)).collect(), closed: c.closed })
+            .collect();
+        let Some(bbox) = bbox_of(contours.iter().flat_map(|c| &c.points)) else { continue };
+        if bbox[0] >= rect[0] && bbox[1] >= rect[1] && bbox[2] <= rect[2] && bbox[3] <= rect[3] {
+            continue;
+        }
+        if bbox[2] < rect[0] || bbox[0] > rect[2] || bbox[3] < rect[1] || bbox[1] > rect[3] {
+            deleted.push(id);
+            continue;
+        }
+
+        let filled = path.style.fill.is_some();
+        let runs: Vec<Vec<[f32; 2]>> = if filled {
+            let inside = boolean_contours(&contours, path.style.fill_rule, std::slice::from_ref(&page), FillRule::NonZero, BooleanOp::Intersection, tolerance.max(1e-4));
+            inside.into_iter().map(|c| c.points.iter().chain(c.points.first()).copied().collect()).collect()
+        } else {
+            contours.iter().flat_map(|c| clip_polyline(&c.points, c.closed, rect)).collect()
+        };
+        if runs.is_empty() {
+            deleted.push(id);
+            continue;
+        }
+        commands.push(Box::new(SetSegments::new(doc, id, polylines(&runs, &ts.invert().unwrap_or_default()))));
+        if filled && path.style.fill_rule != FillRule::NonZero {
+            // the outlines of the intersection are meant to be filled nonzero
+            commands.push(Box::new(SetStyle { id, old: path.style.clone(), new: Style { fill_rule: FillRule::NonZero, ..path.style.clone() } }));
+        }
+        cut += 1;
+    }
+    if cut == 0 && deleted.is_empty() {
+        return None;
+    }
+    let deleted_count = deleted.len();
+    commands.push(Box::new(DeleteElements::new(deleted)));
+    Some(CropResult { edit: Box::new(EditGroup::new("Clip to page", commands)), cut, deleted: deleted_count })
+}
+
+/// The parts of the polyline through `points` inside `rect`, as runs of points. A closed one
+/// that starts inside is picked up again where it left off, so it is not split at its start.
+fn clip_polyline(points: &[[f32; 2]], closed: bool, rect: [f32; 4]) -> Vec<Vec<[f32; 2]>> {
+    let n = points.len();
+    let edges = if closed { n } else { n.saturating_sub(1) };
+    let mut runs: Vec<Vec<[f32; 2]>> = vec![];
+    let mut run: Vec<[f32; 2]> = vec![];
+    for i in 0..edges {
+        let Some((a, b)) = clip_line(points[i], points[(i + 1) % n], rect) else { continue };
+        if run.last() != Some(&a) {
+            runs.extend((run.len() > 1).then(|| std::mem::take(&mut run)));
+            run = vec![a];
+        }
+        run.push(b);
+    }
+    runs.extend((run.len() > 1).then_some(run));
+    if closed && runs.len() > 1 && runs[0].first() == runs.last().and_then(|r| r.last()) {
+        let first = runs.remove(0);
+        if let Some(last) = runs.last_mut() {
+            last.extend(&first[1..]);
+        }
+    }
+    runs
+}
+
+/// The part of a..b inside `rect`, Liang–Barsky. The ends stay exactly the same where they are
+/// inside.
+fn clip_line(a: [f32; 2], b: [f32; 2], rect: [f32; 4]) -> Option<([f32; 2], [f32; 2])> {
+    let d = [b[0] - a[0], b[1] - a[1]];
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    for (p, q) in [(-d[0], a[0] - rect[0]), (d[0], rect[2] - a[0]), (-d[1], a[1] - rect[1]), (d[1], rect[3] - a[1])] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let t = q / p;
+        if p < 0.0 {
+            t0 = t0.max(t);
+        } else {
+            t1 = t1.min(t);
+        }
+    }
+    if t0 >= t1 {
+        return None;
+    }
+    let at = |t: f32| match t {
+        0.0 => a,
+        1.0 => b,
+        _ => [a[0] + d[0] * t, a[1] + d[1] * t],
+    };
+    Some((at(t0), at(t1)))
+}
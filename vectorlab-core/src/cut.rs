@@ -0,0 +1,192 @@
+use std::fmt::Write;
+
+use crate::boolean::{boolean_contours, BooleanOp};
+use crate::document::{transform_point, transform_scale, Contour, Document, ElementKind};
+use crate::loader::flatten_segments;
+use crate::offset::stroke_outline;
+use crate::style::FillRule;
+use crate::units::Unit;
+
+/// How a drawing is prepared for a craft cutter like a Cricut or Silhouette, see
+/// [`cut_job`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CutSettings {
+    /// Cut around strokes, their outline joined with the fill, instead of along their middle
+    pub outline_strokes: bool,
+    /// Open paths whose ends are this close are closed, in mm. Others are left out, cutters
+    /// only cut shapes out
+    pub close_gaps: f32,
+    /// How far flattened curves may deviate from the true ones, in mm
+    pub tolerance: f32,
+}
+
+impl Default for CutSettings {
+    fn default() -> Self {
+        Self { outline_strokes: true, close_gaps: 0.5, tolerance: 0.05 }
+    }
+}
+
+/// Closed outlines to cut, on the page in mm from its top left corner.
+#[derive(Clone, Debug, Default)]
+pub struct CutJob {
+    pub contours: Vec<Contour>,
+    /// of the page, in mm
+    pub size: [f32; 2],
+    /// what could not be cut as drawn
+    pub warnings: Vec<String>,
+}
+
+/// The outlines of the visible paths as a cutter should cut them: fills along their edge,
+/// strokes around their outline if `settings` says so, everything closed. What has no
+/// outline to cut or is cut differently than it looks is listed in the warnings.
+pub fn cut_job(doc: &Document, settings: &CutSettings) -> CutJob {
+    let to_page = doc.viewport_transform();
+    let mut job = CutJob { size: doc.size_mm(), ..Default::default() };
+    let (mut open, mut images, mut clipped, mut invisible, mut thin) = (0, 0, 0, 0, 0);
+    doc.walk(|_, element, ts, _| {
+        let path = match &element.kind {
+            ElementKind::Path(path) => path,
+            ElementKind::Image { .. } => {
+                images += 1;
+                return;
+            }
+            _ => return,
+        };
+        let ts = to_page.pre_concat(ts);
+        // the tolerance is in the path's own coordinates
+        let mm_per_local = Unit::Px.to_mm(transform_scale(&ts)).max(f32::EPSILON);
+        let tolerance = settings.tolerance / mm_per_local;
+        let style = &path.style;
+        let stroked = style.stroke.is_some() && style.stroke_width > 0.0;
+        if style.fill.is_none() && !stroked {
+            invisible += 1;
+            return;
+        }
+        if path.clip.is_some() {
+            clipped += 1;
+        }
+        let contours = flatten_segments(&path.segments, tolerance);
+        let mut cut: Vec<Contour> = if style.fill.is_some() {
+            // a fill closes what it fills
+            contours.iter().map(|c| Contour { points: c.points.clone(), closed: true }).collect()
+        } else {
+            vec![]
+        };
+        if stroked && settings.outline_strokes {
+            let outline = stroke_outline(&contours, style.stroke_width, style.line_cap, style.line_join, style.miter_limit, tolerance);
+            cut = if cut.is_empty() { outline } else { boolean_contours(&cut, style.fill_rule, &outline, FillRule::NonZero, BooleanOp::Union, tolerance) };
+        } else if stroked && style.fill.is_none() {
+            let gap = settings.close_gaps / mm_per_local;
+            for contour in contours {
+                let ends = contour.points.first().zip(contour.points.last());
+                let closes = ends.is_some_and(|(a, b)| (a[0] - b[0]).hypot(a[1] - b[1]) <= gap);
+                if contour.closed || closes {
+                    cut.push(Contour { closed: true, ..contour });
+                } else {
+                    open += 1;
+                }
+            }
+        }
+        for contour in cut {
+            if contour.points.len() < 3 {
+                thin += 1;
+                continue;
+            }
+            let points = contour.points.iter().map(|&p| transform_point(&ts, p).map(|px| Unit::Px.to_mm(px))).collect();
+            job.contours.push(Contour { points, closed: true });
+        }
+    });
+
+    let warnings = &mut job.warnings;
+    if doc.size == [0.0, 0.0] {
+        warnings.push("The drawing has no width and height, its units are taken as CSS pixels, 96 per inch".to_string());
+    }
+    if open > 0 {
+        warnings.push(format!("{} open paths left out, outline the strokes to cut around them", open));
+    }
+    if images > 0 {
+        warnings.push(format!("{} images left out, trace them to cut their shapes", images));
+    }
+    if clipped > 0 {
+        warnings.push(format!("{} paths are clipped or masked, they are cut whole", clipped));
+    }
+    if invisible > 0 {
+        warnings.push(format!("{} paths without fill or stroke left out", invisible));
+    }
+    if thin > 0 {
+        warnings.push(format!("{} outlines too small to cut left out", thin));
+    }
+    if job.contours.is_empty() {
+        warnings.push("Nothing to cut".to_string());
+    }
+    job
+}
+
+impl CutJob {
+    /// An SVG of hairline outlines sized in mm, which Cricut Design Space and Silhouette
+    /// Studio take at their real size.
+    pub fn svg(&self) -> String {
+        let [w, h] = self.size;
+        let mut svg = String::new();
+        let _ = writeln!(svg, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        let _ = writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.3}mm" height="{:.3}mm" viewBox="0 0 {:.3} {:.3}">"#, w, h, w, h);
+        for contour in &self.contours {
+            let mut d = String::new();
+            for (i, [x, y]) in contour.points.iter().enumerate() {
+                let _ = write!(d, "{}{:.3} {:.3} ", if i == 0 { "M" } else { "L" }, x, y);
+            }
+            d.push('Z');
+            let _ = writeln!(svg, r#"<path d="{}" fill="none" stroke="black" stroke-width="0.1"/>"#, d);
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// A DXF R12 of closed polylines in mm, y up, the version Silhouette Studio's basic
+    /// edition and most cutter software import.
+    pub fn dxf(&self) -> String {
+        let [w, h] = self.size;
+        let mut dxf = String::new();
+        let mut pair = |code: u32, value: &str| {
+            let _ = writeln!(dxf, "{}\n{}", code, value);
+        };
+        pair(0, "SECTION");
+        pair(2, "HEADER");
+        pair(9, "$ACADVER");
+        pair(1, "AC1009");
+        // millimeters
+        pair(9, "$INSUNITS");
+        pair(70, "4");
+        pair(9, "$EXTMIN");
+        pair(10, "0.0");
+        pair(20, "0.0");
+        pair(9, "$EXTMAX");
+        pair(10, &format!("{:.3}", w));
+        pair(20, &format!("{:.3}", h));
+        pair(0, "ENDSEC");
+        pair(0, "SECTION");
+        pair(2, "ENTITIES");
+        for contour in &self.contours {
+            pair(0, "POLYLINE");
+            pair(8, "0");
+            pair(66, "1");
+            pair(10, "0.0");
+            pair(20, "0.0");
+            pair(30, "0.0");
+            // closed
+            pair(70, "1");
+            for [x, y] in &contour.points {
+                pair(0, "VERTEX");
+                pair(8, "0");
+                pair(10, &format!("{:.3}", x));
+                pair(20, &format!("{:.3}", h - y));
+                pair(30, "0.0");
+            }
+            pair(0, "SEQEND");
+            pair(8, "0");
+        }
+        pair(0, "ENDSEC");
+        pair(0, "EOF");
+        dxf
+    }
+}
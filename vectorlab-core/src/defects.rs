@@ -0,0 +1,125 @@
+use crate::boolean::crossing;
+use crate::document::{transform_point, Document, ElementId, Segment};
+
+/// Geometry that laser and plotter software tends to trip over, see
+/// [`Document::find_defects`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DefectKind {
+    /// the outline crosses itself or another subpath of the same path
+    SelfIntersection,
+    /// an open subpath whose ends meet, which looks closed but is cut with a gap or drawn twice
+    NearlyClosed,
+    /// a segment shorter than the tolerance
+    ZeroLength,
+    /// a segment going nowhere, the same point twice in a row
+    DuplicatePoint,
+}
+
+impl DefectKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::SelfIntersection => "Self-intersection",
+            Self::NearlyClosed => "Not closed",
+            Self::ZeroLength => "Zero-length segment",
+            Self::DuplicatePoint => "Duplicate point",
+        }
+    }
+}
+
+/// Defects of one kind in one path.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Defect {
+    pub id: ElementId,
+    pub kind: DefectKind,
+    /// where the first one is, in document coordinates
+    pub at: [f32; 2],
+    pub count: usize,
+}
+
+impl Document {
+    /// Look through the visible paths for self-intersections, open subpaths whose ends are
+    /// less than `tolerance` apart, segments shorter than that and repeated points.
+    /// `tolerance` is in document units. One entry per path and kind, in paint order.
+    pub fn find_defects(&self, tolerance: f32) -> Vec<Defect> {
+        let mut defects = vec![];
+        self.walk(|id, element, ts, _| {
+            let Some(path) = element.as_path() else { return };
+            let mut found: Vec<Defect> = vec![];
+            let mut report = |kind: DefectKind, at: [f32; 2]| match found.iter_mut().find(|d| d.kind == kind) {
+                Some(defect) => defect.count += 1,
+                None => found.push(Defect { id, kind, at, count: 1 }),
+            };
+            let map = |p: [f32; 2]| transform_point(&ts, p);
+            let near = |a: [f32; 2], b: [f32; 2]| distance(map(a), map(b)) < tolerance;
+
+            // the segments, for what the flattening would hide
+            let (mut start, mut current) = ([0.0; 2], [0.0; 2]);
+            let mut segments = 0;
+            let finish = |start: [f32; 2], current: [f32; 2], segments: usize, report: &mut dyn FnMut(DefectKind, [f32; 2])| {
+                if segments > 1 && near(start, current) {
+                    report(DefectKind::NearlyClosed, map(start));
+                }
+            };
+            for segment in &path.segments {
+                match *segment {
+                    Segment::MoveTo(p) => {
+                        finish(start, current, segments, &mut report);
+                        (start, current, segments) = (p, p, 0);
+                    }
+                    Segment::Close => {
+                        // the closing line, nothing to see if the last point is the start
+                        if current != start && near(current, start) {
+                            report(DefectKind::ZeroLength, map(start));
+                        }
+                        (current, segments) = (start, 0);
+                    }
+                    Segment::LineTo(end) | Segment::QuadTo(_, end) | Segment::CubicTo(_, _, end) => {
+                        let controls: &[[f32; 2]] = match segment {
+                            Segment::QuadTo(c, _) => &[*c],
+                            Segment::CubicTo(c1, c2, _) => &[*c1, *c2],
+                            _ => &[],
+                        };
+                        if end == current && controls.iter().all(|&c| c == current) {
+                            report(DefectKind::DuplicatePoint, map(current));
+                        } else if near(current, end) && controls.iter().all(|&c| near(current, c)) {
+                            report(DefectKind::ZeroLength, map(current));
+                        }
+                        current = end;
+                        segments += 1;
+                    }
+                }
+            }
+            finish(start, current, segments, &mut report);
+
+            // the flattened outline, for crossings
+            let mut edges = vec![];
+            for contour in &path.contours {
+                let points: Vec<[f32; 2]> = contour.points.iter().map(|&p| map(p)).collect();
+                let n = points.len();
+                let count = if contour.closed { n } else { n.saturating_sub(1) };
+                edges.extend((0..count).map(|i| [points[i], points[(i + 1) % n]]));
+            }
+            let min_x = |e: &[[f32; 2]; 2]| e[0][0].min(e[1][0]);
+            let max_x = |e: &[[f32; 2]; 2]| e[0][0].max(e[1][0]);
+            edges.sort_by(|a, b| min_x(a).total_cmp(&min_x(b)));
+            for (k, a) in edges.iter().enumerate() {
+                for b in &edges[k + 1..] {
+                    if min_x(b) > max_x(a) {
+                        break;
+                    }
+                    if let Some((_, _, x)) = crossing(*a, *b) {
+                        report(DefectKind::SelfIntersection, x);
+                    }
+                }
+            }
+
+            found.sort_by_key(|d| d.kind);
+            defects.extend(found);
+        });
+        defects
+    }
+}
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt()
+}
@@ -0,0 +1,115 @@
+use std::cell::RefCell;
+use std::fmt;
+
+use resvg::usvg;
+
+/// Why a document could not be loaded.
+#[derive(Debug)]
+pub enum LoadError {
+    Io(std::io::Error),
+    NotUtf8(std::str::Utf8Error),
+    /// rejected by usvg, e.g. malformed XML or no `<svg>` root
+    Svg(usvg::Error),
+    /// rejected by the [`crate::Importer`] of another format
+    Import(String),
+    Cancelled,
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Cannot read the file: {}", e),
+            Self::NotUtf8(e) => write!(f, "Not an SVG text file: {}", e),
+            Self::Svg(e) => write!(f, "Invalid SVG: {}", e),
+            Self::Import(e) => write!(f, "Cannot import: {}", e),
+            Self::Cancelled => write!(f, "Loading cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::NotUtf8(e) => Some(e),
+            Self::Svg(e) => Some(e),
+            Self::Import(_) | Self::Cancelled => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for LoadError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<std::str::Utf8Error> for LoadError {
+    fn from(e: std::str::Utf8Error) -> Self {
+        Self::NotUtf8(e)
+    }
+}
+
+impl From<usvg::Error> for LoadError {
+    fn from(e: usvg::Error) -> Self {
+        Self::Svg(e)
+    }
+}
+
+/// A problem with a loaded SVG that did not stop loading it.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub message: String,
+    /// line and column in the source, both from 1. usvg's own warnings don't have one.
+    pub position: Option<[u32; 2]>,
+}
+
+impl Diagnostic {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self { message: message.into(), position: None }
+    }
+
+    pub(crate) fn at(xml: &usvg::roxmltree::Document, offset: usize, message: impl Into<String>) -> Self {
+        let pos = xml.text_pos_at(offset);
+        Self { message: message.into(), position: Some([pos.row, pos.col]) }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.position {
+            Some([line, column]) => write!(f, "{}:{}: {}", line, column, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+thread_local! {
+    // warnings logged on this thread while a load is collecting them
+    static CAPTURED: RefCell<Option<Vec<Diagnostic>>> = const { RefCell::new(None) };
+}
+
+/// usvg reports everything it skips or ignores (unsupported features, missing fonts, broken
+/// references) through the `log` crate only. An application's logger passes records here
+/// so they end up in [`Document::warnings`](crate::Document::warnings) of the document
+/// being loaded on this thread. False if no load is collecting.
+pub fn capture_log(record: &log::Record) -> bool {
+    if record.level() > log::Level::Warn {
+        return false;
+    }
+    CAPTURED.with_borrow_mut(|captured| match captured {
+        Some(warnings) => {
+            warnings.push(Diagnostic::new(record.args().to_string()));
+            true
+        }
+        None => false,
+    })
+}
+
+/// Run `f` and return what [`capture_log`] received meanwhile.
+pub(crate) fn collect_warnings<T>(f: impl FnOnce() -> T) -> (T, Vec<Diagnostic>) {
+    let outer = CAPTURED.replace(Some(vec![]));
+    let result = f();
+    let warnings = CAPTURED.replace(outer).unwrap_or_default();
+    (result, warnings)
+}
@@ -0,0 +1,536 @@
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use resvg::usvg::{self, AspectRatio, Transform};
+
+use crate::clip::ClipRegion;
+use crate::image::PlacedImage;
+use crate::diagnostics::Diagnostic;
+use crate::style::{Color, Style};
+use crate::text::TextLayout;
+use crate::units::Unit;
+use crate::view::ViewTransform;
+
+/// One subpath, as a polyline in the coordinates of its element.
+#[derive(Clone, Debug)]
+pub struct Contour {
+    pub points: Vec<[f32; 2]>,
+    pub closed: bool,
+}
+
+impl Contour {
+    pub fn length(&self) -> f32 {
+        let dist = |a: [f32; 2], b: [f32; 2]| ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt();
+        let open: f32 = self.points.windows(2).map(|w| dist(w[0], w[1])).sum();
+        match (self.closed, self.points.first(), self.points.last()) {
+            (true, Some(&first), Some(&last)) => open + dist(last, first),
+            _ => open,
+        }
+    }
+}
+
+/// One command of a path in the coordinates of its element. usvg already made them absolute
+/// and turned arcs into cubics.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Segment {
+    MoveTo([f32; 2]),
+    LineTo([f32; 2]),
+    QuadTo([f32; 2], [f32; 2]),
+    CubicTo([f32; 2], [f32; 2], [f32; 2]),
+    Close,
+}
+
+/// A path element. `segments` is the exact geometry, `contours` and the meshes are derived
+/// from it with `tolerance`, see [`FlattenedPath::reflatten`].
+#[derive(Clone, Debug)]
+pub struct FlattenedPath {
+    pub segments: Vec<Segment>,
+    pub contours: Vec<Contour>,
+    pub style: Style,
+    /// Tolerance the curves were flattened and tessellated with
+    pub tolerance: f32,
+    // triangulated fill area and stroke outline, see update_meshes()
+    pub fill_vertices: Vec<[f32; 2]>,
+    pub fill_indices: Vec<u32>,
+    pub stroke_vertices: Vec<[f32; 2]>,
+    pub stroke_indices: Vec<u32>,
+    // per-vertex colors for gradient paints, empty for solid ones
+    pub fill_colors: Vec<Color>,
+    pub stroke_colors: Vec<Color>,
+    /// clip-path / mask of the ancestors, in the path's coordinates. The contours stay
+    /// unclipped, the meshes are cut to it.
+    pub clip: Option<Arc<ClipRegion>>,
+}
+
+impl FlattenedPath {
+    /// Bounding box in the path's own coordinates.
+    pub fn bbox(&self) -> Option<[f32; 4]> {
+        bbox_of(self.contours.iter().flat_map(|c| &c.points))
+    }
+
+    /// Bounding box of the segments' end and control points. Unlike [`FlattenedPath::bbox`] it does
+    /// not depend on the flattening tolerance.
+    pub fn control_bbox(&self) -> Option<[f32; 4]> {
+        bbox_of(self.segments.iter().flat_map(|s| match s {
+            Segment::MoveTo(p) | Segment::LineTo(p) => vec![p],
+            Segment::QuadTo(c, p) => vec![c, p],
+            Segment::CubicTo(c1, c2, p) => vec![c1, c2, p],
+            Segment::Close => vec![],
+        }))
+    }
+
+    pub fn has_curves(&self) -> bool {
+        self.segments.iter().any(|s| matches!(s, Segment::QuadTo(..) | Segment::CubicTo(..)))
+    }
+
+    pub fn point_count(&self) -> usize {
+        self.contours.iter().map(|c| c.points.len()).sum()
+    }
+
+    /// Total length of all subpaths in the path's own coordinates. Measured on the segments,
+    /// so unlike the contours it does not depend on the flattening tolerance.
+    pub fn length(&self) -> f32 {
+        use lyon::geom::{point, CubicBezierSegment, QuadraticBezierSegment};
+        let p = |p: &[f32; 2]| point(p[0], p[1]);
+        let tolerance = self.control_bbox().map_or(0.0, |b| (b[2] - b[0]).max(b[3] - b[1]) * 1e-5).max(1e-6);
+        let mut length = 0.0;
+        let (mut start, mut current) = ([0.0; 2], [0.0; 2]);
+        for segment in &self.segments {
+            match *segment {
+                Segment::MoveTo(to) => {
+                    start = to;
+                    current = to;
+                    continue;
+                }
+                Segment::LineTo(to) => length += (p(&to) - p(&current)).length(),
+                Segment::QuadTo(ctrl, to) => length += QuadraticBezierSegment { from: p(&current), ctrl: p(&ctrl), to: p(&to) }.length(),
+                Segment::CubicTo(ctrl1, ctrl2, to) => {
+                    let cubic = CubicBezierSegment { from: p(&current), ctrl1: p(&ctrl1), ctrl2: p(&ctrl2), to: p(&to) };
+                    length += cubic.approximate_length(tolerance);
+                }
+                Segment::Close => length += (p(&start) - p(&current)).length(),
+            }
+            current = match *segment {
+                Segment::LineTo(to) | Segment::QuadTo(_, to) | Segment::CubicTo(_, _, to) => to,
+                _ => start,
+            };
+        }
+        length
+    }
+
+    /// Area enclosed by the subpaths in the path's own coordinates, all of them closed. Positive
+    /// for subpaths running clockwise on screen, so holes drawn the other way round subtract.
+    /// Exact for curves too.
+    pub fn signed_area(&self) -> f32 {
+        // Green's theorem, twice the area swept from the origin by each segment
+        let line = |a: [f64; 2], b: [f64; 2]| a[0] * b[1] - b[0] * a[1];
+        let cubic = |p0: [f64; 2], p1: [f64; 2], p2: [f64; 2], p3: [f64; 2]| {
+            let ([x0, y0], [x1, y1], [x2, y2], [x3, y3]) = (p0, p1, p2, p3);
+            (6.0 * (x0 * y1 - x1 * y0) + 3.0 * (x1 * y2 - x2 * y1) + 6.0 * (x2 * y3 - x3 * y2)
+                + 3.0 * (x0 * y2 - x2 * y0) + 3.0 * (x1 * y3 - x3 * y1) + (x0 * y3 - x3 * y0))
+                / 10.0
+        };
+        let f = |p: [f32; 2]| [p[0] as f64, p[1] as f64];
+        let mut twice = 0.0;
+        let (mut start, mut current) = ([0.0; 2], [0.0; 2]);
+        for segment in &self.segments {
+            match *segment {
+                Segment::MoveTo(to) => {
+                    twice += line(current, start);
+                    start = f(to);
+                    current = start;
+                }
+                Segment::LineTo(to) => {
+                    twice += line(current, f(to));
+                    current = f(to);
+                }
+                Segment::QuadTo(ctrl, to) => {
+                    // as the equivalent cubic
+                    let (c, to) = (f(ctrl), f(to));
+                    let c1 = [current[0] + 2.0 / 3.0 * (c[0] - current[0]), current[1] + 2.0 / 3.0 * (c[1] - current[1])];
+                    let c2 = [to[0] + 2.0 / 3.0 * (c[0] - to[0]), to[1] + 2.0 / 3.0 * (c[1] - to[1])];
+                    twice += cubic(current, c1, c2, to);
+                    current = to;
+                }
+                Segment::CubicTo(ctrl1, ctrl2, to) => {
+                    twice += cubic(current, f(ctrl1), f(ctrl2), f(to));
+                    current = f(to);
+                }
+                Segment::Close => {
+                    twice += line(current, start);
+                    current = start;
+                }
+            }
+        }
+        twice += line(current, start);
+        (twice / 2.0) as f32
+    }
+}
+
+/// Index of an element in [`Document::elements`]. Stable for the lifetime of the document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ElementId(pub usize);
+
+#[derive(Clone, Debug)]
+pub enum ElementKind {
+    Group,
+    Path(FlattenedPath),
+    /// `<text>`, its characters concatenated. The glyph outlines are path children. Texts
+    /// made with [`crate::AddText`] have a layout to set them again from, loaded ones don't.
+    Text { content: String, layout: Option<TextLayout> },
+    /// `<image>`, `image` is None if it could not be decoded
+    Image { rect: [f32; 4], image: Option<PlacedImage> },
+}
+
+#[derive(Clone, Debug)]
+pub struct Element {
+    pub parent: Option<ElementId>,
+    pub children: Vec<ElementId>,
+    pub kind: ElementKind,
+    /// `id` attribute from the SVG, empty if there was none
+    pub source_id: String,
+    /// Bytes of the XML element this was made from in [`Document::source`]. Elements
+    /// instantiated by `<use>` point at the referenced element.
+    pub source_range: Option<Range<usize>>,
+    /// `class` attribute from the SVG, empty if there was none
+    pub source_class: String,
+    /// Transform relative to the parent element.
+    pub transform: Transform,
+    pub opacity: f32,
+    /// Hidden elements and everything below them are skipped by [`Document::walk`].
+    pub visible: bool,
+}
+
+impl Element {
+    pub fn new(kind: ElementKind) -> Self {
+        Self {
+            parent: None,
+            children: vec![],
+            kind,
+            source_id: String::new(),
+            source_range: None,
+            source_class: String::new(),
+            transform: Transform::identity(),
+            opacity: 1.0,
+            visible: true,
+        }
+    }
+
+    /// SVG tag name this element corresponds to.
+    pub fn tag(&self) -> &'static str {
+        match self.kind {
+            ElementKind::Group => "g",
+            ElementKind::Path(_) => "path",
+            ElementKind::Text { .. } => "text",
+            ElementKind::Image { .. } => "image",
+        }
+    }
+
+    pub fn as_path(&self) -> Option<&FlattenedPath> {
+        match &self.kind {
+            ElementKind::Path(p) => Some(p),
+            _ => None,
+        }
+    }
+
+    pub fn as_path_mut(&mut self) -> Option<&mut FlattenedPath> {
+        match &mut self.kind {
+            ElementKind::Path(p) => Some(p),
+            _ => None,
+        }
+    }
+}
+
+/// A loaded SVG as a tree of elements stored in an arena. Geometry is kept in element
+/// coordinates; [`Document::walk`] supplies the accumulated transforms.
+#[derive(Clone, Debug)]
+pub struct Document {
+    /// `width` / `height` of the SVG in CSS pixels, 96 per inch
+    pub size: [f32; 2],
+    /// What `width` and `height` were given in, and are written in again when saving
+    pub unit: Unit,
+    /// `viewBox` of the SVG as [x, y, width, height], the area of user space shown at `size`.
+    /// Element coordinates are in user units.
+    pub view_box: [f32; 4],
+    /// `preserveAspectRatio` of the SVG, how `view_box` is fitted into `size`
+    pub aspect: AspectRatio,
+    pub elements: Vec<Element>,
+    pub root: ElementId,
+    /// Changes with every edit, for caches built from the document. Never the same for
+    /// two different documents.
+    pub revision: u64,
+    /// Problems found while loading that did not stop it, e.g. unsupported features,
+    /// missing fonts or broken references. usvg skips whatever they affect.
+    pub warnings: Vec<Diagnostic>,
+    /// The SVG text as loaded, decompressed. Edits don't change it.
+    pub source: String,
+}
+
+impl Default for Document {
+    fn default() -> Self {
+        Self {
+            size: [0.0, 0.0],
+            unit: Unit::Px,
+            view_box: [0.0, 0.0, 0.0, 0.0],
+            aspect: AspectRatio::default(),
+            elements: vec![Element::new(ElementKind::Group)],
+            root: ElementId(0),
+            revision: next_revision(),
+            warnings: vec![],
+            source: String::new(),
+        }
+    }
+}
+
+fn next_revision() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+impl Document {
+    /// Millimeters per user unit along x and y, see [`Document::viewport_transform`].
+    pub fn mm_per_unit(&self) -> [f32; 2] {
+        let ts = self.viewport_transform();
+        [Unit::Px.to_mm(ts.sx), Unit::Px.to_mm(ts.sy)]
+    }
+
+    /// From user units to the CSS pixels of the SVG's own size, `view_box` fitted into `size`
+    /// as `aspect` says. Identity for documents without a size.
+    pub fn viewport_transform(&self) -> Transform {
+        let [x, y, w, h] = self.view_box;
+        match (usvg::NonZeroRect::from_xywh(x, y, w, h), usvg::Size::from_wh(self.size[0], self.size[1])) {
+            (Some(view_box), Some(size)) => usvg::utils::view_box_to_transform(view_box, self.aspect, size),
+            _ => Transform::identity(),
+        }
+    }
+
+    /// The view showing the document at its own size as a browser would, the origin of the
+    /// page at the top left of the canvas. preserveAspectRatio="none" scales x and y
+    /// differently, the view gets their average.
+    pub fn initial_view(&self) -> ViewTransform {
+        let ts = self.viewport_transform();
+        ViewTransform {
+            zoom: transform_scale(&ts).clamp(ViewTransform::MIN_ZOOM, ViewTransform::MAX_ZOOM),
+            pan: [ts.tx, ts.ty],
+            ..ViewTransform::default()
+        }
+    }
+
+    /// Mark the document as changed, see [`Document::revision`].
+    pub fn touch(&mut self) {
+        self.revision = next_revision();
+    }
+
+    pub fn get(&self, id: ElementId) -> &Element {
+        &self.elements[id.0]
+    }
+
+    pub fn get_mut(&mut self, id: ElementId) -> &mut Element {
+        &mut self.elements[id.0]
+    }
+
+    /// Append `element` as the last child of `parent` and return its id.
+    pub fn add(&mut self, parent: ElementId, mut element: Element) -> ElementId {
+        let id = ElementId(self.elements.len());
+        element.parent = Some(parent);
+        self.elements.push(element);
+        self.elements[parent.0].children.push(id);
+        id
+    }
+
+    /// Take `id` out of its parent's children. Returns the parent and the position it had there,
+    /// None for the root and for elements that are already detached.
+    pub fn detach(&mut self, id: ElementId) -> Option<(ElementId, usize)> {
+        let parent = self.get(id).parent?;
+        let children = &mut self.get_mut(parent).children;
+        let index = children.iter().position(|&c| c == id)?;
+        children.remove(index);
+        Some((parent, index))
+    }
+
+    /// Elements made from the innermost XML element around byte `offset` of the source.
+    /// Several if `<use>` instantiated it more than once.
+    pub fn elements_at_source(&self, offset: usize) -> Vec<ElementId> {
+        let mut found = vec![];
+        let mut innermost = usize::MAX;
+        for id in self.descendants(self.root) {
+            let Some(range) = &self.get(id).source_range else { continue };
+            if !range.contains(&offset) || range.len() > innermost {
+                continue;
+            }
+            if range.len() < innermost {
+                innermost = range.len();
+                found.clear();
+            }
+            found.push(id);
+        }
+        found
+    }
+
+    /// Insert a detached element back into `parent` at `index`.
+    pub fn attach(&mut self, id: ElementId, parent: ElementId, index: usize) {
+        self.get_mut(id).parent = Some(parent);
+        let children = &mut self.get_mut(parent).children;
+        children.insert(index.min(children.len()), id);
+    }
+
+    /// Add a copy of `id` and everything below it as the last child of `parent`. The copies
+    /// have no SVG `id`, so saving does not repeat them.
+    pub(crate) fn copy_subtree(&mut self, id: ElementId, parent: ElementId) -> ElementId {
+        let mut element = self.get(id).clone();
+        element.children.clear();
+        element.source_id.clear();
+        let copy = self.add(parent, element);
+        for child in self.get(id).children.clone() {
+            self.copy_subtree(child, copy);
+        }
+        copy
+    }
+
+    /// Like `copy_subtree`, copying `id` from `other`. The copies don't point into this
+    /// document's source.
+    pub(crate) fn copy_from(&mut self, other: &Document, id: ElementId, parent: ElementId) -> ElementId {
+        let mut element = other.get(id).clone();
+        element.children.clear();
+        element.source_id.clear();
+        element.source_range = None;
+        let copy = self.add(parent, element);
+        for &child in &other.get(id).children {
+            self.copy_from(other, child, copy);
+        }
+        copy
+    }
+
+    /// `id` and everything below it in paint order, hidden elements included.
+    pub fn descendants(&self, id: ElementId) -> Vec<ElementId> {
+        let mut out = vec![];
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            out.push(id);
+            stack.extend(self.get(id).children.iter().rev());
+        }
+        out
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.get(self.root).children.is_empty()
+    }
+
+    /// All path elements in the tree, in paint order. Deleted (detached) elements are not included.
+    pub fn paths(&self) -> impl Iterator<Item = (ElementId, &FlattenedPath)> {
+        self.descendants(self.root).into_iter().filter_map(|id| self.get(id).as_path().map(|p| (id, p)))
+    }
+
+    pub fn path_count(&self) -> usize {
+        self.paths().count()
+    }
+
+    /// Transform from element coordinates to document coordinates.
+    pub fn abs_transform(&self, id: ElementId) -> Transform {
+        let element = self.get(id);
+        match element.parent {
+            Some(parent) => self.abs_transform(parent).pre_concat(element.transform),
+            None => element.transform,
+        }
+    }
+
+    /// Depth-first walk over the visible elements in paint order. The callback gets the element,
+    /// its absolute transform and the product of all opacities from the root down to it.
+    pub fn walk(&self, mut f: impl FnMut(ElementId, &Element, Transform, f32)) {
+        self.walk_from(self.root, Transform::identity(), 1.0, &mut f);
+    }
+
+    fn walk_from(&self, id: ElementId, parent_ts: Transform, parent_opacity: f32, f: &mut impl FnMut(ElementId, &Element, Transform, f32)) {
+        let element = self.get(id);
+        if !element.visible {
+            return;
+        }
+        let ts = parent_ts.pre_concat(element.transform);
+        let opacity = parent_opacity * element.opacity;
+        f(id, element, ts, opacity);
+        for &child in &element.children {
+            self.walk_from(child, ts, opacity, f);
+        }
+    }
+
+    /// Flatten the curves of the visible paths again where needed, so they deviate about
+    /// `tolerance` document units from the true curve, e.g. a screen tolerance divided by the
+    /// zoom. Paths only a few tolerances across get a tolerance of their own size, which leaves
+    /// about one segment per curve. Tolerances are rounded down to powers of two so that zooming
+    /// a little does not re-flatten anything.
+    pub fn reflatten(&mut self, tolerance: f32) {
+        let mut outdated = vec![];
+        self.walk(|id, element, ts, _| {
+            let Some(path) = element.as_path().filter(|p| p.has_curves()) else { return };
+            let scale = transform_scale(&ts);
+            if scale <= 0.0 {
+                return;
+            }
+            let mut local = tolerance / scale;
+            if let Some(b) = path.control_bbox() {
+                let size = (b[2] - b[0]).max(b[3] - b[1]);
+                if size < 8.0 * local {
+                    local = local.max(size);
+                }
+            }
+            let local = local.max(1e-4).log2().floor().exp2();
+            if local != path.tolerance {
+                outdated.push((id, local));
+            }
+        });
+        if !outdated.is_empty() {
+            self.touch();
+        }
+        for (id, local) in outdated {
+            if let Some(path) = self.get_mut(id).as_path_mut() {
+                path.reflatten(local);
+            }
+        }
+    }
+
+    /// Bounding box of a path or image element in document coordinates.
+    pub fn element_bbox(&self, id: ElementId) -> Option<[f32; 4]> {
+        let ts = self.abs_transform(id);
+        let points: Vec<[f32; 2]> = match &self.get(id).kind {
+            ElementKind::Path(path) => path.contours.iter().flat_map(|c| &c.points).map(|&p| transform_point(&ts, p)).collect(),
+            ElementKind::Image { rect: [x, y, w, h], .. } => {
+                [[*x, *y], [x + w, *y], [x + w, y + h], [*x, y + h]].map(|p| transform_point(&ts, p)).to_vec()
+            }
+            _ => return None,
+        };
+        bbox_of(points.iter())
+    }
+
+    /// Bounding box of all visible path points and images in document coordinates as
+    /// [min_x, min_y, max_x, max_y], None if there are none.
+    pub fn bbox(&self) -> Option<[f32; 4]> {
+        let mut points = vec![];
+        self.walk(|_, element, ts, _| match &element.kind {
+            ElementKind::Path(path) => {
+                points.extend(path.contours.iter().flat_map(|c| &c.points).map(|&p| transform_point(&ts, p)));
+            }
+            ElementKind::Image { rect: [x, y, w, h], .. } => {
+                points.extend([[*x, *y], [x + w, *y], [x + w, y + h], [*x, y + h]].map(|p| transform_point(&ts, p)));
+            }
+            _ => {}
+        });
+        bbox_of(points.iter())
+    }
+}
+
+pub fn transform_point(ts: &Transform, p: [f32; 2]) -> [f32; 2] {
+    [ts.sx * p[0] + ts.kx * p[1] + ts.tx, ts.ky * p[0] + ts.sy * p[1] + ts.ty]
+}
+
+/// Length scale factor of `ts`, the geometric mean for non-uniform scales.
+pub fn transform_scale(ts: &Transform) -> f32 {
+    (ts.sx * ts.sy - ts.kx * ts.ky).abs().sqrt()
+}
+
+pub fn bbox_of<'a>(mut points: impl Iterator<Item = &'a [f32; 2]>) -> Option<[f32; 4]> {
+    let first = points.next()?;
+    Some(points.fold([first[0], first[1], first[0], first[1]], |b, p| {
+        [b[0].min(p[0]), b[1].min(p[1]), b[2].max(p[0]), b[3].max(p[1])]
+    }))
+}
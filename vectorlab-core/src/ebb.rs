@@ -0,0 +1,144 @@
+use crate::document::{transform_point, Contour, Document, ElementId};
+use crate::gcode::{optimize_path_contours, path_contours};
+use crate::units::Unit;
+
+/// Motor steps per mm of an AxiDraw at 1/16 microstepping, 2032 per inch.
+pub const STEPS_PER_MM: f32 = 2032.0 / 25.4;
+
+/// Fastest the EBB steps a motor, steps per second.
+const MAX_STEP_RATE: f32 = 25000.0;
+
+/// Longest single `SM` move, in ms.
+const MAX_DURATION: u32 = 16_777_215;
+
+/// Servo positions of 0 and 100 % pen height, as the AxiDraw software uses them.
+const SERVO_MIN: f32 = 9855.0;
+const SERVO_MAX: f32 = 27831.0;
+
+/// How an AxiDraw or another plotter with an EiBotBoard draws.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlotterSettings {
+    /// Speed with the pen down and up, in mm/s
+    pub speed_down: f32,
+    pub speed_up: f32,
+    /// Pen heights in percent of the servo's range
+    pub pen_up: f32,
+    pub pen_down: f32,
+    /// Time the pen is given to rise or drop, in ms
+    pub pen_delay: u32,
+    /// How far flattened curves may deviate from the true ones, in mm
+    pub tolerance: f32,
+    /// Reorder and reverse the paths to shorten the travel, see `optimize_contours`
+    pub optimize: bool,
+}
+
+impl Default for PlotterSettings {
+    fn default() -> Self {
+        Self { speed_down: 40.0, speed_up: 100.0, pen_up: 60.0, pen_down: 30.0, pen_delay: 150, tolerance: 0.05, optimize: true }
+    }
+}
+
+/// One command for the EBB, without the carriage return that ends it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlotStep {
+    pub command: String,
+    /// index into the job's contours while drawing one, None for travel and setup
+    pub contour: Option<usize>,
+    /// how long the plotter is busy with it, in ms
+    pub duration: u32,
+    /// the carriage after it, in steps along x and y from home
+    pub position: [i64; 2],
+}
+
+/// What to send to plot a document, and the outlines it draws.
+#[derive(Clone, Debug, Default)]
+pub struct PlotJob {
+    /// in document coordinates, with the path each comes from
+    pub contours: Vec<(ElementId, Contour)>,
+    pub steps: Vec<PlotStep>,
+}
+
+impl PlotJob {
+    /// Total time of the steps in ms.
+    pub fn duration(&self) -> u64 {
+        self.steps.iter().map(|s| s.duration as u64).sum()
+    }
+
+    /// True if the carriage would have to go left of or above home, where it can't.
+    pub fn leaves_page(&self) -> bool {
+        self.steps.iter().any(|s| s.position[0] < 0 || s.position[1] < 0)
+    }
+}
+
+/// Servo position of a pen height in percent.
+fn servo_position(percent: f32) -> u32 {
+    (SERVO_MIN + (SERVO_MAX - SERVO_MIN) * percent.clamp(0.0, 100.0) / 100.0).round() as u32
+}
+
+/// Commands that set up the plotter before plotting: motors on at 1/16 microstepping, the
+/// pen heights, and the pen up.
+pub fn ebb_setup(settings: &PlotterSettings) -> Vec<String> {
+    vec![
+        "EM,1,1".to_string(),
+        format!("SC,4,{}", servo_position(settings.pen_up)),
+        format!("SC,5,{}", servo_position(settings.pen_down)),
+        pen_command(true, settings.pen_delay),
+    ]
+}
+
+/// Raise or lower the pen, then wait `delay` ms for it.
+pub fn pen_command(up: bool, delay: u32) -> String {
+    format!("SP,{},{}", if up { 1 } else { 0 }, delay)
+}
+
+/// A straight move between two positions in steps at `speed` mm/s. The AxiDraw's belts
+/// drive x + y with one motor and x - y with the other. None if there is nothing to move.
+pub fn ebb_move(from: [i64; 2], to: [i64; 2], speed: f32) -> Option<(String, u32)> {
+    let [dx, dy] = [to[0] - from[0], to[1] - from[1]];
+    if dx == 0 && dy == 0 {
+        return None;
+    }
+    let (m1, m2) = (dx + dy, dx - dy);
+    let mm = (dx as f32).hypot(dy as f32) / STEPS_PER_MM;
+    let fastest = m1.abs().max(m2.abs()) as f32 / MAX_STEP_RATE * 1000.0;
+    let duration = (mm / speed.max(0.1) * 1000.0).max(fastest).ceil().clamp(1.0, MAX_DURATION as f32) as u32;
+    Some((format!("SM,{},{},{}", duration, m1, m2), duration))
+}
+
+/// The visible paths of `doc` as EBB commands, from home at the top left of the page and back
+/// to it with the motors off. The page's own size is what comes out on paper.
+pub fn plot_job(doc: &Document, settings: &PlotterSettings) -> PlotJob {
+    let mm = doc.mm_per_unit();
+    let tolerance = settings.tolerance / mm[0].max(mm[1]).max(f32::EPSILON);
+    let mut contours = path_contours(doc, tolerance);
+    let to_page = doc.viewport_transform();
+    let home = transform_point(&to_page.invert().unwrap_or_default(), [0.0, 0.0]);
+    if settings.optimize {
+        optimize_path_contours(&mut contours, home);
+    }
+    let to_steps = |p: [f32; 2]| transform_point(&to_page, p).map(|px| (Unit::Px.to_mm(px) * STEPS_PER_MM).round() as i64);
+
+    let mut steps: Vec<PlotStep> = ebb_setup(settings).into_iter().map(|command| PlotStep { command, contour: None, duration: 0, position: [0, 0] }).collect();
+    let pen_delay = settings.pen_delay;
+    let mut at = [0, 0];
+    let push_move = |steps: &mut Vec<PlotStep>, at: &mut [i64; 2], to: [i64; 2], contour: Option<usize>| {
+        let speed = if contour.is_some() { settings.speed_down } else { settings.speed_up };
+        if let Some((command, duration)) = ebb_move(*at, to, speed) {
+            steps.push(PlotStep { command, contour, duration, position: to });
+            *at = to;
+        }
+    };
+    for (i, (_, contour)) in contours.iter().enumerate() {
+        let Some(&first) = contour.points.first() else { continue };
+        push_move(&mut steps, &mut at, to_steps(first), None);
+        steps.push(PlotStep { command: pen_command(false, pen_delay), contour: Some(i), duration: pen_delay, position: at });
+        let closing = if contour.closed { Some(first) } else { None };
+        for &p in contour.points[1..].iter().chain(closing.iter()) {
+            push_move(&mut steps, &mut at, to_steps(p), Some(i));
+        }
+        steps.push(PlotStep { command: pen_command(true, pen_delay), contour: Some(i), duration: pen_delay, position: at });
+    }
+    push_move(&mut steps, &mut at, [0, 0], None);
+    steps.push(PlotStep { command: "EM,0,0".to_string(), contour: None, duration: 0, position: at });
+    PlotJob { contours, steps }
+}
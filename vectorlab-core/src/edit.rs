@@ -0,0 +1,650 @@
+use std::any::Any;
+use std::time::{Duration, Instant};
+
+use resvg::usvg::Transform;
+
+use crate::document::{Document, Element, ElementId, Segment};
+use crate::style::Style;
+
+/// A reversible change to a [`Document`]. `revert` must restore exactly the state `apply` started from.
+pub trait EditCommand: Send {
+    fn apply(&mut self, doc: &mut Document);
+    fn revert(&mut self, doc: &mut Document);
+    /// Shown in the Edit menu, e.g. "Undo Change style".
+    fn name(&self) -> &str;
+    /// Fold `next` into `self` if both edit the same thing, so that dragging a slider
+    /// ends up as a single undo step.
+    fn merge(&mut self, _next: &dyn EditCommand) -> bool {
+        false
+    }
+    fn as_any(&self) -> &dyn Any;
+}
+
+/// Edits following each other closer than this are merged where the commands allow it.
+const MERGE_WINDOW: Duration = Duration::from_millis(500);
+
+/// Undo and redo stacks.
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Box<dyn EditCommand>>,
+    redo: Vec<Box<dyn EditCommand>>,
+    last_push: Option<Instant>,
+    version: u64,
+}
+
+impl History {
+    /// Apply `command` to `doc` and record it.
+    pub fn push(&mut self, mut command: Box<dyn EditCommand>, doc: &mut Document) {
+        command.apply(doc);
+        doc.touch();
+        self.version += 1;
+        self.redo.clear();
+        let recent = self.last_push.is_some_and(|t| t.elapsed() < MERGE_WINDOW);
+        self.last_push = Some(Instant::now());
+        if recent {
+            if let Some(top) = self.undo.last_mut() {
+                if top.merge(command.as_ref()) {
+                    return;
+                }
+            }
+        }
+        self.undo.push(command);
+    }
+
+    /// Record `command` that was applied to `doc` already, step by step as a script ran.
+    pub fn record(&mut self, command: Box<dyn EditCommand>, doc: &mut Document) {
+        doc.touch();
+        self.version += 1;
+        self.redo.clear();
+        self.last_push = None;
+        self.undo.push(command);
+    }
+
+    pub fn undo(&mut self, doc: &mut Document) {
+        if let Some(mut command) = self.undo.pop() {
+            command.revert(doc);
+            doc.touch();
+            self.version += 1;
+            self.redo.push(command);
+            self.last_push = None;
+        }
+    }
+
+    pub fn redo(&mut self, doc: &mut Document) {
+        if let Some(mut command) = self.redo.pop() {
+            command.apply(doc);
+            doc.touch();
+            self.version += 1;
+            self.undo.push(command);
+            self.last_push = None;
+        }
+    }
+
+    pub fn undo_name(&self) -> Option<&str> {
+        self.undo.last().map(|c| c.name())
+    }
+
+    pub fn redo_name(&self) -> Option<&str> {
+        self.redo.last().map(|c| c.name())
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self { version: self.version + 1, ..Self::default() };
+    }
+
+    /// Changes with every edit, undo, redo and clear. Unlike [`Document::revision`] it stays
+    /// when only the flattening does, so it tells whether the drawing is still the one it was.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+}
+
+/// Replace the style of a path element.
+pub struct SetStyle {
+    pub id: ElementId,
+    pub old: Style,
+    pub new: Style,
+}
+
+impl SetStyle {
+    fn set(doc: &mut Document, id: ElementId, style: &Style) {
+        let Some(path) = doc.get_mut(id).as_path_mut() else { return };
+        path.style = style.clone();
+        // the meshes depend on the fill rule and the stroke geometry, gradient colors are baked in
+        path.update_meshes();
+    }
+}
+
+impl EditCommand for SetStyle {
+    fn apply(&mut self, doc: &mut Document) {
+        Self::set(doc, self.id, &self.new);
+    }
+
+    fn revert(&mut self, doc: &mut Document) {
+        Self::set(doc, self.id, &self.old);
+    }
+
+    fn name(&self) -> &str {
+        "Change style"
+    }
+
+    fn merge(&mut self, next: &dyn EditCommand) -> bool {
+        match next.as_any().downcast_ref::<Self>() {
+            Some(next) if next.id == self.id => {
+                self.new = next.new.clone();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Replace the transform of an element relative to its parent.
+pub struct SetTransform {
+    pub id: ElementId,
+    pub old: Transform,
+    pub new: Transform,
+}
+
+impl EditCommand for SetTransform {
+    fn apply(&mut self, doc: &mut Document) {
+        doc.get_mut(self.id).transform = self.new;
+    }
+
+    fn revert(&mut self, doc: &mut Document) {
+        doc.get_mut(self.id).transform = self.old;
+    }
+
+    fn name(&self) -> &str {
+        "Transform"
+    }
+
+    fn merge(&mut self, next: &dyn EditCommand) -> bool {
+        match next.as_any().downcast_ref::<Self>() {
+            Some(next) if next.id == self.id => {
+                self.new = next.new;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Move, scale or turn several elements at once, e.g. the selection.
+pub struct SetTransforms {
+    /// element, old and new transform
+    pub changes: Vec<(ElementId, Transform, Transform)>,
+}
+
+impl SetTransforms {
+    /// Apply `ts`, in document coordinates, to the elements among `ids` on top of what they
+    /// have. Elements inside others among `ids` go along with those.
+    pub fn new(doc: &Document, ids: &[ElementId], ts: Transform) -> Self {
+        let inside_another = |id: ElementId| {
+            let mut parent = doc.get(id).parent;
+            while let Some(p) = parent {
+                if ids.contains(&p) {
+                    return true;
+                }
+                parent = doc.get(p).parent;
+            }
+            false
+        };
+        let mut changes = vec![];
+        for &id in ids {
+            if inside_another(id) || changes.iter().any(|&(c, _, _)| c == id) {
+                continue;
+            }
+            let element = doc.get(id);
+            let parent = element.parent.map_or(Transform::identity(), |p| doc.abs_transform(p));
+            let new = parent.invert().unwrap_or_default().pre_concat(ts).pre_concat(parent).pre_concat(element.transform);
+            changes.push((id, element.transform, new));
+        }
+        Self { changes }
+    }
+}
+
+impl EditCommand for SetTransforms {
+    fn apply(&mut self, doc: &mut Document) {
+        for &(id, _, new) in &self.changes {
+            doc.get_mut(id).transform = new;
+        }
+    }
+
+    fn revert(&mut self, doc: &mut Document) {
+        for &(id, old, _) in &self.changes {
+            doc.get_mut(id).transform = old;
+        }
+    }
+
+    fn name(&self) -> &str {
+        "Transform"
+    }
+
+    fn merge(&mut self, next: &dyn EditCommand) -> bool {
+        match next.as_any().downcast_ref::<Self>() {
+            Some(next) if next.changes.iter().map(|c| c.0).eq(self.changes.iter().map(|c| c.0)) => {
+                for (change, next) in self.changes.iter_mut().zip(&next.changes) {
+                    change.2 = next.2;
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+pub struct SetOpacity {
+    pub id: ElementId,
+    pub old: f32,
+    pub new: f32,
+}
+
+impl EditCommand for SetOpacity {
+    fn apply(&mut self, doc: &mut Document) {
+        doc.get_mut(self.id).opacity = self.new;
+    }
+
+    fn revert(&mut self, doc: &mut Document) {
+        doc.get_mut(self.id).opacity = self.old;
+    }
+
+    fn name(&self) -> &str {
+        "Change opacity"
+    }
+
+    fn merge(&mut self, next: &dyn EditCommand) -> bool {
+        match next.as_any().downcast_ref::<Self>() {
+            Some(next) if next.id == self.id => {
+                self.new = next.new;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+pub struct SetVisibility {
+    pub id: ElementId,
+    pub visible: bool,
+}
+
+impl EditCommand for SetVisibility {
+    fn apply(&mut self, doc: &mut Document) {
+        doc.get_mut(self.id).visible = self.visible;
+    }
+
+    fn revert(&mut self, doc: &mut Document) {
+        doc.get_mut(self.id).visible = !self.visible;
+    }
+
+    fn name(&self) -> &str {
+        if self.visible { "Show" } else { "Hide" }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Remove elements from the tree. They stay in the arena, so ids remain valid and
+/// reverting just puts them back where they were.
+pub struct DeleteElements {
+    pub ids: Vec<ElementId>,
+    // (element, parent, index among the parent's children) in the order they were detached
+    detached: Vec<(ElementId, ElementId, usize)>,
+}
+
+impl DeleteElements {
+    pub fn new(ids: Vec<ElementId>) -> Self {
+        Self { ids, detached: vec![] }
+    }
+}
+
+impl EditCommand for DeleteElements {
+    fn apply(&mut self, doc: &mut Document) {
+        self.detached.clear();
+        for &id in &self.ids {
+            if let Some((parent, index)) = doc.detach(id) {
+                self.detached.push((id, parent, index));
+            }
+        }
+    }
+
+    fn revert(&mut self, doc: &mut Document) {
+        for &(id, parent, index) in self.detached.iter().rev() {
+            doc.attach(id, parent, index);
+        }
+    }
+
+    fn name(&self) -> &str {
+        "Delete"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Replace the geometry of a path element, e.g. after simplifying it.
+pub struct SetSegments {
+    pub id: ElementId,
+    pub old: Vec<Segment>,
+    pub new: Vec<Segment>,
+}
+
+impl SetSegments {
+    /// Change the path `id` to `new`, remembering what it is now.
+    pub fn new(doc: &Document, id: ElementId, new: Vec<Segment>) -> Self {
+        let old = doc.get(id).as_path().map(|p| p.segments.clone()).unwrap_or_default();
+        Self { id, old, new }
+    }
+}
+
+impl EditCommand for SetSegments {
+    fn apply(&mut self, doc: &mut Document) {
+        if let Some(path) = doc.get_mut(self.id).as_path_mut() {
+            path.set_segments(self.new.clone());
+        }
+    }
+
+    fn revert(&mut self, doc: &mut Document) {
+        if let Some(path) = doc.get_mut(self.id).as_path_mut() {
+            path.set_segments(self.old.clone());
+        }
+    }
+
+    fn name(&self) -> &str {
+        "Edit path"
+    }
+
+    fn merge(&mut self, next: &dyn EditCommand) -> bool {
+        match next.as_any().downcast_ref::<Self>() {
+            Some(next) if next.id == self.id => {
+                self.new = next.new.clone();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Several edits that are undone and redone together, under one name.
+pub struct EditGroup {
+    name: String,
+    commands: Vec<Box<dyn EditCommand>>,
+}
+
+impl EditGroup {
+    pub fn new(name: impl Into<String>, commands: Vec<Box<dyn EditCommand>>) -> Self {
+        Self { name: name.into(), commands }
+    }
+}
+
+impl EditCommand for EditGroup {
+    fn apply(&mut self, doc: &mut Document) {
+        for command in &mut self.commands {
+            command.apply(doc);
+        }
+    }
+
+    fn revert(&mut self, doc: &mut Document) {
+        for command in self.commands.iter_mut().rev() {
+            command.revert(doc);
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Insert a new element right after one of its siblings, e.g. the hatching made from a fill.
+pub struct AddElement {
+    pub parent: ElementId,
+    /// the sibling it goes after, None for the first place
+    pub after: Option<ElementId>,
+    // until the first apply, then the id it was given
+    element: Option<Element>,
+    id: Option<ElementId>,
+}
+
+impl AddElement {
+    /// `element` goes after `sibling`, into the same parent.
+    pub fn after(doc: &Document, sibling: ElementId, element: Element) -> Self {
+        let parent = doc.get(sibling).parent.unwrap_or(doc.root);
+        Self { parent, after: Some(sibling), element: Some(element), id: None }
+    }
+
+    /// `element` goes above everything else in `parent`.
+    pub fn on_top(doc: &Document, parent: ElementId, element: Element) -> Self {
+        Self { parent, after: doc.get(parent).children.last().copied(), element: Some(element), id: None }
+    }
+
+    /// Id of the new element once applied.
+    pub fn id(&self) -> Option<ElementId> {
+        self.id
+    }
+}
+
+impl EditCommand for AddElement {
+    fn apply(&mut self, doc: &mut Document) {
+        if let Some(element) = self.element.take() {
+            let id = doc.add(self.parent, element);
+            doc.detach(id);
+            self.id = Some(id);
+        }
+        let Some(id) = self.id else { return };
+        let siblings = &doc.get(self.parent).children;
+        let index = self.after.and_then(|after| siblings.iter().position(|&c| c == after)).map_or(0, |i| i + 1);
+        doc.attach(id, self.parent, index);
+    }
+
+    fn revert(&mut self, doc: &mut Document) {
+        if let Some(id) = self.id {
+            doc.detach(id);
+        }
+    }
+
+    fn name(&self) -> &str {
+        "Add"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Copies of elements with everything below them, each placed by a transform in document
+/// coordinates on top of the original's. The copies of an element go right after it.
+pub struct AddCopies {
+    /// element and a transform for each copy of it
+    pub copies: Vec<(ElementId, Vec<Transform>)>,
+    // once applied, each copy with its parent and the sibling it goes after
+    made: Vec<(ElementId, ElementId, ElementId)>,
+}
+
+impl AddCopies {
+    pub fn new(copies: Vec<(ElementId, Vec<Transform>)>) -> Self {
+        Self { copies, made: vec![] }
+    }
+
+    /// Ids of the copies once applied.
+    pub fn ids(&self) -> Vec<ElementId> {
+        self.made.iter().map(|&(id, _, _)| id).collect()
+    }
+}
+
+impl EditCommand for AddCopies {
+    fn apply(&mut self, doc: &mut Document) {
+        if self.made.is_empty() {
+            for (id, placements) in &self.copies {
+                let Some(parent) = doc.get(*id).parent else { continue };
+                let parent_ts = doc.abs_transform(parent);
+                let mut after = *id;
+                for &ts in placements {
+                    let copy = doc.copy_subtree(*id, parent);
+                    doc.detach(copy);
+                    let local = doc.get(*id).transform;
+                    doc.get_mut(copy).transform = parent_ts.invert().unwrap_or_default().pre_concat(ts).pre_concat(parent_ts).pre_concat(local);
+                    self.made.push((copy, parent, after));
+                    after = copy;
+                }
+            }
+        }
+        for &(copy, parent, after) in &self.made {
+            let index = doc.get(parent).children.iter().position(|&c| c == after).map_or(0, |i| i + 1);
+            doc.attach(copy, parent, index);
+        }
+    }
+
+    fn revert(&mut self, doc: &mut Document) {
+        for &(copy, _, _) in self.made.iter().rev() {
+            doc.detach(copy);
+        }
+    }
+
+    fn name(&self) -> &str {
+        "Duplicate"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// [`Document::flatten`] as an edit. It changes nearly every element, so it keeps a copy of
+/// all of them from before and swaps between the two.
+#[derive(Default)]
+pub struct FlattenDocument {
+    other: Option<Vec<Element>>,
+}
+
+impl EditCommand for FlattenDocument {
+    fn apply(&mut self, doc: &mut Document) {
+        match self.other.as_mut() {
+            Some(other) => std::mem::swap(&mut doc.elements, other),
+            None => {
+                let before = doc.elements.clone();
+                doc.flatten();
+                self.other = Some(before);
+            }
+        }
+    }
+
+    fn revert(&mut self, doc: &mut Document) {
+        if let Some(other) = self.other.as_mut() {
+            std::mem::swap(&mut doc.elements, other);
+        }
+    }
+
+    fn name(&self) -> &str {
+        "Flatten"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// The drawing of another document in place of this one's, e.g. what a plugin made of it.
+/// Swaps the elements and the page like [`FlattenDocument`], the source stays, so the new
+/// elements lose their place in theirs.
+pub struct ReplaceDrawing {
+    name: String,
+    other: Document,
+}
+
+impl ReplaceDrawing {
+    pub fn new(name: impl Into<String>, mut doc: Document) -> Self {
+        for element in &mut doc.elements {
+            element.source_range = None;
+        }
+        Self { name: name.into(), other: doc }
+    }
+
+    fn swap(&mut self, doc: &mut Document) {
+        let other = &mut self.other;
+        std::mem::swap(&mut doc.elements, &mut other.elements);
+        std::mem::swap(&mut doc.root, &mut other.root);
+        std::mem::swap(&mut doc.size, &mut other.size);
+        std::mem::swap(&mut doc.unit, &mut other.unit);
+        std::mem::swap(&mut doc.view_box, &mut other.view_box);
+        std::mem::swap(&mut doc.aspect, &mut other.aspect);
+    }
+}
+
+impl EditCommand for ReplaceDrawing {
+    fn apply(&mut self, doc: &mut Document) {
+        self.swap(doc);
+    }
+
+    fn revert(&mut self, doc: &mut Document) {
+        self.swap(doc);
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// [`Document::split_by_color`] as an edit, swapping the elements like [`FlattenDocument`].
+#[derive(Default)]
+pub struct SplitByColor {
+    other: Option<Vec<Element>>,
+}
+
+impl EditCommand for SplitByColor {
+    fn apply(&mut self, doc: &mut Document) {
+        match self.other.as_mut() {
+            Some(other) => std::mem::swap(&mut doc.elements, other),
+            None => {
+                let before = doc.elements.clone();
+                doc.split_by_color();
+                self.other = Some(before);
+            }
+        }
+    }
+
+    fn revert(&mut self, doc: &mut Document) {
+        if let Some(other) = self.other.as_mut() {
+            std::mem::swap(&mut doc.elements, other);
+        }
+    }
+
+    fn name(&self) -> &str {
+        "Split by color"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
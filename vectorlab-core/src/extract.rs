@@ -0,0 +1,79 @@
+use crate::align::union;
+use crate::document::{Document, ElementId};
+use crate::saver::{to_svg_string_with, SaveOptions};
+use crate::units::Unit;
+
+/// The elements among `ids` as a document of their own, cropped to them and at their size on
+/// the page, for the clipboard or exporting a piece of a larger drawing. None if none of them
+/// has a size.
+pub fn selection_document(doc: &Document, ids: &[ElementId]) -> Option<Document> {
+    let ids: Vec<ElementId> = ids.iter().flat_map(|&id| if id == doc.root { doc.get(id).children.clone() } else { vec![id] }).collect();
+    let boxes = doc.outermost_bboxes(&ids);
+    let [x0, y0, x1, y1] = union(boxes.iter().map(|&(_, b)| b))?;
+    // as they were painted, not as they were picked
+    let order = doc.descendants(doc.root);
+    let mut ids: Vec<ElementId> = boxes.into_iter().map(|(id, _)| id).collect();
+    ids.sort_by_key(|id| order.iter().position(|o| o == id));
+
+    let mut out = Document::default();
+    let to_root = doc.abs_transform(doc.root).invert().unwrap_or_default();
+    for id in ids {
+        let copy = out.copy_from(doc, id, out.root);
+        out.get_mut(copy).transform = to_root.pre_concat(doc.abs_transform(id));
+    }
+    // a straight line has no height
+    let (w, h) = ((x1 - x0).max(1e-3), (y1 - y0).max(1e-3));
+    let mm = doc.mm_per_unit();
+    out.view_box = [x0, y0, w, h];
+    out.size = [Unit::Px.from_mm(w * mm[0]), Unit::Px.from_mm(h * mm[1])];
+    out.unit = doc.unit;
+    Some(out)
+}
+
+/// Names of the elements below the root for exporting them one by one, their ids or `layer-N`
+/// counting from 1.
+pub fn layer_names(doc: &Document) -> Vec<String> {
+    let layers = &doc.get(doc.root).children;
+    layers
+        .iter()
+        .enumerate()
+        .map(|(i, &layer)| {
+            let id = &doc.get(layer).source_id;
+            if id.is_empty() {
+                format!("layer-{}", i + 1)
+            } else {
+                id.clone()
+            }
+        })
+        .collect()
+}
+
+/// A document per element below the root, each alone on the same page, named as
+/// [`layer_names`] does. For plotting or exporting the layers one after the other.
+pub fn layer_documents(doc: &Document) -> Vec<(String, Document)> {
+    let layers = doc.get(doc.root).children.clone();
+    layers
+        .into_iter()
+        .zip(layer_names(doc))
+        .map(|(layer, name)| {
+            let mut single = doc.clone();
+            single.get_mut(single.root).children = vec![layer];
+            (name, single)
+        })
+        .collect()
+}
+
+/// An SVG per element below the root, each alone on the same page so they line up, named as
+/// [`layer_names`] does.
+pub fn layer_svgs(doc: &Document, opts: SaveOptions) -> Vec<(String, String)> {
+    let mut single = doc.clone();
+    let layers = doc.get(doc.root).children.clone();
+    layers
+        .into_iter()
+        .zip(layer_names(doc))
+        .map(|(layer, name)| {
+            single.get_mut(single.root).children = vec![layer];
+            (name, to_svg_string_with(&single, opts))
+        })
+        .collect()
+}
@@ -0,0 +1,200 @@
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::diagnostics::LoadError;
+use crate::document::Document;
+use crate::loader::{load_data, LoadOptions};
+use crate::pdf::to_pdf;
+use crate::saver::{to_svg_string_with, SaveOptions};
+use crate::tikz::{to_tikz, TikzSettings};
+
+/// A file format drawings can be opened from. Formats beyond SVG live in crates of their
+/// own and are added with [`Formats::add_importer`].
+pub trait Importer: Send + Sync {
+    /// Shown in the filters of the Open dialog, e.g. "HP-GL"
+    fn name(&self) -> &str;
+    /// File name extensions without the dot, in lower case
+    fn extensions(&self) -> &[&str];
+    /// The drawing in `data`. For files `opts.resources_dir` is their directory.
+    fn import(&self, data: &[u8], opts: &LoadOptions) -> Result<Document, LoadError>;
+}
+
+/// A file format drawings can be written in, added with [`Formats::add_exporter`].
+pub trait Exporter: Send + Sync {
+    /// Shown in the filters of the Export dialog
+    fn name(&self) -> &str;
+    /// File name extensions without the dot, in lower case, the first one for new files
+    fn extensions(&self) -> &[&str];
+    fn export(&self, doc: &Document) -> Result<Vec<u8>, Box<dyn Error>>;
+}
+
+/// The importers and exporters files are opened and exported with, picked by extension.
+/// The default has the formats VectorLab comes with.
+#[derive(Clone)]
+pub struct Formats {
+    importers: Vec<Arc<dyn Importer>>,
+    exporters: Vec<Arc<dyn Exporter>>,
+}
+
+impl Default for Formats {
+    fn default() -> Self {
+        Self {
+            importers: vec![Arc::new(SvgFormat)],
+            exporters: vec![Arc::new(SvgFormat), Arc::new(SvgzFormat), Arc::new(PdfFormat), Arc::new(TikzFormat)],
+        }
+    }
+}
+
+/// Lower case extension of `path`.
+fn extension(path: &Path) -> Option<String> {
+    path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase())
+}
+
+impl Formats {
+    /// Without any formats, not even SVG.
+    pub fn empty() -> Self {
+        Self { importers: vec![], exporters: vec![] }
+    }
+
+    /// `importer` is used for its extensions from now on, before importers added earlier.
+    pub fn add_importer(&mut self, importer: impl Importer + 'static) {
+        self.importers.insert(0, Arc::new(importer));
+    }
+
+    /// `exporter` is used for its extensions from now on, before exporters added earlier.
+    pub fn add_exporter(&mut self, exporter: impl Exporter + 'static) {
+        self.exporters.insert(0, Arc::new(exporter));
+    }
+
+    pub fn importers(&self) -> impl Iterator<Item = &dyn Importer> {
+        self.importers.iter().map(|i| i.as_ref())
+    }
+
+    pub fn exporters(&self) -> impl Iterator<Item = &dyn Exporter> {
+        self.exporters.iter().map(|e| e.as_ref())
+    }
+
+    pub fn importer_for(&self, path: &Path) -> Option<&dyn Importer> {
+        let extension = extension(path)?;
+        self.importers().find(|i| i.extensions().contains(&extension.as_str()))
+    }
+
+    pub fn exporter_for(&self, path: &Path) -> Option<&dyn Exporter> {
+        let extension = extension(path)?;
+        self.exporters().find(|e| e.extensions().contains(&extension.as_str()))
+    }
+
+    /// Every extension files can be opened with, for the Open dialog.
+    pub fn import_extensions(&self) -> Vec<&str> {
+        let mut extensions = vec![];
+        for extension in self.importers().flat_map(|i| i.extensions().iter().copied()) {
+            if !extensions.contains(&extension) {
+                extensions.push(extension);
+            }
+        }
+        extensions
+    }
+
+    /// Open `path` with the importer for its extension. Files with an extension no importer
+    /// knows are tried as SVG, which is what they most likely are when they came without one.
+    pub fn load_file(&self, path: &Path, opts: &LoadOptions) -> Result<Document, LoadError> {
+        let opts = LoadOptions { resources_dir: path.parent().map(|p| p.to_path_buf()), ..opts.clone() };
+        let data = fs::read(path)?;
+        match self.importer_for(path) {
+            Some(importer) => importer.import(&data, &opts),
+            None => load_data(&data, &opts),
+        }
+    }
+
+    /// Write `doc` to `path` with the exporter for its extension.
+    pub fn save_file(&self, doc: &Document, path: &Path) -> Result<(), Box<dyn Error>> {
+        let exporter = self.exporter_for(path).ok_or_else(|| format!("No format for {}", path.display()))?;
+        fs::write(path, exporter.export(doc)?)?;
+        Ok(())
+    }
+}
+
+/// Plain and gzip compressed SVG in, plain SVG out.
+struct SvgFormat;
+
+impl Importer for SvgFormat {
+    fn name(&self) -> &str {
+        "SVG"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["svg", "svgz"]
+    }
+
+    fn import(&self, data: &[u8], opts: &LoadOptions) -> Result<Document, LoadError> {
+        load_data(data, opts)
+    }
+}
+
+impl Exporter for SvgFormat {
+    fn name(&self) -> &str {
+        "SVG"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["svg"]
+    }
+
+    fn export(&self, doc: &Document) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(to_svg_string_with(doc, SaveOptions::default()).into_bytes())
+    }
+}
+
+struct SvgzFormat;
+
+impl Exporter for SvgzFormat {
+    fn name(&self) -> &str {
+        "Compressed SVG"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["svgz"]
+    }
+
+    fn export(&self, doc: &Document) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(to_svg_string_with(doc, SaveOptions::default()).as_bytes())?;
+        Ok(encoder.finish()?)
+    }
+}
+
+struct PdfFormat;
+
+impl Exporter for PdfFormat {
+    fn name(&self) -> &str {
+        "PDF"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["pdf"]
+    }
+
+    fn export(&self, doc: &Document) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(to_pdf(std::slice::from_ref(doc)))
+    }
+}
+
+/// A standalone LaTeX document, File > Export TikZ has the other settings.
+struct TikzFormat;
+
+impl Exporter for TikzFormat {
+    fn name(&self) -> &str {
+        "TikZ"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tex"]
+    }
+
+    fn export(&self, doc: &Document) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(to_tikz(doc, &TikzSettings { standalone: true, ..Default::default() }).into_bytes())
+    }
+}
@@ -0,0 +1,196 @@
+use crate::document::Segment;
+
+/// Smooth cubics through `points`, such as a stroke of the pointer, staying within
+/// `tolerance` of them (Schneider's algorithm). Corners where the points turn sharply survive
+/// as long as the tolerance is small compared to the stroke. Empty for fewer than two
+/// distinct points.
+pub fn fit_curve(points: &[[f32; 2]], tolerance: f32) -> Vec<Segment> {
+    let mut distinct: Vec<[f32; 2]> = vec![];
+    for &p in points {
+        if distinct.last().is_none_or(|&q| distance(p, q) > 1e-6) {
+            distinct.push(p);
+        }
+    }
+    let n = distinct.len();
+    if n < 2 {
+        return vec![];
+    }
+    let mut out = vec![Segment::MoveTo(distinct[0])];
+    let start = normalize(sub(distinct[1], distinct[0]));
+    let end = normalize(sub(distinct[n - 2], distinct[n - 1]));
+    fit_cubic(&distinct, start, end, tolerance.max(1e-6), &mut out);
+    out
+}
+
+/// Outline of a stroke along `points` that is `widths[i]` wide at each of them, filled
+/// rather than stroked, each side fitted like [`fit_curve`].
+pub fn variable_width_outline(points: &[[f32; 2]], widths: &[f32], tolerance: f32) -> Vec<Segment> {
+    let n = points.len().min(widths.len());
+    if n < 2 {
+        return vec![];
+    }
+    let mut left = vec![];
+    let mut right = vec![];
+    for i in 0..n {
+        let d = normalize(sub(points[(i + 1).min(n - 1)], points[i.saturating_sub(1)]));
+        let normal = [-d[1] * widths[i] * 0.5, d[0] * widths[i] * 0.5];
+        left.push([points[i][0] + normal[0], points[i][1] + normal[1]]);
+        right.push([points[i][0] - normal[0], points[i][1] - normal[1]]);
+    }
+    right.reverse();
+    let mut segments = fit_curve(&left, tolerance);
+    let back = fit_curve(&right, tolerance);
+    let Some(&Segment::MoveTo(turn)) = back.first() else { return vec![] };
+    if segments.is_empty() {
+        return vec![];
+    }
+    segments.push(Segment::LineTo(turn));
+    segments.extend(back.into_iter().skip(1));
+    segments.push(Segment::Close);
+    segments
+}
+
+/// Fit `points` with one cubic leaving the first along `start` and reaching the last from
+/// `end`, or split where it misses them most and fit both halves.
+fn fit_cubic(points: &[[f32; 2]], start: [f32; 2], end: [f32; 2], tolerance: f32, out: &mut Vec<Segment>) {
+    let (first, last) = (points[0], points[points.len() - 1]);
+    if points.len() == 2 {
+        let d = distance(first, last) / 3.0;
+        out.push(Segment::CubicTo(add(first, scale(start, d)), add(last, scale(end, d)), last));
+        return;
+    }
+
+    let mut params = chord_lengths(points);
+    let mut bezier = least_squares(points, &params, start, end);
+    let (mut error, mut split) = max_error(points, &bezier, &params);
+    if error > tolerance * tolerance && error <= 16.0 * tolerance * tolerance {
+        // close, a better parametrisation may be enough
+        for _ in 0..4 {
+            params = params.iter().zip(points).map(|(&u, &p)| newton_step(&bezier, p, u)).collect();
+            bezier = least_squares(points, &params, start, end);
+            (error, split) = max_error(points, &bezier, &params);
+            if error <= tolerance * tolerance {
+                break;
+            }
+        }
+    }
+    if error <= tolerance * tolerance {
+        out.push(Segment::CubicTo(bezier[1], bezier[2], bezier[3]));
+        return;
+    }
+
+    let center = normalize(sub(points[split - 1], points[split + 1]));
+    fit_cubic(&points[..=split], start, center, tolerance, out);
+    fit_cubic(&points[split..], scale(center, -1.0), end, tolerance, out);
+}
+
+/// Parameter of each point, its distance along the polyline as a fraction of the length.
+fn chord_lengths(points: &[[f32; 2]]) -> Vec<f32> {
+    let mut params = vec![0.0];
+    for pair in points.windows(2) {
+        params.push(params[params.len() - 1] + distance(pair[0], pair[1]));
+    }
+    let total = params[params.len() - 1];
+    params.iter().map(|&u| u / total).collect()
+}
+
+/// The cubic from the first to the last point along the tangents that comes closest to the
+/// points at their parameters.
+fn least_squares(points: &[[f32; 2]], params: &[f32], start: [f32; 2], end: [f32; 2]) -> [[f32; 2]; 4] {
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let mut c = [[0.0f32; 2]; 2];
+    let mut x = [0.0f32; 2];
+    for (&p, &u) in points.iter().zip(params) {
+        let b = bernstein(u);
+        let a = [scale(start, b[1]), scale(end, b[2])];
+        c[0][0] += dot(a[0], a[0]);
+        c[0][1] += dot(a[0], a[1]);
+        c[1][1] += dot(a[1], a[1]);
+        let rest = sub(p, add(scale(first, b[0] + b[1]), scale(last, b[2] + b[3])));
+        x[0] += dot(a[0], rest);
+        x[1] += dot(a[1], rest);
+    }
+    c[1][0] = c[0][1];
+    let det = c[0][0] * c[1][1] - c[0][1] * c[1][0];
+    let (mut alpha_start, mut alpha_end) = if det.abs() > 1e-12 {
+        ((x[0] * c[1][1] - x[1] * c[0][1]) / det, (c[0][0] * x[1] - c[1][0] * x[0]) / det)
+    } else {
+        (0.0, 0.0)
+    };
+    // handles pointing backwards or collapsing, fall back to a third of the chord
+    let length = distance(first, last);
+    if alpha_start < length * 1e-6 || alpha_end < length * 1e-6 {
+        alpha_start = length / 3.0;
+        alpha_end = length / 3.0;
+    }
+    [first, add(first, scale(start, alpha_start)), add(last, scale(end, alpha_end)), last]
+}
+
+/// Largest squared distance between the points and the cubic at their parameters, and the
+/// point where it is.
+fn max_error(points: &[[f32; 2]], bezier: &[[f32; 2]; 4], params: &[f32]) -> (f32, usize) {
+    let mut worst = (0.0, points.len() / 2);
+    for i in 1..points.len() - 1 {
+        let d = sub(evaluate(bezier, params[i]), points[i]);
+        let error = dot(d, d);
+        if error > worst.0 {
+            worst = (error, i);
+        }
+    }
+    worst
+}
+
+/// `u` moved towards the parameter of the point on the cubic nearest to `p`.
+fn newton_step(bezier: &[[f32; 2]; 4], p: [f32; 2], u: f32) -> f32 {
+    let d = sub(evaluate(bezier, u), p);
+    let first: [[f32; 2]; 3] = std::array::from_fn(|i| scale(sub(bezier[i + 1], bezier[i]), 3.0));
+    let second: [[f32; 2]; 2] = std::array::from_fn(|i| scale(sub(first[i + 1], first[i]), 2.0));
+    let v = 1.0 - u;
+    let d1 = add(add(scale(first[0], v * v), scale(first[1], 2.0 * v * u)), scale(first[2], u * u));
+    let d2 = add(scale(second[0], v), scale(second[1], u));
+    let denominator = dot(d1, d1) + dot(d, d2);
+    if denominator.abs() < 1e-12 {
+        u
+    } else {
+        (u - dot(d, d1) / denominator).clamp(0.0, 1.0)
+    }
+}
+
+fn bernstein(u: f32) -> [f32; 4] {
+    let v = 1.0 - u;
+    [v * v * v, 3.0 * v * v * u, 3.0 * v * u * u, u * u * u]
+}
+
+fn evaluate(bezier: &[[f32; 2]; 4], u: f32) -> [f32; 2] {
+    let b = bernstein(u);
+    (0..4).fold([0.0, 0.0], |acc, i| add(acc, scale(bezier[i], b[i])))
+}
+
+fn add(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] + b[0], a[1] + b[1]]
+}
+
+fn sub(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+fn scale(a: [f32; 2], k: f32) -> [f32; 2] {
+    [a[0] * k, a[1] * k]
+}
+
+fn dot(a: [f32; 2], b: [f32; 2]) -> f32 {
+    a[0] * b[0] + a[1] * b[1]
+}
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    (a[0] - b[0]).hypot(a[1] - b[1])
+}
+
+fn normalize(a: [f32; 2]) -> [f32; 2] {
+    let length = a[0].hypot(a[1]);
+    if length > 0.0 {
+        scale(a, 1.0 / length)
+    } else {
+        a
+    }
+}
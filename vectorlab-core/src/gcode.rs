@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+
+use resvg::usvg::Transform;
+
+use crate::document::{transform_point, transform_scale, Contour, Document, ElementId};
+use crate::loader::flatten_segments;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GcodeUnits {
+    Mm,
+    Inch,
+}
+
+impl GcodeUnits {
+    /// Units in a millimeter.
+    pub fn per_mm(self) -> f32 {
+        match self {
+            Self::Mm => 1.0,
+            Self::Inch => 1.0 / 25.4,
+        }
+    }
+}
+
+/// Where the machine's 0,0 is on the page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GcodeOrigin {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Center,
+}
+
+impl GcodeOrigin {
+    pub const ALL: [GcodeOrigin; 5] = [Self::BottomLeft, Self::TopLeft, Self::BottomRight, Self::TopRight, Self::Center];
+
+    /// As in the settings file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::TopLeft => "top-left",
+            Self::TopRight => "top-right",
+            Self::BottomLeft => "bottom-left",
+            Self::BottomRight => "bottom-right",
+            Self::Center => "center",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|o| o.name() == name)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::TopLeft => "Top left",
+            Self::TopRight => "Top right",
+            Self::BottomLeft => "Bottom left",
+            Self::BottomRight => "Bottom right",
+            Self::Center => "Center",
+        }
+    }
+
+    /// Where it is on a page of `size`, y down.
+    pub(crate) fn on_page(self, [w, h]: [f32; 2]) -> [f32; 2] {
+        match self {
+            Self::TopLeft => [0.0, 0.0],
+            Self::TopRight => [w, 0.0],
+            Self::BottomLeft => [0.0, h],
+            Self::BottomRight => [w, h],
+            Self::Center => [w * 0.5, h * 0.5],
+        }
+    }
+}
+
+/// How the machine is driven, see `to_gcode`.
+#[derive(Clone, Debug)]
+pub struct GcodeSettings {
+    /// Speed while cutting or drawing, `units` per minute
+    pub feed_rate: f32,
+    /// Speed of the moves in between, `units` per minute
+    pub travel_rate: f32,
+    /// Switch a laser on with M3 and off with M5 instead of lowering and lifting a pen or
+    /// spindle along Z
+    pub laser: bool,
+    /// Z for travelling and for cutting, in `units`
+    pub z_up: f32,
+    pub z_down: f32,
+    /// S word of M3
+    pub laser_power: f32,
+    pub units: GcodeUnits,
+    pub origin: GcodeOrigin,
+    /// The machine's x axis points left and its y axis down, instead of right and up
+    pub invert_x: bool,
+    pub invert_y: bool,
+    /// How far flattened curves may deviate from the true ones, in `units`
+    pub tolerance: f32,
+    /// Reorder and reverse the paths of each layer to shorten the travel, see
+    /// `optimize_contours`
+    pub optimize: bool,
+    /// How often each layer is cut, see `plot_contours`. 1 for those not listed, 0 skips them
+    pub passes: HashMap<ElementId, u32>,
+}
+
+impl Default for GcodeSettings {
+    fn default() -> Self {
+        Self {
+            feed_rate: 1000.0,
+            travel_rate: 3000.0,
+            laser: false,
+            z_up: 5.0,
+            z_down: 0.0,
+            laser_power: 1000.0,
+            units: GcodeUnits::Mm,
+            origin: GcodeOrigin::BottomLeft,
+            invert_x: false,
+            invert_y: false,
+            tolerance: 0.05,
+            optimize: true,
+            passes: HashMap::new(),
+        }
+    }
+}
+
+/// A straight move of the tool, in document coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ToolMove {
+    pub from: [f32; 2],
+    pub to: [f32; 2],
+    /// with the pen down or the laser on, otherwise travelling
+    pub cutting: bool,
+}
+
+impl ToolMove {
+    pub fn length(&self) -> f32 {
+        ((self.to[0] - self.from[0]).powi(2) + (self.to[1] - self.from[1]).powi(2)).sqrt()
+    }
+}
+
+/// Document coordinates to the machine's, in `settings.units`.
+pub(crate) fn to_machine(doc: &Document, settings: &GcodeSettings) -> Transform {
+    let scale = 25.4 / 96.0 * settings.units.per_mm();
+    let [x, y] = settings.origin.on_page(doc.size.map(|v| v * scale));
+    let sx = if settings.invert_x { -1.0 } else { 1.0 };
+    let sy = if settings.invert_y { 1.0 } else { -1.0 };
+    Transform::from_row(sx, 0.0, 0.0, sy, -sx * x, -sy * y).pre_scale(scale, scale).pre_concat(doc.viewport_transform())
+}
+
+/// Outlines of the visible paths in document coordinates and paint order, flattened so
+/// they stay within `tolerance` document units of the curves, each with the layer it belongs
+/// to: its top-level group, or the root for paths not in a group.
+pub fn plot_contours(doc: &Document, tolerance: f32) -> Vec<(ElementId, Contour)> {
+    path_contours(doc, tolerance)
+        .into_iter()
+        .map(|(id, contour)| {
+            let mut layer = id;
+            while let Some(parent) = doc.get(layer).parent.filter(|&p| p != doc.root) {
+                layer = parent;
+            }
+            // paths outside of any group form one layer
+            (if layer == id { doc.root } else { layer }, contour)
+        })
+        .collect()
+}
+
+/// Like [`plot_contours`], but each outline with the path it comes from.
+pub fn path_contours(doc: &Document, tolerance: f32) -> Vec<(ElementId, Contour)> {
+    let mut contours = vec![];
+    doc.walk(|id, element, ts, _| {
+        let Some(path) = element.as_path() else { return };
+        // the tolerance is in the path's own coordinates
+        let local = tolerance / transform_scale(&ts).max(f32::EPSILON);
+        for contour in flatten_segments(&path.segments, local) {
+            let points = contour.points.iter().map(|&p| transform_point(&ts, p)).collect();
+            contours.push((id, Contour { points, closed: contour.closed }));
+        }
+    });
+    contours
+}
+
+/// The contours to cut, each layer repeated as often as `settings.passes` says.
+pub fn job_contours(doc: &Document, settings: &GcodeSettings) -> Vec<Contour> {
+    let per_unit = doc.mm_per_unit();
+    let tolerance = settings.tolerance / settings.units.per_mm() / per_unit[0].max(per_unit[1]).max(f32::EPSILON);
+    let contours = plot_contours(doc, tolerance);
+    let mut job: Vec<Contour> = vec![];
+    let mut start = 0;
+    // consecutive contours of one layer are repeated together, pass after pass
+    while start < contours.len() {
+        let layer = contours[start].0;
+        let end = contours[start..].iter().position(|(l, _)| *l != layer).map_or(contours.len(), |n| start + n);
+        let passes = settings.passes.get(&layer).copied().unwrap_or(1);
+        let mut run: Vec<Contour> = contours[start..end].iter().map(|(_, c)| c.clone()).collect();
+        if settings.optimize && passes > 0 {
+            let at = job.last().and_then(end_point).unwrap_or_else(|| machine_origin(doc, settings));
+            optimize_contours(&mut run, at);
+        }
+        for _ in 0..passes {
+            job.extend(run.iter().cloned());
+        }
+        start = end;
+    }
+    job
+}
+
+/// Where cutting along `contour` ends.
+fn end_point(contour: &Contour) -> Option<[f32; 2]> {
+    if contour.closed { contour.points.first() } else { contour.points.last() }.copied()
+}
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt()
+}
+
+/// Length of the moves with the tool up when cutting `contours` in order, from `home` and
+/// back to it.
+pub fn travel_length(contours: &[Contour], home: [f32; 2]) -> f32 {
+    let mut at = home;
+    let mut length = 0.0;
+    for contour in contours {
+        let Some(&first) = contour.points.first() else { continue };
+        length += distance(at, first);
+        at = end_point(contour).unwrap_or(first);
+    }
+    length + distance(at, home)
+}
+
+/// Shorten the travel between `contours` for pen plotters: nearest neighbor first, starting
+/// at `home`. Open contours are reversed when their end is closer, closed ones start at
+/// their point closest to where the pen is. Greedy, so not the shortest tour, but usually
+/// far shorter than the drawing order.
+pub fn optimize_contours(contours: &mut Vec<Contour>, home: [f32; 2]) {
+    optimize_by(contours, |c| c, home);
+}
+
+/// [`optimize_contours`] for contours that come with the path they belong to.
+pub fn optimize_path_contours(contours: &mut Vec<(ElementId, Contour)>, home: [f32; 2]) {
+    optimize_by(contours, |(_, c)| c, home);
+}
+
+fn optimize_by<T>(contours: &mut Vec<T>, contour_of: fn(&mut T) -> &mut Contour, home: [f32; 2]) {
+    let mut left: Vec<T> = std::mem::take(contours);
+    let mut at = home;
+    while !left.is_empty() {
+        // closed contours are only compared by their first point, looking at all of them
+        // would make this quadratic in the points instead of the contours
+        let (i, reverse) = left
+            .iter_mut()
+            .map(contour_of)
+            .enumerate()
+            .flat_map(|(i, c)| {
+                let start = c.points.first().map_or(f32::INFINITY, |&p| distance(at, p));
+                let end = if c.closed { f32::INFINITY } else { c.points.last().map_or(f32::INFINITY, |&p| distance(at, p)) };
+                [(start, i, false), (end, i, true)]
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, i, reverse)| (i, reverse))
+            .unwrap_or((0, false));
+        let mut item = left.swap_remove(i);
+        let contour = contour_of(&mut item);
+        if reverse {
+            contour.points.reverse();
+        } else if contour.closed {
+            let nearest = contour.points.iter().enumerate().min_by(|a, b| distance(at, *a.1).total_cmp(&distance(at, *b.1))).map_or(0, |(i, _)| i);
+            contour.points.rotate_left(nearest);
+        }
+        at = end_point(contour).unwrap_or(at);
+        contours.push(item);
+    }
+}
+
+/// Where the machine's origin is, in document coordinates.
+pub fn machine_origin(doc: &Document, settings: &GcodeSettings) -> [f32; 2] {
+    let to_doc = to_machine(doc, settings).invert().unwrap_or_default();
+    transform_point(&to_doc, [0.0, 0.0])
+}
+
+/// Travel and cutting moves along `contours`, starting and ending at `home`.
+pub fn tool_moves(contours: &[Contour], home: [f32; 2]) -> Vec<ToolMove> {
+    contour_moves(contours, home).into_iter().map(|(m, _)| m).collect()
+}
+
+/// [`tool_moves`] with the index of the contour each cutting move belongs to.
+pub(crate) fn contour_moves(contours: &[Contour], home: [f32; 2]) -> Vec<(ToolMove, Option<usize>)> {
+    let mut moves = vec![];
+    let mut at = home;
+    for (i, contour) in contours.iter().enumerate() {
+        let Some(&first) = contour.points.first() else { continue };
+        if first != at {
+            moves.push((ToolMove { from: at, to: first, cutting: false }, None));
+        }
+        let closing = if contour.closed { Some(first) } else { None };
+        at = first;
+        for &p in contour.points[1..].iter().chain(closing.iter()) {
+            moves.push((ToolMove { from: at, to: p, cutting: true }, Some(i)));
+            at = p;
+        }
+    }
+    if at != home {
+        moves.push((ToolMove { from: at, to: home, cutting: false }, None));
+    }
+    moves
+}
+
+/// G-code for `moves`, from `tool_moves`.
+pub fn to_gcode(doc: &Document, moves: &[ToolMove], settings: &GcodeSettings) -> String {
+    gcode_lines(doc, moves, settings).into_iter().map(|(line, _)| line + "\n").collect()
+}
+
+/// The lines of [`to_gcode`], each with the index of the move it is part of. Lowering the
+/// tool goes with the move after it, raising it with the one before.
+pub(crate) fn gcode_lines(doc: &Document, moves: &[ToolMove], settings: &GcodeSettings) -> Vec<(String, Option<usize>)> {
+    let ts = to_machine(doc, settings);
+    let mut out = vec![];
+    out.push((format!("; VectorLab, {} moves", moves.len()), None));
+    out.push((format!("{} ; {}", if settings.units == GcodeUnits::Mm { "G21" } else { "G20" }, if settings.units == GcodeUnits::Mm { "mm" } else { "inch" }), None));
+    out.push(("G90 ; absolute coordinates".to_string(), None));
+    let (up, down) = if settings.laser {
+        ("M5".to_string(), format!("M3 S{}", num(settings.laser_power)))
+    } else {
+        (format!("G0 Z{}", num(settings.z_up)), format!("G1 Z{} F{}", num(settings.z_down), num(settings.feed_rate)))
+    };
+    out.push((up.clone(), None));
+    let mut cutting = false;
+    for (i, m) in moves.iter().enumerate() {
+        let [x, y] = transform_point(&ts, m.to);
+        if m.cutting != cutting {
+            let with = if m.cutting { Some(i) } else { i.checked_sub(1) };
+            out.push((if m.cutting { down.clone() } else { up.clone() }, with));
+            cutting = m.cutting;
+        }
+        if m.cutting {
+            out.push((format!("G1 X{} Y{} F{}", num(x), num(y), num(settings.feed_rate)), Some(i)));
+        } else {
+            out.push((format!("G0 X{} Y{} F{}", num(x), num(y), num(settings.travel_rate)), Some(i)));
+        }
+    }
+    if cutting {
+        out.push((up, moves.len().checked_sub(1)));
+    }
+    out.push(("M2".to_string(), None));
+    out
+}
+
+/// Three decimals are a micrometer, well below what the machines resolve.
+fn num(v: f32) -> String {
+    let s = format!("{:.3}", v);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" { "0".to_string() } else { s.to_string() }
+}
@@ -0,0 +1,434 @@
+use std::collections::HashMap;
+use std::f32::consts::{PI, TAU};
+
+use crate::document::{bbox_of, Segment};
+use crate::shapes::{ellipse, polyline};
+
+/// Symbols an L-system may grow to before it stops iterating.
+const MAX_SYMBOLS: usize = 1_000_000;
+
+/// L-systems to start from as name, axiom, rules and angle. F, G, A and B draw forward, f
+/// moves, + and - turn, [ and ] save and restore the turtle, | turns around.
+pub const LSYSTEM_PRESETS: [(&str, &str, &str, f32); 6] = [
+    ("Koch snowflake", "F--F--F", "F=F+F--F+F", 60.0),
+    ("Sierpinski triangle", "F-G-G", "F=F-G+F+G-F; G=GG", 120.0),
+    ("Dragon curve", "FX", "X=X+YF+; Y=-FX-Y", 90.0),
+    ("Hilbert curve", "X", "X=+YF-XFX-FY+; Y=-XF+YFY+FX-", 90.0),
+    ("Gosper curve", "A", "A=A-B--B+A++AA+B-; B=+A-BB--B-A++A+B", 60.0),
+    ("Fractal plant", "X", "X=F+[[X]-X]-F[-FX]+X; F=FF", 25.0),
+];
+
+/// Parametric drawings of the Generate menu. Lengths are in mm.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Generator {
+    /// The curve of a pen in a toothed wheel rolling inside or around a ring, `pen` its
+    /// distance from the wheel's center with 1 at the rim
+    Spirograph { ring: u32, wheel: u32, pen: f32, outside: bool, radius: f32 },
+    /// A turtle drawing of the axiom rewritten by the rules, `X=…` separated by `;` or lines,
+    /// scaled to `size`
+    LSystem { axiom: String, rules: String, angle: f32, iterations: u32, size: f32 },
+    /// A perfect maze of square cells, entered at the top left and left at the bottom right
+    Maze { columns: u32, rows: u32, cell: f32, seed: u64 },
+    /// A perfect maze of rings around a middle cell, left through the outer ring
+    ThetaMaze { rings: u32, cell: f32, seed: u64 },
+    /// {points/skip}: every skip-th of `points` corners joined, in several loops where they
+    /// share a factor
+    Star { points: u32, skip: u32, radius: f32 },
+    /// Spur gear with involute teeth, `module` mm of pitch diameter per tooth
+    Gear { teeth: u32, module: f32, pressure_angle: f32, bore: f32 },
+}
+
+impl Generator {
+    /// Each kind with parameters to start from.
+    pub fn all() -> Vec<Generator> {
+        let (_, axiom, rules, angle) = LSYSTEM_PRESETS[0];
+        vec![
+            Self::Spirograph { ring: 96, wheel: 35, pen: 0.8, outside: false, radius: 60.0 },
+            Self::LSystem { axiom: axiom.to_string(), rules: rules.to_string(), angle, iterations: 4, size: 100.0 },
+            Self::Maze { columns: 20, rows: 20, cell: 5.0, seed: 1 },
+            Self::ThetaMaze { rings: 10, cell: 5.0, seed: 1 },
+            Self::Star { points: 7, skip: 3, radius: 50.0 },
+            Self::Gear { teeth: 24, module: 2.0, pressure_angle: 20.0, bore: 8.0 },
+        ]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Spirograph { .. } => "Spirograph",
+            Self::LSystem { .. } => "L-system",
+            Self::Maze { .. } => "Maze",
+            Self::ThetaMaze { .. } => "Theta maze",
+            Self::Star { .. } => "Star polygon",
+            Self::Gear { .. } => "Gear",
+        }
+    }
+
+    /// The drawing in mm, centered on the origin, y down.
+    pub fn segments(&self) -> Vec<Segment> {
+        match self {
+            Self::Spirograph { ring, wheel, pen, outside, radius } => spirograph(*ring, *wheel, *pen, *outside, *radius),
+            Self::LSystem { axiom, rules, angle, iterations, size } => l_system(axiom, rules, *angle, *iterations, *size),
+            Self::Maze { columns, rows, cell, seed } => maze(*columns, *rows, *cell, *seed),
+            Self::ThetaMaze { rings, cell, seed } => theta_maze(*rings, *cell, *seed),
+            Self::Star { points, skip, radius } => star(*points, *skip, *radius),
+            Self::Gear { teeth, module, pressure_angle, bore } => gear(*teeth, *module, *pressure_angle, *bore),
+        }
+    }
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// xorshift, so a seed always gives the same maze.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // zero would stay zero
+        Self(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+/// Straight lines between the pairs of points, those meeting end to end joined, for fewer
+/// pen lifts.
+fn lines(pairs: &[[[f32; 2]; 2]]) -> Vec<Segment> {
+    let mut segments = vec![];
+    let mut last = None;
+    for &[a, b] in pairs {
+        if last != Some(a) {
+            segments.push(Segment::MoveTo(a));
+        }
+        segments.push(Segment::LineTo(b));
+        last = Some(b);
+    }
+    segments
+}
+
+fn spirograph(ring: u32, wheel: u32, pen: f32, outside: bool, radius: f32) -> Vec<Segment> {
+    let ring = ring.max(2);
+    let wheel = if outside { wheel.max(1) } else { wheel.clamp(1, ring - 1) };
+    let (big, small) = (ring as f32, wheel as f32);
+    let d = pen * small;
+    // centers of the wheel and the pen around it
+    let (arm, sign) = if outside { (big + small, -1.0) } else { (big - small, 1.0) };
+    let ratio = arm / small;
+    let scale = radius / (arm + d).max(f32::EPSILON);
+    // back where it started once the wheel has rolled a whole number of its teeth
+    let turns = wheel / gcd(ring, wheel);
+    let steps = ((turns as f32 * 64.0 * (ratio + 1.0)) as usize).clamp(64, 200_000);
+    let points: Vec<[f32; 2]> = (0..steps)
+        .map(|i| {
+            let t = TAU * turns as f32 * i as f32 / steps as f32;
+            [(arm * t.cos() + sign * d * (ratio * t).cos()) * scale, (arm * t.sin() - d * (ratio * t).sin()) * scale]
+        })
+        .collect();
+    polyline(&points, true)
+}
+
+fn l_system(axiom: &str, rules: &str, angle: f32, iterations: u32, size: f32) -> Vec<Segment> {
+    let rules: HashMap<char, &str> = rules
+        .split([';', '\n'])
+        .filter_map(|rule| {
+            let (from, to) = rule.split_once("->").or_else(|| rule.split_once('='))?;
+            let mut from = from.trim().chars();
+            let symbol = from.next().filter(|_| from.next().is_none())?;
+            Some((symbol, to.trim()))
+        })
+        .collect();
+    let mut text = axiom.trim().to_string();
+    for _ in 0..iterations {
+        let mut grown = String::with_capacity(text.len() * 2);
+        for c in text.chars() {
+            match rules.get(&c) {
+                Some(replacement) => grown.push_str(replacement),
+                None => grown.push(c),
+            }
+        }
+        if grown.len() > MAX_SYMBOLS {
+            break;
+        }
+        text = grown;
+    }
+
+    // the turtle starts facing right, + turns counterclockwise on the page
+    let turn = angle.to_radians();
+    let (mut at, mut heading) = ([0.0f32, 0.0f32], 0.0f32);
+    let mut stack = vec![];
+    let mut runs: Vec<Vec<[f32; 2]>> = vec![vec![at]];
+    for c in text.chars() {
+        match c {
+            'F' | 'G' | 'A' | 'B' | 'f' => {
+                at = [at[0] + heading.cos(), at[1] - heading.sin()];
+                if c == 'f' {
+                    runs.push(vec![at]);
+                } else if let Some(run) = runs.last_mut() {
+                    run.push(at);
+                }
+            }
+            '+' => heading += turn,
+            '-' => heading -= turn,
+            '|' => heading += PI,
+            '[' => stack.push((at, heading)),
+            ']' => {
+                if let Some(saved) = stack.pop() {
+                    (at, heading) = saved;
+                    runs.push(vec![at]);
+                }
+            }
+            _ => {}
+        }
+    }
+    runs.retain(|run| run.len() > 1);
+    let Some(b) = bbox_of(runs.iter().flatten()) else { return vec![] };
+    let scale = size / (b[2] - b[0]).max(b[3] - b[1]).max(f32::EPSILON);
+    let center = [(b[0] + b[2]) * 0.5, (b[1] + b[3]) * 0.5];
+    runs.iter()
+        .flat_map(|run| {
+            let points: Vec<[f32; 2]> = run.iter().map(|p| [(p[0] - center[0]) * scale, (p[1] - center[1]) * scale]).collect();
+            polyline(&points, false)
+        })
+        .collect()
+}
+
+fn maze(columns: u32, rows: u32, cell: f32, seed: u64) -> Vec<Segment> {
+    let (w, h) = (columns.max(1) as usize, rows.max(1) as usize);
+    // walls on the top of each cell, a row more for the bottom edge, and on the left of
+    // each, a column more for the right edge
+    let mut top = vec![true; w * (h + 1)];
+    let mut left = vec![true; (w + 1) * h];
+    let mut visited = vec![false; w * h];
+    let mut rng = Rng::new(seed);
+    let mut stack = vec![(0usize, 0usize)];
+    visited[0] = true;
+    while let Some(&(x, y)) = stack.last() {
+        let mut next = vec![];
+        if x > 0 && !visited[y * w + x - 1] {
+            next.push((x - 1, y));
+        }
+        if x + 1 < w && !visited[y * w + x + 1] {
+            next.push((x + 1, y));
+        }
+        if y > 0 && !visited[(y - 1) * w + x] {
+            next.push((x, y - 1));
+        }
+        if y + 1 < h && !visited[(y + 1) * w + x] {
+            next.push((x, y + 1));
+        }
+        if next.is_empty() {
+            stack.pop();
+            continue;
+        }
+        let (nx, ny) = next[rng.below(next.len())];
+        match (nx as i64 - x as i64, ny as i64 - y as i64) {
+            (-1, _) => left[y * (w + 1) + x] = false,
+            (1, _) => left[y * (w + 1) + nx] = false,
+            (_, -1) => top[y * w + x] = false,
+            _ => top[ny * w + x] = false,
+        }
+        visited[ny * w + nx] = true;
+        stack.push((nx, ny));
+    }
+    // the way in and out
+    top[0] = false;
+    top[h * w + w - 1] = false;
+
+    let origin = [-(w as f32) * cell * 0.5, -(h as f32) * cell * 0.5];
+    let at = |x: usize, y: usize| [origin[0] + x as f32 * cell, origin[1] + y as f32 * cell];
+    let mut pairs = vec![];
+    // walls in a row or column as one line
+    for y in 0..=h {
+        let mut x = 0;
+        while x < w {
+            if top[y * w + x] {
+                let start = x;
+                while x < w && top[y * w + x] {
+                    x += 1;
+                }
+                pairs.push([at(start, y), at(x, y)]);
+            } else {
+                x += 1;
+            }
+        }
+    }
+    for x in 0..=w {
+        let mut y = 0;
+        while y < h {
+            if left[y * (w + 1) + x] {
+                let start = y;
+                while y < h && left[y * (w + 1) + x] {
+                    y += 1;
+                }
+                pairs.push([at(x, start), at(x, y)]);
+            } else {
+                y += 1;
+            }
+        }
+    }
+    lines(&pairs)
+}
+
+/// Cubics along the circle of `radius` around the origin from angle `a0` to `a1`, starting
+/// with a move unless the pen is there already.
+fn arc(radius: f32, a0: f32, a1: f32, segments: &mut Vec<Segment>) {
+    let pieces = ((a1 - a0).abs() / (PI / 2.0)).ceil().max(1.0) as usize;
+    let step = (a1 - a0) / pieces as f32;
+    let k = 4.0 / 3.0 * (step / 4.0).tan() * radius;
+    let at = |a: f32| [radius * a.cos(), radius * a.sin()];
+    segments.push(Segment::MoveTo(at(a0)));
+    for i in 0..pieces {
+        let (s, e) = (a0 + step * i as f32, a0 + step * (i + 1) as f32);
+        let (p0, p1) = (at(s), at(e));
+        segments.push(Segment::CubicTo([p0[0] - k * s.sin(), p0[1] + k * s.cos()], [p1[0] + k * e.sin(), p1[1] - k * e.cos()], p1));
+    }
+}
+
+fn theta_maze(rings: u32, cell: f32, seed: u64) -> Vec<Segment> {
+    let rings = rings.max(1) as usize;
+    // cells in each ring, the middle one alone and six around it, doubled once they would
+    // get twice as wide as deep
+    let mut counts = vec![1usize, 6];
+    for ring in 2..=rings {
+        let previous = counts[ring - 1];
+        counts.push(if TAU * ring as f32 / previous as f32 >= 2.0 { previous * 2 } else { previous });
+    }
+    let index = |ring: usize, j: usize| counts[..ring].iter().sum::<usize>() + j;
+    let total: usize = counts.iter().sum();
+    // open passages: to the cell inward, and to the next cell counterclockwise
+    let mut inward = vec![false; total];
+    let mut around = vec![false; total];
+    let mut visited = vec![false; total];
+    let mut rng = Rng::new(seed);
+    let mut stack = vec![(0usize, 0usize)];
+    visited[0] = true;
+    while let Some(&(ring, j)) = stack.last() {
+        let n = counts[ring];
+        let mut next = vec![];
+        if ring > 0 {
+            next.push((ring, (j + 1) % n));
+            next.push((ring, (j + n - 1) % n));
+            let inner = counts[ring - 1];
+            next.push((ring - 1, j * inner / n));
+        }
+        if ring < rings {
+            let outer = counts[ring + 1];
+            let per = outer / n;
+            next.extend((0..per).map(|k| (ring + 1, j * per + k)));
+        }
+        next.retain(|&(r, k)| !visited[index(r, k)]);
+        if next.is_empty() {
+            stack.pop();
+            continue;
+        }
+        let (nr, nj) = next[rng.below(next.len())];
+        if nr == ring {
+            // the passage belongs to the cell clockwise of it
+            let from = if nj == (j + 1) % n { j } else { nj };
+            around[index(ring, from)] = true;
+        } else if nr < ring {
+            inward[index(ring, j)] = true;
+        } else {
+            inward[index(nr, nj)] = true;
+        }
+        visited[index(nr, nj)] = true;
+        stack.push((nr, nj));
+    }
+
+    let mut segments = vec![];
+    for ring in 1..=rings {
+        let n = counts[ring];
+        let angle = |j: usize| TAU * j as f32 / n as f32;
+        // inner walls of the ring, neighbors joined
+        let mut j = 0;
+        while j < n {
+            if inward[index(ring, j)] {
+                j += 1;
+                continue;
+            }
+            let start = j;
+            while j < n && !inward[index(ring, j)] {
+                j += 1;
+            }
+            arc(ring as f32 * cell, angle(start), angle(j), &mut segments);
+        }
+        for j in 0..n {
+            if !around[index(ring, j)] {
+                let a = angle(j + 1);
+                segments.push(Segment::MoveTo([ring as f32 * cell * a.cos(), ring as f32 * cell * a.sin()]));
+                segments.push(Segment::LineTo([(ring + 1) as f32 * cell * a.cos(), (ring + 1) as f32 * cell * a.sin()]));
+            }
+        }
+    }
+    // the outer wall, open at one cell to get out
+    let n = counts[rings];
+    arc((rings + 1) as f32 * cell, TAU / n as f32, TAU, &mut segments);
+    segments
+}
+
+fn star(points: u32, skip: u32, radius: f32) -> Vec<Segment> {
+    let n = points.max(3);
+    let k = skip.clamp(1, (n - 1) / 2);
+    let loops = gcd(n, k);
+    let corner = |i: u32| {
+        // the first corner at the top
+        let a = TAU * i as f32 / n as f32 - PI / 2.0;
+        [radius * a.cos(), radius * a.sin()]
+    };
+    (0..loops)
+        .flat_map(|start| {
+            let points: Vec<[f32; 2]> = (0..n / loops).map(|m| corner((start + m * k) % n)).collect();
+            polyline(&points, true)
+        })
+        .collect()
+}
+
+fn gear(teeth: u32, module: f32, pressure_angle: f32, bore: f32) -> Vec<Segment> {
+    let z = teeth.max(4);
+    let pitch = module * z as f32 * 0.5;
+    let base = pitch * pressure_angle.clamp(5.0, 40.0).to_radians().cos();
+    let outer = pitch + module;
+    let root = (pitch - 1.25 * module).max(module * 0.1);
+    // the involute's angle at a radius, and half the angle a tooth spans there
+    let involute = |r: f32| {
+        let a = (base / r.max(base)).acos();
+        a.tan() - a
+    };
+    let half = |r: f32| PI / (2.0 * z as f32) + involute(pitch) - involute(r);
+    let polar = |r: f32, a: f32| [r * a.cos(), r * a.sin()];
+    let start = root.max(base);
+    const FLANK: usize = 8;
+    let radii: Vec<f32> = (0..=FLANK).map(|i| start + (outer - start) * i as f32 / FLANK as f32).collect();
+
+    let mut points = vec![];
+    for i in 0..z {
+        let c = TAU * i as f32 / z as f32;
+        // up the leading flank, across the top and down the trailing one
+        if root < base {
+            points.push(polar(root, c - half(base)));
+        }
+        points.extend(radii.iter().map(|&r| polar(r, c - half(r))));
+        points.extend(radii.iter().rev().map(|&r| polar(r, c + half(r))));
+        if root < base {
+            points.push(polar(root, c + half(base)));
+        }
+        // the bottom of the gap
+        points.push(polar(root, c + PI / z as f32));
+    }
+    let mut segments = polyline(&points, true);
+    if bore > 0.0 && bore * 0.5 < root {
+        let r = bore * 0.5;
+        segments.extend(ellipse([-r, -r], [r, r]));
+    }
+    segments
+}
@@ -0,0 +1,155 @@
+use crate::document::{transform_point, Contour, Document};
+use crate::gcode::{contour_moves, gcode_lines, job_contours, machine_origin, to_machine, GcodeSettings};
+
+/// One line for a GRBL controller, without comments.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GcodeLine {
+    pub text: String,
+    /// index into the job's contours while cutting one, None for travel and setup
+    pub contour: Option<usize>,
+    /// how long the machine takes for it at the programmed rates, in ms
+    pub duration: u32,
+}
+
+/// What to stream to a GRBL controller, and the outlines it cuts.
+#[derive(Clone, Debug, Default)]
+pub struct GcodeJob {
+    /// in document coordinates, in the order they are cut, passes repeated
+    pub contours: Vec<Contour>,
+    pub lines: Vec<GcodeLine>,
+    /// the smallest and largest x and y the machine goes to, in mm of work coordinates
+    pub extent: [f32; 4],
+}
+
+impl GcodeJob {
+    /// Total time of the lines in ms.
+    pub fn duration(&self) -> u64 {
+        self.lines.iter().map(|l| l.duration as u64).sum()
+    }
+}
+
+/// The G-code of `to_gcode` as lines to stream, knowing which contour each line cuts.
+pub fn gcode_job(doc: &Document, settings: &GcodeSettings) -> GcodeJob {
+    let contours = job_contours(doc, settings);
+    let moves = contour_moves(&contours, machine_origin(doc, settings));
+    let just_moves: Vec<_> = moves.iter().map(|(m, _)| *m).collect();
+    let ts = to_machine(doc, settings);
+    let to_mm = 1.0 / settings.units.per_mm();
+
+    let mut extent = [0.0f32; 4];
+    let mut lines = vec![];
+    for (text, index) in gcode_lines(doc, &just_moves, settings) {
+        let text = text.split(';').next().unwrap_or_default().trim().to_string();
+        if text.is_empty() {
+            continue;
+        }
+        let mut contour = None;
+        let mut duration = 0;
+        if let Some((m, c)) = index.map(|i| moves[i]) {
+            contour = c;
+            // only the line doing the move takes time, the tool goes up and down quickly
+            if text.starts_with("G0 X") || text.starts_with("G1 X") {
+                let [x0, y0] = transform_point(&ts, m.from).map(|v| v * to_mm);
+                let [x, y] = transform_point(&ts, m.to).map(|v| v * to_mm);
+                extent = [extent[0].min(x), extent[1].min(y), extent[2].max(x), extent[3].max(y)];
+                let rate = if m.cutting { settings.feed_rate } else { settings.travel_rate } * to_mm;
+                duration = ((x - x0).hypot(y - y0) / rate.max(f32::EPSILON) * 60_000.0).ceil() as u32;
+            }
+        }
+        lines.push(GcodeLine { text, contour, duration });
+    }
+    GcodeJob { contours, lines, extent }
+}
+
+/// A `<…>` status report of GRBL 1.1, positions in mm.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GrblStatus {
+    /// Idle, Run, Hold, Alarm, …
+    pub state: String,
+    /// machine and work position, GRBL reports one of them
+    pub machine: Option<[f32; 3]>,
+    pub work: Option<[f32; 3]>,
+    /// work coordinate offset, only reported now and then
+    pub offset: Option<[f32; 3]>,
+}
+
+impl GrblStatus {
+    pub fn parse(line: &str) -> Option<Self> {
+        let fields = line.trim().strip_prefix('<')?.strip_suffix('>')?;
+        let mut fields = fields.split('|');
+        let state = fields.next()?.split(':').next()?.to_string();
+        let mut status = Self { state, ..Default::default() };
+        let xyz = |v: &str| {
+            let v: Vec<f32> = v.split(',').filter_map(|n| n.parse().ok()).collect();
+            (v.len() >= 3).then(|| [v[0], v[1], v[2]])
+        };
+        for field in fields {
+            let Some((key, value)) = field.split_once(':') else { continue };
+            match key {
+                "MPos" => status.machine = xyz(value),
+                "WPos" => status.work = xyz(value),
+                "WCO" => status.offset = xyz(value),
+                _ => {}
+            }
+        }
+        Some(status)
+    }
+
+    /// The work position, from the machine position and `offset` if need be.
+    pub fn work_position(&self, offset: Option<[f32; 3]>) -> Option<[f32; 3]> {
+        self.work.or_else(|| {
+            let (m, o) = (self.machine?, self.offset.or(offset)?);
+            Some([m[0] - o[0], m[1] - o[1], m[2] - o[2]])
+        })
+    }
+}
+
+/// What GRBL's settings say about where the machine can go.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GrblLimits {
+    /// $130 and $131, in mm
+    pub max_travel: [Option<f32>; 2],
+    /// $23, axes that home towards their negative end have their bit set
+    pub homing_invert: u32,
+    /// $20
+    pub soft_limits: bool,
+}
+
+impl GrblLimits {
+    /// Take in a `$n=value` line of the reply to `$$`, false for other lines.
+    pub fn parse_setting(&mut self, line: &str) -> bool {
+        let Some((key, value)) = line.trim().strip_prefix('$').and_then(|l| l.split_once('=')) else { return false };
+        // GRBL 0.9 adds a description in parentheses
+        let value = value.split_whitespace().next().unwrap_or_default();
+        match key {
+            "130" | "131" => self.max_travel[if key == "130" { 0 } else { 1 }] = value.parse().ok(),
+            "23" => self.homing_invert = value.parse().unwrap_or(0),
+            "20" => self.soft_limits = value == "1",
+            _ => return false,
+        }
+        true
+    }
+
+    /// The machine coordinates of axis 0 (x) or 1 (y) the machine can reach: after homing
+    /// GRBL puts the machine's zero where it homed, so the travel is negative unless homing
+    /// goes to the negative end.
+    pub fn range(&self, axis: usize) -> Option<[f32; 2]> {
+        let travel = self.max_travel[axis]?;
+        Some(if self.homing_invert & (1 << axis) != 0 { [0.0, travel] } else { [-travel, 0.0] })
+    }
+
+    /// The axes `job` would leave the work area along with the work coordinate `offset` in
+    /// place, empty when it fits. None when the travel is not known yet.
+    pub fn exceeded_axes(&self, job: &GcodeJob, offset: [f32; 3]) -> Option<Vec<char>> {
+        let mut axes = vec![];
+        for (axis, name) in [(0, 'X'), (1, 'Y')] {
+            let [min, max] = self.range(axis)?;
+            // a little slack for rounding
+            let (lo, hi) = (job.extent[axis] + offset[axis], job.extent[axis + 2] + offset[axis]);
+            if lo < min - 0.01 || hi > max + 0.01 {
+                axes.push(name);
+            }
+        }
+        Some(axes)
+    }
+}
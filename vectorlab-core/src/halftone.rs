@@ -0,0 +1,246 @@
+use std::f32::consts::TAU;
+use std::fmt::Write;
+
+use crate::image::RasterImage;
+use crate::simplify::{simplify_polyline, SimplifyMethod};
+use crate::style::Color;
+
+/// Lines of [`halftone`], each wavier where the image is darker.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HalftonePattern {
+    /// One line spiralling out from the middle
+    Spiral,
+    /// Circles around the middle
+    Concentric,
+    /// Parallel lines at the angle
+    Lines,
+    /// Parallel lines at the angle and at right angles to it, each set a layer of its own
+    Crosshatch,
+}
+
+impl HalftonePattern {
+    pub const ALL: [HalftonePattern; 4] = [Self::Spiral, Self::Concentric, Self::Lines, Self::Crosshatch];
+
+    /// As in the settings file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Spiral => "spiral",
+            Self::Concentric => "concentric",
+            Self::Lines => "lines",
+            Self::Crosshatch => "crosshatch",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.name() == name)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Spiral => "Spiral",
+            Self::Concentric => "Concentric circles",
+            Self::Lines => "Line screen",
+            Self::Crosshatch => "Crosshatch",
+        }
+    }
+}
+
+/// How [`halftone`] draws an image with lines. Lengths are in mm.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HalftoneSettings {
+    pub pattern: HalftonePattern,
+    /// between the lines, also the wavelength of their waves
+    pub spacing: f32,
+    /// of the lines in degrees clockwise from horizontal, or where circles and the spiral
+    /// start their waves
+    pub angle: f32,
+    /// how far the waves swing out where the image is white
+    pub min_amplitude: f32,
+    /// and where it is black
+    pub max_amplitude: f32,
+    /// stroke width of the lines
+    pub pen_width: f32,
+}
+
+impl Default for HalftoneSettings {
+    fn default() -> Self {
+        Self { pattern: HalftonePattern::Lines, spacing: 1.5, angle: 0.0, min_amplitude: 0.0, max_amplitude: 0.7, pen_width: 0.3 }
+    }
+}
+
+/// Polylines drawing an image, in mm from its top left corner.
+#[derive(Clone, Debug, Default)]
+pub struct Halftone {
+    /// Named sets of lines, each for a layer
+    pub layers: Vec<(String, Vec<Vec<[f32; 2]>>)>,
+    /// of the drawing, in mm
+    pub size: [f32; 2],
+}
+
+/// Darkness of an image from 0 to 1, looked up in mm on a drawing `width` wide.
+struct Darkness {
+    values: Vec<f32>,
+    columns: usize,
+    rows: usize,
+    /// image pixels per mm
+    scale: f32,
+}
+
+impl Darkness {
+    fn new(image: &RasterImage, width: f32) -> Self {
+        let values = image.luminance().into_iter().map(|l| 1.0 - l.clamp(0.0, 1.0)).collect();
+        Self { values, columns: image.width as usize, rows: image.height as usize, scale: image.width as f32 / width }
+    }
+
+    /// Interpolated between the pixel centers.
+    fn at(&self, p: [f32; 2]) -> f32 {
+        let x = (p[0] * self.scale - 0.5).clamp(0.0, (self.columns - 1) as f32);
+        let y = (p[1] * self.scale - 0.5).clamp(0.0, (self.rows - 1) as f32);
+        let (x0, y0) = (x as usize, y as usize);
+        let (x1, y1) = ((x0 + 1).min(self.columns - 1), (y0 + 1).min(self.rows - 1));
+        let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+        let v = |x: usize, y: usize| self.values[y * self.columns + x];
+        let top = v(x0, y0) + (v(x1, y0) - v(x0, y0)) * fx;
+        let bottom = v(x0, y1) + (v(x1, y1) - v(x0, y1)) * fx;
+        top + (bottom - top) * fy
+    }
+}
+
+/// Displace the evenly spaced `base` points along their normals by a sine wave as strong as
+/// the image is dark there. Parts outside the drawing are left out, which may split a line.
+fn modulate(base: impl Iterator<Item = ([f32; 2], [f32; 2])>, step: f32, size: [f32; 2], darkness: &Darkness, settings: &HalftoneSettings, lines: &mut Vec<Vec<[f32; 2]>>) {
+    let mut line = vec![];
+    let inside = |p: [f32; 2]| p[0] >= 0.0 && p[1] >= 0.0 && p[0] <= size[0] && p[1] <= size[1];
+    let mut finish = |line: &mut Vec<[f32; 2]>| {
+        if line.len() > 1 {
+            // straight stretches in the light parts need few points
+            lines.push(simplify_polyline(line, false, 0.01, SimplifyMethod::DouglasPeucker));
+        }
+        line.clear();
+    };
+    for (i, (p, n)) in base.enumerate() {
+        if !inside(p) {
+            finish(&mut line);
+            continue;
+        }
+        let amplitude = settings.min_amplitude + (settings.max_amplitude - settings.min_amplitude) * darkness.at(p);
+        let offset = amplitude * (i as f32 * step / settings.spacing * TAU).sin();
+        line.push([p[0] + n[0] * offset, p[1] + n[1] * offset]);
+    }
+    finish(&mut line);
+}
+
+/// Parallel lines `angle` degrees from horizontal covering a drawing of `size`, each as its
+/// points and normal, every other one going back for less travel.
+fn screen(angle: f32, size: [f32; 2], spacing: f32, step: f32) -> Vec<Vec<([f32; 2], [f32; 2])>> {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let (d, n) = ([cos, sin], [-sin, cos]);
+    let center = [size[0] * 0.5, size[1] * 0.5];
+    let reach = size[0].hypot(size[1]) * 0.5;
+    let count = (reach / spacing).ceil() as i32;
+    let samples = (2.0 * reach / step).ceil() as i32;
+    (-count..=count)
+        .enumerate()
+        .map(|(k, i)| {
+            let o = i as f32 * spacing;
+            let along = |j: i32| -reach + j as f32 * step;
+            let mut line: Vec<_> = (0..=samples).map(|j| ([center[0] + n[0] * o + d[0] * along(j), center[1] + n[1] * o + d[1] * along(j)], n)).collect();
+            if k % 2 == 1 {
+                line.reverse();
+            }
+            line
+        })
+        .collect()
+}
+
+/// Lines drawing `image` at `width` mm, wavier where it is darker, as pen plotter artists do
+/// with photos. Amplitudes are clamped to half the spacing so neighboring lines don't cross.
+pub fn halftone(image: &RasterImage, width: f32, settings: &HalftoneSettings) -> Halftone {
+    let size = [width, width * image.height as f32 / image.width.max(1) as f32];
+    let mut result = Halftone { layers: vec![], size };
+    if image.width == 0 || image.height == 0 || settings.spacing <= 0.0 || width <= 0.0 {
+        return result;
+    }
+    let darkness = Darkness::new(image, width);
+    let half = settings.spacing * 0.5;
+    let settings = &HalftoneSettings { min_amplitude: settings.min_amplitude.clamp(0.0, half), max_amplitude: settings.max_amplitude.clamp(0.0, half), ..settings.clone() };
+    // a dozen points per wave
+    let step = settings.spacing / 12.0;
+    let center = [size[0] * 0.5, size[1] * 0.5];
+    let reach = size[0].hypot(size[1]) * 0.5;
+    let start = settings.angle.to_radians();
+
+    match settings.pattern {
+        HalftonePattern::Lines | HalftonePattern::Crosshatch => {
+            let angles: &[f32] = if settings.pattern == HalftonePattern::Lines { &[0.0] } else { &[0.0, 90.0] };
+            for (i, extra) in angles.iter().enumerate() {
+                let mut lines = vec![];
+                for line in screen(settings.angle + extra, size, settings.spacing, step) {
+                    modulate(line.into_iter(), step, size, &darkness, settings, &mut lines);
+                }
+                result.layers.push((format!("{}", i + 1), lines));
+            }
+        }
+        HalftonePattern::Concentric => {
+            let mut lines = vec![];
+            let mut r = settings.spacing;
+            while r < reach + settings.spacing {
+                let samples = (TAU * r / step).ceil().max(8.0) as usize;
+                let circle = (0..=samples).map(|j| {
+                    let a = start + TAU * j as f32 / samples as f32;
+                    let n = [a.cos(), a.sin()];
+                    ([center[0] + n[0] * r, center[1] + n[1] * r], n)
+                });
+                modulate(circle, TAU * r / samples as f32, size, &darkness, settings, &mut lines);
+                r += settings.spacing;
+            }
+            result.layers.push(("1".to_string(), lines));
+        }
+        HalftonePattern::Spiral => {
+            // Archimedean, r = spacing · turns, walked in steps of about the same length
+            let mut points = vec![];
+            let mut a = 0.0f32;
+            loop {
+                let r = settings.spacing * a / TAU;
+                if r > reach + settings.spacing {
+                    break;
+                }
+                let n = [(a + start).cos(), (a + start).sin()];
+                points.push(([center[0] + n[0] * r, center[1] + n[1] * r], n));
+                a += step / r.max(step);
+            }
+            let mut lines = vec![];
+            modulate(points.into_iter(), step, size, &darkness, settings, &mut lines);
+            result.layers.push(("1".to_string(), lines));
+        }
+    }
+    result
+}
+
+impl Halftone {
+    /// The lines as an SVG with a group for each layer, ids starting with `name`, stroked
+    /// with `ink` as wide as `settings` says.
+    pub fn svg(&self, name: &str, ink: Color, settings: &HalftoneSettings) -> String {
+        let [w, h] = self.size;
+        let mut svg = String::new();
+        let _ = writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.3}mm" height="{:.3}mm" viewBox="0 0 {:.3} {:.3}">"#, w, h, w, h);
+        for (layer, lines) in &self.layers {
+            let id = if self.layers.len() > 1 { format!("{}-{}", name, layer) } else { name.to_string() };
+            let _ = writeln!(
+                svg,
+                r#"<g id="{}" fill="none" stroke="rgb({},{},{})" stroke-width="{:.3}" stroke-linecap="round" stroke-linejoin="round">"#,
+                id, ink.r, ink.g, ink.b, settings.pen_width
+            );
+            for line in lines {
+                let mut d = String::new();
+                for (i, p) in line.iter().enumerate() {
+                    let _ = write!(d, "{}{:.3} {:.3}", if i == 0 { "M" } else { " L" }, p[0], p[1]);
+                }
+                let _ = writeln!(svg, r#"<path d="{}"/>"#, d);
+            }
+            svg.push_str("</g>\n");
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
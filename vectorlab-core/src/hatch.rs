@@ -0,0 +1,242 @@
+use std::collections::HashSet;
+
+use crate::document::{bbox_of, transform_point, transform_scale, Contour, Document, Element, ElementId, ElementKind, FlattenedPath, Segment};
+use crate::edit::{AddElement, DeleteElements, EditCommand, EditGroup, SetStyle};
+use crate::loader::flatten_segments;
+use crate::style::{Color, FillRule, Paint, Style};
+
+/// How `hatch_fills` draws a fill with lines. Lengths are in document units.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HatchSettings {
+    /// of the lines, in degrees clockwise from horizontal
+    pub angle: f32,
+    /// between the lines
+    pub spacing: f32,
+    /// a second set of lines at right angles to the first
+    pub crosshatch: bool,
+    /// how far the lines stay away from the outline
+    pub inset: f32,
+    /// stroke width of the lines
+    pub pen_width: f32,
+    /// keep the outline, stroked with the fill color if it had no stroke
+    pub outline: bool,
+}
+
+/// What `hatch_fills` did.
+pub struct HatchResult {
+    pub edit: Box<dyn EditCommand>,
+    /// all the lines, in document coordinates
+    pub lines: Vec<[[f32; 2]; 2]>,
+    /// fills that were turned into lines
+    pub fills: usize,
+}
+
+/// Turn the fills of the paths among `ids` into lines a pen plotter can draw. Each gets a
+/// new path right above it with the hatching, stroked in the fill color, and loses its fill.
+/// The lines are laid out on the page, not in the path's coordinates, so neighboring shapes
+/// line up. None if none of them has a fill.
+pub fn hatch_fills(doc: &Document, ids: &[ElementId], settings: &HatchSettings) -> Option<HatchResult> {
+    let wanted: HashSet<ElementId> = ids.iter().copied().collect();
+    let mut filled = vec![];
+    doc.walk(|id, element, ts, _| {
+        if element.as_path().is_some_and(|p| p.style.fill.is_some()) && wanted.contains(&id) {
+            filled.push((id, ts));
+        }
+    });
+    let mut commands: Vec<Box<dyn EditCommand>> = vec![];
+    let mut deleted = vec![];
+    let mut all_lines = vec![];
+    for &(id, ts) in &filled {
+        let Some(path) = doc.get(id).as_path() else { continue };
+        let scale = transform_scale(&ts).max(f32::EPSILON);
+        let contours: Vec<Contour> = flatten_segments(&path.segments, path.tolerance)
+            .into_iter()
+            .map(|c| Contour { points: c.points.iter().map(|&p| transform_point(&ts, p)).collect(), closed: true })
+            .collect();
+        let mut lines = hatch_lines(&contours, path.style.fill_rule, settings.angle, settings.spacing, settings.inset);
+        if settings.crosshatch {
+            lines.extend(hatch_lines(&contours, path.style.fill_rule, settings.angle + 90.0, settings.spacing, settings.inset));
+        }
+        let center = bbox_of(contours.iter().flat_map(|c| &c.points)).map_or([0.0; 2], |b| [(b[0] + b[2]) * 0.5, (b[1] + b[3]) * 0.5]);
+        let color = match &path.style.fill {
+            Some(Paint::Solid(color)) => *color,
+            Some(Paint::Gradient(gradient)) => gradient.color_at(center),
+            None => Color::BLACK,
+        };
+
+        if !lines.is_empty() {
+            let to_local = ts.invert().unwrap_or_default();
+            let mut segments = vec![];
+            for [p, q] in &lines {
+                segments.push(Segment::MoveTo(transform_point(&to_local, *p)));
+                segments.push(Segment::LineTo(transform_point(&to_local, *q)));
+            }
+            let style = Style { fill: None, stroke: Some(Paint::Solid(color)), stroke_width: settings.pen_width / scale, ..path.style.clone() };
+            let mut hatching = FlattenedPath {
+                segments: vec![],
+                contours: vec![],
+                style,
+                tolerance: path.tolerance,
+                fill_vertices: vec![],
+                fill_indices: vec![],
+                stroke_vertices: vec![],
+                stroke_indices: vec![],
+                fill_colors: vec![],
+                stroke_colors: vec![],
+                clip: path.clip.clone(),
+            };
+            hatching.set_segments(segments);
+            let mut element = Element::new(ElementKind::Path(hatching));
+            element.transform = doc.get(id).transform;
+            element.opacity = doc.get(id).opacity;
+            commands.push(Box::new(AddElement::after(doc, id, element)));
+        }
+
+        let mut outline = Style { fill: None, ..path.style.clone() };
+        if settings.outline && outline.stroke.is_none() {
+            outline.stroke = Some(Paint::Solid(color));
+            outline.stroke_width = settings.pen_width / scale;
+        }
+        if outline.stroke.is_some() {
+            commands.push(Box::new(SetStyle { id, old: path.style.clone(), new: outline }));
+        } else {
+            // nothing left to draw
+            deleted.push(id);
+        }
+        all_lines.extend(lines);
+    }
+    if filled.is_empty() {
+        return None;
+    }
+    commands.push(Box::new(DeleteElements::new(deleted)));
+    Some(HatchResult { edit: Box::new(EditGroup::new("Fill to hatch", commands)), lines: all_lines, fills: filled.len() })
+}
+
+/// Parallel lines `spacing` apart at `angle` degrees covering the area `contours` fill under
+/// `rule`, keeping `inset` away from their outline. Every other line runs backwards so a
+/// plotter can draw them in a zigzag.
+pub fn hatch_lines(contours: &[Contour], rule: FillRule, angle: f32, spacing: f32, inset: f32) -> Vec<[[f32; 2]; 2]> {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    // turned so the lines are horizontal, and back
+    let turn = |p: [f32; 2]| [p[0] * cos + p[1] * sin, -p[0] * sin + p[1] * cos];
+    let back = |p: [f32; 2]| [p[0] * cos - p[1] * sin, p[0] * sin + p[1] * cos];
+    let mut edges = vec![];
+    for contour in contours {
+        let n = contour.points.len();
+        for i in 0..n {
+            edges.push([turn(contour.points[i]), turn(contour.points[(i + 1) % n])]);
+        }
+    }
+    let Some(bbox) = bbox_of(edges.iter().flat_map(|e| e.iter())) else { return vec![] };
+    let spacing = spacing.max((bbox[3] - bbox[1]) / 10000.0).max(f32::EPSILON);
+    let mut lines = vec![];
+    // on a grid through the origin, half a step off so lines miss horizontal edges
+    let first = (bbox[1] / spacing - 0.5).ceil() as i64;
+    let last = (bbox[3] / spacing - 0.5).floor() as i64;
+    for k in first..=last {
+        let y = (k as f32 + 0.5) * spacing;
+        // crossings with the direction the edge runs
+        let mut crossings: Vec<(f32, i32)> = edges
+            .iter()
+            .filter(|[a, b]| (a[1] <= y) != (b[1] <= y))
+            .map(|[a, b]| (a[0] + (y - a[1]) / (b[1] - a[1]) * (b[0] - a[0]), if b[1] > a[1] { 1 } else { -1 }))
+            .collect();
+        crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut winding = 0;
+        let mut spans = vec![];
+        for pair in crossings.windows(2) {
+            winding += pair[0].1;
+            let inside = match rule {
+                FillRule::NonZero => winding != 0,
+                FillRule::EvenOdd => winding % 2 != 0,
+            };
+            if !inside || pair[1].0 <= pair[0].0 {
+                continue;
+            }
+            match spans.last_mut() {
+                // overlapping subpaths cross in between
+                Some((_, end)) if *end == pair[0].0 => *end = pair[1].0,
+                _ => spans.push((pair[0].0, pair[1].0)),
+            }
+        }
+        if inset > 0.0 {
+            spans = without_border(spans, &edges, y, inset);
+        }
+        let reverse = k % 2 != 0;
+        if reverse {
+            spans.reverse();
+        }
+        for (x0, x1) in spans {
+            let (a, b) = if reverse { (x1, x0) } else { (x0, x1) };
+            lines.push([back([a, y]), back([b, y])]);
+        }
+    }
+    lines
+}
+
+/// `spans` of the horizontal line at `y` without what is within `inset` of `edges`.
+fn without_border(spans: Vec<(f32, f32)>, edges: &[[[f32; 2]; 2]], y: f32, inset: f32) -> Vec<(f32, f32)> {
+    let mut near: Vec<(f32, f32)> = edges
+        .iter()
+        .filter(|[a, b]| a[1].min(b[1]) - inset <= y && a[1].max(b[1]) + inset >= y)
+        .filter_map(|&[a, b]| near_edge(a, b, y, inset))
+        .collect();
+    near.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut kept = vec![];
+    for (mut x0, x1) in spans {
+        for &(n0, n1) in &near {
+            if n1 <= x0 || n0 >= x1 {
+                continue;
+            }
+            if n0 > x0 {
+                kept.push((x0, n0));
+            }
+            x0 = x0.max(n1);
+        }
+        if x1 > x0 {
+            kept.push((x0, x1));
+        }
+    }
+    kept
+}
+
+/// Where the horizontal line at `y` comes within `r` of the edge a..b. The points near a
+/// segment form a capsule, which is convex, so this is one range.
+fn near_edge(a: [f32; 2], b: [f32; 2], y: f32, r: f32) -> Option<(f32, f32)> {
+    let mut range: Option<(f32, f32)> = None;
+    let mut add = |lo: f32, hi: f32| {
+        range = Some(range.map_or((lo, hi), |(l, h)| (l.min(lo), h.max(hi))));
+    };
+    // the round ends
+    for c in [a, b] {
+        let dy = y - c[1];
+        if dy.abs() <= r {
+            let dx = (r * r - dy * dy).sqrt();
+            add(c[0] - dx, c[0] + dx);
+        }
+    }
+    // the band along the edge: within r of its line and between its ends
+    let d = [b[0] - a[0], b[1] - a[1]];
+    let length = (d[0] * d[0] + d[1] * d[1]).sqrt();
+    if length > f32::EPSILON {
+        let n = [-d[1] / length, d[0] / length];
+        let across = linear_range(n[0], n[1] * (y - a[1]) - n[0] * a[0], -r, r);
+        let along = linear_range(d[0], d[1] * (y - a[1]) - d[0] * a[0], 0.0, length * length);
+        if let (Some(p), Some(q)) = (across, along) {
+            let (lo, hi) = (p.0.max(q.0), p.1.min(q.1));
+            if lo <= hi && lo.is_finite() && hi.is_finite() {
+                add(lo, hi);
+            }
+        }
+    }
+    range
+}
+
+/// The x with lo <= k * x + c <= hi, unbounded when k is 0 and c fits.
+fn linear_range(k: f32, c: f32, lo: f32, hi: f32) -> Option<(f32, f32)> {
+    if k.abs() <= f32::EPSILON {
+        return (lo..=hi).contains(&c).then_some((f32::NEG_INFINITY, f32::INFINITY));
+    }
+    let (x0, x1) = ((lo - c) / k, (hi - c) / k);
+    Some((x0.min(x1), x0.max(x1)))
+}
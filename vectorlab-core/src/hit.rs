@@ -0,0 +1,79 @@
+use crate::document::{transform_point, transform_scale, Contour, Document, ElementId, ElementKind, FlattenedPath};
+use crate::style::FillRule;
+
+impl FlattenedPath {
+    /// Whether `p` lies inside the fill or within `tolerance` of the stroke outline.
+    /// Both are in the path's own coordinates.
+    pub fn hit(&self, p: [f32; 2], tolerance: f32) -> bool {
+        if self.style.fill.is_some() && fill_contains(&self.contours, self.style.fill_rule, p) {
+            return true;
+        }
+        // unstroked paths are still pickable at their outline
+        let reach = if self.style.stroke.is_some() { self.style.stroke_width * 0.5 } else { 0.0 } + tolerance;
+        self.contours.iter().any(|c| {
+            let n = c.points.len();
+            let segments = if c.closed { n } else { n.saturating_sub(1) };
+            (0..segments).any(|i| distance_to_segment(p, c.points[i], c.points[(i + 1) % n]) <= reach)
+        })
+    }
+}
+
+impl Document {
+    /// Topmost visible path or image under `p` (document coordinates). `tolerance` is in document units.
+    pub fn hit_test(&self, p: [f32; 2], tolerance: f32) -> Option<ElementId> {
+        let mut hit = None;
+        // later paths paint over earlier ones, so the last match wins
+        self.walk(|id, element, ts, _| {
+            let Some(inverse) = ts.invert() else { return };
+            let local = transform_point(&inverse, p);
+            let scale = transform_scale(&ts);
+            let hit_here = match &element.kind {
+                ElementKind::Path(path) => scale > 0.0 && path.hit(local, tolerance / scale),
+                ElementKind::Image { image: Some(image), .. } => {
+                    let [x, y, w, h] = image.rect;
+                    local[0] >= x && local[0] <= x + w && local[1] >= y && local[1] <= y + h
+                }
+                _ => false,
+            };
+            if hit_here {
+                hit = Some(id);
+            }
+        });
+        hit
+    }
+}
+
+/// Whether `p` is in the area `contours` fill under `rule`, open ones closed implicitly.
+pub(crate) fn fill_contains(contours: &[Contour], rule: FillRule, p: [f32; 2]) -> bool {
+    let winding = winding_number(contours, p);
+    match rule {
+        FillRule::NonZero => winding != 0,
+        FillRule::EvenOdd => winding % 2 != 0,
+    }
+}
+
+/// Winding number of the (implicitly closed) contours around `p`.
+pub(crate) fn winding_number(contours: &[Contour], p: [f32; 2]) -> i32 {
+    let mut winding = 0;
+    for c in contours {
+        let n = c.points.len();
+        for i in 0..n {
+            let (a, b) = (c.points[i], c.points[(i + 1) % n]);
+            let side = (b[0] - a[0]) * (p[1] - a[1]) - (p[0] - a[0]) * (b[1] - a[1]);
+            if a[1] <= p[1] && b[1] > p[1] && side > 0.0 {
+                winding += 1;
+            } else if a[1] > p[1] && b[1] <= p[1] && side < 0.0 {
+                winding -= 1;
+            }
+        }
+    }
+    winding
+}
+
+pub fn distance_to_segment(p: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 > 0.0 { (((p[0] - a[0]) * dx + (p[1] - a[1]) * dy) / len2).clamp(0.0, 1.0) } else { 0.0 };
+    let (x, y) = (a[0] + t * dx - p[0], a[1] + t * dy - p[1]);
+    (x * x + y * y).sqrt()
+}
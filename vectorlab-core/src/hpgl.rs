@@ -0,0 +1,144 @@
+use crate::document::{transform_point, Contour, Document, ElementId};
+use crate::gcode::{optimize_path_contours, path_contours};
+use crate::quantize::{distance2, pen_colors};
+use crate::style::{Color, Paint};
+use crate::units::Unit;
+
+/// HPGL plotter units in a millimeter.
+pub const HPGL_UNITS_PER_MM: f32 = 40.0;
+
+/// Pen speed of an HP 7475A, for guessing how long a job takes when no velocity is set.
+const DEFAULT_VELOCITY: f32 = 38.1;
+
+/// Points per PD instruction.
+const PD_POINTS: usize = 64;
+
+/// How a classic HPGL pen plotter draws.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HpglSettings {
+    /// Pens in the carousel. With more than one, each color goes to the pen nearest to it
+    pub pens: u32,
+    /// Pen speed in cm/s, 0 leaves the plotter's own
+    pub velocity: f32,
+    /// How far flattened curves may deviate from the true ones, in mm
+    pub tolerance: f32,
+    /// Reorder and reverse the paths of each pen to shorten the travel, see
+    /// `optimize_contours`
+    pub optimize: bool,
+}
+
+impl Default for HpglSettings {
+    fn default() -> Self {
+        Self { pens: 1, velocity: 0.0, tolerance: 0.1, optimize: true }
+    }
+}
+
+/// One HPGL instruction with its terminator.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HpglCommand {
+    pub text: String,
+    /// index into the job's contours while drawing one, None for travel and setup
+    pub contour: Option<usize>,
+    /// roughly how long the plotter takes for it, in ms
+    pub duration: u32,
+}
+
+/// What to send to an HPGL plotter, and the outlines it draws.
+#[derive(Clone, Debug, Default)]
+pub struct HpglJob {
+    /// in document coordinates, in the order they are drawn
+    pub contours: Vec<Contour>,
+    pub commands: Vec<HpglCommand>,
+}
+
+impl HpglJob {
+    /// The whole job as a .plt file.
+    pub fn text(&self) -> String {
+        self.commands.iter().map(|c| c.text.clone() + "\n").collect()
+    }
+}
+
+/// Solid color a path is drawn with, its stroke before its fill.
+fn path_color(doc: &Document, id: ElementId) -> Color {
+    let style = doc.get(id).as_path().map(|p| &p.style);
+    match style.and_then(|s| s.stroke.as_ref().or(s.fill.as_ref())) {
+        Some(Paint::Solid(color)) => *color,
+        _ => Color::BLACK,
+    }
+}
+
+/// The visible paths of `doc` as HPGL, with the page's bottom left corner at the plotter's
+/// origin. Colors are sorted onto the pens so each pen is picked up once.
+pub fn hpgl_job(doc: &Document, settings: &HpglSettings) -> HpglJob {
+    let mm = doc.mm_per_unit();
+    let tolerance = settings.tolerance / mm[0].max(mm[1]).max(f32::EPSILON);
+    let to_page = doc.viewport_transform();
+    let height = doc.size_mm()[1];
+    let to_plotter = |p: [f32; 2]| {
+        let [x, y] = transform_point(&to_page, p).map(|px| Unit::Px.to_mm(px));
+        [(x * HPGL_UNITS_PER_MM).round() as i32, ((height - y) * HPGL_UNITS_PER_MM).round() as i32]
+    };
+    let velocity = if settings.velocity > 0.0 { settings.velocity } else { DEFAULT_VELOCITY };
+    // plotter units per ms
+    let speed = velocity * 10.0 * HPGL_UNITS_PER_MM / 1000.0;
+    let duration = |from: [i32; 2], to: [i32; 2]| ((to[0] - from[0]) as f32).hypot((to[1] - from[1]) as f32) / speed;
+
+    let pens = settings.pens.max(1) as usize;
+    let colors = if pens > 1 { pen_colors(doc, pens) } else { vec![] };
+    let mut by_pen = vec![vec![]; pens];
+    for (id, contour) in path_contours(doc, tolerance) {
+        let color = path_color(doc, id);
+        let pen = (0..colors.len()).min_by_key(|&i| distance2(colors[i], color)).unwrap_or(0);
+        by_pen[pen].push((id, contour));
+    }
+
+    let command = |text: String, contour, duration: f32| HpglCommand { text, contour, duration: duration.ceil() as u32 };
+    let mut commands = vec![command("IN;".to_string(), None, 0.0)];
+    if settings.velocity > 0.0 {
+        commands.push(command(format!("VS{};", settings.velocity), None, 0.0));
+    }
+    let mut contours = vec![];
+    let mut at = [0, 0];
+    // where the pen is in document coordinates, starting at the origin
+    let mut at_doc = transform_point(&to_page.invert().unwrap_or_default(), [0.0, doc.size[1]]);
+    for (pen, mut run) in by_pen.into_iter().enumerate() {
+        if run.is_empty() {
+            continue;
+        }
+        if settings.optimize {
+            optimize_path_contours(&mut run, at_doc);
+        }
+        commands.push(command(format!("SP{};", pen + 1), None, 0.0));
+        for (_, contour) in run {
+            let Some(&first) = contour.points.first() else { continue };
+            let index = contours.len();
+            let start = to_plotter(first);
+            commands.push(command(format!("PU{},{};", start[0], start[1]), None, duration(at, start)));
+            at = start;
+            let closing = if contour.closed { Some(first) } else { None };
+            let points: Vec<[i32; 2]> = contour.points[1..].iter().chain(closing.iter()).map(|&p| to_plotter(p)).collect();
+            if points.is_empty() {
+                // a dot
+                commands.push(command("PD;".to_string(), Some(index), 0.0));
+            }
+            // in pieces, plotters have small buffers and pausing waits for the piece
+            for piece in points.chunks(PD_POINTS) {
+                let mut time = 0.0;
+                let coordinates: Vec<String> = piece
+                    .iter()
+                    .map(|&p| {
+                        time += duration(at, p);
+                        at = p;
+                        format!("{},{}", p[0], p[1])
+                    })
+                    .collect();
+                commands.push(command(format!("PD{};", coordinates.join(",")), Some(index), time));
+            }
+            at_doc = closing.or(contour.points.last().copied()).unwrap_or(first);
+            contours.push(contour);
+        }
+    }
+    commands.push(command("PU;".to_string(), None, 0.0));
+    commands.push(command("SP0;".to_string(), None, 0.0));
+    HpglJob { contours, commands }
+}
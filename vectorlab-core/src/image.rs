@@ -0,0 +1,156 @@
+use std::sync::Arc;
+
+use resvg::usvg::{self, Align};
+
+/// Decoded pixels of an `<image>`, RGBA with premultiplied alpha.
+#[derive(Debug)]
+pub struct RasterImage {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl RasterImage {
+    /// Decode the image data usvg resolved from `href` (embedded base64 or an external file).
+    pub fn decode(kind: &usvg::ImageKind, rect: usvg::NonZeroRect) -> Result<Self, Box<dyn std::error::Error>> {
+        match kind {
+            usvg::ImageKind::PNG(data) => {
+                let pixmap = tiny_skia::Pixmap::decode_png(data)?;
+                Ok(Self { width: pixmap.width(), height: pixmap.height(), rgba: pixmap.take() })
+            }
+            usvg::ImageKind::JPEG(data) => {
+                let mut decoder = jpeg_decoder::Decoder::new(data.as_slice());
+                let pixels = decoder.decode()?;
+                let info = decoder.info().ok_or("JPEG without frame")?;
+                let rgba = match info.pixel_format {
+                    jpeg_decoder::PixelFormat::L8 => pixels.iter().flat_map(|&l| [l, l, l, 255]).collect(),
+                    jpeg_decoder::PixelFormat::L16 => pixels.chunks_exact(2).flat_map(|l| [l[0], l[0], l[0], 255]).collect(),
+                    jpeg_decoder::PixelFormat::RGB24 => pixels.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+                    // Adobe CMYK JPEGs are stored inverted
+                    jpeg_decoder::PixelFormat::CMYK32 => pixels
+                        .chunks_exact(4)
+                        .flat_map(|p| {
+                            let k = p[3] as u16;
+                            [(p[0] as u16 * k / 255) as u8, (p[1] as u16 * k / 255) as u8, (p[2] as u16 * k / 255) as u8, 255]
+                        })
+                        .collect(),
+                };
+                Ok(Self { width: info.width as u32, height: info.height as u32, rgba })
+            }
+            usvg::ImageKind::GIF(data) => {
+                let mut options = gif::DecodeOptions::new();
+                options.set_color_output(gif::ColorOutput::RGBA);
+                let mut decoder = options.read_info(data.as_slice())?;
+                let frame = decoder.read_next_frame()?.ok_or("GIF without frames")?;
+                let mut rgba = frame.buffer.to_vec();
+                for p in rgba.chunks_exact_mut(4) {
+                    if p[3] == 0 {
+                        p[..3].fill(0);
+                    }
+                }
+                Ok(Self { width: frame.width as u32, height: frame.height as u32, rgba })
+            }
+            usvg::ImageKind::SVG(tree) => {
+                // rasterized at twice the placed size, so it stays sharp when zooming in a bit
+                let scale = 2.0;
+                let w = (rect.width() * scale).ceil().clamp(1.0, 4096.0) as u32;
+                let h = (rect.height() * scale).ceil().clamp(1.0, 4096.0) as u32;
+                let mut pixmap = tiny_skia::Pixmap::new(w, h).ok_or("invalid image size")?;
+                let ts = tiny_skia::Transform::from_scale(w as f32 / tree.size.width(), h as f32 / tree.size.height());
+                resvg::render(tree, ts, &mut pixmap.as_mut());
+                Ok(Self { width: w, height: h, rgba: pixmap.take() })
+            }
+        }
+    }
+
+    /// Decode a PNG, JPEG or GIF file, told apart by its first bytes.
+    pub fn decode_file(data: Vec<u8>) -> Result<Self, Box<dyn std::error::Error>> {
+        let data = Arc::new(data);
+        let kind = if data.starts_with(b"\x89PNG") {
+            usvg::ImageKind::PNG(data)
+        } else if data.starts_with(&[0xff, 0xd8]) {
+            usvg::ImageKind::JPEG(data)
+        } else if data.starts_with(b"GIF8") {
+            usvg::ImageKind::GIF(data)
+        } else {
+            return Err("not a PNG, JPEG or GIF image".into());
+        };
+        // only SVGs are sized by the rect
+        let rect = usvg::NonZeroRect::from_xywh(0.0, 0.0, 1.0, 1.0).ok_or("invalid image size")?;
+        Self::decode(&kind, rect)
+    }
+
+    /// Brightness of each pixel, row by row, from 0 for black to 1 for white. Transparent
+    /// pixels count as white paper.
+    pub fn luminance(&self) -> Vec<f32> {
+        self.rgba
+            .chunks_exact(4)
+            .map(|p| {
+                // premultiplied, so over white the background adds 255 - alpha to each channel
+                let paper = 255.0 - p[3] as f32;
+                let [r, g, b] = [p[0], p[1], p[2]].map(|c| c as f32 + paper);
+                (0.2126 * r + 0.7152 * g + 0.0722 * b) / 255.0
+            })
+            .collect()
+    }
+
+    /// The pixels with straight alpha, the way image files store them.
+    pub fn unpremultiplied(&self) -> Vec<u8> {
+        let mut rgba = self.rgba.clone();
+        for p in rgba.chunks_exact_mut(4) {
+            let a = p[3] as u16;
+            if a != 0 && a != 255 {
+                for c in &mut p[..3] {
+                    *c = ((*c as u16 * 255 + a / 2) / a).min(255) as u8;
+                }
+            }
+        }
+        rgba
+    }
+}
+
+/// An `<image>` placed into its viewport.
+#[derive(Clone, Debug)]
+pub struct PlacedImage {
+    /// [x, y, width, height] in element coordinates, after preserveAspectRatio
+    pub rect: [f32; 4],
+    /// Visible part of the image as [u0, v0, u1, v1], less than the full image for `slice`
+    pub uv: [f32; 4],
+    pub pixels: Arc<RasterImage>,
+}
+
+impl PlacedImage {
+    /// Fit `pixels` into the `<image>` viewport the way preserveAspectRatio asks for.
+    pub fn new(view_box: &usvg::ViewBox, pixels: Arc<RasterImage>) -> Self {
+        let r = view_box.rect;
+        let (vx, vy, vw, vh) = (r.x(), r.y(), r.width(), r.height());
+        let (iw, ih) = (pixels.width as f32, pixels.height as f32);
+        let aspect = view_box.aspect;
+        if aspect.align == Align::None || iw == 0.0 || ih == 0.0 {
+            return Self { rect: [vx, vy, vw, vh], uv: [0.0, 0.0, 1.0, 1.0], pixels };
+        }
+
+        let (fx, fy) = match aspect.align {
+            Align::XMinYMin => (0.0, 0.0),
+            Align::XMidYMin => (0.5, 0.0),
+            Align::XMaxYMin => (1.0, 0.0),
+            Align::XMinYMid => (0.0, 0.5),
+            Align::XMaxYMid => (1.0, 0.5),
+            Align::XMinYMax => (0.0, 1.0),
+            Align::XMidYMax => (0.5, 1.0),
+            Align::XMaxYMax => (1.0, 1.0),
+            Align::XMidYMid | Align::None => (0.5, 0.5),
+        };
+        if aspect.slice {
+            // fill the viewport and crop the overhanging part of the image
+            let scale = (vw / iw).max(vh / ih);
+            let (cw, ch) = (vw / scale / iw, vh / scale / ih);
+            let (u0, v0) = ((1.0 - cw) * fx, (1.0 - ch) * fy);
+            Self { rect: [vx, vy, vw, vh], uv: [u0, v0, u0 + cw, v0 + ch], pixels }
+        } else {
+            let scale = (vw / iw).min(vh / ih);
+            let (w, h) = (iw * scale, ih * scale);
+            Self { rect: [vx + (vw - w) * fx, vy + (vh - h) * fy, w, h], uv: [0.0, 0.0, 1.0, 1.0], pixels }
+        }
+    }
+}
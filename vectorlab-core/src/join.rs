@@ -0,0 +1,197 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use resvg::usvg::Transform;
+
+use crate::document::{transform_point, transform_scale, Document, ElementId, Segment};
+use crate::edit::{DeleteElements, EditCommand, EditGroup, SetSegments};
+use crate::style::{Paint, Style};
+
+/// A path that is one open subpath, with its ends in document coordinates.
+struct Piece {
+    id: ElementId,
+    ts: Transform,
+    start: [f32; 2],
+    end: [f32; 2],
+}
+
+/// What joining did, see `join_paths`.
+pub struct JoinResult {
+    pub edit: Box<dyn EditCommand>,
+    /// paths that were merged into others
+    pub joined: usize,
+    /// paths they were merged into
+    pub chains: usize,
+}
+
+/// Concatenate the open paths among `ids` whose ends are within `epsilon` document units of
+/// each other, so a plotter draws them without lifting the pen. CAD and tracing programs
+/// write lines that belong together as separate paths.
+///
+/// Only paths drawn the same way are joined: same fill, same solid stroke color and width,
+/// same opacity. Each chain is kept in its member that is painted first, the others are
+/// deleted. The ends are snapped together, a chain that comes back to where it started is
+/// closed. None if there is nothing to join.
+pub fn join_paths(doc: &Document, ids: &[ElementId], epsilon: f32) -> Option<JoinResult> {
+    let wanted: HashSet<ElementId> = ids.iter().copied().collect();
+    let mut pieces = vec![];
+    // in paint order, so the first member of a chain is the one painted first
+    doc.walk(|id, element, ts, _| {
+        let Some(path) = element.as_path() else { return };
+        if !wanted.contains(&id) || !is_open_subpath(&path.segments) {
+            return;
+        }
+        let (Some(start), Some(end)) = (first_point(&path.segments), last_point(&path.segments)) else { return };
+        pieces.push(Piece { id, ts, start: transform_point(&ts, start), end: transform_point(&ts, end) });
+    });
+
+    let epsilon = epsilon.max(f32::EPSILON);
+    let cell = |p: [f32; 2]| ((p[0] / epsilon).floor() as i64, (p[1] / epsilon).floor() as i64);
+    // both ends of every piece, by grid cell
+    let mut grid: HashMap<(i64, i64), Vec<(usize, bool)>> = HashMap::new();
+    for (i, piece) in pieces.iter().enumerate() {
+        grid.entry(cell(piece.start)).or_default().push((i, false));
+        grid.entry(cell(piece.end)).or_default().push((i, true));
+    }
+    let mut used = vec![false; pieces.len()];
+    // the unused piece with an end closest to `p`, and whether that is its end
+    let nearest = |p: [f32; 2], of: usize, used: &[bool]| {
+        let (cx, cy) = cell(p);
+        let mut best: Option<(f32, usize, bool)> = None;
+        for x in cx - 1..=cx + 1 {
+            for y in cy - 1..=cy + 1 {
+                for &(i, at_end) in grid.get(&(x, y)).into_iter().flatten() {
+                    if used[i] || !looks_alike(doc, &pieces[of], &pieces[i]) {
+                        continue;
+                    }
+                    let d = distance(p, if at_end { pieces[i].end } else { pieces[i].start });
+                    if d <= epsilon && best.is_none_or(|b| d < b.0) {
+                        best = Some((d, i, at_end));
+                    }
+                }
+            }
+        }
+        best.map(|(_, i, at_end)| (i, at_end))
+    };
+
+    let mut commands: Vec<Box<dyn EditCommand>> = vec![];
+    let mut deleted = vec![];
+    let mut chains = 0;
+    for first in 0..pieces.len() {
+        if used[first] {
+            continue;
+        }
+        used[first] = true;
+        // (piece, reversed) in drawing order
+        let mut chain = VecDeque::from([(first, false)]);
+        let tail = |&(i, reversed): &(usize, bool)| if reversed { pieces[i].start } else { pieces[i].end };
+        let head = |&(i, reversed): &(usize, bool)| if reversed { pieces[i].end } else { pieces[i].start };
+        while let Some((i, at_end)) = chain.back().and_then(|c| nearest(tail(c), first, &used)) {
+            used[i] = true;
+            chain.push_back((i, at_end));
+        }
+        while let Some((i, at_end)) = chain.front().and_then(|c| nearest(head(c), first, &used)) {
+            used[i] = true;
+            chain.push_front((i, !at_end));
+        }
+        if chain.len() < 2 {
+            continue;
+        }
+
+        // everything goes into the coordinates of the chain's first piece in paint order
+        let keep = chain.iter().map(|&(i, _)| i).min().unwrap_or(first);
+        let to_keep = pieces[keep].ts.invert().unwrap_or_default();
+        let mut segments = vec![];
+        for &(i, reversed) in &chain {
+            let path = doc.get(pieces[i].id).as_path()?;
+            let own = if reversed { reverse_segments(&path.segments) } else { path.segments.clone() };
+            let ts = to_keep.pre_concat(pieces[i].ts);
+            let map = |p: [f32; 2]| transform_point(&ts, p);
+            for segment in own {
+                segments.push(match segment {
+                    // snapped onto the previous piece's end
+                    Segment::MoveTo(_) if !segments.is_empty() => continue,
+                    Segment::MoveTo(p) => Segment::MoveTo(map(p)),
+                    Segment::LineTo(p) => Segment::LineTo(map(p)),
+                    Segment::QuadTo(c, p) => Segment::QuadTo(map(c), map(p)),
+                    Segment::CubicTo(c1, c2, p) => Segment::CubicTo(map(c1), map(c2), map(p)),
+                    Segment::Close => Segment::Close,
+                });
+            }
+        }
+        if chain.front().zip(chain.back()).is_some_and(|(a, b)| distance(head(a), tail(b)) <= epsilon) {
+            segments.push(Segment::Close);
+        }
+        commands.push(Box::new(SetSegments::new(doc, pieces[keep].id, segments)));
+        deleted.extend(chain.iter().filter(|&&(i, _)| i != keep).map(|&(i, _)| pieces[i].id));
+        chains += 1;
+    }
+    if chains == 0 {
+        return None;
+    }
+    let joined = deleted.len();
+    commands.push(Box::new(DeleteElements::new(deleted)));
+    Some(JoinResult { edit: Box::new(EditGroup::new("Join paths", commands)), joined, chains })
+}
+
+/// One MoveTo followed by drawing commands, no Close.
+fn is_open_subpath(segments: &[Segment]) -> bool {
+    matches!(segments.first(), Some(Segment::MoveTo(_)))
+        && segments.len() > 1
+        && segments[1..].iter().all(|s| !matches!(s, Segment::MoveTo(_) | Segment::Close))
+}
+
+fn first_point(segments: &[Segment]) -> Option<[f32; 2]> {
+    match segments.first()? {
+        Segment::MoveTo(p) => Some(*p),
+        _ => None,
+    }
+}
+
+fn last_point(segments: &[Segment]) -> Option<[f32; 2]> {
+    match segments.last()? {
+        Segment::MoveTo(p) | Segment::LineTo(p) | Segment::QuadTo(_, p) | Segment::CubicTo(_, _, p) => Some(*p),
+        Segment::Close => None,
+    }
+}
+
+/// The same open subpath, drawn from its end to its start.
+fn reverse_segments(segments: &[Segment]) -> Vec<Segment> {
+    let Some(end) = last_point(segments) else { return segments.to_vec() };
+    let mut reversed = vec![Segment::MoveTo(end)];
+    for pair in segments.windows(2).rev() {
+        // each command goes back to where the one before it ended
+        let Some(to) = last_point(&pair[..1]) else { continue };
+        reversed.push(match pair[1] {
+            Segment::QuadTo(c, _) => Segment::QuadTo(c, to),
+            Segment::CubicTo(c1, c2, _) => Segment::CubicTo(c2, c1, to),
+            _ => Segment::LineTo(to),
+        });
+    }
+    reversed
+}
+
+/// Whether joining `a` and `b` leaves the drawing looking the same.
+fn looks_alike(doc: &Document, a: &Piece, b: &Piece) -> bool {
+    let (ea, eb) = (doc.get(a.id), doc.get(b.id));
+    let (Some(pa), Some(pb)) = (ea.as_path(), eb.as_path()) else { return false };
+    let solid = |paint: &Option<Paint>| match paint {
+        None => Some(None),
+        Some(Paint::Solid(color)) => Some(Some(*color)),
+        Some(Paint::Gradient(_)) => None,
+    };
+    let (sa, sb) = (&pa.style, &pb.style);
+    let width = |style: &Style, ts: &Transform| style.stroke_width * transform_scale(ts);
+    let (wa, wb) = (width(sa, &a.ts), width(sb, &b.ts));
+    solid(&sa.fill).is_some()
+        && solid(&sa.fill) == solid(&sb.fill)
+        && solid(&sa.stroke).is_some()
+        && solid(&sa.stroke) == solid(&sb.stroke)
+        && (wa - wb).abs() <= wa.max(wb) * 0.01
+        && ea.opacity == eb.opacity
+        && pa.clip.is_none()
+        && pb.clip.is_none()
+}
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt()
+}
@@ -0,0 +1,103 @@
+//! SVG loading and flattening for VectorLab, independent of any windowing stack.
+
+mod align;
+mod array;
+mod bake;
+mod boolean;
+mod clip;
+mod clipboard;
+mod crop;
+mod cut;
+mod defects;
+mod diagnostics;
+mod document;
+mod ebb;
+mod edit;
+mod extract;
+mod formats;
+mod freehand;
+mod gcode;
+mod generate;
+mod grbl;
+mod halftone;
+mod hatch;
+mod hit;
+mod hpgl;
+mod image;
+mod join;
+mod loader;
+mod measure;
+mod nodes;
+mod offset;
+mod overlap;
+mod palette;
+mod pdf;
+mod profile;
+mod quantize;
+mod raster;
+mod saver;
+mod search;
+mod shapes;
+mod simplify;
+mod simulate;
+mod source;
+mod stipple;
+mod spatial;
+mod style;
+mod text;
+mod tikz;
+mod trace;
+mod units;
+mod validate;
+mod view;
+mod winding;
+
+pub use align::{align_elements, distribute_elements, Align};
+pub use array::{array_elements, array_outlines, duplicate_elements, ArrayLayout};
+pub use boolean::{boolean_contours, boolean_paths, BooleanOp, BooleanResult};
+pub use clip::ClipRegion;
+pub use clipboard::{parse_clipboard, selection_svg, Paste};
+pub use crop::{crop_paths, CropResult};
+pub use cut::{cut_job, CutJob, CutSettings};
+pub use defects::{Defect, DefectKind};
+pub use diagnostics::{capture_log, Diagnostic, LoadError};
+pub use document::{bbox_of, transform_point, transform_scale, Contour, Document, Element, ElementId, ElementKind, FlattenedPath, Segment};
+pub use ebb::{ebb_move, ebb_setup, pen_command, plot_job, PlotJob, PlotStep, PlotterSettings, STEPS_PER_MM};
+pub use edit::{AddCopies, AddElement, DeleteElements, EditCommand, EditGroup, FlattenDocument, History, ReplaceDrawing, SetOpacity, SetSegments, SetStyle, SetTransform, SetTransforms, SetVisibility, SplitByColor};
+pub use extract::{layer_documents, layer_names, layer_svgs, selection_document};
+pub use formats::{Exporter, Formats, Importer};
+pub use freehand::{fit_curve, variable_width_outline};
+pub use gcode::{job_contours, machine_origin, optimize_contours, optimize_path_contours, path_contours, plot_contours, to_gcode, tool_moves, travel_length, GcodeOrigin, GcodeSettings, GcodeUnits, ToolMove};
+pub use generate::{Generator, LSYSTEM_PRESETS};
+pub use grbl::{gcode_job, GcodeJob, GcodeLine, GrblLimits, GrblStatus};
+pub use halftone::{halftone, Halftone, HalftonePattern, HalftoneSettings};
+pub use hatch::{hatch_fills, hatch_lines, HatchResult, HatchSettings};
+pub use hit::distance_to_segment;
+pub use hpgl::{hpgl_job, HpglCommand, HpglJob, HpglSettings, HPGL_UNITS_PER_MM};
+pub use image::{PlacedImage, RasterImage};
+pub use join::{join_paths, JoinResult};
+pub use loader::{load_data, load_file, load_str, LoadOptions, LoadProgress};
+pub use measure::PathPoint;
+pub use nodes::{delete_node, editable_segments, insert_node, move_node, nearest_segment, path_nodes, toggle_smooth, Node, NodePart};
+pub use offset::{offset_contours, offset_paths, outline_strokes, stroke_outline, OffsetResult, StrokeResult};
+pub use overlap::{remove_overlaps, OverlapResult};
+pub use palette::{color_layer_svgs, replace_color, PaletteColor};
+pub use pdf::{save_pdf, to_pdf};
+pub use profile::{MachineKind, MachineProfile};
+pub use quantize::{apply_color_mode, pen_colors, ColorMode};
+pub use raster::{render_document, render_png, render_view_png, save_png};
+pub use saver::{save_file, save_file_with, to_svg_string, to_svg_string_with, SaveOptions};
+pub use shapes::{add_shape, ellipse, polygon, polyline, rectangle};
+pub use simplify::{simplify_paths, simplify_polyline, SimplifyMethod, Simplified};
+pub use simulate::{simulate, SimulatedMove, Simulation, SimulationSettings};
+pub use spatial::{IndexEntry, SpatialIndex};
+pub use stipple::{stipple, Stipple, StippleSettings};
+pub use style::{Color, FillRule, Gradient, GradientShape, LineCap, LineJoin, Paint, SpreadMethod, Style};
+pub use text::{AddText, EditText, TextAnchor, TextLayout};
+pub use tikz::{to_tikz, TikzSettings};
+pub use trace::{trace_bitmap, Trace, TraceSettings};
+pub use units::{parse_length, Unit, CSS_DPI};
+pub use view::ViewTransform;
+pub use winding::{even_odd_paths, winding_regions, EvenOddResult, WindingRegion};
+
+pub use resvg::usvg;
@@ -0,0 +1,849 @@
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use resvg::tiny_skia::PathSegment;
+use resvg::usvg::{self, TreeParsing, TreePostProc};
+
+use crate::clip::ClipRegion;
+use crate::diagnostics::{collect_warnings, Diagnostic, LoadError};
+use crate::document::{bbox_of, transform_point, Contour, Document, Element, ElementId, ElementKind, FlattenedPath, Segment};
+use crate::image::{PlacedImage, RasterImage};
+use crate::source::{attach_sources, tag_elements};
+use crate::style::{Color, FillRule, Gradient, GradientShape, LineCap, LineJoin, Paint, SpreadMethod, Style};
+use crate::units::{parse_attribute, Unit, CSS_DPI};
+use crate::validate::validate;
+
+/// Settings shared by all documents loaded in a session.
+#[derive(Clone)]
+pub struct LoadOptions {
+    /// Fonts for converting `<text>` into paths
+    pub fontdb: Arc<usvg::fontdb::Database>,
+    /// Base directory for relative references like `<image href="…">`. Set by [`load_file`].
+    pub resources_dir: Option<PathBuf>,
+    /// How far flattened curves may deviate from the true curve, in user units.
+    /// See [`Document::reflatten`] for adapting it to the zoom later.
+    pub tolerance: f32,
+    /// Reported to and checked for cancellation while loading, for loads on another thread.
+    pub progress: Option<Arc<LoadProgress>>,
+    /// Check the source against the spec and list every violation with its line and column
+    /// in [`Document::warnings`]. Otherwise only what usvg reports about content it skipped.
+    pub strict: bool,
+    /// Pixels per inch of a `width` and `height` given in px or without a unit. CSS says 96,
+    /// older Inkscape used 90 and some programs 72.
+    pub dpi: f32,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self { fontdb: system_fonts(), resources_dir: None, tolerance: DEFAULT_TOLERANCE, progress: None, strict: false, dpi: CSS_DPI }
+    }
+}
+
+/// Shared between a load running on a worker thread and the UI waiting for it.
+#[derive(Debug, Default)]
+pub struct LoadProgress {
+    // f32 bits
+    fraction: AtomicU32,
+    cancelled: AtomicBool,
+}
+
+impl LoadProgress {
+    /// How much of the load is done, 0..1.
+    pub fn fraction(&self) -> f32 {
+        f32::from_bits(self.fraction.load(Ordering::Relaxed))
+    }
+
+    fn set_fraction(&self, fraction: f32) {
+        self.fraction.store(fraction.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Make the load stop at the next opportunity and fail.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+impl LoadOptions {
+    /// System fonts plus the fonts found in `dirs`.
+    pub fn with_font_dirs(dirs: &[PathBuf]) -> Self {
+        if dirs.is_empty() {
+            return Self::default();
+        }
+        let mut fontdb = (*system_fonts()).clone();
+        for dir in dirs {
+            fontdb.load_fonts_dir(dir);
+        }
+        Self { fontdb: Arc::new(fontdb), ..Self::default() }
+    }
+}
+
+/// Scanning the system fonts takes a while, so it is done once per process.
+fn system_fonts() -> Arc<usvg::fontdb::Database> {
+    static FONTS: OnceLock<Arc<usvg::fontdb::Database>> = OnceLock::new();
+    FONTS
+        .get_or_init(|| {
+            let mut fontdb = usvg::fontdb::Database::new();
+            fontdb.load_system_fonts();
+            Arc::new(fontdb)
+        })
+        .clone()
+}
+
+pub fn load_file(path: &Path, opts: &LoadOptions) -> Result<Document, LoadError> {
+    let opts = LoadOptions { resources_dir: path.parent().map(|p| p.to_path_buf()), ..opts.clone() };
+    load_data(&fs::read(path)?, &opts)
+}
+
+/// Plain or gzip compressed (.svgz) SVG data.
+pub fn load_data(data: &[u8], opts: &LoadOptions) -> Result<Document, LoadError> {
+    if data.starts_with(&[0x1f, 0x8b]) {
+        let mut svg = String::new();
+        flate2::read::GzDecoder::new(data).read_to_string(&mut svg)?;
+        load_str(&svg, opts)
+    } else {
+        load_str(std::str::from_utf8(data)?, opts)
+    }
+}
+
+pub fn load_str(svg: &str, opts: &LoadOptions) -> Result<Document, LoadError> {
+    let progress = opts.progress.as_deref();
+    let check_cancelled = |fraction: f32| match progress {
+        Some(p) if p.is_cancelled() => Err(LoadError::Cancelled),
+        Some(p) => {
+            p.set_fraction(fraction);
+            Ok(())
+        }
+        None => Ok(()),
+    };
+
+    let usvg_opts = usvg::Options {
+        resources_dir: opts.resources_dir.clone(),
+        ..Default::default()
+    };
+    let (doc, warnings) = collect_warnings(|| -> Result<Document, LoadError> {
+        // what usvg::Tree::from_str does, but keeping the XML for checking it and finding
+        // where elements came from
+        let xml_opts = usvg::roxmltree::ParsingOptions { allow_dtd: true, ..Default::default() };
+        let xml = usvg::roxmltree::Document::parse_with_options(svg, xml_opts).map_err(usvg::Error::ParsingFailed)?;
+        let problems = if opts.strict { validate(&xml) } else { vec![] };
+        let (tagged, ranges) = tag_elements(svg, &xml);
+        let tagged = usvg::roxmltree::Document::parse_with_options(&tagged, xml_opts).map_err(usvg::Error::ParsingFailed)?;
+        let mut tree = usvg::Tree::from_xmltree(&tagged, &usvg_opts)?;
+        check_cancelled(0.3)?;
+        // fills Text::flattened with the glyph outlines
+        tree.postprocess(usvg::PostProcessingSteps { convert_text_into_paths: true }, &opts.fontdb);
+        check_cancelled(0.4)?;
+
+        // usvg takes px as CSS pixels, the physical units are right already
+        let unit = xml.root_element().attribute("width").and_then(parse_attribute).map_or(Unit::Px, |(_, unit)| unit);
+        let scale = if unit == Unit::Px && opts.dpi > 0.0 { CSS_DPI / opts.dpi } else { 1.0 };
+        let mut doc = Document {
+            size: [tree.size.width() * scale, tree.size.height() * scale],
+            unit,
+            view_box: [tree.view_box.rect.x(), tree.view_box.rect.y(), tree.view_box.rect.width(), tree.view_box.rect.height()],
+            aspect: tree.view_box.aspect,
+            warnings: problems,
+            source: svg.to_string(),
+            ..Default::default()
+        };
+        let root = doc.root;
+        let mut converter = Converter { tolerance: opts.tolerance, progress, converted: 0, total: count_nodes(&tree.root) };
+        convert_group_into(&mut doc, root, &tree.root, &mut converter);
+        attach_sources(&mut doc, &ranges);
+        Ok(doc)
+    });
+    let mut doc = doc?;
+    // usvg's warnings after the problems found in the source, before the converter's own
+    let at = doc.warnings.iter().take_while(|w| w.position.is_some()).count();
+    doc.warnings.splice(at..at, warnings);
+    check_cancelled(0.6)?;
+    flatten_paths(&mut doc, progress);
+    check_cancelled(1.0)?;
+    if doc.bbox().is_none() {
+        doc.warnings.push(Diagnostic::new("The document has no visible content"));
+    }
+    Ok(doc)
+}
+
+/// Flatten and tessellate all paths of a freshly converted document, spread over all cores.
+/// Each path only touches its own element, so the document order stays as it is.
+fn flatten_paths(doc: &mut Document, progress: Option<&LoadProgress>) {
+    use rayon::prelude::*;
+
+    let total = doc.path_count().max(1);
+    let done = AtomicUsize::new(0);
+    doc.elements.par_iter_mut().filter_map(|e| e.as_path_mut()).for_each(|path| {
+        if let Some(progress) = progress {
+            if progress.is_cancelled() {
+                return;
+            }
+            let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+            if done.is_multiple_of(256) {
+                progress.set_fraction(0.6 + 0.4 * done as f32 / total as f32);
+            }
+        }
+        path.contours = flatten_segments(&path.segments, path.tolerance);
+        path.update_meshes();
+    });
+}
+
+/// State of converting one usvg tree into a document.
+struct Converter<'a> {
+    tolerance: f32,
+    progress: Option<&'a LoadProgress>,
+    converted: usize,
+    total: usize,
+}
+
+impl Converter<'_> {
+    /// Count a converted node. False once the load was cancelled.
+    fn step(&mut self) -> bool {
+        self.converted += 1;
+        let Some(progress) = self.progress else { return true };
+        if self.converted.is_multiple_of(256) {
+            // parsing took the first 40%, flattening takes the last 40%
+            progress.set_fraction(0.4 + 0.2 * self.converted as f32 / self.total.max(1) as f32);
+        }
+        !progress.is_cancelled()
+    }
+}
+
+fn count_nodes(group: &usvg::Group) -> usize {
+    group.children.iter().map(|node| match node {
+        usvg::Node::Group(g) => 1 + count_nodes(g),
+        usvg::Node::Text(t) => 1 + t.flattened.as_deref().map_or(0, count_nodes),
+        _ => 1,
+    }).sum()
+}
+
+const DEFAULT_TOLERANCE: f32 = 0.05;
+
+/// `bbox` is the path's bounding box in its own coordinates, which is also the space the
+/// resulting gradient is evaluated in.
+fn convert_paint(paint: &usvg::Paint, opacity: f32, bbox: Option<usvg::NonZeroRect>) -> Paint {
+    let opacity = opacity.clamp(0.0, 1.0);
+    let (base, shape) = match paint {
+        usvg::Paint::Color(c) => {
+            return Paint::Solid(Color::rgb(c.red, c.green, c.blue).with_alpha(opacity));
+        }
+        usvg::Paint::LinearGradient(lg) => {
+            (&lg.base, GradientShape::Linear { x1: lg.x1, y1: lg.y1, x2: lg.x2, y2: lg.y2 })
+        }
+        usvg::Paint::RadialGradient(rg) => {
+            (&rg.base, GradientShape::Radial { cx: rg.cx, cy: rg.cy, r: rg.r.get(), fx: rg.fx, fy: rg.fy })
+        }
+        // patterns are not supported yet, show them as neutral gray
+        usvg::Paint::Pattern(_) => {
+            return Paint::Solid(Color::rgb(128, 128, 128).with_alpha(opacity));
+        }
+    };
+
+    // objectBoundingBox gradients are defined in the unit square spanned by the path's bbox
+    let mut to_user = base.transform;
+    if base.units == usvg::Units::ObjectBoundingBox {
+        if let Some(rect) = bbox {
+            to_user = usvg::Transform::from_bbox(rect).pre_concat(to_user);
+        }
+    }
+
+    let stops = base.stops.iter()
+        .map(|s| {
+            let c = s.color;
+            (s.offset.get(), [
+                c.red as f32 / 255.0,
+                c.green as f32 / 255.0,
+                c.blue as f32 / 255.0,
+                s.opacity.get() * opacity,
+            ])
+        })
+        .collect();
+
+    Paint::Gradient(Arc::new(Gradient {
+        shape,
+        stops,
+        spread: match base.spread_method {
+            usvg::SpreadMethod::Pad => SpreadMethod::Pad,
+            usvg::SpreadMethod::Reflect => SpreadMethod::Reflect,
+            usvg::SpreadMethod::Repeat => SpreadMethod::Repeat,
+        },
+        to_gradient: to_user.invert().unwrap_or_default(),
+    }))
+}
+
+/// Copy the properties of the usvg group `group` onto the document element `id` and convert
+/// its children into child elements. usvg already moved `transform` attributes of paths
+/// into their parent group, so only groups carry transforms.
+fn convert_group_into(doc: &mut Document, id: ElementId, group: &usvg::Group, cx: &mut Converter) {
+    let element = doc.get_mut(id);
+    element.source_id = group.id.clone();
+    element.transform = group.transform;
+    element.opacity = group.opacity.get();
+
+    let mut last_path = None;
+    for node in &group.children {
+        if !cx.step() {
+            return;
+        }
+        let prev_path = last_path.take();
+        match node {
+            usvg::Node::Group(g) => {
+                // markers become children of their path, so they are hidden and deleted with it
+                let parent = prev_path.filter(|_| is_marker_group(g)).unwrap_or(id);
+                let child = doc.add(parent, Element::new(ElementKind::Group));
+                convert_group_into(doc, child, g, cx);
+            }
+            usvg::Node::Path(p) if p.visibility == usvg::Visibility::Visible => {
+                let mut element = Element::new(ElementKind::Path(convert_path(p, cx.tolerance)));
+                element.source_id = p.id.clone();
+                last_path = Some(doc.add(id, element));
+            }
+            usvg::Node::Text(t) => {
+                let content = t.chunks.iter().map(|c| c.text.as_str()).collect();
+                let text = doc.add(id, Element::new(ElementKind::Text { content, layout: None }));
+                // the glyph outlines become path children of the text element
+                if let Some(flattened) = &t.flattened {
+                    convert_group_into(doc, text, flattened, cx);
+                }
+                doc.get_mut(text).source_id = t.id.clone();
+            }
+            usvg::Node::Image(img) if img.visibility == usvg::Visibility::Visible => {
+                let r = img.view_box.rect;
+                let image = match RasterImage::decode(&img.kind, r) {
+                    Ok(pixels) => Some(PlacedImage::new(&img.view_box, Arc::new(pixels))),
+                    Err(e) => {
+                        doc.warnings.push(Diagnostic::new(format!("Failed to decode image '{}': {}", img.id, e)));
+                        None
+                    }
+                };
+                let mut element = Element::new(ElementKind::Image { rect: [r.x(), r.y(), r.width(), r.height()], image });
+                element.source_id = img.id.clone();
+                doc.add(id, element);
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(region) = group_clip_region(doc, id, group, cx.tolerance) {
+        for (child, ts) in relative_transforms(doc, id) {
+            let Some(inverse) = ts.invert() else { continue };
+            let local = region.transformed(&inverse);
+            if let Some(path) = doc.get_mut(child).as_path_mut() {
+                // clips of inner groups were applied first, intersect with them
+                let clip = match &path.clip {
+                    Some(inner) => inner.intersect(&local),
+                    None => local,
+                };
+                path.clip = Some(Arc::new(clip));
+            }
+        }
+    }
+}
+
+/// usvg instantiates marker-start/-mid/-end as an anonymous group right after the path, with
+/// one positioned and oriented group per marker. A plain `<g>` without any ids or attributes
+/// after a path looks the same, it only ends up below the path then, which draws identically.
+fn is_marker_group(g: &usvg::Group) -> bool {
+    g.id.is_empty()
+        && g.transform.is_identity()
+        && g.opacity.get() == 1.0
+        && g.clip_path.is_none()
+        && g.mask.is_none()
+        && g.filters.is_empty()
+        && !g.children.is_empty()
+        && g.children.iter().all(|n| matches!(n, usvg::Node::Group(m) if m.id.is_empty()))
+}
+
+/// All elements below `id` with their transforms relative to `id`'s own coordinates.
+fn relative_transforms(doc: &Document, id: ElementId) -> Vec<(ElementId, usvg::Transform)> {
+    let mut out = vec![];
+    let mut stack: Vec<(ElementId, usvg::Transform)> = doc.get(id).children.iter().map(|&c| (c, doc.get(c).transform)).collect();
+    while let Some((child, ts)) = stack.pop() {
+        out.push((child, ts));
+        stack.extend(doc.get(child).children.iter().map(|&c| (c, ts.pre_concat(doc.get(c).transform))));
+    }
+    out
+}
+
+/// The clip-path and mask of `group` as one region in the group's coordinates. Masks are
+/// approximated by the area their content covers, luminance and alpha are not evaluated.
+/// Images inside clipped groups are not cut.
+fn group_clip_region(doc: &Document, id: ElementId, group: &usvg::Group, tolerance: f32) -> Option<ClipRegion> {
+    if group.clip_path.is_none() && group.mask.is_none() {
+        return None;
+    }
+    // objectBoundingBox units refer to the bbox of the group's content
+    let points: Vec<[f32; 2]> = relative_transforms(doc, id)
+        .into_iter()
+        .filter_map(|(child, ts)| Some((doc.get(child).as_path()?, ts)))
+        // not flattened yet at this point
+        .flat_map(|(path, ts)| flatten_segments(&path.segments, path.tolerance).into_iter().flat_map(|c| c.points).map(move |p| transform_point(&ts, p)))
+        .collect();
+    let bbox = bbox_of(points.iter()).and_then(|b| usvg::NonZeroRect::from_ltrb(b[0], b[1], b[2], b[3]));
+
+    let mut region = group.clip_path.as_ref().map(|clip| clip_path_region(&clip.borrow(), bbox, tolerance));
+    if let Some(mask) = &group.mask {
+        let mask = mask.borrow();
+        let bbox_ts = bbox.map(usvg::Transform::from_bbox).unwrap_or_default();
+        let content_ts = if mask.content_units == usvg::Units::ObjectBoundingBox { bbox_ts } else { usvg::Transform::identity() };
+        let r = mask.rect;
+        let mut mask_region = ClipRegion::rect([r.x(), r.y(), r.width(), r.height()]);
+        if mask.units == usvg::Units::ObjectBoundingBox {
+            mask_region = mask_region.transformed(&bbox_ts);
+        }
+        let mask_region = mask_region.intersect(&group_region(&mask.root, content_ts, tolerance));
+        region = Some(match region {
+            Some(clip) => clip.intersect(&mask_region),
+            None => mask_region,
+        });
+    }
+    region
+}
+
+fn clip_path_region(clip: &usvg::ClipPath, bbox: Option<usvg::NonZeroRect>, tolerance: f32) -> ClipRegion {
+    let mut ts = clip.transform;
+    if clip.units == usvg::Units::ObjectBoundingBox {
+        if let Some(bbox) = bbox {
+            ts = ts.pre_concat(usvg::Transform::from_bbox(bbox));
+        }
+    }
+    let region = group_region(&clip.root, ts, tolerance);
+    match &clip.clip_path {
+        // a clip-path on the clipPath element itself
+        Some(nested) => region.intersect(&clip_path_region(&nested.borrow(), bbox, tolerance)),
+        None => region,
+    }
+}
+
+/// The area covered by the filled shapes of `group`, transformed by `ts`.
+fn group_region(group: &usvg::Group, ts: usvg::Transform, tolerance: f32) -> ClipRegion {
+    let mut region = ClipRegion::default();
+    for node in &group.children {
+        match node {
+            usvg::Node::Group(g) => region.triangles.extend(group_region(g, ts.pre_concat(g.transform), tolerance).triangles),
+            usvg::Node::Path(p) => {
+                let rule = match p.fill.as_ref().map(|f| f.rule) {
+                    Some(usvg::FillRule::EvenOdd) => FillRule::EvenOdd,
+                    _ => FillRule::NonZero,
+                };
+                let (vertices, indices) = tessellate_fill(&convert_segments(&p.data), rule, tolerance);
+                region.triangles.extend(ClipRegion::from_mesh(&vertices, &indices).transformed(&ts).triangles);
+            }
+            usvg::Node::Text(t) => {
+                if let Some(flattened) = &t.flattened {
+                    region.triangles.extend(group_region(flattened, ts.pre_concat(flattened.transform), tolerance).triangles);
+                }
+            }
+            usvg::Node::Image(_) => {}
+        }
+    }
+    region
+}
+
+/// Convert `path` into a path element to be flattened with `tolerance`. Contours and meshes
+/// stay empty until [`flatten_paths`] fills them in.
+fn convert_path(path: &usvg::Path, tolerance: f32) -> FlattenedPath {
+    let local_bbox = path.data.compute_tight_bounds().and_then(|r| r.to_non_zero_rect());
+    let style = Style {
+        fill: path.fill.as_ref().map(|f| convert_paint(&f.paint, f.opacity.get(), local_bbox)),
+        fill_rule: match path.fill.as_ref().map(|f| f.rule) {
+            Some(usvg::FillRule::EvenOdd) => FillRule::EvenOdd,
+            _ => FillRule::NonZero,
+        },
+        stroke: path.stroke.as_ref().map(|s| convert_paint(&s.paint, s.opacity.get(), local_bbox)),
+        stroke_width: path.stroke.as_ref().map_or(0.0, |s| s.width.get()),
+        line_cap: match path.stroke.as_ref().map(|s| s.linecap) {
+            Some(usvg::LineCap::Round) => LineCap::Round,
+            Some(usvg::LineCap::Square) => LineCap::Square,
+            _ => LineCap::Butt,
+        },
+        line_join: match path.stroke.as_ref().map(|s| s.linejoin) {
+            Some(usvg::LineJoin::Round) => LineJoin::Round,
+            Some(usvg::LineJoin::Bevel) => LineJoin::Bevel,
+            _ => LineJoin::Miter,
+        },
+        miter_limit: path.stroke.as_ref().map_or(4.0, |s| s.miterlimit.get()),
+    };
+
+    FlattenedPath {
+        segments: convert_segments(&path.data),
+        contours: vec![],
+        style,
+        tolerance,
+        fill_vertices: vec![],
+        fill_indices: vec![],
+        fill_colors: vec![],
+        stroke_vertices: vec![],
+        stroke_indices: vec![],
+        stroke_colors: vec![],
+        clip: None,
+    }
+}
+
+fn convert_segments(data: &resvg::tiny_skia::Path) -> Vec<Segment> {
+    let point = |p: resvg::tiny_skia::Point| [p.x, p.y];
+    data.segments()
+        .map(|segment| match segment {
+            PathSegment::MoveTo(p) => Segment::MoveTo(point(p)),
+            PathSegment::LineTo(p) => Segment::LineTo(point(p)),
+            PathSegment::QuadTo(p1, p) => Segment::QuadTo(point(p1), point(p)),
+            PathSegment::CubicTo(p1, p2, p) => Segment::CubicTo(point(p1), point(p2), point(p)),
+            PathSegment::Close => Segment::Close,
+        })
+        .collect()
+}
+
+/// Polylines for the subpaths of `segments`, curves split so they deviate at most `tolerance`.
+/// Every kind of segment is handled. usvg turns elliptical arcs into cubics, H and V into
+/// lines and S and T into plain cubics and quadratics while parsing, quadratics stay
+/// `QuadTo` and are flattened as they are.
+pub(crate) fn flatten_segments(segments: &[Segment], tolerance: f32) -> Vec<Contour> {
+    let mut contours = vec![];
+    let mut current: Vec<[f32; 2]> = vec![];
+
+    fn finish(contours: &mut Vec<Contour>, current: &mut Vec<[f32; 2]>, closed: bool) {
+        if current.len() > 1 {
+            contours.push(Contour { points: std::mem::take(current), closed });
+        }
+        current.clear();
+    }
+
+    for &segment in segments {
+        let start = current.last().copied().unwrap_or([0.0, 0.0]);
+        match segment {
+            Segment::MoveTo(p) => {
+                finish(&mut contours, &mut current, false);
+                current.push(p);
+            }
+            Segment::LineTo(p) => current.push(p),
+            Segment::QuadTo(p1, p) => {
+                let steps = curve_steps(&[start, p1, p], tolerance);
+                for i in 1..=steps {
+                    let t = i as f32 / steps as f32;
+                    let mt = 1.0 - t;
+                    let (a, b, c) = (mt * mt, 2.0 * mt * t, t * t);
+                    current.push([
+                        a * start[0] + b * p1[0] + c * p[0],
+                        a * start[1] + b * p1[1] + c * p[1],
+                    ]);
+                }
+            }
+            Segment::CubicTo(p1, p2, p) => {
+                let steps = curve_steps(&[start, p1, p2, p], tolerance);
+                for i in 1..=steps {
+                    let t = i as f32 / steps as f32;
+                    let mt = 1.0 - t;
+                    let (a, b, c, d) = (mt * mt * mt, 3.0 * mt * mt * t, 3.0 * mt * t * t, t * t * t);
+                    current.push([
+                        a * start[0] + b * p1[0] + c * p2[0] + d * p[0],
+                        a * start[1] + b * p1[1] + c * p2[1] + d * p[1],
+                    ]);
+                }
+            }
+            Segment::Close => {
+                let restart = current.first().copied();
+                finish(&mut contours, &mut current, true);
+                // a segment following closepath starts at the contour's first point
+                current.extend(restart);
+            }
+        }
+    }
+    finish(&mut contours, &mut current, false);
+    contours
+}
+
+/// Number of line segments that keep a Bezier with the control points `points` within
+/// `tolerance` of the curve (Wang's formula).
+fn curve_steps(points: &[[f32; 2]], tolerance: f32) -> usize {
+    let degree = (points.len() - 1) as f32;
+    let max_second_diff = points
+        .windows(3)
+        .map(|w| ((w[0][0] - 2.0 * w[1][0] + w[2][0]).powi(2) + (w[0][1] - 2.0 * w[1][1] + w[2][1]).powi(2)).sqrt())
+        .fold(0.0, f32::max);
+    let steps = (degree * (degree - 1.0) / 8.0 * max_second_diff / tolerance.max(1e-6)).sqrt().ceil();
+    (steps as usize).clamp(1, 1000)
+}
+
+
+impl FlattenedPath {
+    /// Flatten the curves again with `tolerance`, in the path's coordinates, and tessellate
+    /// the meshes with it. Paths without curves stay as they are.
+    pub fn reflatten(&mut self, tolerance: f32) {
+        if !self.has_curves() {
+            return;
+        }
+        self.contours = flatten_segments(&self.segments, tolerance);
+        self.tolerance = tolerance;
+        self.update_meshes();
+    }
+
+    /// Swap in new geometry, flattened and tessellated with the current tolerance.
+    pub fn set_segments(&mut self, segments: Vec<Segment>) {
+        self.contours = flatten_segments(&segments, self.tolerance);
+        self.segments = segments;
+        self.update_meshes();
+    }
+
+    /// Tessellate the fill and stroke meshes from the segments, needed after the segments, the
+    /// style or the clip changed.
+    pub fn update_meshes(&mut self) {
+        let style = &self.style;
+        (self.fill_vertices, self.fill_indices) = match style.fill {
+            Some(_) => tessellate_fill(&self.segments, style.fill_rule, self.tolerance),
+            None => (vec![], vec![]),
+        };
+        (self.stroke_vertices, self.stroke_indices) = match style.stroke {
+            Some(_) if style.stroke_width > 0.0 => tessellate_stroke(&self.segments, style, self.tolerance),
+            _ => (vec![], vec![]),
+        };
+
+        if let (Some(Paint::Gradient(_)), Some(b)) = (&style.fill, self.bbox()) {
+            let diagonal = ((b[2] - b[0]).powi(2) + (b[3] - b[1]).powi(2)).sqrt();
+            subdivide_mesh(&mut self.fill_vertices, &mut self.fill_indices, diagonal / 16.0);
+        }
+
+        if let Some(clip) = &self.clip {
+            (self.fill_vertices, self.fill_indices) = clip.clip_mesh(&self.fill_vertices, &self.fill_indices);
+            (self.stroke_vertices, self.stroke_indices) = clip.clip_mesh(&self.stroke_vertices, &self.stroke_indices);
+        }
+
+        let colors = |paint: &Option<Paint>, vertices: &[[f32; 2]]| match paint {
+            Some(Paint::Gradient(gradient)) => vertices.iter().map(|&v| gradient.color_at(v)).collect(),
+            _ => vec![],
+        };
+        self.fill_colors = colors(&self.style.fill, &self.fill_vertices);
+        self.stroke_colors = colors(&self.style.stroke, &self.stroke_vertices);
+    }
+}
+
+/// Split triangles into four until no edge is longer than `max_len` (at most a few rounds),
+/// so that per-vertex gradient colors interpolate smoothly across large triangles.
+fn subdivide_mesh(vertices: &mut Vec<[f32; 2]>, indices: &mut Vec<u32>, max_len: f32) {
+    let len2 = |a: [f32; 2], b: [f32; 2]| (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2);
+    let max_len2 = max_len * max_len;
+
+    for _ in 0..4 {
+        let mut out = Vec::with_capacity(indices.len());
+        let mut split_any = false;
+        for tri in indices.chunks_exact(3) {
+            let (a, b, c) = (tri[0], tri[1], tri[2]);
+            let (pa, pb, pc) = (vertices[a as usize], vertices[b as usize], vertices[c as usize]);
+            if len2(pa, pb).max(len2(pb, pc)).max(len2(pc, pa)) <= max_len2 {
+                out.extend_from_slice(tri);
+                continue;
+            }
+            split_any = true;
+            let mut midpoint = |p: [f32; 2], q: [f32; 2]| {
+                vertices.push([(p[0] + q[0]) * 0.5, (p[1] + q[1]) * 0.5]);
+                (vertices.len() - 1) as u32
+            };
+            let ab = midpoint(pa, pb);
+            let bc = midpoint(pb, pc);
+            let ca = midpoint(pc, pa);
+            out.extend_from_slice(&[a, ab, ca, ab, b, bc, ca, bc, c, ab, bc, ca]);
+        }
+        *indices = out;
+        if !split_any {
+            break;
+        }
+    }
+}
+
+/// The segments as a lyon path. Subpaths that do not start with a move continue from the
+/// start of the previous one, as after a closepath.
+fn lyon_path(segments: &[Segment]) -> lyon::path::Path {
+    use lyon::math::point;
+
+    let mut builder = lyon::path::Path::builder();
+    let mut open = false;
+    let mut start = [0.0, 0.0];
+    for &segment in segments {
+        if !open && !matches!(segment, Segment::MoveTo(_) | Segment::Close) {
+            builder.begin(point(start[0], start[1]));
+            open = true;
+        }
+        match segment {
+            Segment::MoveTo(p) => {
+                if open {
+                    builder.end(false);
+                }
+                builder.begin(point(p[0], p[1]));
+                start = p;
+                open = true;
+            }
+            Segment::LineTo(p) => {
+                builder.line_to(point(p[0], p[1]));
+            }
+            Segment::QuadTo(c, p) => {
+                builder.quadratic_bezier_to(point(c[0], c[1]), point(p[0], p[1]));
+            }
+            Segment::CubicTo(c1, c2, p) => {
+                builder.cubic_bezier_to(point(c1[0], c1[1]), point(c2[0], c2[1]), point(p[0], p[1]));
+            }
+            Segment::Close => {
+                if open {
+                    builder.end(true);
+                    open = false;
+                }
+            }
+        }
+    }
+    if open {
+        builder.end(false);
+    }
+    builder.build()
+}
+
+/// Triangulate the area enclosed by `segments` under `rule`, curves within `tolerance`.
+/// Open subpaths are implicitly closed, as SVG does for fills.
+fn tessellate_fill(segments: &[Segment], rule: FillRule, tolerance: f32) -> (Vec<[f32; 2]>, Vec<u32>) {
+    use lyon::tessellation::{BuffersBuilder, FillOptions, FillTessellator, FillVertex, VertexBuffers};
+
+    let lyon_path = lyon_path(segments);
+    let options = FillOptions::tolerance(tolerance.max(1e-4)).with_fill_rule(match rule {
+        FillRule::NonZero => lyon::tessellation::FillRule::NonZero,
+        FillRule::EvenOdd => lyon::tessellation::FillRule::EvenOdd,
+    });
+    let mut buffers: VertexBuffers<[f32; 2], u32> = VertexBuffers::new();
+    let result = FillTessellator::new().tessellate_path(
+        &lyon_path,
+        &options,
+        &mut BuffersBuilder::new(&mut buffers, |v: FillVertex| v.position().to_array()),
+    );
+    match result {
+        Ok(()) => (buffers.vertices, buffers.indices),
+        Err(e) => {
+            log::warn!("Failed to tessellate fill: {:?}", e);
+            (vec![], vec![])
+        }
+    }
+}
+
+/// Triangulate the outline of the stroke along `segments` with the width, caps and joins
+/// of `style`.
+fn tessellate_stroke(segments: &[Segment], style: &Style, tolerance: f32) -> (Vec<[f32; 2]>, Vec<u32>) {
+    use lyon::tessellation::{BuffersBuilder, StrokeOptions, StrokeTessellator, StrokeVertex, VertexBuffers};
+
+    let options = StrokeOptions::tolerance(tolerance.max(1e-4))
+        .with_line_width(style.stroke_width)
+        .with_line_cap(match style.line_cap {
+            LineCap::Butt => lyon::tessellation::LineCap::Butt,
+            LineCap::Round => lyon::tessellation::LineCap::Round,
+            LineCap::Square => lyon::tessellation::LineCap::Square,
+        })
+        .with_line_join(match style.line_join {
+            LineJoin::Miter => lyon::tessellation::LineJoin::Miter,
+            LineJoin::Round => lyon::tessellation::LineJoin::Round,
+            LineJoin::Bevel => lyon::tessellation::LineJoin::Bevel,
+        })
+        .with_miter_limit(style.miter_limit.max(StrokeOptions::MINIMUM_MITER_LIMIT));
+    let mut buffers: VertexBuffers<[f32; 2], u32> = VertexBuffers::new();
+    let result = StrokeTessellator::new().tessellate_path(
+        &lyon_path(segments),
+        &options,
+        &mut BuffersBuilder::new(&mut buffers, |v: StrokeVertex| v.position().to_array()),
+    );
+    match result {
+        Ok(()) => (buffers.vertices, buffers.indices),
+        Err(e) => {
+            log::warn!("Failed to tessellate stroke: {:?}", e);
+            (vec![], vec![])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The paths of `d`, loaded on a 200 × 200 page.
+    fn load_path(d: &str) -> FlattenedPath {
+        let svg = format!(r#"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="200"><path d="{}" fill="none" stroke="black"/></svg>"#, d);
+        let doc = load_str(&svg, &LoadOptions::default()).unwrap();
+        let mut paths = doc.elements.iter().filter_map(|e| e.as_path());
+        let path = paths.next().expect("no path loaded").clone();
+        assert!(paths.next().is_none());
+        path
+    }
+
+    fn assert_bbox(path: &FlattenedPath, expected: [f32; 4]) {
+        let bbox = path.bbox().unwrap();
+        for (got, want) in bbox.iter().zip(expected) {
+            assert!((got - want).abs() < 0.5, "bbox {:?}, expected {:?}", bbox, expected);
+        }
+    }
+
+    fn kinds(path: &FlattenedPath) -> String {
+        path.segments
+            .iter()
+            .map(|s| match s {
+                Segment::MoveTo(_) => 'M',
+                Segment::LineTo(_) => 'L',
+                Segment::QuadTo(..) => 'Q',
+                Segment::CubicTo(..) => 'C',
+                Segment::Close => 'Z',
+            })
+            .collect()
+    }
+
+    #[test]
+    fn full_circle_of_arcs() {
+        let path = load_path("M 10 50 A 40 40 0 1 0 90 50 A 40 40 0 1 0 10 50 Z");
+        let kinds = kinds(&path);
+        assert!(kinds.starts_with("MC") && kinds.ends_with('Z') && !kinds.contains('L'), "{}", kinds);
+        assert_eq!(path.contours.len(), 1);
+        assert!(path.contours[0].closed);
+        assert_bbox(&path, [10.0, 10.0, 90.0, 90.0]);
+    }
+
+    #[test]
+    fn relative_arcs() {
+        let path = load_path("M 10 50 a 40 40 0 0 0 80 0 a 40 40 0 0 0 -80 0 z");
+        assert!(path.contours[0].closed);
+        assert_bbox(&path, [10.0, 10.0, 90.0, 90.0]);
+    }
+
+    #[test]
+    fn large_arc_and_sweep_flags() {
+        // (20, 10) and (80, 10) are on circles of radius 50 around (50, 50) and (50, -30)
+        for (flags, expected) in [("0 1", [20.0, 0.0, 80.0, 10.0]), ("1 0", [0.0, 10.0, 100.0, 100.0]), ("0 0", [20.0, 10.0, 80.0, 20.0]), ("1 1", [0.0, -80.0, 100.0, 10.0])] {
+            let path = load_path(&format!("M 20 10 A 50 50 0 {} 80 10", flags));
+            assert!(kinds(&path).starts_with("MC"));
+            assert_eq!(path.contours.len(), 1);
+            assert!(!path.contours[0].closed);
+            assert_bbox(&path, expected);
+        }
+    }
+
+    #[test]
+    fn rotated_ellipse() {
+        // the long axis turned upright
+        let path = load_path("M 50 10 A 40 20 90 0 1 50 90 A 40 20 90 0 1 50 10 Z");
+        assert!(path.contours[0].closed);
+        assert_bbox(&path, [30.0, 10.0, 70.0, 90.0]);
+    }
+
+    #[test]
+    fn smooth_quadratics() {
+        let path = load_path("M 0 100 Q 50 200 100 100 T 200 100");
+        assert_eq!(kinds(&path), "MQQ");
+        // T mirrors the control point to (150, 0)
+        assert_bbox(&path, [0.0, 50.0, 200.0, 150.0]);
+    }
+
+    #[test]
+    fn close_path() {
+        let path = load_path("M 0 0 L 10 0 L 10 10 Z L 0 10 M 50 50 L 60 50");
+        assert_eq!(path.contours.len(), 3);
+        assert!(path.contours[0].closed);
+        // after Z the next segment starts at the first point of the closed contour
+        assert_eq!(path.contours[1].points, vec![[0.0, 0.0], [0.0, 10.0]]);
+        assert!(!path.contours[2].closed);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::document::{transform_point, transform_scale, Contour, Document, ElementId};
+
+/// A point on the outline of a path, e.g. where a measurement starts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PathPoint {
+    pub id: ElementId,
+    /// index into the path's contours
+    pub contour: usize,
+    /// distance from the start of the contour along it, in document units
+    pub offset: f32,
+    /// in document coordinates
+    pub point: [f32; 2],
+}
+
+impl Document {
+    /// Closest point to `p` on the outline of a visible path, if one is within `tolerance`.
+    /// Both are in document coordinates, the topmost path wins a tie.
+    pub fn point_on_path(&self, p: [f32; 2], tolerance: f32) -> Option<PathPoint> {
+        let mut best: Option<(f32, PathPoint)> = None;
+        self.walk(|id, element, ts, _| {
+            let Some(path) = element.as_path() else { return };
+            for (i, contour) in path.contours.iter().enumerate() {
+                let points = doc_points(contour, &ts);
+                let mut offset = 0.0;
+                for w in points.windows(2) {
+                    let length = distance(w[0], w[1]);
+                    let t = if length > 0.0 {
+                        (((p[0] - w[0][0]) * (w[1][0] - w[0][0]) + (p[1] - w[0][1]) * (w[1][1] - w[0][1])) / (length * length)).clamp(0.0, 1.0)
+                    } else {
+                        0.0
+                    };
+                    let q = [w[0][0] + t * (w[1][0] - w[0][0]), w[0][1] + t * (w[1][1] - w[0][1])];
+                    let d = distance(p, q);
+                    if d <= tolerance && best.is_none_or(|(closest, _)| d <= closest) {
+                        best = Some((d, PathPoint { id, contour: i, offset: offset + t * length, point: q }));
+                    }
+                    offset += length;
+                }
+            }
+        });
+        best.map(|(_, point)| point)
+    }
+
+    /// Total length of the paths in the subtree of `id` in document units, what a plotter's
+    /// pen travels down. Non-uniform scales count with their average.
+    pub fn pen_down_length(&self, id: ElementId) -> f32 {
+        self.descendants(id)
+            .into_iter()
+            .filter_map(|id| Some(self.get(id).as_path()?.length() * transform_scale(&self.abs_transform(id))))
+            .sum()
+    }
+
+    /// Area enclosed by path `id` in document units, positive if it runs clockwise on screen.
+    pub fn path_area(&self, id: ElementId) -> Option<f32> {
+        let ts = self.abs_transform(id);
+        // the determinant is negative for mirroring transforms, which flip the direction too
+        Some(self.get(id).as_path()?.signed_area() * (ts.sx * ts.sy - ts.kx * ts.ky))
+    }
+
+    /// Distance between two points along the contour they are on, the shorter way round
+    /// closed contours. None if they are on different contours.
+    pub fn arc_length(&self, a: &PathPoint, b: &PathPoint) -> Option<f32> {
+        if (a.id, a.contour) != (b.id, b.contour) {
+            return None;
+        }
+        let contour = self.get(a.id).as_path()?.contours.get(a.contour)?;
+        let along = (a.offset - b.offset).abs();
+        if !contour.closed {
+            return Some(along);
+        }
+        let points = doc_points(contour, &self.abs_transform(a.id));
+        let total: f32 = points.windows(2).map(|w| distance(w[0], w[1])).sum();
+        Some(along.min(total - along))
+    }
+}
+
+/// Points of `contour` in document coordinates, closed ones ending at their first point again.
+fn doc_points(contour: &Contour, ts: &resvg::usvg::Transform) -> Vec<[f32; 2]> {
+    let mut points: Vec<[f32; 2]> = contour.points.iter().map(|&p| transform_point(ts, p)).collect();
+    if contour.closed {
+        if let Some(&first) = points.first() {
+            points.push(first);
+        }
+    }
+    points
+}
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt()
+}
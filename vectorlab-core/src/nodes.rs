@@ -0,0 +1,386 @@
+use crate::document::Segment;
+
+/// What of a node is dragged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodePart {
+    Anchor,
+    /// control point of the curve coming in
+    In,
+    /// control point of the curve going out
+    Out,
+}
+
+/// An anchor point of a path with its Bézier handles, in the path's own coordinates. The
+/// functions here work on the segments as [`editable_segments`] makes them, which is what
+/// `segment` indexes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Node {
+    /// index of the segment ending in the node, the MoveTo for the first one of a subpath
+    pub segment: usize,
+    pub point: [f32; 2],
+    /// None next to lines
+    pub in_handle: Option<[f32; 2]>,
+    pub out_handle: Option<[f32; 2]>,
+    /// the curve goes through without a corner
+    pub smooth: bool,
+}
+
+/// `segments` with quadratic curves raised to cubic ones, so each handle belongs to one node,
+/// and closed subpaths ending with a line back to their start, so it is a node like the others.
+/// Looks the same.
+pub fn editable_segments(segments: &[Segment]) -> Vec<Segment> {
+    let mut out = Vec::with_capacity(segments.len());
+    let (mut start, mut current) = ([0.0; 2], [0.0; 2]);
+    for &segment in segments {
+        match segment {
+            Segment::MoveTo(p) => (start, current) = (p, p),
+            Segment::QuadTo(c, p) => {
+                let c1 = lerp(current, c, 2.0 / 3.0);
+                let c2 = lerp(p, c, 2.0 / 3.0);
+                out.push(Segment::CubicTo(c1, c2, p));
+                current = p;
+                continue;
+            }
+            Segment::LineTo(p) | Segment::CubicTo(_, _, p) => current = p,
+            Segment::Close => {
+                if current != start && !matches!(out.last(), None | Some(Segment::MoveTo(_))) {
+                    out.push(Segment::LineTo(start));
+                }
+                current = start;
+            }
+        }
+        out.push(segment);
+    }
+    out
+}
+
+/// The nodes of `segments`, see [`editable_segments`]. The end of a closed subpath is the same
+/// node as its start and not listed again.
+pub fn path_nodes(segments: &[Segment]) -> Vec<Node> {
+    let segments = editable_segments(segments);
+    let mut nodes = vec![];
+    for (i, segment) in segments.iter().enumerate() {
+        let point = match *segment {
+            Segment::MoveTo(p) | Segment::LineTo(p) | Segment::CubicTo(_, _, p) => p,
+            Segment::QuadTo(..) | Segment::Close => continue,
+        };
+        if closing(&segments, i) {
+            continue;
+        }
+        let links = links(&segments, i);
+        let in_handle = links.incoming.and_then(|k| match segments[k] {
+            Segment::CubicTo(_, c2, _) => Some(c2),
+            _ => None,
+        });
+        let out_handle = links.outgoing.and_then(|k| match segments[k] {
+            Segment::CubicTo(c1, _, _) => Some(c1),
+            _ => None,
+        });
+        // going on in about the same direction
+        let smooth = match (direction_in(&segments, i, &links), direction_out(&segments, i, &links)) {
+            (Some(a), Some(b)) => (a[0] * b[1] - a[1] * b[0]).abs() < 0.02 && a[0] * b[0] + a[1] * b[1] > 0.0,
+            _ => false,
+        };
+        nodes.push(Node { segment: i, point, in_handle, out_handle, smooth });
+    }
+    nodes
+}
+
+/// `segments` with `part` of the node at `segment` moved to `to`. Anchors take their handles
+/// along, the handles of smooth nodes turn together.
+pub fn move_node(segments: &[Segment], segment: usize, part: NodePart, to: [f32; 2]) -> Vec<Segment> {
+    let mut segments = editable_segments(segments);
+    let Some(node) = path_nodes(&segments).into_iter().find(|n| n.segment == segment) else { return segments };
+    let links = links(&segments, segment);
+    match part {
+        NodePart::Anchor => {
+            let delta = [to[0] - node.point[0], to[1] - node.point[1]];
+            let shift = |p: [f32; 2]| [p[0] + delta[0], p[1] + delta[1]];
+            for k in [Some(segment), links.twin].into_iter().flatten() {
+                set_end(&mut segments[k], to);
+            }
+            if let Some(Segment::CubicTo(_, c2, _)) = links.incoming.map(|k| &mut segments[k]) {
+                *c2 = shift(*c2);
+            }
+            if let Some(Segment::CubicTo(c1, _, _)) = links.outgoing.map(|k| &mut segments[k]) {
+                *c1 = shift(*c1);
+            }
+        }
+        NodePart::In | NodePart::Out => {
+            let (this, other) = if part == NodePart::In { (links.incoming, links.outgoing) } else { (links.outgoing, links.incoming) };
+            let Some(this) = this else { return segments };
+            set_handle(&mut segments[this], part == NodePart::In, to);
+            let (Some(other), true) = (other, node.smooth) else { return segments };
+            let Some(old) = handle(&segments[other], part == NodePart::Out) else { return segments };
+            let length = distance(old, node.point);
+            let away = [node.point[0] - to[0], node.point[1] - to[1]];
+            let d = distance(to, node.point);
+            if d > 0.0 {
+                let mirrored = [node.point[0] + away[0] / d * length, node.point[1] + away[1] / d * length];
+                set_handle(&mut segments[other], part == NodePart::Out, mirrored);
+            }
+        }
+    }
+    segments
+}
+
+/// `segments` with a node added at `t` (0 to 1) along `segment`, splitting it without changing
+/// the shape.
+pub fn insert_node(segments: &[Segment], segment: usize, t: f32) -> Vec<Segment> {
+    let mut segments = editable_segments(segments);
+    if segment >= segments.len() {
+        return segments;
+    }
+    let from = start_of(&segments, segment);
+    let replacement = match segments.get(segment) {
+        Some(&Segment::LineTo(p)) => vec![Segment::LineTo(lerp(from, p, t)), Segment::LineTo(p)],
+        Some(&Segment::CubicTo(c1, c2, p)) => {
+            // de Casteljau
+            let (a, b, c) = (lerp(from, c1, t), lerp(c1, c2, t), lerp(c2, p, t));
+            let (d, e) = (lerp(a, b, t), lerp(b, c, t));
+            vec![Segment::CubicTo(a, d, lerp(d, e, t)), Segment::CubicTo(e, c, p)]
+        }
+        _ => return segments,
+    };
+    segments.splice(segment..=segment, replacement);
+    segments
+}
+
+/// `segments` without the node at `segment`, the curves on either side joined into one. Open
+/// subpaths left with a single node are removed.
+pub fn delete_node(segments: &[Segment], segment: usize) -> Vec<Segment> {
+    let mut segments = editable_segments(segments);
+    if segment >= segments.len() {
+        return segments;
+    }
+    let (first, end) = subpath(&segments, segment);
+    let closed = matches!(segments.get(end - 1), Some(Segment::Close));
+    let mut segment = segment;
+    if closed && segment == first {
+        // start the loop at the next node instead
+        let Some(&next) = segments.get(first + 1).filter(|s| !matches!(s, Segment::Close)) else { return segments };
+        let mut rotated = vec![Segment::MoveTo(end_of(next))];
+        rotated.extend_from_slice(&segments[first + 2..end - 1]);
+        rotated.push(next);
+        rotated.push(Segment::Close);
+        segments.splice(first..end, rotated);
+        segment = end - 3;
+    }
+    let next = segment + 1;
+    let next_draws = matches!(segments.get(next), Some(Segment::LineTo(_) | Segment::CubicTo(..)));
+    let drawing = (end - first) - usize::from(closed) - 1;
+    if drawing <= if closed { 2 } else { 1 } {
+        segments.drain(first..end);
+        return segments;
+    }
+    match (segments[segment], next_draws) {
+        // the start of an open subpath, the next node takes its place
+        (Segment::MoveTo(_), true) => {
+            let start = Segment::MoveTo(end_of(segments[next]));
+            segments.splice(segment..=next, [start]);
+        }
+        (Segment::MoveTo(_), false) => {}
+        // the last node of an open subpath
+        (_, false) => {
+            segments.remove(segment);
+        }
+        (a, true) => {
+            let b = segments[next];
+            let from = start_of(&segments, segment);
+            let to = end_of(b);
+            let joined = match (a, b) {
+                (Segment::LineTo(_), Segment::LineTo(_)) => Segment::LineTo(to),
+                _ => {
+                    let c1 = handle(&a, false).unwrap_or(from);
+                    let c2 = handle(&b, true).unwrap_or(to);
+                    Segment::CubicTo(c1, c2, to)
+                }
+            };
+            segments.splice(segment..=next, [joined]);
+        }
+    }
+    segments
+}
+
+/// `segments` with the node at `segment` turned from a smooth one into a corner without
+/// handles, or from a corner into a smooth one with handles along the line through its
+/// neighbors. Lines next to it become curves for that.
+pub fn toggle_smooth(segments: &[Segment], segment: usize) -> Vec<Segment> {
+    let mut segments = editable_segments(segments);
+    let Some(node) = path_nodes(&segments).into_iter().find(|n| n.segment == segment) else { return segments };
+    let links = links(&segments, segment);
+    let (Some(incoming), Some(outgoing)) = (links.incoming, links.outgoing) else { return segments };
+    if node.smooth {
+        set_handle(&mut segments[incoming], true, node.point);
+        set_handle(&mut segments[outgoing], false, node.point);
+        return segments;
+    }
+    for k in [incoming, outgoing] {
+        if let Segment::LineTo(p) = segments[k] {
+            let from = start_of(&segments, k);
+            segments[k] = Segment::CubicTo(lerp(from, p, 1.0 / 3.0), lerp(from, p, 2.0 / 3.0), p);
+        }
+    }
+    let previous = start_of(&segments, incoming);
+    let next = end_of(segments[outgoing]);
+    let d = [next[0] - previous[0], next[1] - previous[1]];
+    let length = distance(previous, next);
+    if length == 0.0 {
+        return segments;
+    }
+    let d = [d[0] / length, d[1] / length];
+    let (back, ahead) = (distance(previous, node.point) / 3.0, distance(node.point, next) / 3.0);
+    set_handle(&mut segments[incoming], true, [node.point[0] - d[0] * back, node.point[1] - d[1] * back]);
+    set_handle(&mut segments[outgoing], false, [node.point[0] + d[0] * ahead, node.point[1] + d[1] * ahead]);
+    segments
+}
+
+/// The segment of `segments` passing closest to `p`, with how far along it and the point there.
+pub fn nearest_segment(segments: &[Segment], p: [f32; 2]) -> Option<(usize, f32, [f32; 2])> {
+    const STEPS: usize = 32;
+    let segments = editable_segments(segments);
+    let mut best: Option<(f32, usize, f32, [f32; 2])> = None;
+    for (i, &segment) in segments.iter().enumerate() {
+        if !matches!(segment, Segment::LineTo(_) | Segment::CubicTo(..)) {
+            continue;
+        }
+        let from = start_of(&segments, i);
+        let at = |t: f32| match segment {
+            Segment::LineTo(q) => lerp(from, q, t),
+            Segment::CubicTo(c1, c2, q) => {
+                let (a, b, c) = (lerp(from, c1, t), lerp(c1, c2, t), lerp(c2, q, t));
+                lerp(lerp(a, b, t), lerp(b, c, t), t)
+            }
+            _ => from,
+        };
+        for k in 0..=STEPS {
+            let t = k as f32 / STEPS as f32;
+            let q = at(t);
+            let d = distance(p, q);
+            if best.is_none_or(|b| d < b.0) {
+                best = Some((d, i, t, q));
+            }
+        }
+        // narrow it down around the best sample
+        if let Some((_, j, t, _)) = best.filter(|b| b.1 == i) {
+            let (mut lo, mut hi) = ((t - 1.0 / STEPS as f32).max(0.0), (t + 1.0 / STEPS as f32).min(1.0));
+            for _ in 0..16 {
+                let (a, b) = (lo + (hi - lo) / 3.0, hi - (hi - lo) / 3.0);
+                if distance(p, at(a)) < distance(p, at(b)) {
+                    hi = b;
+                } else {
+                    lo = a;
+                }
+            }
+            let t = (lo + hi) * 0.5;
+            best = Some((distance(p, at(t)), j, t, at(t)));
+        }
+    }
+    best.map(|(_, i, t, q)| (i, t, q))
+}
+
+/// The segments the handles of the node at `i` belong to, and the last segment of a closed
+/// subpath whose end is the node too.
+struct Links {
+    incoming: Option<usize>,
+    outgoing: Option<usize>,
+    twin: Option<usize>,
+}
+
+fn links(segments: &[Segment], i: usize) -> Links {
+    let (first, end) = subpath(segments, i);
+    let closed = matches!(segments.get(end - 1), Some(Segment::Close));
+    let draws = |k: usize| matches!(segments.get(k), Some(Segment::LineTo(_) | Segment::CubicTo(..)));
+    let twin = (closed && i == first && end >= first + 3 && draws(end - 2)).then_some(end - 2);
+    let incoming = if i == first { twin } else { Some(i) };
+    let outgoing = (i + 1 < end && draws(i + 1)).then_some(i + 1);
+    Links { incoming, outgoing, twin }
+}
+
+/// Whether segment `i` ends a closed subpath where it started, the node at the MoveTo.
+fn closing(segments: &[Segment], i: usize) -> bool {
+    let (first, end) = subpath(segments, i);
+    i != first && i + 2 == end && matches!(segments[end - 1], Segment::Close) && Some(end_of(segments[i])) == segments.get(first).map(|&s| end_of(s))
+}
+
+/// The subpath segment `i` is in, from its MoveTo to the next one.
+fn subpath(segments: &[Segment], i: usize) -> (usize, usize) {
+    let first = segments[..=i.min(segments.len().saturating_sub(1))].iter().rposition(|s| matches!(s, Segment::MoveTo(_))).unwrap_or(0);
+    let end = segments[first + 1..].iter().position(|s| matches!(s, Segment::MoveTo(_))).map_or(segments.len(), |k| first + 1 + k);
+    (first, end)
+}
+
+/// Direction the path comes into the node at `i` from, None without one.
+fn direction_in(segments: &[Segment], i: usize, links: &Links) -> Option<[f32; 2]> {
+    let k = links.incoming?;
+    let point = end_of(segments[i]);
+    let from = match segments[k] {
+        Segment::CubicTo(_, c2, _) if c2 != point => c2,
+        Segment::CubicTo(c1, _, _) if c1 != point => c1,
+        _ => start_of(segments, k),
+    };
+    unit([point[0] - from[0], point[1] - from[1]])
+}
+
+fn direction_out(segments: &[Segment], i: usize, links: &Links) -> Option<[f32; 2]> {
+    let k = links.outgoing?;
+    let point = end_of(segments[i]);
+    let to = match segments[k] {
+        Segment::CubicTo(c1, _, _) if c1 != point => c1,
+        Segment::CubicTo(_, c2, _) if c2 != point => c2,
+        s => end_of(s),
+    };
+    unit([to[0] - point[0], to[1] - point[1]])
+}
+
+/// Where segment `i` starts, the end of the one before or the start of the subpath.
+fn start_of(segments: &[Segment], i: usize) -> [f32; 2] {
+    let (first, _) = subpath(segments, i);
+    if i == first {
+        end_of(segments[i])
+    } else {
+        end_of(segments[i - 1])
+    }
+}
+
+fn end_of(segment: Segment) -> [f32; 2] {
+    match segment {
+        Segment::MoveTo(p) | Segment::LineTo(p) | Segment::QuadTo(_, p) | Segment::CubicTo(_, _, p) => p,
+        Segment::Close => [0.0; 2],
+    }
+}
+
+fn set_end(segment: &mut Segment, to: [f32; 2]) {
+    match segment {
+        Segment::MoveTo(p) | Segment::LineTo(p) | Segment::QuadTo(_, p) | Segment::CubicTo(_, _, p) => *p = to,
+        Segment::Close => {}
+    }
+}
+
+/// The handle of a cubic at its end (`at_end`) or its start.
+fn handle(segment: &Segment, at_end: bool) -> Option<[f32; 2]> {
+    match *segment {
+        Segment::CubicTo(_, c2, _) if at_end => Some(c2),
+        Segment::CubicTo(c1, _, _) => Some(c1),
+        _ => None,
+    }
+}
+
+fn set_handle(segment: &mut Segment, at_end: bool, to: [f32; 2]) {
+    if let Segment::CubicTo(c1, c2, _) = segment {
+        *(if at_end { c2 } else { c1 }) = to;
+    }
+}
+
+fn unit(v: [f32; 2]) -> Option<[f32; 2]> {
+    let length = (v[0] * v[0] + v[1] * v[1]).sqrt();
+    (length > 0.0).then(|| [v[0] / length, v[1] / length])
+}
+
+fn lerp(a: [f32; 2], b: [f32; 2], t: f32) -> [f32; 2] {
+    [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t]
+}
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt()
+}
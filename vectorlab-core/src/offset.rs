@@ -0,0 +1,327 @@
+use std::collections::HashSet;
+
+use crate::boolean::{chain_pieces, dedup, split_edges};
+use crate::document::{transform_point, transform_scale, Contour, Document, Element, ElementId, ElementKind, Segment};
+use crate::edit::{AddElement, EditCommand, EditGroup, SetSegments, SetStyle};
+use crate::hit::{fill_contains, winding_number};
+use crate::loader::flatten_segments;
+use crate::style::{FillRule, LineCap, LineJoin, Style};
+
+/// How far a miter may reach out, in offset distances, before it is beveled.
+const MITER_LIMIT: f32 = 4.0;
+
+/// What `offset_paths` did.
+pub struct OffsetResult {
+    pub edit: Box<dyn EditCommand>,
+    /// the new outlines, in document coordinates
+    pub contours: Vec<Contour>,
+    /// paths that got an offset
+    pub paths: usize,
+}
+
+/// Offset the paths among `ids` by `distance` document units, outwards for positive and
+/// inwards for negative distances, e.g. to make up for the kerf of a laser. With `copies`
+/// above 1 the offset is repeated at twice, three times… the distance for as long as
+/// anything is left, which fills shapes with concentric outlines. The result replaces the
+/// path's outline, or goes into a new path above it with `keep_original`.
+pub fn offset_paths(doc: &Document, ids: &[ElementId], distance: f32, join: LineJoin, copies: u32, keep_original: bool) -> Option<OffsetResult> {
+    let wanted: HashSet<ElementId> = ids.iter().copied().collect();
+    let mut paths = vec![];
+    doc.walk(|id, element, ts, _| {
+        if element.as_path().is_some() && wanted.contains(&id) {
+            paths.push((id, ts));
+        }
+    });
+    let mut commands: Vec<Box<dyn EditCommand>> = vec![];
+    let mut all = vec![];
+    for (id, ts) in paths {
+        let Some(path) = doc.get(id).as_path() else { continue };
+        let scale = transform_scale(&ts).max(f32::EPSILON);
+        let tolerance = (path.tolerance * scale).min(distance.abs() * 0.1).max(distance.abs() * 1e-4);
+        let contours: Vec<Contour> = flatten_segments(&path.segments, tolerance / scale)
+            .into_iter()
+            .map(|c| Contour { points: c.points.iter().map(|&p| transform_point(&ts, p)).collect(), closed: c.closed })
+            .collect();
+        let mut offset = vec![];
+        for copy in 1..=copies.max(1) {
+            let ring = offset_contours(&contours, path.style.fill_rule, distance * copy as f32, join, tolerance);
+            if ring.is_empty() {
+                break;
+            }
+            offset.extend(ring);
+        }
+        if offset.is_empty() {
+            continue;
+        }
+        let to_local = ts.invert().unwrap_or_default();
+        let mut segments = vec![];
+        for contour in &offset {
+            segments.push(Segment::MoveTo(transform_point(&to_local, contour.points[0])));
+            segments.extend(contour.points[1..].iter().map(|&p| Segment::LineTo(transform_point(&to_local, p))));
+            segments.push(Segment::Close);
+        }
+        if keep_original {
+            let mut outline = path.clone();
+            outline.set_segments(segments);
+            let mut element = Element::new(ElementKind::Path(outline));
+            element.transform = doc.get(id).transform;
+            element.opacity = doc.get(id).opacity;
+            commands.push(Box::new(AddElement::after(doc, id, element)));
+        } else {
+            commands.push(Box::new(SetSegments::new(doc, id, segments)));
+        }
+        all.extend(offset);
+    }
+    if commands.is_empty() {
+        return None;
+    }
+    let paths = commands.len();
+    Some(OffsetResult { edit: Box::new(EditGroup::new("Offset", commands)), contours: all, paths })
+}
+
+/// What `outline_strokes` did.
+pub struct StrokeResult {
+    pub edit: Box<dyn EditCommand>,
+    /// strokes turned into fills
+    pub paths: usize,
+}
+
+/// Turn the strokes of the paths among `ids` into fills of their outline, see
+/// `stroke_outline`, for boolean operations, engraving thick lines and formats without
+/// strokes. A path with a fill as well keeps it and gets the outline as a new path above it.
+/// None if none of them has a stroke.
+pub fn outline_strokes(doc: &Document, ids: &[ElementId]) -> Option<StrokeResult> {
+    let wanted: HashSet<ElementId> = ids.iter().copied().collect();
+    let mut stroked = vec![];
+    doc.walk(|id, element, _, _| {
+        if element.as_path().is_some_and(|p| p.style.stroke.is_some() && p.style.stroke_width > 0.0) && wanted.contains(&id) {
+            stroked.push(id);
+        }
+    });
+    let mut commands: Vec<Box<dyn EditCommand>> = vec![];
+    for &id in &stroked {
+        let Some(path) = doc.get(id).as_path() else { continue };
+        let style = &path.style;
+        // in the path's own coordinates, where the stroke width is
+        let contours = flatten_segments(&path.segments, path.tolerance);
+        let outline = stroke_outline(&contours, style.stroke_width, style.line_cap, style.line_join, style.miter_limit, path.tolerance);
+        let mut segments = vec![];
+        for contour in &outline {
+            segments.push(Segment::MoveTo(contour.points[0]));
+            segments.extend(contour.points[1..].iter().map(|&p| Segment::LineTo(p)));
+            segments.push(Segment::Close);
+        }
+        let filled = Style { fill: style.stroke.clone(), fill_rule: FillRule::NonZero, stroke: None, ..style.clone() };
+        if style.fill.is_some() {
+            commands.push(Box::new(SetStyle { id, old: style.clone(), new: Style { stroke: None, ..style.clone() } }));
+            if !segments.is_empty() {
+                let mut stroke = path.clone();
+                stroke.style = filled;
+                stroke.set_segments(segments);
+                let mut element = Element::new(ElementKind::Path(stroke));
+                element.transform = doc.get(id).transform;
+                element.opacity = doc.get(id).opacity;
+                commands.push(Box::new(AddElement::after(doc, id, element)));
+            }
+        } else {
+            commands.push(Box::new(SetSegments::new(doc, id, segments)));
+            commands.push(Box::new(SetStyle { id, old: style.clone(), new: filled }));
+        }
+    }
+    if stroked.is_empty() {
+        return None;
+    }
+    Some(StrokeResult { edit: Box::new(EditGroup::new("Stroke to path", commands)), paths: stroked.len() })
+}
+
+/// The outline `distance` away from `contours`: closed ones bound the area they fill under
+/// `rule`, which grows for positive and shrinks for negative distances. Open ones are lines,
+/// they get an outline all round at the absolute distance. Corners on the outside are
+/// joined the way `join` says, round ones and line ends are kept within `tolerance`.
+///
+/// Like Clipper, every edge is moved out and the corners joined, then only the outline of
+/// what these loops wind around positively is kept, which cuts away where they turned
+/// inside out.
+pub fn offset_contours(contours: &[Contour], rule: FillRule, distance: f32, join: LineJoin, tolerance: f32) -> Vec<Contour> {
+    if distance == 0.0 {
+        return contours.iter().filter(|c| c.closed).cloned().collect();
+    }
+    let tolerance = tolerance.clamp(distance.abs() * 1e-4, distance.abs() * 0.5);
+    let cap = if join == LineJoin::Round { LineCap::Round } else { LineCap::Square };
+    let closed: Vec<Contour> = contours.iter().filter(|c| c.closed && c.points.len() > 2).cloned().collect();
+    let open: Vec<&Contour> = contours.iter().filter(|c| !c.closed && c.points.len() > 1).collect();
+
+    // the raw outlines, loops that still cross themselves and each other, all running with
+    // the area they grow or shrink on their left
+    let mut raw = vec![];
+    for contour in &closed {
+        let mut points = dedup(&contour.points, true);
+        if points.len() < 3 {
+            continue;
+        }
+        // which side the fill is on, seen walking along the first edge
+        let (a, b) = (points[0], points[1]);
+        let length = distance2(a, b).sqrt().max(f32::EPSILON);
+        let nudge = tolerance.min(length * 0.25);
+        let left = [(a[0] + b[0]) * 0.5 - (b[1] - a[1]) / length * nudge, (a[1] + b[1]) * 0.5 + (b[0] - a[0]) / length * nudge];
+        if !fill_contains(&closed, rule, left) {
+            points.reverse();
+        }
+        raw.push(raw_offset(&points, -distance, join, cap, MITER_LIMIT, tolerance));
+    }
+    if distance > 0.0 {
+        for contour in &open {
+            let points = dedup(&contour.points, false);
+            if points.len() < 2 {
+                continue;
+            }
+            raw.push(raw_offset(&there_and_back(&points), -distance, join, cap, MITER_LIMIT, tolerance));
+        }
+    }
+    positive_outline(&raw, tolerance)
+}
+
+/// The outline of the area a stroke `width` wide along `contours` covers, with the ends of
+/// open ones drawn as `cap` and the corners as `join`, beveled where a miter would reach out
+/// further than `miter_limit` times the width. Like `offset_contours` both sides of each
+/// contour are moved out by half the width, so overlapping parts of the stroke merge.
+/// Subpaths of a single point get a dot with round and square caps, as in SVG.
+pub fn stroke_outline(contours: &[Contour], width: f32, cap: LineCap, join: LineJoin, miter_limit: f32, tolerance: f32) -> Vec<Contour> {
+    let w = width * 0.5;
+    if w <= 0.0 {
+        return vec![];
+    }
+    let tolerance = tolerance.clamp(w * 1e-4, w * 0.5);
+    let mut raw = vec![];
+    for contour in contours {
+        let mut points = dedup(&contour.points, contour.closed);
+        match points.len() {
+            0 => {}
+            1 => {
+                // a point on its own, the caps of both ends make a dot
+                let [x, y] = points[0];
+                match cap {
+                    LineCap::Butt => {}
+                    LineCap::Square => raw.push(vec![[x - w, y - w], [x + w, y - w], [x + w, y + w], [x - w, y + w]]),
+                    LineCap::Round => {
+                        let step = 2.0 * (1.0 - tolerance * 0.5 / w).clamp(-1.0, 1.0).acos();
+                        let steps = (std::f32::consts::TAU / step.max(1e-3)).ceil().clamp(8.0, 1000.0) as usize;
+                        raw.push((0..steps).map(|k| (k as f32 / steps as f32 * std::f32::consts::TAU).sin_cos()).map(|(sin, cos)| [x + cos * w, y + sin * w]).collect());
+                    }
+                }
+            }
+            n if contour.closed && n > 2 => {
+                // the outside and the inside, each running with the stroke on its left
+                raw.push(raw_offset(&points, -w, join, cap, miter_limit, tolerance));
+                points.reverse();
+                raw.push(raw_offset(&points, -w, join, cap, miter_limit, tolerance));
+            }
+            _ => raw.push(raw_offset(&there_and_back(&points), -w, join, cap, miter_limit, tolerance)),
+        }
+    }
+    positive_outline(&raw, tolerance)
+}
+
+/// An open polyline as a closed loop going there and back, the turns at the ends become the
+/// caps.
+fn there_and_back(points: &[[f32; 2]]) -> Vec<[f32; 2]> {
+    let mut there_and_back = points.to_vec();
+    there_and_back.extend(points[1..points.len() - 1].iter().rev());
+    there_and_back
+}
+
+/// The outline of what the `raw` loops wind around positively, see `offset_contours`.
+fn positive_outline(raw: &[Vec<[f32; 2]>], tolerance: f32) -> Vec<Contour> {
+    // keep the pieces with the area the loops wind around positively on their left and
+    // nothing on their right, which is the union of what the loops enclose without the bits
+    // where they turned inside out
+    let loops: Vec<Contour> = raw.iter().map(|points| Contour { points: points.clone(), closed: true }).collect();
+    let covered = |p: [f32; 2]| winding_number(&loops, p) > 0;
+    let nudge = tolerance * 0.05;
+    let valid = |[a, b]: [[f32; 2]; 2]| {
+        let length = distance2(a, b).sqrt().max(f32::EPSILON);
+        let n = [-(b[1] - a[1]) / length * nudge, (b[0] - a[0]) / length * nudge];
+        let middle = [(a[0] + b[0]) * 0.5, (a[1] + b[1]) * 0.5];
+        covered([middle[0] + n[0], middle[1] + n[1]]) && !covered([middle[0] - n[0], middle[1] - n[1]])
+    };
+    let pieces: Vec<[[f32; 2]; 2]> = split_edges(raw).into_iter().filter(|&piece| valid(piece)).collect();
+    chain_pieces(&pieces, tolerance * tolerance)
+}
+
+/// The closed loop through `points` with every edge moved `w` to its left, and the corners
+/// joined. Corners on the inside get the original point in between, the little loops that
+/// makes are cut away later.
+fn raw_offset(points: &[[f32; 2]], w: f32, join: LineJoin, cap: LineCap, miter_limit: f32, tolerance: f32) -> Vec<[f32; 2]> {
+    let n = points.len();
+    let normal = |a: [f32; 2], b: [f32; 2]| {
+        let length = distance2(a, b).sqrt().max(f32::EPSILON);
+        [-(b[1] - a[1]) / length, (b[0] - a[0]) / length]
+    };
+    let mut out = vec![];
+    for i in 0..n {
+        let (prev, v, next) = (points[(i + n - 1) % n], points[i], points[(i + 1) % n]);
+        let (na, nb) = (normal(prev, v), normal(v, next));
+        let a = [v[0] + na[0] * w, v[1] + na[1] * w];
+        let b = [v[0] + nb[0] * w, v[1] + nb[1] * w];
+        let cross = na[0] * nb[1] - na[1] * nb[0];
+        let dot = na[0] * nb[0] + na[1] * nb[1];
+        if dot > 0.9999 {
+            // straight on
+            out.push(a);
+        } else if cross * w > 0.0 {
+            // turning towards the offset side, the two offset edges cross
+            out.extend([a, v, b]);
+        } else {
+            out.push(a);
+            join_corner(&mut out, v, na, nb, w, dot, join, cap, miter_limit, tolerance);
+            out.push(b);
+        }
+    }
+    out
+}
+
+/// Points between the ends of two offset edges meeting at the outside of a corner at `v`,
+/// or turning back at the end of a line.
+#[allow(clippy::too_many_arguments)]
+fn join_corner(out: &mut Vec<[f32; 2]>, v: [f32; 2], na: [f32; 2], nb: [f32; 2], w: f32, dot: f32, join: LineJoin, cap: LineCap, miter_limit: f32, tolerance: f32) {
+    let r = w.abs();
+    let end = dot < -0.9999;
+    match (end, join, cap) {
+        (true, _, LineCap::Butt) => {}
+        (true, _, LineCap::Square) => {
+            let along = [na[1] * r, -na[0] * r];
+            out.push([v[0] + na[0] * w + along[0], v[1] + na[1] * w + along[1]]);
+            out.push([v[0] + nb[0] * w + along[0], v[1] + nb[1] * w + along[1]]);
+        }
+        (true, _, LineCap::Round) | (false, LineJoin::Round, _) => {
+            let start = (na[1] * w.signum()).atan2(na[0] * w.signum());
+            let mut sweep = (nb[1] * w.signum()).atan2(nb[0] * w.signum()) - start;
+            if end {
+                // half a circle on the side the normals point away from
+                sweep = std::f32::consts::PI * -w.signum();
+            } else if sweep > std::f32::consts::PI {
+                // the short way round is the outside of the corner
+                sweep -= std::f32::consts::TAU;
+            } else if sweep < -std::f32::consts::PI {
+                sweep += std::f32::consts::TAU;
+            }
+            // chords no more than half the tolerance inside the arc
+            let step = 2.0 * (1.0 - tolerance * 0.5 / r).clamp(-1.0, 1.0).acos();
+            let steps = (sweep.abs() / step.max(1e-3)).ceil().clamp(1.0, 1000.0) as usize;
+            for k in 1..steps {
+                let angle = start + sweep * k as f32 / steps as f32;
+                out.push([v[0] + angle.cos() * r, v[1] + angle.sin() * r]);
+            }
+        }
+        (false, LineJoin::Miter, _) if 2.0 / (1.0 + dot) <= miter_limit * miter_limit => {
+            let k = w / (1.0 + dot);
+            out.push([v[0] + (na[0] + nb[0]) * k, v[1] + (na[1] + nb[1]) * k]);
+        }
+        // bevel, the two ends joined straight
+        _ => {}
+    }
+}
+
+fn distance2(a: [f32; 2], b: [f32; 2]) -> f32 {
+    (b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)
+}
@@ -0,0 +1,295 @@
+use std::collections::{HashMap, HashSet};
+
+use resvg::usvg::Transform;
+
+use crate::document::{bbox_of, transform_point, transform_scale, Contour, Document, ElementId, Segment};
+use crate::edit::{DeleteElements, EditCommand, EditGroup, SetSegments};
+use crate::hit::fill_contains;
+use crate::loader::flatten_segments;
+use crate::style::{FillRule, Paint};
+
+/// What `remove_overlaps` did.
+pub struct OverlapResult {
+    pub edit: Box<dyn EditCommand>,
+    /// the pieces of lines taken out, in document coordinates
+    pub removed: Vec<[[f32; 2]; 2]>,
+    /// paths that were cut shorter, and those that are gone altogether
+    pub changed: usize,
+    pub deleted: usize,
+}
+
+/// An opaque fill above the path being looked at, in document coordinates.
+struct Occluder {
+    contours: Vec<Contour>,
+    rule: FillRule,
+    bbox: [f32; 4],
+}
+
+/// Lines already kept, bucketed by grid cell so only nearby ones are compared.
+struct Drawn {
+    cell: f32,
+    lines: Vec<[[f32; 2]; 2]>,
+    grid: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl Drawn {
+    fn cells(&self, bbox: [f32; 4]) -> impl Iterator<Item = (i64, i64)> {
+        let c = |v: f32| (v / self.cell).floor() as i64;
+        let (x0, y0, x1, y1) = (c(bbox[0]), c(bbox[1]), c(bbox[2]), c(bbox[3]));
+        (x0..=x1).flat_map(move |x| (y0..=y1).map(move |y| (x, y)))
+    }
+
+    fn add(&mut self, line: [[f32; 2]; 2]) {
+        let index = self.lines.len();
+        self.lines.push(line);
+        for cell in self.cells(line_bbox(line, 0.0)).collect::<Vec<_>>() {
+            self.grid.entry(cell).or_default().push(index);
+        }
+    }
+
+    /// Kept lines whose bounding boxes come within `tolerance` of `line`.
+    fn near(&self, line: [[f32; 2]; 2], tolerance: f32) -> Vec<[[f32; 2]; 2]> {
+        let mut indices: Vec<usize> = self.cells(line_bbox(line, tolerance)).flat_map(|c| self.grid.get(&c).into_iter().flatten().copied()).collect();
+        indices.sort_unstable();
+        indices.dedup();
+        indices.into_iter().map(|i| self.lines[i]).collect()
+    }
+}
+
+/// Take out the parts of the stroked paths among `ids` that retrace a line already drawn,
+/// within `tolerance` document units, so a plotter draws every line once. With `hidden`, the
+/// parts under opaque fills painted above them go as well.
+///
+/// Paths are looked at from the top down, so of several copies the topmost stays. Paths with
+/// a fill keep their outline, cutting it would change the fill, but still count as drawn
+/// and hide what is below. Cut paths become polylines, paths with nothing left are deleted.
+/// None if nothing overlaps.
+pub fn remove_overlaps(doc: &Document, ids: &[ElementId], tolerance: f32, hidden: bool) -> Option<OverlapResult> {
+    let wanted: HashSet<ElementId> = ids.iter().copied().collect();
+    let mut paths = vec![];
+    doc.walk(|id, element, ts, opacity| {
+        if element.as_path().is_some() && wanted.contains(&id) {
+            paths.push((id, ts, opacity));
+        }
+    });
+    let tolerance = tolerance.max(f32::EPSILON);
+    let extent = doc.bbox().map_or(1.0, |b| (b[2] - b[0]).max(b[3] - b[1]));
+    // small cells for fast lookups, but not so small that long lines fill thousands
+    let mut drawn = Drawn { cell: (extent / 256.0).max(tolerance * 4.0), lines: vec![], grid: HashMap::new() };
+    let mut occluders: Vec<Occluder> = vec![];
+    let mut removed = vec![];
+    let mut commands: Vec<Box<dyn EditCommand>> = vec![];
+    let mut deleted = vec![];
+
+    for &(id, ts, opacity) in paths.iter().rev() {
+        let Some(path) = doc.get(id).as_path() else { continue };
+        let style = &path.style;
+        let filled = style.fill.is_some();
+        if !filled && style.stroke.is_none() {
+            continue;
+        }
+        let local = tolerance / transform_scale(&ts).max(f32::EPSILON);
+        let contours: Vec<Contour> = flatten_segments(&path.segments, local * 0.25)
+            .into_iter()
+            .map(|c| Contour { points: c.points.iter().map(|&p| transform_point(&ts, p)).collect(), closed: c.closed })
+            .collect();
+
+        if filled {
+            // the outline stays as it is
+            for contour in &contours {
+                let n = contour.points.len();
+                let edges = if contour.closed { n } else { n.saturating_sub(1) };
+                for i in 0..edges {
+                    drawn.add([contour.points[i], contour.points[(i + 1) % n]]);
+                }
+            }
+            let opaque = match &style.fill {
+                Some(Paint::Solid(color)) => color.a == 255,
+                _ => true,
+            };
+            if opaque && opacity >= 1.0 {
+                if let Some(bbox) = bbox_of(contours.iter().flat_map(|c| &c.points)) {
+                    occluders.push(Occluder { contours, rule: style.fill_rule, bbox });
+                }
+            }
+            continue;
+        }
+
+        // kept parts of each contour, as runs of points
+        let mut runs: Vec<Vec<[f32; 2]>> = vec![];
+        let mut cut = false;
+        for contour in &contours {
+            let n = contour.points.len();
+            let edges = if contour.closed { n } else { n.saturating_sub(1) };
+            let mut run: Vec<[f32; 2]> = vec![];
+            for i in 0..edges {
+                let line = [contour.points[i], contour.points[(i + 1) % n]];
+                let mut gone = overlapping(line, &drawn.near(line, tolerance), tolerance);
+                if hidden {
+                    gone.extend(occluded(line, &occluders));
+                }
+                let kept = complement(gone, line_length(line), tolerance);
+                if kept != [(0.0, 1.0)] {
+                    cut = true;
+                }
+                let mut at = 0.0;
+                for &(t0, t1) in &kept {
+                    if t0 > at {
+                        removed.push([lerp(line, at), lerp(line, t0)]);
+                    }
+                    let (a, b) = (lerp(line, t0), lerp(line, t1));
+                    if run.last() != Some(&a) {
+                        runs.extend((run.len() > 1).then(|| std::mem::take(&mut run)));
+                        run = vec![a];
+                    }
+                    run.push(b);
+                    // drawn from now on, also for the rest of this path
+                    drawn.add([a, b]);
+                    at = t1;
+                }
+                if at < 1.0 {
+                    removed.push([lerp(line, at), line[1]]);
+                }
+            }
+            runs.extend((run.len() > 1).then_some(run));
+        }
+
+        if !cut {
+            continue;
+        }
+        if runs.is_empty() {
+            deleted.push(id);
+            continue;
+        }
+        let to_local = ts.invert().unwrap_or_default();
+        commands.push(Box::new(SetSegments::new(doc, id, polylines(&runs, &to_local))));
+    }
+
+    let changed = commands.len();
+    if changed == 0 && deleted.is_empty() {
+        return None;
+    }
+    let deleted_count = deleted.len();
+    commands.push(Box::new(DeleteElements::new(deleted)));
+    Some(OverlapResult { edit: Box::new(EditGroup::new("Remove overlaps", commands)), removed, changed, deleted: deleted_count })
+}
+
+/// Runs of document points as path segments in the coordinates `to_local` maps to.
+pub(crate) fn polylines(runs: &[Vec<[f32; 2]>], to_local: &Transform) -> Vec<Segment> {
+    let mut segments = vec![];
+    for run in runs {
+        let closed = run.len() > 2 && run.first() == run.last();
+        let points = if closed { &run[..run.len() - 1] } else { &run[..] };
+        segments.push(Segment::MoveTo(transform_point(to_local, points[0])));
+        segments.extend(points[1..].iter().map(|&p| Segment::LineTo(transform_point(to_local, p))));
+        if closed {
+            segments.push(Segment::Close);
+        }
+    }
+    segments
+}
+
+/// Parts of `line`, as ranges of 0..1 along it, that retrace one of `drawn`.
+fn overlapping(line: [[f32; 2]; 2], drawn: &[[[f32; 2]; 2]], tolerance: f32) -> Vec<(f32, f32)> {
+    let [p, q] = line;
+    let length = line_length(line);
+    if length <= f32::EPSILON {
+        return vec![];
+    }
+    let u = [(q[0] - p[0]) / length, (q[1] - p[1]) / length];
+    let along = |a: [f32; 2]| ((a[0] - p[0]) * u[0] + (a[1] - p[1]) * u[1]) / length;
+    let across = |a: [f32; 2]| ((a[1] - p[1]) * u[0] - (a[0] - p[0]) * u[1]).abs();
+    drawn
+        .iter()
+        // both ends on the line, so the drawn one runs along it
+        .filter(|&&[a, b]| across(a) <= tolerance && across(b) <= tolerance)
+        .map(|&[a, b]| {
+            let (ta, tb) = (along(a), along(b));
+            (ta.min(tb).max(0.0), ta.max(tb).min(1.0))
+        })
+        .filter(|(t0, t1)| t1 > t0)
+        .collect()
+}
+
+/// Parts of `line` inside one of the `occluders`.
+fn occluded(line: [[f32; 2]; 2], occluders: &[Occluder]) -> Vec<(f32, f32)> {
+    let [p, q] = line;
+    let bbox = line_bbox(line, 0.0);
+    let mut hidden = vec![];
+    for occluder in occluders {
+        let b = occluder.bbox;
+        if bbox[2] < b[0] || bbox[0] > b[2] || bbox[3] < b[1] || bbox[1] > b[3] {
+            continue;
+        }
+        // where the line crosses the outline, in between it is either inside or outside
+        let mut ts = vec![0.0, 1.0];
+        for contour in &occluder.contours {
+            let n = contour.points.len();
+            for i in 0..n {
+                if let Some(t) = intersect(p, q, contour.points[i], contour.points[(i + 1) % n]) {
+                    ts.push(t);
+                }
+            }
+        }
+        ts.sort_by(f32::total_cmp);
+        for pair in ts.windows(2) {
+            let middle = lerp(line, (pair[0] + pair[1]) * 0.5);
+            if pair[1] > pair[0] && fill_contains(&occluder.contours, occluder.rule, middle) {
+                hidden.push((pair[0], pair[1]));
+            }
+        }
+    }
+    hidden
+}
+
+/// Where along p..q it crosses a..b, if it does.
+fn intersect(p: [f32; 2], q: [f32; 2], a: [f32; 2], b: [f32; 2]) -> Option<f32> {
+    let (r, s) = ([q[0] - p[0], q[1] - p[1]], [b[0] - a[0], b[1] - a[1]]);
+    let denominator = r[0] * s[1] - r[1] * s[0];
+    if denominator.abs() <= f32::EPSILON {
+        return None;
+    }
+    let (dx, dy) = (a[0] - p[0], a[1] - p[1]);
+    let t = (dx * s[1] - dy * s[0]) / denominator;
+    let u = (dx * r[1] - dy * r[0]) / denominator;
+    ((0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u)).then_some(t)
+}
+
+/// What is left of 0..1 without the `gone` ranges, leaving out bits shorter than `tolerance`
+/// of a line `length` long.
+fn complement(mut gone: Vec<(f32, f32)>, length: f32, tolerance: f32) -> Vec<(f32, f32)> {
+    gone.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let shortest = tolerance / length.max(f32::EPSILON);
+    let mut kept = vec![];
+    let mut at = 0.0;
+    for (t0, t1) in gone {
+        if t0 - at > shortest {
+            kept.push((at, t0));
+        }
+        at = f32::max(at, t1);
+    }
+    if 1.0 - at > shortest || (at == 0.0 && kept.is_empty()) {
+        kept.push((at, 1.0));
+    }
+    kept
+}
+
+/// The point `t` of the way along `line`, exactly its ends for 0 and 1.
+fn lerp(line: [[f32; 2]; 2], t: f32) -> [f32; 2] {
+    let [p, q] = line;
+    match t {
+        0.0 => p,
+        1.0 => q,
+        _ => [p[0] + (q[0] - p[0]) * t, p[1] + (q[1] - p[1]) * t],
+    }
+}
+
+fn line_length(line: [[f32; 2]; 2]) -> f32 {
+    let [p, q] = line;
+    ((q[0] - p[0]).powi(2) + (q[1] - p[1]).powi(2)).sqrt()
+}
+
+fn line_bbox(line: [[f32; 2]; 2], margin: f32) -> [f32; 4] {
+    let [p, q] = line;
+    [p[0].min(q[0]) - margin, p[1].min(q[1]) - margin, p[0].max(q[0]) + margin, p[1].max(q[1]) + margin]
+}
@@ -0,0 +1,134 @@
+use crate::document::{Document, Element, ElementId, ElementKind};
+use crate::edit::{EditCommand, EditGroup, SetStyle};
+use crate::extract::layer_svgs;
+use crate::saver::SaveOptions;
+use crate::style::{Color, Paint};
+
+/// A solid color the paths use, see [`Document::palette`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct PaletteColor {
+    pub color: Color,
+    /// paths filled with it, in paint order
+    pub fills: Vec<ElementId>,
+    /// paths stroked with it, in paint order
+    pub strokes: Vec<ElementId>,
+}
+
+impl PaletteColor {
+    /// Paths using it for the fill or the stroke, each once.
+    pub fn ids(&self) -> Vec<ElementId> {
+        let mut ids = self.fills.clone();
+        ids.extend(self.strokes.iter().filter(|id| !self.fills.contains(id)));
+        ids
+    }
+
+    pub fn count(&self) -> usize {
+        self.ids().len()
+    }
+}
+
+impl Document {
+    /// The distinct solid fill and stroke colors of the paths, the most used first. Colors
+    /// differing only in alpha count as different. Gradients are left out.
+    pub fn palette(&self) -> Vec<PaletteColor> {
+        let mut colors: Vec<PaletteColor> = vec![];
+        fn entry(colors: &mut Vec<PaletteColor>, color: Color) -> &mut PaletteColor {
+            let i = colors.iter().position(|c| c.color == color).unwrap_or_else(|| {
+                colors.push(PaletteColor { color, fills: vec![], strokes: vec![] });
+                colors.len() - 1
+            });
+            &mut colors[i]
+        }
+        for (id, path) in self.paths() {
+            if let Some(Paint::Solid(color)) = path.style.fill {
+                entry(&mut colors, color).fills.push(id);
+            }
+            if let Some(Paint::Solid(color)) = path.style.stroke {
+                entry(&mut colors, color).strokes.push(id);
+            }
+        }
+        // stable, equally used colors stay in paint order
+        colors.sort_by_key(|c| std::cmp::Reverse(c.count()));
+        colors
+    }
+
+    /// Regroup everything into a layer per color below the root, for plotting or cutting each
+    /// with its own pen or pass. A path goes by its stroke, or its fill if it has no stroke,
+    /// texts by their first glyph, alpha ignored. Images and paths without a solid color go
+    /// into a layer "other". The groups are dissolved as by [`Document::flatten`] first. The
+    /// layers come in the order their colors are first painted and get ids like `color-ff0000`.
+    /// See `SplitByColor` for the undoable edit.
+    pub fn split_by_color(&mut self) {
+        self.flatten();
+        let root = self.root;
+        let mut layers: Vec<(Option<Color>, ElementId)> = vec![];
+        for id in self.get(root).children.clone() {
+            let color = self.pen_color(id);
+            let layer = match layers.iter().find(|&&(c, _)| c == color) {
+                Some(&(_, layer)) => layer,
+                None => {
+                    let mut group = Element::new(ElementKind::Group);
+                    group.source_id = layer_name(color);
+                    let layer = self.add(root, group);
+                    layers.push((color, layer));
+                    layer
+                }
+            };
+            self.detach(id);
+            self.attach(id, layer, usize::MAX);
+        }
+        self.touch();
+    }
+
+    /// Opaque color of the first path at or below `id` that has a solid one.
+    fn pen_color(&self, id: ElementId) -> Option<Color> {
+        self.descendants(id).into_iter().find_map(|id| {
+            let style = &self.get(id).as_path()?.style;
+            match (&style.stroke, &style.fill) {
+                (Some(Paint::Solid(c)), _) | (None, Some(Paint::Solid(c))) => Some(Color { a: 255, ..*c }),
+                _ => None,
+            }
+        })
+    }
+}
+
+fn layer_name(color: Option<Color>) -> String {
+    match color {
+        Some(c) => format!("color-{:02x}{:02x}{:02x}", c.r, c.g, c.b),
+        None => "other".to_string(),
+    }
+}
+
+/// An SVG per color as [`Document::split_by_color`] sorts them, see [`layer_svgs`].
+pub fn color_layer_svgs(doc: &Document, opts: SaveOptions) -> Vec<(String, String)> {
+    let mut split = doc.clone();
+    split.split_by_color();
+    layer_svgs(&split, opts)
+}
+
+/// Recolor the fills and strokes of the paths among `ids` that are `from` with `to`. None if
+/// none of them uses it.
+pub fn replace_color(doc: &Document, ids: &[ElementId], from: Color, to: Color) -> Option<Box<dyn EditCommand>> {
+    let replace = |paint: &mut Option<Paint>| match paint {
+        Some(Paint::Solid(color)) if *color == from => {
+            *color = to;
+            true
+        }
+        _ => false,
+    };
+    let mut commands: Vec<Box<dyn EditCommand>> = vec![];
+    for &id in ids {
+        let Some(path) = doc.get(id).as_path() else { continue };
+        let mut style = path.style.clone();
+        // both, not just the first
+        let fill = replace(&mut style.fill);
+        let stroke = replace(&mut style.stroke);
+        if fill || stroke {
+            commands.push(Box::new(SetStyle { id, old: path.style.clone(), new: style }));
+        }
+    }
+    if commands.is_empty() {
+        return None;
+    }
+    Some(Box::new(EditGroup::new("Replace color", commands)))
+}
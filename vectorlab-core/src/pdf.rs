@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::io::Write as _;
+use std::path::Path;
+use std::sync::Arc;
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use resvg::usvg::Transform;
+
+use crate::document::{Document, ElementKind, FlattenedPath, Segment};
+use crate::image::{PlacedImage, RasterImage};
+use crate::style::{FillRule, Gradient, GradientShape, LineCap, LineJoin, Paint};
+
+/// PDF points per CSS pixel, 72 / 96.
+const PT_PER_PX: f32 = 0.75;
+
+/// Write `docs` as one PDF, a page for each at its own size.
+pub fn save_pdf(docs: &[Document], path: &Path) -> std::io::Result<()> {
+    fs::write(path, to_pdf(docs))
+}
+
+/// Paths keep their curves and gradients become PDF shadings. What PDF can't say as
+/// simply is approximated: group opacity is applied to each element on its own, gradient
+/// stops are opaque and spread like `pad`, clip paths and masks are left out.
+pub fn to_pdf(docs: &[Document]) -> Vec<u8> {
+    let mut pdf = PdfWriter::default();
+    let pages_id = pdf.reserve();
+    let mut kids = vec![];
+    for doc in docs {
+        kids.push(pdf.page(doc, pages_id));
+    }
+    let kids: Vec<String> = kids.iter().map(|id| format!("{id} 0 R")).collect();
+    pdf.set(pages_id, format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), docs.len()).into_bytes());
+    let catalog = pdf.add(format!("<< /Type /Catalog /Pages {pages_id} 0 R >>").into_bytes());
+    pdf.finish(catalog)
+}
+
+/// Numbers without exponents or trailing zeros.
+struct N(f32);
+
+impl fmt::Display for N {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = format!("{:.4}", if self.0.is_finite() { self.0 } else { 0.0 });
+        let s = s.trim_end_matches('0').trim_end_matches('.');
+        f.write_str(if s == "-0" { "0" } else { s })
+    }
+}
+
+fn matrix(t: Transform) -> String {
+    format!("{} {} {} {} {} {}", N(t.sx), N(t.ky), N(t.kx), N(t.sy), N(t.tx), N(t.ty))
+}
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).expect("writing to memory");
+    encoder.finish().expect("writing to memory")
+}
+
+/// Objects are numbered from 1 in the order they are added.
+#[derive(Default)]
+struct PdfWriter {
+    objects: Vec<Vec<u8>>,
+}
+
+impl PdfWriter {
+    fn add(&mut self, body: Vec<u8>) -> usize {
+        self.objects.push(body);
+        self.objects.len()
+    }
+
+    /// A number for an object that is only written later, e.g. a parent.
+    fn reserve(&mut self) -> usize {
+        self.add(vec![])
+    }
+
+    fn set(&mut self, id: usize, body: Vec<u8>) {
+        self.objects[id - 1] = body;
+    }
+
+    fn stream(&mut self, dict: &str, data: &[u8]) -> usize {
+        let data = deflate(data);
+        let dict = format!("{dict} /Filter /FlateDecode /Length {}", data.len());
+        let mut body = format!("<< {} >>\nstream\n", dict.trim_start()).into_bytes();
+        body.extend_from_slice(&data);
+        body.extend_from_slice(b"\nendstream");
+        self.add(body)
+    }
+
+    fn page(&mut self, doc: &Document, parent: usize) -> usize {
+        let [w, h] = doc.size.map(|v| v.max(1.0) * PT_PER_PX);
+        // y grows downwards in SVG and upwards in PDF
+        let base = Transform::from_row(PT_PER_PX, 0.0, 0.0, -PT_PER_PX, 0.0, h).pre_concat(doc.viewport_transform());
+        let mut page = PageWriter { pdf: self, base, content: String::new(), resources: Resources::default(), images: HashMap::new() };
+        let _ = writeln!(page.content, "{} cm", matrix(base));
+        doc.walk(|_, element, ts, opacity| match &element.kind {
+            ElementKind::Path(path) => page.path(path, ts, opacity),
+            ElementKind::Image { image: Some(image), .. } => page.image(image, ts, opacity),
+            _ => {}
+        });
+        let PageWriter { content, resources, .. } = page;
+        let content = self.stream("", content.as_bytes());
+        let body = format!(
+            "<< /Type /Page /Parent {parent} 0 R /MediaBox [0 0 {} {}] /Resources {} /Contents {content} 0 R >>",
+            N(w),
+            N(h),
+            resources.dict()
+        );
+        self.add(body.into_bytes())
+    }
+
+    fn image(&mut self, pixels: &RasterImage) -> usize {
+        let rgba = pixels.unpremultiplied();
+        let rgb: Vec<u8> = rgba.chunks_exact(4).flat_map(|p| [p[0], p[1], p[2]]).collect();
+        let alpha: Vec<u8> = rgba.chunks_exact(4).map(|p| p[3]).collect();
+        let size = format!("/Type /XObject /Subtype /Image /Width {} /Height {} /BitsPerComponent 8", pixels.width, pixels.height);
+        let smask = self.stream(&format!("{size} /ColorSpace /DeviceGray"), &alpha);
+        self.stream(&format!("{size} /ColorSpace /DeviceRGB /SMask {smask} 0 R"), &rgb)
+    }
+
+    /// An axial or radial shading for `gradient`, `ts` maps its coordinates to the page's.
+    fn pattern(&mut self, gradient: &Gradient, ts: Transform) -> usize {
+        let rgb = |c: [f32; 4]| format!("[{} {} {}]", N(c[0]), N(c[1]), N(c[2]));
+        // stops that don't start at 0 or end at 1 are padded with their colors
+        let mut stops = gradient.stops.clone();
+        match (stops.first().copied(), stops.last().copied()) {
+            (Some(first), Some(last)) => {
+                if first.0 > 0.0 {
+                    stops.insert(0, (0.0, first.1));
+                }
+                if last.0 < 1.0 {
+                    stops.push((1.0, last.1));
+                }
+            }
+            _ => stops = vec![(0.0, [0.0; 4]), (1.0, [0.0; 4])],
+        }
+        let parts: Vec<String> = stops.windows(2).map(|s| format!("<< /FunctionType 2 /Domain [0 1] /C0 {} /C1 {} /N 1 >>", rgb(s[0].1), rgb(s[1].1))).collect();
+        let bounds: Vec<String> = stops[1..stops.len() - 1].iter().map(|s| N(s.0.clamp(0.0, 1.0)).to_string()).collect();
+        let encode = vec!["0 1"; parts.len()].join(" ");
+        let function = format!("<< /FunctionType 3 /Domain [0 1] /Functions [{}] /Bounds [{}] /Encode [{encode}] >>", parts.join(" "), bounds.join(" "));
+        let shading = match gradient.shape {
+            GradientShape::Linear { x1, y1, x2, y2 } => format!("/ShadingType 2 /Coords [{} {} {} {}]", N(x1), N(y1), N(x2), N(y2)),
+            GradientShape::Radial { cx, cy, r, fx, fy } => format!("/ShadingType 3 /Coords [{} {} 0 {} {} {}]", N(fx), N(fy), N(cx), N(cy), N(r)),
+        };
+        let body = format!(
+            "<< /PatternType 2 /Matrix [{}] /Shading << {shading} /ColorSpace /DeviceRGB /Function {function} /Extend [true true] >> >>",
+            matrix(ts)
+        );
+        self.add(body.into_bytes())
+    }
+
+    fn finish(self, root: usize) -> Vec<u8> {
+        let mut out = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = vec![];
+        for (i, body) in self.objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            out.extend_from_slice(body);
+            out.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = out.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", self.objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(table, "{offset:010} 00000 n ");
+        }
+        let _ = write!(table, "trailer\n<< /Size {} /Root {root} 0 R >>\nstartxref\n{xref}\n%%EOF\n", self.objects.len() + 1);
+        out.extend_from_slice(table.as_bytes());
+        out
+    }
+}
+
+/// Named resources of one page.
+#[derive(Default)]
+struct Resources {
+    /// fill and stroke alpha
+    gstates: Vec<(u8, u8)>,
+    patterns: Vec<usize>,
+    images: Vec<usize>,
+}
+
+impl Resources {
+    fn gstate(&mut self, fill: u8, stroke: u8) -> String {
+        let i = self.gstates.iter().position(|&g| g == (fill, stroke)).unwrap_or_else(|| {
+            self.gstates.push((fill, stroke));
+            self.gstates.len() - 1
+        });
+        format!("/G{i}")
+    }
+
+    fn dict(&self) -> String {
+        let mut dict = String::from("<<");
+        if !self.gstates.is_empty() {
+            dict.push_str(" /ExtGState <<");
+            for (i, (fill, stroke)) in self.gstates.iter().enumerate() {
+                let _ = write!(dict, " /G{i} << /ca {} /CA {} >>", N(*fill as f32 / 255.0), N(*stroke as f32 / 255.0));
+            }
+            dict.push_str(" >>");
+        }
+        if !self.patterns.is_empty() {
+            dict.push_str(" /Pattern <<");
+            for (i, id) in self.patterns.iter().enumerate() {
+                let _ = write!(dict, " /P{i} {id} 0 R");
+            }
+            dict.push_str(" >>");
+        }
+        if !self.images.is_empty() {
+            dict.push_str(" /XObject <<");
+            for (i, id) in self.images.iter().enumerate() {
+                let _ = write!(dict, " /I{i} {id} 0 R");
+            }
+            dict.push_str(" >>");
+        }
+        dict.push_str(" >>");
+        dict
+    }
+}
+
+struct PageWriter<'a> {
+    pdf: &'a mut PdfWriter,
+    /// document to page coordinates, patterns are placed in the latter
+    base: Transform,
+    content: String,
+    resources: Resources,
+    /// resource index of each image already on the page
+    images: HashMap<*const RasterImage, usize>,
+}
+
+impl PageWriter<'_> {
+    /// Operators selecting `paint`, `stroke` for the stroking color, and its alpha.
+    fn paint(&mut self, paint: &Paint, ts: Transform, stroke: bool) -> (String, u8) {
+        match paint {
+            Paint::Solid(c) => {
+                let op = if stroke { "RG" } else { "rg" };
+                (format!("{} {} {} {op}", N(c.r as f32 / 255.0), N(c.g as f32 / 255.0), N(c.b as f32 / 255.0)), c.a)
+            }
+            Paint::Gradient(g) => {
+                let to_page = self.base.pre_concat(ts).pre_concat(g.to_gradient.invert().unwrap_or_default());
+                let id = self.pdf.pattern(g, to_page);
+                self.resources.patterns.push(id);
+                let name = self.resources.patterns.len() - 1;
+                let (cs, scn) = if stroke { ("CS", "SCN") } else { ("cs", "scn") };
+                (format!("/Pattern {cs} /P{name} {scn}"), 255)
+            }
+        }
+    }
+
+    fn path(&mut self, path: &FlattenedPath, ts: Transform, opacity: f32) {
+        let style = &path.style;
+        if path.segments.is_empty() || (style.fill.is_none() && style.stroke.is_none()) {
+            return;
+        }
+        let mut ops = String::new();
+        let (mut fill_alpha, mut stroke_alpha) = (255, 255);
+        if let Some(paint) = &style.fill {
+            let (op, a) = self.paint(paint, ts, false);
+            let _ = writeln!(ops, "{op}");
+            fill_alpha = a;
+        }
+        if let Some(paint) = &style.stroke {
+            let (op, a) = self.paint(paint, ts, true);
+            let cap = match style.line_cap {
+                LineCap::Butt => 0,
+                LineCap::Round => 1,
+                LineCap::Square => 2,
+            };
+            let join = match style.line_join {
+                LineJoin::Miter => 0,
+                LineJoin::Round => 1,
+                LineJoin::Bevel => 2,
+            };
+            let _ = writeln!(ops, "{op} {} w {cap} J {join} j {} M", N(style.stroke_width), N(style.miter_limit.max(1.0)));
+            stroke_alpha = a;
+        }
+        let alpha = |a: u8| (a as f32 * opacity).round() as u8;
+        let (fill_alpha, stroke_alpha) = (alpha(fill_alpha), alpha(stroke_alpha));
+
+        let _ = writeln!(self.content, "q {} cm", matrix(ts));
+        if (fill_alpha, stroke_alpha) != (255, 255) {
+            let _ = writeln!(self.content, "{} gs", self.resources.gstate(fill_alpha, stroke_alpha));
+        }
+        self.content.push_str(&ops);
+        let (mut start, mut current) = ([0.0; 2], [0.0; 2]);
+        for segment in &path.segments {
+            let _ = match *segment {
+                Segment::MoveTo(p) => write!(self.content, "{} {} m ", N(p[0]), N(p[1])),
+                Segment::LineTo(p) => write!(self.content, "{} {} l ", N(p[0]), N(p[1])),
+                Segment::QuadTo(c, p) => {
+                    // PDF only has cubics
+                    let c1 = [current[0] + 2.0 / 3.0 * (c[0] - current[0]), current[1] + 2.0 / 3.0 * (c[1] - current[1])];
+                    let c2 = [p[0] + 2.0 / 3.0 * (c[0] - p[0]), p[1] + 2.0 / 3.0 * (c[1] - p[1])];
+                    write!(self.content, "{} {} {} {} {} {} c ", N(c1[0]), N(c1[1]), N(c2[0]), N(c2[1]), N(p[0]), N(p[1]))
+                }
+                Segment::CubicTo(c1, c2, p) => write!(self.content, "{} {} {} {} {} {} c ", N(c1[0]), N(c1[1]), N(c2[0]), N(c2[1]), N(p[0]), N(p[1])),
+                Segment::Close => write!(self.content, "h "),
+            };
+            current = match *segment {
+                Segment::MoveTo(p) => {
+                    start = p;
+                    p
+                }
+                Segment::LineTo(p) | Segment::QuadTo(_, p) | Segment::CubicTo(_, _, p) => p,
+                Segment::Close => start,
+            };
+        }
+        let even_odd = style.fill_rule == FillRule::EvenOdd;
+        let op = match (style.fill.is_some(), style.stroke.is_some(), even_odd) {
+            (true, true, false) => "B",
+            (true, true, true) => "B*",
+            (true, false, false) => "f",
+            (true, false, true) => "f*",
+            _ => "S",
+        };
+        let _ = writeln!(self.content, "{op} Q");
+    }
+
+    fn image(&mut self, image: &PlacedImage, ts: Transform, opacity: f32) {
+        let key = Arc::as_ptr(&image.pixels);
+        let name = match self.images.get(&key) {
+            Some(&name) => name,
+            None => {
+                let id = self.pdf.image(&image.pixels);
+                self.resources.images.push(id);
+                let name = self.resources.images.len() - 1;
+                self.images.insert(key, name);
+                name
+            }
+        };
+        // the whole image, of which `uv` is shown in `rect`
+        let [x, y, w, h] = image.rect;
+        let [u0, v0, u1, v1] = image.uv;
+        let (fw, fh) = (w / (u1 - u0).max(f32::EPSILON), h / (v1 - v0).max(f32::EPSILON));
+        let (fx, fy) = (x - u0 * fw, y - v0 * fh);
+        let _ = writeln!(self.content, "q {} cm", matrix(ts));
+        let alpha = (opacity * 255.0).round() as u8;
+        if alpha < 255 {
+            let _ = writeln!(self.content, "{} gs", self.resources.gstate(alpha, alpha));
+        }
+        // images fill the unit square with their first row at the top
+        let _ = writeln!(self.content, "{} {} {} {} re W n", N(x), N(y), N(w), N(h));
+        let _ = writeln!(self.content, "{} 0 0 {} {} {} cm /I{name} Do Q", N(fw), N(-fh), N(fx), N(fy + fh));
+    }
+}
@@ -0,0 +1,153 @@
+use crate::document::{transform_point, Contour, Document};
+use crate::ebb::PlotterSettings;
+use crate::gcode::{GcodeOrigin, GcodeSettings};
+use crate::hpgl::HpglSettings;
+use crate::simulate::SimulationSettings;
+use crate::units::Unit;
+
+/// What drives a machine, which decides what its pen heights mean.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MachineKind {
+    /// pen heights are Z in mm
+    Gcode,
+    /// pen heights are percent of the servo's range
+    AxiDraw,
+    /// the plotter lifts its pens itself
+    Hpgl,
+}
+
+impl MachineKind {
+    pub const ALL: [MachineKind; 3] = [Self::Gcode, Self::AxiDraw, Self::Hpgl];
+
+    /// As in the settings file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Gcode => "gcode",
+            Self::AxiDraw => "axidraw",
+            Self::Hpgl => "hpgl",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.name() == name)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Gcode => "G-code",
+            Self::AxiDraw => "AxiDraw",
+            Self::Hpgl => "HPGL",
+        }
+    }
+}
+
+/// A machine the drawing is made for: its work area and how it moves. The active one is
+/// taken over into the settings of every exporter and device, see the `apply_` methods.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MachineProfile {
+    pub name: String,
+    pub kind: MachineKind,
+    /// Width and height of the work area, in mm
+    pub bed: [f32; 2],
+    /// The corner of the page that goes to the machine's origin, the bed lies against it
+    pub origin: GcodeOrigin,
+    /// The machine's x axis points left and its y axis down, instead of right and up
+    pub invert_x: bool,
+    pub invert_y: bool,
+    /// Top speed with the pen down and up, in mm/s
+    pub draw_speed: f32,
+    pub travel_speed: f32,
+    /// in mm/s²
+    pub acceleration: f32,
+    /// Z in mm for G-code machines, percent of the servo's range for an AxiDraw
+    pub pen_up: f32,
+    pub pen_down: f32,
+}
+
+impl Default for MachineProfile {
+    fn default() -> Self {
+        Self::new("Machine", MachineKind::Gcode)
+    }
+}
+
+impl MachineProfile {
+    /// Sizes and speeds of a typical machine of `kind`.
+    pub fn new(name: &str, kind: MachineKind) -> Self {
+        let profile = Self {
+            name: name.to_string(),
+            kind,
+            bed: [300.0, 300.0],
+            origin: GcodeOrigin::BottomLeft,
+            invert_x: false,
+            invert_y: false,
+            draw_speed: 25.0,
+            travel_speed: 75.0,
+            acceleration: 1000.0,
+            pen_up: 5.0,
+            pen_down: 0.0,
+        };
+        match kind {
+            MachineKind::Gcode => profile,
+            // the AxiDraw V3 homes in the top left corner, its y axis points down
+            MachineKind::AxiDraw => Self { bed: [300.0, 218.0], origin: GcodeOrigin::TopLeft, invert_y: true, draw_speed: 40.0, travel_speed: 100.0, pen_up: 60.0, pen_down: 30.0, ..profile },
+            // A3 on a classic flatbed
+            MachineKind::Hpgl => Self { bed: [420.0, 297.0], draw_speed: 200.0, travel_speed: 380.0, pen_up: 0.0, ..profile },
+        }
+    }
+
+    /// The origin, axes, speeds and for G-code machines the Z heights.
+    pub fn apply_gcode(&self, settings: &mut GcodeSettings) {
+        let per_mm = settings.units.per_mm();
+        settings.origin = self.origin;
+        settings.invert_x = self.invert_x;
+        settings.invert_y = self.invert_y;
+        settings.feed_rate = self.draw_speed * 60.0 * per_mm;
+        settings.travel_rate = self.travel_speed * 60.0 * per_mm;
+        if self.kind == MachineKind::Gcode {
+            settings.z_up = self.pen_up * per_mm;
+            settings.z_down = self.pen_down * per_mm;
+        }
+    }
+
+    /// The speeds and for an AxiDraw the pen heights.
+    pub fn apply_plotter(&self, settings: &mut PlotterSettings) {
+        settings.speed_down = self.draw_speed;
+        settings.speed_up = self.travel_speed;
+        if self.kind == MachineKind::AxiDraw {
+            settings.pen_up = self.pen_up;
+            settings.pen_down = self.pen_down;
+        }
+    }
+
+    /// HPGL plotters have one speed, the drawing one.
+    pub fn apply_hpgl(&self, settings: &mut HpglSettings) {
+        settings.velocity = self.draw_speed / 10.0;
+    }
+
+    pub fn apply_simulation(&self, settings: &mut SimulationSettings) {
+        settings.draw_speed = self.draw_speed;
+        settings.travel_speed = self.travel_speed;
+        settings.acceleration = self.acceleration;
+    }
+
+    /// The work area as [x0, y0, x1, y1] in document coordinates, the bed laid against the
+    /// origin corner of the page, or centered on it.
+    pub fn work_area(&self, doc: &Document) -> [f32; 4] {
+        let page = doc.size_mm();
+        let [cx, cy] = self.origin.on_page(page);
+        let [bx, by] = self.origin.on_page(self.bed);
+        let to_doc = doc.viewport_transform().invert().unwrap_or_default();
+        let corner = |x: f32, y: f32| transform_point(&to_doc, [Unit::Px.from_mm(x), Unit::Px.from_mm(y)]);
+        let [x0, y0] = corner(cx - bx, cy - by);
+        let [x1, y1] = corner(cx - bx + self.bed[0], cy - by + self.bed[1]);
+        [x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1)]
+    }
+
+    /// Whether any of `contours`, in document coordinates, reaches beyond the work area.
+    pub fn exceeded_by<'a>(&self, doc: &Document, contours: impl IntoIterator<Item = &'a Contour>) -> bool {
+        let [x0, y0, x1, y1] = self.work_area(doc);
+        // rounding of the unit conversions
+        let slack = (x1 - x0).max(y1 - y0) * 1e-5;
+        contours.into_iter().flat_map(|c| &c.points).any(|&[x, y]| x < x0 - slack || x > x1 + slack || y < y0 - slack || y > y1 + slack)
+    }
+}
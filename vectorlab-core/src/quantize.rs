@@ -0,0 +1,160 @@
+use std::sync::Arc;
+
+use crate::document::{Document, ElementKind, FlattenedPath};
+use crate::edit::{EditCommand, EditGroup, SetStyle};
+use crate::style::{Color, Gradient, Paint};
+
+/// How colors are turned into others to see what a plotter or laser will make of a drawing,
+/// see [`Document::recolored`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ColorMode {
+    Grayscale,
+    /// black below this luminance in 0..1, white from it
+    Threshold(f32),
+    /// the nearest of these pens
+    Pens(Vec<Color>),
+}
+
+impl ColorMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Grayscale => "Grayscale",
+            Self::Threshold(_) => "Black and white",
+            Self::Pens(_) => "Pen colors",
+        }
+    }
+
+    /// `c` as it comes out, with its alpha.
+    pub fn map(&self, c: Color) -> Color {
+        match self {
+            Self::Grayscale => {
+                let l = (luminance(c) * 255.0).round() as u8;
+                Color { r: l, g: l, b: l, a: c.a }
+            }
+            Self::Threshold(t) => {
+                let l = if luminance(c) < *t { 0 } else { 255 };
+                Color { r: l, g: l, b: l, a: c.a }
+            }
+            Self::Pens(pens) => match pens.iter().min_by_key(|&&p| distance2(p, c)) {
+                Some(&p) => Color { a: c.a, ..p },
+                None => c,
+            },
+        }
+    }
+}
+
+/// Relative luminance of the sRGB values as they are, in 0..1.
+fn luminance(c: Color) -> f32 {
+    (0.2126 * c.r as f32 + 0.7152 * c.g as f32 + 0.0722 * c.b as f32) / 255.0
+}
+
+pub(crate) fn distance2(a: Color, b: Color) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.r, b.r) + d(a.g, b.g) + d(a.b, b.b)
+}
+
+/// True if it changed.
+fn map_paint(paint: &mut Option<Paint>, mode: &ColorMode) -> bool {
+    match paint {
+        Some(Paint::Solid(c)) => {
+            let m = mode.map(*c);
+            std::mem::replace(c, m) != m
+        }
+        Some(Paint::Gradient(gradient)) => {
+            let mut stops = gradient.stops.clone();
+            for (_, rgba) in &mut stops {
+                let c = Color::rgba((rgba[0] * 255.0).round() as u8, (rgba[1] * 255.0).round() as u8, (rgba[2] * 255.0).round() as u8, (rgba[3] * 255.0).round() as u8);
+                let m = mode.map(c);
+                *rgba = [m.r as f32 / 255.0, m.g as f32 / 255.0, m.b as f32 / 255.0, rgba[3]];
+            }
+            let changed = stops.iter().zip(&gradient.stops).any(|(a, b)| a.1.iter().zip(&b.1).any(|(x, y)| (x - y).abs() > 0.5 / 255.0));
+            *gradient = Arc::new(Gradient { stops, ..(**gradient).clone() });
+            changed
+        }
+        None => false,
+    }
+}
+
+impl FlattenedPath {
+    /// Colors mapped by `mode`, the meshes' gradient colors along without tessellating again.
+    fn recolor(&mut self, mode: &ColorMode) {
+        map_paint(&mut self.style.fill, mode);
+        map_paint(&mut self.style.stroke, mode);
+        for c in self.fill_colors.iter_mut().chain(&mut self.stroke_colors) {
+            *c = mode.map(*c);
+        }
+    }
+}
+
+impl Document {
+    /// A copy with the colors of the paths mapped by `mode`, for previewing. Images are left as
+    /// they are.
+    pub fn recolored(&self, mode: &ColorMode) -> Document {
+        let mut doc = self.clone();
+        for element in &mut doc.elements {
+            if let ElementKind::Path(path) = &mut element.kind {
+                path.recolor(mode);
+            }
+        }
+        doc.touch();
+        doc
+    }
+}
+
+/// What [`Document::recolored`] shows, done to the document. None if no color changes.
+pub fn apply_color_mode(doc: &Document, mode: &ColorMode) -> Option<Box<dyn EditCommand>> {
+    let mut commands: Vec<Box<dyn EditCommand>> = vec![];
+    for (id, path) in doc.paths() {
+        let mut style = path.style.clone();
+        // both, not just the first
+        let fill = map_paint(&mut style.fill, mode);
+        let stroke = map_paint(&mut style.stroke, mode);
+        if fill || stroke {
+            commands.push(Box::new(SetStyle { id, old: path.style.clone(), new: style }));
+        }
+    }
+    if commands.is_empty() {
+        return None;
+    }
+    Some(Box::new(EditGroup::new(mode.name(), commands)))
+}
+
+/// `n` pens that come closest to the solid colors of the paths, the more used colors counting
+/// more (k-means). Fewer if the paths have fewer colors.
+pub fn pen_colors(doc: &Document, n: usize) -> Vec<Color> {
+    let n = n.max(1);
+    let colors: Vec<(Color, usize)> = doc.palette().iter().map(|c| (Color { a: 255, ..c.color }, c.count())).collect();
+    if colors.len() <= n {
+        return colors.into_iter().map(|(c, _)| c).collect();
+    }
+    // the most used first, then each time the one farthest from those taken
+    let mut pens = vec![colors[0].0];
+    while pens.len() < n {
+        let farthest = colors.iter().max_by_key(|&&(c, _)| pens.iter().map(|&p| distance2(p, c)).min().unwrap_or(0));
+        pens.push(farthest.map_or(Color::BLACK, |&(c, _)| c));
+    }
+    for _ in 0..16 {
+        let mut sums = vec![[0.0f64; 4]; n];
+        for &(c, count) in &colors {
+            let nearest = (0..n).min_by_key(|&i| distance2(pens[i], c)).unwrap_or(0);
+            let w = count as f64;
+            sums[nearest][0] += c.r as f64 * w;
+            sums[nearest][1] += c.g as f64 * w;
+            sums[nearest][2] += c.b as f64 * w;
+            sums[nearest][3] += w;
+        }
+        let mut moved = false;
+        for (pen, [r, g, b, w]) in pens.iter_mut().zip(sums) {
+            if w == 0.0 {
+                continue;
+            }
+            let mean = Color::rgb((r / w).round() as u8, (g / w).round() as u8, (b / w).round() as u8);
+            moved |= mean != *pen;
+            *pen = mean;
+        }
+        if !moved {
+            break;
+        }
+    }
+    pens
+}
@@ -0,0 +1,95 @@
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+
+use resvg::usvg::{self, TreeParsing, TreePostProc};
+
+use crate::document::Document;
+use crate::image::RasterImage;
+use crate::loader::LoadOptions;
+use crate::saver::to_svg_string;
+use crate::style::Color;
+use crate::view::ViewTransform;
+
+/// Headless export through resvg's own raster pipeline, no window or GL context involved.
+/// `width` wins over `height`; with neither the SVG's own size is used.
+pub fn render_png(input: &Path, output: &Path, width: Option<u32>, height: Option<u32>, bg: Option<Color>, fonts: &LoadOptions) -> Result<(u32, u32), Box<dyn std::error::Error>> {
+    let data = fs::read(input)?;
+    let opts = usvg::Options {
+        resources_dir: input.parent().map(|p| p.to_path_buf()),
+        ..Default::default()
+    };
+    let mut tree = usvg::Tree::from_data(&data, &opts)?;
+    tree.postprocess(usvg::PostProcessingSteps::default(), &fonts.fontdb);
+
+    let size = tree.size;
+    let scale = match (width, height) {
+        (Some(w), _) => w as f32 / size.width(),
+        (None, Some(h)) => h as f32 / size.height(),
+        (None, None) => 1.0,
+    };
+    let w = (size.width() * scale).round().max(1.0) as u32;
+    let h = (size.height() * scale).round().max(1.0) as u32;
+    let mut pixmap = tiny_skia::Pixmap::new(w, h).ok_or("invalid output size")?;
+    if let Some(c) = bg {
+        pixmap.fill(tiny_skia::Color::from_rgba8(c.r, c.g, c.b, c.a));
+    }
+    resvg::render(&tree, tiny_skia::Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+    pixmap.save_png(output)?;
+    Ok((w, h))
+}
+
+/// What `view` shows of `doc` in a canvas of `size` pixels, `scale` times as many pixels
+/// wide and high. Hidden elements stay hidden since the document is rendered as it would
+/// be saved. Returns the size written.
+pub fn render_view_png(doc: &Document, view: &ViewTransform, size: [f32; 2], scale: f32, output: &Path, bg: Option<Color>, fonts: &LoadOptions) -> Result<(u32, u32), Box<dyn std::error::Error>> {
+    let svg = to_svg_string(doc);
+    let mut tree = usvg::Tree::from_str(&svg, &usvg::Options::default())?;
+    tree.postprocess(usvg::PostProcessingSteps::default(), &fonts.fontdb);
+
+    let w = (size[0] * scale).round().max(1.0) as u32;
+    let h = (size[1] * scale).round().max(1.0) as u32;
+    let mut pixmap = tiny_skia::Pixmap::new(w, h).ok_or("invalid output size")?;
+    if let Some(c) = bg {
+        pixmap.fill(tiny_skia::Color::from_rgba8(c.r, c.g, c.b, c.a));
+    }
+    // resvg maps the viewBox onto the SVG's size first, the view starts from user units
+    let viewport = usvg::utils::view_box_to_transform(tree.view_box.rect, tree.view_box.aspect, tree.size);
+    let [a, b, c, d] = view.matrix();
+    let ts = tiny_skia::Transform::from_row(a, c, b, d, view.pan[0], view.pan[1])
+        .post_scale(scale, scale)
+        .pre_concat(viewport.invert().ok_or("degenerate viewBox")?);
+    resvg::render(&tree, ts, &mut pixmap.as_mut());
+    pixmap.save_png(output)?;
+    Ok((w, h))
+}
+
+/// The whole page of `doc` at `width` × `height` pixels, premultiplied like decoded images.
+/// A size of a different aspect ratio than the page's stretches it.
+pub fn render_document(doc: &Document, width: u32, height: u32, bg: Option<Color>, fonts: &LoadOptions) -> Result<RasterImage, Box<dyn std::error::Error>> {
+    let svg = to_svg_string(doc);
+    let mut tree = usvg::Tree::from_str(&svg, &usvg::Options::default())?;
+    tree.postprocess(usvg::PostProcessingSteps::default(), &fonts.fontdb);
+
+    let mut pixmap = tiny_skia::Pixmap::new(width, height).ok_or("invalid output size")?;
+    if let Some(c) = bg {
+        pixmap.fill(tiny_skia::Color::from_rgba8(c.r, c.g, c.b, c.a));
+    }
+    let ts = tiny_skia::Transform::from_scale(width as f32 / tree.size.width(), height as f32 / tree.size.height());
+    resvg::render(&tree, ts, &mut pixmap.as_mut());
+    Ok(RasterImage { width, height, rgba: pixmap.take() })
+}
+
+/// Write `image` as PNG, marked with `dpi` so that other programs print it at the intended size.
+pub fn save_png(image: &RasterImage, output: &Path, dpi: f32) -> Result<(), Box<dyn std::error::Error>> {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(output)?), image.width, image.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    // PNG counts pixels per meter
+    let ppm = (dpi / 0.0254).round() as u32;
+    encoder.set_pixel_dims(Some(png::PixelDimensions { xppu: ppm, yppu: ppm, unit: png::Unit::Meter }));
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&image.unpremultiplied())?;
+    writer.finish()?;
+    Ok(())
+}
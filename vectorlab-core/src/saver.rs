@@ -0,0 +1,270 @@
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use resvg::usvg::{Align, AspectRatio, Transform};
+
+use crate::document::{Document, ElementId, ElementKind, FlattenedPath, Segment};
+use crate::image::RasterImage;
+use crate::style::{Color, FillRule, GradientShape, LineCap, LineJoin, Paint, SpreadMethod};
+use crate::text::text_markup;
+use crate::units::Unit;
+
+/// How [`save_file_with`] writes a document.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SaveOptions {
+    /// texts as their glyph outlines, for programs and machines without the fonts
+    pub text_to_paths: bool,
+}
+
+/// Write `doc` as SVG. Paths keep their curves, images are embedded as PNGs.
+pub fn save_file(doc: &Document, path: &Path) -> std::io::Result<()> {
+    save_file_with(doc, path, SaveOptions::default())
+}
+
+pub fn save_file_with(doc: &Document, path: &Path, opts: SaveOptions) -> std::io::Result<()> {
+    fs::write(path, to_svg_string_with(doc, opts))
+}
+
+pub fn to_svg_string(doc: &Document) -> String {
+    to_svg_string_with(doc, SaveOptions::default())
+}
+
+pub fn to_svg_string_with(doc: &Document, opts: SaveOptions) -> String {
+    let mut writer = SvgWriter { opts, ..SvgWriter::default() };
+    let [w, h] = doc.size;
+    let [vx, vy, vw, vh] = if doc.view_box[2] > 0.0 && doc.view_box[3] > 0.0 { doc.view_box } else { [0.0, 0.0, w, h] };
+    for &child in &doc.get(doc.root).children {
+        writer.element(doc, child, 1);
+    }
+
+    let mut out = String::new();
+    let _ = writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let aspect = if doc.aspect == AspectRatio::default() { String::new() } else { format!(r#" preserveAspectRatio="{}""#, aspect_ratio(doc.aspect)) };
+    let (width, height) = (length(w, doc.unit), length(h, doc.unit));
+    let _ = writeln!(out, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="{vx} {vy} {vw} {vh}"{aspect}>"#);
+    if !writer.defs.is_empty() {
+        let _ = write!(out, "  <defs>\n{}  </defs>\n", writer.defs);
+    }
+    out.push_str(&writer.body);
+    out.push_str("</svg>\n");
+    out
+}
+
+#[derive(Default)]
+struct SvgWriter {
+    opts: SaveOptions,
+    defs: String,
+    body: String,
+    gradient_count: usize,
+}
+
+impl SvgWriter {
+    fn element(&mut self, doc: &Document, id: ElementId, depth: usize) {
+        let element = doc.get(id);
+        let indent = "  ".repeat(depth);
+        let mut id_attr = String::new();
+        if !element.source_id.is_empty() {
+            let _ = write!(id_attr, r#" id="{}""#, escape(&element.source_id));
+        }
+        let mut attrs = String::new();
+        if !element.transform.is_identity() {
+            let t = element.transform;
+            let _ = write!(attrs, r#" transform="matrix({} {} {} {} {} {})""#, t.sx, t.ky, t.kx, t.sy, t.tx, t.ty);
+        }
+        if element.opacity < 1.0 {
+            let _ = write!(attrs, r#" opacity="{}""#, element.opacity);
+        }
+        if !element.visible {
+            attrs.push_str(r#" display="none""#);
+        }
+
+        match &element.kind {
+            ElementKind::Group => {
+                let _ = writeln!(self.body, "{indent}<g{id_attr}{attrs}>");
+                for &child in &element.children {
+                    self.element(doc, child, depth + 1);
+                }
+                let _ = writeln!(self.body, "{indent}</g>");
+            }
+            ElementKind::Path(path) => {
+                let style = self.style_attrs(path);
+                let _ = writeln!(self.body, r#"{indent}<path{id_attr}{attrs}{style} d="{}"/>"#, path_data(path));
+                // marker instances, written as plain geometry in the path's coordinates
+                if attrs.is_empty() {
+                    for &child in &element.children {
+                        self.element(doc, child, depth);
+                    }
+                } else if !element.children.is_empty() {
+                    let _ = writeln!(self.body, "{indent}<g{attrs}>");
+                    for &child in &element.children {
+                        self.element(doc, child, depth + 1);
+                    }
+                    let _ = writeln!(self.body, "{indent}</g>");
+                }
+            }
+            ElementKind::Text { .. } if self.opts.text_to_paths => {
+                let _ = writeln!(self.body, "{indent}<g{id_attr}{attrs}>");
+                for &child in &element.children {
+                    self.element(doc, child, depth + 1);
+                }
+                let _ = writeln!(self.body, "{indent}</g>");
+            }
+            ElementKind::Text { content, layout: Some(layout) } => {
+                let _ = writeln!(self.body, "{indent}{}", text_markup(content, layout, &format!("{id_attr}{attrs}")));
+            }
+            ElementKind::Text { content, layout: None } => {
+                let _ = writeln!(self.body, "{indent}<text{id_attr}{attrs}>{}</text>", escape(content));
+            }
+            ElementKind::Image { rect, image } => match image.as_ref().and_then(|i| Some((i, png_data_url(&i.pixels)?))) {
+                Some((image, href)) => {
+                    let ([x, y, w, h], aspect) = if image.uv == [0.0, 0.0, 1.0, 1.0] {
+                        (image.rect, "none")
+                    } else {
+                        (*rect, "xMidYMid slice")
+                    };
+                    let _ = writeln!(
+                        self.body,
+                        r#"{indent}<image{id_attr}{attrs} x="{x}" y="{y}" width="{w}" height="{h}" preserveAspectRatio="{aspect}" href="{href}"/>"#
+                    );
+                }
+                None => {
+                    let _ = writeln!(self.body, "{indent}<!-- image{id_attr}{attrs} could not be decoded -->");
+                }
+            },
+        }
+    }
+
+    fn style_attrs(&mut self, path: &FlattenedPath) -> String {
+        let mut attrs = String::new();
+        let style = &path.style;
+        match &style.fill {
+            Some(paint) => {
+                let (value, opacity) = self.paint(paint);
+                let _ = write!(attrs, r#" fill="{value}""#);
+                if let Some(o) = opacity {
+                    let _ = write!(attrs, r#" fill-opacity="{o}""#);
+                }
+            }
+            None => attrs.push_str(r#" fill="none""#),
+        }
+        if style.fill_rule == FillRule::EvenOdd {
+            attrs.push_str(r#" fill-rule="evenodd""#);
+        }
+        if let Some(paint) = &style.stroke {
+            let (value, opacity) = self.paint(paint);
+            let _ = write!(attrs, r#" stroke="{value}" stroke-width="{}""#, style.stroke_width);
+            if let Some(o) = opacity {
+                let _ = write!(attrs, r#" stroke-opacity="{o}""#);
+            }
+            match style.line_cap {
+                LineCap::Butt => {}
+                LineCap::Round => attrs.push_str(r#" stroke-linecap="round""#),
+                LineCap::Square => attrs.push_str(r#" stroke-linecap="square""#),
+            }
+            match style.line_join {
+                LineJoin::Miter => {}
+                LineJoin::Round => attrs.push_str(r#" stroke-linejoin="round""#),
+                LineJoin::Bevel => attrs.push_str(r#" stroke-linejoin="bevel""#),
+            }
+            if style.line_join == LineJoin::Miter && style.miter_limit != 4.0 {
+                let _ = write!(attrs, r#" stroke-miterlimit="{}""#, style.miter_limit);
+            }
+        }
+        attrs
+    }
+
+    /// Attribute value for `paint` and its opacity if not opaque. Gradients are added to the defs.
+    fn paint(&mut self, paint: &Paint) -> (String, Option<f32>) {
+        let gradient = match paint {
+            Paint::Solid(c) => return (hex(*c), (c.a < 255).then_some(c.a as f32 / 255.0)),
+            Paint::Gradient(g) => g,
+        };
+        self.gradient_count += 1;
+        let id = format!("vectorlab-gradient-{}", self.gradient_count);
+
+        let (tag, geometry) = match gradient.shape {
+            GradientShape::Linear { x1, y1, x2, y2 } => ("linearGradient", format!(r#"x1="{x1}" y1="{y1}" x2="{x2}" y2="{y2}""#)),
+            GradientShape::Radial { cx, cy, r, fx, fy } => ("radialGradient", format!(r#"cx="{cx}" cy="{cy}" r="{r}" fx="{fx}" fy="{fy}""#)),
+        };
+        let spread = match gradient.spread {
+            SpreadMethod::Pad => "pad",
+            SpreadMethod::Reflect => "reflect",
+            SpreadMethod::Repeat => "repeat",
+        };
+        // to_gradient maps path coordinates into the gradient, gradientTransform goes the other way
+        let t = gradient.to_gradient.invert().unwrap_or(Transform::identity());
+        let _ = writeln!(
+            self.defs,
+            r#"    <{tag} id="{id}" gradientUnits="userSpaceOnUse" spreadMethod="{spread}" gradientTransform="matrix({} {} {} {} {} {})" {geometry}>"#,
+            t.sx, t.ky, t.kx, t.sy, t.tx, t.ty
+        );
+        for &(offset, [r, g, b, a]) in &gradient.stops {
+            let color = Color::rgb((r * 255.0).round() as u8, (g * 255.0).round() as u8, (b * 255.0).round() as u8);
+            let _ = writeln!(self.defs, r#"      <stop offset="{offset}" stop-color="{}" stop-opacity="{a}"/>"#, hex(color));
+        }
+        let _ = writeln!(self.defs, "    </{tag}>");
+        (format!("url(#{id})"), None)
+    }
+}
+
+/// `px` CSS pixels as an attribute value in `unit`, plain numbers for px.
+fn length(px: f32, unit: Unit) -> String {
+    if unit == Unit::Px {
+        return px.to_string();
+    }
+    // 793.7008 px are 210 mm, not 210.00002
+    let value = (unit.from_mm(Unit::Px.to_mm(px)) as f64 * 1e4).round() / 1e4;
+    format!("{value}{}", unit.name())
+}
+
+fn path_data(path: &FlattenedPath) -> String {
+    let mut d = String::new();
+    for segment in &path.segments {
+        let _ = match segment {
+            Segment::MoveTo(p) => write!(d, "M{} {} ", p[0], p[1]),
+            Segment::LineTo(p) => write!(d, "L{} {} ", p[0], p[1]),
+            Segment::QuadTo(c, p) => write!(d, "Q{} {} {} {} ", c[0], c[1], p[0], p[1]),
+            Segment::CubicTo(c1, c2, p) => write!(d, "C{} {} {} {} {} {} ", c1[0], c1[1], c2[0], c2[1], p[0], p[1]),
+            Segment::Close => write!(d, "Z "),
+        };
+    }
+    d.trim_end().to_string()
+}
+
+/// `preserveAspectRatio` attribute value, e.g. "xMidYMid slice".
+fn aspect_ratio(aspect: AspectRatio) -> String {
+    let align = match aspect.align {
+        Align::None => "none",
+        Align::XMinYMin => "xMinYMin",
+        Align::XMidYMin => "xMidYMin",
+        Align::XMaxYMin => "xMaxYMin",
+        Align::XMinYMid => "xMinYMid",
+        Align::XMidYMid => "xMidYMid",
+        Align::XMaxYMid => "xMaxYMid",
+        Align::XMinYMax => "xMinYMax",
+        Align::XMidYMax => "xMidYMax",
+        Align::XMaxYMax => "xMaxYMax",
+    };
+    if aspect.slice {
+        format!("{} slice", align)
+    } else {
+        align.to_string()
+    }
+}
+
+/// The pixels re-encoded as PNG, embedded as a data: URL.
+fn png_data_url(pixels: &RasterImage) -> Option<String> {
+    use base64::Engine;
+    let size = tiny_skia::IntSize::from_wh(pixels.width, pixels.height)?;
+    let png = tiny_skia::Pixmap::from_vec(pixels.rgba.clone(), size)?.encode_png().ok()?;
+    Some(format!("data:image/png;base64,{}", base64::engine::general_purpose::STANDARD.encode(png)))
+}
+
+fn hex(c: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b)
+}
+
+pub(crate) fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,48 @@
+use crate::document::{Document, ElementId};
+
+impl Document {
+    /// Elements whose id, class or SVG tag name match `query`, in paint order. `#id` and
+    /// `.class` must match exactly, a plain word matches tag names exactly and ids and
+    /// classes as a substring. Case is ignored.
+    pub fn find(&self, query: &str) -> Vec<ElementId> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return vec![];
+        }
+        self.descendants(self.root)
+            .into_iter()
+            .filter(|&id| {
+                let element = self.get(id);
+                let id_attr = element.source_id.to_lowercase();
+                let mut classes = element.source_class.split_whitespace().map(|c| c.to_lowercase());
+                if let Some(wanted) = query.strip_prefix('#') {
+                    id_attr == wanted
+                } else if let Some(wanted) = query.strip_prefix('.') {
+                    classes.any(|c| c == wanted)
+                } else {
+                    self.source_tag(id).is_some_and(|tag| tag.eq_ignore_ascii_case(&query))
+                        || id_attr.contains(&query)
+                        || classes.any(|c| c.contains(&query))
+                }
+            })
+            .collect()
+    }
+
+    /// Tag name of the XML element `id` was made from, without namespace prefix, e.g. "rect"
+    /// for what became a path.
+    pub fn source_tag(&self, id: ElementId) -> Option<&str> {
+        let range = self.get(id).source_range.as_ref()?;
+        let tag = self.source.get(range.start + 1..range.end)?;
+        let name = &tag[..tag.find(|c: char| c.is_whitespace() || c == '/' || c == '>').unwrap_or(tag.len())];
+        Some(name.rsplit(':').next().unwrap_or(name))
+    }
+
+    /// Bounding box of `id` and everything below it, hidden elements included, in document
+    /// coordinates.
+    pub fn subtree_bbox(&self, id: ElementId) -> Option<[f32; 4]> {
+        self.descendants(id)
+            .into_iter()
+            .filter_map(|id| self.element_bbox(id))
+            .reduce(|a, b| [a[0].min(b[0]), a[1].min(b[1]), a[2].max(b[2]), a[3].max(b[3])])
+    }
+}
@@ -0,0 +1,109 @@
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use crate::document::{transform_point, transform_scale, Document, Element, ElementKind, FlattenedPath, Segment};
+use crate::edit::AddElement;
+use crate::style::{FillRule, LineCap, LineJoin, Paint, Style};
+
+/// Closed rectangle with corners `a` and `b`.
+pub fn rectangle(a: [f32; 2], b: [f32; 2]) -> Vec<Segment> {
+    vec![Segment::MoveTo(a), Segment::LineTo([b[0], a[1]]), Segment::LineTo(b), Segment::LineTo([a[0], b[1]]), Segment::Close]
+}
+
+/// Ellipse in the box with corners `a` and `b`, four cubics starting at the right.
+pub fn ellipse(a: [f32; 2], b: [f32; 2]) -> Vec<Segment> {
+    // control point distance for a quarter circle
+    const K: f32 = 0.552_284_8;
+    let c = [(a[0] + b[0]) * 0.5, (a[1] + b[1]) * 0.5];
+    let r = [(b[0] - a[0]).abs() * 0.5, (b[1] - a[1]).abs() * 0.5];
+    let at = |x: f32, y: f32| [c[0] + x * r[0], c[1] + y * r[1]];
+    vec![
+        Segment::MoveTo(at(1.0, 0.0)),
+        Segment::CubicTo(at(1.0, K), at(K, 1.0), at(0.0, 1.0)),
+        Segment::CubicTo(at(-K, 1.0), at(-1.0, K), at(-1.0, 0.0)),
+        Segment::CubicTo(at(-1.0, -K), at(-K, -1.0), at(0.0, -1.0)),
+        Segment::CubicTo(at(K, -1.0), at(1.0, -K), at(1.0, 0.0)),
+        Segment::Close,
+    ]
+}
+
+/// Regular polygon around `center` with `corners` corners, one of them at `corner`. With
+/// `inner` it is a star whose inner corners are that fraction of the radius out.
+pub fn polygon(center: [f32; 2], corner: [f32; 2], corners: u32, inner: Option<f32>) -> Vec<Segment> {
+    let corners = corners.max(3);
+    let radius = (corner[0] - center[0]).hypot(corner[1] - center[1]);
+    let start = if radius > 0.0 { (corner[1] - center[1]).atan2(corner[0] - center[0]) } else { -FRAC_PI_2 };
+    let points = if inner.is_some() { corners * 2 } else { corners };
+    let mut segments: Vec<Segment> = (0..points)
+        .map(|i| {
+            let r = if i % 2 == 1 { inner.map_or(radius, |f| radius * f) } else { radius };
+            let angle = start + TAU * i as f32 / points as f32;
+            let p = [center[0] + r * angle.cos(), center[1] + r * angle.sin()];
+            if i == 0 { Segment::MoveTo(p) } else { Segment::LineTo(p) }
+        })
+        .collect();
+    segments.push(Segment::Close);
+    segments
+}
+
+/// Straight lines through `points`, back to the first one if `closed`.
+pub fn polyline(points: &[[f32; 2]], closed: bool) -> Vec<Segment> {
+    let mut segments: Vec<Segment> = points.iter().enumerate().map(|(i, &p)| if i == 0 { Segment::MoveTo(p) } else { Segment::LineTo(p) }).collect();
+    if closed && points.len() > 2 {
+        segments.push(Segment::Close);
+    }
+    segments
+}
+
+impl FlattenedPath {
+    /// A path of `segments` drawn with `style`, flattened with `tolerance`.
+    pub fn new(segments: Vec<Segment>, style: Style, tolerance: f32) -> Self {
+        let mut path = FlattenedPath {
+            segments: vec![],
+            contours: vec![],
+            style,
+            tolerance,
+            fill_vertices: vec![],
+            fill_indices: vec![],
+            fill_colors: vec![],
+            stroke_vertices: vec![],
+            stroke_indices: vec![],
+            stroke_colors: vec![],
+            clip: None,
+        };
+        path.set_segments(segments);
+        path
+    }
+}
+
+/// A new path on top of everything else with `segments` in document coordinates, the stroke
+/// `stroke_width` document units wide.
+pub fn add_shape(doc: &Document, segments: Vec<Segment>, fill: Option<Paint>, stroke: Option<Paint>, stroke_width: f32) -> AddElement {
+    let to_local = doc.abs_transform(doc.root).invert().unwrap_or_default();
+    let segments = segments
+        .into_iter()
+        .map(|s| match s {
+            Segment::MoveTo(p) => Segment::MoveTo(transform_point(&to_local, p)),
+            Segment::LineTo(p) => Segment::LineTo(transform_point(&to_local, p)),
+            Segment::QuadTo(c, p) => Segment::QuadTo(transform_point(&to_local, c), transform_point(&to_local, p)),
+            Segment::CubicTo(c1, c2, p) => Segment::CubicTo(transform_point(&to_local, c1), transform_point(&to_local, c2), transform_point(&to_local, p)),
+            Segment::Close => Segment::Close,
+        })
+        .collect();
+    let scale = transform_scale(&to_local);
+    let style = Style {
+        fill,
+        fill_rule: FillRule::NonZero,
+        stroke,
+        stroke_width: stroke_width * scale,
+        line_cap: LineCap::Butt,
+        line_join: LineJoin::Miter,
+        miter_limit: 4.0,
+    };
+    let mut path = FlattenedPath::new(segments, style, 1.0);
+    // fine enough until the view flattens it for its zoom
+    if let Some(b) = path.control_bbox() {
+        path.reflatten(((b[2] - b[0]).max(b[3] - b[1]) * 1e-3).max(1e-4));
+    }
+    let element = Element::new(ElementKind::Path(path));
+    AddElement::on_top(doc, doc.root, element)
+}
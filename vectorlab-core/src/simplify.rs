@@ -0,0 +1,198 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+
+use crate::document::{transform_scale, Document, ElementId, Segment};
+use crate::hit::distance_to_segment;
+use crate::loader::flatten_segments;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SimplifyMethod {
+    /// Ramer–Douglas–Peucker: keeps every point further than the tolerance from the
+    /// simplified line. Keeps sharp corners.
+    DouglasPeucker,
+    /// Visvalingam–Whyatt: drops the points spanning the smallest triangles with their
+    /// neighbors first, until all are larger than the tolerance squared. Smoother.
+    Visvalingam,
+}
+
+impl SimplifyMethod {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::DouglasPeucker => "Douglas–Peucker",
+            Self::Visvalingam => "Visvalingam",
+        }
+    }
+}
+
+/// A path with fewer points, see `simplify_paths`.
+pub struct Simplified {
+    pub id: ElementId,
+    pub segments: Vec<Segment>,
+    /// commands of the path before and after, not counting closepath
+    pub before: usize,
+    pub after: usize,
+}
+
+/// The paths among `ids` as polylines that stay within `tolerance` document units of them,
+/// only those that end up with fewer points. Curves are flattened first, so a path made of a
+/// few curves is left alone while the dense polylines of tracing programs shrink a lot.
+pub fn simplify_paths(doc: &Document, ids: &[ElementId], tolerance: f32, method: SimplifyMethod) -> Vec<Simplified> {
+    let wanted: HashSet<ElementId> = ids.iter().copied().collect();
+    let mut simplified = vec![];
+    doc.walk(|id, element, ts, _| {
+        let Some(path) = element.as_path() else { return };
+        if !wanted.contains(&id) {
+            return;
+        }
+        // the tolerance is in the path's own coordinates, a quarter of it for flattening
+        let local = tolerance / transform_scale(&ts).max(f32::EPSILON);
+        let mut segments = vec![];
+        for contour in flatten_segments(&path.segments, local * 0.25) {
+            let points = simplify_polyline(&contour.points, contour.closed, local, method);
+            segments.push(Segment::MoveTo(points[0]));
+            segments.extend(points[1..].iter().map(|&p| Segment::LineTo(p)));
+            if contour.closed {
+                segments.push(Segment::Close);
+            }
+        }
+        let count = |segments: &[Segment]| segments.iter().filter(|s| !matches!(s, Segment::Close)).count();
+        let (before, after) = (count(&path.segments), count(&segments));
+        if after < before {
+            simplified.push(Simplified { id, segments, before, after });
+        }
+    });
+    simplified
+}
+
+/// `points` with those removed that `method` finds not worth keeping at `tolerance`. The
+/// ends of open polylines stay, closed ones keep at least three points.
+pub fn simplify_polyline(points: &[[f32; 2]], closed: bool, tolerance: f32, method: SimplifyMethod) -> Vec<[f32; 2]> {
+    let least = if closed { 3 } else { 2 };
+    if points.len() <= least {
+        return points.to_vec();
+    }
+    let keep = match method {
+        SimplifyMethod::DouglasPeucker if closed => {
+            // two open halves, split at the point furthest from the first one
+            let far = (1..points.len()).max_by(|&a, &b| distance2(points[0], points[a]).total_cmp(&distance2(points[0], points[b]))).unwrap_or(1);
+            let mut keep = douglas_peucker(&points[..=far], tolerance);
+            let mut closing = points[far..].to_vec();
+            closing.push(points[0]);
+            let rest = douglas_peucker(&closing, tolerance);
+            keep.extend(&rest[1..rest.len() - 1]);
+            if keep.iter().filter(|&&k| k).count() < least {
+                // a thin shape, keep the point that gives it some width
+                let widest = (1..points.len()).max_by(|&a, &b| {
+                    distance_to_segment(points[a], points[0], points[far]).total_cmp(&distance_to_segment(points[b], points[0], points[far]))
+                });
+                keep[widest.unwrap_or(1)] = true;
+            }
+            keep
+        }
+        SimplifyMethod::DouglasPeucker => douglas_peucker(points, tolerance),
+        SimplifyMethod::Visvalingam => visvalingam(points, closed, tolerance * tolerance, least),
+    };
+    let kept: Vec<[f32; 2]> = points.iter().zip(&keep).filter(|(_, &k)| k).map(|(&p, _)| p).collect();
+    if kept.len() < least {
+        // a closed polyline flattened to a line, keep its outline recognizable
+        return points.to_vec();
+    }
+    kept
+}
+
+/// Which of `points` to keep, the ends always.
+fn douglas_peucker(points: &[[f32; 2]], tolerance: f32) -> Vec<bool> {
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    // spans still to look at, a stack instead of recursion for long polylines
+    let mut spans = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = spans.pop() {
+        let mut furthest = (0.0, first);
+        for i in first + 1..last {
+            let d = distance_to_segment(points[i], points[first], points[last]);
+            if d > furthest.0 {
+                furthest = (d, i);
+            }
+        }
+        if furthest.0 > tolerance {
+            keep[furthest.1] = true;
+            spans.push((first, furthest.1));
+            spans.push((furthest.1, last));
+        }
+    }
+    keep
+}
+
+/// A point and the area of the triangle it spans with its neighbors, smallest first.
+struct Candidate {
+    area: f32,
+    index: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // reversed, BinaryHeap pops the largest
+        other.area.total_cmp(&self.area).then(other.index.cmp(&self.index))
+    }
+}
+
+/// Which of `points` to keep, dropping the one with the smallest triangle again and again
+/// while that is below `min_area`.
+fn visvalingam(points: &[[f32; 2]], closed: bool, min_area: f32, least: usize) -> Vec<bool> {
+    let n = points.len();
+    let mut keep = vec![true; n];
+    let mut prev: Vec<usize> = (0..n).map(|i| (i + n - 1) % n).collect();
+    let mut next: Vec<usize> = (0..n).map(|i| (i + 1) % n).collect();
+    let area = |a: [f32; 2], b: [f32; 2], c: [f32; 2]| ((b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1])).abs() * 0.5;
+    // the ends of open polylines are never candidates
+    let inner = if closed { 0..n } else { 1..n - 1 };
+    let mut current = vec![f32::INFINITY; n];
+    let mut heap = BinaryHeap::new();
+    for i in inner {
+        current[i] = area(points[prev[i]], points[i], points[next[i]]);
+        heap.push(Candidate { area: current[i], index: i });
+    }
+    let mut left = n;
+    while let Some(Candidate { area: a, index: i }) = heap.pop() {
+        if a >= min_area || left <= least {
+            break;
+        }
+        if !keep[i] || a != current[i] {
+            // removed already, or its triangle changed since it was queued
+            continue;
+        }
+        keep[i] = false;
+        left -= 1;
+        let (p, q) = (prev[i], next[i]);
+        next[p] = q;
+        prev[q] = p;
+        for j in [p, q] {
+            if current[j].is_finite() {
+                // a point's area never shrinks below the one just removed, or the
+                // order of removal would no longer follow the areas
+                current[j] = area(points[prev[j]], points[j], points[next[j]]).max(a);
+                heap.push(Candidate { area: current[j], index: j });
+            }
+        }
+    }
+    keep
+}
+
+fn distance2(a: [f32; 2], b: [f32; 2]) -> f32 {
+    (b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)
+}
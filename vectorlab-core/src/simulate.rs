@@ -0,0 +1,136 @@
+use crate::gcode::ToolMove;
+
+/// How fast the simulated machine moves.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulationSettings {
+    /// Top speed with the pen down and up, in mm/s
+    pub draw_speed: f32,
+    pub travel_speed: f32,
+    /// How quickly it gets up to speed and slows down, in mm/s²
+    pub acceleration: f32,
+    /// Time to raise or lower the pen, in ms
+    pub pen_delay: f32,
+}
+
+impl Default for SimulationSettings {
+    fn default() -> Self {
+        Self { draw_speed: 25.0, travel_speed: 75.0, acceleration: 1000.0, pen_delay: 150.0 }
+    }
+}
+
+/// A tool move with its timing, speeds in mm/s.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SimulatedMove {
+    pub tool_move: ToolMove,
+    /// in mm
+    pub length: f32,
+    /// seconds from the start of the job
+    pub start: f32,
+    pub duration: f32,
+    pub entry_speed: f32,
+    pub peak_speed: f32,
+    pub exit_speed: f32,
+}
+
+/// A job played through at the machine's speeds, see [`simulate`].
+#[derive(Clone, Debug, Default)]
+pub struct Simulation {
+    pub moves: Vec<SimulatedMove>,
+    /// in seconds
+    pub duration: f32,
+    /// in mm
+    pub draw_length: f32,
+    pub travel_length: f32,
+    acceleration: f32,
+}
+
+impl Simulation {
+    /// Index of the move under way at `time` seconds, the last one after the end.
+    pub fn move_at(&self, time: f32) -> Option<usize> {
+        let after = self.moves.partition_point(|m| m.start <= time);
+        after.checked_sub(1)
+    }
+
+    /// Where the pen is at `time` seconds, with the move it is on. Before a move starts it
+    /// waits for the pen at the move's beginning.
+    pub fn position_at(&self, time: f32) -> Option<([f32; 2], usize)> {
+        let Some(i) = self.move_at(time) else { return self.moves.first().map(|m| (m.tool_move.from, 0)) };
+        let m = &self.moves[i];
+        let t = (time - m.start).clamp(0.0, m.duration);
+        let a = self.acceleration;
+        let t1 = (m.peak_speed - m.entry_speed) / a;
+        let t3 = (m.peak_speed - m.exit_speed) / a;
+        let d1 = (m.entry_speed + m.peak_speed) * 0.5 * t1;
+        let t2 = (m.duration - t1 - t3).max(0.0);
+        let travelled = if t < t1 {
+            m.entry_speed * t + a * t * t * 0.5
+        } else if t < t1 + t2 {
+            d1 + m.peak_speed * (t - t1)
+        } else {
+            let t = t - t1 - t2;
+            d1 + m.peak_speed * t2 + m.peak_speed * t - a * t * t * 0.5
+        };
+        let f = if m.length > 0.0 { (travelled / m.length).clamp(0.0, 1.0) } else { 1.0 };
+        let [from, to] = [m.tool_move.from, m.tool_move.to];
+        Some(([from[0] + (to[0] - from[0]) * f, from[1] + (to[1] - from[1]) * f], i))
+    }
+}
+
+/// Time `moves` take on a machine that speeds up and slows down at a constant rate, slows
+/// down for corners, stops to raise or lower the pen, and never outruns what it can brake
+/// for. `mm_per_unit` scales the document coordinates of the moves.
+pub fn simulate(moves: &[ToolMove], mm_per_unit: [f32; 2], settings: &SimulationSettings) -> Simulation {
+    let a = settings.acceleration.max(1.0);
+    let scaled = |m: &ToolMove| [(m.to[0] - m.from[0]) * mm_per_unit[0], (m.to[1] - m.from[1]) * mm_per_unit[1]];
+    let lengths: Vec<f32> = moves.iter().map(|m| scaled(m)[0].hypot(scaled(m)[1])).collect();
+    let top = |m: &ToolMove| if m.cutting { settings.draw_speed } else { settings.travel_speed }.max(0.1);
+
+    // speeds where the moves meet: none when the pen changes, less the sharper the corner
+    let n = moves.len();
+    let mut junction = vec![0.0f32; n + 1];
+    for i in 1..n {
+        let (p, q) = (&moves[i - 1], &moves[i]);
+        if p.cutting != q.cutting || lengths[i - 1] == 0.0 || lengths[i] == 0.0 {
+            continue;
+        }
+        let (u, v) = (scaled(p), scaled(q));
+        let cos = (u[0] * v[0] + u[1] * v[1]) / (lengths[i - 1] * lengths[i]);
+        junction[i] = top(p).min(top(q)) * cos.max(0.0);
+    }
+    // as fast as braking for what comes and speeding up from what was allow
+    for i in (0..n).rev() {
+        junction[i] = junction[i].min((junction[i + 1].powi(2) + 2.0 * a * lengths[i]).sqrt());
+    }
+    for i in 0..n {
+        junction[i + 1] = junction[i + 1].min((junction[i].powi(2) + 2.0 * a * lengths[i]).sqrt());
+    }
+
+    let pen_delay = settings.pen_delay.max(0.0) / 1000.0;
+    let mut simulation = Simulation { acceleration: a, ..Default::default() };
+    let mut time = 0.0;
+    let mut pen_down = false;
+    for (i, m) in moves.iter().enumerate() {
+        if m.cutting != pen_down {
+            time += pen_delay;
+            pen_down = m.cutting;
+        }
+        let (length, entry, exit) = (lengths[i], junction[i], junction[i + 1]);
+        let peak = top(m).min(((2.0 * a * length + entry * entry + exit * exit) * 0.5).sqrt()).max(entry.max(exit));
+        let accelerating = (peak * peak - entry * entry) / (2.0 * a);
+        let braking = (peak * peak - exit * exit) / (2.0 * a);
+        let cruising = (length - accelerating - braking).max(0.0);
+        let duration = (peak - entry) / a + (peak - exit) / a + if peak > 0.0 { cruising / peak } else { 0.0 };
+        simulation.moves.push(SimulatedMove { tool_move: *m, length, start: time, duration, entry_speed: entry, peak_speed: peak, exit_speed: exit });
+        time += duration;
+        if m.cutting {
+            simulation.draw_length += length;
+        } else {
+            simulation.travel_length += length;
+        }
+    }
+    if pen_down {
+        time += pen_delay;
+    }
+    simulation.duration = time;
+    simulation
+}
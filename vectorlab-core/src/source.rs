@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use resvg::usvg::roxmltree;
+
+use crate::document::Document;
+
+/// Start of the ids given to elements that have none, see [`tag_elements`].
+const GENERATED_ID: &str = "__vectorlab_src_";
+
+/// usvg keeps nothing of the source but the `id` attributes. To find out where each element
+/// of the converted document came from, every element without an id gets a generated one.
+/// Returns the source to hand to usvg instead of `svg`, and where each element is in `svg`
+/// by id.
+pub(crate) fn tag_elements(svg: &str, xml: &roxmltree::Document) -> (String, HashMap<String, SourceElement>) {
+    let mut tagged = String::with_capacity(svg.len() + svg.len() / 8);
+    let mut ranges = HashMap::new();
+    let mut copied = 0;
+    for node in xml.descendants().filter(|n| n.is_element()) {
+        let source = SourceElement { range: node.range(), class: node.attribute("class").unwrap_or_default().to_string() };
+        if let Some(id) = node.attribute("id") {
+            ranges.entry(id.to_string()).or_insert(source);
+            continue;
+        }
+        // elements expanded from DTD entities point into the DTD, leave them alone
+        let Some(name_len) = svg.get(source.range.start..).filter(|s| s.starts_with('<')).map(|s| {
+            s[1..].find(|c: char| c.is_whitespace() || c == '/' || c == '>').unwrap_or(s.len() - 1)
+        }) else {
+            continue;
+        };
+        let insert_at = source.range.start + 1 + name_len;
+        if insert_at < copied {
+            continue;
+        }
+        let id = format!("{}{}", GENERATED_ID, ranges.len());
+        tagged.push_str(&svg[copied..insert_at]);
+        tagged.push_str(&format!(r#" id="{}""#, id));
+        copied = insert_at;
+        ranges.insert(id, source);
+    }
+    tagged.push_str(&svg[copied..]);
+    (tagged, ranges)
+}
+
+/// What usvg drops of an element.
+pub(crate) struct SourceElement {
+    range: Range<usize>,
+    class: String,
+}
+
+/// Set the source ranges and classes of the elements converted from the tagged source and
+/// take the generated ids out again, so they are neither shown nor saved.
+pub(crate) fn attach_sources(doc: &mut Document, ranges: &HashMap<String, SourceElement>) {
+    for element in &mut doc.elements {
+        if let Some(source) = ranges.get(&element.source_id) {
+            element.source_range = Some(source.range.clone());
+            element.source_class = source.class.clone();
+        }
+        if element.source_id.starts_with(GENERATED_ID) {
+            element.source_id.clear();
+        }
+    }
+}
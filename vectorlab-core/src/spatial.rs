@@ -0,0 +1,131 @@
+use std::ops::Range;
+
+use resvg::usvg::Transform;
+
+use crate::document::{bbox_of, transform_point, Document, ElementId, ElementKind};
+
+/// Children per tree node.
+const NODE_SIZE: usize = 16;
+
+/// A visible path or image with what drawing it needs from the walk down to it.
+#[derive(Clone, Debug)]
+pub struct IndexEntry {
+    pub id: ElementId,
+    /// position in paint order
+    pub order: usize,
+    /// document coordinates, including the stroke
+    pub bbox: [f32; 4],
+    pub transform: Transform,
+    pub opacity: f32,
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    bbox: [f32; 4],
+    // entries for the lowest level, nodes of the level below otherwise
+    children: Range<usize>,
+}
+
+/// Bounding boxes of the visible paths and images in a packed R-tree (sort-tile-recursive),
+/// for finding what is on screen without walking the whole document.
+#[derive(Clone, Debug, Default)]
+pub struct SpatialIndex {
+    revision: u64,
+    entries: Vec<IndexEntry>,
+    // levels[0] groups the entries, the last level is the root
+    levels: Vec<Vec<Node>>,
+}
+
+impl SpatialIndex {
+    pub fn build(doc: &Document) -> Self {
+        let mut entries = vec![];
+        doc.walk(|id, element, ts, opacity| {
+            let points: Vec<[f32; 2]> = match &element.kind {
+                // the meshes cover the stroke width and are cut to the clip
+                ElementKind::Path(path) => path.fill_vertices.iter().chain(&path.stroke_vertices).map(|&p| transform_point(&ts, p)).collect(),
+                ElementKind::Image { rect: [x, y, w, h], .. } => {
+                    [[*x, *y], [x + w, *y], [x + w, y + h], [*x, y + h]].map(|p| transform_point(&ts, p)).to_vec()
+                }
+                _ => return,
+            };
+            if let Some(bbox) = bbox_of(points.iter()) {
+                entries.push(IndexEntry { id, order: entries.len(), bbox, transform: ts, opacity });
+            }
+        });
+
+        let mut levels = vec![pack(&mut entries, |e| e.bbox)];
+        while levels.last().is_some_and(|l| l.len() > 1) {
+            // sorting a level moves its nodes, their ranges into the level below stay valid
+            let mut below = levels.pop().unwrap();
+            let above = pack(&mut below, |n| n.bbox);
+            levels.push(below);
+            levels.push(above);
+        }
+        Self { revision: doc.revision, entries, levels }
+    }
+
+    /// Rebuild if `doc` changed since the index was built.
+    pub fn update(&mut self, doc: &Document) {
+        if self.revision != doc.revision {
+            *self = Self::build(doc);
+        }
+    }
+
+    /// Bounding box of all entries, strokes included, None for an empty document.
+    pub fn bbox(&self) -> Option<[f32; 4]> {
+        let top = self.levels.last()?;
+        top.iter().map(|n| n.bbox).reduce(|a, b| [a[0].min(b[0]), a[1].min(b[1]), a[2].max(b[2]), a[3].max(b[3])])
+    }
+
+    /// Entries whose bounding boxes overlap `rect` ([min_x, min_y, max_x, max_y] in document
+    /// coordinates), in paint order.
+    pub fn query(&self, rect: [f32; 4]) -> Vec<&IndexEntry> {
+        let mut found = vec![];
+        if let Some(top) = self.levels.len().checked_sub(1) {
+            for node in &self.levels[top] {
+                self.query_node(top, node, rect, &mut found);
+            }
+        }
+        found.sort_by_key(|e| e.order);
+        found
+    }
+
+    fn query_node<'a>(&'a self, level: usize, node: &Node, rect: [f32; 4], found: &mut Vec<&'a IndexEntry>) {
+        if !overlaps(node.bbox, rect) {
+            return;
+        }
+        if level == 0 {
+            found.extend(self.entries[node.children.clone()].iter().filter(|e| overlaps(e.bbox, rect)));
+        } else {
+            for child in &self.levels[level - 1][node.children.clone()] {
+                self.query_node(level - 1, child, rect, found);
+            }
+        }
+    }
+}
+
+/// Sort `items` into sort-tile-recursive order: vertical slices by x, runs of NODE_SIZE by y
+/// within them. Returns a parent node for each run.
+fn pack<T>(items: &mut [T], bbox: impl Fn(&T) -> [f32; 4]) -> Vec<Node> {
+    let center = |item: &T, axis: usize| {
+        let b = bbox(item);
+        b[axis] + b[axis + 2]
+    };
+    items.sort_by(|a, b| center(a, 0).total_cmp(&center(b, 0)));
+    let slices = (items.len().div_ceil(NODE_SIZE) as f32).sqrt().ceil() as usize;
+    for slice in items.chunks_mut(slices.max(1) * NODE_SIZE) {
+        slice.sort_by(|a, b| center(a, 1).total_cmp(&center(b, 1)));
+    }
+    items
+        .chunks(NODE_SIZE)
+        .enumerate()
+        .map(|(i, run)| {
+            let bbox = run.iter().map(&bbox).reduce(|a, b| [a[0].min(b[0]), a[1].min(b[1]), a[2].max(b[2]), a[3].max(b[3])]).unwrap();
+            Node { bbox, children: i * NODE_SIZE..i * NODE_SIZE + run.len() }
+        })
+        .collect()
+}
+
+fn overlaps(a: [f32; 4], b: [f32; 4]) -> bool {
+    a[0] <= b[2] && b[0] <= a[2] && a[1] <= b[3] && b[1] <= a[3]
+}
@@ -0,0 +1,282 @@
+use std::fmt::Write;
+
+use rayon::prelude::*;
+
+use crate::image::RasterImage;
+use crate::style::Color;
+
+/// Cells of the grid the points are relaxed on, larger images are averaged down to it.
+const MAX_CELLS: f32 = 250_000.0;
+/// How far ahead the tour looks for crossings to undo.
+const TWO_OPT_WINDOW: usize = 64;
+
+/// How [`stipple`] places dots on an image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StippleSettings {
+    pub points: usize,
+    /// Rounds of moving each point to the weighted center of its Voronoi cell
+    pub iterations: u32,
+    /// Diameter of the dots, or width of the line through them, in mm
+    pub dot_size: f32,
+    /// One line through all the points instead of dots
+    pub connect: bool,
+}
+
+impl Default for StippleSettings {
+    fn default() -> Self {
+        Self { points: 2000, iterations: 30, dot_size: 0.5, connect: false }
+    }
+}
+
+/// Points stippled on an image, in its pixels, in the order a pen should visit them.
+#[derive(Clone, Debug, Default)]
+pub struct Stipple {
+    pub points: Vec<[f32; 2]>,
+    /// of the image, in pixels
+    pub size: [u32; 2],
+}
+
+/// xorshift, so the same image and settings always give the same stipple.
+struct Rng(u64);
+
+impl Rng {
+    /// in 0..1
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+fn distance2(a: [f32; 2], b: [f32; 2]) -> f32 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)
+}
+
+/// Points sorted into square cells, for finding the nearest one.
+struct Buckets {
+    cell: f32,
+    columns: usize,
+    rows: usize,
+    cells: Vec<Vec<u32>>,
+}
+
+impl Buckets {
+    fn new(points: &[[f32; 2]], width: f32, height: f32, cell: f32) -> Self {
+        let columns = (width / cell).ceil().max(1.0) as usize;
+        let rows = (height / cell).ceil().max(1.0) as usize;
+        let mut buckets = Self { cell, columns, rows, cells: vec![vec![]; columns * rows] };
+        for (i, &p) in points.iter().enumerate() {
+            let c = buckets.cell_of(p);
+            buckets.cells[c].push(i as u32);
+        }
+        buckets
+    }
+
+    fn cell_of(&self, p: [f32; 2]) -> usize {
+        let x = ((p[0] / self.cell).max(0.0) as usize).min(self.columns - 1);
+        let y = ((p[1] / self.cell).max(0.0) as usize).min(self.rows - 1);
+        y * self.columns + x
+    }
+
+    /// Index of the point nearest to `p`, looking in rings of cells around it until none can
+    /// be nearer.
+    fn nearest(&self, points: &[[f32; 2]], p: [f32; 2]) -> Option<usize> {
+        let c = self.cell_of(p);
+        let (cx, cy) = ((c % self.columns) as i64, (c / self.columns) as i64);
+        let mut best: Option<(f32, usize)> = None;
+        for r in 0..=self.columns.max(self.rows) as i64 {
+            for y in cy - r..=cy + r {
+                for x in cx - r..=cx + r {
+                    let on_ring = (x - cx).abs() == r || (y - cy).abs() == r;
+                    if !on_ring || x < 0 || y < 0 || x >= self.columns as i64 || y >= self.rows as i64 {
+                        continue;
+                    }
+                    for &i in &self.cells[y as usize * self.columns + x as usize] {
+                        let d = distance2(points[i as usize], p);
+                        if best.is_none_or(|(b, _)| d < b) {
+                            best = Some((d, i as usize));
+                        }
+                    }
+                }
+            }
+            // anything further out is at least r cells away
+            if best.is_some_and(|(b, _)| b <= (r as f32 * self.cell).powi(2)) {
+                break;
+            }
+        }
+        best.map(|(_, i)| i)
+    }
+
+    fn remove(&mut self, points: &[[f32; 2]], i: usize) {
+        let c = self.cell_of(points[i]);
+        self.cells[c].retain(|&j| j as usize != i);
+    }
+}
+
+/// Dots whose density follows the darkness of `image`, by weighted Voronoi stippling: points
+/// scattered by darkness, then moved to the darkness-weighted centers of their Voronoi cells
+/// a few times so they spread out evenly. Ordered nearest neighbor first and uncrossed, so a
+/// pen has little travel between the dots, or makes a single line through them.
+pub fn stipple(image: &RasterImage, settings: &StippleSettings) -> Stipple {
+    let (w, h) = (image.width as usize, image.height as usize);
+    let mut result = Stipple { points: vec![], size: [image.width, image.height] };
+    if w == 0 || h == 0 || settings.points == 0 {
+        return result;
+    }
+    // darkness averaged down to the grid
+    let scale = (MAX_CELLS / (w * h) as f32).sqrt().min(1.0);
+    let (gw, gh) = (((w as f32 * scale).round() as usize).max(1), ((h as f32 * scale).round() as usize).max(1));
+    let mut density = vec![0.0f32; gw * gh];
+    let mut counts = vec![0u32; gw * gh];
+    for (i, l) in image.luminance().into_iter().enumerate() {
+        let c = (i / w * gh / h) * gw + (i % w * gw / w);
+        density[c] += 1.0 - l.clamp(0.0, 1.0);
+        counts[c] += 1;
+    }
+    for (d, &n) in density.iter_mut().zip(&counts) {
+        *d /= n.max(1) as f32;
+    }
+    let total: f64 = density.iter().map(|&d| d as f64).sum();
+    if total <= 0.0 {
+        return result;
+    }
+
+    // scattered by darkness, through its running sum
+    let mut running = Vec::with_capacity(density.len());
+    let mut sum = 0.0f64;
+    for &d in &density {
+        sum += d as f64;
+        running.push(sum);
+    }
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    let mut points: Vec<[f32; 2]> = (0..settings.points)
+        .map(|_| {
+            let target = rng.next() as f64 * total;
+            let c = running.partition_point(|&s| s < target).min(density.len() - 1);
+            [(c % gw) as f32 + rng.next(), (c / gw) as f32 + rng.next()]
+        })
+        .collect();
+
+    let cell = ((gw * gh) as f32 / points.len() as f32).sqrt().max(1.0);
+    for _ in 0..settings.iterations {
+        let buckets = Buckets::new(&points, gw as f32, gh as f32, cell);
+        // darkness and its moments over the cells of each point
+        let sums = (0..gh)
+            .into_par_iter()
+            .fold(
+                || vec![[0.0f64; 3]; points.len()],
+                |mut sums, y| {
+                    for x in 0..gw {
+                        let d = density[y * gw + x];
+                        if d <= 0.0 {
+                            continue;
+                        }
+                        let p = [x as f32 + 0.5, y as f32 + 0.5];
+                        if let Some(i) = buckets.nearest(&points, p) {
+                            let s = &mut sums[i];
+                            s[0] += d as f64;
+                            s[1] += (d * p[0]) as f64;
+                            s[2] += (d * p[1]) as f64;
+                        }
+                    }
+                    sums
+                },
+            )
+            .reduce(
+                || vec![[0.0f64; 3]; points.len()],
+                |mut a, b| {
+                    for (a, b) in a.iter_mut().zip(&b) {
+                        a[0] += b[0];
+                        a[1] += b[1];
+                        a[2] += b[2];
+                    }
+                    a
+                },
+            );
+        for (p, s) in points.iter_mut().zip(&sums) {
+            if s[0] > 0.0 {
+                *p = [(s[1] / s[0]) as f32, (s[2] / s[0]) as f32];
+            }
+        }
+    }
+
+    let mut tour = nearest_neighbor_tour(&points, gw as f32, gh as f32, cell);
+    uncross(&mut tour);
+    result.points = tour.into_iter().map(|p| [p[0] / gw as f32 * w as f32, p[1] / gh as f32 * h as f32]).collect();
+    result
+}
+
+/// `points` ordered by going to the nearest one not visited yet, from the top left corner.
+fn nearest_neighbor_tour(points: &[[f32; 2]], width: f32, height: f32, cell: f32) -> Vec<[f32; 2]> {
+    let mut buckets = Buckets::new(points, width, height, cell);
+    let mut tour = Vec::with_capacity(points.len());
+    let mut at = [0.0, 0.0];
+    while let Some(i) = buckets.nearest(points, at) {
+        buckets.remove(points, i);
+        at = points[i];
+        tour.push(at);
+    }
+    tour
+}
+
+/// 2-opt on nearby stretches of an open tour: reverse the part between two edges wherever
+/// that makes it shorter, which undoes the crossings a greedy tour leaves.
+fn uncross(tour: &mut [[f32; 2]]) {
+    let n = tour.len();
+    let d = |a: [f32; 2], b: [f32; 2]| distance2(a, b).sqrt();
+    for _ in 0..8 {
+        let mut improved = false;
+        for i in 0..n.saturating_sub(3) {
+            for j in i + 2..(i + TWO_OPT_WINDOW).min(n - 1) {
+                let (a, b, c, e) = (tour[i], tour[i + 1], tour[j], tour[j + 1]);
+                if d(a, c) + d(b, e) < d(a, b) + d(c, e) - 1e-4 {
+                    tour[i + 1..=j].reverse();
+                    improved = true;
+                }
+            }
+        }
+        if !improved {
+            break;
+        }
+    }
+}
+
+impl Stipple {
+    /// The points as an SVG of one group with the id `name`, `width` mm wide: dots of
+    /// `ink`, or a line through them if `settings` connects them.
+    pub fn svg(&self, name: &str, ink: Color, width: f32, settings: &StippleSettings) -> String {
+        let [w, h] = self.size;
+        let mm = width / w.max(1) as f32;
+        let color = format!("rgb({},{},{})", ink.r, ink.g, ink.b);
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.3}mm" height="{:.3}mm" viewBox="0 0 {} {}">"#,
+            width,
+            h as f32 * mm,
+            w,
+            h
+        );
+        let size = settings.dot_size / mm;
+        if settings.connect {
+            let mut d = String::new();
+            for (i, p) in self.points.iter().enumerate() {
+                let _ = write!(d, "{}{:.2} {:.2}", if i == 0 { "M" } else { " L" }, p[0], p[1]);
+            }
+            let _ = writeln!(
+                svg,
+                r#"<path id="{}" d="{}" fill="none" stroke="{}" stroke-width="{:.3}" stroke-linecap="round" stroke-linejoin="round"/>"#,
+                name, d, color, size
+            );
+        } else {
+            let _ = writeln!(svg, r#"<g id="{}" fill="{}">"#, name, color);
+            for p in &self.points {
+                let _ = writeln!(svg, r#"<circle cx="{:.2}" cy="{:.2}" r="{:.3}"/>"#, p[0], p[1], size * 0.5);
+            }
+            svg.push_str("</g>\n");
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
@@ -0,0 +1,147 @@
+use std::sync::Arc;
+
+use resvg::usvg;
+
+/// 8-bit RGBA, not premultiplied.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+    pub const GRAY: Color = Color::rgb(160, 160, 160);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b, a: 255 }
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// `alpha` in 0..1
+    pub fn with_alpha(self, alpha: f32) -> Self {
+        Self { a: (alpha.clamp(0.0, 1.0) * 255.0).round() as u8, ..self }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FillRule {
+    NonZero,
+    EvenOdd,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineCap {
+    Butt,
+    Round,
+    Square,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineJoin {
+    Miter,
+    Round,
+    Bevel,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpreadMethod {
+    Pad,
+    Reflect,
+    Repeat,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum GradientShape {
+    Linear { x1: f32, y1: f32, x2: f32, y2: f32 },
+    Radial { cx: f32, cy: f32, r: f32, fx: f32, fy: f32 },
+}
+
+#[derive(Clone, Debug)]
+pub struct Gradient {
+    pub shape: GradientShape,
+    /// (offset, unmultiplied rgba in 0..1), sorted by offset
+    pub stops: Vec<(f32, [f32; 4])>,
+    pub spread: SpreadMethod,
+    /// Maps document coordinates into the gradient's own coordinate system.
+    pub to_gradient: usvg::Transform,
+}
+
+impl Gradient {
+    pub fn color_at(&self, p: [f32; 2]) -> Color {
+        let mut pt = usvg::tiny_skia_path::Point::from_xy(p[0], p[1]);
+        self.to_gradient.map_point(&mut pt);
+
+        let t = match self.shape {
+            GradientShape::Linear { x1, y1, x2, y2 } => {
+                let (dx, dy) = (x2 - x1, y2 - y1);
+                let len2 = dx * dx + dy * dy;
+                if len2 > 0.0 { ((pt.x - x1) * dx + (pt.y - y1) * dy) / len2 } else { 1.0 }
+            }
+            GradientShape::Radial { cx, cy, r, fx, fy } => {
+                // t is the fraction of the way from the focal point to the circle, along the ray through pt
+                let (px, py) = (pt.x - fx, pt.y - fy);
+                let dist = (px * px + py * py).sqrt();
+                if dist == 0.0 {
+                    0.0
+                } else {
+                    let (dx, dy) = (px / dist, py / dist);
+                    let (ox, oy) = (fx - cx, fy - cy);
+                    let b = dx * ox + dy * oy;
+                    let c = ox * ox + oy * oy - r * r;
+                    let s = -b + (b * b - c).max(0.0).sqrt();
+                    if s > 0.0 { dist / s } else { 1.0 }
+                }
+            }
+        };
+
+        let t = match self.spread {
+            SpreadMethod::Pad => t.clamp(0.0, 1.0),
+            SpreadMethod::Repeat => t.rem_euclid(1.0),
+            SpreadMethod::Reflect => {
+                let m = t.rem_euclid(2.0);
+                if m > 1.0 { 2.0 - m } else { m }
+            }
+        };
+
+        let rgba = match self.stops.iter().position(|&(offset, _)| offset >= t) {
+            None => self.stops.last().map_or([0.0; 4], |s| s.1),
+            Some(0) => self.stops[0].1,
+            Some(i) => {
+                let (o0, c0) = self.stops[i - 1];
+                let (o1, c1) = self.stops[i];
+                let f = if o1 > o0 { (t - o0) / (o1 - o0) } else { 1.0 };
+                [0, 1, 2, 3].map(|k| c0[k] + (c1[k] - c0[k]) * f)
+            }
+        };
+        let [r, g, b, a] = rgba.map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8);
+        Color::rgba(r, g, b, a)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Paint {
+    Solid(Color),
+    Gradient(Arc<Gradient>),
+}
+
+/// Paint information carried over from `usvg::Path`.
+/// Fill and stroke paints already include their own fill-opacity / stroke-opacity,
+/// group opacity lives on the document elements.
+#[derive(Clone, Debug)]
+pub struct Style {
+    pub fill: Option<Paint>,
+    pub fill_rule: FillRule,
+    pub stroke: Option<Paint>,
+    /// in element coordinates
+    pub stroke_width: f32,
+    pub line_cap: LineCap,
+    pub line_join: LineJoin,
+    pub miter_limit: f32,
+}
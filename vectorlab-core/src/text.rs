@@ -0,0 +1,181 @@
+use std::any::Any;
+use std::fmt::Write;
+
+use crate::diagnostics::LoadError;
+use crate::document::{Document, Element, ElementId, ElementKind};
+use crate::edit::EditCommand;
+use crate::loader::{load_str, LoadOptions};
+use crate::saver::escape;
+use crate::style::Color;
+
+/// Which end of the lines sits at the position, SVG's `text-anchor`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextAnchor {
+    Start,
+    Middle,
+    End,
+}
+
+impl TextAnchor {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Start => "start",
+            Self::Middle => "middle",
+            Self::End => "end",
+        }
+    }
+}
+
+/// How a text made in VectorLab is set, lengths in the coordinates of its parent.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextLayout {
+    pub family: String,
+    pub size: f32,
+    pub anchor: TextAnchor,
+    /// of the baseline of the first line
+    pub position: [f32; 2],
+    pub color: Color,
+}
+
+/// Lines apart, in font sizes.
+const LINE_HEIGHT: f32 = 1.2;
+
+/// `content` as a `<text>` element set with `layout`, a `<tspan>` per line. `attrs` go into
+/// the start tag.
+pub(crate) fn text_markup(content: &str, layout: &TextLayout, attrs: &str) -> String {
+    let [x, y] = layout.position;
+    let c = layout.color;
+    let mut out = format!(
+        r##"<text{attrs} x="{x}" y="{y}" font-family="{}" font-size="{}" text-anchor="{}" fill="#{:02x}{:02x}{:02x}""##,
+        escape(&layout.family),
+        layout.size,
+        layout.anchor.name(),
+        c.r,
+        c.g,
+        c.b
+    );
+    if c.a < 255 {
+        let _ = write!(out, r#" fill-opacity="{}""#, c.a as f32 / 255.0);
+    }
+    out.push_str(r#" xml:space="preserve">"#);
+    for (i, line) in content.lines().enumerate() {
+        let dy = if i == 0 { 0.0 } else { layout.size * LINE_HEIGHT };
+        let _ = write!(out, r#"<tspan x="{x}" dy="{dy}">{}</tspan>"#, escape(line));
+    }
+    out.push_str("</text>");
+    out
+}
+
+/// `content` set with `layout` through the same text-to-path conversion as loaded files, as
+/// a document of its own, with the text element if there was anything to set.
+fn typeset(content: &str, layout: &TextLayout, opts: &LoadOptions) -> Result<(Document, Option<ElementId>), LoadError> {
+    let svg = format!(r#"<svg xmlns="http://www.w3.org/2000/svg">{}</svg>"#, text_markup(content, layout, ""));
+    let opts = LoadOptions { strict: false, progress: None, ..opts.clone() };
+    let doc = load_str(&svg, &opts)?;
+    let text = doc.descendants(doc.root).into_iter().find(|&id| matches!(doc.get(id).kind, ElementKind::Text { .. }));
+    Ok((doc, text))
+}
+
+/// A new text on top of `parent`. It is typeset right away, so a missing font or a broken
+/// layout shows up before anything changes.
+pub struct AddText {
+    parent: ElementId,
+    after: Option<ElementId>,
+    content: String,
+    layout: TextLayout,
+    // until the first apply, then the id it was given
+    typeset: Option<(Document, Option<ElementId>)>,
+    id: Option<ElementId>,
+}
+
+impl AddText {
+    pub fn new(doc: &Document, parent: ElementId, content: &str, layout: TextLayout, opts: &LoadOptions) -> Result<Self, LoadError> {
+        let typeset = typeset(content, &layout, opts)?;
+        let after = doc.get(parent).children.last().copied();
+        Ok(Self { parent, after, content: content.to_string(), layout, typeset: Some(typeset), id: None })
+    }
+
+    /// Id of the new text once applied.
+    pub fn id(&self) -> Option<ElementId> {
+        self.id
+    }
+}
+
+impl EditCommand for AddText {
+    fn apply(&mut self, doc: &mut Document) {
+        if let Some((glyphs, text)) = self.typeset.take() {
+            let id = match text {
+                Some(text) => doc.copy_from(&glyphs, text, self.parent),
+                None => doc.add(self.parent, Element::new(ElementKind::Text { content: String::new(), layout: None })),
+            };
+            doc.get_mut(id).kind = ElementKind::Text { content: self.content.clone(), layout: Some(self.layout.clone()) };
+            doc.detach(id);
+            self.id = Some(id);
+        }
+        let Some(id) = self.id else { return };
+        let siblings = &doc.get(self.parent).children;
+        let index = self.after.and_then(|after| siblings.iter().position(|&c| c == after)).map_or(0, |i| i + 1);
+        doc.attach(id, self.parent, index);
+    }
+
+    fn revert(&mut self, doc: &mut Document) {
+        if let Some(id) = self.id {
+            doc.detach(id);
+        }
+    }
+
+    fn name(&self) -> &str {
+        "Add text"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// New content or layout for a text, with the glyphs set again. The text keeps its transform.
+pub struct EditText {
+    pub id: ElementId,
+    old: (ElementKind, Vec<ElementId>),
+    new: (ElementKind, Vec<ElementId>),
+    // until the first apply
+    typeset: Option<(Document, Option<ElementId>)>,
+}
+
+impl EditText {
+    pub fn new(doc: &Document, id: ElementId, content: &str, layout: TextLayout, opts: &LoadOptions) -> Result<Self, LoadError> {
+        let typeset = typeset(content, &layout, opts)?;
+        let element = doc.get(id);
+        let new = ElementKind::Text { content: content.to_string(), layout: Some(layout) };
+        Ok(Self { id, old: (element.kind.clone(), element.children.clone()), new: (new, vec![]), typeset: Some(typeset) })
+    }
+}
+
+impl EditCommand for EditText {
+    fn apply(&mut self, doc: &mut Document) {
+        if let Some((glyphs, text)) = self.typeset.take() {
+            let before = doc.get(self.id).children.len();
+            for &child in text.map_or(&[][..], |text| &glyphs.get(text).children[..]) {
+                doc.copy_from(&glyphs, child, self.id);
+            }
+            self.new.1 = doc.get(self.id).children[before..].to_vec();
+        }
+        let element = doc.get_mut(self.id);
+        element.kind = self.new.0.clone();
+        element.children = self.new.1.clone();
+    }
+
+    fn revert(&mut self, doc: &mut Document) {
+        let element = doc.get_mut(self.id);
+        element.kind = self.old.0.clone();
+        element.children = self.old.1.clone();
+    }
+
+    fn name(&self) -> &str {
+        "Edit text"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
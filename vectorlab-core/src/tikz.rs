@@ -0,0 +1,166 @@
+use std::fmt::Write;
+
+use crate::document::{transform_point, transform_scale, Document, ElementKind, Segment};
+use crate::style::{Color, FillRule, LineCap, LineJoin, Paint};
+use crate::units::Unit;
+
+/// How [`to_tikz`] writes a drawing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TikzSettings {
+    /// Decimals of the coordinates and widths
+    pub precision: usize,
+    /// Length in the paper of a millimeter of the page, 1 for the real size
+    pub scale: f32,
+    /// A whole document of the standalone class instead of just the picture, to compile on
+    /// its own
+    pub standalone: bool,
+}
+
+impl Default for TikzSettings {
+    fn default() -> Self {
+        Self { precision: 2, scale: 1.0, standalone: false }
+    }
+}
+
+/// `value` with at most `precision` decimals, without trailing zeros.
+fn number(value: f32, precision: usize) -> String {
+    let text = format!("{:.*}", precision, value);
+    let text = if text.contains('.') { text.trim_end_matches('0').trim_end_matches('.') } else { &text };
+    if text == "-0" {
+        "0".to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+/// Solid color of a paint, gradients as the color halfway.
+fn paint_color(paint: &Paint) -> Color {
+    match paint {
+        Paint::Solid(color) => *color,
+        Paint::Gradient(gradient) => {
+            let stops = &gradient.stops;
+            let middle = stops.iter().find(|(offset, _)| *offset >= 0.5).or(stops.last()).map_or([0.0, 0.0, 0.0, 1.0], |(_, rgba)| *rgba);
+            let [r, g, b, a] = middle.map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8);
+            Color::rgba(r, g, b, a)
+        }
+    }
+}
+
+/// The visible paths of `doc` as a TikZ picture, curves kept, in mm of the page with y down
+/// like SVG. Colors are defined by name first, images are left out with a comment.
+pub fn to_tikz(doc: &Document, settings: &TikzSettings) -> String {
+    let to_page = doc.viewport_transform();
+    let p = settings.precision;
+    let mut colors: Vec<Color> = vec![];
+    let mut paths = String::new();
+    let mut images = 0;
+    doc.walk(|_, element, ts, opacity| {
+        let path = match &element.kind {
+            ElementKind::Path(path) => path,
+            ElementKind::Image { .. } => {
+                images += 1;
+                return;
+            }
+            _ => return,
+        };
+        let style = &path.style;
+        let stroked = style.stroke.is_some() && style.stroke_width > 0.0;
+        if (style.fill.is_none() && !stroked) || path.segments.is_empty() {
+            return;
+        }
+        let ts = to_page.pre_concat(ts);
+        let mut color_name = |paint: &Paint| {
+            let color = paint_color(paint);
+            let opaque = Color { a: 255, ..color };
+            let i = colors.iter().position(|&c| c == opaque).unwrap_or_else(|| {
+                colors.push(opaque);
+                colors.len() - 1
+            });
+            (format!("c{}", i + 1), color.a as f32 / 255.0 * opacity)
+        };
+        let mut options = vec![];
+        if let Some(fill) = &style.fill {
+            let (name, alpha) = color_name(fill);
+            options.push(format!("fill={}", name));
+            if alpha < 1.0 {
+                options.push(format!("fill opacity={}", number(alpha, 3)));
+            }
+            if style.fill_rule == FillRule::EvenOdd {
+                options.push("even odd rule".to_string());
+            }
+        }
+        if let Some(stroke) = style.stroke.as_ref().filter(|_| stroked) {
+            let (name, alpha) = color_name(stroke);
+            options.push(format!("draw={}", name));
+            if alpha < 1.0 {
+                options.push(format!("draw opacity={}", number(alpha, 3)));
+            }
+            let width = Unit::Px.to_mm(style.stroke_width * transform_scale(&ts)) * settings.scale;
+            options.push(format!("line width={}mm", number(width, p.max(2))));
+            match style.line_cap {
+                LineCap::Butt => {}
+                LineCap::Round => options.push("line cap=round".to_string()),
+                LineCap::Square => options.push("line cap=rect".to_string()),
+            }
+            match style.line_join {
+                LineJoin::Miter => {}
+                LineJoin::Round => options.push("line join=round".to_string()),
+                LineJoin::Bevel => options.push("line join=bevel".to_string()),
+            }
+        }
+
+        let at = |q: [f32; 2]| {
+            let [x, y] = transform_point(&ts, q).map(|px| Unit::Px.to_mm(px));
+            format!("({}, {})", number(x, p), number(y, p))
+        };
+        let mut d = String::new();
+        let (mut current, mut start) = ([0.0; 2], [0.0; 2]);
+        for segment in &path.segments {
+            match *segment {
+                Segment::MoveTo(q) => {
+                    let _ = write!(d, " {}", at(q));
+                    (current, start) = (q, q);
+                }
+                Segment::LineTo(q) => {
+                    let _ = write!(d, " -- {}", at(q));
+                    current = q;
+                }
+                Segment::QuadTo(c, q) => {
+                    // the same curve as a cubic
+                    let c1 = [current[0] + (c[0] - current[0]) * 2.0 / 3.0, current[1] + (c[1] - current[1]) * 2.0 / 3.0];
+                    let c2 = [q[0] + (c[0] - q[0]) * 2.0 / 3.0, q[1] + (c[1] - q[1]) * 2.0 / 3.0];
+                    let _ = write!(d, " .. controls {} and {} .. {}", at(c1), at(c2), at(q));
+                    current = q;
+                }
+                Segment::CubicTo(c1, c2, q) => {
+                    let _ = write!(d, " .. controls {} and {} .. {}", at(c1), at(c2), at(q));
+                    current = q;
+                }
+                Segment::Close => {
+                    d.push_str(" -- cycle");
+                    current = start;
+                }
+            }
+        }
+        let _ = writeln!(paths, "\\path[{}]{};", options.join(", "), d);
+    });
+
+    let mut tex = String::new();
+    if settings.standalone {
+        tex.push_str("\\documentclass[tikz]{standalone}\n\\begin{document}\n");
+    }
+    let unit = number(settings.scale, 4);
+    let _ = writeln!(tex, "\\begin{{tikzpicture}}[x={}mm, y=-{}mm]", unit, unit);
+    for (i, color) in colors.iter().enumerate() {
+        let _ = writeln!(tex, "\\definecolor{{c{}}}{{HTML}}{{{:02X}{:02X}{:02X}}}", i + 1, color.r, color.g, color.b);
+    }
+    if images > 0 {
+        let _ = writeln!(tex, "% {} images left out", images);
+    }
+    tex.push_str(&paths);
+    tex.push_str("\\end{tikzpicture}\n");
+    if settings.standalone {
+        tex.push_str("\\end{document}\n");
+    }
+    tex
+}
@@ -0,0 +1,249 @@
+use std::fmt::Write;
+
+use crate::document::Segment;
+use crate::image::RasterImage;
+use crate::simplify::{simplify_polyline, SimplifyMethod};
+use crate::style::Color;
+
+/// How [`trace_bitmap`] turns the pixels of an image into outlines, the way potrace does.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceSettings {
+    /// Pixels darker than this brightness, from 0 to 1, are traced
+    pub threshold: f32,
+    /// Trace the light pixels instead
+    pub invert: bool,
+    /// Specks and holes of up to this many pixels are left out
+    pub despeckle: u32,
+    /// potrace's alphamax: 0 keeps every corner sharp, 4/3 rounds them all
+    pub smoothing: f32,
+    /// How far the outlines may stray from the pixel edges, in pixels
+    pub tolerance: f32,
+}
+
+impl Default for TraceSettings {
+    fn default() -> Self {
+        Self { threshold: 0.5, invert: false, despeckle: 2, smoothing: 1.0, tolerance: 1.0 }
+    }
+}
+
+/// Outlines traced from an image, in its pixels.
+#[derive(Clone, Debug, Default)]
+pub struct Trace {
+    /// A path for each patch of connected pixels, its outline followed by those of its holes
+    pub shapes: Vec<Vec<Segment>>,
+    /// of the image, in pixels
+    pub size: [u32; 2],
+}
+
+/// Connected pixels of the same kind.
+struct Component {
+    pixels: usize,
+    touches_border: bool,
+}
+
+/// Label the pixels equal to `value` by their connected patch, across corners too if
+/// `diagonal`. Other pixels get `u32::MAX`.
+fn components(bits: &[bool], w: usize, h: usize, value: bool, diagonal: bool) -> (Vec<u32>, Vec<Component>) {
+    let mut labels = vec![u32::MAX; bits.len()];
+    let mut found = vec![];
+    let mut stack = vec![];
+    for start in 0..bits.len() {
+        if bits[start] != value || labels[start] != u32::MAX {
+            continue;
+        }
+        let label = found.len() as u32;
+        let mut component = Component { pixels: 0, touches_border: false };
+        labels[start] = label;
+        stack.push(start);
+        while let Some(i) = stack.pop() {
+            let (x, y) = ((i % w) as i64, (i / w) as i64);
+            component.pixels += 1;
+            component.touches_border |= x == 0 || y == 0 || x == w as i64 - 1 || y == h as i64 - 1;
+            for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)] {
+                if !diagonal && dx != 0 && dy != 0 {
+                    continue;
+                }
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx >= w as i64 || ny >= h as i64 {
+                    continue;
+                }
+                let n = ny as usize * w + nx as usize;
+                if bits[n] == value && labels[n] == u32::MAX {
+                    labels[n] = label;
+                    stack.push(n);
+                }
+            }
+        }
+        found.push(component);
+    }
+    (labels, found)
+}
+
+/// The pixels to trace, without the specks and holes `settings` leaves out.
+fn bitmap(image: &RasterImage, settings: &TraceSettings) -> Vec<bool> {
+    let (w, h) = (image.width as usize, image.height as usize);
+    let mut bits: Vec<bool> = image.luminance().into_iter().map(|l| (l < settings.threshold) != settings.invert).collect();
+    let small = settings.despeckle as usize;
+    if small == 0 {
+        return bits;
+    }
+    // ink touching across corners is one patch, as the outlines join it
+    let (labels, specks) = components(&bits, w, h, true, true);
+    for (bit, &label) in bits.iter_mut().zip(&labels) {
+        if label != u32::MAX && specks[label as usize].pixels <= small {
+            *bit = false;
+        }
+    }
+    let (labels, holes) = components(&bits, w, h, false, false);
+    for (bit, &label) in bits.iter_mut().zip(&labels) {
+        if label != u32::MAX && !holes[label as usize].touches_border && holes[label as usize].pixels <= small {
+            *bit = true;
+        }
+    }
+    bits
+}
+
+/// Follow the pixel edges between ink and paper from corner `start` in direction `d`, with the
+/// ink on the left, until back there. Returns the corners where it turns, and marks the
+/// horizontal edges passed in `used`.
+fn follow(ink: &impl Fn(i64, i64) -> bool, start: (i64, i64), d: (i64, i64), used: &mut [bool], w: usize) -> Vec<[f32; 2]> {
+    let (mut p, mut d) = (start, d);
+    let first = d;
+    let mut corners = vec![];
+    loop {
+        let (dx, dy) = d;
+        if dy == 0 {
+            used[p.1 as usize * w + p.0.min(p.0 + dx) as usize] = true;
+        }
+        p = (p.0 + dx, p.1 + dy);
+        // the two pixels ahead, left and right of the way
+        let left = ink(p.0 + (dx + dy - 1) / 2, p.1 + (dy - dx - 1) / 2);
+        let right = ink(p.0 + (dx - dy - 1) / 2, p.1 + (dy + dx - 1) / 2);
+        let next = match (left, right) {
+            (true, false) => d,
+            (false, false) => (dy, -dx),
+            // blocked by ink, or ink touching the ink behind across a corner, which joins it
+            (true, true) | (false, true) => (-dy, dx),
+        };
+        if next != d {
+            corners.push([p.0 as f32, p.1 as f32]);
+        }
+        d = next;
+        if p == start && d == first {
+            return corners;
+        }
+    }
+}
+
+/// A closed outline through the midpoints of the sides of `polygon`, rounded at its vertices
+/// unless they are sharper than `smoothing` allows, as potrace fits its curves.
+fn smooth_outline(polygon: &[[f32; 2]], smoothing: f32, segments: &mut Vec<Segment>) {
+    let n = polygon.len();
+    let vertex = |i: usize| polygon[i % n];
+    let mid = |a: [f32; 2], b: [f32; 2]| [(a[0] + b[0]) * 0.5, (a[1] + b[1]) * 0.5];
+    let lerp = |a: [f32; 2], b: [f32; 2], t: f32| [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t];
+    // how far each vertex sticks out of the line between its neighbors, scaled as potrace does
+    let alphas: Vec<f32> = (0..n)
+        .map(|i| {
+            let (a, b, c) = (vertex(i + n - 1), vertex(i), vertex(i + 1));
+            let chord = [c[0] - a[0], c[1] - a[1]];
+            let denom = chord[0].abs() + chord[1].abs();
+            let dd = if denom > 0.0 { (chord[0] * (b[1] - a[1]) - chord[1] * (b[0] - a[0])).abs() / denom } else { 0.0 };
+            if dd > 1.0 {
+                (1.0 - 1.0 / dd) / 0.75
+            } else {
+                0.0
+            }
+        })
+        .collect();
+    let corner = |i: usize| alphas[i % n] >= smoothing;
+
+    segments.push(Segment::MoveTo(if corner(0) { vertex(0) } else { mid(vertex(n - 1), vertex(0)) }));
+    for (i, &alpha) in alphas.iter().enumerate() {
+        let (a, b, c) = (vertex(i + n - 1), vertex(i), vertex(i + 1));
+        if corner(i) {
+            if i > 0 {
+                segments.push(Segment::LineTo(b));
+            }
+            if i + 1 < n && !corner(i + 1) {
+                segments.push(Segment::LineTo(mid(b, c)));
+            }
+        } else {
+            let t = 0.5 + 0.5 * alpha.clamp(0.55, 1.0);
+            segments.push(Segment::CubicTo(lerp(a, b, t), lerp(c, b, t), mid(b, c)));
+        }
+    }
+    segments.push(Segment::Close);
+}
+
+/// Trace the dark pixels of `image`, or the light ones, into smooth outlines. Each patch of
+/// pixels becomes a shape with its holes, outlines and holes going opposite ways so either
+/// fill rule fills them. Patches inside holes come after the patch around them.
+pub fn trace_bitmap(image: &RasterImage, settings: &TraceSettings) -> Trace {
+    let (w, h) = (image.width as usize, image.height as usize);
+    let bits = bitmap(image, settings);
+    let (labels, patches) = components(&bits, w, h, true, true);
+    let ink = |x: i64, y: i64| x >= 0 && y >= 0 && x < w as i64 && y < h as i64 && bits[y as usize * w + x as usize];
+    let mut outlines: Vec<Vec<Vec<[f32; 2]>>> = vec![vec![]; patches.len()];
+    // the top edges of the pixels in each row, the ink ones below paper start outlines
+    let mut used = vec![false; w * (h + 1)];
+    for y in 0..h {
+        for x in 0..w {
+            let (xi, yi) = (x as i64, y as i64);
+            if !ink(xi, yi) || ink(xi, yi - 1) || used[y * w + x] {
+                continue;
+            }
+            // the outer outline of a patch is always met first
+            let corners = follow(&ink, (xi + 1, yi), (-1, 0), &mut used, w);
+            outlines[labels[y * w + x] as usize].push(corners);
+        }
+    }
+
+    let shapes = outlines
+        .into_iter()
+        .filter_map(|outlines| {
+            let mut segments = vec![];
+            for corners in outlines {
+                let polygon = if settings.tolerance > 0.0 { simplify_polyline(&corners, true, settings.tolerance, SimplifyMethod::DouglasPeucker) } else { corners };
+                if polygon.len() >= 3 {
+                    smooth_outline(&polygon, settings.smoothing, &mut segments);
+                }
+            }
+            (!segments.is_empty()).then_some(segments)
+        })
+        .collect();
+    Trace { shapes, size: [image.width, image.height] }
+}
+
+impl Trace {
+    /// The shapes as an SVG of one group with the id `name`, filled with `fill` and sized for
+    /// `dpi` pixels per inch.
+    pub fn svg(&self, name: &str, fill: Color, dpi: f32) -> String {
+        let [w, h] = self.size;
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.4}in" height="{:.4}in" viewBox="0 0 {} {}">"#,
+            w as f32 / dpi,
+            h as f32 / dpi,
+            w,
+            h
+        );
+        let _ = writeln!(svg, r#"<g id="{}" fill="rgb({},{},{})" fill-opacity="{:.3}">"#, name, fill.r, fill.g, fill.b, fill.a as f32 / 255.0);
+        for shape in &self.shapes {
+            let mut d = String::new();
+            for segment in shape {
+                let _ = match segment {
+                    Segment::MoveTo(p) => write!(d, "M{:.2} {:.2}", p[0], p[1]),
+                    Segment::LineTo(p) => write!(d, "L{:.2} {:.2}", p[0], p[1]),
+                    Segment::QuadTo(c, p) => write!(d, "Q{:.2} {:.2} {:.2} {:.2}", c[0], c[1], p[0], p[1]),
+                    Segment::CubicTo(c1, c2, p) => write!(d, "C{:.2} {:.2} {:.2} {:.2} {:.2} {:.2}", c1[0], c1[1], c2[0], c2[1], p[0], p[1]),
+                    Segment::Close => write!(d, "Z"),
+                };
+            }
+            let _ = writeln!(svg, r#"<path d="{}"/>"#, d);
+        }
+        svg.push_str("</g>\n</svg>\n");
+        svg
+    }
+}
@@ -0,0 +1,103 @@
+use crate::document::Document;
+
+/// CSS pixels per inch, what `Document::size` is in.
+pub const CSS_DPI: f32 = 96.0;
+
+/// Physical units for showing and entering lengths, and what an SVG's `width` and `height`
+/// are written in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Unit {
+    #[default]
+    Px,
+    Mm,
+    Cm,
+    In,
+    Pt,
+}
+
+impl Unit {
+    pub const ALL: [Unit; 5] = [Unit::Mm, Unit::Cm, Unit::In, Unit::Pt, Unit::Px];
+
+    /// As in SVG and CSS lengths.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Px => "px",
+            Self::Mm => "mm",
+            Self::Cm => "cm",
+            Self::In => "in",
+            Self::Pt => "pt",
+        }
+    }
+
+    /// From its name, "inch" and "inches" too.
+    pub fn parse(name: &str) -> Option<Unit> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "px" => Some(Self::Px),
+            "mm" => Some(Self::Mm),
+            "cm" => Some(Self::Cm),
+            "in" | "inch" | "inches" | "\"" => Some(Self::In),
+            "pt" => Some(Self::Pt),
+            _ => None,
+        }
+    }
+
+    /// Millimeters in one of these, CSS pixels at 96 per inch.
+    pub fn mm(self) -> f32 {
+        match self {
+            Self::Px => 25.4 / CSS_DPI,
+            Self::Mm => 1.0,
+            Self::Cm => 10.0,
+            Self::In => 25.4,
+            Self::Pt => 25.4 / 72.0,
+        }
+    }
+
+    pub fn from_mm(self, mm: f32) -> f32 {
+        mm / self.mm()
+    }
+
+    pub fn to_mm(self, value: f32) -> f32 {
+        value * self.mm()
+    }
+
+    /// Decimals that show a length in this unit to about a hundredth of a millimeter.
+    pub fn decimals(self) -> usize {
+        match self {
+            Self::Px | Self::Pt | Self::Mm => 2,
+            Self::Cm | Self::In => 3,
+        }
+    }
+
+    /// `mm` in this unit with its name, e.g. "1.250 in".
+    pub fn format(self, mm: f32) -> String {
+        format!("{:.*} {}", self.decimals(), self.from_mm(mm), self.name())
+    }
+}
+
+/// A typed in length like "12", "12.5 mm" or "0.5in", in millimeters. Numbers without a
+/// unit are in `default`.
+pub fn parse_length(text: &str, default: Unit) -> Option<f32> {
+    let text = text.trim().replace('−', "-");
+    let split = text.find(|c: char| c.is_ascii_alphabetic() || c == '"').unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let unit = if unit.is_empty() { default } else { Unit::parse(unit)? };
+    let value: f32 = number.trim().parse().ok()?;
+    Some(unit.to_mm(value))
+}
+
+/// A length attribute like `width="210mm"`: its value and unit. Px for plain numbers, None
+/// for percentages and units that are not physical.
+pub fn parse_attribute(value: &str) -> Option<(f32, Unit)> {
+    let value = value.trim();
+    let split = value.find(|c: char| c.is_ascii_alphabetic() || c == '%').unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let unit = if unit.is_empty() { Unit::Px } else { Unit::parse(unit)? };
+    Some((number.trim().parse().ok()?, unit))
+}
+
+impl Document {
+    /// `size` in millimeters, zero for documents without a size.
+    pub fn size_mm(&self) -> [f32; 2] {
+        self.size.map(|px| Unit::Px.to_mm(px))
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use resvg::usvg::roxmltree::{Attribute, Document, Node};
+
+use crate::diagnostics::Diagnostic;
+
+const SVG_NS: &str = "http://www.w3.org/2000/svg";
+const XLINK_NS: &str = "http://www.w3.org/1999/xlink";
+
+/// Elements of SVG 1.1 and SVG 2, whether usvg renders them or not.
+const ELEMENTS: &[&str] = &[
+    "a", "animate", "animateMotion", "animateTransform", "circle", "clipPath", "defs", "desc", "ellipse",
+    "feBlend", "feColorMatrix", "feComponentTransfer", "feComposite", "feConvolveMatrix", "feDiffuseLighting",
+    "feDisplacementMap", "feDistantLight", "feDropShadow", "feFlood", "feFuncA", "feFuncB", "feFuncG", "feFuncR",
+    "feGaussianBlur", "feImage", "feMerge", "feMergeNode", "feMorphology", "feOffset", "fePointLight",
+    "feSpecularLighting", "feSpotLight", "feTile", "feTurbulence", "filter", "foreignObject", "g", "image", "line",
+    "linearGradient", "marker", "mask", "metadata", "mpath", "path", "pattern", "polygon", "polyline",
+    "radialGradient", "rect", "script", "set", "stop", "style", "svg", "switch", "symbol", "text", "textPath",
+    "title", "tspan", "use", "view",
+];
+
+/// Check `xml` against the parts of the SVG spec that usvg silently works around: unknown
+/// elements, unparsable attribute values, negative sizes, duplicate ids and references to
+/// ids that don't exist. In document order.
+pub(crate) fn validate(xml: &Document) -> Vec<Diagnostic> {
+    let mut problems = vec![];
+    let root = xml.root_element();
+    if root.tag_name().namespace() != Some(SVG_NS) || root.tag_name().name() != "svg" {
+        problems.push(Diagnostic::at(xml, root.range().start, "The root element is not <svg> in the SVG namespace"));
+    }
+
+    let mut ids: HashMap<&str, Node> = HashMap::new();
+    for node in xml.descendants().filter(|n| n.is_element()) {
+        let Some(id) = node.attribute("id") else { continue };
+        if let Some(first) = ids.get(id) {
+            let pos = xml.text_pos_at(first.range().start);
+            problems.push(Diagnostic::at(xml, node.range().start, format!("Duplicate id '{}', first used at {}:{}", id, pos.row, pos.col)));
+        } else {
+            ids.insert(id, node);
+        }
+    }
+
+    for node in xml.descendants().filter(|n| n.is_element() && n.tag_name().namespace() == Some(SVG_NS)) {
+        let tag = node.tag_name().name();
+        if !ELEMENTS.contains(&tag) {
+            problems.push(Diagnostic::at(xml, node.range().start, format!("Unknown element <{}>", tag)));
+            continue;
+        }
+        for attr in node.attributes() {
+            if let Err(message) = check_attribute(tag, &attr, &ids) {
+                problems.push(Diagnostic::at(xml, attr.position(), message));
+            }
+        }
+    }
+    // duplicate ids were found first
+    problems.sort_by_key(|p| p.position);
+    problems
+}
+
+/// Describes what is wrong with `attr` of a `tag` element.
+fn check_attribute(tag: &str, attr: &Attribute, ids: &HashMap<&str, Node>) -> Result<(), String> {
+    let (name, value) = (attr.name(), attr.value().trim());
+    let invalid = |e: &dyn std::fmt::Display| format!("Invalid {} value '{}': {}", name, value, e);
+    let reference = |id: &str| match ids.contains_key(id) {
+        true => Ok(()),
+        false => Err(format!("{} refers to the unknown id '{}'", name, id)),
+    };
+    if matches!(attr.namespace(), Some(XLINK_NS) | None) && name == "href" {
+        // external resources are for the loader to find
+        return match value.strip_prefix('#') {
+            Some(id) => reference(id),
+            None => Ok(()),
+        };
+    }
+    if attr.namespace().is_some() || value == "inherit" {
+        return Ok(());
+    }
+
+    match name {
+        "fill" | "stroke" => match svgtypes::Paint::from_str(value).map_err(|e| invalid(&e))? {
+            svgtypes::Paint::FuncIRI(id, _) => reference(id),
+            _ => Ok(()),
+        },
+        "clip-path" | "mask" | "filter" | "marker-start" | "marker-mid" | "marker-end" if value.starts_with("url(") => {
+            reference(svgtypes::FuncIRI::from_str(value).map_err(|e| invalid(&e))?.0)
+        }
+        "stop-color" | "flood-color" | "lighting-color" | "color" if value != "currentColor" => {
+            svgtypes::Color::from_str(value).map(|_| ()).map_err(|e| invalid(&e))
+        }
+        "transform" | "gradientTransform" | "patternTransform" => {
+            svgtypes::Transform::from_str(value).map(|_| ()).map_err(|e| invalid(&e))
+        }
+        "d" if tag == "path" => svgtypes::PathParser::from(value).try_for_each(|s| s.map(|_| ())).map_err(|e| invalid(&e)),
+        "viewBox" => svgtypes::ViewBox::from_str(value).map(|_| ()).map_err(|e| invalid(&e)),
+        "opacity" | "fill-opacity" | "stroke-opacity" | "stop-opacity" | "offset" => {
+            // SVG 2 also allows percentages
+            svgtypes::Number::from_str(value.strip_suffix('%').unwrap_or(value)).map(|_| ()).map_err(|e| invalid(&e))
+        }
+        "fill-rule" | "clip-rule" if !matches!(value, "nonzero" | "evenodd") => Err(invalid(&"expected nonzero or evenodd")),
+        "stroke-linecap" if !matches!(value, "butt" | "round" | "square") => Err(invalid(&"expected butt, round or square")),
+        "stroke-linejoin" if !matches!(value, "miter" | "miter-clip" | "round" | "bevel" | "arcs") => {
+            Err(invalid(&"expected miter, round or bevel"))
+        }
+        // lists of coordinates on text
+        "x" | "y" if matches!(tag, "text" | "tspan") => Ok(()),
+        "x" | "y" | "cx" | "cy" | "x1" | "y1" | "x2" | "y2" | "fx" | "fy" => {
+            svgtypes::Length::from_str(value).map(|_| ()).map_err(|e| invalid(&e))
+        }
+        "width" | "height" | "r" | "rx" | "ry" | "stroke-width" => {
+            if value == "auto" && !matches!(name, "r" | "stroke-width") {
+                return Ok(());
+            }
+            match svgtypes::Length::from_str(value).map_err(|e| invalid(&e))? {
+                length if length.number < 0.0 => Err(format!("{} must not be negative", name)),
+                _ => Ok(()),
+            }
+        }
+        _ => Ok(()),
+    }
+}
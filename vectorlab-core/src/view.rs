@@ -0,0 +1,153 @@
+/// Maps document coordinates to canvas pixels: `screen = R(rotation) * flip * doc * zoom + pan`.
+/// Rotation and flipping only change how the document is looked at, never its geometry.
+#[derive(Clone, Copy, Debug)]
+pub struct ViewTransform {
+    pub zoom: f32,
+    pub pan: [f32; 2],
+    /// Clockwise on screen, in degrees, 0 ≤ rotation < 360
+    pub rotation: f32,
+    /// Mirror the document's x axis, before rotating
+    pub flip_x: bool,
+    /// Mirror the document's y axis, before rotating
+    pub flip_y: bool,
+}
+
+impl Default for ViewTransform {
+    fn default() -> Self {
+        Self { zoom: 1.0, pan: [0.0, 0.0], rotation: 0.0, flip_x: false, flip_y: false }
+    }
+}
+
+impl ViewTransform {
+    pub const MIN_ZOOM: f32 = 0.001;
+    pub const MAX_ZOOM: f32 = 10000.0;
+
+    /// The linear part of the mapping, rows of a 2×2 matrix: [a, b, c, d] maps (x, y) to
+    /// (a x + b y, c x + d y).
+    pub fn matrix(&self) -> [f32; 4] {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let sx = if self.flip_x { -self.zoom } else { self.zoom };
+        let sy = if self.flip_y { -self.zoom } else { self.zoom };
+        [cos * sx, -sin * sy, sin * sx, cos * sy]
+    }
+
+    pub fn to_screen(&self, p: [f32; 2]) -> [f32; 2] {
+        let [a, b, c, d] = self.matrix();
+        [a * p[0] + b * p[1] + self.pan[0], c * p[0] + d * p[1] + self.pan[1]]
+    }
+
+    pub fn to_doc(&self, p: [f32; 2]) -> [f32; 2] {
+        // the matrix is a rotation times a reflection times zoom, its inverse is the
+        // transpose divided by zoom²
+        let [a, b, c, d] = self.matrix();
+        let (x, y) = (p[0] - self.pan[0], p[1] - self.pan[1]);
+        let zz = self.zoom * self.zoom;
+        [(a * x + c * y) / zz, (b * x + d * y) / zz]
+    }
+
+    /// Smallest document rectangle [min_x, min_y, max_x, max_y] that covers the screen
+    /// rectangle `rect`, in canvas pixels.
+    pub fn doc_bbox(&self, rect: [f32; 4]) -> [f32; 4] {
+        let corners = [[rect[0], rect[1]], [rect[2], rect[1]], [rect[2], rect[3]], [rect[0], rect[3]]].map(|p| self.to_doc(p));
+        corners.iter().fold([f32::INFINITY, f32::INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY], |b, p| {
+            [b[0].min(p[0]), b[1].min(p[1]), b[2].max(p[0]), b[3].max(p[1])]
+        })
+    }
+
+    /// The document axis that runs most nearly along `screen_axis` (0 for x, 1 for y) and
+    /// the signed pixels per document unit along it.
+    pub fn doc_axis(&self, screen_axis: usize) -> (usize, f32) {
+        let m = self.matrix();
+        let row = [m[2 * screen_axis], m[2 * screen_axis + 1]];
+        if row[0].abs() >= row[1].abs() {
+            (0, row[0])
+        } else {
+            (1, row[1])
+        }
+    }
+
+    /// True if the document axes are parallel to the screen's, rotation a multiple of 90°.
+    pub fn is_axis_aligned(&self) -> bool {
+        let r = self.rotation.rem_euclid(90.0);
+        !(1e-3..=90.0 - 1e-3).contains(&r)
+    }
+
+    /// Rotated or flipped, not showing the document the way it is meant to be looked at.
+    pub fn is_oriented(&self) -> bool {
+        self.rotation != 0.0 || self.flip_x || self.flip_y
+    }
+
+    /// Zoom by `factor` while keeping the document point under `anchor` (canvas pixels) in place.
+    pub fn zoom_at(&mut self, anchor: [f32; 2], factor: f32) {
+        let fixed = self.to_doc(anchor);
+        self.zoom = (self.zoom * factor).clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+        self.keep(fixed, anchor);
+    }
+
+    /// Rotate clockwise by `degrees` around `anchor` (canvas pixels).
+    pub fn rotate_at(&mut self, anchor: [f32; 2], degrees: f32) {
+        let fixed = self.to_doc(anchor);
+        self.rotation = (self.rotation + degrees).rem_euclid(360.0);
+        // rem_euclid may round up to 360 for tiny negative angles
+        if self.rotation >= 360.0 {
+            self.rotation = 0.0;
+        }
+        self.keep(fixed, anchor);
+    }
+
+    /// Mirror what is on screen around `anchor`, left to right if `horizontal`, top to
+    /// bottom otherwise.
+    pub fn flip_at(&mut self, anchor: [f32; 2], horizontal: bool) {
+        let fixed = self.to_doc(anchor);
+        // mirroring the screen turns a rotation the other way, and mirrors one document
+        // axis: diag(-1, 1) R(a) F = R(-a) F diag(-1, 1), diag(1, -1) R(a) F = R(-a) F diag(1, -1)
+        self.rotation = (-self.rotation).rem_euclid(360.0);
+        if horizontal {
+            self.flip_x = !self.flip_x;
+        } else {
+            self.flip_y = !self.flip_y;
+        }
+        self.keep(fixed, anchor);
+    }
+
+    /// Back to upright and unmirrored, keeping the document point under `anchor` in place.
+    pub fn reset_orientation(&mut self, anchor: [f32; 2]) {
+        let fixed = self.to_doc(anchor);
+        self.rotation = 0.0;
+        self.flip_x = false;
+        self.flip_y = false;
+        self.keep(fixed, anchor);
+    }
+
+    /// Pan so the document point `doc` is at `screen`.
+    fn keep(&mut self, doc: [f32; 2], screen: [f32; 2]) {
+        self.pan = [0.0, 0.0];
+        let s = self.to_screen(doc);
+        self.pan = [screen[0] - s[0], screen[1] - s[1]];
+    }
+
+    pub fn pan_by(&mut self, delta: [f32; 2]) {
+        self.pan[0] += delta[0];
+        self.pan[1] += delta[1];
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Fit `bbox` ([min_x, min_y, max_x, max_y] in document units) centered into a
+    /// viewport of `size` pixels, leaving `padding` pixels on each side. Rotation and
+    /// flipping stay.
+    pub fn fit(&mut self, bbox: [f32; 4], size: [f32; 2], padding: f32) {
+        let w = (bbox[2] - bbox[0]).max(f32::EPSILON);
+        let h = (bbox[3] - bbox[1]).max(f32::EPSILON);
+        // extents of the rotated box
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let (w, h) = (cos.abs() * w + sin.abs() * h, sin.abs() * w + cos.abs() * h);
+        let avail_w = (size[0] - 2.0 * padding).max(1.0);
+        let avail_h = (size[1] - 2.0 * padding).max(1.0);
+        self.zoom = (avail_w / w).min(avail_h / h).clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+        let center = [(bbox[0] + bbox[2]) * 0.5, (bbox[1] + bbox[3]) * 0.5];
+        self.keep(center, [size[0] * 0.5, size[1] * 0.5]);
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::boolean::{boolean_contours, chain_pieces, dedup, split_edges, BooleanOp};
+use crate::document::{Contour, Document, ElementId, Segment};
+use crate::edit::{EditCommand, EditGroup, SetSegments, SetStyle};
+use crate::hit::winding_number;
+use crate::loader::flatten_segments;
+use crate::style::{FillRule, Style};
+
+/// The part of a path's area with one winding number.
+pub struct WindingRegion {
+    pub winding: i32,
+    /// outlines with the region on their left, holes the other way round
+    pub contours: Vec<Contour>,
+}
+
+/// The area inside `contours`, every one taken as closed, split up by how often they wind
+/// around it, lowest first. Nonzero fills all of it, even-odd only the odd ones, so regions
+/// of ±2, ±4… are where the two rules differ. Slivers smaller than `tolerance` squared are
+/// left out.
+pub fn winding_regions(contours: &[Contour], tolerance: f32) -> Vec<WindingRegion> {
+    let loops: Vec<Vec<[f32; 2]>> = contours.iter().map(|c| dedup(&c.points, true)).filter(|l| l.len() > 2).collect();
+    let closed: Vec<Contour> = loops.iter().map(|l| Contour { points: l.clone(), closed: true }).collect();
+    let nudge = tolerance * 0.05;
+    let key = |p: [f32; 2]| (p[0].to_bits(), p[1].to_bits());
+    let mut seen = HashSet::new();
+    let mut pieces: BTreeMap<i32, Vec<[[f32; 2]; 2]>> = BTreeMap::new();
+    for [p, q] in split_edges(&loops) {
+        let length = ((q[0] - p[0]).powi(2) + (q[1] - p[1]).powi(2)).sqrt().max(f32::EPSILON);
+        let n = [-(q[1] - p[1]) / length * nudge, (q[0] - p[0]) / length * nudge];
+        let middle = [(p[0] + q[0]) * 0.5, (p[1] + q[1]) * 0.5];
+        let left = winding_number(&closed, [middle[0] + n[0], middle[1] + n[1]]);
+        let right = winding_number(&closed, [middle[0] - n[0], middle[1] - n[1]]);
+        if left == right {
+            continue;
+        }
+        // the boundary of the region on either side, each running with it on the left
+        for (winding, piece) in [(left, [p, q]), (right, [q, p])] {
+            if winding != 0 && seen.insert((winding, key(piece[0]), key(piece[1]))) {
+                pieces.entry(winding).or_default().push(piece);
+            }
+        }
+    }
+    pieces
+        .into_iter()
+        .map(|(winding, pieces)| WindingRegion { winding, contours: chain_pieces(&pieces, tolerance * tolerance) })
+        .filter(|region| !region.contours.is_empty())
+        .collect()
+}
+
+/// What `even_odd_paths` did.
+pub struct EvenOddResult {
+    pub edit: Box<dyn EditCommand>,
+    /// paths switched to even-odd, and those of them that needed new outlines for it
+    pub paths: usize,
+    pub rebuilt: usize,
+}
+
+/// Switch the nonzero fills among `ids` to even-odd without changing what they cover. Paths
+/// winding around some area more than once get new outlines first, the area they filled
+/// without overlaps, which also changes their strokes. The others keep their curves. None if
+/// none of them is filled nonzero.
+pub fn even_odd_paths(doc: &Document, ids: &[ElementId]) -> Option<EvenOddResult> {
+    let mut commands: Vec<Box<dyn EditCommand>> = vec![];
+    let (mut paths, mut rebuilt) = (0, 0);
+    for &id in ids {
+        let Some(path) = doc.get(id).as_path() else { continue };
+        if path.style.fill.is_none() || path.style.fill_rule != FillRule::NonZero {
+            continue;
+        }
+        let tolerance = path.tolerance.max(1e-4);
+        let contours = flatten_segments(&path.segments, tolerance);
+        if winding_regions(&contours, tolerance).iter().any(|region| region.winding.abs() > 1) {
+            let outlines = boolean_contours(&contours, FillRule::NonZero, &[], FillRule::NonZero, BooleanOp::Union, tolerance);
+            let mut segments = vec![];
+            for contour in &outlines {
+                segments.push(Segment::MoveTo(contour.points[0]));
+                segments.extend(contour.points[1..].iter().map(|&p| Segment::LineTo(p)));
+                segments.push(Segment::Close);
+            }
+            commands.push(Box::new(SetSegments::new(doc, id, segments)));
+            rebuilt += 1;
+        }
+        commands.push(Box::new(SetStyle { id, old: path.style.clone(), new: Style { fill_rule: FillRule::EvenOdd, ..path.style.clone() } }));
+        paths += 1;
+    }
+    if paths == 0 {
+        return None;
+    }
+    Some(EvenOddResult { edit: Box::new(EditGroup::new("Convert to even-odd", commands)), paths, rebuilt })
+}